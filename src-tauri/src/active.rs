@@ -0,0 +1,305 @@
+use crate::ai_analyzer::SecurityRisk;
+use crate::params::{self, ParamLocation};
+use crate::proxy::{HttpRequest, HttpResponse, HttpTransaction};
+use crate::scanner::{Finding, FindingCategory};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub const ACTIVE_PROBE_TAG: &str = "active-probe";
+
+fn default_requests_per_second() -> f64 {
+    2.0
+}
+
+fn default_max_probes() -> usize {
+    60
+}
+
+// 主动扫描会向目标发送攻击载荷，必须显式开启并把目标主机加入范围
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveScanSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    // 允许探测的主机，同时匹配其子域名
+    #[serde(default)]
+    pub scope: Vec<String>,
+    // 对同一主机的探测速率上限
+    #[serde(default = "default_requests_per_second")]
+    pub requests_per_second: f64,
+    // 单个请求最多发送的探测数
+    #[serde(default = "default_max_probes")]
+    pub max_probes_per_request: usize,
+}
+
+impl Default for ActiveScanSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scope: Vec::new(),
+            requests_per_second: default_requests_per_second(),
+            max_probes_per_request: default_max_probes(),
+        }
+    }
+}
+
+impl ActiveScanSettings {
+    pub fn normalized(mut self) -> Self {
+        let mut scope: Vec<String> = self
+            .scope
+            .iter()
+            .map(|h| h.trim().trim_start_matches("*.").trim_end_matches('.').to_lowercase())
+            .filter(|h| !h.is_empty())
+            .collect();
+        scope.sort();
+        scope.dedup();
+        self.scope = scope;
+        if !self.requests_per_second.is_finite() || self.requests_per_second <= 0.0 {
+            self.requests_per_second = default_requests_per_second();
+        }
+        self.requests_per_second = self.requests_per_second.min(50.0);
+        self.max_probes_per_request = self.max_probes_per_request.clamp(1, 500);
+        self
+    }
+
+    pub fn in_scope(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.scope
+            .iter()
+            .any(|expected| host == *expected || host.ends_with(&format!(".{}", expected)))
+    }
+
+    // 未开启或主机不在范围内时拒绝扫描
+    pub fn authorize(&self, url: &str) -> Result<String> {
+        if !self.enabled {
+            return Err(anyhow!("Active scanning is disabled"));
+        }
+        let host = url::Url::parse(url)?
+            .host_str()
+            .map(str::to_lowercase)
+            .ok_or_else(|| anyhow!("Request has no host: {}", url))?;
+        if !self.in_scope(&host) {
+            return Err(anyhow!("Host {} is not in the active scan scope", host));
+        }
+        Ok(host)
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.requests_per_second)
+    }
+}
+
+// 按主机限速：返回本次探测需要等待的时间
+#[derive(Debug, Default)]
+pub struct ProbeRateLimiter {
+    next_slot: HashMap<String, Instant>,
+}
+
+impl ProbeRateLimiter {
+    pub fn reserve(&mut self, host: &str, settings: &ActiveScanSettings) -> Duration {
+        let now = Instant::now();
+        let slot = self.next_slot.get(host).copied().filter(|t| *t > now).unwrap_or(now);
+        self.next_slot.insert(host.to_string(), slot + settings.interval());
+        slot - now
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeKind {
+    SqlInjection,
+    Xss,
+    PathTraversal,
+}
+
+struct Payload {
+    kind: ProbeKind,
+    value: &'static str,
+    // 追加到原值之后，否则替换原值
+    append: bool,
+}
+
+// XSS 载荷中的标记，原样出现在响应中说明未经编码
+const XSS_MARKER: &str = "pmx7f3a";
+
+const PAYLOADS: [Payload; 6] = [
+    Payload { kind: ProbeKind::SqlInjection, value: "'", append: true },
+    Payload { kind: ProbeKind::SqlInjection, value: "\"", append: true },
+    Payload { kind: ProbeKind::Xss, value: "\"'><pmx7f3a>", append: true },
+    Payload { kind: ProbeKind::PathTraversal, value: "../../../../../../../../etc/passwd", append: false },
+    Payload { kind: ProbeKind::PathTraversal, value: "..%2f..%2f..%2f..%2f..%2f..%2f..%2f..%2fetc%2fpasswd", append: false },
+    Payload { kind: ProbeKind::PathTraversal, value: "..\\..\\..\\..\\..\\..\\..\\..\\windows\\win.ini", append: false },
+];
+
+// 数据库报错信息，探测响应中新出现即视为注入成立
+pub const SQL_ERROR_SIGNATURES: [&str; 14] = [
+    "you have an error in your sql syntax",
+    "warning: mysql",
+    "mysqli_",
+    "unclosed quotation mark after the character string",
+    "quoted string not properly terminated",
+    "pg_query(",
+    "syntax error at or near",
+    "unterminated quoted string",
+    "sqlite3::",
+    "sqlite_error",
+    "sqlstate[",
+    "ora-00933",
+    "ora-01756",
+    "microsoft ole db provider for",
+];
+
+// 系统文件内容特征
+const TRAVERSAL_SIGNATURES: [&str; 3] = ["root:x:0:0:", "[boot loader]", "; for 16-bit app support"];
+
+#[derive(Debug, Clone)]
+pub struct Probe {
+    pub kind: ProbeKind,
+    pub location: ParamLocation,
+    pub parameter: String,
+    pub payload: String,
+    pub request: HttpRequest,
+}
+
+// 为请求中每个可注入参数生成变异请求
+pub fn build_probes(transaction: &HttpTransaction, limit: usize) -> Vec<Probe> {
+    params::extract(transaction)
+        .iter()
+        .flat_map(|param| {
+            PAYLOADS.iter().map(move |payload| {
+                let value = if payload.append {
+                    format!("{}{}", param.value, payload.value)
+                } else {
+                    payload.value.to_string()
+                };
+                let mut request = params::with_value(&transaction.request, param, &value);
+                request.timestamp = chrono::Utc::now();
+                Probe {
+                    kind: payload.kind,
+                    location: param.location,
+                    parameter: param.name.clone(),
+                    payload: payload.value.to_string(),
+                    request,
+                }
+            })
+        })
+        .take(limit)
+        .collect()
+}
+
+// 探测响应与原始响应的差异
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbeOutcome {
+    pub probe_transaction_id: String,
+    pub kind: ProbeKind,
+    pub location: ParamLocation,
+    pub parameter: String,
+    pub payload: String,
+    pub baseline_status: Option<u16>,
+    pub status: Option<u16>,
+    pub length_delta: i64,
+    pub confirmed: bool,
+    // 探测请求未能发出或上游无响应，此时不做对比
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl ProbeOutcome {
+    pub fn failed(probe: &Probe, probe_transaction_id: Option<&str>, baseline: Option<&HttpResponse>, error: String) -> Self {
+        Self {
+            probe_transaction_id: probe_transaction_id.unwrap_or_default().to_string(),
+            kind: probe.kind,
+            location: probe.location,
+            parameter: probe.parameter.clone(),
+            payload: probe.payload.clone(),
+            baseline_status: baseline.map(|r| r.status),
+            status: None,
+            length_delta: 0,
+            confirmed: false,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveScanReport {
+    pub transaction_id: String,
+    pub host: String,
+    pub probes: Vec<ProbeOutcome>,
+    pub findings: Vec<Finding>,
+}
+
+// 对比原始响应和探测响应，只报告由载荷引起的新特征
+pub fn evaluate(
+    probe: &Probe,
+    probe_transaction_id: &str,
+    baseline: Option<&HttpResponse>,
+    response: Option<&HttpResponse>,
+) -> (ProbeOutcome, Option<Finding>) {
+    let baseline_body = baseline.map(|r| String::from_utf8_lossy(&r.body).to_lowercase()).unwrap_or_default();
+    let body = response.map(|r| String::from_utf8_lossy(&r.body).to_string()).unwrap_or_default();
+    let lower = body.to_lowercase();
+    let new_signature = |signatures: &[&'static str]| {
+        signatures
+            .iter()
+            .copied()
+            .find(|s| lower.contains(s) && !baseline_body.contains(s))
+    };
+    let target = format!("{} {}", probe.location.label(), probe.parameter);
+
+    let finding = match probe.kind {
+        ProbeKind::SqlInjection => new_signature(&SQL_ERROR_SIGNATURES).map(|signature| {
+            Finding::new(
+                "active-sql-injection",
+                SecurityRisk::Critical,
+                FindingCategory::SqlInjection,
+                format!("已确认 SQL 注入：{} 注入引号后响应出现数据库报错", target),
+            )
+            .evidence(format!("载荷 {} 触发响应内容 \"{}\"", probe.payload, signature))
+            .remediation("使用参数化查询或 ORM 绑定参数，不要把请求参数拼接进 SQL 语句；关闭向客户端返回数据库错误详情")
+        }),
+        ProbeKind::Xss => {
+            let html = response
+                .and_then(|r| crate::proxy::find_header(&r.headers, "content-type"))
+                .map(|ct| ct.to_lowercase().contains("html"))
+                .unwrap_or(false);
+            (html && body.contains(&format!("<{}>", XSS_MARKER))).then(|| {
+                Finding::new(
+                    "active-xss",
+                    SecurityRisk::High,
+                    FindingCategory::Xss,
+                    format!("已确认反射型 XSS：{} 的值未经编码输出到 HTML 响应", target),
+                )
+                .evidence(format!("载荷 {} 原样出现在响应中", probe.payload))
+                .remediation("输出到页面时按上下文进行 HTML/属性/JavaScript 编码，并配置 Content-Security-Policy")
+            })
+        }
+        ProbeKind::PathTraversal => new_signature(&TRAVERSAL_SIGNATURES).map(|signature| {
+            Finding::new(
+                "active-path-traversal",
+                SecurityRisk::Critical,
+                FindingCategory::PathTraversal,
+                format!("已确认路径遍历：{} 可读取服务器上的系统文件", target),
+            )
+            .evidence(format!("载荷 {} 返回的内容包含 \"{}\"", probe.payload, signature))
+            .remediation("不要用请求参数直接拼接文件路径；规范化路径后校验位于允许的目录内，或改用文件 ID 映射")
+        }),
+    };
+
+    let finding = finding.map(|f| f.subject(probe.parameter.clone()).probe(probe_transaction_id));
+    let outcome = ProbeOutcome {
+        probe_transaction_id: probe_transaction_id.to_string(),
+        kind: probe.kind,
+        location: probe.location,
+        parameter: probe.parameter.clone(),
+        payload: probe.payload.clone(),
+        baseline_status: baseline.map(|r| r.status),
+        status: response.map(|r| r.status),
+        length_delta: response.map(|r| r.body.len() as i64).unwrap_or(0)
+            - baseline.map(|r| r.body.len() as i64).unwrap_or(0),
+        confirmed: finding.is_some(),
+        error: None,
+    };
+    (outcome, finding)
+}
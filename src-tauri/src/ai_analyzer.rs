@@ -1,6 +1,10 @@
-use crate::proxy::{HttpTransaction, HttpRequest};
+use crate::proxy::{find_header, HttpTransaction};
+use crate::chunking::{digest_body, BodyDigest, DEFAULT_BODY_BUDGET};
+use crate::classify::BodyKind;
+use crate::llm::{self, ChatMessage};
+use crate::scoring;
+use crate::config::{AiProvider, AiProviderConfig};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use anyhow::Result;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,9 +15,18 @@ pub struct AIAnalysisResult {
     pub anomaly_detection: Vec<String>,
     pub api_patterns: Vec<ApiPattern>,
     pub data_flow_analysis: DataFlowAnalysis,
+    // 超出上下文的请求/响应体如何被分块摘要
+    #[serde(default)]
+    pub body_digests: Vec<BodyDigest>,
+    // 生成结果的模型；未配置模型时为空，表示结果来自本地启发式规则
+    #[serde(default)]
+    pub model: Option<String>,
+    // 结果来自缓存，未重新调用模型
+    #[serde(default)]
+    pub cached: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum SecurityRisk {
     Low,
     Medium,
@@ -36,76 +49,374 @@ pub struct DataFlowAnalysis {
     pub compliance_issues: Vec<String>,
 }
 
+// 没有配置文件时使用的模型
+pub const DEFAULT_AI_MODEL: &str = "gpt-3.5-turbo";
+
+#[derive(Clone)]
 pub struct AIAnalyzer {
     api_key: Option<String>,
     model: AIModel,
+    client: reqwest::Client,
+    // 为空时使用各服务商的官方地址
+    base_url: Option<String>,
+    max_tokens: u32,
+}
+
+// 要求模型按固定结构输出，解析失败时报错而不是返回半成品
+const ANALYSIS_SYSTEM_PROMPT: &str = r#"你是 HTTP 流量的安全与性能分析助手。只输出一个 JSON 对象，不要输出任何其他文字，结构如下：
+{
+  "security_risk": "Low" | "Medium" | "High" | "Critical",
+  "performance_insights": [string],
+  "optimization_suggestions": [string],
+  "anomaly_detection": [string],
+  "api_patterns": [{"pattern_type": string, "confidence": 0 到 1 之间的小数, "description": string}],
+  "data_flow_analysis": {
+    "data_types": [string],
+    "sensitive_data_detected": boolean,
+    "data_flow_direction": string,
+    "compliance_issues": [string]
+  }
+}
+没有发现的项目返回空数组。所有描述使用中文，具体指出涉及的请求头、参数或字段。"#;
+
+// 模型输出可能缺字段或大小写不一致，先宽松解析再转换
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ModelAnalysis {
+    security_risk: String,
+    performance_insights: Vec<String>,
+    optimization_suggestions: Vec<String>,
+    anomaly_detection: Vec<String>,
+    api_patterns: Vec<ModelApiPattern>,
+    data_flow_analysis: ModelDataFlow,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ModelApiPattern {
+    pattern_type: String,
+    confidence: f32,
+    description: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ModelDataFlow {
+    data_types: Vec<String>,
+    sensitive_data_detected: bool,
+    data_flow_direction: String,
+    compliance_issues: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
 pub enum AIModel {
     OpenAI { model: String },
     Anthropic { model: String },
+    // 本地或自建的 OpenAI 兼容服务，不需要密钥即可离线分析
+    Ollama { base_url: String, model: String },
     Local { model_path: String },
 }
 
 impl AIAnalyzer {
     pub fn new(api_key: Option<String>, model: AIModel) -> Self {
-        Self { api_key, model }
+        Self { api_key, model, client: llm::client(), base_url: None, max_tokens: llm::DEFAULT_MAX_TOKENS }
+    }
+
+    // 密钥由调用方从钥匙串或环境变量取得，为空时退回启发式分析
+    pub fn from_config(config: &AiProviderConfig, api_key: Option<String>) -> Self {
+        let model = match config.provider {
+            AiProvider::Openai => AIModel::OpenAI { model: config.model.clone() },
+            AiProvider::Anthropic => AIModel::Anthropic { model: config.model.clone() },
+            AiProvider::Ollama => AIModel::Ollama {
+                base_url: config.base_url.clone().unwrap_or_else(|| llm::OLLAMA_BASE_URL.to_string()),
+                model: config.model.clone(),
+            },
+            AiProvider::Local => AIModel::Local { model_path: config.model.clone() },
+        };
+        let mut analyzer = Self::new(api_key, model).with_base_url(config.base_url.clone());
+        if let Some(max_tokens) = config.max_tokens {
+            analyzer = analyzer.with_max_tokens(max_tokens);
+        }
+        analyzer
     }
 
-    pub async fn analyze_transaction(&self, transaction: &HttpTransaction) -> Result<AIAnalysisResult> {
+    // 缓存键的一部分，区分服务商和模型
+    pub fn model_label(&self) -> String {
         match &self.model {
-            AIModel::OpenAI { model } => self.analyze_with_openai(transaction, model).await,
-            AIModel::Anthropic { model } => self.analyze_with_anthropic(transaction, model).await,
-            AIModel::Local { model_path } => self.analyze_with_local_model(transaction, model_path).await,
+            AIModel::OpenAI { model } => format!("openai/{}", model),
+            AIModel::Anthropic { model } => format!("anthropic/{}", model),
+            AIModel::Ollama { base_url, model } => format!("ollama/{}@{}", model, base_url),
+            AIModel::Local { model_path } => format!("local/{}", model_path),
         }
     }
 
-    async fn analyze_with_openai(&self, transaction: &HttpTransaction, _model: &str) -> Result<AIAnalysisResult> {
-        let _prompt = self.build_analysis_prompt(transaction);
+    pub fn with_base_url(mut self, base_url: Option<String>) -> Self {
+        self.base_url = base_url.filter(|url| !url.trim().is_empty());
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens.max(1);
+        self
+    }
+
+    // 传入 on_delta 时以流式方式调用模型，每收到一段输出回调一次
+    pub async fn analyze_transaction_streaming(
+        &self,
+        transaction: &HttpTransaction,
+        on_delta: Option<&mut (dyn FnMut(&str) + Send)>,
+    ) -> Result<AIAnalysisResult> {
+        let body_digests = Self::digest_bodies(transaction);
+        let prompt = self.build_analysis_prompt(transaction, &body_digests);
         
-        // 这里需要集成 OpenAI API
-        // 暂时返回模拟结果
+        let messages = [ChatMessage::user(prompt)];
+        let mut result = match self.complete(ANALYSIS_SYSTEM_PROMPT, &messages, true, on_delta).await? {
+            Some(content) => {
+                let mut result = Self::parse_analysis(&content)?;
+                result.model = Some(self.model_name().to_string());
+                result
+            }
+            // 未配置密钥时退回到本地启发式分析
+            None => Self::heuristic_analysis(transaction),
+        };
+        result.body_digests = body_digests;
+        Ok(result)
+    }
+
+    // 大型请求体先做分块摘要，避免超出模型上下文
+    fn digest_bodies(transaction: &HttpTransaction) -> Vec<BodyDigest> {
+        let request = &transaction.request;
+        let mut digests = vec![digest_body(&request.body, transaction.request_kind(), DEFAULT_BODY_BUDGET)];
+        if let Some(response) = &transaction.response {
+            digests.push(digest_body(&response.body, transaction.response_kind(), DEFAULT_BODY_BUDGET));
+        }
+        digests
+    }
+
+    // 返回 None 表示没有可用的模型：未配置密钥，或本地模型尚未接入
+    pub async fn complete(
+        &self,
+        system: &str,
+        messages: &[ChatMessage],
+        json_mode: bool,
+        on_delta: Option<&mut (dyn FnMut(&str) + Send)>,
+    ) -> Result<Option<String>> {
+        let content = match &self.model {
+            AIModel::OpenAI { model } => {
+                let Some(api_key) = self.api_key.as_deref() else {
+                    return Ok(None);
+                };
+                let base_url = self.base_url.as_deref().unwrap_or(llm::OPENAI_BASE_URL);
+                self.openai_compatible(base_url, Some(api_key), model, system, messages, json_mode, on_delta).await?
+            }
+            // 本地服务不会退回启发式分析：用户明确选择了它，连不上时应当报错
+            AIModel::Ollama { base_url, model } => {
+                let api_key = self.api_key.as_deref();
+                self.openai_compatible(base_url, api_key, model, system, messages, json_mode, on_delta).await?
+            }
+            // Messages API 没有 JSON 模式，由系统提示约束输出格式
+            AIModel::Anthropic { model } => {
+                let Some(api_key) = self.api_key.as_deref() else {
+                    return Ok(None);
+                };
+                let base_url = self.base_url.as_deref().unwrap_or(llm::ANTHROPIC_BASE_URL);
+                match on_delta {
+                    Some(on_delta) => {
+                        llm::anthropic_messages_stream(
+                            &self.client,
+                            base_url,
+                            api_key,
+                            model,
+                            system,
+                            messages,
+                            self.max_tokens,
+                            on_delta,
+                        )
+                        .await?
+                    }
+                    None => {
+                        llm::anthropic_messages(&self.client, base_url, api_key, model, system, messages, self.max_tokens)
+                            .await?
+                    }
+                }
+            }
+            // 尚未接入本地模型
+            AIModel::Local { .. } => return Ok(None),
+        };
+        Ok(Some(content))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn openai_compatible(
+        &self,
+        base_url: &str,
+        api_key: Option<&str>,
+        model: &str,
+        system: &str,
+        messages: &[ChatMessage],
+        json_mode: bool,
+        on_delta: Option<&mut (dyn FnMut(&str) + Send)>,
+    ) -> Result<String> {
+        let messages: Vec<ChatMessage> =
+            std::iter::once(ChatMessage::system(system)).chain(messages.iter().cloned()).collect();
+        match on_delta {
+            Some(on_delta) => {
+                llm::openai_chat_stream(&self.client, base_url, api_key, model, &messages, json_mode, on_delta).await
+            }
+            None => llm::openai_chat(&self.client, base_url, api_key, model, &messages, json_mode).await,
+        }
+    }
+
+    pub fn model_name(&self) -> &str {
+        match &self.model {
+            AIModel::OpenAI { model } | AIModel::Anthropic { model } | AIModel::Ollama { model, .. } => model,
+            AIModel::Local { model_path } => model_path,
+        }
+    }
+
+    fn parse_analysis(content: &str) -> Result<AIAnalysisResult> {
+        let json = llm::extract_json(content)?;
+        let parsed: ModelAnalysis = serde_json::from_str(json)
+            .map_err(|e| anyhow::anyhow!("Failed to parse model response: {}", e))?;
         Ok(AIAnalysisResult {
-            security_risk: SecurityRisk::Medium,
-            performance_insights: vec![
-                "请求响应时间较长，建议优化".to_string(),
-                "可以考虑启用缓存".to_string(),
-            ],
-            optimization_suggestions: vec![
-                "使用 CDN 加速静态资源".to_string(),
-                "启用 Gzip 压缩".to_string(),
-            ],
-            anomaly_detection: vec![
-                "检测到异常的请求频率".to_string(),
-            ],
-            api_patterns: vec![
-                ApiPattern {
-                    pattern_type: "REST API".to_string(),
-                    confidence: 0.95,
-                    description: "标准的 RESTful API 调用".to_string(),
-                },
-            ],
+            security_risk: parse_risk(&parsed.security_risk),
+            performance_insights: parsed.performance_insights,
+            optimization_suggestions: parsed.optimization_suggestions,
+            anomaly_detection: parsed.anomaly_detection,
+            api_patterns: parsed
+                .api_patterns
+                .into_iter()
+                .map(|p| ApiPattern {
+                    pattern_type: p.pattern_type,
+                    confidence: p.confidence.clamp(0.0, 1.0),
+                    description: p.description,
+                })
+                .collect(),
             data_flow_analysis: DataFlowAnalysis {
-                data_types: vec!["JSON".to_string(), "User Data".to_string()],
-                sensitive_data_detected: false,
-                data_flow_direction: "Client to Server".to_string(),
-                compliance_issues: vec![],
+                data_types: parsed.data_flow_analysis.data_types,
+                sensitive_data_detected: parsed.data_flow_analysis.sensitive_data_detected,
+                data_flow_direction: parsed.data_flow_analysis.data_flow_direction,
+                compliance_issues: parsed.data_flow_analysis.compliance_issues,
             },
+            body_digests: Vec::new(),
+            model: None,
+            cached: false,
         })
     }
 
-    async fn analyze_with_anthropic(&self, transaction: &HttpTransaction, model: &str) -> Result<AIAnalysisResult> {
-        // 集成 Anthropic Claude API
-        self.analyze_with_openai(transaction, model).await
-    }
+    // 不调用模型，只根据事务本身得出结论
+    pub fn heuristic_analysis(transaction: &HttpTransaction) -> AIAnalysisResult {
+        let risk = scoring::score(transaction);
+        let security_risk = match risk.heuristic_score {
+            90.. => SecurityRisk::Critical,
+            60..=89 => SecurityRisk::High,
+            30..=59 => SecurityRisk::Medium,
+            _ => SecurityRisk::Low,
+        };
+        let request = &transaction.request;
+        let response = transaction.response.as_ref();
+        let duration_ms = transaction.duration.map(|d| d.as_millis()).unwrap_or(0);
+        let response_size = response.map(|r| r.body.len()).unwrap_or(0);
+        let response_kind = transaction.response_kind();
+        let textual = matches!(response_kind, BodyKind::Json | BodyKind::Html | BodyKind::Xml | BodyKind::Text);
+
+        let mut performance_insights = Vec::new();
+        if duration_ms > 1000 {
+            performance_insights.push(format!("响应耗时 {}ms，超过 1 秒", duration_ms));
+        }
+        if response_size > 1024 * 1024 {
+            performance_insights.push(format!("响应体 {} KB，体积较大", response_size / 1024));
+        }
+
+        let mut optimization_suggestions = Vec::new();
+        if textual && response_size > 1024 && transaction.response_encoding.is_none() {
+            optimization_suggestions.push("文本响应未压缩，建议启用 gzip 或 br".to_string());
+        }
+        if request.method == "GET"
+            && response.is_some_and(|r| r.status == 200 && find_header(&r.headers, "cache-control").is_none())
+        {
+            optimization_suggestions.push("GET 响应缺少 Cache-Control，可考虑设置缓存策略".to_string());
+        }
+
+        let mut anomaly_detection = Vec::new();
+        match response.map(|r| r.status) {
+            Some(status) if status >= 500 => anomaly_detection.push(format!("服务器错误 {}", status)),
+            Some(status) if status >= 400 => anomaly_detection.push(format!("客户端错误 {}", status)),
+            None => anomaly_detection.push("未收到响应".to_string()),
+            _ => {}
+        }
+
+        let mut api_patterns = Vec::new();
+        if !transaction.graphql.is_empty() {
+            api_patterns.push(ApiPattern {
+                pattern_type: "GraphQL".to_string(),
+                confidence: 0.9,
+                description: format!("包含 {} 个 GraphQL 操作", transaction.graphql.len()),
+            });
+        } else if crate::grpc::is_grpc_transaction(transaction) {
+            api_patterns.push(ApiPattern {
+                pattern_type: "gRPC".to_string(),
+                confidence: 0.9,
+                description: "gRPC 调用".to_string(),
+            });
+        } else if response_kind == BodyKind::Json || transaction.request_kind() == BodyKind::Json {
+            api_patterns.push(ApiPattern {
+                pattern_type: "REST API".to_string(),
+                confidence: 0.6,
+                description: format!("{} 请求，JSON 数据交换", request.method),
+            });
+        }
 
-    async fn analyze_with_local_model(&self, transaction: &HttpTransaction, _model_path: &str) -> Result<AIAnalysisResult> {
-        // 集成本地模型 (如 ONNX, TensorFlow Lite)
-        self.analyze_with_openai(transaction, "local").await
+        let sensitive_data_detected = risk
+            .reasons
+            .iter()
+            .any(|r| r.contains("Credentials") || r.contains("Secrets"));
+        let mut data_types = Vec::new();
+        for kind in [transaction.request_kind(), response_kind] {
+            let name = format!("{:?}", kind);
+            if kind != BodyKind::Empty && !data_types.contains(&name) {
+                data_types.push(name);
+            }
+        }
+        let data_flow_direction = match (request.body.is_empty(), response_size == 0) {
+            (false, false) => "Bidirectional",
+            (false, true) => "Client to Server",
+            _ => "Server to Client",
+        };
+        let mut compliance_issues = Vec::new();
+        if sensitive_data_detected && request.url.starts_with("http://") {
+            compliance_issues.push("敏感数据经明文 HTTP 传输".to_string());
+        }
+
+        AIAnalysisResult {
+            security_risk,
+            performance_insights,
+            optimization_suggestions,
+            anomaly_detection,
+            api_patterns,
+            data_flow_analysis: DataFlowAnalysis {
+                data_types,
+                sensitive_data_detected,
+                data_flow_direction: data_flow_direction.to_string(),
+                compliance_issues,
+            },
+            body_digests: Vec::new(),
+            model: None,
+            cached: false,
+        }
     }
 
-    fn build_analysis_prompt(&self, transaction: &HttpTransaction) -> String {
+    fn build_analysis_prompt(&self, transaction: &HttpTransaction, body_digests: &[BodyDigest]) -> String {
+        let describe = |digest: Option<&BodyDigest>| match digest {
+            None => "(无)".to_string(),
+            Some(d) if d.original_bytes == 0 => "(空)".to_string(),
+            Some(d) if d.summarized => format!(
+                "(原始 {} 字节，已分 {} 块摘要)\n{}",
+                d.original_bytes, d.chunks, d.content
+            ),
+            Some(d) => d.content.clone(),
+        };
+        
         format!(
             r#"
 分析以下 HTTP 请求并提供详细的安全、性能和优化建议：
@@ -117,6 +428,10 @@ impl AIAnalyzer {
 - 响应时间: {}ms
 - 请求头: {:?}
 - 响应头: {:?}
+- 请求体: {}
+- 响应体: {}
+
+凭据类请求头和敏感字段已替换为 [redacted]，不要推测其原值。
 
 请从以下角度进行分析：
 1. 安全风险评估
@@ -132,46 +447,19 @@ impl AIAnalyzer {
             transaction.duration.map(|d| d.as_millis()).unwrap_or(0),
             transaction.request.headers,
             transaction.response.as_ref().map(|r| &r.headers),
+            describe(body_digests.first()),
+            describe(body_digests.get(1)),
         )
     }
 
     pub async fn batch_analyze(&self, transactions: &[HttpTransaction]) -> Result<Vec<AIAnalysisResult>> {
         let mut results = Vec::new();
         for transaction in transactions {
-            results.push(self.analyze_transaction(transaction).await?);
+            results.push(self.analyze_transaction_streaming(transaction, None).await?);
         }
         Ok(results)
     }
 
-    pub async fn detect_anomalies(&self, transactions: &[HttpTransaction]) -> Result<Vec<String>> {
-        // 使用 AI 检测异常模式
-        let mut anomalies = Vec::new();
-        
-        // 检测异常请求频率
-        let mut request_counts: HashMap<String, usize> = HashMap::new();
-        for transaction in transactions {
-            let domain = extract_domain(&transaction.request.url);
-            *request_counts.entry(domain).or_insert(0) += 1;
-        }
-
-        for (domain, count) in request_counts {
-            if count > 100 {
-                anomalies.push(format!("域名 {} 请求频率异常: {} 次", domain, count));
-            }
-        }
-
-        // 检测异常状态码
-        for transaction in transactions {
-            if let Some(response) = &transaction.response {
-                if response.status >= 500 {
-                    anomalies.push(format!("检测到服务器错误: {} - {}", response.status, transaction.request.url));
-                }
-            }
-        }
-
-        Ok(anomalies)
-    }
-
     pub async fn suggest_optimizations(&self, transactions: &[HttpTransaction]) -> Result<Vec<String>> {
         let mut suggestions = Vec::new();
         
@@ -203,86 +491,11 @@ impl AIAnalyzer {
     }
 }
 
-fn extract_domain(url: &str) -> String {
-    url.split("://")
-        .nth(1)
-        .unwrap_or(url)
-        .split('/')
-        .next()
-        .unwrap_or(url)
-        .to_string()
-}
-
-// AI 驱动的安全检测
-pub struct SecurityAnalyzer {
-    ai_analyzer: AIAnalyzer,
-}
-
-impl SecurityAnalyzer {
-    pub fn new(ai_analyzer: AIAnalyzer) -> Self {
-        Self { ai_analyzer }
-    }
-
-    pub async fn detect_vulnerabilities(&self, transaction: &HttpTransaction) -> Result<Vec<String>> {
-        let mut vulnerabilities = Vec::new();
-        
-        // SQL 注入检测
-        if self.detect_sql_injection(&transaction.request).await {
-            vulnerabilities.push("潜在的 SQL 注入攻击".to_string());
-        }
-
-        // XSS 检测
-        if self.detect_xss(&transaction.request).await {
-            vulnerabilities.push("潜在的 XSS 攻击".to_string());
-        }
-
-        // 敏感信息泄露检测
-        if self.detect_sensitive_data(&transaction).await {
-            vulnerabilities.push("检测到敏感信息泄露".to_string());
-        }
-
-        Ok(vulnerabilities)
-    }
-
-    async fn detect_sql_injection(&self, request: &HttpRequest) -> bool {
-        let sql_patterns = [
-            "SELECT", "INSERT", "UPDATE", "DELETE", "DROP", "UNION",
-            "OR 1=1", "OR '1'='1", "'; DROP", "'; --",
-        ];
-
-        let url_lower = request.url.to_lowercase();
-        let body_lower = String::from_utf8_lossy(&request.body).to_lowercase();
-
-        sql_patterns.iter().any(|pattern| {
-            url_lower.contains(&pattern.to_lowercase()) || body_lower.contains(&pattern.to_lowercase())
-        })
-    }
-
-    async fn detect_xss(&self, request: &HttpRequest) -> bool {
-        let xss_patterns = [
-            "<script>", "javascript:", "onload=", "onerror=", "onclick=",
-            "alert(", "confirm(", "prompt(",
-        ];
-
-        let url_lower = request.url.to_lowercase();
-        let body_lower = String::from_utf8_lossy(&request.body).to_lowercase();
-
-        xss_patterns.iter().any(|pattern| {
-            url_lower.contains(&pattern.to_lowercase()) || body_lower.contains(&pattern.to_lowercase())
-        })
-    }
-
-    async fn detect_sensitive_data(&self, transaction: &HttpTransaction) -> bool {
-        let sensitive_patterns = [
-            "password", "token", "key", "secret", "api_key", "auth",
-            "credit_card", "ssn", "social_security",
-        ];
-
-        let url_lower = transaction.request.url.to_lowercase();
-        let body_lower = String::from_utf8_lossy(&transaction.request.body).to_lowercase();
-
-        sensitive_patterns.iter().any(|pattern| {
-            url_lower.contains(pattern) || body_lower.contains(pattern)
-        })
+fn parse_risk(value: &str) -> SecurityRisk {
+    match value.trim().to_lowercase().as_str() {
+        "critical" => SecurityRisk::Critical,
+        "high" => SecurityRisk::High,
+        "medium" | "moderate" => SecurityRisk::Medium,
+        _ => SecurityRisk::Low,
     }
 }
@@ -1,5 +1,7 @@
+use crate::mockdata::{self, MockLocale, MockOptions};
 use crate::proxy::{HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use anyhow::Result;
 use std::collections::HashMap;
 
@@ -9,6 +11,21 @@ pub struct AIResponseConfig {
     pub response_type: ResponseType,
     pub content_template: Option<String>,
     pub ai_model: String,
+    // 按抓包推断的 schema 生成数据时，记录列表的条数和数据语言
+    #[serde(default)]
+    pub record_count: Option<usize>,
+    #[serde(default)]
+    pub locale: MockLocale,
+}
+
+// 返回给界面的模拟响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedMock {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    // 参与推断 schema 的抓包样本数；为 0 且未传入 schema 时使用内置模板
+    pub observed_samples: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,22 +39,27 @@ pub enum ResponseType {
 pub struct AIResponseGenerator {
     config: AIResponseConfig,
     templates: ResponseTemplates,
+    // 同一接口真实响应推断出的 JSON Schema
+    observed_schema: Option<Value>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ResponseTemplates {
-    pub mock_responses: HashMap<String, String>,
     pub error_responses: HashMap<u16, String>,
 }
 
 impl AIResponseGenerator {
     pub fn new(config: AIResponseConfig) -> Self {
         let templates = ResponseTemplates {
-            mock_responses: Self::load_mock_templates(),
             error_responses: Self::load_error_templates(),
         };
         
-        Self { config, templates }
+        Self { config, templates, observed_schema: None }
+    }
+
+    pub fn with_observed_schema(mut self, schema: Value) -> Self {
+        self.observed_schema = Some(schema);
+        self
     }
 
     pub async fn generate_response(&self, request: &HttpRequest) -> Result<HttpResponse> {
@@ -50,7 +72,11 @@ impl AIResponseGenerator {
     }
 
     async fn generate_mock_response(&self, request: &HttpRequest) -> Result<HttpResponse> {
-        let content_type = self.detect_content_type(request);
+        let content_type = if self.observed_schema.is_some() {
+            "application/json".to_string()
+        } else {
+            self.detect_content_type(request)
+        };
         let mock_data = self.generate_mock_data(request, &content_type).await;
         
         let mut headers = HashMap::new();
@@ -62,6 +88,7 @@ impl AIResponseGenerator {
             headers,
             body: mock_data.into_bytes(),
             timestamp: chrono::Utc::now(),
+            version: None,
         })
     }
 
@@ -78,6 +105,7 @@ impl AIResponseGenerator {
             headers,
             body: enhanced_content.into_bytes(),
             timestamp: chrono::Utc::now(),
+            version: None,
         })
     }
 
@@ -104,6 +132,7 @@ impl AIResponseGenerator {
             headers,
             body: serde_json::to_string(&error_body)?.into_bytes(),
             timestamp: chrono::Utc::now(),
+            version: None,
         })
     }
 
@@ -119,6 +148,7 @@ impl AIResponseGenerator {
                 headers,
                 body: custom_content.into_bytes(),
                 timestamp: chrono::Utc::now(),
+                version: None,
             })
         } else {
             self.generate_mock_response(request).await
@@ -148,6 +178,11 @@ impl AIResponseGenerator {
     }
 
     async fn generate_json_mock(&self, request: &HttpRequest) -> String {
+        // 接口抓到过真实响应时按其结构生成，否则退回内置模板
+        if let Some(schema) = &self.observed_schema {
+            let options = MockOptions { record_count: self.config.record_count, locale: self.config.locale };
+            return mockdata::generate(schema, &options).to_string();
+        }
         let endpoint = self.extract_endpoint(request);
         
         match endpoint.as_str() {
@@ -325,14 +360,6 @@ impl AIResponseGenerator {
             .to_string()
     }
 
-    fn load_mock_templates() -> HashMap<String, String> {
-        let mut templates = HashMap::new();
-        templates.insert("users".to_string(), "users_template".to_string());
-        templates.insert("products".to_string(), "products_template".to_string());
-        templates.insert("orders".to_string(), "orders_template".to_string());
-        templates
-    }
-
     fn load_error_templates() -> HashMap<u16, String> {
         let mut templates = HashMap::new();
         templates.insert(400, "Bad Request".to_string());
@@ -376,7 +403,8 @@ impl AIRouter {
             if self.matches_pattern(&request.url, &rule.pattern) {
                 let mut config = self.response_generator.config.clone();
                 config.response_type = rule.response_type.clone();
-                let generator = AIResponseGenerator::new(config);
+                let mut generator = AIResponseGenerator::new(config);
+                generator.observed_schema = self.response_generator.observed_schema.clone();
                 return generator.generate_response(request).await;
             }
         }
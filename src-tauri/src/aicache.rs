@@ -0,0 +1,121 @@
+use crate::ai_analyzer::AIAnalysisResult;
+use crate::proxy::HttpTransaction;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::warn;
+
+pub const AI_CACHE_FILE_NAME: &str = "ai_cache.json";
+
+// 超出后淘汰最早缓存的结果
+const MAX_ENTRIES: usize = 2000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedAnalysis {
+    // 请求与响应内容的摘要，用于按事务失效
+    content_hash: String,
+    result: AIAnalysisResult,
+    cached_at: DateTime<Utc>,
+}
+
+// 只缓存模型给出的结果；启发式分析不花费 token，配置密钥后应当重新分析
+#[derive(Debug, Default)]
+pub struct AiCache {
+    path: Option<PathBuf>,
+    // 键为 "内容摘要:模型"，切换模型后不会命中其他模型的结果
+    entries: HashMap<String, CachedAnalysis>,
+}
+
+impl AiCache {
+    pub fn load(path: PathBuf) -> Self {
+        let entries = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!("Failed to parse AI cache {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self { path: Some(path), entries }
+    }
+
+    pub fn get(&self, content_hash: &str, model: &str) -> Option<AIAnalysisResult> {
+        let mut result = self.entries.get(&entry_key(content_hash, model))?.result.clone();
+        result.cached = true;
+        Some(result)
+    }
+
+    // 返回需要落盘的数据
+    pub fn insert(&mut self, content_hash: &str, model: &str, result: &AIAnalysisResult) -> Option<(PathBuf, Vec<u8>)> {
+        // 启发式结果不缓存
+        result.model.as_ref()?;
+        let mut result = result.clone();
+        result.cached = false;
+        self.entries.insert(
+            entry_key(content_hash, model),
+            CachedAnalysis { content_hash: content_hash.to_string(), result, cached_at: Utc::now() },
+        );
+        if self.entries.len() > MAX_ENTRIES {
+            let mut by_age: Vec<(DateTime<Utc>, String)> =
+                self.entries.iter().map(|(key, entry)| (entry.cached_at, key.clone())).collect();
+            by_age.sort();
+            for (_, key) in by_age.into_iter().take(self.entries.len() - MAX_ENTRIES) {
+                self.entries.remove(&key);
+            }
+        }
+        self.snapshot()
+    }
+
+    // 给定内容摘要时只清除该事务在所有模型下的结果；返回清除的数量
+    pub fn invalidate(&mut self, content_hash: Option<&str>) -> (usize, Option<(PathBuf, Vec<u8>)>) {
+        let before = self.entries.len();
+        match content_hash {
+            Some(hash) => self.entries.retain(|_, entry| entry.content_hash != hash),
+            None => self.entries.clear(),
+        }
+        let removed = before - self.entries.len();
+        (removed, if removed > 0 { self.snapshot() } else { None })
+    }
+
+    fn snapshot(&self) -> Option<(PathBuf, Vec<u8>)> {
+        let path = self.path.clone()?;
+        let data = serde_json::to_vec(&self.entries).ok()?;
+        Some((path, data))
+    }
+}
+
+fn entry_key(content_hash: &str, model: &str) -> String {
+    format!("{}:{}", content_hash, model)
+}
+
+// 方法、URL、请求头与请求体，以及响应的状态、头与响应体；头按名称排序，与抓包时的顺序无关
+pub fn content_hash(transaction: &HttpTransaction) -> String {
+    let mut hasher = Sha256::new();
+    let request = &transaction.request;
+    hash_field(&mut hasher, request.method.as_bytes());
+    hash_field(&mut hasher, request.url.as_bytes());
+    hash_headers(&mut hasher, &request.headers);
+    hash_field(&mut hasher, &request.body);
+    if let Some(response) = &transaction.response {
+        hash_field(&mut hasher, &response.status.to_be_bytes());
+        hash_headers(&mut hasher, &response.headers);
+        hash_field(&mut hasher, &response.body);
+    }
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 带上长度前缀，避免相邻字段拼接后产生相同的输入
+fn hash_field(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_be_bytes());
+    hasher.update(bytes);
+}
+
+fn hash_headers(hasher: &mut Sha256, headers: &HashMap<String, String>) {
+    let mut sorted: Vec<(String, &String)> = headers.iter().map(|(k, v)| (k.to_lowercase(), v)).collect();
+    sorted.sort();
+    for (name, value) in sorted {
+        hash_field(hasher, name.as_bytes());
+        hash_field(hasher, value.as_bytes());
+    }
+}
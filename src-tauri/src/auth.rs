@@ -0,0 +1,66 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use hyper::header::HeaderValue;
+
+pub const REALM: &str = "PacketMind";
+
+// 代理监听在局域网地址时，要求客户端携带 Proxy-Authorization（目前仅支持 Basic）
+#[derive(Debug, Clone)]
+pub struct ProxyCredentials {
+    username: String,
+    password: String,
+}
+
+impl ProxyCredentials {
+    pub fn new(username: String, password: String) -> Result<Self> {
+        // Basic 认证以第一个冒号分隔用户名和密码
+        if username.is_empty() || username.contains(':') {
+            return Err(anyhow!("Username must be non-empty and must not contain ':'"));
+        }
+        if password.is_empty() {
+            return Err(anyhow!("Password must not be empty"));
+        }
+        Ok(Self { username, password })
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    pub fn authorize(&self, header: Option<&HeaderValue>) -> bool {
+        let Some((username, password)) = header.and_then(parse_basic) else {
+            return false;
+        };
+        // 两项都比较完，不因用户名不符提前返回
+        let username_ok = constant_time_eq(username.as_bytes(), self.username.as_bytes());
+        let password_ok = constant_time_eq(password.as_bytes(), self.password.as_bytes());
+        username_ok & password_ok
+    }
+}
+
+fn parse_basic(header: &HeaderValue) -> Option<(String, String)> {
+    let value = header.to_str().ok()?.trim();
+    let (scheme, encoded) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = general_purpose::STANDARD.decode(encoded.trim()).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+    Some((username.to_string(), password.to_string()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn challenge() -> String {
+    format!("Basic realm=\"{}\", charset=\"UTF-8\"", REALM)
+}
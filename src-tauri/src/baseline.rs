@@ -0,0 +1,337 @@
+use crate::history::split_endpoint;
+use crate::proxy::HttpTransaction;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+pub const BASELINE_FILE_NAME: &str = "anomaly_baseline.json";
+pub const ANOMALY_EVENT: &str = "anomaly:detected";
+
+// 内存中保留的最近异常数量
+pub const MAX_RECENT_ANOMALIES: usize = 500;
+
+// 两次落盘之间的最短间隔
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+// 延迟与状态占比的平滑系数，约等于最近 40 个请求的滑动窗口
+const SAMPLE_ALPHA: f64 = 0.05;
+// 每分钟请求数的平滑系数，约等于最近 20 分钟
+const RATE_ALPHA: f64 = 0.1;
+// 学习期：样本不足时只更新基线，不判断异常
+const MIN_SAMPLES: u64 = 20;
+const MIN_RATE_MINUTES: u64 = 10;
+// 超过该分钟数没有请求视为停止抓包，不把空档计入速率基线
+const MAX_IDLE_MINUTES: i64 = 5;
+// 偏离达到该 z 分数才报告
+const Z_THRESHOLD: f64 = 3.0;
+// 低于这些绝对值的偏离即使 z 分数很高也不值得关注
+const MIN_LATENCY_MS: f64 = 250.0;
+const MIN_RATE_PER_MINUTE: f64 = 10.0;
+// 状态占比的概率下限，避免从未出错的接口第一次出错时 z 分数无穷大
+const MIN_PROBABILITY: f64 = 0.001;
+// 对数延迟的标准差下限，约等于 10% 的抖动
+const MIN_LOG_LATENCY_SD: f64 = 0.1;
+// 状态占比按最近这么多个响应计算，单个响应说明不了占比的变化
+const STATUS_WINDOW: usize = 20;
+// 窗口内某类错误少于该次数时不判断，偶发的错误不算异常
+const MIN_WINDOW_ERRORS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    RequestRate,
+    Latency,
+    StatusRatio,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalySeverity {
+    Low,
+    Medium,
+    High,
+}
+
+impl AnomalySeverity {
+    fn from_z(z: f64) -> Self {
+        if z >= 8.0 {
+            Self::High
+        } else if z >= 5.0 {
+            Self::Medium
+        } else {
+            Self::Low
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Anomaly {
+    pub endpoint: String,
+    pub kind: AnomalyKind,
+    pub severity: AnomalySeverity,
+    pub transaction_id: String,
+    pub observed: f64,
+    pub expected: f64,
+    pub z_score: f64,
+    pub description: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+// 指数加权的均值与方差
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Ewma {
+    mean: f64,
+    variance: f64,
+    samples: u64,
+}
+
+impl Ewma {
+    fn update(&mut self, value: f64, alpha: f64) {
+        if self.samples == 0 {
+            self.mean = value;
+            self.variance = 0.0;
+        } else {
+            let diff = value - self.mean;
+            let increment = alpha * diff;
+            self.mean += increment;
+            self.variance = (1.0 - alpha) * (self.variance + diff * increment);
+        }
+        self.samples += 1;
+    }
+
+    fn z_score(&self, value: f64, min_sd: f64) -> f64 {
+        (value - self.mean) / self.variance.sqrt().max(min_sd)
+    }
+}
+
+// 状态分类：2xx、3xx、4xx、5xx、无响应（含 1xx 等其他情况）
+const STATUS_CLASSES: usize = 5;
+
+fn status_class(status: Option<u16>) -> usize {
+    match status {
+        Some(200..=299) => 0,
+        Some(300..=399) => 1,
+        Some(400..=499) => 2,
+        Some(500..=599) => 3,
+        _ => 4,
+    }
+}
+
+fn status_class_name(class: usize) -> &'static str {
+    ["2xx", "3xx", "4xx", "5xx", "无响应"][class]
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct EndpointBaseline {
+    // 对数尺度上的延迟，延迟分布通常右偏
+    log_latency: Ewma,
+    // 各状态分类的出现概率
+    status_ratio: [Ewma; STATUS_CLASSES],
+    requests_per_minute: Ewma,
+    // 当前分钟（Unix 分钟数）及其请求数，分钟结束后计入速率基线
+    current_minute: i64,
+    current_count: u64,
+    // 本分钟已报告过速率异常
+    #[serde(default)]
+    rate_flagged: bool,
+    // 最近响应的状态分类
+    #[serde(default)]
+    status_window: VecDeque<u8>,
+    // 各状态分类正处于已报告的异常中，占比回落后才会再次报告
+    #[serde(default)]
+    status_flagged: [bool; STATUS_CLASSES],
+}
+
+impl EndpointBaseline {
+    // 先按已有基线判断，再把本次观察计入基线
+    fn observe(&mut self, endpoint: &str, transaction: &HttpTransaction, anomalies: &mut Vec<Anomaly>) {
+        let timestamp = transaction.request.timestamp;
+        let mut flag = |kind, observed: f64, expected: f64, z_score: f64, description: String| {
+            anomalies.push(Anomaly {
+                endpoint: endpoint.to_string(),
+                kind,
+                severity: AnomalySeverity::from_z(z_score),
+                transaction_id: transaction.id.clone(),
+                observed,
+                expected,
+                z_score,
+                description,
+                timestamp,
+            });
+        };
+
+        // 请求速率
+        let minute = timestamp.timestamp().div_euclid(60);
+        if minute != self.current_minute {
+            if self.current_count > 0 {
+                self.requests_per_minute.update(self.current_count as f64, RATE_ALPHA);
+                let idle = minute - self.current_minute - 1;
+                if (1..=MAX_IDLE_MINUTES).contains(&idle) {
+                    for _ in 0..idle {
+                        self.requests_per_minute.update(0.0, RATE_ALPHA);
+                    }
+                }
+            }
+            self.current_minute = minute;
+            self.current_count = 0;
+            self.rate_flagged = false;
+        }
+        self.current_count += 1;
+        let rate = &self.requests_per_minute;
+        let count = self.current_count as f64;
+        if rate.samples >= MIN_RATE_MINUTES && !self.rate_flagged && count >= MIN_RATE_PER_MINUTE {
+            // 按泊松分布估计标准差下限
+            let z = rate.z_score(count, rate.mean.sqrt().max(1.0));
+            if z >= Z_THRESHOLD {
+                self.rate_flagged = true;
+                flag(
+                    AnomalyKind::RequestRate,
+                    count,
+                    rate.mean,
+                    z,
+                    format!("{} 请求频率异常：本分钟 {} 次，通常约 {:.1} 次/分钟", endpoint, count, rate.mean),
+                );
+            }
+        }
+
+        // 延迟
+        if let Some(duration) = transaction.duration {
+            let ms = duration.as_secs_f64() * 1000.0;
+            let log_ms = ms.ln_1p();
+            let latency = &self.log_latency;
+            if latency.samples >= MIN_SAMPLES && ms >= MIN_LATENCY_MS {
+                let z = latency.z_score(log_ms, MIN_LOG_LATENCY_SD);
+                if z >= Z_THRESHOLD {
+                    let expected = latency.mean.exp_m1();
+                    flag(
+                        AnomalyKind::Latency,
+                        ms,
+                        expected,
+                        z,
+                        format!("{} 响应变慢：{:.0}ms，通常约 {:.0}ms", endpoint, ms, expected),
+                    );
+                }
+            }
+            self.log_latency.update(log_ms, SAMPLE_ALPHA);
+        }
+
+        // 状态占比：只关注错误类状态的占比上升，按窗口内的占比与基线比较（二项分布）
+        let class = status_class(transaction.response.as_ref().map(|r| r.status));
+        // 基线只吸收滑出窗口的响应，避免正在发生的突增把基线一起抬高
+        self.status_window.push_back(class as u8);
+        let evicted = if self.status_window.len() > STATUS_WINDOW {
+            self.status_window.pop_front()
+        } else {
+            None
+        };
+        let window = self.status_window.len();
+        for error_class in 2..STATUS_CLASSES {
+            let ratio = &self.status_ratio[error_class];
+            let count = self.status_window.iter().filter(|c| **c as usize == error_class).count();
+            if ratio.samples < MIN_SAMPLES || window < STATUS_WINDOW || count < MIN_WINDOW_ERRORS {
+                self.status_flagged[error_class] = false;
+                continue;
+            }
+            let p = ratio.mean.clamp(MIN_PROBABILITY, 1.0 - MIN_PROBABILITY);
+            let observed = count as f64 / window as f64;
+            let z = (observed - p) / (p * (1.0 - p) / window as f64).sqrt();
+            if z < Z_THRESHOLD {
+                self.status_flagged[error_class] = false;
+                continue;
+            }
+            // 只在该类响应上报告，且同一段异常只报告一次
+            if error_class != class || self.status_flagged[error_class] {
+                continue;
+            }
+            self.status_flagged[error_class] = true;
+            flag(
+                AnomalyKind::StatusRatio,
+                observed,
+                ratio.mean,
+                z,
+                format!(
+                    "{} 最近 {} 个请求中有 {} 个返回 {}（{:.0}%），该接口通常约 {:.1}%",
+                    endpoint,
+                    window,
+                    count,
+                    status_class_name(error_class),
+                    observed * 100.0,
+                    ratio.mean * 100.0
+                ),
+            );
+        }
+        if let Some(evicted) = evicted {
+            for (index, ratio) in self.status_ratio.iter_mut().enumerate() {
+                ratio.update(if index == evicted as usize { 1.0 } else { 0.0 }, SAMPLE_ALPHA);
+            }
+        }
+    }
+}
+
+// 跨会话保留的每个接口的流量基线，随抓包持续更新
+#[derive(Debug, Default)]
+pub struct AnomalyBaseline {
+    path: Option<PathBuf>,
+    endpoints: HashMap<String, EndpointBaseline>,
+    dirty: bool,
+    last_saved: Option<Instant>,
+}
+
+impl AnomalyBaseline {
+    pub fn load(path: PathBuf) -> Self {
+        let endpoints = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!("Failed to parse anomaly baseline {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self { path: Some(path), endpoints, dirty: false, last_saved: None }
+    }
+
+    pub fn observe(&mut self, transaction: &HttpTransaction) -> Vec<Anomaly> {
+        let (host, mut path) = split_endpoint(&transaction.request.url);
+        // 与长期统计一致，同一 GraphQL 端点按操作名称分别建立基线
+        if let Some(label) = transaction.graphql_label() {
+            path = format!("{}#{}", path, label);
+        }
+        let endpoint = format!("{} {}{}", transaction.request.method, host, path);
+        let mut anomalies = Vec::new();
+        self.endpoints
+            .entry(endpoint.clone())
+            .or_default()
+            .observe(&endpoint, transaction, &mut anomalies);
+        self.dirty = true;
+        anomalies
+    }
+
+    // 清空后重新学习
+    pub fn reset(&mut self) -> Option<(PathBuf, Vec<u8>)> {
+        self.endpoints.clear();
+        self.dirty = true;
+        self.snapshot()
+    }
+
+    pub fn snapshot_if_due(&mut self) -> Option<(PathBuf, Vec<u8>)> {
+        let due = self.last_saved.map(|t| t.elapsed() >= SAVE_INTERVAL).unwrap_or(true);
+        if due {
+            self.snapshot()
+        } else {
+            None
+        }
+    }
+
+    pub fn snapshot(&mut self) -> Option<(PathBuf, Vec<u8>)> {
+        if !self.dirty {
+            return None;
+        }
+        let path = self.path.clone()?;
+        let data = serde_json::to_vec(&self.endpoints).ok()?;
+        self.dirty = false;
+        self.last_saved = Some(Instant::now());
+        Some((path, data))
+    }
+}
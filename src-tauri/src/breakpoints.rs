@@ -0,0 +1,197 @@
+use crate::proxy::{HttpRequest, HttpResponse};
+use crate::rules;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::oneshot;
+
+pub const BREAKPOINT_EVENT: &str = "breakpoint:hit";
+// 超时未处理的请求自动放行，避免客户端连接一直挂起
+pub const INTERCEPT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BreakpointDirection {
+    Request,
+    Response,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum InterceptPhase {
+    Request,
+    Response,
+}
+
+// pattern 与规则相同：含 * 时按通配符匹配整个 URL，否则按子串匹配
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Breakpoint {
+    pub id: String,
+    pub pattern: String,
+    pub method: Option<String>,
+    pub direction: BreakpointDirection,
+    pub enabled: bool,
+}
+
+impl Breakpoint {
+    fn matches(&self, phase: InterceptPhase, request: &HttpRequest) -> bool {
+        let direction_matches = matches!(
+            (self.direction, phase),
+            (BreakpointDirection::Both, _)
+                | (BreakpointDirection::Request, InterceptPhase::Request)
+                | (BreakpointDirection::Response, InterceptPhase::Response)
+        );
+        self.enabled
+            && direction_matches
+            && self
+                .method
+                .as_ref()
+                .map(|m| m.eq_ignore_ascii_case(&request.method))
+                .unwrap_or(true)
+            && rules::matches(&self.pattern, &request.url)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterceptedItem {
+    pub id: String,
+    pub breakpoint_id: String,
+    pub phase: InterceptPhase,
+    pub request: HttpRequest,
+    // 仅响应阶段有值
+    pub response: Option<HttpResponse>,
+    pub paused_at: chrono::DateTime<chrono::Utc>,
+}
+
+// 请求阶段修改 method、url、headers、body；响应阶段修改 status、headers、body
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InterceptModification {
+    pub method: Option<String>,
+    pub url: Option<String>,
+    pub status: Option<u16>,
+    pub headers: Option<HashMap<String, String>>,
+    pub body: Option<String>,
+}
+
+pub enum InterceptResolution {
+    Resume(Box<InterceptedItem>),
+    Drop,
+}
+
+struct Paused {
+    item: InterceptedItem,
+    sender: oneshot::Sender<InterceptResolution>,
+}
+
+#[derive(Default)]
+pub struct InterceptQueue {
+    breakpoints: Vec<Breakpoint>,
+    paused: HashMap<String, Paused>,
+}
+
+impl InterceptQueue {
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.retain(|b| b.id != breakpoint.id);
+        self.breakpoints.push(breakpoint);
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint_id: &str) {
+        self.breakpoints.retain(|b| b.id != breakpoint_id);
+    }
+
+    pub fn breakpoints(&self) -> Vec<Breakpoint> {
+        self.breakpoints.clone()
+    }
+
+    pub fn will_pause(&self, phase: InterceptPhase, request: &HttpRequest) -> bool {
+        self.breakpoints.iter().any(|b| b.matches(phase, request))
+    }
+
+    // 命中断点时加入暂停队列，返回通知界面的条目和等待处理结果的接收端
+    pub fn pause(
+        &mut self,
+        phase: InterceptPhase,
+        request: &HttpRequest,
+        response: Option<&HttpResponse>,
+    ) -> Option<(InterceptedItem, oneshot::Receiver<InterceptResolution>)> {
+        let breakpoint = self.breakpoints.iter().find(|b| b.matches(phase, request))?;
+        let item = InterceptedItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            breakpoint_id: breakpoint.id.clone(),
+            phase,
+            request: request.clone(),
+            response: response.cloned(),
+            paused_at: chrono::Utc::now(),
+        };
+        let (sender, receiver) = oneshot::channel();
+        self.paused.insert(item.id.clone(), Paused { item: item.clone(), sender });
+        Some((item, receiver))
+    }
+
+    pub fn list(&self) -> Vec<InterceptedItem> {
+        let mut items: Vec<InterceptedItem> = self.paused.values().map(|p| p.item.clone()).collect();
+        items.sort_by_key(|i| i.paused_at);
+        items
+    }
+
+    pub fn modify(&mut self, item_id: &str, modification: InterceptModification) -> Result<InterceptedItem> {
+        let paused = self
+            .paused
+            .get_mut(item_id)
+            .ok_or_else(|| anyhow!("Intercepted item not found: {}", item_id))?;
+        let item = &mut paused.item;
+        match (item.phase, item.response.as_mut()) {
+            (InterceptPhase::Response, Some(response)) => {
+                if let Some(status) = modification.status {
+                    response.status = status;
+                }
+                if let Some(headers) = modification.headers {
+                    response.headers = headers;
+                }
+                if let Some(body) = modification.body {
+                    response.body = body.into_bytes();
+                }
+            }
+            _ => {
+                let request = &mut item.request;
+                if let Some(method) = modification.method {
+                    request.method = method;
+                }
+                if let Some(url) = modification.url {
+                    url::Url::parse(&url)?;
+                    request.url = url;
+                }
+                if let Some(headers) = modification.headers {
+                    request.headers = headers;
+                }
+                if let Some(body) = modification.body {
+                    request.body = body.into_bytes();
+                }
+            }
+        }
+        Ok(item.clone())
+    }
+
+    pub fn resume(&mut self, item_id: &str) -> Result<()> {
+        let paused = self.take(item_id)?;
+        // 接收端已超时放弃时忽略
+        let _ = paused.sender.send(InterceptResolution::Resume(Box::new(paused.item)));
+        Ok(())
+    }
+
+    pub fn drop_item(&mut self, item_id: &str) -> Result<()> {
+        let paused = self.take(item_id)?;
+        let _ = paused.sender.send(InterceptResolution::Drop);
+        Ok(())
+    }
+
+    // 等待超时后由代理移除
+    pub fn discard(&mut self, item_id: &str) {
+        self.paused.remove(item_id);
+    }
+
+    fn take(&mut self, item_id: &str) -> Result<Paused> {
+        self.paused
+            .remove(item_id)
+            .ok_or_else(|| anyhow!("Intercepted item not found: {}", item_id))
+    }
+}
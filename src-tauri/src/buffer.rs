@@ -0,0 +1,124 @@
+use crate::proxy::HttpTransaction;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+pub const DEFAULT_MAX_ENTRIES: usize = 100_000;
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024 * 1024;
+
+// 内存中事务缓冲区的上限，None 表示不限制
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BufferLimits {
+    pub max_entries: Option<usize>,
+    pub max_body_bytes: Option<usize>,
+}
+
+impl Default for BufferLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: Some(DEFAULT_MAX_ENTRIES),
+            max_body_bytes: Some(DEFAULT_MAX_BODY_BYTES),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureStats {
+    pub transactions: usize,
+    pub body_bytes: usize,
+    // 含 URL、请求头等的估算内存占用
+    pub estimated_memory_bytes: usize,
+    pub limits: BufferLimits,
+    pub evicted: u64,
+    pub evicted_body_bytes: u64,
+}
+
+// 跟踪缓冲区内请求体/响应体的总字节数及淘汰计数
+#[derive(Debug, Default)]
+pub struct CaptureBuffer {
+    body_bytes: usize,
+    evicted: u64,
+    evicted_body_bytes: u64,
+}
+
+pub fn body_bytes(transaction: &HttpTransaction) -> usize {
+    transaction.request.body.len()
+        + transaction.response.as_ref().map(|r| r.body.len()).unwrap_or(0)
+        + transaction.ws_messages.iter().map(|m| m.payload.len()).sum::<usize>()
+}
+
+fn estimated_memory(transaction: &HttpTransaction) -> usize {
+    let headers = |headers: &std::collections::HashMap<String, String>| {
+        headers.iter().map(|(k, v)| k.len() + v.len()).sum::<usize>()
+    };
+    std::mem::size_of::<HttpTransaction>()
+        + body_bytes(transaction)
+        + transaction.request.url.len()
+        + headers(&transaction.request.headers)
+        + transaction.response.as_ref().map(|r| headers(&r.headers)).unwrap_or(0)
+}
+
+impl CaptureBuffer {
+    pub fn added(&mut self, transaction: &HttpTransaction) {
+        self.body_bytes += body_bytes(transaction);
+    }
+
+    // 事务原地修改（如追加 WebSocket 消息）前后的大小变化
+    pub fn resized(&mut self, before: usize, after: usize) {
+        self.body_bytes = (self.body_bytes + after).saturating_sub(before);
+    }
+
+    // 整体替换或批量合并事务后重新统计
+    pub fn recount(&mut self, transactions: &[HttpTransaction]) {
+        self.body_bytes = transactions.iter().map(body_bytes).sum();
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // 超出上限时按时间从旧到新淘汰，收藏的事务不淘汰；返回淘汰的条目数
+    pub fn enforce(&mut self, transactions: &mut Vec<HttpTransaction>, limits: &BufferLimits) -> usize {
+        let over_entries = limits.max_entries.map(|max| transactions.len() > max).unwrap_or(false);
+        let over_bytes = limits.max_body_bytes.map(|max| self.body_bytes > max).unwrap_or(false);
+        if !over_entries && !over_bytes {
+            return 0;
+        }
+
+        // 一次淘汰到上限的 90% 以下，避免缓冲区满后每条新事务都整体搬移一次
+        let target = |max: usize| max - max / 10;
+        let target_entries = limits.max_entries.map(target).unwrap_or(usize::MAX);
+        let target_bytes = limits.max_body_bytes.map(target).unwrap_or(usize::MAX);
+        let mut remaining_entries = transactions.len();
+        let mut remaining_bytes = self.body_bytes;
+        let mut evicted = HashSet::new();
+        for transaction in transactions.iter() {
+            if remaining_entries <= target_entries && remaining_bytes <= target_bytes {
+                break;
+            }
+            if transaction.is_favorite {
+                continue;
+            }
+            let size = body_bytes(transaction);
+            remaining_entries -= 1;
+            remaining_bytes = remaining_bytes.saturating_sub(size);
+            self.evicted += 1;
+            self.evicted_body_bytes += size as u64;
+            evicted.insert(transaction.id.clone());
+        }
+        transactions.retain(|t| !evicted.contains(&t.id));
+        self.body_bytes = remaining_bytes;
+        evicted.len()
+    }
+
+    pub fn stats(&self, transactions: &[HttpTransaction], limits: &BufferLimits) -> CaptureStats {
+        CaptureStats {
+            transactions: transactions.len(),
+            body_bytes: self.body_bytes,
+            estimated_memory_bytes: transactions.iter().map(estimated_memory).sum(),
+            limits: limits.clone(),
+            evicted: self.evicted,
+            evicted_body_bytes: self.evicted_body_bytes,
+        }
+    }
+}
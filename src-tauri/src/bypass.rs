@@ -0,0 +1,108 @@
+use anyhow::{anyhow, Result};
+use std::net::IpAddr;
+
+// 设置系统代理前各平台原有的绕过列表，停止代理时原样恢复
+#[derive(Debug, Clone)]
+pub enum OriginalBypass {
+    // 每个网络服务各自的列表
+    #[cfg(target_os = "macos")]
+    MacOs(Vec<(String, Vec<String>)>),
+    // ProxyOverride 注册表值，未设置时为 None
+    #[cfg(target_os = "windows")]
+    Windows(Option<String>),
+    // gsettings 输出的原始 GVariant 文本
+    #[cfg(target_os = "linux")]
+    Linux(String),
+}
+
+#[derive(Debug, Default)]
+pub struct SystemProxyState {
+    pub bypass: Vec<String>,
+    pub original: Option<OriginalBypass>,
+}
+
+// 支持主机名、*.后缀通配、IP 地址与 CIDR 网段
+pub fn normalize(entries: Vec<String>) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for entry in entries {
+        let entry = entry.trim().to_lowercase();
+        if entry.is_empty() {
+            continue;
+        }
+        if !is_valid_entry(&entry) {
+            return Err(anyhow!("Invalid bypass entry: {}", entry));
+        }
+        if !normalized.contains(&entry) {
+            normalized.push(entry);
+        }
+    }
+    Ok(normalized)
+}
+
+fn is_valid_entry(entry: &str) -> bool {
+    if entry.parse::<IpAddr>().is_ok() || entry == "<local>" {
+        return true;
+    }
+    if let Some((addr, prefix)) = entry.split_once('/') {
+        let Ok(addr) = addr.parse::<IpAddr>() else {
+            return false;
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        return prefix.parse::<u8>().map(|p| p <= max).unwrap_or(false);
+    }
+    let host = entry.strip_prefix("*.").unwrap_or(entry);
+    !host.is_empty()
+        && host
+            .split('.')
+            .all(|label| !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '*'))
+}
+
+// Windows 的 ProxyOverride 不支持 CIDR，按字节对齐的网段转换为通配形式，其余网段无法表达时跳过
+#[cfg(target_os = "windows")]
+pub fn windows_override(entries: &[String]) -> String {
+    entries
+        .iter()
+        .filter_map(|entry| match entry.split_once('/') {
+            Some((addr, prefix)) => {
+                let IpAddr::V4(addr) = addr.parse().ok()? else {
+                    return None;
+                };
+                let prefix: usize = prefix.parse().ok()?;
+                if prefix % 8 != 0 {
+                    return None;
+                }
+                let mut parts: Vec<String> = addr.octets()[..prefix / 8].iter().map(u8::to_string).collect();
+                if parts.len() < 4 {
+                    parts.push("*".to_string());
+                }
+                Some(parts.join("."))
+            }
+            None => Some(entry.clone()),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+// gsettings 的字符串数组字面量
+#[cfg(target_os = "linux")]
+pub fn gsettings_list(entries: &[String]) -> String {
+    let items: Vec<String> = entries
+        .iter()
+        .map(|entry| format!("'{}'", entry.replace('\\', "\\\\").replace('\'', "\\'")))
+        .collect();
+    format!("[{}]", items.join(", "))
+}
+
+// networksetup -getproxybypassdomains 未设置时输出一行提示而不是域名
+#[cfg(target_os = "macos")]
+pub fn parse_macos_bypass(output: &str) -> Vec<String> {
+    if output.contains("There aren't any bypass domains") {
+        return Vec::new();
+    }
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
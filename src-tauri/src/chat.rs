@@ -0,0 +1,191 @@
+use crate::chunking::{digest_body, BodyDigest, DEFAULT_BODY_BUDGET};
+use crate::llm::ChatMessage;
+use crate::proxy::HttpTransaction;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::warn;
+
+// 数据目录下的子目录，每个对话一个文件
+pub const CONVERSATIONS_DIR: &str = "conversations";
+
+// 发给模型的历史消息条数上限，更早的消息只保留在记录中
+const MAX_HISTORY_MESSAGES: usize = 20;
+
+const CHAT_SYSTEM_PROMPT: &str = "你是 HTTP 调试助手，帮助用户理解下面这条抓包记录。回答要具体，引用相关的请求头、状态码或响应内容；信息不足以判断时直接说明还需要什么。凭据类的头已被替换为占位符，不要推测其原值。使用中文回答。";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatRole {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationMessage {
+    pub role: ChatRole,
+    pub content: String,
+    pub timestamp: DateTime<Utc>,
+    // 生成该回复的模型，仅助手消息有
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: String,
+    pub transaction_id: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub messages: Vec<ConversationMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatReply {
+    pub conversation_id: String,
+    pub reply: ConversationMessage,
+}
+
+impl Conversation {
+    pub fn new(transaction_id: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            transaction_id: transaction_id.to_string(),
+            created_at: now,
+            updated_at: now,
+            messages: Vec::new(),
+        }
+    }
+
+    // 系统提示带上事务内容，之后是最近的历史和本次提问
+    pub fn prompt(&self, transaction: &HttpTransaction, message: &str) -> (String, Vec<ChatMessage>) {
+        let system = format!("{}\n\n{}", CHAT_SYSTEM_PROMPT, transaction_context(transaction, DEFAULT_BODY_BUDGET));
+        let skip = self.messages.len().saturating_sub(MAX_HISTORY_MESSAGES);
+        let mut messages: Vec<ChatMessage> = self.messages[skip..]
+            .iter()
+            .map(|m| ChatMessage {
+                role: match m.role {
+                    ChatRole::User => "user",
+                    ChatRole::Assistant => "assistant",
+                }
+                .to_string(),
+                content: m.content.clone(),
+            })
+            .collect();
+        messages.push(ChatMessage::user(message));
+        (system, messages)
+    }
+
+    pub fn push(&mut self, role: ChatRole, content: String, model: Option<String>) -> ConversationMessage {
+        let message = ConversationMessage { role, content, timestamp: Utc::now(), model };
+        self.updated_at = message.timestamp;
+        self.messages.push(message.clone());
+        message
+    }
+}
+
+// 对话在内存中全量保存，启动时从数据目录加载
+#[derive(Debug, Default)]
+pub struct ConversationStore {
+    dir: Option<PathBuf>,
+    conversations: HashMap<String, Conversation>,
+}
+
+impl ConversationStore {
+    pub fn load(dir: PathBuf) -> Self {
+        let mut conversations = HashMap::new();
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for path in entries.flatten().map(|e| e.path()) {
+                if path.extension().is_none_or(|ext| ext != "json") {
+                    continue;
+                }
+                match std::fs::read(&path).map_err(anyhow::Error::from).and_then(|data| {
+                    serde_json::from_slice::<Conversation>(&data).map_err(anyhow::Error::from)
+                }) {
+                    Ok(conversation) => {
+                        conversations.insert(conversation.id.clone(), conversation);
+                    }
+                    Err(e) => warn!("Failed to load conversation {}: {}", path.display(), e),
+                }
+            }
+        }
+        Self { dir: Some(dir), conversations }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Conversation> {
+        self.conversations.get(id).cloned()
+    }
+
+    // 按最近更新排序
+    pub fn list(&self, transaction_id: Option<&str>) -> Vec<Conversation> {
+        let mut conversations: Vec<Conversation> = self
+            .conversations
+            .values()
+            .filter(|c| transaction_id.is_none_or(|id| c.transaction_id == id))
+            .cloned()
+            .collect();
+        conversations.sort_by_key(|c| std::cmp::Reverse(c.updated_at));
+        conversations
+    }
+
+    // 返回需要写入的文件
+    pub fn save(&mut self, conversation: Conversation) -> Option<(PathBuf, Vec<u8>)> {
+        let snapshot = self
+            .dir
+            .as_ref()
+            .and_then(|dir| Some((dir.join(format!("{}.json", conversation.id)), serde_json::to_vec(&conversation).ok()?)));
+        self.conversations.insert(conversation.id.clone(), conversation);
+        snapshot
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<bool> {
+        if self.conversations.remove(id).is_none() {
+            return Ok(false);
+        }
+        if let Some(dir) = &self.dir {
+            match std::fs::remove_file(dir.join(format!("{}.json", id))) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(anyhow!("Failed to delete conversation {}: {}", id, e)),
+            }
+        }
+        Ok(true)
+    }
+}
+
+// 请求与响应的文本描述；凭据由调用方事先按脱敏策略替换
+pub fn transaction_context(transaction: &HttpTransaction, body_budget: usize) -> String {
+    let request = &transaction.request;
+    let describe = |digest: &BodyDigest| match digest {
+        d if d.original_bytes == 0 => "(空)".to_string(),
+        d if d.summarized => format!("(原始 {} 字节，已摘要)\n{}", d.original_bytes, d.content),
+        d => d.content.clone(),
+    };
+    let mut context = format!(
+        "请求：{} {}\n请求头：\n{}\n请求体：{}\n",
+        request.method,
+        request.url,
+        header_lines(&request.headers),
+        describe(&digest_body(&request.body, transaction.request_kind(), body_budget)),
+    );
+    match &transaction.response {
+        Some(response) => context.push_str(&format!(
+            "响应状态：{}\n耗时：{}\n响应头：\n{}\n响应体：{}",
+            response.status,
+            transaction.duration.map(|d| format!("{}ms", d.as_millis())).unwrap_or_else(|| "未知".to_string()),
+            header_lines(&response.headers),
+            describe(&digest_body(&response.body, transaction.response_kind(), body_budget)),
+        )),
+        None => context.push_str("响应：未收到响应"),
+    }
+    context
+}
+
+fn header_lines(headers: &HashMap<String, String>) -> String {
+    let mut lines: Vec<String> = headers.iter().map(|(name, value)| format!("  {}: {}", name, value)).collect();
+    lines.sort();
+    lines.join("\n")
+}
@@ -0,0 +1,264 @@
+use crate::classify::BodyKind;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::OnceLock;
+
+// 单个请求/响应体在提示词中可占用的字符数
+pub const DEFAULT_BODY_BUDGET: usize = 8 * 1024;
+// map 阶段每块的大小
+const CHUNK_SIZE: usize = 64 * 1024;
+const ARRAY_CHUNK_ITEMS: usize = 500;
+const MAX_SHAPE_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DigestKind {
+    Raw,
+    Json,
+    Html,
+    Text,
+    Binary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyDigest {
+    pub kind: DigestKind,
+    pub original_bytes: usize,
+    pub chunks: usize,
+    // 为 false 时 content 即原始内容
+    pub summarized: bool,
+    pub content: String,
+}
+
+// 超出预算的请求体按入库时识别的类型分块摘要后再合并，保证结果不超过 budget 个字符
+pub fn digest_body(body: &[u8], body_kind: BodyKind, budget: usize) -> BodyDigest {
+    if body_kind.is_binary() {
+        return BodyDigest {
+            kind: DigestKind::Binary,
+            original_bytes: body.len(),
+            chunks: 0,
+            summarized: true,
+            content: format!("[binary body, {} bytes]", body.len()),
+        };
+    }
+    let text = match std::str::from_utf8(body) {
+        Ok(text) => text,
+        Err(e) => std::str::from_utf8(&body[..e.valid_up_to()]).unwrap_or_default(),
+    };
+
+    if text.chars().count() <= budget {
+        return BodyDigest {
+            kind: DigestKind::Raw,
+            original_bytes: body.len(),
+            chunks: 1,
+            summarized: false,
+            content: text.to_string(),
+        };
+    }
+
+    let json = match body_kind {
+        BodyKind::Json | BodyKind::GraphQl => serde_json::from_str::<Value>(text).ok(),
+        _ => None,
+    };
+    let (kind, chunks, content) = if let Some(value) = json {
+        let (chunks, content) = summarize_json(&value, budget);
+        (DigestKind::Json, chunks, content)
+    } else if matches!(body_kind, BodyKind::Html | BodyKind::Xml) {
+        let (chunks, content) = summarize_html(text, budget);
+        (DigestKind::Html, chunks, content)
+    } else {
+        let (chunks, content) = summarize_text(text, budget);
+        (DigestKind::Text, chunks, content)
+    };
+
+    BodyDigest {
+        kind,
+        original_bytes: body.len(),
+        chunks,
+        summarized: true,
+        content: truncate_chars(&content, budget),
+    }
+}
+
+// JSON：提取结构（字段、类型、数组长度），剩余预算放首个元素样例
+fn summarize_json(value: &Value, budget: usize) -> (usize, String) {
+    let (chunks, shape) = shape_of(value, 0);
+    let shape = serde_json::to_string(&shape).unwrap_or_default();
+
+    let sample = match value {
+        Value::Array(items) => items.first().cloned().unwrap_or(Value::Null),
+        other => other.clone(),
+    };
+    let sample = serde_json::to_string(&sample).unwrap_or_default();
+    let remaining = budget.saturating_sub(shape.chars().count() + 32);
+
+    let content = format!("structure: {}\nsample: {}", shape, truncate_chars(&sample, remaining));
+    (chunks, content)
+}
+
+// 返回 (分块数, 结构描述)；大数组先按块各自归纳，再合并各块的结构
+fn shape_of(value: &Value, depth: usize) -> (usize, Value) {
+    match value {
+        Value::Null => (1, Value::String("null".to_string())),
+        Value::Bool(_) => (1, Value::String("boolean".to_string())),
+        Value::Number(_) => (1, Value::String("number".to_string())),
+        Value::String(_) => (1, Value::String("string".to_string())),
+        _ if depth >= MAX_SHAPE_DEPTH => (1, Value::String("...".to_string())),
+        Value::Array(items) => {
+            let chunk_shapes: Vec<Value> = items
+                .chunks(ARRAY_CHUNK_ITEMS)
+                .map(|chunk| {
+                    chunk
+                        .iter()
+                        .map(|item| shape_of(item, depth + 1).1)
+                        .reduce(merge_shapes)
+                        .unwrap_or(Value::Null)
+                })
+                .collect();
+            let chunks = chunk_shapes.len().max(1);
+            let items_shape = chunk_shapes.into_iter().reduce(merge_shapes).unwrap_or(Value::Null);
+
+            let mut array = Map::new();
+            array.insert("$array".to_string(), Value::from(items.len()));
+            array.insert("$items".to_string(), items_shape);
+            (chunks, Value::Object(array))
+        }
+        Value::Object(map) => {
+            let mut chunks = 1;
+            let mut shape = Map::new();
+            for (key, value) in map {
+                let (child_chunks, child_shape) = shape_of(value, depth + 1);
+                chunks = chunks.max(child_chunks);
+                shape.insert(key.clone(), child_shape);
+            }
+            (chunks, Value::Object(shape))
+        }
+    }
+}
+
+fn merge_shapes(a: Value, b: Value) -> Value {
+    if a == b {
+        return a;
+    }
+    match (a, b) {
+        (Value::Object(mut a), Value::Object(b)) if a.contains_key("$array") == b.contains_key("$array") => {
+            for (key, b_value) in b {
+                let merged = match a.remove(&key) {
+                    Some(Value::Number(a_len)) if key == "$array" => {
+                        // 数组长度取各块中的最大值
+                        Value::from(a_len.as_u64().unwrap_or(0).max(b_value.as_u64().unwrap_or(0)))
+                    }
+                    Some(a_value) => merge_shapes(a_value, b_value),
+                    None => b_value,
+                };
+                a.insert(key, merged);
+            }
+            Value::Object(a)
+        }
+        (a, b) => {
+            // 类型不一致时记为联合类型，如 "null|string"
+            let mut types: Vec<String> = [a, b]
+                .iter()
+                .flat_map(|v| match v {
+                    Value::String(s) => s.split('|').map(str::to_string).collect(),
+                    Value::Object(o) if o.contains_key("$array") => vec!["array".to_string()],
+                    _ => vec!["object".to_string()],
+                })
+                .collect();
+            types.sort();
+            types.dedup();
+            Value::String(types.join("|"))
+        }
+    }
+}
+
+fn tag_regex() -> &'static Regex {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    TAG.get_or_init(|| Regex::new(r"(?is)<script.*?</script>|<style.*?</style>|<[^>]+>").unwrap())
+}
+
+// HTML：提取标题、标题层级、表单和正文摘录
+fn summarize_html(html: &str, budget: usize) -> (usize, String) {
+    static TITLE: OnceLock<Regex> = OnceLock::new();
+    static HEADING: OnceLock<Regex> = OnceLock::new();
+    static FORM: OnceLock<Regex> = OnceLock::new();
+    static ACTION: OnceLock<Regex> = OnceLock::new();
+    let title = TITLE.get_or_init(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+    let heading = HEADING.get_or_init(|| Regex::new(r"(?is)<h[1-3][^>]*>(.*?)</h[1-3]>").unwrap());
+    let form = FORM.get_or_init(|| Regex::new(r"(?is)<form\b[^>]*>").unwrap());
+    let action = ACTION.get_or_init(|| Regex::new(r#"(?i)action\s*=\s*["']([^"']*)["']"#).unwrap());
+
+    let mut lines = Vec::new();
+    if let Some(captures) = title.captures(html) {
+        lines.push(format!("title: {}", clean_text(&captures[1])));
+    }
+    let headings: Vec<String> = heading
+        .captures_iter(html)
+        .map(|c| clean_text(&c[1]))
+        .filter(|h| !h.is_empty())
+        .take(20)
+        .collect();
+    if !headings.is_empty() {
+        lines.push(format!("headings: {}", headings.join(" | ")));
+    }
+    let forms: Vec<String> = form
+        .find_iter(html)
+        .map(|tag| {
+            action
+                .captures(tag.as_str())
+                .map(|c| c[1].to_string())
+                .unwrap_or_default()
+        })
+        .collect();
+    if !forms.is_empty() {
+        lines.push(format!("forms: {} (actions: {})", forms.len(), forms.join(", ")));
+    }
+    lines.push(format!("scripts: {}", html.matches("<script").count()));
+
+    let header = lines.join("\n");
+    let text = clean_text(html);
+    let (chunks, excerpt) = summarize_text(&text, budget.saturating_sub(header.chars().count() + 16));
+    (chunks, format!("{}\ntext: {}", header, excerpt))
+}
+
+fn clean_text(html: &str) -> String {
+    let text = tag_regex().replace_all(html, " ");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// 纯文本：每块保留等长的开头片段，按顺序拼接
+fn summarize_text(text: &str, budget: usize) -> (usize, String) {
+    let chars: Vec<char> = text.chars().collect();
+    let chunks: Vec<&[char]> = chars.chunks(CHUNK_SIZE).collect();
+    if chunks.is_empty() {
+        return (0, String::new());
+    }
+
+    let marker_len = 24;
+    let per_chunk = (budget / chunks.len()).saturating_sub(marker_len);
+    if per_chunk == 0 {
+        // 块太多，只保留开头
+        return (chunks.len(), truncate_chars(text, budget));
+    }
+
+    let total = chunks.len();
+    let content = chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let excerpt: String = chunk.iter().take(per_chunk).collect();
+            format!("[chunk {}/{}] {}", i + 1, total, excerpt.trim())
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    (total, content)
+}
+
+// 超出时以省略号结尾，总长度不超过 max_chars
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    format!("{}…", kept)
+}
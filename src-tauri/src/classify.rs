@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// 嗅探时只检查开头部分，避免大响应体拖慢入库
+const SNIFF_BYTES: usize = 4096;
+const MAX_TOKENS: usize = 4096;
+
+// 请求体/响应体的实际内容类型，由内容嗅探得出，Content-Type 仅作参考；
+// 前端据此选择预览方式，搜索和分析器据此选择解析方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum BodyKind {
+    Empty,
+    Json,
+    Xml,
+    Html,
+    JavaScript,
+    GraphQl,
+    Protobuf,
+    FormUrlEncoded,
+    Multipart,
+    Text,
+    Binary,
+}
+
+impl BodyKind {
+    pub fn is_binary(&self) -> bool {
+        matches!(self, BodyKind::Protobuf | BodyKind::Binary)
+    }
+}
+
+pub fn classify(body: &[u8], content_type: Option<&str>) -> BodyKind {
+    if body.is_empty() {
+        return BodyKind::Empty;
+    }
+    let content_type = content_type.unwrap_or_default().to_lowercase();
+
+    let text = match std::str::from_utf8(body) {
+        Ok(text) => text,
+        // 截断在多字节字符中间时仍视为文本
+        Err(e) if e.error_len().is_none() && e.valid_up_to() > 0 => {
+            std::str::from_utf8(&body[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => {
+            return if content_type.contains("protobuf") || content_type.contains("grpc") || looks_protobuf(body) {
+                BodyKind::Protobuf
+            } else {
+                BodyKind::Binary
+            };
+        }
+    };
+    if text.bytes().take(SNIFF_BYTES).any(|b| b == 0) {
+        return BodyKind::Binary;
+    }
+
+    let trimmed = text.trim_start_matches('\u{feff}').trim();
+    if trimmed.is_empty() {
+        return BodyKind::Text;
+    }
+
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        if let Ok(value) = serde_json::from_str::<Value>(trimmed) {
+            return if is_graphql_payload(&value) { BodyKind::GraphQl } else { BodyKind::Json };
+        }
+    }
+    if content_type.contains("graphql") || looks_graphql(trimmed) {
+        return BodyKind::GraphQl;
+    }
+
+    if trimmed.starts_with('<') {
+        let head: String = trimmed.chars().take(512).collect::<String>().to_lowercase();
+        return if head.starts_with("<!doctype html") || head.contains("<html") {
+            BodyKind::Html
+        } else if head.starts_with("<?xml") || head.contains("xmlns") || content_type.contains("xml") {
+            BodyKind::Xml
+        } else if content_type.contains("html") || looks_html_fragment(&head) {
+            BodyKind::Html
+        } else {
+            BodyKind::Xml
+        };
+    }
+
+    if content_type.starts_with("multipart/") || looks_multipart(trimmed) {
+        return BodyKind::Multipart;
+    }
+    if looks_form(trimmed) {
+        return BodyKind::FormUrlEncoded;
+    }
+    if content_type.contains("javascript") || content_type.contains("ecmascript") || looks_javascript(trimmed) {
+        return BodyKind::JavaScript;
+    }
+    BodyKind::Text
+}
+
+// 按类型切分出用于搜索的词，统一转为小写；二进制内容不参与搜索
+pub fn search_tokens(body: &[u8], kind: BodyKind) -> Vec<String> {
+    let text = String::from_utf8_lossy(body);
+    let mut tokens = Vec::new();
+    match kind {
+        _ if kind.is_binary() => {}
+        BodyKind::Empty => {}
+        BodyKind::Json => match serde_json::from_str::<Value>(&text) {
+            Ok(value) => json_tokens(&value, &mut tokens),
+            Err(_) => split_words(&text, &mut tokens),
+        },
+        BodyKind::FormUrlEncoded => {
+            for (key, value) in url::form_urlencoded::parse(text.trim().as_bytes()) {
+                tokens.push(key.to_lowercase());
+                split_words(&value, &mut tokens);
+            }
+        }
+        BodyKind::Html | BodyKind::Xml => split_words(&strip_tags(&text), &mut tokens),
+        _ => split_words(&text, &mut tokens),
+    }
+    tokens.truncate(MAX_TOKENS);
+    tokens
+}
+
+// 供规则型检测器扫描的文本：表单先解码，二进制内容不扫描
+pub fn analyzable_text(body: &[u8], kind: BodyKind) -> String {
+    match kind {
+        _ if kind.is_binary() => String::new(),
+        BodyKind::FormUrlEncoded => url::form_urlencoded::parse(body)
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&"),
+        _ => String::from_utf8_lossy(body).to_string(),
+    }
+}
+
+fn json_tokens(value: &Value, tokens: &mut Vec<String>) {
+    if tokens.len() >= MAX_TOKENS {
+        return;
+    }
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                tokens.push(key.to_lowercase());
+                json_tokens(value, tokens);
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| json_tokens(item, tokens)),
+        Value::String(s) => {
+            tokens.push(s.to_lowercase());
+            split_words(s, tokens);
+        }
+        Value::Number(n) => tokens.push(n.to_string()),
+        Value::Bool(b) => tokens.push(b.to_string()),
+        Value::Null => {}
+    }
+}
+
+fn split_words(text: &str, tokens: &mut Vec<String>) {
+    tokens.extend(
+        text.split(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '@')))
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase),
+    );
+}
+
+fn strip_tags(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                stripped.push(' ');
+            }
+            _ if !in_tag => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+}
+
+// {"query": "...", "variables": ...} 或批量请求数组
+fn is_graphql_payload(value: &Value) -> bool {
+    let is_operation = |v: &Value| {
+        v.get("query")
+            .and_then(Value::as_str)
+            .map(looks_graphql)
+            .unwrap_or(false)
+    };
+    match value {
+        Value::Array(items) => !items.is_empty() && items.iter().all(is_operation),
+        other => is_operation(other),
+    }
+}
+
+fn looks_graphql(text: &str) -> bool {
+    let text = text.trim_start();
+    ["query", "mutation", "subscription", "fragment"]
+        .iter()
+        .any(|keyword| {
+            text.strip_prefix(keyword)
+                .map(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '{' || c == '('))
+                .unwrap_or(false)
+        })
+        || (text.starts_with('{') && text.contains('}') && !text.contains(':') && !text.contains('"'))
+}
+
+fn looks_html_fragment(head: &str) -> bool {
+    const TAGS: [&str; 12] = [
+        "<head", "<body", "<div", "<span", "<p>", "<a ", "<script", "<style", "<table", "<ul", "<form", "<img",
+    ];
+    TAGS.iter().any(|tag| head.contains(tag))
+}
+
+fn looks_multipart(text: &str) -> bool {
+    text.starts_with("--")
+        && text
+            .lines()
+            .nth(1)
+            .map(|line| line.to_lowercase().starts_with("content-disposition"))
+            .unwrap_or(false)
+}
+
+fn looks_form(text: &str) -> bool {
+    !text.contains(char::is_whitespace)
+        && text.contains('=')
+        && text.split('&').all(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+            !key.is_empty()
+                && key.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '[' | ']' | '%' | '+'))
+        })
+}
+
+fn looks_javascript(text: &str) -> bool {
+    const PREFIXES: [&str; 10] = [
+        "function", "var ", "let ", "const ", "(function", "!function", "import ", "export ", "\"use strict\"", "'use strict'",
+    ];
+    PREFIXES.iter().any(|prefix| text.starts_with(prefix))
+        || (text.starts_with("/*") && (text.contains("function") || text.contains("=>")))
+}
+
+// 按 protobuf 线格式完整解析一遍：字段号合法、wire type 合法且恰好消费全部字节
+fn looks_protobuf(body: &[u8]) -> bool {
+    // gRPC 帧：1 字节压缩标记 + 4 字节大端长度
+    let body = match body {
+        [flag @ (0 | 1), a, b, c, d, rest @ ..]
+            if u32::from_be_bytes([*a, *b, *c, *d]) as usize == rest.len() =>
+        {
+            if *flag == 1 {
+                return true;
+            }
+            rest
+        }
+        _ => body,
+    };
+
+    let mut pos = 0;
+    let mut fields = 0;
+    while pos < body.len() {
+        let Some(key) = read_varint(body, &mut pos) else {
+            return false;
+        };
+        if key >> 3 == 0 {
+            return false;
+        }
+        let consumed = match key & 7 {
+            0 => read_varint(body, &mut pos).map(|_| 0),
+            1 => Some(8),
+            2 => read_varint(body, &mut pos),
+            5 => Some(4),
+            _ => None,
+        };
+        match consumed {
+            Some(len) if (len as usize) <= body.len() - pos => pos += len as usize,
+            _ => return false,
+        }
+        fields += 1;
+    }
+    fields > 0
+}
+
+fn read_varint(body: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *body.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
@@ -0,0 +1,277 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, TimeZone, Utc};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use thiserror::Error;
+
+// 编解码工具箱的统一错误类型，命令层转为字符串返回给前端
+#[derive(Debug, Error)]
+pub enum CodecError {
+    #[error("Invalid hex input: {0}")]
+    InvalidHex(String),
+    #[error("Invalid base64 input: {0}")]
+    InvalidBase64(String),
+    #[error("Decoded bytes are not valid UTF-8")]
+    InvalidUtf8,
+    #[error("Compression failed: {0}")]
+    Compression(String),
+    #[error("Invalid escape sequence: {0}")]
+    InvalidEscape(String),
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+}
+
+pub type CodecResult<T> = std::result::Result<T, CodecError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionFormat {
+    Gzip,
+    Deflate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimestampConversion {
+    pub seconds: i64,
+    pub millis: i64,
+    pub rfc3339: String,
+}
+
+fn utf8(bytes: Vec<u8>) -> CodecResult<String> {
+    String::from_utf8(bytes).map_err(|_| CodecError::InvalidUtf8)
+}
+
+pub fn hex_encode(input: &str) -> String {
+    input.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 允许 0x 前缀以及空格、冒号、短横线分隔（如抓包工具复制出的 "de:ad:be:ef"）
+pub fn hex_decode(input: &str) -> CodecResult<String> {
+    let input = input.trim();
+    let input = input.strip_prefix("0x").or_else(|| input.strip_prefix("0X")).unwrap_or(input);
+    let digits: Vec<u8> = input
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace() && *b != b':' && *b != b'-')
+        .collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(CodecError::InvalidHex("odd number of digits".to_string()));
+    }
+    let bytes = digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).map_err(|_| CodecError::InvalidHex("non-ASCII input".to_string()))?;
+            u8::from_str_radix(pair, 16).map_err(|_| CodecError::InvalidHex(format!("'{}' is not a hex byte", pair)))
+        })
+        .collect::<CodecResult<Vec<u8>>>()?;
+    utf8(bytes)
+}
+
+// 压缩结果是二进制，以 base64 返回；解压的输入同样是 base64
+pub fn compress(input: &str, format: CompressionFormat) -> CodecResult<String> {
+    let compressed = match format {
+        CompressionFormat::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(input.as_bytes()).and_then(|_| encoder.finish())
+        }
+        CompressionFormat::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(input.as_bytes()).and_then(|_| encoder.finish())
+        }
+    }
+    .map_err(|e| CodecError::Compression(e.to_string()))?;
+    Ok(general_purpose::STANDARD.encode(compressed))
+}
+
+pub fn decompress(input: &str, format: CompressionFormat) -> CodecResult<String> {
+    let compressed: String = input.split_whitespace().collect();
+    let compressed = general_purpose::STANDARD
+        .decode(compressed)
+        .map_err(|e| CodecError::InvalidBase64(e.to_string()))?;
+    let encoding = match format {
+        CompressionFormat::Gzip => "gzip",
+        CompressionFormat::Deflate => "deflate",
+    };
+    let decompressed =
+        crate::decoding::decode(&compressed, Some(encoding)).map_err(|e| CodecError::Compression(e.to_string()))?;
+    utf8(decompressed)
+}
+
+pub fn html_escape(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// 支持数字实体和常见的命名实体；无法识别的实体原样保留
+pub fn html_unescape(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest
+            .find(';')
+            .filter(|end| *end <= 32)
+            .and_then(|end| html_entity(&rest[1..end]).map(|c| (c, end)));
+        match decoded {
+            Some((c, end)) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn html_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix('x').or_else(|| number.strip_prefix('X')) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "middot" => '·',
+        "times" => '×',
+        "divide" => '÷',
+        "euro" => '€',
+        "yen" => '¥',
+        "pound" => '£',
+        "cent" => '¢',
+        "sect" => '§',
+        "deg" => '°',
+        _ => return None,
+    })
+}
+
+// 非 ASCII 和控制字符转为 \uXXXX，BMP 之外的字符按 JSON 的代理对形式输出
+pub fn unicode_escape(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if c.is_ascii() && !c.is_ascii_control() {
+            escaped.push(c);
+            continue;
+        }
+        let mut units = [0u16; 2];
+        for unit in c.encode_utf16(&mut units) {
+            escaped.push_str(&format!("\\u{:04x}", unit));
+        }
+    }
+    escaped
+}
+
+// 识别 \uXXXX（含代理对）、\u{X...} 和 \xHH，其他反斜杠原样保留
+pub fn unicode_unescape(input: &str) -> CodecResult<String> {
+    let mut result = String::with_capacity(input.len());
+    let mut pending_high: Option<u16> = None;
+    let mut rest = input;
+    while let Some(start) = rest.find('\\') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let (code, len) = match rest.as_bytes().get(1) {
+            Some(b'u') if rest[2..].starts_with('{') => {
+                let end = rest.find('}').ok_or_else(|| CodecError::InvalidEscape(truncate(rest)))?;
+                let code = u32::from_str_radix(&rest[3..end], 16).map_err(|_| CodecError::InvalidEscape(truncate(rest)))?;
+                (code, end + 1)
+            }
+            Some(b'u') => (hex_digits(rest, 4)?, 6),
+            Some(b'x') => (hex_digits(rest, 2)?, 4),
+            _ => {
+                result.push('\\');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        rest = &rest[len..];
+        match (pending_high.take(), code) {
+            (None, 0xD800..=0xDBFF) => pending_high = Some(code as u16),
+            (Some(high), 0xDC00..=0xDFFF) => {
+                let c = char::decode_utf16([high, code as u16])
+                    .next()
+                    .and_then(|c| c.ok())
+                    .ok_or_else(|| CodecError::InvalidEscape(format!("\\u{:04x}\\u{:04x}", high, code)))?;
+                result.push(c);
+            }
+            (Some(high), _) => return Err(CodecError::InvalidEscape(format!("unpaired surrogate \\u{:04x}", high))),
+            (None, code) => {
+                result.push(char::from_u32(code).ok_or_else(|| CodecError::InvalidEscape(format!("U+{:X}", code)))?);
+            }
+        }
+    }
+    if let Some(high) = pending_high {
+        return Err(CodecError::InvalidEscape(format!("unpaired surrogate \\u{:04x}", high)));
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn hex_digits(escape: &str, count: usize) -> CodecResult<u32> {
+    escape
+        .get(2..2 + count)
+        .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+        .ok_or_else(|| CodecError::InvalidEscape(truncate(escape)))
+}
+
+fn truncate(text: &str) -> String {
+    text.chars().take(12).collect()
+}
+
+// 数字按量级判断单位（秒、毫秒、微秒、纳秒），其余按 RFC 3339 解析
+pub fn convert_timestamp(input: &str) -> CodecResult<TimestampConversion> {
+    let input = input.trim();
+    let time: DateTime<Utc> = if let Ok(number) = input.parse::<f64>() {
+        let millis = match number.abs() {
+            n if n >= 1e17 => number / 1e6,
+            n if n >= 1e14 => number / 1e3,
+            n if n >= 1e11 => number,
+            _ => number * 1e3,
+        };
+        Utc.timestamp_millis_opt(millis.round() as i64)
+            .single()
+            .ok_or_else(|| CodecError::InvalidTimestamp(input.to_string()))?
+    } else {
+        DateTime::parse_from_rfc3339(input)
+            .map_err(|e| CodecError::InvalidTimestamp(format!("{}: {}", input, e)))?
+            .with_timezone(&Utc)
+    };
+    Ok(TimestampConversion {
+        seconds: time.timestamp(),
+        millis: time.timestamp_millis(),
+        rfc3339: time.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+    })
+}
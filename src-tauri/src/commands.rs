@@ -1,6 +1,63 @@
-use crate::proxy::{ProxyServer, RequestRule, SearchFilter};
-use crate::ai_analyzer::{AIAnalyzer, AIAnalysisResult, SecurityAnalyzer, AIModel};
-use crate::ai_response::{AIResponseGenerator, AIResponseConfig, ResponseType};
+use crate::proxy::{HttpRequest, HttpTransaction, ProxyServer, RequestOverrides, RequestRule, SearchFilter};
+use crate::ai_analyzer::{AIAnalysisResult, SecurityRisk};
+use crate::scanner::Finding;
+use crate::active::{ActiveScanReport, ActiveScanSettings};
+use crate::secreport::FindingsFormat;
+use crate::geoip::GeoIpDatabaseInfo;
+use crate::dns::{DnsCacheEntry, DnsSettings};
+use crate::otel::{OtelSettings, OtelStatus};
+use crate::ai_response::GeneratedMock;
+use crate::mockdata::MockOptions;
+use crate::schema::InferredSchema;
+use crate::baseline::{Anomaly, AnomalySeverity};
+use crate::redaction::{MaskingSettings, RedactionPolicy};
+use crate::history::EndpointHistory;
+use crate::settings::CaptureSettings;
+use crate::buffer::CaptureStats;
+use crate::detail::{BodyPart, TransactionDetail};
+use crate::hexdump::HexDumpPage;
+use crate::jsonbody::JsonBody;
+use crate::formdata::ParsedBody;
+use crate::grpc::GrpcBody;
+use crate::protobuf::ProtoRegistrySummary;
+use crate::jwt::{self, FoundJwt, JwtInfo};
+use crate::cookies::Cookie;
+use crate::secheaders::SecurityHeaderReport;
+use crate::tlsinfo::TlsInfo;
+use crate::pinning::PinningSuspect;
+use crate::codecs::{self, CompressionFormat, TimestampConversion};
+use crate::hashing::{self, HashAlgorithm, HashOutput};
+use crate::openapi::{ConformanceSummary, SpecViolation};
+use crate::hooks::{ExportHook, HookInfo};
+use crate::selftest::SelfTestReport;
+use crate::stats::{ActivityHeatmap, HeatmapGroupBy, TrafficStats};
+use crate::emulation::{DeviceProfile, EmulationSettings};
+use crate::auth::ProxyCredentials;
+use crate::throttle::{ThrottleProfile, ThrottleSettings};
+use crate::mitm::CaCertInfo;
+use crate::triage::{TriageItem, TriageState, TriageSummary, TriageUpdate};
+use crate::waterfall::{LatencyBudget, PageLoadGroup};
+use crate::websocket::WsMessage;
+use crate::palette::{QuickFindResult, SavedSearch, DEFAULT_QUICK_FIND_LIMIT};
+use crate::shadow::{ShadowConfig, ShadowReport};
+use crate::modifications::ModificationStage;
+use crate::quotas::{HostQuota, QuotaAlert, QuotaUsage};
+use crate::breakpoints::{Breakpoint, InterceptModification, InterceptedItem};
+use crate::export::ExportOptions;
+use crate::classify::BodyKind;
+use crate::recovery::RecoveryInfo;
+use crate::session::SessionSummary;
+use crate::ruleset::{ImportMode, RuleImportSummary};
+use crate::config::{AiConfigView, AiProvider, AiProviderConfig, ConfigStatus};
+use crate::llm::{self, EndpointStatus};
+use crate::loadtest::LoadTestFormat;
+use crate::scoring::DEFAULT_MODEL_SCORING_LIMIT;
+use crate::curl::CurlOptions;
+use crate::nlsearch::InterpretedQuery;
+use crate::chat::{ChatReply, Conversation};
+use crate::summary::{CaptureRange, CaptureSummary};
+use crate::rulegen::RuleProposal;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tauri::State;
 use serde::{Deserialize, Serialize};
@@ -13,6 +70,42 @@ pub struct TransactionData {
     pub status: Option<u16>,
     pub duration: Option<u64>,
     pub timestamp: String,
+    pub preflight_id: Option<String>,
+    pub preflight_for: Option<String>,
+    pub replay_of: Option<String>,
+    pub request_body_kind: Option<BodyKind>,
+    pub response_body_kind: Option<BodyKind>,
+    pub risk_score: Option<u8>,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+}
+
+// 自然语言搜索的结果，附带查询被解析成的过滤条件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SemanticSearchResult {
+    pub interpretation: InterpretedQuery,
+    pub transactions: Vec<TransactionData>,
+}
+
+impl From<HttpTransaction> for TransactionData {
+    fn from(t: HttpTransaction) -> Self {
+        Self {
+            id: t.id,
+            method: t.request.method,
+            url: t.request.url,
+            status: t.response.as_ref().map(|r| r.status),
+            duration: t.duration.map(|d| d.as_millis() as u64),
+            timestamp: t.request.timestamp.to_rfc3339(),
+            preflight_id: t.preflight_id,
+            preflight_for: t.preflight_for,
+            replay_of: t.replay_of,
+            request_body_kind: t.request_body_kind,
+            response_body_kind: t.response_body_kind,
+            risk_score: t.risk.map(|r| r.score),
+            tags: t.tags,
+            notes: t.notes,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,19 +135,17 @@ pub async fn stop_proxy(proxy: State<'_, ProxyState>) -> Result<String, String>
 }
 
 #[tauri::command]
-pub async fn get_transactions(proxy: State<'_, ProxyState>) -> Result<Vec<TransactionData>, String> {
+pub async fn get_transactions(
+    proxy: State<'_, ProxyState>,
+    collapse_preflight: Option<bool>,
+) -> Result<Vec<TransactionData>, String> {
     let transactions = proxy.get_transactions().await;
+    let collapse_preflight = collapse_preflight.unwrap_or(false);
     
     let transaction_data: Vec<TransactionData> = transactions
         .into_iter()
-        .map(|t| TransactionData {
-            id: t.id,
-            method: t.request.method,
-            url: t.request.url,
-            status: t.response.as_ref().map(|r| r.status),
-            duration: t.duration.map(|d| d.as_millis() as u64),
-            timestamp: t.request.timestamp.to_rfc3339(),
-        })
+        .filter(|t| !(collapse_preflight && t.preflight_for.is_some()))
+        .map(TransactionData::from)
         .collect();
     
     Ok(transaction_data)
@@ -89,195 +180,1433 @@ pub async fn is_proxy_running(proxy: State<'_, ProxyState>) -> Result<bool, Stri
     Ok(proxy.is_running().await)
 }
 
+#[tauri::command]
+pub async fn run_self_test(proxy: State<'_, ProxyState>) -> Result<SelfTestReport, String> {
+    Ok(proxy.run_self_test().await)
+}
+
 // 搜索功能
 #[tauri::command]
 pub async fn search_transactions(
     proxy: State<'_, ProxyState>,
     filter: SearchFilter,
 ) -> Result<Vec<TransactionData>, String> {
-    let transactions = proxy.search_transactions(filter).await;
+    let transactions = proxy.search_transactions(filter).await
+        .map_err(|e| e.to_string())?;
     
     let transaction_data: Vec<TransactionData> = transactions
         .into_iter()
-        .map(|t| TransactionData {
-            id: t.id,
-            method: t.request.method,
-            url: t.request.url,
-            status: t.response.as_ref().map(|r| r.status),
-            duration: t.duration.map(|d| d.as_millis() as u64),
-            timestamp: t.request.timestamp.to_rfc3339(),
-        })
+        .map(TransactionData::from)
         .collect();
     
     Ok(transaction_data)
 }
 
-// 收藏功能
 #[tauri::command]
-pub async fn toggle_favorite(
+pub async fn semantic_search(
     proxy: State<'_, ProxyState>,
-    transaction_id: String,
-) -> Result<bool, String> {
-    Ok(proxy.toggle_favorite(&transaction_id).await)
+    query: String,
+) -> Result<SemanticSearchResult, String> {
+    let (interpretation, transactions) = proxy.semantic_search(&query).await
+        .map_err(|e| e.to_string())?;
+    Ok(SemanticSearchResult {
+        interpretation,
+        transactions: transactions.into_iter().map(TransactionData::from).collect(),
+    })
 }
 
+// 保存的搜索
 #[tauri::command]
-pub async fn get_favorites(proxy: State<'_, ProxyState>) -> Result<Vec<TransactionData>, String> {
-    let transactions = proxy.get_favorites().await;
-    
-    let transaction_data: Vec<TransactionData> = transactions
-        .into_iter()
-        .map(|t| TransactionData {
-            id: t.id,
-            method: t.request.method,
-            url: t.request.url,
-            status: t.response.as_ref().map(|r| r.status),
-            duration: t.duration.map(|d| d.as_millis() as u64),
-            timestamp: t.request.timestamp.to_rfc3339(),
-        })
-        .collect();
-    
-    Ok(transaction_data)
+pub async fn save_search(
+    proxy: State<'_, ProxyState>,
+    search: SavedSearch,
+) -> Result<String, String> {
+    proxy.save_search(search).await;
+    Ok("Search saved".to_string())
 }
 
-// 规则管理
 #[tauri::command]
-pub async fn add_rule(
+pub async fn remove_saved_search(
     proxy: State<'_, ProxyState>,
-    rule: RequestRule,
+    search_id: String,
 ) -> Result<String, String> {
-    proxy.add_rule(rule).await;
-    Ok("Rule added".to_string())
+    proxy.remove_saved_search(&search_id).await;
+    Ok("Saved search removed".to_string())
 }
 
 #[tauri::command]
-pub async fn remove_rule(
+pub async fn get_saved_searches(proxy: State<'_, ProxyState>) -> Result<Vec<SavedSearch>, String> {
+    Ok(proxy.get_saved_searches().await)
+}
+
+// 命令面板
+#[tauri::command]
+pub async fn quick_find(
     proxy: State<'_, ProxyState>,
-    rule_id: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<QuickFindResult>, String> {
+    Ok(proxy.quick_find(&query, limit.unwrap_or(DEFAULT_QUICK_FIND_LIMIT)).await)
+}
+
+// 仪表盘实时事件流
+#[tauri::command]
+pub async fn start_dashboard_stream(
+    app: tauri::AppHandle,
+    proxy: State<'_, ProxyState>,
+    interval_ms: Option<u64>,
 ) -> Result<String, String> {
-    proxy.remove_rule(&rule_id).await;
-    Ok("Rule removed".to_string())
+    let interval_ms = interval_ms.unwrap_or(1000).max(100);
+    proxy.start_dashboard_stream(app, std::time::Duration::from_millis(interval_ms)).await;
+    Ok("Dashboard stream started".to_string())
 }
 
 #[tauri::command]
-pub async fn get_rules(proxy: State<'_, ProxyState>) -> Result<Vec<RequestRule>, String> {
-    Ok(proxy.get_rules().await)
+pub async fn stop_dashboard_stream(proxy: State<'_, ProxyState>) -> Result<String, String> {
+    proxy.stop_dashboard_stream().await;
+    Ok("Dashboard stream stopped".to_string())
 }
 
-// HAR 导出
+// 长期统计
 #[tauri::command]
-pub async fn export_har(proxy: State<'_, ProxyState>) -> Result<String, String> {
-    Ok(proxy.export_har().await)
+pub async fn get_historical_stats(
+    proxy: State<'_, ProxyState>,
+    host: Option<String>,
+    days: Option<u32>,
+) -> Result<Vec<EndpointHistory>, String> {
+    Ok(proxy.get_historical_stats(host.as_deref(), days).await)
 }
 
-// 编码工具
+// 系统代理绕过列表
 #[tauri::command]
-pub fn encode_base64(input: String) -> Result<String, String> {
-    Ok(ProxyServer::encode_base64(&input))
+pub async fn set_proxy_bypass(
+    proxy: State<'_, ProxyState>,
+    entries: Vec<String>,
+) -> Result<Vec<String>, String> {
+    proxy.set_proxy_bypass(entries).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn decode_base64(input: String) -> Result<String, String> {
-    ProxyServer::decode_base64(&input)
+pub async fn get_proxy_bypass(proxy: State<'_, ProxyState>) -> Result<Vec<String>, String> {
+    Ok(proxy.get_proxy_bypass().await)
 }
 
+// 代理认证，用户名为空时关闭
 #[tauri::command]
-pub fn encode_url(input: String) -> Result<String, String> {
-    Ok(ProxyServer::encode_url(&input))
+pub async fn set_proxy_credentials(
+    proxy: State<'_, ProxyState>,
+    username: Option<String>,
+    password: Option<String>,
+) -> Result<(), String> {
+    let credentials = match username.filter(|u| !u.is_empty()) {
+        Some(username) => Some(
+            ProxyCredentials::new(username, password.unwrap_or_default()).map_err(|e| e.to_string())?,
+        ),
+        None => None,
+    };
+    proxy.set_proxy_credentials(credentials).await.map_err(|e| e.to_string())
 }
 
+// 允许局域网设备连接，须先设置代理认证；代理重启后生效
 #[tauri::command]
-pub fn decode_url(input: String) -> Result<String, String> {
-    Ok(ProxyServer::decode_url(&input))
+pub async fn set_allow_lan(proxy: State<'_, ProxyState>, enabled: bool) -> Result<(), String> {
+    proxy.set_allow_lan(enabled).await.map_err(|e| e.to_string())
 }
 
-// AI 分析命令
 #[tauri::command]
-pub async fn analyze_transaction(
+pub async fn get_allow_lan(proxy: State<'_, ProxyState>) -> Result<bool, String> {
+    Ok(proxy.get_allow_lan().await)
+}
+
+// 配置文件
+#[tauri::command]
+pub async fn reload_config(proxy: State<'_, ProxyState>) -> Result<ConfigStatus, String> {
+    proxy.reload_config().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_config_status(proxy: State<'_, ProxyState>) -> Result<Option<ConfigStatus>, String> {
+    Ok(proxy.get_config_status().await)
+}
+
+#[tauri::command]
+pub async fn get_ai_provider_config(proxy: State<'_, ProxyState>) -> Result<Option<AiProviderConfig>, String> {
+    Ok(proxy.get_ai_provider_config().await)
+}
+
+// AI 服务商设置，密钥保存在系统钥匙串中
+#[tauri::command]
+pub async fn set_ai_config(
+    proxy: State<'_, ProxyState>,
+    provider: AiProvider,
+    model: String,
+    api_key: Option<String>,
+    base_url: Option<String>,
+) -> Result<AiConfigView, String> {
+    proxy.set_ai_config(provider, model, api_key, base_url).await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_ai_config(proxy: State<'_, ProxyState>) -> Result<Option<AiConfigView>, String> {
+    Ok(proxy.get_ai_config().await)
+}
+
+// 抓包设置
+#[tauri::command]
+pub async fn get_capture_settings(proxy: State<'_, ProxyState>) -> Result<CaptureSettings, String> {
+    Ok(proxy.get_settings().await)
+}
+
+#[tauri::command]
+pub async fn set_capture_settings(
+    proxy: State<'_, ProxyState>,
+    settings: CaptureSettings,
+) -> Result<String, String> {
+    proxy.set_settings(settings).await
+        .map_err(|e| e.to_string())?;
+    Ok("Capture settings updated".to_string())
+}
+
+#[tauri::command]
+pub async fn get_capture_stats(proxy: State<'_, ProxyState>) -> Result<CaptureStats, String> {
+    Ok(proxy.get_capture_stats().await)
+}
+
+// 设备模拟
+#[tauri::command]
+pub async fn get_device_profiles(proxy: State<'_, ProxyState>) -> Result<Vec<DeviceProfile>, String> {
+    Ok(proxy.get_device_profiles().await)
+}
+
+#[tauri::command]
+pub async fn get_emulation_settings(proxy: State<'_, ProxyState>) -> Result<EmulationSettings, String> {
+    Ok(proxy.get_emulation_settings().await)
+}
+
+// host 为空时设置全局配置，profile_id 为空时取消模拟
+#[tauri::command]
+pub async fn set_device_profile(
+    proxy: State<'_, ProxyState>,
+    host: Option<String>,
+    profile_id: Option<String>,
+) -> Result<String, String> {
+    proxy.set_device_profile(host.as_deref(), profile_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok("Device profile updated".to_string())
+}
+
+#[tauri::command]
+pub async fn save_device_profile(
+    proxy: State<'_, ProxyState>,
+    profile: DeviceProfile,
+) -> Result<String, String> {
+    proxy.save_device_profile(profile).await;
+    Ok("Device profile saved".to_string())
+}
+
+#[tauri::command]
+pub async fn remove_device_profile(
+    proxy: State<'_, ProxyState>,
+    profile_id: String,
+) -> Result<String, String> {
+    proxy.remove_device_profile(&profile_id).await;
+    Ok("Device profile removed".to_string())
+}
+
+// 网络节流
+#[tauri::command]
+pub async fn get_throttle_settings(proxy: State<'_, ProxyState>) -> Result<ThrottleSettings, String> {
+    Ok(proxy.get_throttle_settings().await)
+}
+
+// host 为空时设置全局配置，profile 为空时取消节流
+#[tauri::command]
+pub async fn set_throttle_profile(
+    proxy: State<'_, ProxyState>,
+    host: Option<String>,
+    profile: Option<ThrottleProfile>,
+) -> Result<String, String> {
+    if let Some(ThrottleProfile::Custom { download_kbps: 0, upload_kbps: 0, latency_ms: 0 }) = profile {
+        return Err("Custom throttle profile must limit bandwidth or add latency".to_string());
+    }
+    proxy.set_throttle_profile(host.as_deref(), profile).await;
+    Ok("Throttle profile updated".to_string())
+}
+
+// 失败请求排查
+#[tauri::command]
+pub async fn get_triage_queue(
+    proxy: State<'_, ProxyState>,
+    state: Option<TriageState>,
+) -> Result<Vec<TriageItem>, String> {
+    Ok(proxy.get_triage_queue(state).await)
+}
+
+#[tauri::command]
+pub async fn get_triage_summary(proxy: State<'_, ProxyState>) -> Result<TriageSummary, String> {
+    Ok(proxy.get_triage_summary().await)
+}
+
+#[tauri::command]
+pub async fn update_triage_item(
     proxy: State<'_, ProxyState>,
     transaction_id: String,
-) -> Result<AIAnalysisResult, String> {
-    let transactions = proxy.get_transactions().await;
-    let transaction = transactions
-        .iter()
-        .find(|t| t.id == transaction_id)
-        .ok_or("Transaction not found")?;
-    
-    let ai_analyzer = AIAnalyzer::new(
-        None,
-        AIModel::OpenAI { model: "gpt-3.5-turbo".to_string() }
-    );
-    
-    ai_analyzer.analyze_transaction(transaction).await
+    update: TriageUpdate,
+) -> Result<TriageItem, String> {
+    proxy.update_triage_item(&transaction_id, update).await
         .map_err(|e| e.to_string())
 }
 
+// 对失败请求生成 AI 解释并关联到排查条目
 #[tauri::command]
-pub async fn detect_vulnerabilities(
+pub async fn explain_triage_item(
     proxy: State<'_, ProxyState>,
     transaction_id: String,
-) -> Result<Vec<String>, String> {
+) -> Result<TriageItem, String> {
     let transactions = proxy.get_transactions().await;
     let transaction = transactions
         .iter()
         .find(|t| t.id == transaction_id)
         .ok_or("Transaction not found")?;
     
-    let ai_analyzer = AIAnalyzer::new(
-        None,
-        AIModel::OpenAI { model: "gpt-3.5-turbo".to_string() }
-    );
-    let security_analyzer = SecurityAnalyzer::new(ai_analyzer);
+    let analysis = proxy.analyze_transaction(transaction).await
+        .map_err(|e| e.to_string())?;
     
-    security_analyzer.detect_vulnerabilities(transaction).await
+    proxy.attach_triage_analysis(&transaction_id, analysis).await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_ai_insights(
+pub async fn clear_resolved_triage(proxy: State<'_, ProxyState>) -> Result<String, String> {
+    proxy.clear_resolved_triage().await;
+    Ok("Resolved triage items cleared".to_string())
+}
+
+// 根证书管理
+#[tauri::command]
+pub async fn generate_ca_cert(proxy: State<'_, ProxyState>) -> Result<CaCertInfo, String> {
+    proxy.generate_ca_cert().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_ca_cert_info(proxy: State<'_, ProxyState>) -> Result<CaCertInfo, String> {
+    proxy.get_ca_cert_info().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_ca_cert(
     proxy: State<'_, ProxyState>,
-) -> Result<Vec<String>, String> {
-    let transactions = proxy.get_transactions().await;
-    
-    let ai_analyzer = AIAnalyzer::new(
-        None,
-        AIModel::OpenAI { model: "gpt-3.5-turbo".to_string() }
-    );
-    
-    let mut insights = Vec::new();
-    
-    // 获取异常检测
-    let anomalies = ai_analyzer.detect_anomalies(&transactions).await
+    path: String,
+) -> Result<String, String> {
+    proxy.export_ca_cert(&path).await.map_err(|e| e.to_string())?;
+    Ok(format!("CA certificate exported to {}", path))
+}
+
+#[tauri::command]
+pub async fn install_ca_cert(proxy: State<'_, ProxyState>) -> Result<String, String> {
+    proxy.install_ca_cert().await.map_err(|e| e.to_string())
+}
+
+// OpenAPI 规范校验
+#[tauri::command]
+pub async fn import_openapi_spec(
+    proxy: State<'_, ProxyState>,
+    path: String,
+) -> Result<String, String> {
+    let content = tokio::fs::read_to_string(&path).await
         .map_err(|e| e.to_string())?;
-    insights.extend(anomalies);
-    
-    // 获取优化建议
-    let optimizations = ai_analyzer.suggest_optimizations(&transactions).await
+    let (title, operations) = proxy.import_openapi_spec(&content).await
         .map_err(|e| e.to_string())?;
-    insights.extend(optimizations);
-    
-    Ok(insights)
+    Ok(format!("Imported '{}' with {} operations", title, operations))
 }
 
-// AI 响应生成命令
 #[tauri::command]
-pub async fn generate_ai_response(
-    _request_data: serde_json::Value,
+pub async fn clear_openapi_spec(proxy: State<'_, ProxyState>) -> Result<String, String> {
+    proxy.clear_openapi_spec().await;
+    Ok("OpenAPI spec cleared".to_string())
+}
+
+#[tauri::command]
+pub async fn get_spec_violations(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+) -> Result<Vec<SpecViolation>, String> {
+    proxy.get_spec_violations(&transaction_id).await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_spec_conformance(proxy: State<'_, ProxyState>) -> Result<ConformanceSummary, String> {
+    proxy.get_spec_conformance().await
+        .map_err(|e| e.to_string())
+}
+
+// 导出钩子
+#[tauri::command]
+pub async fn add_hook(
+    proxy: State<'_, ProxyState>,
+    hook: ExportHook,
 ) -> Result<String, String> {
-    let config = AIResponseConfig {
-        enable_ai_responses: true,
-        response_type: ResponseType::Enhanced,
-        content_template: None,
-        ai_model: "gpt-3.5-turbo".to_string(),
-    };
-    
-    let _generator = AIResponseGenerator::new(config);
-    
-    // 这里需要从 request_data 构建 HttpRequest
-    // 暂时返回模拟响应
-    Ok(serde_json::json!({
-        "ai_generated": true,
-        "message": "AI 生成的响应",
-        "timestamp": chrono::Utc::now().to_rfc3339(),
-    }).to_string())
+    proxy.add_hook(hook).await.map_err(|e| e.to_string())?;
+    Ok("Hook added".to_string())
+}
+
+#[tauri::command]
+pub async fn remove_hook(
+    proxy: State<'_, ProxyState>,
+    hook_id: String,
+) -> Result<String, String> {
+    proxy.remove_hook(&hook_id).await;
+    Ok("Hook removed".to_string())
+}
+
+#[tauri::command]
+pub async fn get_hooks(proxy: State<'_, ProxyState>) -> Result<Vec<HookInfo>, String> {
+    Ok(proxy.get_hooks().await)
+}
+
+// 请求频率热力图
+#[tauri::command]
+pub async fn get_activity_heatmap(
+    proxy: State<'_, ProxyState>,
+    bucket_seconds: u64,
+    group_by: HeatmapGroupBy,
+) -> Result<ActivityHeatmap, String> {
+    Ok(proxy.get_activity_heatmap(bucket_seconds, group_by).await)
+}
+
+// 不传范围时统计全部事务，不传桶宽时按时间跨度自动选择
+#[tauri::command]
+pub async fn get_traffic_stats(
+    proxy: State<'_, ProxyState>,
+    range: Option<CaptureRange>,
+    bucket_seconds: Option<u64>,
+) -> Result<TrafficStats, String> {
+    Ok(proxy.get_traffic_stats(range.unwrap_or_default(), bucket_seconds).await)
+}
+
+// 延迟预算与瀑布图
+#[tauri::command]
+pub async fn add_latency_budget(
+    proxy: State<'_, ProxyState>,
+    budget: LatencyBudget,
+) -> Result<String, String> {
+    proxy.add_latency_budget(budget).await;
+    Ok("Latency budget added".to_string())
+}
+
+#[tauri::command]
+pub async fn remove_latency_budget(
+    proxy: State<'_, ProxyState>,
+    budget_id: String,
+) -> Result<String, String> {
+    proxy.remove_latency_budget(&budget_id).await;
+    Ok("Latency budget removed".to_string())
+}
+
+#[tauri::command]
+pub async fn get_latency_budgets(proxy: State<'_, ProxyState>) -> Result<Vec<LatencyBudget>, String> {
+    Ok(proxy.get_latency_budgets().await)
+}
+
+#[tauri::command]
+pub async fn get_waterfall(proxy: State<'_, ProxyState>) -> Result<Vec<PageLoadGroup>, String> {
+    Ok(proxy.get_waterfall().await)
+}
+
+// 内置测试服务
+#[tauri::command]
+pub async fn get_test_server_url(proxy: State<'_, ProxyState>) -> Result<String, String> {
+    proxy.get_test_server_url().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn stop_test_server(proxy: State<'_, ProxyState>) -> Result<String, String> {
+    proxy.stop_test_server().await;
+    Ok("Test server stopped".to_string())
+}
+
+// 断点
+#[tauri::command]
+pub async fn add_breakpoint(
+    proxy: State<'_, ProxyState>,
+    breakpoint: Breakpoint,
+) -> Result<String, String> {
+    proxy.add_breakpoint(breakpoint).await;
+    Ok("Breakpoint added".to_string())
+}
+
+#[tauri::command]
+pub async fn remove_breakpoint(
+    proxy: State<'_, ProxyState>,
+    breakpoint_id: String,
+) -> Result<String, String> {
+    proxy.remove_breakpoint(&breakpoint_id).await;
+    Ok("Breakpoint removed".to_string())
+}
+
+#[tauri::command]
+pub async fn get_breakpoints(proxy: State<'_, ProxyState>) -> Result<Vec<Breakpoint>, String> {
+    Ok(proxy.get_breakpoints().await)
+}
+
+#[tauri::command]
+pub async fn get_intercepted(proxy: State<'_, ProxyState>) -> Result<Vec<InterceptedItem>, String> {
+    Ok(proxy.get_intercepted().await)
+}
+
+#[tauri::command]
+pub async fn modify_intercepted(
+    proxy: State<'_, ProxyState>,
+    item_id: String,
+    modification: InterceptModification,
+) -> Result<InterceptedItem, String> {
+    proxy
+        .modify_intercepted(&item_id, modification)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn resume_intercepted(
+    proxy: State<'_, ProxyState>,
+    item_id: String,
+) -> Result<String, String> {
+    proxy.resume_intercepted(&item_id).await.map_err(|e| e.to_string())?;
+    Ok("Resumed".to_string())
+}
+
+#[tauri::command]
+pub async fn drop_intercepted(
+    proxy: State<'_, ProxyState>,
+    item_id: String,
+) -> Result<String, String> {
+    proxy.drop_intercepted(&item_id).await.map_err(|e| e.to_string())?;
+    Ok("Dropped".to_string())
+}
+
+// 主机配额
+#[tauri::command]
+pub async fn add_quota(
+    proxy: State<'_, ProxyState>,
+    quota: HostQuota,
+) -> Result<String, String> {
+    proxy.add_quota(quota).await;
+    Ok("Quota added".to_string())
+}
+
+#[tauri::command]
+pub async fn remove_quota(
+    proxy: State<'_, ProxyState>,
+    quota_id: String,
+) -> Result<String, String> {
+    proxy.remove_quota(&quota_id).await;
+    Ok("Quota removed".to_string())
+}
+
+#[tauri::command]
+pub async fn get_quota_usage(proxy: State<'_, ProxyState>) -> Result<Vec<QuotaUsage>, String> {
+    Ok(proxy.get_quota_usage().await)
+}
+
+#[tauri::command]
+pub async fn get_quota_alerts(proxy: State<'_, ProxyState>) -> Result<Vec<QuotaAlert>, String> {
+    Ok(proxy.get_quota_alerts().await)
+}
+
+// 差异对比模式
+#[tauri::command]
+pub async fn get_shadow_config(proxy: State<'_, ProxyState>) -> Result<Option<ShadowConfig>, String> {
+    Ok(proxy.get_shadow_config().await)
+}
+
+#[tauri::command]
+pub async fn set_shadow_config(
+    proxy: State<'_, ProxyState>,
+    config: Option<ShadowConfig>,
+) -> Result<String, String> {
+    proxy.set_shadow_config(config).await.map_err(|e| e.to_string())?;
+    Ok("Shadow config updated".to_string())
+}
+
+#[tauri::command]
+pub async fn get_shadow_report(proxy: State<'_, ProxyState>) -> Result<ShadowReport, String> {
+    Ok(proxy.get_shadow_report().await)
+}
+
+// 请求修改记录
+#[tauri::command]
+pub async fn get_transaction_detail(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+) -> Result<TransactionDetail, String> {
+    proxy
+        .get_transaction_detail(&transaction_id)
+        .await
+        .ok_or_else(|| "Transaction not found".to_string())
+}
+
+#[tauri::command]
+pub async fn get_body_hexdump(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+    part: BodyPart,
+    offset: usize,
+    length: usize,
+) -> Result<HexDumpPage, String> {
+    proxy
+        .get_body_hexdump(&transaction_id, part, offset, length)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_body_as_json(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+    part: BodyPart,
+    include_paths: Option<bool>,
+) -> Result<JsonBody, String> {
+    proxy
+        .get_body_as_json(&transaction_id, part, include_paths.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_parsed_body(proxy: State<'_, ProxyState>, transaction_id: String) -> Result<ParsedBody, String> {
+    proxy.get_parsed_body(&transaction_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_grpc_messages(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+    part: BodyPart,
+) -> Result<GrpcBody, String> {
+    proxy.get_grpc_messages(&transaction_id, part).await.map_err(|e| e.to_string())
+}
+
+// gRPC 消息定义
+#[tauri::command]
+pub async fn register_proto_files(
+    proxy: State<'_, ProxyState>,
+    paths: Vec<String>,
+) -> Result<ProtoRegistrySummary, String> {
+    proxy.register_proto_files(paths).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn clear_proto_files(proxy: State<'_, ProxyState>) -> Result<(), String> {
+    proxy.clear_proto_files().await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_proto_registry(proxy: State<'_, ProxyState>) -> Result<ProtoRegistrySummary, String> {
+    Ok(proxy.get_proto_registry().await)
+}
+
+#[tauri::command]
+pub async fn get_modification_history(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+) -> Result<Vec<ModificationStage>, String> {
+    proxy
+        .get_modification_history(&transaction_id)
+        .await
+        .ok_or_else(|| "Transaction not found".to_string())
+}
+
+// WebSocket 消息
+#[tauri::command]
+pub async fn get_ws_messages(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+) -> Result<Vec<WsMessage>, String> {
+    proxy
+        .get_ws_messages(&transaction_id)
+        .await
+        .ok_or_else(|| "Transaction not found".to_string())
+}
+
+// 重放
+#[tauri::command]
+pub async fn replay_transaction(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+    overrides: Option<RequestOverrides>,
+) -> Result<TransactionData, String> {
+    proxy
+        .replay_transaction(&transaction_id, overrides.unwrap_or_default())
+        .await
+        .map(TransactionData::from)
+        .map_err(|e| e.to_string())
+}
+
+// 请求编辑器
+#[tauri::command]
+pub async fn send_custom_request(
+    proxy: State<'_, ProxyState>,
+    method: String,
+    url: String,
+    headers: Option<HashMap<String, String>>,
+    body: Option<String>,
+) -> Result<TransactionData, String> {
+    proxy
+        .send_custom_request(&method, &url, headers.unwrap_or_default(), body.unwrap_or_default())
+        .await
+        .map(TransactionData::from)
+        .map_err(|e| e.to_string())
+}
+
+// 会话保存与打开
+#[tauri::command]
+pub async fn save_session(proxy: State<'_, ProxyState>, path: String) -> Result<SessionSummary, String> {
+    proxy.save_session(&path).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn load_session(proxy: State<'_, ProxyState>, path: String) -> Result<SessionSummary, String> {
+    proxy.load_session(&path).await.map_err(|e| e.to_string())
+}
+
+// 崩溃恢复
+#[tauri::command]
+pub async fn get_recovery_info(proxy: State<'_, ProxyState>) -> Result<Option<RecoveryInfo>, String> {
+    Ok(proxy.get_recovery_info().await)
+}
+
+#[tauri::command]
+pub async fn restore_recovery(proxy: State<'_, ProxyState>) -> Result<usize, String> {
+    proxy.restore_recovery().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn discard_recovery(proxy: State<'_, ProxyState>) -> Result<String, String> {
+    proxy.discard_recovery().await.map_err(|e| e.to_string())?;
+    Ok("Recovery data discarded".to_string())
+}
+
+// 收藏功能
+#[tauri::command]
+pub async fn toggle_favorite(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+) -> Result<bool, String> {
+    Ok(proxy.toggle_favorite(&transaction_id).await)
+}
+
+#[tauri::command]
+pub async fn get_favorites(proxy: State<'_, ProxyState>) -> Result<Vec<TransactionData>, String> {
+    let transactions = proxy.get_favorites().await;
+    
+    let transaction_data: Vec<TransactionData> = transactions
+        .into_iter()
+        .map(TransactionData::from)
+        .collect();
+    
+    Ok(transaction_data)
+}
+
+// 备注
+#[tauri::command]
+pub async fn set_transaction_note(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+    note: Option<String>,
+) -> Result<String, String> {
+    proxy.set_transaction_note(&transaction_id, note).await
+        .map_err(|e| e.to_string())?;
+    Ok("Note updated".to_string())
+}
+
+#[tauri::command]
+pub async fn get_transaction_note(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+) -> Result<Option<String>, String> {
+    proxy
+        .get_transaction_note(&transaction_id)
+        .await
+        .ok_or_else(|| "Transaction not found".to_string())
+}
+
+// 标签管理
+#[tauri::command]
+pub async fn add_tag(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+    tag: String,
+) -> Result<Vec<String>, String> {
+    proxy.add_tag(&transaction_id, &tag).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn remove_tag(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+    tag: String,
+) -> Result<Vec<String>, String> {
+    proxy.remove_tag(&transaction_id, &tag).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_transactions_by_tag(
+    proxy: State<'_, ProxyState>,
+    tag: String,
+) -> Result<Vec<TransactionData>, String> {
+    Ok(proxy
+        .get_transactions_by_tag(&tag)
+        .await
+        .into_iter()
+        .map(TransactionData::from)
+        .collect())
+}
+
+// 规则管理
+#[tauri::command]
+pub async fn add_rule(
+    proxy: State<'_, ProxyState>,
+    rule: RequestRule,
+) -> Result<String, String> {
+    proxy.add_rule(rule).await.map_err(|e| e.to_string())?;
+    Ok("Rule added".to_string())
+}
+
+// 确认规则建议后一次性加入
+#[tauri::command]
+pub async fn add_rules(
+    proxy: State<'_, ProxyState>,
+    rules: Vec<RequestRule>,
+) -> Result<usize, String> {
+    proxy.add_rules(rules).await.map_err(|e| e.to_string())
+}
+
+// 自然语言生成规则，返回的规则需用户确认后再通过 add_rules 加入
+#[tauri::command]
+pub async fn create_rule_from_text(
+    proxy: State<'_, ProxyState>,
+    text: String,
+) -> Result<RuleProposal, String> {
+    proxy.create_rule_from_text(&text).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn remove_rule(
+    proxy: State<'_, ProxyState>,
+    rule_id: String,
+) -> Result<String, String> {
+    proxy.remove_rule(&rule_id).await;
+    Ok("Rule removed".to_string())
+}
+
+#[tauri::command]
+pub async fn get_rules(proxy: State<'_, ProxyState>) -> Result<Vec<RequestRule>, String> {
+    Ok(proxy.get_rules().await)
+}
+
+// 拖拽排序后传入完整的规则 id 列表
+#[tauri::command]
+pub async fn reorder_rules(proxy: State<'_, ProxyState>, rule_ids: Vec<String>) -> Result<Vec<RequestRule>, String> {
+    proxy.reorder_rules(&rule_ids).await.map_err(|e| e.to_string())?;
+    Ok(proxy.get_rules().await)
+}
+
+#[tauri::command]
+pub async fn export_rules(proxy: State<'_, ProxyState>, path: String) -> Result<usize, String> {
+    proxy.export_rules(&path).await.map_err(|e| e.to_string())
+}
+
+// mode 缺省为 merge
+#[tauri::command]
+pub async fn import_rules(
+    proxy: State<'_, ProxyState>,
+    path: String,
+    mode: Option<ImportMode>,
+) -> Result<RuleImportSummary, String> {
+    proxy.import_rules(&path, mode.unwrap_or_default()).await.map_err(|e| e.to_string())
+}
+
+// HAR 导出
+#[tauri::command]
+pub async fn export_har(
+    proxy: State<'_, ProxyState>,
+    options: Option<ExportOptions>,
+) -> Result<String, String> {
+    Ok(proxy.export_har(&options.unwrap_or_default()).await)
+}
+
+#[tauri::command]
+pub async fn export_mitmproxy_flows(proxy: State<'_, ProxyState>, path: String) -> Result<usize, String> {
+    proxy.export_mitmproxy_flows(&path).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_pcapng(proxy: State<'_, ProxyState>, path: String) -> Result<usize, String> {
+    proxy.export_pcapng(&path).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn generate_curl(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+    options: Option<CurlOptions>,
+) -> Result<String, String> {
+    proxy
+        .generate_curl(&transaction_id, &options.unwrap_or_default())
+        .await
+        .ok_or_else(|| "Transaction not found".to_string())
+}
+
+#[tauri::command]
+pub async fn export_postman(
+    proxy: State<'_, ProxyState>,
+    collection_name: String,
+    transaction_ids: Option<Vec<String>>,
+    options: Option<ExportOptions>,
+) -> Result<String, String> {
+    Ok(proxy
+        .export_postman(&collection_name, transaction_ids, &options.unwrap_or_default())
+        .await)
+}
+
+#[tauri::command]
+pub async fn export_load_test(
+    proxy: State<'_, ProxyState>,
+    format: LoadTestFormat,
+    filter: Option<SearchFilter>,
+    transaction_ids: Option<Vec<String>>,
+    options: Option<ExportOptions>,
+) -> Result<String, String> {
+    proxy
+        .export_load_test(format, filter, transaction_ids, &options.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn import_har(
+    proxy: State<'_, ProxyState>,
+    path: String,
+) -> Result<usize, String> {
+    let content = tokio::fs::read_to_string(&path).await
+        .map_err(|e| e.to_string())?;
+    proxy.import_har(&content).await
+        .map_err(|e| e.to_string())
+}
+
+// JWT 检查
+// 读取字典和爆破都会阻塞，放到阻塞线程池中执行
+#[tauri::command]
+pub async fn decode_jwt(token: String, wordlist_path: Option<String>) -> Result<JwtInfo, String> {
+    tokio::task::spawn_blocking(move || {
+        let mut info = jwt::decode(&token)?;
+        let wordlist = jwt::load_wordlist(wordlist_path.as_deref())?;
+        jwt::crack_secret(&token, &mut info, wordlist.iter().map(String::as_str));
+        anyhow::Ok(info)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn find_transaction_jwts(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+    wordlist_path: Option<String>,
+) -> Result<Vec<FoundJwt>, String> {
+    proxy.find_jwts(&transaction_id, wordlist_path).await.map_err(|e| e.to_string())
+}
+
+// Cookie 罐
+#[tauri::command]
+pub async fn get_cookies(proxy: State<'_, ProxyState>, domain: Option<String>) -> Result<Vec<Cookie>, String> {
+    Ok(proxy.get_cookies(domain.as_deref()).await)
+}
+
+#[tauri::command]
+pub async fn clear_cookies(proxy: State<'_, ProxyState>, domain: Option<String>) -> Result<usize, String> {
+    Ok(proxy.clear_cookies(domain.as_deref()).await)
+}
+
+// TLS 握手信息
+#[tauri::command]
+pub async fn get_tls_info(proxy: State<'_, ProxyState>, transaction_id: String) -> Result<TlsInfo, String> {
+    proxy.get_tls_info(&transaction_id).await.map_err(|e| e.to_string())
+}
+
+// 开启 HTTPS 解密时原样转发的主机；返回规范化后的列表
+#[tauri::command]
+pub async fn set_tls_passthrough(proxy: State<'_, ProxyState>, hosts: Vec<String>) -> Result<Vec<String>, String> {
+    proxy.set_tls_passthrough(hosts).await.map_err(|e| e.to_string())
+}
+
+// 疑似证书绑定的主机
+#[tauri::command]
+pub async fn get_pinning_report(proxy: State<'_, ProxyState>) -> Result<Vec<PinningSuspect>, String> {
+    Ok(proxy.get_pinning_report().await)
+}
+
+#[tauri::command]
+pub async fn clear_pinning_report(proxy: State<'_, ProxyState>) -> Result<(), String> {
+    proxy.clear_pinning_report().await;
+    Ok(())
+}
+
+// 安全响应头审计
+#[tauri::command]
+pub async fn get_security_header_report(
+    proxy: State<'_, ProxyState>,
+    host: String,
+) -> Result<SecurityHeaderReport, String> {
+    Ok(proxy.get_security_header_report(&host).await)
+}
+
+// 编码工具
+#[tauri::command]
+pub fn encode_base64(input: String) -> Result<String, String> {
+    Ok(ProxyServer::encode_base64(&input))
+}
+
+#[tauri::command]
+pub fn decode_base64(input: String) -> Result<String, String> {
+    ProxyServer::decode_base64(&input)
+}
+
+#[tauri::command]
+pub fn encode_url(input: String) -> Result<String, String> {
+    Ok(ProxyServer::encode_url(&input))
+}
+
+#[tauri::command]
+pub fn decode_url(input: String) -> Result<String, String> {
+    Ok(ProxyServer::decode_url(&input))
+}
+
+#[tauri::command]
+pub fn encode_hex(input: String) -> Result<String, String> {
+    Ok(codecs::hex_encode(&input))
+}
+
+#[tauri::command]
+pub fn decode_hex(input: String) -> Result<String, String> {
+    codecs::hex_decode(&input).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn compress_text(input: String, format: CompressionFormat) -> Result<String, String> {
+    codecs::compress(&input, format).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn decompress_text(input: String, format: CompressionFormat) -> Result<String, String> {
+    codecs::decompress(&input, format).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn escape_html(input: String) -> Result<String, String> {
+    Ok(codecs::html_escape(&input))
+}
+
+#[tauri::command]
+pub fn unescape_html(input: String) -> Result<String, String> {
+    Ok(codecs::html_unescape(&input))
+}
+
+#[tauri::command]
+pub fn escape_unicode(input: String) -> Result<String, String> {
+    Ok(codecs::unicode_escape(&input))
+}
+
+#[tauri::command]
+pub fn unescape_unicode(input: String) -> Result<String, String> {
+    codecs::unicode_unescape(&input).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn convert_timestamp(input: String) -> Result<TimestampConversion, String> {
+    codecs::convert_timestamp(&input).map_err(|e| e.to_string())
+}
+
+// 哈希与 HMAC，用于核对抓包中观察到的请求签名
+#[tauri::command]
+pub fn compute_hash(input: String, algorithm: HashAlgorithm) -> Result<HashOutput, String> {
+    Ok(hashing::compute_hash(input.as_bytes(), algorithm))
+}
+
+#[tauri::command]
+pub fn compute_hmac(input: String, key: String, algorithm: HashAlgorithm) -> Result<HashOutput, String> {
+    Ok(hashing::compute_hmac(input.as_bytes(), key.as_bytes(), algorithm))
+}
+
+// AI 分析命令
+#[tauri::command]
+pub async fn analyze_transaction(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+) -> Result<AIAnalysisResult, String> {
+    let transactions = proxy.get_transactions().await;
+    let transaction = transactions
+        .iter()
+        .find(|t| t.id == transaction_id)
+        .ok_or("Transaction not found")?;
+    
+    proxy.analyze_transaction(transaction).await
+        .map_err(|e| e.to_string())
+}
+
+// 流式分析：返回请求 id，输出通过 ai:stream 事件推送
+#[tauri::command]
+pub async fn analyze_transaction_stream(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+) -> Result<String, String> {
+    proxy.analyze_transaction_stream(&transaction_id).await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn cancel_ai_request(
+    proxy: State<'_, ProxyState>,
+    request_id: String,
+) -> Result<bool, String> {
+    Ok(proxy.cancel_ai_request(&request_id).await)
+}
+
+// 围绕事务的多轮对话，首次提问不传 conversation_id
+#[tauri::command]
+pub async fn chat_about_transaction(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+    message: String,
+    conversation_id: Option<String>,
+) -> Result<ChatReply, String> {
+    proxy.chat_about_transaction(&transaction_id, &message, conversation_id.as_deref()).await
+        .map_err(|e| e.to_string())
+}
+
+// 流式对话：返回请求 id，完成事件携带 ChatReply
+#[tauri::command]
+pub async fn chat_about_transaction_stream(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+    message: String,
+    conversation_id: Option<String>,
+) -> Result<String, String> {
+    proxy.chat_about_transaction_stream(&transaction_id, &message, conversation_id.as_deref()).await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_conversation(
+    proxy: State<'_, ProxyState>,
+    conversation_id: String,
+) -> Result<Conversation, String> {
+    proxy.get_conversation(&conversation_id).await
+        .ok_or_else(|| "Conversation not found".to_string())
+}
+
+#[tauri::command]
+pub async fn list_conversations(
+    proxy: State<'_, ProxyState>,
+    transaction_id: Option<String>,
+) -> Result<Vec<Conversation>, String> {
+    Ok(proxy.list_conversations(transaction_id.as_deref()).await)
+}
+
+#[tauri::command]
+pub async fn delete_conversation(
+    proxy: State<'_, ProxyState>,
+    conversation_id: String,
+) -> Result<bool, String> {
+    proxy.delete_conversation(&conversation_id).await
+        .map_err(|e| e.to_string())
+}
+
+// 不传范围时汇总全部事务
+#[tauri::command]
+pub async fn summarize_capture(
+    proxy: State<'_, ProxyState>,
+    range: Option<CaptureRange>,
+) -> Result<CaptureSummary, String> {
+    proxy.summarize_capture(range.unwrap_or_default()).await
+        .map_err(|e| e.to_string())
+}
+
+// 不传事务 id 时清空全部缓存
+#[tauri::command]
+pub async fn invalidate_ai_cache(
+    proxy: State<'_, ProxyState>,
+    transaction_id: Option<String>,
+) -> Result<usize, String> {
+    proxy.invalidate_ai_cache(transaction_id.as_deref()).await
+        .map_err(|e| e.to_string())
+}
+
+// 对启发式得分最高的一批事务做模型评分，返回更新后的事务
+#[tauri::command]
+pub async fn score_transactions(
+    proxy: State<'_, ProxyState>,
+    limit: Option<usize>,
+    min_score: Option<u8>,
+) -> Result<Vec<TransactionData>, String> {
+    let candidates = proxy
+        .model_scoring_candidates(limit.unwrap_or(DEFAULT_MODEL_SCORING_LIMIT), min_score.unwrap_or_default())
+        .await;
+    
+    let mut scored = Vec::new();
+    for transaction in &candidates {
+        proxy.analyze_transaction(transaction).await
+            .map_err(|e| e.to_string())?;
+        scored.push(transaction.id.clone());
+    }
+    
+    Ok(proxy
+        .get_transactions()
+        .await
+        .into_iter()
+        .filter(|t| scored.contains(&t.id))
+        .map(TransactionData::from)
+        .collect())
+}
+
+// 立即重新扫描指定事务，结果同时计入累计的扫描结果
+#[tauri::command]
+pub async fn detect_vulnerabilities(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+) -> Result<Vec<Finding>, String> {
+    proxy.scan_transaction(&transaction_id).await.map_err(|e| e.to_string())
+}
+
+// 被动和主动扫描累计的结果，按时间倒序，默认 200 条
+#[tauri::command]
+pub async fn get_security_findings(
+    proxy: State<'_, ProxyState>,
+    min_severity: Option<SecurityRisk>,
+    transaction_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<Finding>, String> {
+    Ok(proxy.get_security_findings(min_severity, transaction_id.as_deref(), limit.unwrap_or(200)).await)
+}
+
+// 扫描结果导出为 SARIF 或按主机分组的 Markdown 报告
+#[tauri::command]
+pub async fn export_findings(proxy: State<'_, ProxyState>, format: FindingsFormat) -> Result<String, String> {
+    Ok(proxy.export_findings(format).await)
+}
+
+// 主动扫描默认关闭，只探测范围列表中的主机
+#[tauri::command]
+pub async fn get_active_scan_settings(proxy: State<'_, ProxyState>) -> Result<ActiveScanSettings, String> {
+    Ok(proxy.get_active_scan_settings().await)
+}
+
+#[tauri::command]
+pub async fn set_active_scan_settings(
+    proxy: State<'_, ProxyState>,
+    settings: ActiveScanSettings,
+) -> Result<ActiveScanSettings, String> {
+    Ok(proxy.set_active_scan_settings(settings).await)
+}
+
+#[tauri::command]
+pub async fn active_scan_transaction(
+    proxy: State<'_, ProxyState>,
+    transaction_id: String,
+) -> Result<ActiveScanReport, String> {
+    proxy.active_scan_transaction(&transaction_id).await.map_err(|e| e.to_string())
+}
+
+// 加载 MaxMind 格式的国家/城市或 ASN 数据库，可同时加载多个
+#[tauri::command]
+pub async fn load_geoip_database(proxy: State<'_, ProxyState>, path: String) -> Result<GeoIpDatabaseInfo, String> {
+    proxy.load_geoip_database(&path).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_geoip_databases(proxy: State<'_, ProxyState>) -> Result<Vec<GeoIpDatabaseInfo>, String> {
+    Ok(proxy.get_geoip_databases().await)
+}
+
+#[tauri::command]
+pub async fn clear_geoip_databases(proxy: State<'_, ProxyState>) -> Result<(), String> {
+    proxy.clear_geoip_databases().await;
+    Ok(())
+}
+
+// 转发使用的解析方式：系统解析、自定义 DNS 服务器或 DNS-over-HTTPS
+#[tauri::command]
+pub async fn get_dns_settings(proxy: State<'_, ProxyState>) -> Result<DnsSettings, String> {
+    Ok(proxy.get_dns_settings().await)
+}
+
+#[tauri::command]
+pub async fn set_dns_settings(proxy: State<'_, ProxyState>, settings: DnsSettings) -> Result<DnsSettings, String> {
+    proxy.set_dns_settings(settings).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_dns_cache(proxy: State<'_, ProxyState>) -> Result<Vec<DnsCacheEntry>, String> {
+    Ok(proxy.get_dns_cache().await)
+}
+
+#[tauri::command]
+pub async fn clear_dns_cache(proxy: State<'_, ProxyState>) -> Result<(), String> {
+    proxy.clear_dns_cache().await;
+    Ok(())
+}
+
+// 以 OTLP/HTTP 向收集器导出转发的请求，便于与后端链路对照
+#[tauri::command]
+pub async fn get_otel_settings(proxy: State<'_, ProxyState>) -> Result<OtelSettings, String> {
+    Ok(proxy.get_otel_settings().await)
+}
+
+#[tauri::command]
+pub async fn set_otel_settings(proxy: State<'_, ProxyState>, settings: OtelSettings) -> Result<OtelSettings, String> {
+    proxy.set_otel_settings(settings).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_otel_status(proxy: State<'_, ProxyState>) -> Result<OtelStatus, String> {
+    Ok(proxy.get_otel_status().await)
+}
+
+// 立即发送积压的 span，不等待批次攒满
+#[tauri::command]
+pub async fn flush_otel_export(proxy: State<'_, ProxyState>) -> Result<OtelStatus, String> {
+    Ok(proxy.flush_otel_export().await)
+}
+
+// 最近的异常，默认 100 条
+#[tauri::command]
+pub async fn get_anomalies(
+    proxy: State<'_, ProxyState>,
+    min_severity: Option<AnomalySeverity>,
+    limit: Option<usize>,
+) -> Result<Vec<Anomaly>, String> {
+    Ok(proxy.get_anomalies(min_severity, limit.unwrap_or(100)).await)
+}
+
+#[tauri::command]
+pub async fn reset_anomaly_baseline(proxy: State<'_, ProxyState>) -> Result<(), String> {
+    proxy.reset_anomaly_baseline().await;
+    Ok(())
+}
+
+// 个人信息遮盖设置
+#[tauri::command]
+pub async fn get_pii_masking(proxy: State<'_, ProxyState>) -> Result<MaskingSettings, String> {
+    Ok(proxy.get_pii_masking().await)
+}
+
+#[tauri::command]
+pub async fn set_pii_masking(proxy: State<'_, ProxyState>, settings: MaskingSettings) -> Result<(), String> {
+    proxy.set_pii_masking(settings).await;
+    Ok(())
+}
+
+// 发给模型和导出前替换为占位符的请求头与字段
+#[tauri::command]
+pub async fn get_redaction_policy(proxy: State<'_, ProxyState>) -> Result<RedactionPolicy, String> {
+    Ok(proxy.get_redaction_policy().await)
+}
+
+#[tauri::command]
+pub async fn set_redaction_policy(proxy: State<'_, ProxyState>, policy: RedactionPolicy) -> Result<RedactionPolicy, String> {
+    proxy.set_redaction_policy(policy).await;
+    Ok(proxy.get_redaction_policy().await)
+}
+
+// 洞察列表中最多列出的异常数
+const MAX_INSIGHT_ANOMALIES: usize = 20;
+
+#[tauri::command]
+pub async fn get_ai_insights(
+    proxy: State<'_, ProxyState>,
+) -> Result<Vec<String>, String> {
+    let transactions = proxy.get_transactions().await;
+    
+    let ai_analyzer = proxy.ai_analyzer().await;
+    
+    let mut insights = Vec::new();
+    
+    // 按流量基线检测到的最近异常
+    let anomalies = proxy.get_anomalies(None, MAX_INSIGHT_ANOMALIES).await;
+    insights.extend(anomalies.into_iter().map(|a| a.description));
+    
+    // 获取优化建议
+    let optimizations = ai_analyzer.suggest_optimizations(&transactions).await
+        .map_err(|e| e.to_string())?;
+    insights.extend(optimizations);
+    
+    Ok(insights)
+}
+
+// 本地模型服务；未指定地址时检查配置中的 Ollama 地址
+#[tauri::command]
+pub async fn check_ai_endpoint(
+    proxy: State<'_, ProxyState>,
+    base_url: Option<String>,
+) -> Result<EndpointStatus, String> {
+    let base_url = match base_url.filter(|url| !url.trim().is_empty()) {
+        Some(url) => url,
+        None => proxy
+            .get_ai_provider_config()
+            .await
+            .filter(|config| config.provider == AiProvider::Ollama)
+            .and_then(|config| config.base_url)
+            .unwrap_or_else(|| llm::OLLAMA_BASE_URL.to_string()),
+    };
+    Ok(llm::check_endpoint(&llm::client(), &base_url, None).await)
+}
+
+#[tauri::command]
+pub async fn list_ai_models(
+    proxy: State<'_, ProxyState>,
+    base_url: Option<String>,
+) -> Result<Vec<String>, String> {
+    let status = check_ai_endpoint(proxy, base_url).await?;
+    match status.error {
+        Some(error) => Err(error),
+        None => Ok(status.models),
+    }
+}
+
+// AI 响应生成命令：该接口抓到过 JSON 响应时按其结构生成模拟数据
+#[tauri::command]
+pub async fn generate_ai_response(
+    proxy: State<'_, ProxyState>,
+    request_data: serde_json::Value,
+    options: Option<MockOptions>,
+    schema: Option<serde_json::Value>,
+) -> Result<GeneratedMock, String> {
+    let url = request_data["url"].as_str().ok_or("Request URL is required")?;
+    let request = HttpRequest {
+        method: request_data["method"].as_str().unwrap_or("GET").to_uppercase(),
+        url: url.to_string(),
+        headers: serde_json::from_value(request_data["headers"].clone()).unwrap_or_default(),
+        body: request_data["body"].as_str().unwrap_or_default().as_bytes().to_vec(),
+        timestamp: chrono::Utc::now(),
+        version: None,
+    };
+    
+    proxy.generate_mock_response(request, options.unwrap_or_default(), schema).await
+        .map_err(|e| e.to_string())
+}
+
+// 合并多个事务的请求体/响应体推断 JSON Schema；endpoint 形如 "GET api.example.com/users/{id}"
+#[tauri::command]
+pub async fn infer_json_schema(
+    proxy: State<'_, ProxyState>,
+    transaction_ids: Option<Vec<String>>,
+    endpoint: Option<String>,
+) -> Result<InferredSchema, String> {
+    proxy.infer_json_schema(transaction_ids.as_deref(), endpoint.as_deref()).await
+        .map_err(|e| e.to_string())
 }
@@ -0,0 +1,163 @@
+use crate::active::ActiveScanSettings;
+use crate::dns::DnsSettings;
+use crate::otel::OtelSettings;
+use crate::keychain::ApiKeySource;
+use crate::proxy::RequestRule;
+use crate::redaction::{MaskingSettings, RedactionPolicy};
+use crate::rules;
+use crate::settings::CaptureSettings;
+use crate::throttle::ThrottleSettings;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::time::Duration;
+
+pub const CONFIG_FILE_NAME: &str = "config.json";
+// 编辑器保存时常连续触发写入、重命名等多个事件，静默这么久之后才重新加载
+pub const CONFIG_DEBOUNCE: Duration = Duration::from_millis(300);
+pub const CONFIG_RELOADED_EVENT: &str = "config:reloaded";
+pub const CONFIG_ERROR_EVENT: &str = "config:error";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AiProvider {
+    Openai,
+    Anthropic,
+    // Ollama 或其他 OpenAI 兼容的本地服务
+    Ollama,
+    Local,
+}
+
+// 配置文件中不保存明文密钥，只记录从哪个环境变量读取
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiProviderConfig {
+    pub provider: AiProvider,
+    pub model: String,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    // 单次回复的输出上限，目前只有 Anthropic 使用
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+}
+
+impl AiProviderConfig {
+    // 与界面保存时的校验一致
+    pub fn validated(mut self) -> Result<Self> {
+        self.model = self.model.trim().to_string();
+        if self.model.is_empty() {
+            return Err(anyhow!("Model name is required"));
+        }
+        self.base_url = self.base_url.map(|url| url.trim().trim_end_matches('/').to_string()).filter(|url| !url.is_empty());
+        if let Some(url) = &self.base_url {
+            url::Url::parse(url).map_err(|e| anyhow!("Invalid base URL {}: {}", url, e))?;
+        }
+        Ok(self)
+    }
+}
+
+// 返回给界面的 AI 配置，不包含密钥本身
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiConfigView {
+    pub provider: AiProvider,
+    pub model: String,
+    pub base_url: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub has_api_key: bool,
+    pub api_key_source: Option<ApiKeySource>,
+}
+
+impl AiProvider {
+    // 未指定 api_key_env 时读取的环境变量
+    pub fn default_api_key_env(self) -> Option<&'static str> {
+        match self {
+            AiProvider::Openai => Some("OPENAI_API_KEY"),
+            AiProvider::Anthropic => Some("ANTHROPIC_API_KEY"),
+            AiProvider::Ollama | AiProvider::Local => None,
+        }
+    }
+}
+
+// 数据目录下的配置文件；省略的部分不受文件管理，保留界面中的设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub capture: Option<CaptureSettings>,
+    pub rules: Option<Vec<RequestRule>>,
+    pub filters: Option<Vec<String>>,
+    pub bypass: Option<Vec<String>>,
+    pub throttle: Option<ThrottleSettings>,
+    pub ai: Option<AiProviderConfig>,
+    pub redaction: Option<RedactionPolicy>,
+    pub pii_masking: Option<MaskingSettings>,
+    pub active_scan: Option<ActiveScanSettings>,
+    pub dns: Option<DnsSettings>,
+    pub otel: Option<OtelSettings>,
+}
+
+impl AppConfig {
+    // 实际生效的部分，随重载事件通知界面
+    pub fn sections(&self) -> Vec<&'static str> {
+        [
+            ("capture", self.capture.is_some()),
+            ("rules", self.rules.is_some()),
+            ("filters", self.filters.is_some()),
+            ("bypass", self.bypass.is_some()),
+            ("throttle", self.throttle.is_some()),
+            ("ai", self.ai.is_some()),
+            ("redaction", self.redaction.is_some()),
+            ("pii_masking", self.pii_masking.is_some()),
+            ("active_scan", self.active_scan.is_some()),
+            ("dns", self.dns.is_some()),
+            ("otel", self.otel.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, present)| present.then_some(name))
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigStatus {
+    pub path: String,
+    pub loaded_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub sections: Vec<String>,
+    pub error: Option<String>,
+}
+
+// 有任一规则无效时整体拒绝，保持上一次成功加载的配置
+pub fn read(path: &Path) -> Result<AppConfig> {
+    let config: AppConfig = serde_json::from_slice(&std::fs::read(path)?)
+        .map_err(|e| anyhow!("Invalid config file {}: {}", path.display(), e))?;
+    for rule in config.rules.iter().flatten() {
+        rules::validate(rule).map_err(|e| anyhow!("Invalid rule '{}' in config: {}", rule.name, e))?;
+    }
+    Ok(config)
+}
+
+// 文件内容的哈希，内容不变的保存（如只更新了修改时间）不触发重新加载；文件不存在时为 None
+pub fn fingerprint(path: &Path) -> Option<[u8; 32]> {
+    let content = std::fs::read(path).ok()?;
+    Some(Sha256::digest(content).into())
+}
+
+// 界面修改的 AI 配置写回配置文件，文件中的其他部分保持不变；
+// 写入后由文件监听重新加载，之后的重载也不会覆盖界面中的设置
+pub fn write_ai(path: &Path, ai: &AiProviderConfig) -> Result<()> {
+    let mut document = match std::fs::read(path) {
+        Ok(content) => serde_json::from_slice::<serde_json::Value>(&content)
+            .map_err(|e| anyhow!("Invalid config file {}: {}", path.display(), e))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => serde_json::Value::Object(Default::default()),
+        Err(e) => return Err(e.into()),
+    };
+    let object = document
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Config file {} is not a JSON object", path.display()))?;
+    object.insert("ai".to_string(), serde_json::to_value(ai)?);
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, serde_json::to_vec_pretty(&document)?)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
@@ -0,0 +1,276 @@
+use crate::proxy::{find_header, HttpTransaction};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CookieSource {
+    // 服务器通过 Set-Cookie 设置
+    SetCookie,
+    // 只在请求的 Cookie 头中出现过，通常由前端脚本写入，属性未知
+    Request,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    // 小写、不带前导 "."
+    pub domain: String,
+    // 未指定 Domain 属性时只发送给设置它的主机
+    pub host_only: bool,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<String>,
+    // 由 Max-Age 或 Expires 得出；为空表示会话 Cookie
+    pub expires: Option<DateTime<Utc>>,
+    pub expired: bool,
+    pub source: CookieSource,
+    // 最近一次设置该 Cookie 的事务
+    pub set_by: Option<String>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    // 客户端在请求中携带该 Cookie 的次数
+    pub sent_count: u64,
+}
+
+// 按 (domain, path, name) 唯一标识一个 Cookie，与浏览器的覆盖规则一致
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    // 先处理请求携带的 Cookie，再处理响应设置的 Cookie
+    pub fn observe(&mut self, transaction: &HttpTransaction) {
+        let Ok(url) = url::Url::parse(&transaction.request.url) else {
+            return;
+        };
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+        if host.is_empty() {
+            return;
+        }
+        let request_time = transaction.request.timestamp;
+
+        if let Some(header) = find_header(&transaction.request.headers, "cookie") {
+            for pair in header.split([';', '\n']) {
+                let Some((name, value)) = pair.split_once('=') else {
+                    continue;
+                };
+                self.record_sent(&host, url.path(), name.trim(), value.trim(), request_time);
+            }
+        }
+
+        let Some(response) = &transaction.response else {
+            return;
+        };
+        let Some(header) = find_header(&response.headers, "set-cookie") else {
+            return;
+        };
+        for line in header.split('\n') {
+            if let Some(cookie) = parse_set_cookie(line, &host, url.path(), response.timestamp, &transaction.id) {
+                self.store(cookie, response.timestamp);
+            }
+        }
+    }
+
+    pub fn rebuild(&mut self, transactions: &[HttpTransaction]) {
+        self.cookies.clear();
+        for transaction in transactions {
+            self.observe(transaction);
+        }
+    }
+
+    // 给定域名时返回与其相关的 Cookie：会发送给该主机的，以及设置在其子域名上的
+    pub fn cookies(&self, domain: Option<&str>) -> Vec<Cookie> {
+        let now = Utc::now();
+        let domain = domain.map(|d| d.trim().trim_start_matches('.').to_lowercase());
+        let mut cookies: Vec<Cookie> = self
+            .cookies
+            .iter()
+            .filter(|c| match &domain {
+                Some(domain) => {
+                    (c.host_only && c.domain == *domain)
+                        || (!c.host_only && domain_matches(domain, &c.domain))
+                        || domain_matches(&c.domain, domain)
+                }
+                None => true,
+            })
+            .cloned()
+            .map(|mut c| {
+                c.expired = c.expires.is_some_and(|expires| expires <= now);
+                c
+            })
+            .collect();
+        cookies.sort_by(|a, b| (&a.domain, &a.path, &a.name).cmp(&(&b.domain, &b.path, &b.name)));
+        cookies
+    }
+
+    // 返回清除的数量；按域名清除时包含其子域名
+    pub fn clear(&mut self, domain: Option<&str>) -> usize {
+        let before = self.cookies.len();
+        match domain.map(|d| d.trim().trim_start_matches('.').to_lowercase()) {
+            Some(domain) => self.cookies.retain(|c| !domain_matches(&c.domain, &domain)),
+            None => self.cookies.clear(),
+        }
+        before - self.cookies.len()
+    }
+
+    fn store(&mut self, cookie: Cookie, now: DateTime<Utc>) {
+        let existing = self
+            .cookies
+            .iter()
+            .position(|c| c.domain == cookie.domain && c.path == cookie.path && c.name == cookie.name);
+        // 过期时间早于响应时间即为删除指令
+        if cookie.expires.is_some_and(|expires| expires <= now) {
+            if let Some(index) = existing {
+                self.cookies.remove(index);
+            }
+            return;
+        }
+        match existing {
+            Some(index) => {
+                let previous = &self.cookies[index];
+                let cookie = Cookie { first_seen: previous.first_seen, sent_count: previous.sent_count, ..cookie };
+                self.cookies[index] = cookie;
+            }
+            None => self.cookies.push(cookie),
+        }
+    }
+
+    fn record_sent(&mut self, host: &str, path: &str, name: &str, value: &str, time: DateTime<Utc>) {
+        if name.is_empty() {
+            return;
+        }
+        // 同名时取路径最长的那个，与浏览器的发送顺序一致
+        let matched = self
+            .cookies
+            .iter_mut()
+            .filter(|c| {
+                c.name == name
+                    && if c.host_only { c.domain == host } else { domain_matches(host, &c.domain) }
+                    && path_matches(path, &c.path)
+            })
+            .max_by_key(|c| c.path.len());
+        match matched {
+            Some(cookie) => {
+                cookie.value = value.to_string();
+                cookie.last_seen = time;
+                cookie.sent_count += 1;
+            }
+            None => self.cookies.push(Cookie {
+                name: name.to_string(),
+                value: value.to_string(),
+                domain: host.to_string(),
+                host_only: true,
+                path: "/".to_string(),
+                secure: false,
+                http_only: false,
+                same_site: None,
+                expires: None,
+                expired: false,
+                source: CookieSource::Request,
+                set_by: None,
+                first_seen: time,
+                last_seen: time,
+                sent_count: 1,
+            }),
+        }
+    }
+}
+
+// 按 RFC 6265 第 5.2 节解析；Domain 与请求主机不匹配的 Cookie 会被浏览器拒绝，这里同样忽略
+pub fn parse_set_cookie(
+    line: &str,
+    host: &str,
+    request_path: &str,
+    time: DateTime<Utc>,
+    transaction_id: &str,
+) -> Option<Cookie> {
+    let mut parts = line.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let mut cookie = Cookie {
+        name: name.to_string(),
+        value: value.trim().trim_matches('"').to_string(),
+        domain: host.to_string(),
+        host_only: true,
+        path: default_path(request_path),
+        secure: false,
+        http_only: false,
+        same_site: None,
+        expires: None,
+        expired: false,
+        source: CookieSource::SetCookie,
+        set_by: Some(transaction_id.to_string()),
+        first_seen: time,
+        last_seen: time,
+        sent_count: 0,
+    };
+    let mut max_age = None;
+    for attribute in parts {
+        let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+        let value = value.trim();
+        match key.trim().to_lowercase().as_str() {
+            "domain" if !value.is_empty() => {
+                let domain = value.trim_start_matches('.').to_lowercase();
+                if !domain_matches(host, &domain) {
+                    return None;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+            "path" if value.starts_with('/') => cookie.path = value.to_string(),
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.http_only = true,
+            "samesite" if !value.is_empty() => cookie.same_site = Some(value.to_string()),
+            "max-age" => max_age = value.parse::<i64>().ok(),
+            "expires" => cookie.expires = cookie.expires.or_else(|| parse_cookie_date(value)),
+            _ => {}
+        }
+    }
+    // Max-Age 优先于 Expires
+    if let Some(seconds) = max_age {
+        cookie.expires = Some(time + chrono::Duration::seconds(seconds.clamp(-1, 400 * 24 * 3600)));
+    }
+    Some(cookie)
+}
+
+// 常见的三种写法：RFC 1123、Netscape 的短横线格式、以及两位年份
+fn parse_cookie_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    ["%a, %d-%b-%Y %H:%M:%S GMT", "%a, %d-%b-%y %H:%M:%S GMT", "%A, %d-%b-%y %H:%M:%S GMT"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|date| date.and_utc())
+}
+
+// RFC 6265 5.1.4：请求路径最后一个 "/" 之前的部分
+fn default_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => request_path[..index].to_string(),
+    }
+}
+
+// 按常见命名判断是否为会话或认证 Cookie
+pub fn is_session_cookie(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["sess", "sid", "token", "auth", "jwt", "login", "remember"].iter().any(|marker| name.contains(marker))
+}
+
+pub fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.strip_suffix(domain).is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
@@ -0,0 +1,137 @@
+use crate::proxy::{HttpRequest, ProxyServer};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CurlOptions {
+    // 以 --compressed 代替原始的 Accept-Encoding，curl 会自动解压响应
+    pub compressed: bool,
+    // 附加 -k，跳过证书校验（如目标使用自签名证书）
+    pub insecure: bool,
+    // 每个参数单独一行，以反斜杠续行
+    pub multiline: bool,
+}
+
+impl Default for CurlOptions {
+    fn default() -> Self {
+        Self {
+            compressed: true,
+            insecure: false,
+            multiline: true,
+        }
+    }
+}
+
+// 生成可在 POSIX shell（bash/zsh/sh）中直接执行的 curl 命令，所有参数都经过单引号转义
+pub fn generate(request: &HttpRequest, options: &CurlOptions) -> String {
+    let url = url::Url::parse(&request.url).ok();
+    let text_body = std::str::from_utf8(&request.body).ok().filter(|b| !b.contains('\0'));
+    let has_body = !request.body.is_empty();
+
+    let mut args: Vec<String> = vec!["curl".to_string()];
+    // URL 中的 [] {} 会被 curl 当作通配模式展开
+    if request.url.contains(['[', ']', '{', '}']) {
+        args.push("-g".to_string());
+    }
+    match (request.method.as_str(), has_body) {
+        ("GET", false) | ("POST", true) => {}
+        ("HEAD", false) => args.push("--head".to_string()),
+        (method, _) => args.push(format!("-X {}", quote(method))),
+    }
+    args.push(quote(&request.url));
+
+    match request.version.as_deref() {
+        Some("HTTP/1.0") => args.push("--http1.0".to_string()),
+        Some("HTTP/2.0") | Some("HTTP/2") => args.push("--http2".to_string()),
+        _ => {}
+    }
+
+    let mut headers: Vec<(&String, &String)> = request
+        .headers
+        .iter()
+        .filter(|(name, value)| {
+            // 与 URL 一致的 Host 由 curl 自动生成，不一致时保留（如按虚拟主机访问）
+            let implied_host = name.eq_ignore_ascii_case("host")
+                && url.as_ref().map(|u| is_url_host(u, value)).unwrap_or(false);
+            let replaced_by_compressed = options.compressed && name.eq_ignore_ascii_case("accept-encoding");
+            !(name.starts_with(':')
+                || ProxyServer::is_hop_by_hop(name)
+                || name.eq_ignore_ascii_case("content-length")
+                || implied_host
+                || replaced_by_compressed)
+        })
+        .collect();
+    headers.sort();
+    for (name, value) in &headers {
+        // 空值的请求头需写成 "Name;"，否则 curl 会把它当作删除内置头
+        let header = if value.is_empty() {
+            format!("{};", name)
+        } else {
+            format!("{}: {}", name, value)
+        };
+        args.push(format!("-H {}", quote(&header)));
+    }
+    let has_content_type = headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+    if has_body && !has_content_type {
+        // --data-* 默认附加 application/x-www-form-urlencoded，原请求没有时去掉
+        args.push(format!("-H {}", quote("Content-Type:")));
+    }
+
+    let mut stdin_body = None;
+    if has_body {
+        match text_body {
+            Some(text) => args.push(format!("--data-raw {}", quote(text))),
+            None => {
+                // 参数中无法传递 NUL 等字节，二进制请求体经 printf 从标准输入传入
+                args.push("--data-binary @-".to_string());
+                stdin_body = Some(printf_literal(&request.body));
+            }
+        }
+    }
+    if options.compressed
+        && request.headers.keys().any(|name| name.eq_ignore_ascii_case("accept-encoding"))
+    {
+        args.push("--compressed".to_string());
+    }
+    if options.insecure {
+        args.push("-k".to_string());
+    }
+
+    let separator = if options.multiline { " \\\n  " } else { " " };
+    let command = args.join(separator);
+    match stdin_body {
+        Some(body) => format!("printf {} | {}", body, command),
+        None => command,
+    }
+}
+
+// 单引号内除单引号外不做任何解释，单引号写作 '\''
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// printf 格式串：可打印 ASCII 原样保留，其余字节用 POSIX 支持的八进制转义
+fn printf_literal(body: &[u8]) -> String {
+    let mut literal = String::from("'");
+    for &byte in body {
+        match byte {
+            0x20..=0x7e if !matches!(byte, b'\'' | b'\\' | b'%') => literal.push(byte as char),
+            _ => {
+                let _ = write!(literal, "\\{:03o}", byte);
+            }
+        }
+    }
+    literal.push('\'');
+    literal
+}
+
+fn is_url_host(url: &url::Url, host: &str) -> bool {
+    let Some(url_host) = url.host_str() else {
+        return false;
+    };
+    match url.port() {
+        Some(port) => host.eq_ignore_ascii_case(&format!("{}:{}", url_host, port)),
+        None => host.eq_ignore_ascii_case(url_host),
+    }
+}
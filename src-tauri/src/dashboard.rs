@@ -0,0 +1,108 @@
+use crate::proxy::HttpTransaction;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+pub const DASHBOARD_EVENT: &str = "dashboard:tick";
+
+// 主机在该时间窗口内有请求即视为活跃
+const ACTIVE_HOST_WINDOW: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatusClassCounts {
+    pub informational: u64,
+    pub success: u64,
+    pub redirection: u64,
+    pub client_error: u64,
+    pub server_error: u64,
+    pub failed: u64,
+}
+
+impl StatusClassCounts {
+    pub fn record(&mut self, status: Option<u16>) {
+        match status {
+            Some(100..=199) => self.informational += 1,
+            Some(200..=299) => self.success += 1,
+            Some(300..=399) => self.redirection += 1,
+            Some(400..=499) => self.client_error += 1,
+            Some(500..=599) => self.server_error += 1,
+            _ => self.failed += 1,
+        }
+    }
+
+    fn total(&self) -> u64 {
+        self.informational + self.success + self.redirection
+            + self.client_error + self.server_error + self.failed
+    }
+}
+
+// 仪表盘推送的增量数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardTick {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub interval_ms: u64,
+    pub new_transactions: u64,
+    pub per_second: f64,
+    pub by_status_class: StatusClassCounts,
+    pub active_hosts: usize,
+    pub total_transactions: u64,
+}
+
+// 实时计数器：在请求入库时累加，每个周期清零
+#[derive(Debug, Default)]
+pub struct LiveStats {
+    pending: StatusClassCounts,
+    host_last_seen: HashMap<String, Instant>,
+    total: u64,
+}
+
+impl LiveStats {
+    pub fn record(&mut self, transaction: &HttpTransaction, host: String) {
+        self.pending.record(transaction.response.as_ref().map(|r| r.status));
+        self.host_last_seen.insert(host, Instant::now());
+        self.total += 1;
+    }
+
+    fn take_tick(&mut self, interval: Duration) -> DashboardTick {
+        let counts = std::mem::take(&mut self.pending);
+        let new_transactions = counts.total();
+
+        let now = Instant::now();
+        self.host_last_seen
+            .retain(|_, seen| now.duration_since(*seen) <= ACTIVE_HOST_WINDOW);
+
+        DashboardTick {
+            timestamp: chrono::Utc::now(),
+            interval_ms: interval.as_millis() as u64,
+            new_transactions,
+            per_second: new_transactions as f64 / interval.as_secs_f64().max(0.001),
+            by_status_class: counts,
+            active_hosts: self.host_last_seen.len(),
+            total_transactions: self.total,
+        }
+    }
+}
+
+pub fn spawn_dashboard_stream(
+    app: AppHandle,
+    live_stats: Arc<RwLock<LiveStats>>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // 第一次 tick 会立即返回，跳过它以保证每个周期长度一致
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            let tick = live_stats.write().await.take_tick(interval);
+            if let Err(e) = app.emit(DASHBOARD_EVENT, tick) {
+                tracing::warn!("Failed to emit dashboard tick: {}", e);
+            }
+        }
+    })
+}
@@ -0,0 +1,64 @@
+use anyhow::{anyhow, Result};
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use std::io::Read;
+
+// 解码后的上限，防止压缩炸弹占满内存
+const MAX_DECODED_BYTES: u64 = 256 * 1024 * 1024;
+
+// 按 Content-Encoding 解码，多重编码按声明的逆序逐层解开
+pub fn decode(body: &[u8], content_encoding: Option<&str>) -> Result<Vec<u8>> {
+    decode_with(body, content_encoding, false)
+}
+
+// 流式转发只记录了响应体前缀，压缩流在中途截断；保留截断前已解出的内容
+pub fn decode_prefix(body: &[u8], content_encoding: Option<&str>) -> Result<Vec<u8>> {
+    decode_with(body, content_encoding, true)
+}
+
+fn decode_with(body: &[u8], content_encoding: Option<&str>, prefix: bool) -> Result<Vec<u8>> {
+    let mut data = body.to_vec();
+    let encodings: Vec<String> = content_encoding
+        .unwrap_or_default()
+        .split(',')
+        .map(|e| e.trim().to_lowercase())
+        .filter(|e| !e.is_empty() && e != "identity")
+        .collect();
+    for encoding in encodings.iter().rev() {
+        data = decode_one(&data, encoding, prefix)?;
+    }
+    Ok(data)
+}
+
+fn decode_one(data: &[u8], encoding: &str, prefix: bool) -> Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    match encoding {
+        "gzip" | "x-gzip" => {
+            read_limited(GzDecoder::new(data), &mut decoded, prefix)?;
+        }
+        // 规范要求 zlib 封装，但不少服务器直接发送裸 deflate 流
+        "deflate" => {
+            if read_limited(ZlibDecoder::new(data), &mut decoded, prefix).is_err() {
+                decoded.clear();
+                read_limited(DeflateDecoder::new(data), &mut decoded, prefix)?;
+            }
+        }
+        "br" => {
+            read_limited(brotli::Decompressor::new(data, 4096), &mut decoded, prefix)?;
+        }
+        other => return Err(anyhow!("Unsupported content encoding: {}", other)),
+    }
+    Ok(decoded)
+}
+
+fn read_limited(reader: impl Read, decoded: &mut Vec<u8>, prefix: bool) -> Result<()> {
+    match reader.take(MAX_DECODED_BYTES + 1).read_to_end(decoded) {
+        Ok(_) => {}
+        // 截断处各解码器报错不一（gzip 为意外结束，brotli 为数据无效），之前解出的内容已写入 decoded
+        Err(_) if prefix && !decoded.is_empty() => {}
+        Err(e) => return Err(e.into()),
+    }
+    if decoded.len() as u64 > MAX_DECODED_BYTES {
+        return Err(anyhow!("Decoded body exceeds {} bytes", MAX_DECODED_BYTES));
+    }
+    Ok(())
+}
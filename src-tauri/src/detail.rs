@@ -0,0 +1,150 @@
+use crate::classify::{self, BodyKind};
+use crate::decoding;
+use crate::dns::DnsResolution;
+use crate::geoip::GeoInfo;
+use crate::proxy::{find_header, HttpTransaction, StoredEncoding};
+use crate::redaction::PiiFinding;
+use crate::scoring::RiskScore;
+use crate::upstream::Timings;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyPart {
+    Request,
+    Response,
+}
+
+impl BodyPart {
+    // 存储的消息体及对应的头部；没有响应的事务返回 None
+    pub fn select(self, t: &HttpTransaction) -> Option<(&[u8], &HashMap<String, String>)> {
+        match self {
+            Self::Request => Some((&t.request.body, &t.request.headers)),
+            Self::Response => t.response.as_ref().map(|r| (r.body.as_slice(), &r.headers)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyDetail {
+    // 文本内容原样返回，二进制内容为 base64
+    pub text: String,
+    pub base64: bool,
+    pub kind: BodyKind,
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+    // 存储的字节数与解码 Content-Encoding 后的字节数
+    pub size: usize,
+    pub decoded_size: usize,
+    // 解码失败时返回原始字节
+    pub decode_error: Option<String>,
+    pub truncated: bool,
+}
+
+// 详情面板所需的全部内容，一次返回
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionDetail {
+    pub id: String,
+    pub method: String,
+    pub url: String,
+    pub timestamp: String,
+    pub duration: Option<u64>,
+    pub request_version: Option<String>,
+    pub request_headers: HashMap<String, String>,
+    pub request_body: BodyDetail,
+    pub status: Option<u16>,
+    pub response_version: Option<String>,
+    pub response_headers: Option<HashMap<String, String>>,
+    pub response_body: Option<BodyDetail>,
+    pub tags: Vec<String>,
+    pub is_favorite: bool,
+    pub replay_of: Option<String>,
+    pub applied_rules: Vec<String>,
+    pub security_findings: Vec<String>,
+    pub risk: Option<RiskScore>,
+    pub notes: Option<String>,
+    pub pii_detected: Vec<PiiFinding>,
+    pub geo: Option<GeoInfo>,
+    pub dns: Option<DnsResolution>,
+    pub timings: Option<Timings>,
+}
+
+impl From<&HttpTransaction> for TransactionDetail {
+    fn from(t: &HttpTransaction) -> Self {
+        let request = &t.request;
+        let response = t.response.as_ref();
+        Self {
+            id: t.id.clone(),
+            method: request.method.clone(),
+            url: request.url.clone(),
+            timestamp: request.timestamp.to_rfc3339(),
+            duration: t.duration.map(|d| d.as_millis() as u64),
+            request_version: request.version.clone(),
+            request_headers: request.headers.clone(),
+            request_body: body_detail(&request.body, &request.headers, t.request_kind(), None, false),
+            status: response.map(|r| r.status),
+            response_version: response.and_then(|r| r.version.clone()),
+            response_headers: response.map(|r| r.headers.clone()),
+            response_body: response.map(|r| {
+                let truncated = t.tags.iter().any(|tag| tag == "body-truncated");
+                body_detail(&r.body, &r.headers, t.response_kind(), t.response_encoding.as_ref(), truncated)
+            }),
+            tags: t.tags.clone(),
+            is_favorite: t.is_favorite,
+            replay_of: t.replay_of.clone(),
+            applied_rules: t.applied_rules.clone(),
+            security_findings: t.security_findings.clone(),
+            risk: t.risk.clone(),
+            notes: t.notes.clone(),
+            pii_detected: t.pii_detected.clone(),
+            geo: t.geo.clone(),
+            dns: t.dns.clone(),
+            timings: t.timings,
+        }
+    }
+}
+
+fn body_detail(
+    body: &[u8],
+    headers: &HashMap<String, String>,
+    kind: BodyKind,
+    stored: Option<&StoredEncoding>,
+    truncated: bool,
+) -> BodyDetail {
+    let content_type = find_header(headers, "content-type");
+    // 入库时已解码的响应体直接返回，大小按传输时的编码字节数计
+    if let Some(stored) = stored {
+        let mut detail = body_detail(body, headers, kind, None, truncated);
+        detail.content_encoding = Some(stored.content_encoding.clone());
+        detail.size = stored.encoded_size;
+        return detail;
+    }
+    let content_encoding = find_header(headers, "content-encoding");
+    let (decoded, kind, decode_error) = match decoding::decode(body, content_encoding) {
+        // 入库时按编码后的字节嗅探，解码后重新判断内容类型
+        Ok(decoded) if content_encoding.is_some() => {
+            let kind = classify::classify(&decoded, content_type);
+            (decoded, kind, None)
+        }
+        Ok(decoded) => (decoded, kind, None),
+        Err(e) => (body.to_vec(), kind, Some(e.to_string())),
+    };
+    let base64 = kind.is_binary();
+    BodyDetail {
+        text: if base64 {
+            general_purpose::STANDARD.encode(&decoded)
+        } else {
+            String::from_utf8_lossy(&decoded).into_owned()
+        },
+        base64,
+        kind,
+        content_type: content_type.map(str::to_string),
+        content_encoding: content_encoding.map(str::to_string),
+        size: body.len(),
+        decoded_size: decoded.len(),
+        decode_error,
+        truncated,
+    }
+}
@@ -0,0 +1,417 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::RwLock;
+
+const MAX_CACHE_ENTRIES: usize = 4096;
+// 解析失败的结果短暂缓存，避免转发时对同一主机再次等待超时
+const NEGATIVE_TTL: Duration = Duration::from_secs(5);
+const DNS_PORT: u16 = 53;
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const DOH_CONTENT_TYPE: &str = "application/dns-message";
+
+fn default_cache_ttl() -> u64 {
+    300
+}
+
+fn default_timeout_ms() -> u64 {
+    3000
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ResolverConfig {
+    #[default]
+    System,
+    // 自定义 DNS 服务器，如 "1.1.1.1" 或 "10.0.0.2:5353"；响应被截断时改用 TCP
+    Server { address: String },
+    // DNS-over-HTTPS（RFC 8484），如 "https://cloudflare-dns.com/dns-query"
+    Https { url: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsSettings {
+    #[serde(default)]
+    pub resolver: ResolverConfig,
+    // 缓存时间上限，取记录 TTL 与该值中的较小者；系统解析没有 TTL，直接使用该值；为 0 时不缓存
+    #[serde(default = "default_cache_ttl")]
+    pub cache_ttl_secs: u64,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for DnsSettings {
+    fn default() -> Self {
+        Self {
+            resolver: ResolverConfig::default(),
+            cache_ttl_secs: default_cache_ttl(),
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+impl DnsSettings {
+    pub fn validated(mut self) -> Result<Self> {
+        match &mut self.resolver {
+            ResolverConfig::System => {}
+            ResolverConfig::Server { address } => {
+                *address = server_address(address.trim())?.to_string();
+            }
+            ResolverConfig::Https { url } => {
+                let parsed = url::Url::parse(url.trim()).map_err(|e| anyhow!("Invalid DNS-over-HTTPS URL {}: {}", url, e))?;
+                if parsed.scheme() != "https" {
+                    return Err(anyhow!("DNS-over-HTTPS URL must use https: {}", url));
+                }
+                *url = parsed.to_string();
+            }
+        }
+        self.timeout_ms = self.timeout_ms.clamp(100, 30_000);
+        Ok(self)
+    }
+
+    fn label(&self) -> String {
+        match &self.resolver {
+            ResolverConfig::System => "system".to_string(),
+            ResolverConfig::Server { address } => address.clone(),
+            ResolverConfig::Https { url } => url.clone(),
+        }
+    }
+}
+
+// 接受 IP 或 IP:端口，IPv6 带端口时写作 [::1]:5353
+fn server_address(address: &str) -> Result<SocketAddr> {
+    address
+        .parse::<SocketAddr>()
+        .or_else(|_| address.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, DNS_PORT)))
+        .map_err(|_| anyhow!("Invalid DNS server address: {}", address))
+}
+
+// 一次上游连接前的域名解析；命中缓存时 duration_ms 为 0
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsResolution {
+    pub host: String,
+    pub addresses: Vec<IpAddr>,
+    pub resolver: String,
+    pub duration_ms: f64,
+    pub cached: bool,
+    // 记录中最小的 TTL，系统解析时为空
+    pub ttl: Option<u32>,
+    pub resolved_at: chrono::DateTime<chrono::Utc>,
+    pub error: Option<String>,
+}
+
+struct CacheEntry {
+    resolution: DnsResolution,
+    expires: Instant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsCacheEntry {
+    pub host: String,
+    pub addresses: Vec<IpAddr>,
+    pub resolver: String,
+    pub expires_in_secs: u64,
+    pub error: Option<String>,
+}
+
+// 转发客户端和 GeoIP 查询共用的解析器
+#[derive(Clone)]
+pub struct DnsResolver {
+    settings: Arc<RwLock<DnsSettings>>,
+    cache: Arc<RwLock<HashMap<String, CacheEntry>>>,
+    // DoH 请求使用独立的客户端，其自身的域名走系统解析
+    doh_client: reqwest::Client,
+}
+
+impl Default for DnsResolver {
+    fn default() -> Self {
+        Self {
+            settings: Arc::new(RwLock::new(DnsSettings::default())),
+            cache: Arc::new(RwLock::new(HashMap::new())),
+            doh_client: reqwest::Client::builder().no_proxy().build().unwrap_or_default(),
+        }
+    }
+}
+
+impl DnsResolver {
+    pub async fn settings(&self) -> DnsSettings {
+        self.settings.read().await.clone()
+    }
+
+    // 更换解析方式后旧缓存不再可信，一并清空
+    pub async fn set_settings(&self, settings: DnsSettings) -> Result<DnsSettings> {
+        let settings = settings.validated()?;
+        let previous = std::mem::replace(&mut *self.settings.write().await, settings.clone());
+        if previous.resolver != settings.resolver {
+            self.clear_cache().await;
+        }
+        Ok(settings)
+    }
+
+    pub async fn clear_cache(&self) {
+        self.cache.write().await.clear();
+    }
+
+    pub async fn cache_entries(&self) -> Vec<DnsCacheEntry> {
+        let now = Instant::now();
+        let mut entries: Vec<DnsCacheEntry> = self
+            .cache
+            .read()
+            .await
+            .iter()
+            .filter(|(_, e)| e.expires > now)
+            .map(|(host, e)| DnsCacheEntry {
+                host: host.clone(),
+                addresses: e.resolution.addresses.clone(),
+                resolver: e.resolution.resolver.clone(),
+                expires_in_secs: (e.expires - now).as_secs(),
+                error: e.resolution.error.clone(),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.host.cmp(&b.host));
+        entries
+    }
+
+    // URL 主机为 IP 字面量时不需要解析，返回 None
+    pub async fn lookup_url(&self, url: &str) -> Option<DnsResolution> {
+        match url::Url::parse(url).ok()?.host()? {
+            url::Host::Domain(host) => Some(self.lookup(host).await),
+            _ => None,
+        }
+    }
+
+    // 失败时返回带 error 的结果，不返回 Err
+    pub async fn lookup(&self, host: &str) -> DnsResolution {
+        let host = host.trim_end_matches('.').to_lowercase();
+        if let Some(entry) = self.cache.read().await.get(&host) {
+            if entry.expires > Instant::now() {
+                return DnsResolution { cached: true, duration_ms: 0.0, ..entry.resolution.clone() };
+            }
+        }
+
+        let settings = self.settings().await;
+        let start = Instant::now();
+        let timeout = Duration::from_millis(settings.timeout_ms);
+        let result = match tokio::time::timeout(timeout, self.query(&settings, &host)).await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow!("DNS lookup of {} timed out after {} ms", host, settings.timeout_ms)),
+        };
+        let (addresses, ttl, error) = match result {
+            Ok((addresses, _)) if addresses.is_empty() => (addresses, None, Some(format!("No addresses found for {}", host))),
+            Ok((addresses, ttl)) => (addresses, ttl, None),
+            Err(e) => (Vec::new(), None, Some(e.to_string())),
+        };
+        let resolution = DnsResolution {
+            host: host.clone(),
+            addresses,
+            resolver: settings.label(),
+            duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+            cached: false,
+            ttl,
+            resolved_at: chrono::Utc::now(),
+            error,
+        };
+
+        let lifetime = if resolution.error.is_some() {
+            NEGATIVE_TTL
+        } else {
+            let max = settings.cache_ttl_secs;
+            Duration::from_secs(ttl.map(|t| (t as u64).min(max)).unwrap_or(max))
+        };
+        if settings.cache_ttl_secs > 0 && !lifetime.is_zero() {
+            let mut cache = self.cache.write().await;
+            if cache.len() >= MAX_CACHE_ENTRIES {
+                let now = Instant::now();
+                cache.retain(|_, e| e.expires > now);
+                if cache.len() >= MAX_CACHE_ENTRIES {
+                    cache.clear();
+                }
+            }
+            cache.insert(host, CacheEntry { resolution: resolution.clone(), expires: Instant::now() + lifetime });
+        }
+        resolution
+    }
+
+    async fn query(&self, settings: &DnsSettings, host: &str) -> Result<(Vec<IpAddr>, Option<u32>)> {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok((vec![ip], None));
+        }
+        match &settings.resolver {
+            ResolverConfig::System => {
+                let addrs = tokio::net::lookup_host((host, 0)).await?;
+                Ok((addrs.map(|a| a.ip()).collect(), None))
+            }
+            ResolverConfig::Server { address } => {
+                let server = server_address(address)?;
+                let (v4, v6) = tokio::join!(query_server(server, host, TYPE_A), query_server(server, host, TYPE_AAAA));
+                merge(v4, v6)
+            }
+            ResolverConfig::Https { url } => {
+                let (v4, v6) = tokio::join!(self.query_https(url, host, TYPE_A), self.query_https(url, host, TYPE_AAAA));
+                merge(v4, v6)
+            }
+        }
+    }
+
+    async fn query_https(&self, url: &str, host: &str, qtype: u16) -> Result<(Vec<IpAddr>, Option<u32>)> {
+        // RFC 8484 建议 ID 置 0，便于 HTTP 缓存
+        let response = self
+            .doh_client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, DOH_CONTENT_TYPE)
+            .header(reqwest::header::ACCEPT, DOH_CONTENT_TYPE)
+            .body(build_query(0, host, qtype)?)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("DNS-over-HTTPS server returned {}", response.status()));
+        }
+        parse_response(&response.bytes().await?, 0)
+    }
+}
+
+// 一种记录类型查询失败而另一种成功时，仍使用成功的结果；IPv4 地址排在前面
+fn merge(
+    v4: Result<(Vec<IpAddr>, Option<u32>)>,
+    v6: Result<(Vec<IpAddr>, Option<u32>)>,
+) -> Result<(Vec<IpAddr>, Option<u32>)> {
+    match (v4, v6) {
+        (Err(e), Err(_)) => Err(e),
+        (v4, v6) => {
+            let (mut addresses, ttl4) = v4.unwrap_or_default();
+            let (v6, ttl6) = v6.unwrap_or_default();
+            addresses.extend(v6);
+            let ttl = [ttl4, ttl6].into_iter().flatten().min();
+            Ok((addresses, ttl))
+        }
+    }
+}
+
+async fn query_server(server: SocketAddr, host: &str, qtype: u16) -> Result<(Vec<IpAddr>, Option<u32>)> {
+    let id: u16 = rand_id();
+    let query = build_query(id, host, qtype)?;
+    let bind: SocketAddr = if server.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(server).await?;
+    socket.send(&query).await?;
+    let mut buf = vec![0u8; 4096];
+    let response = loop {
+        let len = socket.recv(&mut buf).await?;
+        // 忽略 ID 不匹配的迟到响应
+        if len >= 2 && u16::from_be_bytes([buf[0], buf[1]]) == id {
+            break &buf[..len];
+        }
+    };
+    if response.len() >= 3 && response[2] & 0x02 != 0 {
+        return query_tcp(server, &query, id).await;
+    }
+    parse_response(response, id)
+}
+
+async fn query_tcp(server: SocketAddr, query: &[u8], id: u16) -> Result<(Vec<IpAddr>, Option<u32>)> {
+    let mut stream = TcpStream::connect(server).await?;
+    let mut framed = (query.len() as u16).to_be_bytes().to_vec();
+    framed.extend_from_slice(query);
+    stream.write_all(&framed).await?;
+    let len = stream.read_u16().await? as usize;
+    let mut response = vec![0u8; len];
+    stream.read_exact(&mut response).await?;
+    parse_response(&response, id)
+}
+
+fn rand_id() -> u16 {
+    let bytes = uuid::Uuid::new_v4().into_bytes();
+    u16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+pub fn build_query(id: u16, host: &str, qtype: u16) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(host.len() + 18);
+    out.extend_from_slice(&id.to_be_bytes());
+    // 标准查询，期望递归
+    out.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in host.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(anyhow!("Invalid host name: {}", host));
+        }
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    out.extend_from_slice(&qtype.to_be_bytes());
+    out.extend_from_slice(&1u16.to_be_bytes());
+    Ok(out)
+}
+
+// 只取应答区中的 A/AAAA 记录，CNAME 链由递归服务器展开
+pub fn parse_response(data: &[u8], id: u16) -> Result<(Vec<IpAddr>, Option<u32>)> {
+    let truncated = || anyhow!("Truncated DNS response");
+    if data.len() < 12 {
+        return Err(truncated());
+    }
+    let u16_at = |offset: usize| -> Result<u16> {
+        data.get(offset..offset + 2).map(|b| u16::from_be_bytes([b[0], b[1]])).ok_or_else(truncated)
+    };
+    if u16_at(0)? != id {
+        return Err(anyhow!("DNS response ID mismatch"));
+    }
+    if data[2] & 0x80 == 0 {
+        return Err(anyhow!("DNS message is not a response"));
+    }
+    match data[3] & 0x0F {
+        0 => {}
+        3 => return Err(anyhow!("Domain does not exist (NXDOMAIN)")),
+        2 => return Err(anyhow!("DNS server failure (SERVFAIL)")),
+        5 => return Err(anyhow!("DNS query refused")),
+        rcode => return Err(anyhow!("DNS query failed with rcode {}", rcode)),
+    }
+    let questions = u16_at(4)?;
+    let answers = u16_at(6)?;
+
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = skip_name(data, offset)? + 4;
+    }
+    let mut addresses = Vec::new();
+    let mut ttl: Option<u32> = None;
+    for _ in 0..answers {
+        offset = skip_name(data, offset)?;
+        let rtype = u16_at(offset)?;
+        let record_ttl = data
+            .get(offset + 4..offset + 8)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(truncated)?;
+        let rdlength = u16_at(offset + 8)? as usize;
+        let rdata = data.get(offset + 10..offset + 10 + rdlength).ok_or_else(truncated)?;
+        offset += 10 + rdlength;
+        let ip = match (rtype, rdata.len()) {
+            (TYPE_A, 4) => IpAddr::from(<[u8; 4]>::try_from(rdata)?),
+            (TYPE_AAAA, 16) => IpAddr::from(<[u8; 16]>::try_from(rdata)?),
+            _ => continue,
+        };
+        addresses.push(ip);
+        ttl = Some(ttl.map_or(record_ttl, |t| t.min(record_ttl)));
+    }
+    Ok((addresses, ttl))
+}
+
+// 返回名称之后的偏移；遇到压缩指针时名称到此结束
+fn skip_name(data: &[u8], mut offset: usize) -> Result<usize> {
+    loop {
+        let len = *data.get(offset).ok_or_else(|| anyhow!("Truncated DNS response"))?;
+        match len {
+            0 => return Ok(offset + 1),
+            l if l & 0xC0 == 0xC0 => return Ok(offset + 2),
+            l => offset += 1 + l as usize,
+        }
+    }
+}
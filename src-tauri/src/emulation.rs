@@ -0,0 +1,153 @@
+use crate::proxy::HttpRequest;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// 启用任一设备配置时都会先清除原始的客户端提示头，避免与新的 User-Agent 矛盾
+const CLIENT_HINT_PREFIX: &str = "sec-ch-ua";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceProfile {
+    pub id: String,
+    pub name: String,
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub remove_headers: Vec<String>,
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+// host_profiles 的键支持精确主机名和以 "." 开头的子域名后缀 (.example.com)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmulationSettings {
+    pub global_profile: Option<String>,
+    pub host_profiles: HashMap<String, String>,
+    pub custom_profiles: Vec<DeviceProfile>,
+}
+
+pub fn builtin_profiles() -> Vec<DeviceProfile> {
+    vec![
+        DeviceProfile {
+            id: "iphone-safari".to_string(),
+            name: "iPhone Safari".to_string(),
+            headers: HashMap::from([
+                (
+                    "user-agent".to_string(),
+                    "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1".to_string(),
+                ),
+                ("accept-language".to_string(), "en-US,en;q=0.9".to_string()),
+            ]),
+            // Safari 不发送客户端提示头
+            remove_headers: Vec::new(),
+            builtin: true,
+        },
+        DeviceProfile {
+            id: "android-chrome".to_string(),
+            name: "Android Chrome".to_string(),
+            headers: HashMap::from([
+                (
+                    "user-agent".to_string(),
+                    "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36".to_string(),
+                ),
+                ("accept-language".to_string(), "en-US,en;q=0.9".to_string()),
+                (
+                    "sec-ch-ua".to_string(),
+                    "\"Not/A)Brand\";v=\"8\", \"Chromium\";v=\"126\", \"Google Chrome\";v=\"126\"".to_string(),
+                ),
+                ("sec-ch-ua-mobile".to_string(), "?1".to_string()),
+                ("sec-ch-ua-platform".to_string(), "\"Android\"".to_string()),
+            ]),
+            remove_headers: Vec::new(),
+            builtin: true,
+        },
+        DeviceProfile {
+            id: "curl".to_string(),
+            name: "curl".to_string(),
+            headers: HashMap::from([
+                ("user-agent".to_string(), "curl/8.7.1".to_string()),
+                ("accept".to_string(), "*/*".to_string()),
+            ]),
+            remove_headers: vec!["accept-language".to_string(), "accept-encoding".to_string()],
+            builtin: true,
+        },
+    ]
+}
+
+impl EmulationSettings {
+    pub fn profiles(&self) -> Vec<DeviceProfile> {
+        let mut profiles = builtin_profiles();
+        profiles.extend(self.custom_profiles.iter().cloned());
+        profiles
+    }
+
+    pub fn find_profile(&self, profile_id: &str) -> Option<DeviceProfile> {
+        // 自定义配置可覆盖同名内置配置
+        self.custom_profiles
+            .iter()
+            .find(|p| p.id == profile_id)
+            .cloned()
+            .or_else(|| builtin_profiles().into_iter().find(|p| p.id == profile_id))
+    }
+
+    // 主机配置优先于全局配置；精确匹配优先于最长的后缀匹配
+    pub fn profile_id_for(&self, host: &str) -> Option<&str> {
+        let host = host.to_lowercase();
+        if let Some(id) = self.host_profiles.get(&host) {
+            return Some(id);
+        }
+
+        self.host_profiles
+            .iter()
+            .filter(|(pattern, _)| {
+                pattern.starts_with('.') && (host.ends_with(pattern.as_str()) || host == pattern[1..])
+            })
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, id)| id.as_str())
+            .or(self.global_profile.as_deref())
+    }
+
+    // 返回实际生效的配置 id
+    pub fn apply(&self, host: &str, request: &mut HttpRequest) -> Option<String> {
+        let profile = self.find_profile(self.profile_id_for(host)?)?;
+
+        request.headers.retain(|name, _| {
+            let name = name.to_lowercase();
+            !name.starts_with(CLIENT_HINT_PREFIX)
+                && !profile.headers.keys().any(|h| h.eq_ignore_ascii_case(&name))
+                && !profile.remove_headers.iter().any(|h| h.eq_ignore_ascii_case(&name))
+        });
+        for (name, value) in &profile.headers {
+            request.headers.insert(name.to_lowercase(), value.clone());
+        }
+
+        Some(profile.id)
+    }
+
+    pub fn set_profile(&mut self, host: Option<&str>, profile_id: Option<String>) {
+        match (host, profile_id) {
+            (Some(host), Some(id)) => {
+                self.host_profiles.insert(host.to_lowercase(), id);
+            }
+            (Some(host), None) => {
+                self.host_profiles.remove(&host.to_lowercase());
+            }
+            (None, id) => self.global_profile = id,
+        }
+    }
+
+    pub fn save_custom_profile(&mut self, mut profile: DeviceProfile) {
+        profile.builtin = false;
+        self.custom_profiles.retain(|p| p.id != profile.id);
+        self.custom_profiles.push(profile);
+    }
+
+    pub fn remove_custom_profile(&mut self, profile_id: &str) {
+        self.custom_profiles.retain(|p| p.id != profile_id);
+        // 没有同名内置配置可回退时，清理引用该配置的映射
+        if self.find_profile(profile_id).is_none() {
+            self.host_profiles.retain(|_, id| id != profile_id);
+            if self.global_profile.as_deref() == Some(profile_id) {
+                self.global_profile = None;
+            }
+        }
+    }
+}
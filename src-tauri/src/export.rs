@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const REDACTED_PLACEHOLDER: &str = "[redacted]";
+
+// 导出时请求体/响应体的处理方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BodyInclusion {
+    // 全部以 base64 保存，保证字节级一致
+    Include,
+    Omit,
+    // 保留大小和类型，内容替换为占位符
+    Redact,
+    // 文本原样保存，仅二进制内容使用 base64
+    #[default]
+    Base64BinaryOnly,
+}
+
+// 各导出格式共用的选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub bodies: BodyInclusion,
+    // 耗时保留的小数位数（毫秒），0 为整数毫秒
+    pub timing_precision: u8,
+    // 单个请求体/响应体保留的最大字节数，超出部分截断
+    pub max_body_bytes: Option<usize>,
+}
+
+pub struct ExportedBody {
+    pub text: String,
+    // 为 Some("base64") 时 text 为 base64 编码
+    pub encoding: Option<&'static str>,
+    pub truncated: bool,
+    pub redacted: bool,
+}
+
+impl ExportOptions {
+    pub fn round_ms(&self, duration: Duration) -> f64 {
+        let factor = 10f64.powi(self.timing_precision.min(6) as i32);
+        (duration.as_secs_f64() * 1000.0 * factor).round() / factor
+    }
+
+    pub fn includes_bodies(&self) -> bool {
+        !matches!(self.bodies, BodyInclusion::Omit | BodyInclusion::Redact)
+    }
+
+    // 空内容或选择不导出时返回 None
+    pub fn encode_body(&self, body: &[u8]) -> Option<ExportedBody> {
+        use base64::{Engine as _, engine::general_purpose};
+
+        if body.is_empty() || self.bodies == BodyInclusion::Omit {
+            return None;
+        }
+        if self.bodies == BodyInclusion::Redact {
+            return Some(ExportedBody {
+                text: REDACTED_PLACEHOLDER.to_string(),
+                encoding: None,
+                truncated: false,
+                redacted: true,
+            });
+        }
+        let limit = self.max_body_bytes.unwrap_or(usize::MAX).min(body.len());
+        let truncated = limit < body.len();
+
+        match (self.bodies, std::str::from_utf8(body)) {
+            (BodyInclusion::Base64BinaryOnly, Ok(text)) => {
+                // 截断位置回退到字符边界
+                let mut end = limit;
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                Some(ExportedBody {
+                    text: text[..end].to_string(),
+                    encoding: None,
+                    truncated,
+                    redacted: false,
+                })
+            }
+            _ => Some(ExportedBody {
+                text: general_purpose::STANDARD.encode(&body[..limit]),
+                encoding: Some("base64"),
+                truncated,
+                redacted: false,
+            }),
+        }
+    }
+}
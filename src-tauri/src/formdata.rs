@@ -0,0 +1,135 @@
+use crate::decoding;
+use crate::proxy::find_header;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// 文本字段预览的最大字符数
+pub const PREVIEW_CHARS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormEncoding {
+    Urlencoded,
+    Multipart,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormField {
+    pub name: String,
+    // 仅文件上传字段带有文件名和内容类型
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub size: usize,
+    // 二进制内容没有预览
+    pub preview: Option<String>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedBody {
+    pub encoding: FormEncoding,
+    pub fields: Vec<FormField>,
+}
+
+pub fn parse(body: &[u8], headers: &HashMap<String, String>) -> Result<ParsedBody> {
+    let content_type = find_header(headers, "content-type").unwrap_or_default();
+    let mime = content_type.split(';').next().unwrap_or_default().trim().to_lowercase();
+    let body = decoding::decode(body, find_header(headers, "content-encoding"))?;
+    match mime.as_str() {
+        "application/x-www-form-urlencoded" => Ok(ParsedBody {
+            encoding: FormEncoding::Urlencoded,
+            fields: url::form_urlencoded::parse(&body)
+                .map(|(name, value)| field(name.into_owned(), None, None, value.as_bytes()))
+                .collect(),
+        }),
+        "multipart/form-data" => {
+            let boundary = param(content_type, "boundary")
+                .ok_or_else(|| anyhow!("multipart/form-data body has no boundary"))?;
+            Ok(ParsedBody { encoding: FormEncoding::Multipart, fields: parse_multipart(&body, boundary)? })
+        }
+        _ => Err(anyhow!("Unsupported body type: {}", if mime.is_empty() { "none" } else { &mime })),
+    }
+}
+
+fn param<'a>(header: &'a str, name: &str) -> Option<&'a str> {
+    header.split(';').skip(1).find_map(|part| {
+        let (key, value) = part.split_once('=')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim().trim_matches('"'))
+    })
+}
+
+// 按 RFC 7578 切分各个部分；结束分隔符缺失时保留已解析的部分
+fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<FormField>> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut fields = Vec::new();
+    let mut rest = match find(body, &delimiter) {
+        Some(start) => &body[start + delimiter.len()..],
+        None => return Err(anyhow!("Boundary not found in multipart body")),
+    };
+    while !rest.starts_with(b"--") {
+        let part_start = skip_line_break(rest);
+        let part_end = find(part_start, &delimiter).unwrap_or(part_start.len());
+        // 分隔符前的换行属于分隔符本身
+        let part = part_start[..part_end]
+            .strip_suffix(b"\r\n")
+            .or_else(|| part_start[..part_end].strip_suffix(b"\n"))
+            .unwrap_or(&part_start[..part_end]);
+        if let Some(field) = parse_part(part) {
+            fields.push(field);
+        }
+        if part_end == part_start.len() {
+            break;
+        }
+        rest = &part_start[part_end + delimiter.len()..];
+    }
+    Ok(fields)
+}
+
+fn parse_part(part: &[u8]) -> Option<FormField> {
+    let (head, content) = match find(part, b"\r\n\r\n") {
+        Some(i) => (&part[..i], &part[i + 4..]),
+        None => {
+            let i = find(part, b"\n\n")?;
+            (&part[..i], &part[i + 2..])
+        }
+    };
+    let head = String::from_utf8_lossy(head);
+    let mut disposition = None;
+    let mut content_type = None;
+    for line in head.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if name.trim().eq_ignore_ascii_case("content-disposition") {
+            disposition = Some(value.trim().to_string());
+        } else if name.trim().eq_ignore_ascii_case("content-type") {
+            content_type = Some(value.trim().to_string());
+        }
+    }
+    let disposition = disposition?;
+    let name = param(&disposition, "name").unwrap_or_default().to_string();
+    let filename = param(&disposition, "filename").map(str::to_string);
+    Some(field(name, filename, content_type, content))
+}
+
+fn field(name: String, filename: Option<String>, content_type: Option<String>, content: &[u8]) -> FormField {
+    let text = std::str::from_utf8(content).ok().filter(|text| !text.contains('\0'));
+    let truncated = text.is_some_and(|text| text.chars().count() > PREVIEW_CHARS);
+    FormField {
+        name,
+        filename,
+        content_type,
+        size: content.len(),
+        preview: text.map(|text| text.chars().take(PREVIEW_CHARS).collect()),
+        truncated,
+    }
+}
+
+fn skip_line_break(data: &[u8]) -> &[u8] {
+    data.strip_prefix(b"\r\n").or_else(|| data.strip_prefix(b"\n")).unwrap_or(data)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
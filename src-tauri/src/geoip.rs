@@ -0,0 +1,386 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+// 数据目录下的该子目录中的 .mmdb 文件在启动时自动加载
+pub const GEOIP_DIR: &str = "geoip";
+
+// 元数据位于文件末尾，以该标记开头
+const METADATA_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+// 元数据只可能出现在文件最后 128KB 内
+const METADATA_MAX_SIZE: usize = 128 * 1024;
+// 搜索树与数据区之间的 16 字节分隔
+const DATA_SECTION_SEPARATOR: usize = 16;
+// 解码时允许的最大嵌套深度，防止损坏的文件造成无限递归
+const MAX_DEPTH: usize = 32;
+
+// 事务上游地址的地理与网络归属
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeoInfo {
+    pub ip: String,
+    // ISO 3166-1 两位国家代码
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub asn: Option<u32>,
+    pub organization: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeoIpDatabaseInfo {
+    pub path: String,
+    // 如 GeoLite2-Country、GeoLite2-ASN
+    pub database_type: String,
+    pub ip_version: u16,
+    pub node_count: u32,
+    pub build_epoch: u64,
+}
+
+// MaxMind DB 格式的只读数据库，整个文件读入内存
+pub struct MmdbReader {
+    data: Vec<u8>,
+    info: GeoIpDatabaseInfo,
+    record_size: u16,
+    data_start: usize,
+    // IPv6 树中 IPv4 地址（::/96）所在的节点
+    ipv4_start: u32,
+}
+
+impl MmdbReader {
+    pub fn open(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        Self::from_bytes(data, path.display().to_string())
+    }
+
+    pub fn from_bytes(data: Vec<u8>, path: String) -> Result<Self> {
+        let search_from = data.len().saturating_sub(METADATA_MAX_SIZE);
+        let marker = data[search_from..]
+            .windows(METADATA_MARKER.len())
+            .rposition(|w| w == METADATA_MARKER)
+            .map(|p| search_from + p)
+            .ok_or_else(|| anyhow!("Not a MaxMind DB file: metadata marker not found"))?;
+        let metadata_start = marker + METADATA_MARKER.len();
+        let (metadata, _) = Decoder { data: &data[metadata_start..], depth: 0 }.decode(0)?;
+
+        let number = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(Value::as_u64)
+                .ok_or_else(|| anyhow!("MaxMind DB metadata is missing {}", key))
+        };
+        let node_count = number("node_count")? as u32;
+        let record_size = number("record_size")? as u16;
+        let ip_version = number("ip_version")? as u16;
+        if !matches!(record_size, 24 | 28 | 32) {
+            return Err(anyhow!("Unsupported MaxMind DB record size: {}", record_size));
+        }
+        let tree_size = node_count as usize * record_size as usize / 4;
+        let data_start = tree_size + DATA_SECTION_SEPARATOR;
+        if data_start > marker {
+            return Err(anyhow!("MaxMind DB search tree exceeds file size"));
+        }
+
+        let mut reader = Self {
+            info: GeoIpDatabaseInfo {
+                path,
+                database_type: metadata.get("database_type").and_then(Value::as_str).unwrap_or_default().to_string(),
+                ip_version,
+                node_count,
+                build_epoch: metadata.get("build_epoch").and_then(Value::as_u64).unwrap_or(0),
+            },
+            data,
+            record_size,
+            data_start,
+            ipv4_start: 0,
+        };
+        if ip_version == 6 {
+            let mut node = 0;
+            for _ in 0..96 {
+                if node >= node_count {
+                    break;
+                }
+                node = reader.record(node, 0)?;
+            }
+            reader.ipv4_start = node;
+        }
+        Ok(reader)
+    }
+
+    pub fn info(&self) -> &GeoIpDatabaseInfo {
+        &self.info
+    }
+
+    // 读取节点的左（bit=0）或右（bit=1）记录
+    fn record(&self, node: u32, bit: u8) -> Result<u32> {
+        let node_bytes = self.record_size as usize / 4;
+        let offset = node as usize * node_bytes;
+        let b = self
+            .data
+            .get(offset..offset + node_bytes)
+            .ok_or_else(|| anyhow!("MaxMind DB node {} out of range", node))?;
+        let be = |bytes: &[u8]| bytes.iter().fold(0u32, |acc, &x| (acc << 8) | x as u32);
+        Ok(match (self.record_size, bit) {
+            (24, 0) => be(&b[0..3]),
+            (24, _) => be(&b[3..6]),
+            (28, 0) => ((b[3] as u32 & 0xF0) << 20) | be(&b[0..3]),
+            (28, _) => ((b[3] as u32 & 0x0F) << 24) | be(&b[4..7]),
+            (_, 0) => be(&b[0..4]),
+            (_, _) => be(&b[4..8]),
+        })
+    }
+
+    // 未收录的地址返回 None
+    pub fn lookup(&self, ip: IpAddr) -> Result<Option<Value>> {
+        let (bits, mut node): (Vec<u8>, u32) = match ip {
+            IpAddr::V4(v4) if self.info.ip_version == 6 => (v4.octets().to_vec(), self.ipv4_start),
+            IpAddr::V4(v4) => (v4.octets().to_vec(), 0),
+            IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+                Some(v4) => return self.lookup(IpAddr::V4(v4)),
+                None if self.info.ip_version == 4 => return Ok(None),
+                None => (v6.octets().to_vec(), 0),
+            },
+        };
+        let node_count = self.info.node_count;
+        for i in 0..bits.len() * 8 {
+            if node >= node_count {
+                break;
+            }
+            let bit = (bits[i / 8] >> (7 - i % 8)) & 1;
+            node = self.record(node, bit)?;
+        }
+        if node == node_count {
+            return Ok(None);
+        }
+        if node < node_count {
+            return Err(anyhow!("MaxMind DB search tree is malformed"));
+        }
+        let offset = ((node - node_count) as usize)
+            .checked_sub(DATA_SECTION_SEPARATOR)
+            .ok_or_else(|| anyhow!("MaxMind DB search tree is malformed"))?;
+        let section = &self.data[self.data_start..];
+        Ok(Some(Decoder { data: section, depth: 0 }.decode(offset)?.0))
+    }
+}
+
+// 数据区解码器，偏移相对于数据区起始
+struct Decoder<'a> {
+    data: &'a [u8],
+    depth: usize,
+}
+
+impl Decoder<'_> {
+    fn byte(&self, offset: usize) -> Result<u8> {
+        self.data.get(offset).copied().ok_or_else(|| anyhow!("MaxMind DB data truncated"))
+    }
+
+    fn bytes(&self, offset: usize, len: usize) -> Result<&[u8]> {
+        self.data.get(offset..offset + len).ok_or_else(|| anyhow!("MaxMind DB data truncated"))
+    }
+
+    fn uint(&self, offset: usize, len: usize) -> Result<u64> {
+        Ok(self.bytes(offset, len)?.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+    }
+
+    // 返回解码出的值和紧随其后的偏移
+    fn decode(&mut self, offset: usize) -> Result<(Value, usize)> {
+        if self.depth > MAX_DEPTH {
+            return Err(anyhow!("MaxMind DB data nested too deeply"));
+        }
+        let control = self.byte(offset)?;
+        let mut offset = offset + 1;
+        let mut kind = control >> 5;
+        if kind == 1 {
+            return self.decode_pointer(control, offset);
+        }
+        if kind == 0 {
+            kind = self
+                .byte(offset)?
+                .checked_add(7)
+                .ok_or_else(|| anyhow!("MaxMind DB extended type out of range"))?;
+            offset += 1;
+        }
+        let size = match control & 0x1F {
+            n @ 0..=28 => n as usize,
+            29 => {
+                offset += 1;
+                29 + self.uint(offset - 1, 1)? as usize
+            }
+            30 => {
+                offset += 2;
+                285 + self.uint(offset - 2, 2)? as usize
+            }
+            _ => {
+                offset += 3;
+                65821 + self.uint(offset - 3, 3)? as usize
+            }
+        };
+
+        let value = match kind {
+            // UTF-8 字符串
+            2 => Value::String(String::from_utf8_lossy(self.bytes(offset, size)?).into_owned()),
+            // double
+            3 => serde_json::Number::from_f64(f64::from_be_bytes(self.bytes(offset, 8)?.try_into()?))
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            // 原始字节，转为十六进制
+            4 => Value::String(self.bytes(offset, size)?.iter().map(|b| format!("{:02x}", b)).collect()),
+            // uint16、uint32、uint64
+            5 | 6 | 9 => Value::from(self.uint(offset, size)?),
+            // uint128 超出 JSON 数字范围，以字符串保存
+            10 => Value::String(
+                self.bytes(offset, size)?.iter().fold(0u128, |acc, &b| (acc << 8) | b as u128).to_string(),
+            ),
+            // int32
+            8 => {
+                // 长度为 0 表示 0，否则按实际字节数做符号扩展
+                let raw = self.uint(offset, size)? as u32;
+                match size.min(4) as u32 {
+                    0 => Value::from(0),
+                    n => Value::from(((raw << (32 - 8 * n)) as i32) >> (32 - 8 * n)),
+                }
+            }
+            // map
+            7 => {
+                self.depth += 1;
+                let mut map = Map::new();
+                for _ in 0..size {
+                    let (key, next) = self.decode(offset)?;
+                    let (value, next) = self.decode(next)?;
+                    offset = next;
+                    map.insert(key.as_str().unwrap_or_default().to_string(), value);
+                }
+                self.depth -= 1;
+                return Ok((Value::Object(map), offset));
+            }
+            // array
+            11 => {
+                self.depth += 1;
+                let mut items = Vec::with_capacity(size.min(256));
+                for _ in 0..size {
+                    let (value, next) = self.decode(offset)?;
+                    offset = next;
+                    items.push(value);
+                }
+                self.depth -= 1;
+                return Ok((Value::Array(items), offset));
+            }
+            // 布尔值直接存放在 size 中，不占数据字节
+            14 => return Ok((Value::Bool(size != 0), offset)),
+            // float
+            15 => serde_json::Number::from_f64(f32::from_be_bytes(self.bytes(offset, 4)?.try_into()?) as f64)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            other => return Err(anyhow!("Unsupported MaxMind DB data type {}", other)),
+        };
+        let len = match kind {
+            3 => 8,
+            15 => 4,
+            _ => size,
+        };
+        Ok((value, offset + len))
+    }
+
+    // 指针指向数据区中的另一个值，解码后从指针之后继续
+    fn decode_pointer(&mut self, control: u8, offset: usize) -> Result<(Value, usize)> {
+        let size = ((control >> 3) & 0x3) as usize;
+        let high = (control & 0x7) as usize;
+        let (target, len) = match size {
+            0 => ((high << 8) | self.uint(offset, 1)? as usize, 1),
+            1 => (((high << 16) | self.uint(offset, 2)? as usize) + 2048, 2),
+            2 => (((high << 24) | self.uint(offset, 3)? as usize) + 526336, 3),
+            _ => (self.uint(offset, 4)? as usize, 4),
+        };
+        self.depth += 1;
+        let (value, _) = self.decode(target)?;
+        self.depth -= 1;
+        Ok((value, offset + len))
+    }
+}
+
+// 已加载的数据库，国家/城市库与 ASN 库分别提供不同字段，查询时合并
+#[derive(Default)]
+pub struct GeoIpResolver {
+    databases: Vec<MmdbReader>,
+}
+
+impl GeoIpResolver {
+    pub fn is_empty(&self) -> bool {
+        self.databases.is_empty()
+    }
+
+    pub fn databases(&self) -> Vec<GeoIpDatabaseInfo> {
+        self.databases.iter().map(|db| db.info().clone()).collect()
+    }
+
+    // 同一路径重复加载时替换原有的数据库
+    pub fn add(&mut self, reader: MmdbReader) {
+        self.databases.retain(|db| db.info().path != reader.info().path);
+        self.databases.push(reader);
+    }
+
+    pub fn clear(&mut self) {
+        self.databases.clear();
+    }
+
+    // 加载目录下的全部 .mmdb 文件，目录不存在时忽略
+    pub fn load_dir(&mut self, dir: &Path) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("mmdb")))
+            .collect();
+        paths.sort();
+        for path in paths {
+            match MmdbReader::open(&path) {
+                Ok(reader) => {
+                    info!("Loaded GeoIP database {} ({})", path.display(), reader.info().database_type);
+                    self.add(reader);
+                }
+                Err(e) => warn!("Failed to load GeoIP database {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    pub fn lookup(&self, ip: IpAddr) -> GeoInfo {
+        let mut geo = GeoInfo { ip: ip.to_string(), ..Default::default() };
+        for db in &self.databases {
+            let record = match db.lookup(ip) {
+                Ok(Some(record)) => record,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("GeoIP lookup of {} in {} failed: {}", ip, db.info().path, e);
+                    continue;
+                }
+            };
+            let text = |pointer: &str| record.pointer(pointer).and_then(Value::as_str).map(str::to_string);
+            // 没有国家时退回注册国家，如部分 Anycast 地址
+            let country = ["/country", "/registered_country"]
+                .iter()
+                .find(|p| record.pointer(&format!("{}/iso_code", p)).is_some());
+            if let Some(country) = country {
+                geo.country_code = geo.country_code.or_else(|| text(&format!("{}/iso_code", country)));
+                geo.country = geo.country.or_else(|| text(&format!("{}/names/en", country)));
+            }
+            geo.city = geo.city.or_else(|| text("/city/names/en"));
+            geo.asn = geo.asn.or_else(|| {
+                record.pointer("/autonomous_system_number").and_then(Value::as_u64).map(|n| n as u32)
+            });
+            geo.organization = geo.organization.or_else(|| text("/autonomous_system_organization"));
+        }
+        geo
+    }
+}
+
+impl GeoInfo {
+    // 按国家代码或国家名称匹配，不区分大小写
+    pub fn matches_country(&self, country: &str) -> bool {
+        let country = country.trim();
+        self.country_code.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(country))
+            || self.country.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(country))
+    }
+}
@@ -0,0 +1,161 @@
+use crate::proxy::{find_header, HttpRequest};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphqlOperationType {
+    Query,
+    Mutation,
+    Subscription,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphqlOperation {
+    // 匿名操作没有名称
+    pub operation_name: Option<String>,
+    pub operation_type: GraphqlOperationType,
+    #[serde(default)]
+    pub variables: Option<Value>,
+    // 只发送哈希的持久化查询没有查询文本
+    #[serde(default)]
+    pub persisted: bool,
+}
+
+// POST 的 JSON 体（含批量数组）或 GET 的 query 参数；不是 GraphQL 请求时返回空列表
+pub fn extract(request: &HttpRequest) -> Vec<GraphqlOperation> {
+    let Ok(url) = url::Url::parse(&request.url) else {
+        return Vec::new();
+    };
+    let graphql_path = url.path().to_lowercase().contains("graphql");
+    if request.method.eq_ignore_ascii_case("GET") {
+        let param = |name: &str| url.query_pairs().find(|(k, _)| k == name).map(|(_, v)| v.into_owned());
+        let Some(query) = param("query") else {
+            return Vec::new();
+        };
+        let variables = param("variables").and_then(|v| serde_json::from_str(&v).ok());
+        return operation(Some(&query), param("operationName").as_deref(), variables, false)
+            .into_iter()
+            .collect();
+    }
+
+    let is_json = find_header(&request.headers, "content-type")
+        .map(|ct| ct.to_lowercase().contains("json"))
+        .unwrap_or(graphql_path);
+    if !request.method.eq_ignore_ascii_case("POST") || !is_json {
+        return Vec::new();
+    }
+    let Ok(body) = serde_json::from_slice::<Value>(&request.body) else {
+        return Vec::new();
+    };
+    let entries = match body {
+        Value::Array(entries) => entries,
+        entry => vec![entry],
+    };
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let query = entry.get("query").and_then(Value::as_str);
+            // 持久化查询只在 GraphQL 端点上识别，避免误判普通的 JSON 接口
+            let persisted = query.is_none()
+                && graphql_path
+                && entry.pointer("/extensions/persistedQuery").is_some();
+            if query.is_none() && !persisted {
+                return None;
+            }
+            operation(
+                query,
+                entry.get("operationName").and_then(Value::as_str),
+                entry.get("variables").filter(|v| !v.is_null()).cloned(),
+                persisted,
+            )
+        })
+        .collect()
+}
+
+fn operation(
+    query: Option<&str>,
+    operation_name: Option<&str>,
+    variables: Option<Value>,
+    persisted: bool,
+) -> Option<GraphqlOperation> {
+    let definitions = query.map(definitions).unwrap_or_default();
+    if query.is_some() && definitions.is_empty() {
+        return None;
+    }
+    // 文档含多个操作时由 operationName 选出实际执行的那个
+    let selected = match operation_name.filter(|name| !name.is_empty()) {
+        Some(name) => definitions.iter().find(|(_, n)| n.as_deref() == Some(name)),
+        None => definitions.first(),
+    };
+    Some(GraphqlOperation {
+        operation_name: operation_name
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .or_else(|| selected.and_then(|(_, name)| name.clone())),
+        operation_type: selected.map(|(t, _)| *t).unwrap_or(GraphqlOperationType::Query),
+        variables,
+        persisted,
+    })
+}
+
+// 只识别顶层的操作定义，不做完整的语法分析；片段定义会被跳过
+fn definitions(query: &str) -> Vec<(GraphqlOperationType, Option<String>)> {
+    let mut result = Vec::new();
+    let mut depth = 0usize;
+    // 已读到操作或片段关键字、尚未读到其选择集
+    let mut in_definition = false;
+    let mut chars = query.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '#' => {
+                while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+            }
+            '"' => {
+                let mut escaped = false;
+                for (_, c) in chars.by_ref() {
+                    match c {
+                        '\\' if !escaped => escaped = true,
+                        '"' if !escaped => break,
+                        _ => escaped = false,
+                    }
+                }
+            }
+            '{' => {
+                // 简写形式 "{ ... }" 是匿名查询
+                if depth == 0 && !std::mem::take(&mut in_definition) {
+                    result.push((GraphqlOperationType::Query, None));
+                }
+                depth += 1;
+            }
+            '}' => depth = depth.saturating_sub(1),
+            // 变量名（如 $query）不是关键字
+            '$' => while chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_').is_some() {},
+            c if depth == 0 && (c.is_ascii_alphabetic() || c == '_') => {
+                let end = query[index..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .map(|len| index + len)
+                    .unwrap_or(query.len());
+                let operation_type = match &query[index..end] {
+                    "query" => Some(GraphqlOperationType::Query),
+                    "mutation" => Some(GraphqlOperationType::Mutation),
+                    "subscription" => Some(GraphqlOperationType::Subscription),
+                    "fragment" => {
+                        in_definition = true;
+                        None
+                    }
+                    _ => None,
+                };
+                if let Some(operation_type) = operation_type {
+                    in_definition = true;
+                    let rest = query[end..].trim_start();
+                    let name: String = rest.chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '_').collect();
+                    result.push((operation_type, (!name.is_empty()).then_some(name)));
+                }
+                while chars.next_if(|&(i, _)| i < end).is_some() {}
+            }
+            _ => {}
+        }
+    }
+    result
+}
@@ -0,0 +1,163 @@
+use crate::decoding;
+use crate::detail::BodyPart;
+use crate::proxy::{find_header, HttpTransaction};
+use crate::protobuf::{self, ProtoRegistry};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+// gRPC-Web 在响应体末尾用最高位标记的帧携带 trailer
+const TRAILER_FLAG: u8 = 0x80;
+const COMPRESSED_FLAG: u8 = 0x01;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcMessage {
+    pub compressed: bool,
+    // 帧内的字节数（压缩时为压缩后的大小）
+    pub size: usize,
+    pub json: Option<Value>,
+    // 按 schema 解码失败或未注册 schema 时为无 schema 的尽力解码
+    pub schema: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcBody {
+    pub service: String,
+    pub method: String,
+    pub messages: Vec<GrpcMessage>,
+    pub trailers: HashMap<String, String>,
+    pub status: Option<String>,
+    pub status_message: Option<String>,
+}
+
+// application/grpc、application/grpc+proto 及 gRPC-Web 的各种变体
+pub fn is_grpc(headers: &HashMap<String, String>) -> bool {
+    find_header(headers, "content-type")
+        .map(|ct| ct.trim().to_lowercase().starts_with("application/grpc"))
+        .unwrap_or(false)
+}
+
+pub fn is_grpc_transaction(transaction: &HttpTransaction) -> bool {
+    is_grpc(&transaction.request.headers) || transaction.response.as_ref().is_some_and(|r| is_grpc(&r.headers))
+}
+
+struct Frame<'a> {
+    flags: u8,
+    data: &'a [u8],
+}
+
+// 长度前缀消息：1 字节标志 + 4 字节大端长度 + 消息内容
+fn frames(body: &[u8]) -> Result<Vec<Frame<'_>>> {
+    let mut frames = Vec::new();
+    let mut rest = body;
+    while !rest.is_empty() {
+        if rest.len() < 5 {
+            return Err(anyhow!("Truncated gRPC frame header"));
+        }
+        let len = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]) as usize;
+        let data = rest.get(5..5 + len).ok_or_else(|| anyhow!("Truncated gRPC frame: expected {} bytes", len))?;
+        frames.push(Frame { flags: rest[0], data });
+        rest = &rest[5 + len..];
+    }
+    Ok(frames)
+}
+
+pub fn decode(registry: &ProtoRegistry, transaction: &HttpTransaction, part: BodyPart) -> Result<GrpcBody> {
+    let (body, headers) = part.select(transaction).ok_or_else(|| anyhow!("Transaction has no response"))?;
+    if !is_grpc(headers) && !is_grpc(&transaction.request.headers) {
+        return Err(anyhow!("Not a gRPC message"));
+    }
+    let path = url::Url::parse(&transaction.request.url)
+        .map(|url| url.path().to_string())
+        .unwrap_or_default();
+    let (service, method) = path.trim_start_matches('/').split_once('/').unwrap_or((&path, ""));
+    let schema = registry.method(&path).map(|m| match part {
+        BodyPart::Request => m.input.clone(),
+        BodyPart::Response => m.output.clone(),
+    });
+
+    // grpc-web-text 以 base64 传输，每个分段可能各自带有填充，按 4 字符一组分别解码
+    let is_text = find_header(headers, "content-type").is_some_and(|ct| ct.to_lowercase().contains("grpc-web-text"));
+    let body = if is_text {
+        let text: Vec<u8> = body.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+        text.chunks(4)
+            .map(|group| general_purpose::STANDARD.decode(group))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow!("Invalid grpc-web-text body: {}", e))?
+            .concat()
+    } else {
+        body.to_vec()
+    };
+    let encoding = find_header(headers, "grpc-encoding");
+
+    let mut messages = Vec::new();
+    let mut trailers = HashMap::new();
+    for frame in frames(&body)? {
+        if frame.flags & TRAILER_FLAG != 0 {
+            for line in String::from_utf8_lossy(frame.data).lines() {
+                if let Some((name, value)) = line.split_once(':') {
+                    trailers.insert(name.trim().to_lowercase(), value.trim().to_string());
+                }
+            }
+            continue;
+        }
+        messages.push(decode_message(registry, schema.as_deref(), &frame, encoding));
+    }
+
+    // Trailers-Only 响应把状态放在响应头中；HTTP/2 trailer 未被记录时同样从响应头读取
+    let status_header = |name: &str| trailers.get(name).cloned().or_else(|| find_header(headers, name).map(str::to_string));
+    Ok(GrpcBody {
+        service: service.to_string(),
+        method: method.to_string(),
+        messages,
+        status: status_header("grpc-status"),
+        status_message: status_header("grpc-message").map(|m| urlencoding::decode(&m).map(|m| m.into_owned()).unwrap_or(m)),
+        trailers,
+    })
+}
+
+fn decode_message(registry: &ProtoRegistry, schema: Option<&str>, frame: &Frame, encoding: Option<&str>) -> GrpcMessage {
+    let compressed = frame.flags & COMPRESSED_FLAG != 0;
+    let mut message = GrpcMessage { compressed, size: frame.data.len(), json: None, schema: None, error: None };
+    let data = if compressed {
+        match decoding::decode(frame.data, Some(encoding.unwrap_or("gzip"))) {
+            Ok(data) => data,
+            Err(e) => {
+                message.error = Some(format!("Failed to decompress message: {}", e));
+                return message;
+            }
+        }
+    } else {
+        frame.data.to_vec()
+    };
+    if let Some(schema) = schema {
+        match registry.decode(schema, &data) {
+            Ok(json) => {
+                message.json = Some(json);
+                message.schema = Some(schema.to_string());
+                return message;
+            }
+            Err(e) => message.error = Some(e.to_string()),
+        }
+    }
+    match protobuf::decode_raw(&data) {
+        Ok(json) => message.json = Some(json),
+        Err(e) => message.error = Some(message.error.take().unwrap_or_else(|| e.to_string())),
+    }
+    message
+}
+
+// 供 AI 分析使用：把 gRPC 消息体替换为解码后的 JSON 文本
+pub fn as_json_text(registry: &ProtoRegistry, transaction: &HttpTransaction, part: BodyPart) -> Option<Vec<u8>> {
+    let decoded = decode(registry, transaction, part).ok()?;
+    let messages: Vec<Value> = decoded.messages.into_iter().filter_map(|m| m.json).collect();
+    let value = match messages.len() {
+        0 => return None,
+        1 => messages.into_iter().next()?,
+        _ => Value::Array(messages),
+    };
+    serde_json::to_vec_pretty(&value).ok()
+}
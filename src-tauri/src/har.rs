@@ -0,0 +1,671 @@
+use crate::ai_analyzer::AIAnalysisResult;
+use crate::export::{ExportOptions, ExportedBody};
+use crate::dns::DnsResolution;
+use crate::geoip::GeoInfo;
+use crate::modifications::ModificationStage;
+use crate::proxy::{find_header, HttpRequest, HttpResponse, HttpTransaction, ProxyServer, StoredEncoding, TunnelStats};
+use crate::shadow::ShadowComparison;
+use crate::websocket::WsMessage;
+use crate::tlsinfo::TlsInfo;
+use crate::upstream;
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+
+// HAR 1.2：http://www.softwareishard.com/blog/har-12-spec/
+pub const HAR_VERSION: &str = "1.2";
+// 规范中不可用的计时项取 -1
+const NOT_APPLICABLE: f64 = -1.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Har {
+    pub log: Log,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Log {
+    #[serde(default = "default_version")]
+    pub version: String,
+    #[serde(default)]
+    pub creator: Creator,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Creator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub started_date_time: String,
+    #[serde(default)]
+    pub time: f64,
+    pub request: Request,
+    // 旧版本导出的无响应条目为 null
+    #[serde(default)]
+    pub response: Option<Response>,
+    #[serde(default)]
+    pub cache: Cache,
+    #[serde(default)]
+    pub timings: Timings,
+    #[serde(rename = "serverIPAddress", default, skip_serializing_if = "Option::is_none")]
+    pub server_ip_address: Option<String>,
+    // 自定义扩展字段，其他工具会忽略；内容无法识别时忽略扩展，保留条目本身
+    #[serde(
+        rename = "_packetmind",
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub packetmind: Option<Extension>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    #[serde(default = "default_http_version")]
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<Cookie>,
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+    #[serde(default)]
+    pub query_string: Vec<NameValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<PostData>,
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+    #[serde(default)]
+    pub status: u16,
+    #[serde(default)]
+    pub status_text: String,
+    #[serde(default = "default_http_version")]
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<Cookie>,
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+    #[serde(default)]
+    pub content: Content,
+    #[serde(rename = "redirectURL", default)]
+    pub redirect_url: String,
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameValue {
+    pub name: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cookie {
+    pub name: String,
+    #[serde(default)]
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_only: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secure: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostData {
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<Param>,
+    #[serde(default)]
+    pub text: String,
+    // 非规范字段，与 content.encoding 含义相同，多数工具可识别
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Param {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Content {
+    #[serde(default)]
+    pub size: i64,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    // 解码节省的字节数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cache {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timings {
+    #[serde(default = "not_applicable")]
+    pub blocked: f64,
+    #[serde(default = "not_applicable")]
+    pub dns: f64,
+    #[serde(default = "not_applicable")]
+    pub connect: f64,
+    #[serde(default)]
+    pub send: f64,
+    #[serde(default)]
+    pub wait: f64,
+    #[serde(default)]
+    pub receive: f64,
+    #[serde(default = "not_applicable")]
+    pub ssl: f64,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self {
+            blocked: NOT_APPLICABLE,
+            dns: NOT_APPLICABLE,
+            connect: NOT_APPLICABLE,
+            send: 0.0,
+            wait: 0.0,
+            receive: 0.0,
+            ssl: NOT_APPLICABLE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Extension {
+    pub id: Option<String>,
+    pub tags: Vec<String>,
+    pub is_favorite: bool,
+    pub ai_analysis: Option<AIAnalysisResult>,
+    pub security_findings: Vec<String>,
+    pub ws_messages: Vec<WsMessage>,
+    pub shadow: Option<ShadowComparison>,
+    pub applied_rules: Vec<String>,
+    pub modifications: Vec<ModificationStage>,
+    pub replay_of: Option<String>,
+    pub notes: Option<String>,
+    pub tls: Option<TlsInfo>,
+    pub tunnel: Option<TunnelStats>,
+    pub geo: Option<GeoInfo>,
+    pub dns: Option<DnsResolution>,
+    pub timings: Option<upstream::Timings>,
+}
+
+fn default_version() -> String {
+    HAR_VERSION.to_string()
+}
+
+fn default_http_version() -> String {
+    "HTTP/1.1".to_string()
+}
+
+fn unknown_size() -> i64 {
+    -1
+}
+
+fn not_applicable() -> f64 {
+    NOT_APPLICABLE
+}
+
+fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).ok())
+}
+
+pub fn export(transactions: &[HttpTransaction], options: &ExportOptions) -> Har {
+    Har {
+        log: Log {
+            version: default_version(),
+            creator: Creator {
+                name: "PacketMind AI".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            entries: transactions.iter().map(|t| entry(t, options)).collect(),
+        },
+    }
+}
+
+fn round(options: &ExportOptions, ms: f64) -> f64 {
+    options.round_ms(std::time::Duration::from_secs_f64(ms.max(0.0) / 1000.0))
+}
+
+// HAR 的 connect 包含 ssl；规则、断点和节流等代理自身的耗时计入 blocked
+fn timings(transaction: &HttpTransaction, time: f64, options: &ExportOptions) -> Timings {
+    let Some(phases) = transaction.timings else {
+        // 没有分阶段计时（如 Mock 响应或旧版本记录），只区分域名解析，其余计入等待
+        let dns = transaction.dns.as_ref().map(|d| round(options, d.duration_ms).min(time));
+        return Timings {
+            dns: dns.unwrap_or(NOT_APPLICABLE),
+            wait: time - dns.unwrap_or(0.0),
+            ..Timings::default()
+        };
+    };
+    let dns = phases.dns_ms.map(|ms| round(options, ms));
+    let connect = phases.connect_ms.map(|ms| round(options, ms + phases.tls_ms.unwrap_or(0.0)));
+    let send = round(options, phases.send_ms);
+    let wait = round(options, phases.wait_ms);
+    let receive = round(options, phases.receive_ms);
+    let measured = dns.unwrap_or(0.0) + connect.unwrap_or(0.0) + send + wait + receive;
+    Timings {
+        blocked: round(options, time - measured),
+        dns: dns.unwrap_or(NOT_APPLICABLE),
+        connect: connect.unwrap_or(NOT_APPLICABLE),
+        send,
+        wait,
+        receive,
+        ssl: phases.tls_ms.map(|ms| round(options, ms)).unwrap_or(NOT_APPLICABLE),
+    }
+}
+
+// 其他工具导出的 HAR 没有扩展字段，从标准 timings 还原；只有等待阶段时视为没有分阶段计时
+fn phases(timings: &Timings) -> Option<upstream::Timings> {
+    let measured = |ms: f64| (ms >= 0.0).then_some(ms);
+    if timings.dns < 0.0 && timings.connect < 0.0 && timings.send <= 0.0 && timings.receive <= 0.0 {
+        return None;
+    }
+    let tls_ms = measured(timings.ssl);
+    Some(upstream::Timings {
+        dns_ms: measured(timings.dns),
+        connect_ms: measured(timings.connect).map(|ms| (ms - tls_ms.unwrap_or(0.0)).max(0.0)),
+        tls_ms,
+        send_ms: timings.send.max(0.0),
+        wait_ms: timings.wait.max(0.0),
+        receive_ms: timings.receive.max(0.0),
+    })
+}
+
+fn entry(transaction: &HttpTransaction, options: &ExportOptions) -> Entry {
+    let time = transaction.duration.map(|d| options.round_ms(d)).unwrap_or(0.0);
+    Entry {
+        started_date_time: transaction.request.timestamp.to_rfc3339(),
+        time,
+        request: request(&transaction.request, options),
+        response: Some(
+            transaction
+                .response
+                .as_ref()
+                .map(|r| response(r, transaction.response_encoding.as_ref(), options))
+                .unwrap_or_else(no_response),
+        ),
+        cache: Cache::default(),
+        timings: timings(transaction, time, options),
+        server_ip_address: transaction
+            .geo
+            .as_ref()
+            .map(|g| g.ip.clone())
+            .or_else(|| transaction.dns.as_ref()?.addresses.first().map(|ip| ip.to_string())),
+        packetmind: Some(extension(transaction, options)),
+    }
+}
+
+fn request(request: &HttpRequest, options: &ExportOptions) -> Request {
+    let mime_type = find_header(&request.headers, "content-type").unwrap_or_default().to_string();
+    let post_data = options.encode_body(&request.body).map(|body| {
+        // 表单内容同时给出解析后的参数，便于其他工具展示
+        let params = if !body.redacted && body.encoding.is_none() && mime_type.contains("application/x-www-form-urlencoded") {
+            url::form_urlencoded::parse(body.text.as_bytes())
+                .map(|(name, value)| Param {
+                    name: name.to_string(),
+                    value: Some(value.to_string()),
+                    file_name: None,
+                    content_type: None,
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        PostData {
+            mime_type: mime_type.clone(),
+            params,
+            text: body.text.clone(),
+            encoding: body.encoding.map(str::to_string),
+            comment: body_comment(&body),
+        }
+    });
+
+    Request {
+        method: request.method.clone(),
+        url: request.url.clone(),
+        http_version: request.version.clone().unwrap_or_else(default_http_version),
+        cookies: find_header(&request.headers, "cookie").map(request_cookies).unwrap_or_default(),
+        headers: name_values(&request.headers),
+        query_string: url::Url::parse(&request.url)
+            .map(|url| {
+                url.query_pairs()
+                    .map(|(name, value)| NameValue { name: name.to_string(), value: value.to_string() })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        post_data,
+        headers_size: -1,
+        body_size: request.body.len() as i64,
+    }
+}
+
+// HAR 的 content.text 为解码后的内容，入库时解开的编码写回响应头，bodySize 为传输的字节数
+fn response(response: &HttpResponse, stored: Option<&StoredEncoding>, options: &ExportOptions) -> Response {
+    let mime_type = find_header(&response.headers, "content-type").unwrap_or_default().to_string();
+    let size = response.body.len() as i64;
+    let body_size = stored.map(|s| s.encoded_size as i64).unwrap_or(size);
+    let compression = stored.map(|_| size - body_size);
+    let mut headers = response.headers.clone();
+    if let Some(stored) = stored {
+        headers.insert("content-encoding".to_string(), stored.content_encoding.clone());
+    }
+    let content = match options.encode_body(&response.body) {
+        Some(body) => Content {
+            size,
+            mime_type,
+            comment: body_comment(&body),
+            text: Some(body.text),
+            encoding: body.encoding.map(str::to_string),
+            compression,
+        },
+        None => Content {
+            size,
+            mime_type,
+            compression,
+            ..Content::default()
+        },
+    };
+
+    Response {
+        status: response.status,
+        status_text: hyper::StatusCode::from_u16(response.status)
+            .ok()
+            .and_then(|s| s.canonical_reason())
+            .unwrap_or_default()
+            .to_string(),
+        http_version: response.version.clone().unwrap_or_else(default_http_version),
+        cookies: find_header(&response.headers, "set-cookie")
+            .map(|value| value.lines().filter_map(response_cookie).collect())
+            .unwrap_or_default(),
+        headers: name_values(&headers),
+        content,
+        redirect_url: find_header(&response.headers, "location").unwrap_or_default().to_string(),
+        headers_size: -1,
+        body_size,
+    }
+}
+
+// 请求失败没有响应时按浏览器的做法输出状态码 0
+fn no_response() -> Response {
+    Response {
+        status: 0,
+        status_text: String::new(),
+        http_version: default_http_version(),
+        cookies: Vec::new(),
+        headers: Vec::new(),
+        content: Content::default(),
+        redirect_url: String::new(),
+        headers_size: -1,
+        body_size: -1,
+    }
+}
+
+fn body_comment(body: &ExportedBody) -> Option<String> {
+    if body.redacted {
+        Some("redacted".to_string())
+    } else if body.truncated {
+        Some("truncated".to_string())
+    } else {
+        None
+    }
+}
+
+// 多个 Set-Cookie 合并存储时以换行分隔，导出时还原为多条
+fn name_values(headers: &HashMap<String, String>) -> Vec<NameValue> {
+    let mut values: Vec<NameValue> = headers
+        .iter()
+        .flat_map(|(name, value)| {
+            let values: Vec<&str> = if name.eq_ignore_ascii_case("set-cookie") {
+                value.lines().collect()
+            } else {
+                vec![value.as_str()]
+            };
+            values
+                .into_iter()
+                .map(|value| NameValue { name: name.clone(), value: value.to_string() })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    values.sort_by(|a, b| a.name.cmp(&b.name));
+    values
+}
+
+fn request_cookies(header: &str) -> Vec<Cookie> {
+    header
+        .split(';')
+        .filter_map(|pair| {
+            let (name, value) = pair.trim().split_once('=')?;
+            Some(Cookie {
+                name: name.trim().to_string(),
+                value: value.trim().to_string(),
+                ..Cookie::default()
+            })
+        })
+        .collect()
+}
+
+fn response_cookie(header: &str) -> Option<Cookie> {
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.trim().split_once('=')?;
+    let mut cookie = Cookie {
+        name: name.trim().to_string(),
+        value: value.trim().to_string(),
+        ..Cookie::default()
+    };
+    for attribute in parts {
+        let (key, value) = attribute.trim().split_once('=').unwrap_or((attribute.trim(), ""));
+        match key.to_ascii_lowercase().as_str() {
+            "path" => cookie.path = Some(value.to_string()),
+            "domain" => cookie.domain = Some(value.to_string()),
+            "expires" => {
+                cookie.expires = chrono::DateTime::parse_from_rfc2822(value)
+                    .map(|t| t.to_rfc3339())
+                    .ok()
+                    .or_else(|| Some(value.to_string()))
+            }
+            "httponly" => cookie.http_only = Some(true),
+            "secure" => cookie.secure = Some(true),
+            _ => {}
+        }
+    }
+    Some(cookie)
+}
+
+// 不导出正文时，扩展字段中同样可能含有正文的部分一并清除
+fn extension(transaction: &HttpTransaction, options: &ExportOptions) -> Extension {
+    let mut extension = Extension {
+        id: Some(transaction.id.clone()),
+        tags: transaction.tags.clone(),
+        is_favorite: transaction.is_favorite,
+        ai_analysis: transaction.ai_analysis.clone(),
+        security_findings: transaction.security_findings.clone(),
+        ws_messages: transaction.ws_messages.clone(),
+        shadow: transaction.shadow.clone(),
+        applied_rules: transaction.applied_rules.clone(),
+        modifications: transaction.modifications.clone(),
+        replay_of: transaction.replay_of.clone(),
+        notes: transaction.notes.clone(),
+        tls: transaction.tls.clone(),
+        tunnel: transaction.tunnel,
+        geo: transaction.geo.clone(),
+        dns: transaction.dns.clone(),
+        timings: transaction.timings,
+    };
+    if !options.includes_bodies() {
+        for message in &mut extension.ws_messages {
+            message.payload.clear();
+        }
+        if let Some(response) = extension.shadow.as_mut().and_then(|s| s.response.as_mut()) {
+            response.body.clear();
+        }
+        for stage in &mut extension.modifications {
+            stage.request.body.clear();
+            for change in stage.changes.iter_mut().filter(|c| c.field == "body") {
+                change.before = None;
+                change.after = None;
+            }
+        }
+    }
+    extension
+}
+
+// 解析 HAR，无法识别的条目跳过
+pub fn import(content: &str) -> Result<Vec<HttpTransaction>> {
+    let har: serde_json::Value = serde_json::from_str(content)?;
+    let entries = har["log"]["entries"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Invalid HAR: missing log.entries"))?;
+    Ok(entries
+        .iter()
+        .filter_map(|entry| Entry::deserialize(entry).ok())
+        .map(transaction)
+        .collect())
+}
+
+fn transaction(entry: Entry) -> HttpTransaction {
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&entry.started_date_time)
+        .map(|t| t.with_timezone(&chrono::Utc))
+        .unwrap_or_else(|_| chrono::Utc::now());
+
+    let request = HttpRequest {
+        method: entry.request.method,
+        url: entry.request.url,
+        headers: headers(&entry.request.headers),
+        body: entry
+            .request
+            .post_data
+            .map(|p| decode_body(&p.text, p.encoding.as_deref()))
+            .unwrap_or_default(),
+        timestamp,
+        version: Some(entry.request.http_version),
+    };
+    let body_size = entry.response.as_ref().map(|r| r.body_size).unwrap_or(-1);
+    // 状态码为 0 且没有内容表示请求未得到响应
+    let response = entry
+        .response
+        .filter(|r| r.status != 0 || !r.headers.is_empty() || r.content.text.is_some())
+        .map(|r| HttpResponse {
+            status: r.status,
+            headers: headers(&r.headers),
+            body: r
+                .content
+                .text
+                .as_deref()
+                .map(|text| decode_body(text, r.content.encoding.as_deref()))
+                .unwrap_or_default(),
+            timestamp,
+            version: Some(r.http_version),
+        });
+
+    let extension = entry.packetmind.unwrap_or_default();
+    let mut transaction = HttpTransaction {
+        id: extension.id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        request,
+        response,
+        // 负数、NaN 或超出范围的耗时直接丢弃
+        duration: std::time::Duration::try_from_secs_f64(entry.time / 1000.0).ok(),
+        is_favorite: extension.is_favorite,
+        tags: extension.tags,
+        preflight_id: None,
+        preflight_for: None,
+        ai_analysis: extension.ai_analysis,
+        security_findings: extension.security_findings,
+        ws_messages: extension.ws_messages,
+        shadow: extension.shadow,
+        applied_rules: extension.applied_rules,
+        modifications: extension.modifications,
+        replay_of: extension.replay_of,
+        request_body_kind: None,
+        response_body_kind: None,
+        risk: None,
+        notes: extension.notes,
+        response_encoding: None,
+        graphql: Vec::new(),
+        tls: extension.tls,
+        tunnel: extension.tunnel,
+        pii_detected: Vec::new(),
+        // 其他工具导出的 HAR 只有上游地址，没有归属信息
+        geo: extension.geo.or_else(|| {
+            entry.server_ip_address.filter(|ip| !ip.is_empty()).map(|ip| GeoInfo { ip, ..Default::default() })
+        }),
+        dns: extension.dns,
+        timings: extension.timings.or_else(|| phases(&entry.timings)),
+    };
+    // 旧版本导出的是未解码的原始字节，其余按规范视为已解码，只记录编码
+    transaction.decode_response_body();
+    if let Some(response) = transaction.response.as_mut().filter(|_| transaction.response_encoding.is_none()) {
+        if let Some(content_encoding) = find_header(&response.headers, "content-encoding").map(str::to_string) {
+            response.headers.retain(|name, _| !name.eq_ignore_ascii_case("content-encoding"));
+            transaction.response_encoding = Some(StoredEncoding {
+                content_encoding,
+                encoded_size: usize::try_from(body_size).unwrap_or(response.body.len()),
+            });
+        }
+    }
+    transaction.classify_bodies();
+    transaction
+}
+
+fn headers(values: &[NameValue]) -> HashMap<String, String> {
+    ProxyServer::merge_headers(values.iter().map(|h| (h.name.as_str(), h.value.as_bytes())))
+}
+
+fn decode_body(text: &str, encoding: Option<&str>) -> Vec<u8> {
+    use base64::{Engine as _, engine::general_purpose};
+    if encoding == Some("base64") {
+        general_purpose::STANDARD.decode(text).unwrap_or_default()
+    } else {
+        text.as_bytes().to_vec()
+    }
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+pub const BYTES_PER_LINE: usize = 16;
+// 单页上限，避免一次把大文件全部发给前端
+pub const MAX_PAGE_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HexLine {
+    pub offset: usize,
+    // 以空格分隔的两位小写十六进制
+    pub hex: String,
+    // 不可打印字符显示为 "."
+    pub ascii: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HexDumpPage {
+    pub offset: usize,
+    pub length: usize,
+    pub total_size: usize,
+    pub lines: Vec<HexLine>,
+    pub has_more: bool,
+}
+
+// offset 向下对齐到整行，使翻页时每行的起始偏移保持一致
+pub fn page(body: &[u8], offset: usize, length: usize) -> HexDumpPage {
+    let start = (offset / BYTES_PER_LINE * BYTES_PER_LINE).min(body.len());
+    let end = start.saturating_add(length.min(MAX_PAGE_BYTES)).min(body.len());
+    let lines = body[start..end]
+        .chunks(BYTES_PER_LINE)
+        .enumerate()
+        .map(|(index, bytes)| HexLine {
+            offset: start + index * BYTES_PER_LINE,
+            hex: bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" "),
+            ascii: bytes
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect(),
+        })
+        .collect();
+    HexDumpPage {
+        offset: start,
+        length: end - start,
+        total_size: body.len(),
+        lines,
+        has_more: end < body.len(),
+    }
+}
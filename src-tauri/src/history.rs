@@ -0,0 +1,245 @@
+use crate::proxy::{find_header, HttpTransaction};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+pub const HISTORY_FILE_NAME: &str = "endpoint_stats.json";
+
+// 按天聚合的数据最多保留的天数
+const RETENTION_DAYS: i64 = 90;
+
+// 两次落盘之间的最短间隔
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+// 延迟直方图的桶上界（毫秒），最后一个桶收集超出范围的请求
+pub const LATENCY_BUCKETS_MS: [u64; 8] = [50, 100, 250, 500, 1000, 2500, 5000, u64::MAX];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailyBucket {
+    pub count: u64,
+    pub errors: u64,
+    pub total_latency_ms: u64,
+    pub latency_histogram: [u64; 8],
+    // 请求体与响应体字节数之和
+    #[serde(default)]
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EndpointStats {
+    pub host: String,
+    pub method: String,
+    pub path: String,
+    // 日期 (YYYY-MM-DD) -> 当天聚合
+    pub daily: BTreeMap<String, DailyBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointHistory {
+    pub host: String,
+    pub method: String,
+    pub path: String,
+    pub total_count: u64,
+    pub total_errors: u64,
+    pub avg_latency_ms: f64,
+    pub latency_histogram: [u64; 8],
+    pub daily: Vec<(String, DailyBucket)>,
+}
+
+// 跨会话保留的端点级统计，与原始事务的清理策略相互独立
+#[derive(Debug, Default)]
+pub struct HistoricalStats {
+    path: Option<PathBuf>,
+    endpoints: HashMap<String, EndpointStats>,
+    dirty: bool,
+    last_saved: Option<Instant>,
+}
+
+impl HistoricalStats {
+    pub fn load(path: PathBuf) -> Self {
+        let endpoints = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!("Failed to parse historical stats {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            path: Some(path),
+            endpoints,
+            dirty: false,
+            last_saved: None,
+        }
+    }
+
+    pub fn record(&mut self, transaction: &HttpTransaction) {
+        let (host, mut path) = split_endpoint(&transaction.request.url);
+        // 同一 GraphQL 端点按操作名称分别统计
+        if let Some(label) = transaction.graphql_label() {
+            path = format!("{}#{}", path, label);
+        }
+        let method = transaction.request.method.clone();
+        let key = format!("{} {}{}", method, host, path);
+        let day = transaction.request.timestamp.format("%Y-%m-%d").to_string();
+
+        let endpoint = self.endpoints.entry(key).or_insert_with(|| EndpointStats {
+            host,
+            method,
+            path,
+            daily: BTreeMap::new(),
+        });
+        let bucket = endpoint.daily.entry(day).or_default();
+
+        bucket.count += 1;
+        bucket.bytes += transaction_bytes(transaction);
+        let is_error = transaction.response.as_ref().map(|r| r.status >= 400).unwrap_or(true);
+        if is_error {
+            bucket.errors += 1;
+        }
+        if let Some(duration) = transaction.duration {
+            let ms = duration.as_millis() as u64;
+            bucket.total_latency_ms += ms;
+            let index = LATENCY_BUCKETS_MS.iter().position(|&upper| ms < upper).unwrap_or(7);
+            bucket.latency_histogram[index] += 1;
+        }
+
+        self.dirty = true;
+    }
+
+    // 若到达落盘时间则返回需要写入的数据
+    pub fn snapshot_if_due(&mut self) -> Option<(PathBuf, Vec<u8>)> {
+        let due = self.last_saved.map(|t| t.elapsed() >= SAVE_INTERVAL).unwrap_or(true);
+        if due {
+            self.snapshot()
+        } else {
+            None
+        }
+    }
+
+    pub fn snapshot(&mut self) -> Option<(PathBuf, Vec<u8>)> {
+        if !self.dirty {
+            return None;
+        }
+        let path = self.path.clone()?;
+
+        self.prune();
+        let data = serde_json::to_vec(&self.endpoints).ok()?;
+        self.dirty = false;
+        self.last_saved = Some(Instant::now());
+        Some((path, data))
+    }
+
+    fn prune(&mut self) {
+        let cutoff = (chrono::Utc::now() - chrono::Duration::days(RETENTION_DAYS))
+            .format("%Y-%m-%d")
+            .to_string();
+        for endpoint in self.endpoints.values_mut() {
+            endpoint.daily.retain(|day, _| *day >= cutoff);
+        }
+        self.endpoints.retain(|_, e| !e.daily.is_empty());
+    }
+
+    // 指定主机（含子域名）某一天的请求数和字节数
+    pub fn host_usage(&self, host: &str, day: &str) -> (u64, u64) {
+        let host = host.to_lowercase();
+        let suffix = format!(".{}", host);
+        self.endpoints
+            .values()
+            .filter(|e| {
+                let endpoint_host = e.host.to_lowercase();
+                endpoint_host == host || endpoint_host.ends_with(&suffix)
+            })
+            .filter_map(|e| e.daily.get(day))
+            .fold((0, 0), |(count, bytes), bucket| (count + bucket.count, bytes + bucket.bytes))
+    }
+
+    pub fn query(&self, host: Option<&str>, days: Option<u32>) -> Vec<EndpointHistory> {
+        let cutoff = days.map(|d| {
+            (chrono::Utc::now() - chrono::Duration::days(d as i64))
+                .format("%Y-%m-%d")
+                .to_string()
+        });
+
+        let mut result: Vec<EndpointHistory> = self.endpoints
+            .values()
+            .filter(|e| host.map(|h| e.host.contains(h)).unwrap_or(true))
+            .filter_map(|e| {
+                let daily: Vec<(String, DailyBucket)> = e.daily
+                    .iter()
+                    .filter(|(day, _)| cutoff.as_ref().map(|c| *day >= c).unwrap_or(true))
+                    .map(|(day, bucket)| (day.clone(), bucket.clone()))
+                    .collect();
+                if daily.is_empty() {
+                    return None;
+                }
+
+                let mut history = EndpointHistory {
+                    host: e.host.clone(),
+                    method: e.method.clone(),
+                    path: e.path.clone(),
+                    total_count: 0,
+                    total_errors: 0,
+                    avg_latency_ms: 0.0,
+                    latency_histogram: [0; 8],
+                    daily,
+                };
+                let mut total_latency = 0u64;
+                let mut timed = 0u64;
+                for (_, bucket) in &history.daily {
+                    history.total_count += bucket.count;
+                    history.total_errors += bucket.errors;
+                    total_latency += bucket.total_latency_ms;
+                    for (i, n) in bucket.latency_histogram.iter().enumerate() {
+                        history.latency_histogram[i] += n;
+                        timed += n;
+                    }
+                }
+                history.avg_latency_ms = total_latency as f64 / timed.max(1) as f64;
+                Some(history)
+            })
+            .collect();
+
+        result.sort_by_key(|h| std::cmp::Reverse(h.total_count));
+        result
+    }
+}
+
+// 响应体可能因抓包限制被截断，此时以 Content-Length 为准
+fn transaction_bytes(transaction: &HttpTransaction) -> u64 {
+    let response_bytes = transaction
+        .response
+        .as_ref()
+        .map(|r| {
+            let declared = find_header(&r.headers, "content-length")
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .unwrap_or(0);
+            declared.max(r.body.len() as u64)
+        })
+        .unwrap_or(0);
+    transaction.request.body.len() as u64 + response_bytes
+}
+
+// 拆出主机和归一化路径：去掉查询参数，并将数字/UUID 段替换为 {id}
+pub fn split_endpoint(url: &str) -> (String, String) {
+    let (host, path) = match url::Url::parse(url) {
+        Ok(parsed) => (
+            parsed.host_str().unwrap_or_default().to_string(),
+            parsed.path().to_string(),
+        ),
+        Err(_) => (url.split(':').next().unwrap_or(url).to_string(), String::new()),
+    };
+
+    let normalized: Vec<&str> = path
+        .split('/')
+        .map(|segment| {
+            let is_id = (!segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()))
+                || uuid::Uuid::parse_str(segment).is_ok();
+            if is_id { "{id}" } else { segment }
+        })
+        .collect();
+
+    (host, normalized.join("/"))
+}
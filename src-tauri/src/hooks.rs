@@ -0,0 +1,216 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+use tracing::warn;
+
+// 外部命令的最长执行时间
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+// 脚本沙箱限制
+const SCRIPT_MAX_OPERATIONS: u64 = 1_000_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum HookTrigger {
+    TransactionCompleted,
+    SessionSaved,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum HookAction {
+    // 序列化后的数据通过 stdin 以一行 JSON 传入
+    Command { program: String, args: Vec<String> },
+    // 脚本可通过 `payload` 变量访问数据，非空返回值以 JSON Lines 追加到 output_path
+    Script { source: String, output_path: Option<String> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportHook {
+    pub id: String,
+    pub name: String,
+    pub trigger: HookTrigger,
+    pub action: HookAction,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HookStatus {
+    pub runs: u64,
+    pub failures: u64,
+    pub last_run: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookInfo {
+    pub hook: ExportHook,
+    pub status: HookStatus,
+}
+
+#[derive(Debug, Default)]
+pub struct HookRegistry {
+    hooks: Vec<ExportHook>,
+    status: HashMap<String, HookStatus>,
+}
+
+impl HookRegistry {
+    pub fn add(&mut self, hook: ExportHook) -> Result<()> {
+        if let HookAction::Script { source, .. } = &hook.action {
+            // 注册时即检查语法，避免运行时才发现错误
+            rhai::Engine::new()
+                .compile(source)
+                .map_err(|e| anyhow!("Script error: {}", e))?;
+        }
+        self.hooks.retain(|h| h.id != hook.id);
+        self.hooks.push(hook);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, hook_id: &str) {
+        self.hooks.retain(|h| h.id != hook_id);
+        self.status.remove(hook_id);
+    }
+
+    pub fn list(&self) -> Vec<HookInfo> {
+        self.hooks
+            .iter()
+            .map(|hook| HookInfo {
+                hook: hook.clone(),
+                status: self.status.get(&hook.id).cloned().unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    pub fn has_enabled(&self, trigger: &HookTrigger) -> bool {
+        self.hooks.iter().any(|h| h.enabled && &h.trigger == trigger)
+    }
+
+    fn enabled_for(&self, trigger: &HookTrigger) -> Vec<ExportHook> {
+        self.hooks
+            .iter()
+            .filter(|h| h.enabled && &h.trigger == trigger)
+            .cloned()
+            .collect()
+    }
+
+    fn record(&mut self, hook_id: &str, result: &Result<()>) {
+        let status = self.status.entry(hook_id.to_string()).or_default();
+        status.runs += 1;
+        status.last_run = Some(chrono::Utc::now());
+        match result {
+            Ok(()) => status.last_error = None,
+            Err(e) => {
+                status.failures += 1;
+                status.last_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+// 在后台依次执行匹配触发条件的钩子，不阻塞代理主流程
+pub fn dispatch(registry: Arc<RwLock<HookRegistry>>, trigger: HookTrigger, payload: serde_json::Value) {
+    tokio::spawn(async move {
+        let hooks = registry.read().await.enabled_for(&trigger);
+        for hook in hooks {
+            let result = run_hook(&hook, &payload).await;
+            if let Err(e) = &result {
+                warn!("Export hook '{}' failed: {}", hook.name, e);
+            }
+            registry.write().await.record(&hook.id, &result);
+        }
+    });
+}
+
+async fn run_hook(hook: &ExportHook, payload: &serde_json::Value) -> Result<()> {
+    match &hook.action {
+        HookAction::Command { program, args } => run_command(program, args, payload).await,
+        HookAction::Script { source, output_path } => {
+            let source = source.clone();
+            let payload = payload.clone();
+            let output = tokio::task::spawn_blocking(move || run_script(&source, &payload)).await??;
+
+            match (output, output_path) {
+                (Some(value), Some(path)) => {
+                    let mut line = serde_json::to_vec(&value)?;
+                    line.push(b'\n');
+                    let mut file = tokio::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .await?;
+                    file.write_all(&line).await?;
+                    Ok(())
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+async fn run_command(program: &str, args: &[String], payload: &serde_json::Value) -> Result<()> {
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut line = serde_json::to_vec(payload)?;
+    line.push(b'\n');
+    let stdin = child.stdin.take();
+    // 写入与等待退出同时进行并共用超时：命令不读取 stdin 时，超出管道缓冲的写入会一直阻塞，
+    // 超时后丢弃 child 即由 kill_on_drop 结束进程
+    let write = async move {
+        if let Some(mut stdin) = stdin {
+            match stdin.write_all(&line).await {
+                // 命令未读完 stdin 就退出，以退出状态为准
+                Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+                result => result?,
+            }
+        }
+        Ok::<(), std::io::Error>(())
+    };
+    let run = async {
+        let (written, output) = tokio::join!(write, child.wait_with_output());
+        written?;
+        output
+    };
+    let output = tokio::time::timeout(COMMAND_TIMEOUT, run)
+        .await
+        .map_err(|_| anyhow!("Command timed out after {}s", COMMAND_TIMEOUT.as_secs()))??;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Command exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+fn run_script(source: &str, payload: &serde_json::Value) -> Result<Option<serde_json::Value>> {
+    let mut engine = rhai::Engine::new();
+    engine.set_max_operations(SCRIPT_MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+
+    let mut scope = rhai::Scope::new();
+    let payload = rhai::serde::to_dynamic(payload).map_err(|e| anyhow!("Script error: {}", e))?;
+    scope.push_dynamic("payload", payload);
+
+    let result: rhai::Dynamic = engine
+        .eval_with_scope(&mut scope, source)
+        .map_err(|e| anyhow!("Script error: {}", e))?;
+
+    if result.is_unit() {
+        return Ok(None);
+    }
+    let value = rhai::serde::from_dynamic(&result).map_err(|e| anyhow!("Script error: {}", e))?;
+    Ok(Some(value))
+}
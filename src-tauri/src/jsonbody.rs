@@ -0,0 +1,121 @@
+use crate::decoding;
+use crate::proxy::find_header;
+use anyhow::Result;
+use encoding_rs::{Encoding, UTF_8};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+// 扁平化路径的数量上限，超大数组只列出前面的部分
+pub const MAX_KEY_PATHS: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonParseError {
+    pub message: String,
+    // 从 1 开始计数
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonKeyPath {
+    // JSONPath 形式，如 $.data.items[0]["content-type"]
+    pub path: String,
+    pub value_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonBody {
+    pub value: Option<Value>,
+    pub error: Option<JsonParseError>,
+    // 实际用于解码文本的字符集
+    pub charset: String,
+    pub paths: Option<Vec<JsonKeyPath>>,
+    pub paths_truncated: bool,
+}
+
+// 依次解开 Content-Encoding、按 BOM 或 charset 参数转成文本，再解析为 JSON
+pub fn parse(body: &[u8], headers: &HashMap<String, String>, include_paths: bool) -> Result<JsonBody> {
+    let decoded = decoding::decode(body, find_header(headers, "content-encoding"))?;
+    let declared = find_header(headers, "content-type")
+        .and_then(charset_param)
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+    // BOM 优先于声明的字符集，decode 会一并去掉 BOM
+    let (text, encoding, _) = declared.decode(&decoded);
+
+    let (value, error) = match serde_json::from_str::<Value>(&text) {
+        Ok(value) => (Some(value), None),
+        Err(e) => (
+            None,
+            Some(JsonParseError { message: e.to_string(), line: e.line(), column: e.column() }),
+        ),
+    };
+    let mut paths_truncated = false;
+    let paths = match (&value, include_paths) {
+        (Some(value), true) => {
+            let mut paths = Vec::new();
+            flatten(value, "$".to_string(), &mut paths);
+            paths_truncated = paths.len() > MAX_KEY_PATHS;
+            paths.truncate(MAX_KEY_PATHS);
+            Some(paths)
+        }
+        _ => None,
+    };
+    Ok(JsonBody {
+        value,
+        error,
+        charset: encoding.name().to_string(),
+        paths,
+        paths_truncated,
+    })
+}
+
+fn charset_param(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim().eq_ignore_ascii_case("charset").then(|| value.trim().trim_matches('"'))
+    })
+}
+
+// 先序遍历，父节点排在子节点之前；多收集一条用于判断是否截断
+fn flatten(value: &Value, path: String, paths: &mut Vec<JsonKeyPath>) {
+    if paths.len() > MAX_KEY_PATHS {
+        return;
+    }
+    paths.push(JsonKeyPath { path: path.clone(), value_type: value_type(value).to_string() });
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                flatten(child, child_path(&path, key), paths);
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                flatten(child, format!("{}[{}]", path, index), paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn child_path(parent: &str, key: &str) -> String {
+    let plain = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        format!("{}.{}", parent, key)
+    } else {
+        format!("{}[{}]", parent, Value::String(key.to_string()))
+    }
+}
+
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
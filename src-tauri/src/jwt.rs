@@ -0,0 +1,181 @@
+use crate::hashing;
+use crate::proxy::HttpTransaction;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Sha256, Sha384, Sha512};
+use std::sync::OnceLock;
+
+// 常见的弱密钥和示例代码中的默认密钥，未提供字典时使用
+pub const COMMON_SECRETS: &[&str] = &[
+    "secret", "password", "123456", "12345678", "changeme", "jwt", "jwt_secret", "jwtsecret", "key", "secretkey",
+    "secret_key", "your-256-bit-secret", "your_jwt_secret", "mysecret", "test", "admin", "qwerty", "default",
+    "supersecret", "shhhhh", "s3cr3t", "private", "token", "auth",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtInfo {
+    pub header: Value,
+    pub claims: Value,
+    pub algorithm: Option<String>,
+    pub has_signature: bool,
+    pub issued_at: Option<DateTime<Utc>>,
+    pub not_before: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub expired: bool,
+    // 用字典破解出的 HMAC 密钥
+    pub weak_secret: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoundJwt {
+    // 如 "request header Authorization"、"response cookie session"
+    pub location: String,
+    pub token: String,
+    pub info: JwtInfo,
+}
+
+// 只解码不验证签名；JWE（五段）不支持
+pub fn decode(token: &str) -> Result<JwtInfo> {
+    let token = token.trim();
+    let token = token.strip_prefix("Bearer ").or_else(|| token.strip_prefix("bearer ")).unwrap_or(token).trim();
+    let parts: Vec<&str> = token.split('.').collect();
+    match parts.len() {
+        3 => {}
+        5 => return Err(anyhow!("Encrypted JWTs (JWE) are not supported")),
+        n => return Err(anyhow!("Expected 3 dot-separated segments, found {}", n)),
+    }
+    let header = decode_segment(parts[0]).map_err(|e| anyhow!("Invalid JWT header: {}", e))?;
+    let claims = decode_segment(parts[1]).map_err(|e| anyhow!("Invalid JWT claims: {}", e))?;
+    let algorithm = header.get("alg").and_then(Value::as_str).map(str::to_string);
+    let timestamp = |name: &str| claims.get(name).and_then(Value::as_f64).and_then(|secs| Utc.timestamp_opt(secs as i64, 0).single());
+    let now = Utc::now();
+
+    let mut info = JwtInfo {
+        algorithm: algorithm.clone(),
+        has_signature: !parts[2].is_empty(),
+        issued_at: timestamp("iat"),
+        not_before: timestamp("nbf"),
+        expires_at: timestamp("exp"),
+        expired: false,
+        weak_secret: None,
+        warnings: Vec::new(),
+        header,
+        claims,
+    };
+    match algorithm.as_deref() {
+        None => info.warnings.push("缺少 alg 字段".to_string()),
+        Some(alg) if alg.eq_ignore_ascii_case("none") => {
+            info.warnings.push("alg=none：令牌未签名，服务端若接受则可任意伪造".to_string());
+        }
+        _ if !info.has_signature => info.warnings.push("签名为空".to_string()),
+        _ => {}
+    }
+    match info.expires_at {
+        Some(exp) if exp <= now => {
+            info.expired = true;
+            info.warnings.push(format!("令牌已于 {} 过期", exp.to_rfc3339()));
+        }
+        Some(_) => {}
+        None => info.warnings.push("未设置 exp，令牌永不过期".to_string()),
+    }
+    if info.not_before.is_some_and(|nbf| nbf > now) {
+        info.warnings.push("令牌尚未生效 (nbf 晚于当前时间)".to_string());
+    }
+    Ok(info)
+}
+
+fn decode_segment(segment: &str) -> Result<Value> {
+    let bytes = general_purpose::URL_SAFE_NO_PAD.decode(segment.trim_end_matches('='))?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+// 仅对 HS256/384/512 有效；命中时返回密钥并记录警告
+pub fn crack_secret<'a>(token: &str, info: &mut JwtInfo, candidates: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let token = token.trim();
+    let token = token.strip_prefix("Bearer ").or_else(|| token.strip_prefix("bearer ")).unwrap_or(token).trim();
+    let (signing_input, signature) = token.rsplit_once('.')?;
+    let signature = general_purpose::URL_SAFE_NO_PAD.decode(signature.trim_end_matches('=')).ok()?;
+    let sign: fn(&[u8], &[u8]) -> Vec<u8> = match info.algorithm.as_deref()?.to_uppercase().as_str() {
+        "HS256" => hashing::hmac::<Sha256>,
+        "HS384" => hashing::hmac::<Sha384>,
+        "HS512" => hashing::hmac::<Sha512>,
+        _ => return None,
+    };
+    let secret = candidates
+        .into_iter()
+        .find(|candidate| sign(candidate.as_bytes(), signing_input.as_bytes()) == signature)?
+        .to_string();
+    info.warnings.push(format!("HMAC 密钥强度不足，已被字典破解: \"{}\"", secret));
+    info.weak_secret = Some(secret.clone());
+    Some(secret)
+}
+
+// 内置弱密钥之外追加字典文件中的每一行
+pub fn load_wordlist(path: Option<&str>) -> Result<Vec<String>> {
+    let mut secrets: Vec<String> = COMMON_SECRETS.iter().map(|s| s.to_string()).collect();
+    if let Some(path) = path {
+        let content = std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read wordlist {}: {}", path, e))?;
+        secrets.extend(content.lines().map(str::to_string).filter(|line| !line.is_empty()));
+    }
+    Ok(secrets)
+}
+
+fn jwt_regex() -> &'static regex::Regex {
+    static RE: OnceLock<regex::Regex> = OnceLock::new();
+    // 头部和载荷都是以 {" 开头的 JSON，base64url 编码后以 eyJ 开头
+    RE.get_or_init(|| regex::Regex::new(r"eyJ[A-Za-z0-9_-]+\.eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*").unwrap())
+}
+
+// 扫描请求头、Cookie 和文本形式的响应体，同一令牌只报告第一次出现的位置
+pub fn find_in_transaction(transaction: &HttpTransaction) -> Vec<FoundJwt> {
+    let mut candidates: Vec<(String, String)> = Vec::new();
+    let mut scan_headers = |side: &str, headers: &std::collections::HashMap<String, String>| {
+        let mut names: Vec<&String> = headers.keys().collect();
+        names.sort();
+        for name in names {
+            let value = &headers[name];
+            let is_cookie = name.eq_ignore_ascii_case("cookie") || name.eq_ignore_ascii_case("set-cookie");
+            for m in jwt_regex().find_iter(value) {
+                let location = if is_cookie {
+                    let cookie = cookie_name(&value[..m.start()]).unwrap_or("?");
+                    format!("{} cookie {}", side, cookie)
+                } else {
+                    format!("{} header {}", side, name)
+                };
+                candidates.push((location, m.as_str().to_string()));
+            }
+        }
+    };
+    scan_headers("request", &transaction.request.headers);
+    if let Some(response) = &transaction.response {
+        scan_headers("response", &response.headers);
+        if !transaction.response_kind().is_binary() {
+            let body = String::from_utf8_lossy(&response.body);
+            for m in jwt_regex().find_iter(&body) {
+                candidates.push(("response body".to_string(), m.as_str().to_string()));
+            }
+        }
+    }
+
+    let mut found: Vec<FoundJwt> = Vec::new();
+    for (location, token) in candidates {
+        if found.iter().any(|f| f.token == token) {
+            continue;
+        }
+        if let Ok(info) = decode(&token) {
+            found.push(FoundJwt { location, token, info });
+        }
+    }
+    found
+}
+
+// 令牌前面最近的 "name=" 即所在的 Cookie 名称
+fn cookie_name(before: &str) -> Option<&str> {
+    let before = before.strip_suffix('=')?;
+    let start = before.rfind([';', ',', '\n']).map(|i| i + 1).unwrap_or(0);
+    Some(before[start..].trim())
+}
@@ -0,0 +1,68 @@
+use crate::config::{AiProvider, AiProviderConfig};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+// 系统钥匙串中的服务名，每个服务商保存一条
+const SERVICE: &str = "packetmind-ai";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeySource {
+    Keychain,
+    Environment,
+}
+
+fn account(provider: AiProvider) -> &'static str {
+    match provider {
+        AiProvider::Openai => "openai",
+        AiProvider::Anthropic => "anthropic",
+        AiProvider::Ollama => "ollama",
+        AiProvider::Local => "local",
+    }
+}
+
+fn entry(provider: AiProvider) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, account(provider)).map_err(|e| anyhow!("Failed to open keychain entry: {}", e))
+}
+
+// 以下函数都会阻塞（可能弹出系统授权窗口），异步代码中需放到 spawn_blocking 里调用
+pub fn store_api_key(provider: AiProvider, api_key: &str) -> Result<()> {
+    entry(provider)?
+        .set_password(api_key)
+        .map_err(|e| anyhow!("Failed to store API key in keychain: {}", e))
+}
+
+pub fn load_api_key(provider: AiProvider) -> Result<Option<String>> {
+    match entry(provider)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(anyhow!("Failed to read API key from keychain: {}", e)),
+    }
+}
+
+pub fn delete_api_key(provider: AiProvider) -> Result<()> {
+    match entry(provider)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(anyhow!("Failed to delete API key from keychain: {}", e)),
+    }
+}
+
+// 配置文件显式指定的环境变量优先，其次是钥匙串，最后是服务商的默认环境变量
+pub fn resolve_api_key(config: &AiProviderConfig) -> Option<(String, ApiKeySource)> {
+    let from_env = |name: &str| std::env::var(name).ok().filter(|key| !key.trim().is_empty());
+    if let Some(key) = config.api_key_env.as_deref().and_then(from_env) {
+        return Some((key, ApiKeySource::Environment));
+    }
+    match load_api_key(config.provider) {
+        Ok(Some(key)) => return Some((key, ApiKeySource::Keychain)),
+        Ok(None) => {}
+        // 没有可用的钥匙串服务（如无桌面会话的 Linux）时继续尝试环境变量
+        Err(e) => warn!("{}", e),
+    }
+    config
+        .provider
+        .default_api_key_env()
+        .and_then(from_env)
+        .map(|key| (key, ApiKeySource::Environment))
+}
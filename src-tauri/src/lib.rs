@@ -2,15 +2,160 @@ mod proxy;
 mod commands;
 mod ai_analyzer;
 mod ai_response;
+mod dashboard;
+mod history;
+mod settings;
+mod openapi;
+mod hooks;
+mod selftest;
+mod stats;
+mod mitm;
+mod emulation;
+mod triage;
+mod chunking;
+mod waterfall;
+mod storage;
+mod websocket;
+mod palette;
+mod shadow;
+mod rules;
+mod modifications;
+mod quotas;
+mod breakpoints;
+mod testserver;
+mod export;
+mod classify;
+mod recovery;
+mod session;
+mod loadtest;
+mod har;
+mod postman;
+mod scoring;
+mod curl;
+mod mitmproxy;
+mod pcap;
+mod buffer;
+mod decoding;
+mod detail;
+mod search;
+mod bypass;
+mod auth;
+mod throttle;
+mod ruleset;
+mod config;
+mod hexdump;
+mod jsonbody;
+mod formdata;
+mod graphql;
+mod protobuf;
+mod grpc;
+mod hashing;
+mod jwt;
+mod codecs;
+mod cookies;
+mod secheaders;
+mod tlsinfo;
+mod pinning;
+mod llm;
+mod keychain;
+mod aicache;
+mod nlsearch;
+mod chat;
+mod summary;
+mod rulegen;
+mod schema;
+mod mockdata;
+mod baseline;
+mod redaction;
+mod scanner;
+mod passive;
+mod active;
+mod params;
+mod secrets;
+mod secreport;
+mod geoip;
+mod dns;
+mod upstream;
+mod otel;
 
 use std::sync::Arc;
 use commands::{
     ProxyState, start_proxy, stop_proxy, get_transactions, add_filter, remove_filter, clear_transactions, is_proxy_running,
     search_transactions, toggle_favorite, get_favorites, add_rule, remove_rule, get_rules,
     export_har, encode_base64, decode_base64, encode_url, decode_url,
-    analyze_transaction, detect_vulnerabilities, get_ai_insights, generate_ai_response
+    analyze_transaction, detect_vulnerabilities, get_ai_insights, generate_ai_response,
+    start_dashboard_stream, stop_dashboard_stream, get_historical_stats,
+    get_capture_settings, set_capture_settings,
+    import_openapi_spec, clear_openapi_spec, get_spec_violations, get_spec_conformance,
+    add_hook, remove_hook, get_hooks, run_self_test,
+    get_activity_heatmap,
+    get_device_profiles, get_emulation_settings, set_device_profile, save_device_profile, remove_device_profile,
+    generate_ca_cert, get_ca_cert_info, export_ca_cert, install_ca_cert,
+    get_triage_queue, get_triage_summary, update_triage_item, explain_triage_item, clear_resolved_triage,
+    import_har,
+    add_latency_budget, remove_latency_budget, get_latency_budgets, get_waterfall,
+    get_ws_messages,
+    save_search, remove_saved_search, get_saved_searches, quick_find,
+    get_shadow_config, set_shadow_config, get_shadow_report,
+    get_modification_history,
+    add_quota, remove_quota, get_quota_usage, get_quota_alerts,
+    add_breakpoint, remove_breakpoint, get_breakpoints, get_intercepted, modify_intercepted, resume_intercepted, drop_intercepted,
+    get_test_server_url, stop_test_server,
+    replay_transaction,
+    send_custom_request,
+    get_recovery_info, restore_recovery, discard_recovery,
+    save_session, load_session,
+    export_load_test,
+    export_postman,
+    score_transactions,
+    generate_curl,
+    export_mitmproxy_flows,
+    export_pcapng,
+    get_capture_stats,
+    get_transaction_detail,
+    add_tag, remove_tag, get_transactions_by_tag,
+    set_transaction_note, get_transaction_note,
+    set_proxy_bypass, get_proxy_bypass,
+    set_proxy_credentials,
+    get_throttle_settings, set_throttle_profile,
+    export_rules, import_rules,
+    reorder_rules,
+    reload_config, get_config_status, get_ai_provider_config,
+    get_body_hexdump,
+    get_body_as_json,
+    get_parsed_body,
+    get_grpc_messages, register_proto_files, clear_proto_files, get_proto_registry,
+    decode_jwt, find_transaction_jwts,
+    encode_hex, decode_hex, compress_text, decompress_text, escape_html, unescape_html, escape_unicode, unescape_unicode, convert_timestamp,
+    compute_hash, compute_hmac,
+    get_cookies, clear_cookies,
+    get_security_header_report,
+    get_tls_info,
+    set_tls_passthrough,
+    get_pinning_report, clear_pinning_report,
+    check_ai_endpoint, list_ai_models,
+    set_ai_config, get_ai_config,
+    invalidate_ai_cache,
+    analyze_transaction_stream, cancel_ai_request,
+    semantic_search,
+    chat_about_transaction, chat_about_transaction_stream, get_conversation, list_conversations, delete_conversation,
+    summarize_capture,
+    add_rules, create_rule_from_text,
+    infer_json_schema,
+    get_anomalies, reset_anomaly_baseline,
+    get_pii_masking, set_pii_masking,
+    get_redaction_policy, set_redaction_policy,
+    get_security_findings,
+    get_active_scan_settings, set_active_scan_settings, active_scan_transaction,
+    export_findings,
+    load_geoip_database, get_geoip_databases, clear_geoip_databases,
+    get_dns_settings, set_dns_settings, get_dns_cache, clear_dns_cache,
+    get_traffic_stats,
+    get_otel_settings, set_otel_settings, get_otel_status, flush_otel_export,
+    set_allow_lan, get_allow_lan
 };
 use proxy::ProxyServer;
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -25,10 +170,18 @@ pub fn run() {
 
     // Create proxy server instance
     let proxy_server = Arc::new(ProxyServer::new(8080));
+    let exit_proxy = proxy_server.clone();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .manage::<ProxyState>(proxy_server)
+        .manage::<ProxyState>(proxy_server.clone())
+        .setup(move |app| {
+            // 将应用数据目录交给代理，用于持久化统计等数据
+            let data_dir = app.path().app_data_dir()?;
+            tauri::async_runtime::block_on(proxy_server.set_data_dir(data_dir));
+            tauri::async_runtime::block_on(proxy_server.set_app_handle(app.handle().clone()));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             start_proxy,
             stop_proxy,
@@ -51,8 +204,169 @@ pub fn run() {
             analyze_transaction,
             detect_vulnerabilities,
             get_ai_insights,
-            generate_ai_response
+            generate_ai_response,
+            start_dashboard_stream,
+            stop_dashboard_stream,
+            get_historical_stats,
+            get_capture_settings,
+            set_capture_settings,
+            import_openapi_spec,
+            clear_openapi_spec,
+            get_spec_violations,
+            get_spec_conformance,
+            add_hook,
+            remove_hook,
+            get_hooks,
+            run_self_test,
+            get_activity_heatmap,
+            get_device_profiles,
+            get_emulation_settings,
+            set_device_profile,
+            save_device_profile,
+            remove_device_profile,
+            generate_ca_cert,
+            get_ca_cert_info,
+            export_ca_cert,
+            install_ca_cert,
+            get_triage_queue,
+            get_triage_summary,
+            update_triage_item,
+            explain_triage_item,
+            clear_resolved_triage,
+            import_har,
+            add_latency_budget,
+            remove_latency_budget,
+            get_latency_budgets,
+            get_waterfall,
+            get_ws_messages,
+            save_search,
+            remove_saved_search,
+            get_saved_searches,
+            quick_find,
+            get_shadow_config,
+            set_shadow_config,
+            get_shadow_report,
+            get_modification_history,
+            add_quota,
+            remove_quota,
+            get_quota_usage,
+            get_quota_alerts,
+            add_breakpoint,
+            remove_breakpoint,
+            get_breakpoints,
+            get_intercepted,
+            modify_intercepted,
+            resume_intercepted,
+            drop_intercepted,
+            get_test_server_url,
+            stop_test_server,
+            replay_transaction,
+            send_custom_request,
+            get_recovery_info,
+            restore_recovery,
+            discard_recovery,
+            save_session,
+            load_session,
+            export_load_test,
+            export_postman,
+            score_transactions,
+            generate_curl,
+            export_mitmproxy_flows,
+            export_pcapng,
+            get_capture_stats,
+            get_transaction_detail,
+            add_tag,
+            remove_tag,
+            get_transactions_by_tag,
+            set_transaction_note,
+            get_transaction_note,
+            set_proxy_bypass,
+            get_proxy_bypass,
+            set_proxy_credentials,
+            get_throttle_settings,
+            set_throttle_profile,
+            export_rules,
+            import_rules,
+            reorder_rules,
+            reload_config,
+            get_config_status,
+            get_ai_provider_config,
+            get_body_hexdump,
+            get_body_as_json,
+            get_parsed_body,
+            get_grpc_messages,
+            register_proto_files,
+            clear_proto_files,
+            get_proto_registry,
+            decode_jwt,
+            find_transaction_jwts,
+            encode_hex,
+            decode_hex,
+            compress_text,
+            decompress_text,
+            escape_html,
+            unescape_html,
+            escape_unicode,
+            unescape_unicode,
+            convert_timestamp,
+            compute_hash,
+            compute_hmac,
+            get_cookies,
+            clear_cookies,
+            get_security_header_report,
+            get_tls_info,
+            set_tls_passthrough,
+            get_pinning_report,
+            clear_pinning_report,
+            check_ai_endpoint,
+            list_ai_models,
+            set_ai_config,
+            get_ai_config,
+            invalidate_ai_cache,
+            analyze_transaction_stream,
+            cancel_ai_request,
+            semantic_search,
+            chat_about_transaction,
+            chat_about_transaction_stream,
+            get_conversation,
+            list_conversations,
+            delete_conversation,
+            summarize_capture,
+            add_rules,
+            create_rule_from_text,
+            infer_json_schema,
+            get_anomalies,
+            reset_anomaly_baseline,
+            get_pii_masking,
+            set_pii_masking,
+            get_redaction_policy,
+            set_redaction_policy,
+            get_security_findings,
+            get_active_scan_settings,
+            set_active_scan_settings,
+            active_scan_transaction,
+            export_findings,
+            load_geoip_database,
+            get_geoip_databases,
+            clear_geoip_databases,
+            get_dns_settings,
+            set_dns_settings,
+            get_dns_cache,
+            clear_dns_cache,
+            get_traffic_stats,
+            get_otel_settings,
+            set_otel_settings,
+            get_otel_status,
+            flush_otel_export,
+            set_allow_lan,
+            get_allow_lan
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(move |_app, event| {
+            // 正常退出时清除崩溃恢复标记
+            if let tauri::RunEvent::Exit = event {
+                tauri::async_runtime::block_on(exit_proxy.end_session());
+            }
+        });
 }
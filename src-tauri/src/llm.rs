@@ -0,0 +1,350 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::warn;
+
+pub const AI_STREAM_EVENT: &str = "ai:stream";
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+// Ollama 的 OpenAI 兼容接口；llama.cpp、LM Studio 等本地服务的地址通过配置给出
+pub const OLLAMA_BASE_URL: &str = "http://localhost:11434/v1";
+pub const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+// Messages API 要求显式给出输出上限
+pub const DEFAULT_MAX_TOKENS: u32 = 2048;
+// 模型生成较慢，超时按整个请求计算
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_ATTEMPTS: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+// 设置界面的连通性检查不重试，尽快给出结果
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self { role: "system".to_string(), content: content.into() }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self { role: "user".to_string(), content: content.into() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointStatus {
+    pub base_url: String,
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
+    pub models: Vec<String>,
+    pub error: Option<String>,
+}
+
+// 流式输出推送给界面的事件，按 request_id 区分同时进行的多个请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiStreamEvent {
+    pub request_id: String,
+    #[serde(flatten)]
+    pub payload: StreamPayload,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamPayload {
+    Delta { text: String },
+    Done { result: Value },
+    Error { message: String },
+    Cancelled,
+}
+
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build().unwrap_or_default()
+}
+
+// 429、5xx 和网络错误按指数退避重试，服务端给出 Retry-After 时以其为准
+pub async fn send_with_retry<F>(build: F) -> Result<reqwest::Response>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        let retry_after = match build().send().await {
+            Ok(response) if response.status().is_success() => return Ok(response),
+            Ok(response) if is_retryable(response.status()) && attempt < MAX_ATTEMPTS => retry_after(&response),
+            Ok(response) => {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                return Err(anyhow!("AI provider returned {}: {}", status, error_message(&body)));
+            }
+            Err(e) if attempt < MAX_ATTEMPTS && (e.is_timeout() || e.is_connect()) => None,
+            Err(e) => return Err(anyhow!("AI provider request failed: {}", e)),
+        };
+        let delay = retry_after.unwrap_or(backoff).min(MAX_BACKOFF);
+        warn!("AI request attempt {}/{} failed, retrying in {:?}", attempt, MAX_ATTEMPTS, delay);
+        tokio::time::sleep(delay).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+fn is_retryable(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+// 各家接口的错误体都形如 {"error": {"message": ...}}，取不到时截取原文
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| v["error"]["message"].as_str().or_else(|| v["error"].as_str()).map(str::to_string))
+        .unwrap_or_else(|| body.chars().take(300).collect())
+}
+
+// 兼容模型把 JSON 包在代码块里或前后带有说明文字的情况
+pub fn extract_json(content: &str) -> Result<&str> {
+    match (content.find('{'), content.rfind('}')) {
+        (Some(start), Some(end)) if start < end => Ok(&content[start..=end]),
+        _ => Err(anyhow!("Model response is not a JSON object")),
+    }
+}
+
+// OpenAI Chat Completions；json_mode 要求模型只输出一个 JSON 对象
+pub async fn openai_chat(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: Option<&str>,
+    model: &str,
+    messages: &[ChatMessage],
+    json_mode: bool,
+) -> Result<String> {
+    let response = openai_request(client, base_url, api_key, openai_body(model, messages, json_mode, false)).await?;
+    let completion: Value = response.json().await?;
+    completion["choices"][0]["message"]["content"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("AI provider returned no message content"))
+}
+
+// 流式版本：每收到一段文本就回调一次，返回完整文本
+pub async fn openai_chat_stream(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: Option<&str>,
+    model: &str,
+    messages: &[ChatMessage],
+    json_mode: bool,
+    on_delta: &mut (dyn FnMut(&str) + Send),
+) -> Result<String> {
+    let response = openai_request(client, base_url, api_key, openai_body(model, messages, json_mode, true)).await?;
+    let mut text = String::new();
+    read_sse(response, |data| {
+        let chunk: Value = serde_json::from_str(data)?;
+        if let Some(message) = chunk["error"]["message"].as_str() {
+            return Err(anyhow!("AI provider stream failed: {}", message));
+        }
+        if let Some(delta) = chunk["choices"][0]["delta"]["content"].as_str().filter(|d| !d.is_empty()) {
+            text.push_str(delta);
+            on_delta(delta);
+        }
+        Ok(())
+    })
+    .await?;
+    Ok(text)
+}
+
+fn openai_body(model: &str, messages: &[ChatMessage], json_mode: bool, stream: bool) -> Value {
+    let mut body = json!({
+        "model": model,
+        "messages": messages,
+        "temperature": 0.2,
+    });
+    if json_mode {
+        body["response_format"] = json!({ "type": "json_object" });
+    }
+    if stream {
+        body["stream"] = json!(true);
+    }
+    body
+}
+
+async fn openai_request(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: Option<&str>,
+    body: Value,
+) -> Result<reqwest::Response> {
+    let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
+    send_with_retry(|| {
+        let request = client.post(&url).json(&body);
+        match api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    })
+    .await
+}
+
+// Anthropic Messages API；系统提示单独传递，messages 中只能有 user/assistant
+pub async fn anthropic_messages(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: &str,
+    model: &str,
+    system: &str,
+    messages: &[ChatMessage],
+    max_tokens: u32,
+) -> Result<String> {
+    let body = anthropic_body(model, system, messages, max_tokens, false);
+    let response = anthropic_request(client, base_url, api_key, body).await?;
+    let message: Value = response.json().await?;
+    if message["stop_reason"] == "max_tokens" {
+        warn!("Anthropic response truncated at max_tokens={}", max_tokens);
+    }
+    let text: String = message["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|block| block["type"] == "text")
+        .filter_map(|block| block["text"].as_str())
+        .collect();
+    if text.is_empty() {
+        return Err(anyhow!("AI provider returned no message content"));
+    }
+    Ok(text)
+}
+
+// OpenAI 兼容的 GET /models，Ollama、llama.cpp server、vLLM 均支持
+pub async fn list_models(client: &reqwest::Client, base_url: &str, api_key: Option<&str>) -> Result<Vec<String>> {
+    let url = format!("{}/models", base_url.trim_end_matches('/'));
+    let mut request = client.get(&url).timeout(HEALTH_CHECK_TIMEOUT);
+    if let Some(key) = api_key {
+        request = request.bearer_auth(key);
+    }
+    let response = request.send().await.map_err(|e| anyhow!("Failed to reach {}: {}", base_url, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!("{} returned {}: {}", url, status, error_message(&body)));
+    }
+    let list: Value = response.json().await?;
+    let mut models: Vec<String> = list["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| model["id"].as_str().map(str::to_string))
+        .collect();
+    models.sort();
+    Ok(models)
+}
+
+pub async fn check_endpoint(client: &reqwest::Client, base_url: &str, api_key: Option<&str>) -> EndpointStatus {
+    let start = std::time::Instant::now();
+    let result = list_models(client, base_url, api_key).await;
+    let latency_ms = Some(start.elapsed().as_millis() as u64);
+    match result {
+        Ok(models) => EndpointStatus { base_url: base_url.to_string(), reachable: true, latency_ms, models, error: None },
+        Err(e) => EndpointStatus {
+            base_url: base_url.to_string(),
+            reachable: false,
+            latency_ms: None,
+            models: Vec::new(),
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn anthropic_messages_stream(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: &str,
+    model: &str,
+    system: &str,
+    messages: &[ChatMessage],
+    max_tokens: u32,
+    on_delta: &mut (dyn FnMut(&str) + Send),
+) -> Result<String> {
+    let body = anthropic_body(model, system, messages, max_tokens, true);
+    let response = anthropic_request(client, base_url, api_key, body).await?;
+    let mut text = String::new();
+    read_sse(response, |data| {
+        let event: Value = serde_json::from_str(data)?;
+        match event["type"].as_str() {
+            Some("content_block_delta") => {
+                if let Some(delta) = event["delta"]["text"].as_str() {
+                    text.push_str(delta);
+                    on_delta(delta);
+                }
+            }
+            Some("message_delta") if event["delta"]["stop_reason"] == "max_tokens" => {
+                warn!("Anthropic response truncated at max_tokens={}", max_tokens);
+            }
+            Some("error") => {
+                let message = event["error"]["message"].as_str().unwrap_or("unknown error");
+                return Err(anyhow!("AI provider stream failed: {}", message));
+            }
+            _ => {}
+        }
+        Ok(())
+    })
+    .await?;
+    Ok(text)
+}
+
+fn anthropic_body(model: &str, system: &str, messages: &[ChatMessage], max_tokens: u32, stream: bool) -> Value {
+    json!({
+        "model": model,
+        "system": system,
+        "messages": messages,
+        "max_tokens": max_tokens,
+        "temperature": 0.2,
+        "stream": stream,
+    })
+}
+
+async fn anthropic_request(client: &reqwest::Client, base_url: &str, api_key: &str, body: Value) -> Result<reqwest::Response> {
+    let url = format!("{}/messages", base_url.trim_end_matches('/'));
+    send_with_retry(|| {
+        client
+            .post(&url)
+            .header("x-api-key", api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&body)
+    })
+    .await
+}
+
+// 按行解析 SSE，把每个 data 字段交给 on_data；OpenAI 以 [DONE] 结束
+async fn read_sse(mut response: reqwest::Response, mut on_data: impl FnMut(&str) -> Result<()>) -> Result<()> {
+    let mut buffer = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim_end().strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim_start();
+            if data == "[DONE]" {
+                return Ok(());
+            }
+            on_data(data)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use crate::export::ExportOptions;
+use crate::proxy::{HttpTransaction, ProxyServer};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+// 两个请求之间的停顿超过该值时截断，避免录制时的长时间空闲进入压测脚本
+const MAX_THINK_TIME_MS: f64 = 30_000.0;
+// 低于该值的停顿视为浏览器并发请求，不插入 sleep/pause
+const MIN_THINK_TIME_MS: f64 = 50.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LoadTestFormat {
+    K6,
+    Gatling,
+}
+
+struct Step<'a> {
+    transaction: &'a HttpTransaction,
+    headers: Vec<(&'a String, &'a String)>,
+    // 文本原样嵌入脚本，二进制为 base64
+    body: Option<(String, bool)>,
+    // 本请求结束到下一个请求开始之间的停顿
+    think_time_ms: f64,
+}
+
+// 按录制顺序生成压测脚本；CORS 预检由浏览器自动发出，不写入脚本
+pub fn generate(format: LoadTestFormat, transactions: &[HttpTransaction], options: &ExportOptions) -> String {
+    let mut ordered: Vec<&HttpTransaction> = transactions.iter().filter(|t| !t.is_preflight()).collect();
+    ordered.sort_by_key(|t| t.request.timestamp);
+
+    let steps: Vec<Step> = ordered
+        .iter()
+        .enumerate()
+        .map(|(i, t)| Step {
+            transaction: t,
+            headers: replayable_headers(t),
+            body: options
+                .encode_body(&t.request.body)
+                .map(|body| (body.text, body.encoding.is_some())),
+            think_time_ms: ordered.get(i + 1).map(|next| think_time_ms(t, next, options)).unwrap_or(0.0),
+        })
+        .collect();
+
+    match format {
+        LoadTestFormat::K6 => k6_script(&steps),
+        LoadTestFormat::Gatling => gatling_simulation(&steps),
+    }
+}
+
+fn replayable_headers(transaction: &HttpTransaction) -> Vec<(&String, &String)> {
+    let mut headers: Vec<(&String, &String)> = transaction
+        .request
+        .headers
+        .iter()
+        .filter(|(name, _)| {
+            !ProxyServer::is_hop_by_hop(name)
+                && !name.eq_ignore_ascii_case("host")
+                && !name.eq_ignore_ascii_case("content-length")
+        })
+        .collect();
+    headers.sort();
+    headers
+}
+
+fn think_time_ms(current: &HttpTransaction, next: &HttpTransaction, options: &ExportOptions) -> f64 {
+    let finished = current.request.timestamp
+        + chrono::Duration::from_std(current.duration.unwrap_or_default()).unwrap_or_default();
+    let gap = (next.request.timestamp - finished).to_std().unwrap_or_default();
+    let gap = options.round_ms(gap).min(MAX_THINK_TIME_MS);
+    if gap < MIN_THINK_TIME_MS {
+        0.0
+    } else {
+        gap
+    }
+}
+
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+fn k6_script(steps: &[Step]) -> String {
+    let mut script = String::new();
+    script.push_str("import http from 'k6/http';\n");
+    script.push_str("import encoding from 'k6/encoding';\n");
+    script.push_str("import { check, sleep } from 'k6';\n\n");
+    script.push_str("// Generated by PacketMind from captured traffic\n");
+    // 录制的流量中每一跳重定向都是单独的请求，脚本中不再自动跟随
+    script.push_str("export const options = {\n  vus: 1,\n  iterations: 1,\n  maxRedirects: 0,\n};\n\n");
+    script.push_str("export default function () {\n  let res;\n");
+
+    for step in steps {
+        let request = &step.transaction.request;
+        let headers: Vec<String> = step
+            .headers
+            .iter()
+            .map(|(name, value)| format!("      {}: {},", quote(name), quote(value)))
+            .collect();
+        let body = match &step.body {
+            Some((text, true)) => format!("encoding.b64decode({}, 'std')", quote(text)),
+            Some((text, false)) => quote(text),
+            None => "null".to_string(),
+        };
+
+        let _ = writeln!(script);
+        let _ = writeln!(
+            script,
+            "  res = http.request({}, {}, {}, {{\n    headers: {{\n{}\n    }},\n  }});",
+            quote(&request.method),
+            quote(&request.url),
+            body,
+            headers.join("\n")
+        );
+        if let Some(status) = step.transaction.response.as_ref().map(|r| r.status) {
+            let _ = writeln!(
+                script,
+                "  check(res, {{ 'status is {}': (r) => r.status === {} }});",
+                status, status
+            );
+        }
+        if step.think_time_ms > 0.0 {
+            let _ = writeln!(script, "  sleep({});", step.think_time_ms / 1000.0);
+        }
+    }
+
+    script.push_str("}\n");
+    script
+}
+
+// Gatling EL 会解析 #{...}，录制内容中的字面量需要转义
+fn gatling_quote(value: &str) -> String {
+    quote(value).replace("#{", "\\\\#{")
+}
+
+fn gatling_simulation(steps: &[Step]) -> String {
+    let mut script = String::new();
+    script.push_str("import scala.concurrent.duration._\n\n");
+    script.push_str("import io.gatling.core.Predef._\n");
+    script.push_str("import io.gatling.http.Predef._\n\n");
+    script.push_str("// Generated by PacketMind from captured traffic\n");
+    script.push_str("class RecordedSimulation extends Simulation {\n\n");
+    script.push_str("  val httpProtocol = http.disableFollowRedirect\n\n");
+    script.push_str("  val scn = scenario(\"Recorded\")");
+
+    for (i, step) in steps.iter().enumerate() {
+        let request = &step.transaction.request;
+        let _ = write!(
+            script,
+            "\n    .exec(\n      http(\"request_{}\")\n        .httpRequest({}, {})",
+            i,
+            quote(&request.method),
+            gatling_quote(&request.url)
+        );
+        if !step.headers.is_empty() {
+            let headers: Vec<String> = step
+                .headers
+                .iter()
+                .map(|(name, value)| format!("{} -> {}", quote(name), gatling_quote(value)))
+                .collect();
+            let _ = write!(script, "\n        .headers(Map({}))", headers.join(", "));
+        }
+        match &step.body {
+            Some((text, true)) => {
+                let _ = write!(
+                    script,
+                    "\n        .body(ByteArrayBody(java.util.Base64.getDecoder.decode({})))",
+                    quote(text)
+                );
+            }
+            Some((text, false)) => {
+                let _ = write!(script, "\n        .body(StringBody({}))", gatling_quote(text));
+            }
+            None => {}
+        }
+        if let Some(status) = step.transaction.response.as_ref().map(|r| r.status) {
+            let _ = write!(script, "\n        .check(status.is({}))", status);
+        }
+        script.push_str("\n    )");
+        if step.think_time_ms > 0.0 {
+            let _ = write!(script, "\n    .pause({}.milliseconds)", step.think_time_ms.round() as u64);
+        }
+    }
+
+    script.push_str("\n\n  setUp(scn.inject(atOnceUsers(1))).protocols(httpProtocol)\n}\n");
+    script
+}
@@ -0,0 +1,391 @@
+use anyhow::{anyhow, Result};
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType,
+    ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose, SanType,
+};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+pub const CA_CERT_FILE_NAME: &str = "packetmind-ca.pem";
+pub const CA_KEY_FILE_NAME: &str = "packetmind-ca.key";
+const CA_COMMON_NAME: &str = "PacketMind AI Root CA";
+
+// 证书缓存上限，超出后整体清空重建
+const MAX_CACHED_HOSTS: usize = 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaCertInfo {
+    pub common_name: String,
+    pub fingerprint_sha256: String,
+    pub cert_path: Option<String>,
+    pub pem: String,
+}
+
+// 本地根证书，用于为每个主机动态签发叶子证书
+pub struct CertificateAuthority {
+    cert: Certificate,
+    key: KeyPair,
+    cert_pem: String,
+    cert_der: CertificateDer<'static>,
+    server_configs: Mutex<HashMap<String, Arc<rustls::ServerConfig>>>,
+}
+
+// 根证书私钥可用于为任意主机签发受信任的证书，只允许当前用户读写
+fn write_private_key(path: &Path, pem: &str) -> Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // mode 只在新建文件时生效，覆盖已有文件时同样收紧权限
+    restrict_to_owner(path)?;
+    file.write_all(pem.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restrict_to_owner(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict_to_owner(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+impl CertificateAuthority {
+    pub fn generate() -> Result<Self> {
+        let mut params = CertificateParams::default();
+        let mut name = DistinguishedName::new();
+        name.push(DnType::CommonName, CA_COMMON_NAME);
+        name.push(DnType::OrganizationName, "PacketMind AI");
+        params.distinguished_name = name;
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.key_usages = vec![
+            KeyUsagePurpose::KeyCertSign,
+            KeyUsagePurpose::CrlSign,
+            KeyUsagePurpose::DigitalSignature,
+        ];
+        let (not_before, not_after) = validity_window(365 * 10);
+        params.not_before = not_before;
+        params.not_after = not_after;
+
+        let key = KeyPair::generate()?;
+        let cert = params.self_signed(&key)?;
+        let cert_pem = cert.pem();
+        Self::from_parts(cert, key, cert_pem)
+    }
+
+    pub fn from_pem(cert_pem: &str, key_pem: &str) -> Result<Self> {
+        let key = KeyPair::from_pem(key_pem)?;
+        // 用原证书的参数和私钥重建签发者；签发出的叶子证书可被已安装的原证书验证
+        let params = CertificateParams::from_ca_cert_pem(cert_pem)?;
+        let cert = params.self_signed(&key)?;
+        let mut ca = Self::from_parts(cert, key, cert_pem.to_string())?;
+        ca.cert_der = pem_to_der(cert_pem)?;
+        Ok(ca)
+    }
+
+    fn from_parts(cert: Certificate, key: KeyPair, cert_pem: String) -> Result<Self> {
+        let cert_der = cert.der().clone();
+        Ok(Self {
+            cert,
+            key,
+            cert_pem,
+            cert_der,
+            server_configs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn load_or_generate(dir: &Path) -> Result<Self> {
+        let cert_path = dir.join(CA_CERT_FILE_NAME);
+        let key_path = dir.join(CA_KEY_FILE_NAME);
+
+        if cert_path.exists() && key_path.exists() {
+            let cert_pem = std::fs::read_to_string(&cert_path)?;
+            let key_pem = std::fs::read_to_string(&key_path)?;
+            // 旧版本按默认权限写入的私钥，加载时一并收紧
+            if let Err(e) = restrict_to_owner(&key_path) {
+                tracing::warn!("Failed to restrict permissions of {}: {}", key_path.display(), e);
+            }
+            return Self::from_pem(&cert_pem, &key_pem);
+        }
+
+        let ca = Self::generate()?;
+        ca.save(dir)?;
+        Ok(ca)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(CA_CERT_FILE_NAME), &self.cert_pem)?;
+        write_private_key(&dir.join(CA_KEY_FILE_NAME), &self.key.serialize_pem())?;
+        Ok(())
+    }
+
+    pub fn cert_pem(&self) -> &str {
+        &self.cert_pem
+    }
+
+    pub fn info(&self, cert_path: Option<&Path>) -> CaCertInfo {
+        use sha2::{Digest, Sha256};
+        let fingerprint = Sha256::digest(self.cert_der.as_ref())
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(":");
+        CaCertInfo {
+            common_name: CA_COMMON_NAME.to_string(),
+            fingerprint_sha256: fingerprint,
+            cert_path: cert_path.map(|p| p.to_string_lossy().to_string()),
+            pem: self.cert_pem.clone(),
+        }
+    }
+
+    // 获取（或签发并缓存）指定主机的 TLS 服务端配置
+    pub fn server_config(&self, host: &str) -> Result<Arc<rustls::ServerConfig>> {
+        let host = host.to_lowercase();
+        if let Some(config) = self.server_configs.lock().map_err(|_| anyhow!("Certificate cache poisoned"))?.get(&host) {
+            return Ok(config.clone());
+        }
+
+        let config = Arc::new(self.build_server_config(&host)?);
+        let mut cache = self.server_configs.lock().map_err(|_| anyhow!("Certificate cache poisoned"))?;
+        if cache.len() >= MAX_CACHED_HOSTS {
+            cache.clear();
+        }
+        cache.insert(host, config.clone());
+        Ok(config)
+    }
+
+    fn build_server_config(&self, host: &str) -> Result<rustls::ServerConfig> {
+        let mut params = CertificateParams::default();
+        let mut name = DistinguishedName::new();
+        name.push(DnType::CommonName, host);
+        params.distinguished_name = name;
+        params.subject_alt_names = vec![match host.parse::<std::net::IpAddr>() {
+            Ok(ip) => SanType::IpAddress(ip),
+            Err(_) => SanType::DnsName(host.try_into()?),
+        }];
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature, KeyUsagePurpose::KeyEncipherment];
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        params.use_authority_key_identifier_extension = true;
+        // 部分平台拒绝有效期超过 825 天的叶子证书
+        let (not_before, not_after) = validity_window(365);
+        params.not_before = not_before;
+        params.not_after = not_after;
+
+        let leaf_key = KeyPair::generate()?;
+        let leaf = params.signed_by(&leaf_key, &self.cert, &self.key)?;
+
+        let chain = vec![leaf.der().clone(), self.cert_der.clone()];
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(leaf_key.serialize_der()));
+
+        let mut config = rustls::ServerConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(chain, key)?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(config)
+    }
+}
+
+fn validity_window(days: i64) -> (time::OffsetDateTime, time::OffsetDateTime) {
+    // 提前一天生效，避免客户端时钟偏差
+    let now = time::OffsetDateTime::now_utc();
+    (now - time::Duration::days(1), now + time::Duration::days(days))
+}
+
+fn pem_to_der(pem: &str) -> Result<CertificateDer<'static>> {
+    use base64::{Engine as _, engine::general_purpose};
+    let body: String = pem
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let der = general_purpose::STANDARD.decode(body.trim())?;
+    Ok(CertificateDer::from(der))
+}
+
+// 将根证书加入系统信任库，通常会弹出系统授权提示
+#[cfg(target_os = "macos")]
+pub async fn install_ca_cert(cert_path: &Path) -> Result<String> {
+    let keychain = dirs_home()?.join("Library/Keychains/login.keychain-db");
+    run_installer(
+        "security",
+        &[
+            "add-trusted-cert",
+            "-r",
+            "trustRoot",
+            "-k",
+            &keychain.to_string_lossy(),
+            &cert_path.to_string_lossy(),
+        ],
+    )
+    .await?;
+    Ok("CA certificate added to the login keychain".to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn dirs_home() -> Result<std::path::PathBuf> {
+    std::env::var_os("HOME")
+        .map(std::path::PathBuf::from)
+        .ok_or_else(|| anyhow!("HOME is not set"))
+}
+
+#[cfg(target_os = "windows")]
+pub async fn install_ca_cert(cert_path: &Path) -> Result<String> {
+    // 安装到当前用户的受信任根证书存储，无需管理员权限
+    run_installer("certutil", &["-user", "-addstore", "Root", &cert_path.to_string_lossy()]).await?;
+    Ok("CA certificate added to the current user's Root store".to_string())
+}
+
+#[cfg(target_os = "linux")]
+pub async fn install_ca_cert(cert_path: &Path) -> Result<String> {
+    // Debian/Ubuntu 与 Fedora/RHEL 的证书目录和刷新命令不同
+    let (anchor_dir, update_command) = if Path::new("/usr/local/share/ca-certificates").exists() {
+        ("/usr/local/share/ca-certificates", "update-ca-certificates")
+    } else if Path::new("/etc/pki/ca-trust/source/anchors").exists() {
+        ("/etc/pki/ca-trust/source/anchors", "update-ca-trust")
+    } else {
+        return Err(anyhow!(
+            "Unsupported distribution: install {} into the system trust store manually",
+            cert_path.display()
+        ));
+    };
+
+    let target = format!("{}/packetmind-ca.crt", anchor_dir);
+    // 路径作为位置参数传入固定脚本，不拼接进命令行；合并为一次 pkexec 只需授权一次
+    let cert_path = cert_path.to_string_lossy();
+    run_installer(
+        "pkexec",
+        &["sh", "-c", "cp -- \"$1\" \"$2\" && \"$3\"", "sh", &cert_path, &target, update_command],
+    )
+    .await?;
+    Ok(format!("CA certificate installed to {}", target))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub async fn install_ca_cert(cert_path: &Path) -> Result<String> {
+    Err(anyhow!(
+        "Automatic installation is not supported on this platform: trust {} manually",
+        cert_path.display()
+    ))
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+async fn run_installer(program: &str, args: &[&str]) -> Result<()> {
+    let output = tokio::process::Command::new(program).args(args).output().await?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+// 检查根证书是否已被系统信任；无法判断时返回 None
+#[cfg(target_os = "macos")]
+pub async fn is_ca_trusted(cert_path: &Path) -> Option<bool> {
+    let output = tokio::process::Command::new("security")
+        .args(["verify-cert", "-c", &cert_path.to_string_lossy()])
+        .output()
+        .await
+        .ok()?;
+    Some(output.status.success())
+}
+
+#[cfg(target_os = "windows")]
+pub async fn is_ca_trusted(_cert_path: &Path) -> Option<bool> {
+    let output = tokio::process::Command::new("certutil")
+        .args(["-user", "-verifystore", "Root", CA_COMMON_NAME])
+        .output()
+        .await
+        .ok()?;
+    Some(output.status.success())
+}
+
+#[cfg(target_os = "linux")]
+pub async fn is_ca_trusted(cert_path: &Path) -> Option<bool> {
+    const BUNDLES: [&str; 2] = ["/etc/ssl/certs/ca-certificates.crt", "/etc/pki/tls/certs/ca-bundle.crt"];
+    let pem = tokio::fs::read_to_string(cert_path).await.ok()?;
+    // 比较证书主体部分，忽略换行差异
+    let body: String = pem.lines().filter(|line| !line.starts_with("-----")).collect();
+
+    for bundle in BUNDLES {
+        if let Ok(content) = tokio::fs::read_to_string(bundle).await {
+            let content: String = content.lines().collect();
+            return Some(content.contains(&body));
+        }
+    }
+    None
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub async fn is_ca_trusted(_cert_path: &Path) -> Option<bool> {
+    None
+}
+
+// TLS 记录层的握手类型字节
+pub fn is_tls_client_hello(first_byte: u8) -> bool {
+    first_byte == 0x16
+}
+
+// 将已预读的字节放回流的开头
+pub struct Rewind<T> {
+    prefix: Option<Vec<u8>>,
+    inner: T,
+}
+
+impl<T> Rewind<T> {
+    pub fn new(prefix: Vec<u8>, inner: T) -> Self {
+        Self {
+            prefix: if prefix.is_empty() { None } else { Some(prefix) },
+            inner,
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Rewind<T> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        if let Some(mut prefix) = self.prefix.take() {
+            let n = prefix.len().min(buf.remaining());
+            buf.put_slice(&prefix[..n]);
+            if n < prefix.len() {
+                self.prefix = Some(prefix.split_off(n));
+            }
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Rewind<T> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,283 @@
+use crate::proxy::{HttpRequest, HttpResponse, HttpTransaction};
+use crate::websocket::{WsDirection, WsMessage, WsMessageKind};
+use std::collections::HashMap;
+
+// mitmproxy 10 的流文件格式版本，更新的 mitmproxy 会在加载时自动升级
+pub const FLOW_FORMAT_VERSION: i64 = 20;
+
+// mitmproxy 流文件由逐个流的 tnetstring 直接拼接而成
+pub enum TNetString {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Bytes(Vec<u8>),
+    Str(String),
+    List(Vec<TNetString>),
+    Dict(Vec<(&'static str, TNetString)>),
+}
+
+impl TNetString {
+    // 格式为 "<长度>:<内容><类型标记>"
+    pub fn dump(&self, out: &mut Vec<u8>) {
+        let (payload, tag) = match self {
+            TNetString::Null => (Vec::new(), b'~'),
+            TNetString::Bool(value) => (value.to_string().into_bytes(), b'!'),
+            TNetString::Int(value) => (value.to_string().into_bytes(), b'#'),
+            TNetString::Float(value) => (format!("{:?}", value).into_bytes(), b'^'),
+            TNetString::Bytes(value) => (value.clone(), b','),
+            TNetString::Str(value) => (value.as_bytes().to_vec(), b';'),
+            TNetString::List(items) => {
+                let mut payload = Vec::new();
+                for item in items {
+                    item.dump(&mut payload);
+                }
+                (payload, b']')
+            }
+            TNetString::Dict(entries) => {
+                let mut payload = Vec::new();
+                for (key, value) in entries {
+                    TNetString::Str(key.to_string()).dump(&mut payload);
+                    value.dump(&mut payload);
+                }
+                (payload, b'}')
+            }
+        };
+        out.extend_from_slice(payload.len().to_string().as_bytes());
+        out.push(b':');
+        out.extend_from_slice(&payload);
+        out.push(tag);
+    }
+}
+
+fn str(value: &str) -> TNetString {
+    TNetString::Str(value.to_string())
+}
+
+fn bytes(value: &[u8]) -> TNetString {
+    TNetString::Bytes(value.to_vec())
+}
+
+fn optional<T>(value: Option<T>, f: impl FnOnce(T) -> TNetString) -> TNetString {
+    value.map(f).unwrap_or(TNetString::Null)
+}
+
+fn timestamp(time: chrono::DateTime<chrono::Utc>) -> TNetString {
+    TNetString::Float(time.timestamp_micros() as f64 / 1_000_000.0)
+}
+
+fn address(host: &str, port: u16) -> TNetString {
+    TNetString::List(vec![str(host), TNetString::Int(port as i64)])
+}
+
+// 生成可由 mitmproxy/mitmdump -r 读取的流文件内容
+pub fn flows(transactions: &[HttpTransaction], proxy_port: u16) -> Vec<u8> {
+    let mut out = Vec::new();
+    for transaction in transactions {
+        let Ok(url) = url::Url::parse(&transaction.request.url) else {
+            continue;
+        };
+        flow(transaction, &url, proxy_port).dump(&mut out);
+    }
+    out
+}
+
+fn flow(transaction: &HttpTransaction, url: &url::Url, proxy_port: u16) -> TNetString {
+    let request = &transaction.request;
+    let finished = request.timestamp
+        + chrono::Duration::from_std(transaction.duration.unwrap_or_default()).unwrap_or_default();
+    let tls = url.scheme() == "https" || url.scheme() == "wss";
+    let host = url.host_str().unwrap_or_default();
+    let port = url.port_or_known_default().unwrap_or(80);
+    let response_end = transaction.response.as_ref().map(|_| finished);
+
+    let client_conn = TNetString::Dict(vec![
+        ("id", str(&uuid::Uuid::new_v4().to_string())),
+        ("peername", address("127.0.0.1", 0)),
+        ("sockname", address("127.0.0.1", proxy_port)),
+        ("transport_protocol", str("tcp")),
+        ("error", TNetString::Null),
+        ("tls", TNetString::Bool(tls)),
+        ("certificate_list", TNetString::List(Vec::new())),
+        ("alpn", TNetString::Null),
+        ("alpn_offers", TNetString::List(Vec::new())),
+        ("cipher", TNetString::Null),
+        ("cipher_list", TNetString::List(Vec::new())),
+        ("tls_version", TNetString::Null),
+        ("sni", if tls { str(host) } else { TNetString::Null }),
+        ("timestamp_start", timestamp(request.timestamp)),
+        ("timestamp_end", optional(response_end, timestamp)),
+        ("timestamp_tls_setup", TNetString::Null),
+        ("mitmcert", TNetString::Null),
+        ("proxy_mode", str("regular")),
+    ]);
+    let server_conn = TNetString::Dict(vec![
+        ("id", str(&uuid::Uuid::new_v4().to_string())),
+        ("address", address(host, port)),
+        ("peername", TNetString::Null),
+        ("sockname", TNetString::Null),
+        ("transport_protocol", str("tcp")),
+        ("error", TNetString::Null),
+        ("tls", TNetString::Bool(tls)),
+        ("certificate_list", TNetString::List(Vec::new())),
+        ("alpn", TNetString::Null),
+        ("alpn_offers", TNetString::List(Vec::new())),
+        ("cipher", TNetString::Null),
+        ("cipher_list", TNetString::List(Vec::new())),
+        ("tls_version", TNetString::Null),
+        ("sni", if tls { str(host) } else { TNetString::Null }),
+        ("timestamp_start", timestamp(request.timestamp)),
+        ("timestamp_end", optional(response_end, timestamp)),
+        ("timestamp_tcp_setup", TNetString::Null),
+        ("timestamp_tls_setup", TNetString::Null),
+        ("via", TNetString::Null),
+    ]);
+
+    // 重放和手工构造的请求在 mitmproxy 中同样标记为重放
+    let is_replay = transaction.replay_of.is_some() || transaction.tags.iter().any(|t| t == "composed");
+    TNetString::Dict(vec![
+        ("version", TNetString::Int(FLOW_FORMAT_VERSION)),
+        ("id", str(&transaction.id)),
+        ("type", str("http")),
+        ("error", TNetString::Null),
+        ("client_conn", client_conn),
+        ("server_conn", server_conn),
+        ("intercepted", TNetString::Bool(false)),
+        ("is_replay", if is_replay { str("request") } else { TNetString::Null }),
+        ("marked", str(if transaction.is_favorite { ":default:" } else { "" })),
+        ("metadata", TNetString::Dict(Vec::new())),
+        ("comment", str(transaction.notes.as_deref().unwrap_or_default())),
+        ("timestamp_created", timestamp(request.timestamp)),
+        ("backup", TNetString::Null),
+        ("request", request_state(request, url, port)),
+        (
+            "response",
+            optional(transaction.response.as_ref(), |r| response_state(r, finished)),
+        ),
+        (
+            "websocket",
+            if transaction.ws_messages.is_empty() {
+                TNetString::Null
+            } else {
+                websocket_state(&transaction.ws_messages)
+            },
+        ),
+    ])
+}
+
+// 多个 Set-Cookie 合并存储时以换行分隔，还原为多条
+fn headers(headers: &HashMap<String, String>) -> TNetString {
+    let mut fields: Vec<(&String, &str)> = headers
+        .iter()
+        .flat_map(|(name, value)| {
+            let values: Vec<&str> = if name.eq_ignore_ascii_case("set-cookie") {
+                value.lines().collect()
+            } else {
+                vec![value.as_str()]
+            };
+            values.into_iter().map(move |value| (name, value))
+        })
+        .collect();
+    fields.sort();
+    TNetString::List(
+        fields
+            .into_iter()
+            .map(|(name, value)| TNetString::List(vec![bytes(name.as_bytes()), bytes(value.as_bytes())]))
+            .collect(),
+    )
+}
+
+fn http_version(version: Option<&str>) -> TNetString {
+    bytes(version.unwrap_or("HTTP/1.1").as_bytes())
+}
+
+fn request_state(request: &HttpRequest, url: &url::Url, port: u16) -> TNetString {
+    // WebSocket 在 mitmproxy 中是升级后的 HTTP 流
+    let scheme = match url.scheme() {
+        "ws" => "http",
+        "wss" => "https",
+        other => other,
+    };
+    let mut path = url.path().to_string();
+    if let Some(query) = url.query() {
+        path.push('?');
+        path.push_str(query);
+    }
+    TNetString::Dict(vec![
+        ("http_version", http_version(request.version.as_deref())),
+        ("headers", headers(&request.headers)),
+        ("content", bytes(&request.body)),
+        ("trailers", TNetString::Null),
+        ("timestamp_start", timestamp(request.timestamp)),
+        ("timestamp_end", timestamp(request.timestamp)),
+        ("host", str(url.host_str().unwrap_or_default())),
+        ("port", TNetString::Int(port as i64)),
+        ("method", bytes(request.method.as_bytes())),
+        ("scheme", bytes(scheme.as_bytes())),
+        ("authority", bytes(b"")),
+        ("path", bytes(path.as_bytes())),
+    ])
+}
+
+fn response_state(response: &HttpResponse, finished: chrono::DateTime<chrono::Utc>) -> TNetString {
+    // HTTP/2 没有原因短语
+    let reason = match response.version.as_deref() {
+        Some(version) if version.starts_with("HTTP/2") || version.starts_with("HTTP/3") => "",
+        _ => hyper::StatusCode::from_u16(response.status)
+            .ok()
+            .and_then(|s| s.canonical_reason())
+            .unwrap_or_default(),
+    };
+    TNetString::Dict(vec![
+        ("http_version", http_version(response.version.as_deref())),
+        ("headers", headers(&response.headers)),
+        ("content", bytes(&response.body)),
+        ("trailers", TNetString::Null),
+        ("timestamp_start", timestamp(response.timestamp.min(finished))),
+        ("timestamp_end", timestamp(finished.max(response.timestamp))),
+        ("status_code", TNetString::Int(response.status as i64)),
+        ("reason", bytes(reason.as_bytes())),
+    ])
+}
+
+// mitmproxy 只记录文本与二进制消息，关闭帧记录为关闭方、状态码与原因
+fn websocket_state(messages: &[WsMessage]) -> TNetString {
+    let close = messages.iter().find(|m| m.kind == WsMessageKind::Close);
+    let (close_code, close_reason) = close
+        .map(|m| {
+            let payload = String::from_utf8_lossy(&m.payload);
+            let (code, reason) = payload.split_once(' ').unwrap_or((&payload, ""));
+            (code.parse::<i64>().ok(), reason.to_string())
+        })
+        .unwrap_or((None, String::new()));
+
+    let data_messages = messages
+        .iter()
+        .filter_map(|m| {
+            let opcode = match m.kind {
+                WsMessageKind::Text => 1,
+                WsMessageKind::Binary => 2,
+                _ => return None,
+            };
+            Some(TNetString::List(vec![
+                TNetString::Int(opcode),
+                TNetString::Bool(m.direction == WsDirection::ClientToServer),
+                bytes(&m.payload),
+                timestamp(m.timestamp),
+                TNetString::Bool(false),
+                TNetString::Bool(false),
+            ]))
+        })
+        .collect();
+
+    TNetString::Dict(vec![
+        ("messages", TNetString::List(data_messages)),
+        (
+            "closed_by_client",
+            optional(close, |m| TNetString::Bool(m.direction == WsDirection::ClientToServer)),
+        ),
+        ("close_code", optional(close_code, TNetString::Int)),
+        ("close_reason", optional(close.map(|_| close_reason), TNetString::Str)),
+        ("timestamp_end", optional(close, |m| timestamp(m.timestamp))),
+    ])
+}
@@ -0,0 +1,417 @@
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+// 单个数组最多生成的记录数
+pub const MAX_RECORD_COUNT: usize = 1000;
+// 未指定记录数时数组长度的上限
+const DEFAULT_MAX_ITEMS: usize = 5;
+const MAX_DEPTH: usize = 16;
+// 单次生成的值总数上限，嵌套数组的 schema 不会按层数指数膨胀
+const MAX_NODES: usize = 100_000;
+// 可选字段出现的概率（百分比）
+const OPTIONAL_FIELD_PERCENT: u64 = 70;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MockLocale {
+    #[default]
+    En,
+    Zh,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MockOptions {
+    // 最外层记录列表（元素为对象、不在其他数组中的数组）生成的条数，未指定时按观察到的长度范围随机
+    #[serde(default)]
+    pub record_count: Option<usize>,
+    #[serde(default)]
+    pub locale: MockLocale,
+}
+
+struct LocaleData {
+    first_names: &'static [&'static str],
+    last_names: &'static [&'static str],
+    cities: &'static [&'static str],
+    countries: &'static [&'static str],
+    streets: &'static [&'static str],
+    companies: &'static [&'static str],
+    words: &'static [&'static str],
+    currency: &'static str,
+}
+
+const EN: LocaleData = LocaleData {
+    first_names: &["James", "Mary", "Robert", "Patricia", "John", "Jennifer", "Michael", "Linda", "David", "Emma"],
+    last_names: &["Smith", "Johnson", "Williams", "Brown", "Jones", "Garcia", "Miller", "Davis", "Wilson", "Taylor"],
+    cities: &["New York", "London", "Toronto", "Sydney", "Chicago", "Seattle", "Boston", "Austin"],
+    countries: &["United States", "United Kingdom", "Canada", "Australia", "Germany", "France"],
+    streets: &["Main St", "Oak Ave", "Maple Rd", "Park Blvd", "Cedar Ln", "Elm St"],
+    companies: &["Acme Corp", "Contoso Ltd", "Northwind Traders", "Fabrikam Inc", "Globex Systems"],
+    words: &[
+        "project", "update", "release", "report", "review", "feature", "summary", "meeting", "design", "plan",
+        "quick", "new", "weekly", "final", "draft", "team", "customer", "order", "service", "account",
+    ],
+    currency: "USD",
+};
+
+const ZH: LocaleData = LocaleData {
+    first_names: &["伟", "芳", "娜", "敏", "静", "强", "磊", "洋", "艳", "杰", "婷", "军"],
+    last_names: &["王", "李", "张", "刘", "陈", "杨", "赵", "黄", "周", "吴"],
+    cities: &["北京", "上海", "广州", "深圳", "杭州", "成都", "南京", "武汉"],
+    countries: &["中国"],
+    streets: &["中山路", "人民路", "解放路", "建设路", "长江路", "和平路"],
+    companies: &["星辰科技有限公司", "蓝海信息技术有限公司", "华远贸易有限公司", "云帆网络科技有限公司"],
+    words: &[
+        "项目", "更新", "发布", "报告", "评审", "功能", "总结", "会议", "设计", "计划",
+        "每周", "最终", "草稿", "团队", "客户", "订单", "服务", "账户", "测试", "版本",
+    ],
+    currency: "CNY",
+};
+
+// 借用 v4 UUID 的随机位作种子的 xorshift，足够生成测试数据
+struct Rng(u64);
+
+impl Rng {
+    fn new() -> Self {
+        Self((uuid::Uuid::new_v4().as_u128() as u64) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        if n == 0 { 0 } else { self.next() % n }
+    }
+
+    // [min, max] 内的整数；跨度按 i128 计算，schema 中极端的上下限不会溢出
+    fn range(&mut self, min: i64, max: i64) -> i64 {
+        if max <= min {
+            return min;
+        }
+        let span = max as i128 - min as i128 + 1;
+        let offset = match u64::try_from(span) {
+            Ok(span) => self.below(span),
+            // 覆盖整个 i64 取值范围
+            Err(_) => self.next(),
+        };
+        (min as i128 + offset as i128) as i64
+    }
+
+    fn percent(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len() as u64) as usize]
+    }
+}
+
+struct Generator<'a> {
+    options: &'a MockOptions,
+    locale: &'static LocaleData,
+    rng: Rng,
+    // 自增 id，保证同一响应内的记录 id 不重复
+    next_id: i64,
+    // 当前所在的数组层数，record_count 只作用于最外层
+    array_depth: usize,
+    nodes: usize,
+}
+
+// 按 JSON Schema 生成一份符合结构的数据；字段名用于挑选更像真实数据的取值
+pub fn generate(schema: &Value, options: &MockOptions) -> Value {
+    let mut generator = Generator {
+        options,
+        locale: match options.locale {
+            MockLocale::En => &EN,
+            MockLocale::Zh => &ZH,
+        },
+        rng: Rng::new(),
+        next_id: 1,
+        array_depth: 0,
+        nodes: 0,
+    };
+    generator.value(schema, None, 0)
+}
+
+impl Generator<'_> {
+    fn value(&mut self, schema: &Value, field: Option<&str>, depth: usize) -> Value {
+        self.nodes += 1;
+        if let Some(values) = schema["enum"].as_array().filter(|v| !v.is_empty()) {
+            // 枚举中的 null 只在没有其他取值时使用
+            let non_null: Vec<&Value> = values.iter().filter(|v| !v.is_null()).collect();
+            if non_null.is_empty() {
+                return Value::Null;
+            }
+            return non_null[self.rng.below(non_null.len() as u64) as usize].clone();
+        }
+        let type_name = match &schema["type"] {
+            Value::String(name) => name.as_str(),
+            Value::Array(names) => names
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|name| *name != "null")
+                .unwrap_or("null"),
+            _ if schema["properties"].is_object() => "object",
+            _ if schema["items"].is_object() => "array",
+            _ => "string",
+        };
+        match type_name {
+            "object" if depth < MAX_DEPTH => self.object(schema, depth),
+            "array" if depth < MAX_DEPTH => self.array(schema, field, depth),
+            "object" => json!({}),
+            "array" => json!([]),
+            "integer" => json!(self.integer(schema, field)),
+            "number" => json!(self.number(schema, field)),
+            "boolean" => json!(self.rng.percent(50)),
+            "null" => Value::Null,
+            _ => json!(self.string(schema, field)),
+        }
+    }
+
+    fn object(&mut self, schema: &Value, depth: usize) -> Value {
+        let required: Vec<&str> =
+            schema["required"].as_array().map(|r| r.iter().filter_map(Value::as_str).collect()).unwrap_or_default();
+        let mut object = Map::new();
+        if let Some(properties) = schema["properties"].as_object() {
+            for (name, property) in properties {
+                if required.contains(&name.as_str()) || self.rng.percent(OPTIONAL_FIELD_PERCENT) {
+                    let value = self.value(property, Some(name), depth + 1);
+                    object.insert(name.clone(), value);
+                }
+            }
+        }
+        Value::Object(object)
+    }
+
+    fn array(&mut self, schema: &Value, field: Option<&str>, depth: usize) -> Value {
+        let items = &schema["items"];
+        let is_records = items["type"] == "object" || items["properties"].is_object();
+        let count = match self.options.record_count {
+            Some(count) if is_records && self.array_depth == 0 => count.min(MAX_RECORD_COUNT),
+            _ => {
+                let min = schema["minItems"].as_u64().unwrap_or(1) as i64;
+                let max = schema["maxItems"].as_u64().map_or(DEFAULT_MAX_ITEMS as i64, |m| m.min(DEFAULT_MAX_ITEMS as u64) as i64);
+                self.rng.range(min.min(max), max) as usize
+            }
+        };
+        // 数组元素沿用数组字段名推断取值，如 tags、emails
+        let item_field = field.map(|f| f.strip_suffix('s').unwrap_or(f));
+        self.array_depth += 1;
+        let mut values = Vec::with_capacity(count);
+        // 达到总数上限后数组不再追加元素
+        while values.len() < count && self.nodes < MAX_NODES {
+            values.push(self.value(items, item_field, depth + 1));
+        }
+        self.array_depth -= 1;
+        Value::Array(values)
+    }
+
+    fn integer(&mut self, schema: &Value, field: Option<&str>) -> i64 {
+        let name = normalized(field);
+        if name == "id" {
+            let id = self.next_id;
+            self.next_id += 1;
+            return id;
+        }
+        let minimum = schema["minimum"].as_f64().map(|m| m.ceil() as i64);
+        let maximum = schema["maximum"].as_f64().map(|m| m.floor() as i64);
+        match (minimum, maximum) {
+            (Some(min), Some(max)) => self.rng.range(min, max),
+            (Some(min), None) => self.rng.range(min, min.saturating_add(1000)),
+            (None, Some(max)) => self.rng.range(max.saturating_sub(1000), max),
+            (None, None) if name.contains("age") => self.rng.range(18, 70),
+            (None, None) if name.contains("year") => self.rng.range(2015, 2025),
+            (None, None) => self.rng.range(1, 1000),
+        }
+    }
+
+    fn number(&mut self, schema: &Value, field: Option<&str>) -> f64 {
+        let name = normalized(field);
+        let (default_min, default_max) = if name.contains("lat") {
+            (-90.0, 90.0)
+        } else if name.contains("lng") || name.contains("lon") {
+            (-180.0, 180.0)
+        } else {
+            (1.0, 1000.0)
+        };
+        let min = schema["minimum"].as_f64().unwrap_or(default_min);
+        let max = schema["maximum"].as_f64().unwrap_or(default_max).max(min);
+        let cents = self.rng.range((min * 100.0).ceil() as i64, (max * 100.0).floor() as i64);
+        cents as f64 / 100.0
+    }
+
+    fn string(&mut self, schema: &Value, field: Option<&str>) -> String {
+        match schema["format"].as_str() {
+            Some("date-time") => return self.recent_time().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            Some("date") => return self.recent_time().format("%Y-%m-%d").to_string(),
+            Some("email") => return self.email(),
+            Some("uuid") => return uuid::Uuid::new_v4().to_string(),
+            Some("uri") => return self.url(field),
+            Some("ipv4") => {
+                return format!("192.0.2.{}", self.rng.range(1, 254));
+            }
+            _ => {}
+        }
+        if let Some(value) = self.named_string(field) {
+            return value;
+        }
+        // 字段名无法推断时才使用抓到的真实取值
+        if let Some(examples) = schema["examples"].as_array().filter(|e| !e.is_empty()) {
+            if let Some(example) = examples[self.rng.below(examples.len() as u64) as usize].as_str() {
+                return example.to_string();
+            }
+        }
+        self.words(2)
+    }
+
+    // 按常见字段名生成取值
+    fn named_string(&mut self, field: Option<&str>) -> Option<String> {
+        let name = normalized(field);
+        if name.is_empty() {
+            return None;
+        }
+        let locale = self.locale;
+        let value = match name.as_str() {
+            "firstname" | "givenname" => self.rng.pick(locale.first_names).to_string(),
+            "lastname" | "surname" | "familyname" => self.rng.pick(locale.last_names).to_string(),
+            "name" | "fullname" | "displayname" | "nickname" | "author" | "owner" => self.full_name(),
+            "username" | "login" | "handle" => format!("user{}", self.rng.range(100, 9999)),
+            "city" => self.rng.pick(locale.cities).to_string(),
+            "country" => self.rng.pick(locale.countries).to_string(),
+            "company" | "organization" | "organisation" | "employer" => self.rng.pick(locale.companies).to_string(),
+            "currency" => locale.currency.to_string(),
+            "zip" | "zipcode" | "postcode" | "postalcode" => format!("{:05}", self.rng.range(10000, 99999)),
+            "id" | "uuid" | "guid" => uuid::Uuid::new_v4().to_string(),
+            "token" | "accesstoken" | "refreshtoken" | "apikey" | "secret" => {
+                uuid::Uuid::new_v4().simple().to_string()
+            }
+            // customer_name、authorName 等，排除文件名、主机名这类非人名字段
+            _ if has_suffix(field, "name")
+                && !["file", "host", "domain", "path", "type", "class", "tag", "key", "field"]
+                    .iter()
+                    .any(|k| name.starts_with(k)) =>
+            {
+                self.full_name()
+            }
+            _ if name.contains("email") => self.email(),
+            _ if name.contains("phone") || name.contains("mobile") || name == "tel" => self.phone(),
+            _ if name.contains("address") || name.contains("street") => self.address(),
+            _ if ["avatar", "image", "photo", "picture", "icon", "thumbnail", "logo"].iter().any(|k| name.contains(k)) => {
+                format!("https://picsum.photos/seed/{}/200/200", self.rng.range(1, 10_000))
+            }
+            _ if name.contains("url") || name.contains("link") || name.contains("website") || name.contains("homepage") => {
+                self.url(field)
+            }
+            _ if has_suffix(field, "at") || name.contains("date") || name.contains("time") => {
+                self.recent_time().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+            }
+            _ if ["description", "summary", "content", "bio", "body", "text", "message", "comment", "note"]
+                .iter()
+                .any(|k| name.contains(k)) =>
+            {
+                self.sentence()
+            }
+            _ if name.contains("title") || name.contains("subject") || name.contains("label") => self.words(3),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn full_name(&mut self) -> String {
+        let (first, last) = (self.rng.pick(self.locale.first_names), self.rng.pick(self.locale.last_names));
+        match self.options.locale {
+            MockLocale::En => format!("{} {}", first, last),
+            MockLocale::Zh => format!("{}{}", last, first),
+        }
+    }
+
+    // 邮箱使用保留域名，避免生成真实地址
+    fn email(&mut self) -> String {
+        match self.options.locale {
+            MockLocale::En => format!(
+                "{}.{}{}@example.com",
+                self.rng.pick(EN.first_names).to_lowercase(),
+                self.rng.pick(EN.last_names).to_lowercase(),
+                self.rng.range(1, 99)
+            ),
+            MockLocale::Zh => format!("user{}@example.com", self.rng.range(1000, 99999)),
+        }
+    }
+
+    // 使用不会分配给真实用户的号段
+    fn phone(&mut self) -> String {
+        match self.options.locale {
+            MockLocale::En => format!("+1-555-{:03}-{:04}", self.rng.range(100, 999), self.rng.range(0, 9999)),
+            MockLocale::Zh => format!("+86-170-0000-{:04}", self.rng.range(0, 9999)),
+        }
+    }
+
+    fn address(&mut self) -> String {
+        let number = self.rng.range(1, 999);
+        let street = self.rng.pick(self.locale.streets);
+        let city = self.rng.pick(self.locale.cities);
+        match self.options.locale {
+            MockLocale::En => format!("{} {}, {}", number, street, city),
+            MockLocale::Zh => format!("{}{}{}号", city, street, number),
+        }
+    }
+
+    fn url(&mut self, field: Option<&str>) -> String {
+        let segment = normalized(field);
+        let segment = if segment.is_empty() { "item".to_string() } else { segment };
+        format!("https://example.com/{}/{}", segment, self.rng.range(1, 10_000))
+    }
+
+    fn words(&mut self, count: usize) -> String {
+        let words: Vec<&str> = (0..count).map(|_| self.rng.pick(self.locale.words)).collect();
+        match self.options.locale {
+            MockLocale::En => {
+                let text = words.join(" ");
+                let mut chars = text.chars();
+                chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+            }
+            MockLocale::Zh => words.concat(),
+        }
+    }
+
+    fn sentence(&mut self) -> String {
+        let count = self.rng.range(5, 10) as usize;
+        let words = self.words(count);
+        match self.options.locale {
+            MockLocale::En => format!("{}.", words),
+            MockLocale::Zh => format!("{}。", words),
+        }
+    }
+
+    // 最近 30 天内的时间
+    fn recent_time(&mut self) -> chrono::DateTime<Utc> {
+        Utc::now() - Duration::seconds(self.rng.range(0, 30 * 24 * 3600))
+    }
+}
+
+// created_at、createdAt 这类以单词结尾的字段名
+fn has_suffix(field: Option<&str>, word: &str) -> bool {
+    let Some(field) = field else {
+        return false;
+    };
+    let Some(stem) = field.to_ascii_lowercase().strip_suffix(word).map(str::len) else {
+        return false;
+    };
+    let (head, tail) = field.split_at(stem);
+    head.ends_with(['_', '-'])
+        || head.ends_with(|c: char| c.is_ascii_lowercase()) && tail.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+// 小写并去掉分隔符，user_name、userName、user-name 视为同一字段名
+fn normalized(field: Option<&str>) -> String {
+    field
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
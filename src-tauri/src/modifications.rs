@@ -0,0 +1,107 @@
+use crate::proxy::{find_header, HttpRequest};
+use serde::{Deserialize, Serialize};
+
+pub const ORIGINAL_STAGE: &str = "original";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FieldChange {
+    // method、url、body 或 header:<name>
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModificationStage {
+    // original、rule:<id>、emulation:<profile>、shadow 或 breakpoint
+    pub source: String,
+    pub request: HttpRequest,
+    // 相对上一阶段的变化，原始阶段为空
+    pub changes: Vec<FieldChange>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+// 记录代理在转发前对请求做的每一步修改；请求未被修改时不保留任何阶段
+#[derive(Debug)]
+pub struct ModificationHistory {
+    stages: Vec<ModificationStage>,
+}
+
+impl ModificationHistory {
+    pub fn new(original: &HttpRequest) -> Self {
+        Self {
+            stages: vec![ModificationStage {
+                source: ORIGINAL_STAGE.to_string(),
+                request: original.clone(),
+                changes: Vec::new(),
+                timestamp: chrono::Utc::now(),
+            }],
+        }
+    }
+
+    // 与上一阶段对比，有变化时追加一个阶段
+    pub fn record(&mut self, source: impl Into<String>, request: &HttpRequest) {
+        let Some(previous) = self.stages.last() else {
+            return;
+        };
+        let changes = diff_requests(&previous.request, request);
+        if changes.is_empty() {
+            return;
+        }
+        self.stages.push(ModificationStage {
+            source: source.into(),
+            request: request.clone(),
+            changes,
+            timestamp: chrono::Utc::now(),
+        });
+    }
+
+    pub fn into_stages(self) -> Vec<ModificationStage> {
+        if self.stages.len() > 1 {
+            self.stages
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+pub fn diff_requests(before: &HttpRequest, after: &HttpRequest) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    let mut push = |field: String, a: Option<&str>, b: Option<&str>| {
+        if a != b {
+            changes.push(FieldChange {
+                field,
+                before: a.map(str::to_string),
+                after: b.map(str::to_string),
+            });
+        }
+    };
+
+    push("method".to_string(), Some(&before.method), Some(&after.method));
+    push("url".to_string(), Some(&before.url), Some(&after.url));
+
+    let mut names: Vec<String> = before
+        .headers
+        .keys()
+        .chain(after.headers.keys())
+        .map(|k| k.to_lowercase())
+        .collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        push(
+            format!("header:{}", name),
+            find_header(&before.headers, &name),
+            find_header(&after.headers, &name),
+        );
+    }
+
+    if before.body != after.body {
+        push(
+            "body".to_string(),
+            Some(&String::from_utf8_lossy(&before.body)),
+            Some(&String::from_utf8_lossy(&after.body)),
+        );
+    }
+    changes
+}
@@ -0,0 +1,224 @@
+use crate::ai_analyzer::AIAnalyzer;
+use crate::llm::{self, ChatMessage};
+use crate::proxy::SearchFilter;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing::warn;
+
+const SLOW_MS: u64 = 1000;
+const LARGE_BYTES: usize = 1024 * 1024;
+const RISKY_SCORE: u8 = 60;
+
+// 不参与关键字匹配的虚词和泛指词
+const STOP_WORDS: [&str; 33] = [
+    "a", "all", "an", "and", "any", "are", "at", "by", "call", "calls", "find", "for", "from", "in", "list",
+    "me", "of", "on", "request", "requests", "show", "that", "the", "to", "traffic", "was", "were", "which", "with",
+    "transaction", "transactions", "response", "responses",
+];
+
+const SEARCH_SYSTEM_PROMPT: &str = r#"把用户对抓包记录的自然语言描述翻译为搜索条件。只输出一个 JSON 对象，只包含需要的字段：
+{
+  "keyword": string,               // 在 URL、方法、备注和请求/响应体中匹配的子串
+  "method": string,                // 大写的 HTTP 方法
+  "status": number,                // 精确状态码
+  "min_status": number, "max_status": number,  // 状态码范围，例如失败请求为 400 起
+  "domain": string,                // 主机名或其一部分
+  "from": string, "to": string,    // RFC 3339 时间
+  "min_duration_ms": number, "max_duration_ms": number,
+  "min_body_size": number,         // 请求体与响应体字节数之和
+  "min_risk": number,              // 0-100 风险评分下限
+  "sort_by_risk": boolean,
+  "graphql_operation": string,
+  "country": string,               // 上游服务器所在国家的 ISO 代码，如 "CN"、"US"
+  "asn": number                    // 上游服务器所属自治系统编号
+}
+无法表达的条件忽略即可，不要编造字段。"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterpretationSource {
+    Model,
+    Rules,
+}
+
+// 返回给界面的解析结果，便于用户确认查询被如何理解
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterpretedQuery {
+    pub query: String,
+    pub filter: SearchFilter,
+    pub source: InterpretationSource,
+    // 规则解析时未能识别、也未用作关键字的词
+    pub ignored: Vec<String>,
+    // 模型解析失败时的原因
+    pub fallback_reason: Option<String>,
+}
+
+// 有可用模型时交给模型翻译，失败或未配置时使用规则解析
+pub async fn interpret(analyzer: &AIAnalyzer, query: &str) -> InterpretedQuery {
+    let query = query.trim();
+    let now = Local::now();
+    let fallback_reason = match translate(analyzer, query, now).await {
+        Ok(Some(filter)) => {
+            return InterpretedQuery {
+                query: query.to_string(),
+                filter,
+                source: InterpretationSource::Model,
+                ignored: Vec::new(),
+                fallback_reason: None,
+            };
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Failed to translate search query with model: {}", e);
+            Some(e.to_string())
+        }
+    };
+    let (filter, ignored) = parse(query, now);
+    InterpretedQuery { query: query.to_string(), filter, source: InterpretationSource::Rules, ignored, fallback_reason }
+}
+
+async fn translate(analyzer: &AIAnalyzer, query: &str, now: DateTime<Local>) -> Result<Option<SearchFilter>> {
+    let prompt = format!("当前时间：{}\n查询：{}", now.to_rfc3339(), query);
+    let Some(content) = analyzer.complete(SEARCH_SYSTEM_PROMPT, &[ChatMessage::user(prompt)], true, None).await? else {
+        return Ok(None);
+    };
+    let mut value: serde_json::Value = serde_json::from_str(llm::extract_json(&content)?)?;
+    if value["keyword"].is_null() {
+        value["keyword"] = serde_json::json!("");
+    }
+    let mut filter: SearchFilter = serde_json::from_value(value)?;
+    filter.method = filter.method.map(|m| m.to_uppercase());
+    Ok(Some(filter))
+}
+
+fn duration_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\b(?:slower than|longer than|over|above|more than|>)\s*(\d+)\s*(ms|milliseconds?|s|secs?|seconds?)\b")
+            .expect("valid duration pattern")
+    })
+}
+
+fn window_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\b(?:last|past)\s+(\d+\s+)?(minute|hour|day|week)s?\b").expect("valid time window pattern")
+    })
+}
+
+fn domain_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(?:[a-z0-9-]+\.)+[a-z]{2,}(?::\d+)?$|^localhost(?::\d+)?$|^\d{1,3}(?:\.\d{1,3}){3}(?::\d+)?$")
+            .expect("valid domain pattern")
+    })
+}
+
+type Apply = fn(&mut SearchFilter);
+
+// 规则解析：识别方法、状态、主机、时间、耗时、大小和风险，剩下的第一个词作为关键字
+pub fn parse(query: &str, now: DateTime<Local>) -> (SearchFilter, Vec<String>) {
+    let mut filter = SearchFilter::default();
+    let mut text = format!(" {} ", query.to_lowercase());
+
+    if let Some(captures) = duration_pattern().captures(&text) {
+        let value: u64 = captures[1].parse().unwrap_or(0);
+        filter.min_duration_ms = Some(if captures[2].starts_with('m') { value } else { value * 1000 });
+        text = text.replace(&captures[0], " ");
+    }
+    if let Some(captures) = window_pattern().captures(&text) {
+        let count: i64 = captures.get(1).and_then(|c| c.as_str().trim().parse().ok()).unwrap_or(1);
+        let window = match &captures[2] {
+            "minute" => Duration::minutes(count),
+            "hour" => Duration::hours(count),
+            "day" => Duration::days(count),
+            _ => Duration::weeks(count),
+        };
+        filter.from = Some((now - window).with_timezone(&Utc));
+        text = text.replace(&captures[0], " ");
+    }
+
+    // 多词短语先于单词匹配
+    let phrases: [(&str, Apply); 6] = [
+        ("server errors", |f| set_status_range(f, 500, 599)),
+        ("server error", |f| set_status_range(f, 500, 599)),
+        ("client errors", |f| set_status_range(f, 400, 499)),
+        ("client error", |f| set_status_range(f, 400, 499)),
+        ("not found", |f| f.status = Some(404)),
+        ("high risk", set_risky),
+    ];
+    for (phrase, apply) in phrases {
+        if text.contains(&format!(" {} ", phrase)) {
+            apply(&mut filter);
+            text = text.replace(phrase, " ");
+        }
+    }
+
+    let mut ignored = Vec::new();
+    for word in text.split_whitespace() {
+        let word = word.trim_matches(|c: char| matches!(c, ',' | '?' | '!' | '"' | '\''));
+        if word.is_empty() || STOP_WORDS.contains(&word) {
+            continue;
+        }
+        match word {
+            "get" | "post" | "put" | "delete" | "patch" | "head" | "options" => {
+                filter.method = Some(word.to_uppercase());
+            }
+            "failed" | "failing" | "failure" | "failures" | "error" | "errors" | "errored" | "unsuccessful" => {
+                filter.min_status = filter.min_status.or(Some(400));
+            }
+            "5xx" => set_status_range(&mut filter, 500, 599),
+            "4xx" => set_status_range(&mut filter, 400, 499),
+            "3xx" | "redirect" | "redirects" => set_status_range(&mut filter, 300, 399),
+            "2xx" | "successful" | "succeeded" | "success" | "ok" => set_status_range(&mut filter, 200, 299),
+            "unauthorized" | "unauthenticated" => filter.status = Some(401),
+            "forbidden" => filter.status = Some(403),
+            "slow" | "slower" | "slowest" => filter.min_duration_ms = filter.min_duration_ms.or(Some(SLOW_MS)),
+            "large" | "big" | "huge" => filter.min_body_size = Some(LARGE_BYTES),
+            "risky" | "suspicious" | "dangerous" => set_risky(&mut filter),
+            "today" => filter.from = Some(start_of_day(now, 0)),
+            "yesterday" => {
+                filter.from = Some(start_of_day(now, 1));
+                filter.to = Some(start_of_day(now, 0) - Duration::milliseconds(1));
+            }
+            _ if word.len() == 3 && word.parse::<u16>().is_ok_and(|s| (100..600).contains(&s)) => {
+                filter.status = word.parse().ok();
+            }
+            _ if domain_pattern().is_match(word) => filter.domain = Some(word.to_string()),
+            _ if filter.keyword.is_empty() => filter.keyword = singular(word),
+            _ => ignored.push(word.to_string()),
+        }
+    }
+    (filter, ignored)
+}
+
+fn set_status_range(filter: &mut SearchFilter, min: u16, max: u16) {
+    filter.min_status = Some(min);
+    filter.max_status = Some(max);
+}
+
+fn set_risky(filter: &mut SearchFilter) {
+    filter.min_risk = Some(RISKY_SCORE);
+    filter.sort_by_risk = Some(true);
+}
+
+// 本地时区 days_ago 天前的零点
+fn start_of_day(now: DateTime<Local>, days_ago: i64) -> DateTime<Utc> {
+    let date = now.date_naive() - Duration::days(days_ago);
+    Local
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(|| now.with_timezone(&Utc))
+}
+
+// "logins" 这类复数形式按子串匹配时去掉词尾的 s
+fn singular(word: &str) -> String {
+    match word.strip_suffix('s') {
+        Some(stem) if stem.len() >= 4 && !stem.ends_with('s') => stem.to_string(),
+        _ => word.to_string(),
+    }
+}
@@ -0,0 +1,482 @@
+use crate::proxy::{find_header, HttpTransaction};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+const HTTP_METHODS: [&str; 8] = ["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+// 防止循环 $ref 导致无限递归
+const MAX_SCHEMA_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ViolationKind {
+    UnknownEndpoint,
+    MissingParameter,
+    MissingRequiredField,
+    WrongType,
+    InvalidEnumValue,
+    UndocumentedStatus,
+    InvalidBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecViolation {
+    pub kind: ViolationKind,
+    // 例如 request.body.user.id、response.status
+    pub location: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointConformance {
+    pub method: String,
+    pub path: String,
+    pub total: usize,
+    pub conforming: usize,
+    pub violations: HashMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConformanceSummary {
+    pub spec_title: String,
+    pub total_transactions: usize,
+    pub unknown_endpoints: usize,
+    pub endpoints: Vec<EndpointConformance>,
+}
+
+#[derive(Debug, Clone)]
+struct Operation {
+    method: String,
+    path: String,
+    path_regex: regex::Regex,
+    spec: Value,
+    path_item: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct OpenApiSpec {
+    doc: Value,
+    base_paths: Vec<String>,
+    operations: Vec<Operation>,
+}
+
+impl OpenApiSpec {
+    // 支持 JSON 和 YAML 格式
+    pub fn parse(content: &str) -> Result<Self> {
+        let doc: Value = match serde_json::from_str(content) {
+            Ok(doc) => doc,
+            Err(_) => serde_yaml::from_str(content)
+                .map_err(|e| anyhow!("Invalid OpenAPI document: {}", e))?,
+        };
+
+        if doc.get("openapi").is_none() && doc.get("swagger").is_none() {
+            return Err(anyhow!("Document is missing the openapi version field"));
+        }
+
+        let mut base_paths: Vec<String> = doc.get("servers")
+            .and_then(Value::as_array)
+            .map(|servers| {
+                servers
+                    .iter()
+                    .filter_map(|s| s.get("url").and_then(Value::as_str))
+                    .map(server_base_path)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if let Some(base) = doc.get("basePath").and_then(Value::as_str) {
+            base_paths.push(base.trim_end_matches('/').to_string());
+        }
+        if base_paths.is_empty() {
+            base_paths.push(String::new());
+        }
+
+        let mut operations = Vec::new();
+        if let Some(paths) = doc.get("paths").and_then(Value::as_object) {
+            for (path, path_item) in paths {
+                let path_regex = path_template_regex(path)?;
+                for method in HTTP_METHODS {
+                    if let Some(spec) = path_item.get(method) {
+                        operations.push(Operation {
+                            method: method.to_uppercase(),
+                            path: path.clone(),
+                            path_regex: path_regex.clone(),
+                            spec: spec.clone(),
+                            path_item: path_item.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        // 静态路径优先于模板路径匹配
+        operations.sort_by_key(|op| op.path.matches('{').count());
+
+        Ok(Self { doc, base_paths, operations })
+    }
+
+    pub fn title(&self) -> String {
+        self.doc.pointer("/info/title")
+            .and_then(Value::as_str)
+            .unwrap_or("Untitled API")
+            .to_string()
+    }
+
+    pub fn operation_count(&self) -> usize {
+        self.operations.len()
+    }
+
+    fn find_operation(&self, method: &str, path: &str) -> Option<&Operation> {
+        self.base_paths.iter().find_map(|base| {
+            let relative = path.strip_prefix(base.as_str())?;
+            let relative = if relative.is_empty() { "/" } else { relative };
+            self.operations
+                .iter()
+                .find(|op| op.method.eq_ignore_ascii_case(method) && op.path_regex.is_match(relative))
+        })
+    }
+
+    // 返回 (method, path 模板)，未匹配时为 None
+    pub fn endpoint_for(&self, transaction: &HttpTransaction) -> Option<(String, String)> {
+        let url = url::Url::parse(&transaction.request.url).ok()?;
+        self.find_operation(&transaction.request.method, url.path())
+            .map(|op| (op.method.clone(), op.path.clone()))
+    }
+
+    pub fn validate(&self, transaction: &HttpTransaction) -> Vec<SpecViolation> {
+        let mut violations = Vec::new();
+        let request = &transaction.request;
+
+        let Ok(url) = url::Url::parse(&request.url) else {
+            violations.push(SpecViolation {
+                kind: ViolationKind::UnknownEndpoint,
+                location: "request.url".to_string(),
+                message: format!("无法解析的 URL: {}", request.url),
+            });
+            return violations;
+        };
+
+        let Some(operation) = self.find_operation(&request.method, url.path()) else {
+            violations.push(SpecViolation {
+                kind: ViolationKind::UnknownEndpoint,
+                location: "request.url".to_string(),
+                message: format!("规范中未定义的端点: {} {}", request.method, url.path()),
+            });
+            return violations;
+        };
+
+        self.validate_parameters(operation, &url, transaction, &mut violations);
+
+        // 请求体
+        if let Some(body_spec) = operation.spec.get("requestBody").map(|b| self.resolve(b)) {
+            let required = body_spec.get("required").and_then(Value::as_bool).unwrap_or(false);
+            if request.body.is_empty() {
+                if required {
+                    violations.push(SpecViolation {
+                        kind: ViolationKind::MissingRequiredField,
+                        location: "request.body".to_string(),
+                        message: "缺少必需的请求体".to_string(),
+                    });
+                }
+            } else if let Some(schema) = json_schema_for(body_spec) {
+                self.validate_json_body(&request.body, schema, "request.body", &mut violations);
+            }
+        }
+
+        // 响应状态码与响应体
+        if let Some(response) = &transaction.response {
+            let status = response.status.to_string();
+            let wildcard = format!("{}XX", status.chars().next().unwrap_or('0'));
+            let responses = operation.spec.get("responses");
+            let response_spec = responses
+                .and_then(|r| r.get(&status).or_else(|| r.get(&wildcard)).or_else(|| r.get("default")))
+                .map(|r| self.resolve(r));
+
+            match response_spec {
+                None => violations.push(SpecViolation {
+                    kind: ViolationKind::UndocumentedStatus,
+                    location: "response.status".to_string(),
+                    message: format!("未在规范中声明的状态码: {}", response.status),
+                }),
+                Some(spec) => {
+                    if let Some(schema) = json_schema_for(spec) {
+                        let is_json = find_header(&response.headers, "content-type")
+                            .map(|ct| ct.contains("json"))
+                            .unwrap_or(true);
+                        if is_json && !response.body.is_empty() {
+                            self.validate_json_body(&response.body, schema, "response.body", &mut violations);
+                        }
+                    }
+                }
+            }
+        }
+
+        violations
+    }
+
+    fn validate_parameters(
+        &self,
+        operation: &Operation,
+        url: &url::Url,
+        transaction: &HttpTransaction,
+        violations: &mut Vec<SpecViolation>,
+    ) {
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let parameters = operation.path_item.get("parameters").and_then(Value::as_array).into_iter().flatten()
+            .chain(operation.spec.get("parameters").and_then(Value::as_array).into_iter().flatten());
+
+        for parameter in parameters {
+            let parameter = self.resolve(parameter);
+            let name = parameter.get("name").and_then(Value::as_str).unwrap_or_default();
+            let location = parameter.get("in").and_then(Value::as_str).unwrap_or_default();
+            let required = parameter.get("required").and_then(Value::as_bool).unwrap_or(false);
+
+            let value = match location {
+                "query" => query.get(name).cloned(),
+                "header" => find_header(&transaction.request.headers, name).map(str::to_string),
+                _ => continue,
+            };
+
+            match value {
+                None if required => violations.push(SpecViolation {
+                    kind: ViolationKind::MissingParameter,
+                    location: format!("request.{}.{}", location, name),
+                    message: format!("缺少必需的 {} 参数: {}", location, name),
+                }),
+                Some(value) => {
+                    if let Some(schema) = parameter.get("schema") {
+                        let typed = coerce_scalar(&value, self.resolve(schema));
+                        self.validate_value(&typed, schema, &format!("request.{}.{}", location, name), violations, 0);
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+
+    fn validate_json_body(&self, body: &[u8], schema: &Value, location: &str, violations: &mut Vec<SpecViolation>) {
+        match serde_json::from_slice::<Value>(body) {
+            Ok(value) => self.validate_value(&value, schema, location, violations, 0),
+            Err(e) => violations.push(SpecViolation {
+                kind: ViolationKind::InvalidBody,
+                location: location.to_string(),
+                message: format!("JSON 解析失败: {}", e),
+            }),
+        }
+    }
+
+    fn validate_value(&self, value: &Value, schema: &Value, location: &str, violations: &mut Vec<SpecViolation>, depth: usize) {
+        if depth > MAX_SCHEMA_DEPTH {
+            return;
+        }
+        let schema = self.resolve(schema);
+
+        if let Some(all_of) = schema.get("allOf").and_then(Value::as_array) {
+            for sub in all_of {
+                self.validate_value(value, sub, location, violations, depth + 1);
+            }
+        }
+        for combinator in ["oneOf", "anyOf"] {
+            if let Some(options) = schema.get(combinator).and_then(Value::as_array) {
+                let matches_any = options.iter().any(|sub| {
+                    let mut scratch = Vec::new();
+                    self.validate_value(value, sub, location, &mut scratch, depth + 1);
+                    scratch.is_empty()
+                });
+                if !matches_any {
+                    violations.push(SpecViolation {
+                        kind: ViolationKind::WrongType,
+                        location: location.to_string(),
+                        message: format!("值不符合 {} 中的任何一个结构", combinator),
+                    });
+                }
+            }
+        }
+
+        if value.is_null() && schema.get("nullable").and_then(Value::as_bool).unwrap_or(false) {
+            return;
+        }
+
+        if let Some(expected) = schema.get("type") {
+            let types: Vec<&str> = match expected {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !types.is_empty() && !types.iter().any(|t| type_matches(value, t)) {
+                violations.push(SpecViolation {
+                    kind: ViolationKind::WrongType,
+                    location: location.to_string(),
+                    message: format!("类型错误: 期望 {}，实际为 {}", types.join("|"), json_type_name(value)),
+                });
+                return;
+            }
+        }
+
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+            if !allowed.contains(value) {
+                violations.push(SpecViolation {
+                    kind: ViolationKind::InvalidEnumValue,
+                    location: location.to_string(),
+                    message: format!("取值 {} 不在枚举范围内", value),
+                });
+            }
+        }
+
+        match value {
+            Value::Object(map) => {
+                if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                    for field in required.iter().filter_map(Value::as_str) {
+                        if !map.contains_key(field) {
+                            violations.push(SpecViolation {
+                                kind: ViolationKind::MissingRequiredField,
+                                location: format!("{}.{}", location, field),
+                                message: format!("缺少必需字段: {}", field),
+                            });
+                        }
+                    }
+                }
+                if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                    for (key, field_value) in map {
+                        if let Some(field_schema) = properties.get(key) {
+                            self.validate_value(field_value, field_schema, &format!("{}.{}", location, key), violations, depth + 1);
+                        }
+                    }
+                }
+            }
+            Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    for (i, item) in items.iter().enumerate() {
+                        self.validate_value(item, item_schema, &format!("{}[{}]", location, i), violations, depth + 1);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // 解析本地 $ref（#/components/...），最多跟随若干层
+    fn resolve<'a>(&'a self, mut value: &'a Value) -> &'a Value {
+        for _ in 0..MAX_SCHEMA_DEPTH {
+            let Some(reference) = value.get("$ref").and_then(Value::as_str) else {
+                break;
+            };
+            match reference.strip_prefix('#').and_then(|pointer| self.doc.pointer(pointer)) {
+                Some(target) => value = target,
+                None => break,
+            }
+        }
+        value
+    }
+
+    pub fn conformance(&self, transactions: &[HttpTransaction]) -> ConformanceSummary {
+        let mut endpoints: HashMap<(String, String), EndpointConformance> = HashMap::new();
+        let mut unknown_endpoints = 0;
+
+        for transaction in transactions {
+            let violations = self.validate(transaction);
+            let Some((method, path)) = self.endpoint_for(transaction) else {
+                unknown_endpoints += 1;
+                continue;
+            };
+
+            let entry = endpoints
+                .entry((method.clone(), path.clone()))
+                .or_insert_with(|| EndpointConformance {
+                    method,
+                    path,
+                    total: 0,
+                    conforming: 0,
+                    violations: HashMap::new(),
+                });
+            entry.total += 1;
+            if violations.is_empty() {
+                entry.conforming += 1;
+            }
+            for violation in violations {
+                *entry.violations.entry(format!("{:?}", violation.kind)).or_insert(0) += 1;
+            }
+        }
+
+        let mut endpoints: Vec<EndpointConformance> = endpoints.into_values().collect();
+        endpoints.sort_by(|a, b| a.path.cmp(&b.path).then(a.method.cmp(&b.method)));
+
+        ConformanceSummary {
+            spec_title: self.title(),
+            total_transactions: transactions.len(),
+            unknown_endpoints,
+            endpoints,
+        }
+    }
+}
+
+fn server_base_path(server_url: &str) -> String {
+    let path = match url::Url::parse(server_url) {
+        Ok(url) => url.path().to_string(),
+        // 相对服务器地址，例如 "/v1"
+        Err(_) => server_url.to_string(),
+    };
+    path.trim_end_matches('/').to_string()
+}
+
+fn path_template_regex(template: &str) -> Result<regex::Regex> {
+    let mut pattern = String::from("^");
+    for segment in template.split('/').filter(|s| !s.is_empty()) {
+        pattern.push('/');
+        if segment.starts_with('{') && segment.ends_with('}') {
+            pattern.push_str("[^/]+");
+        } else {
+            pattern.push_str(&regex::escape(segment));
+        }
+    }
+    pattern.push_str("/?$");
+    Ok(regex::Regex::new(&pattern)?)
+}
+
+fn json_schema_for(spec: &Value) -> Option<&Value> {
+    spec.get("content")
+        .and_then(Value::as_object)
+        .and_then(|content| {
+            content
+                .iter()
+                .find(|(mime, _)| mime.contains("json"))
+                .and_then(|(_, media)| media.get("schema"))
+        })
+        // Swagger 2.0 直接在响应上定义 schema
+        .or_else(|| spec.get("schema"))
+}
+
+// 查询参数和请求头均为字符串，按 schema 类型转换后再校验
+fn coerce_scalar(raw: &str, schema: &Value) -> Value {
+    match schema.get("type").and_then(Value::as_str) {
+        Some("integer") => raw.parse::<i64>().map(Value::from).unwrap_or_else(|_| Value::String(raw.to_string())),
+        Some("number") => raw.parse::<f64>().map(Value::from).unwrap_or_else(|_| Value::String(raw.to_string())),
+        Some("boolean") => raw.parse::<bool>().map(Value::from).unwrap_or_else(|_| Value::String(raw.to_string())),
+        _ => Value::String(raw.to_string()),
+    }
+}
+
+fn type_matches(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
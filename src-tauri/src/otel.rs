@@ -0,0 +1,422 @@
+use crate::history::split_endpoint;
+use crate::proxy::HttpTransaction;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+use tracing::warn;
+
+// 导出失败时最多积压的 span 数，超出后丢弃最早的
+const MAX_PENDING_SPANS: usize = 20_000;
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+const SCOPE_NAME: &str = "packetmind-ai.proxy";
+
+// OTLP 的 SpanKind 与 StatusCode 取值
+const SPAN_KIND_INTERNAL: u8 = 1;
+const SPAN_KIND_CLIENT: u8 = 3;
+const STATUS_CODE_ERROR: u8 = 2;
+
+fn default_endpoint() -> String {
+    "http://localhost:4318/v1/traces".to_string()
+}
+
+fn default_service_name() -> String {
+    "packetmind-proxy".to_string()
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+
+fn default_flush_interval_ms() -> u64 {
+    2000
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OtelSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    // OTLP/HTTP 的 traces 地址，以 JSON 编码发送
+    #[serde(default = "default_endpoint")]
+    pub endpoint: String,
+    // 附加到导出请求上的头，如收集器要求的鉴权
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+    // 积攒到该数量或等待 flush_interval_ms 后发送一批
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+}
+
+impl Default for OtelSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: default_endpoint(),
+            headers: HashMap::new(),
+            service_name: default_service_name(),
+            batch_size: default_batch_size(),
+            flush_interval_ms: default_flush_interval_ms(),
+        }
+    }
+}
+
+impl OtelSettings {
+    pub fn validated(mut self) -> Result<Self> {
+        let parsed = url::Url::parse(self.endpoint.trim())
+            .map_err(|e| anyhow!("Invalid OTLP endpoint {}: {}", self.endpoint, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(anyhow!("OTLP endpoint must use http or https: {}", self.endpoint));
+        }
+        self.endpoint = parsed.to_string();
+        for name in self.headers.keys() {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| anyhow!("Invalid OTLP header name: {}", name))?;
+        }
+        self.service_name = self.service_name.trim().to_string();
+        if self.service_name.is_empty() {
+            self.service_name = default_service_name();
+        }
+        self.batch_size = self.batch_size.clamp(1, 1000);
+        self.flush_interval_ms = self.flush_interval_ms.clamp(100, 60_000);
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OtelStatus {
+    pub exported_spans: u64,
+    pub failed_batches: u64,
+    pub dropped_spans: u64,
+    pub pending_spans: usize,
+    pub last_export: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_error: Option<String>,
+}
+
+// 每个事务导出为一个 CLIENT span，转发各阶段为其子 span；按批次异步发送，不阻塞转发
+#[derive(Clone)]
+pub struct OtelExporter {
+    settings: Arc<RwLock<OtelSettings>>,
+    pending: Arc<RwLock<VecDeque<Value>>>,
+    status: Arc<RwLock<OtelStatus>>,
+    // 已安排定时发送时不再重复安排
+    flush_scheduled: Arc<AtomicBool>,
+    // 攒满一批触发的发送同一时间只安排一个；收集器慢或不可达时不会堆积发送任务
+    flush_pending: Arc<AtomicBool>,
+    // 各处触发的发送依次进行，同一时间只有一个导出请求
+    flush_lock: Arc<Mutex<()>>,
+    // 收集器可能就在本机，不能经过系统代理再绕回本代理
+    client: reqwest::Client,
+}
+
+impl Default for OtelExporter {
+    fn default() -> Self {
+        Self {
+            settings: Arc::new(RwLock::new(OtelSettings::default())),
+            pending: Arc::new(RwLock::new(VecDeque::new())),
+            status: Arc::new(RwLock::new(OtelStatus::default())),
+            flush_scheduled: Arc::new(AtomicBool::new(false)),
+            flush_pending: Arc::new(AtomicBool::new(false)),
+            flush_lock: Arc::new(Mutex::new(())),
+            client: reqwest::Client::builder().no_proxy().timeout(EXPORT_TIMEOUT).build().unwrap_or_default(),
+        }
+    }
+}
+
+impl OtelExporter {
+    pub async fn settings(&self) -> OtelSettings {
+        self.settings.read().await.clone()
+    }
+
+    // 关闭导出时丢弃尚未发送的 span
+    pub async fn set_settings(&self, settings: OtelSettings) -> Result<OtelSettings> {
+        let settings = settings.validated()?;
+        if !settings.enabled {
+            self.pending.write().await.clear();
+        }
+        *self.settings.write().await = settings.clone();
+        Ok(settings)
+    }
+
+    pub async fn is_enabled(&self) -> bool {
+        self.settings.read().await.enabled
+    }
+
+    pub async fn status(&self) -> OtelStatus {
+        let mut status = self.status.read().await.clone();
+        status.pending_spans = self.pending.read().await.len();
+        status
+    }
+
+    pub async fn record(&self, transaction: &HttpTransaction) {
+        let (batch_size, interval) = {
+            let settings = self.settings.read().await;
+            if !settings.enabled {
+                return;
+            }
+            (settings.batch_size, Duration::from_millis(settings.flush_interval_ms))
+        };
+
+        let spans = transaction_spans(transaction);
+        let pending_len = {
+            let mut pending = self.pending.write().await;
+            pending.extend(spans);
+            self.drop_overflow(&mut pending).await;
+            pending.len()
+        };
+
+        if pending_len >= batch_size && !self.flush_pending.swap(true, Ordering::SeqCst) {
+            let exporter = self.clone();
+            tokio::spawn(async move {
+                exporter.flush().await;
+                exporter.flush_pending.store(false, Ordering::SeqCst);
+            });
+        } else if !self.flush_scheduled.swap(true, Ordering::SeqCst) {
+            let exporter = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(interval).await;
+                exporter.flush_scheduled.store(false, Ordering::SeqCst);
+                exporter.flush().await;
+            });
+        }
+    }
+
+    // 发送积压的全部 span，失败的批次放回队首等待下次发送
+    pub async fn flush(&self) {
+        let _guard = self.flush_lock.lock().await;
+        let settings = self.settings.read().await.clone();
+        if !settings.enabled {
+            return;
+        }
+        loop {
+            let batch: Vec<Value> = {
+                let mut pending = self.pending.write().await;
+                let count = pending.len().min(settings.batch_size);
+                pending.drain(..count).collect()
+            };
+            if batch.is_empty() {
+                return;
+            }
+            let count = batch.len();
+            match self.export(&settings, &batch).await {
+                Ok(()) => {
+                    let mut status = self.status.write().await;
+                    status.exported_spans += count as u64;
+                    status.last_export = Some(chrono::Utc::now());
+                    status.last_error = None;
+                }
+                Err(e) => {
+                    warn!("Failed to export spans to {}: {}", settings.endpoint, e);
+                    {
+                        let mut status = self.status.write().await;
+                        status.failed_batches += 1;
+                        status.last_error = Some(e.to_string());
+                    }
+                    let mut pending = self.pending.write().await;
+                    for span in batch.into_iter().rev() {
+                        pending.push_front(span);
+                    }
+                    self.drop_overflow(&mut pending).await;
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn drop_overflow(&self, pending: &mut VecDeque<Value>) {
+        let overflow = pending.len().saturating_sub(MAX_PENDING_SPANS);
+        if overflow > 0 {
+            pending.drain(..overflow);
+            self.status.write().await.dropped_spans += overflow as u64;
+        }
+    }
+
+    async fn export(&self, settings: &OtelSettings, spans: &[Value]) -> Result<()> {
+        let mut request = self.client.post(&settings.endpoint).json(&export_request(&settings.service_name, spans));
+        for (name, value) in &settings.headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Collector returned {}: {}", status, body.chars().take(200).collect::<String>()));
+        }
+        Ok(())
+    }
+}
+
+// OTLP/HTTP 的 JSON 编码：trace id 与 span id 为十六进制，64 位整数写作字符串
+pub fn export_request(service_name: &str, spans: &[Value]) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    attribute("service.name", json!({ "stringValue": service_name })),
+                    attribute("telemetry.sdk.name", json!({ "stringValue": "packetmind-ai" })),
+                    attribute("telemetry.sdk.version", json!({ "stringValue": env!("CARGO_PKG_VERSION") })),
+                ],
+            },
+            "scopeSpans": [{
+                "scope": { "name": SCOPE_NAME, "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+fn string_attribute(key: &str, value: impl Into<String>) -> Value {
+    attribute(key, json!({ "stringValue": value.into() }))
+}
+
+fn int_attribute(key: &str, value: u64) -> Value {
+    attribute(key, json!({ "intValue": value.to_string() }))
+}
+
+fn random_span_id() -> String {
+    uuid::Uuid::new_v4().as_bytes()[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 客户端请求带有 W3C traceparent 时沿用其 trace id 并挂在其下，便于与后端链路关联
+fn trace_parent(transaction: &HttpTransaction) -> Option<(String, String)> {
+    let header = transaction
+        .request
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("traceparent"))?
+        .1;
+    let parts: Vec<&str> = header.trim().split('-').collect();
+    let [version, trace_id, parent_id, _flags] = parts[..] else {
+        return None;
+    };
+    let valid = |id: &str, len: usize| {
+        id.len() == len && id.bytes().all(|b| b.is_ascii_hexdigit()) && id.bytes().any(|b| b != b'0')
+    };
+    (version != "ff" && valid(trace_id, 32) && valid(parent_id, 16))
+        .then(|| (trace_id.to_ascii_lowercase(), parent_id.to_ascii_lowercase()))
+}
+
+fn unix_nanos(timestamp: chrono::DateTime<chrono::Utc>) -> i64 {
+    timestamp.timestamp_nanos_opt().unwrap_or_default()
+}
+
+fn millis_to_nanos(ms: f64) -> i64 {
+    (ms * 1_000_000.0) as i64
+}
+
+// 事务本身的 span 在前，随后按发生顺序排列各阶段子 span
+pub fn transaction_spans(transaction: &HttpTransaction) -> Vec<Value> {
+    let request = &transaction.request;
+    let (trace_id, parent_span_id) = match trace_parent(transaction) {
+        Some((trace_id, parent)) => (trace_id, parent),
+        // 没有上游链路时以事务 id 作为 trace id，便于从链路反查事务
+        None => {
+            let id = uuid::Uuid::parse_str(&transaction.id).unwrap_or_else(|_| uuid::Uuid::new_v4());
+            (id.simple().to_string(), String::new())
+        }
+    };
+    let span_id = random_span_id();
+    let start = unix_nanos(request.timestamp);
+    let end = start + transaction.duration.map(|d| d.as_nanos() as i64).unwrap_or_default();
+
+    let (host, path) = split_endpoint(&request.url);
+    let mut attributes = vec![
+        string_attribute("http.request.method", request.method.clone()),
+        string_attribute("url.full", request.url.clone()),
+        string_attribute("server.address", host),
+        int_attribute("http.request.body.size", request.body.len() as u64),
+        string_attribute("packetmind.transaction_id", transaction.id.clone()),
+    ];
+    if let Ok(url) = url::Url::parse(&request.url) {
+        attributes.push(string_attribute("url.scheme", url.scheme()));
+        if let Some(port) = url.port_or_known_default() {
+            attributes.push(int_attribute("server.port", port as u64));
+        }
+    }
+    if let Some(version) = &request.version {
+        attributes.push(string_attribute("network.protocol.version", version.trim_start_matches("HTTP/")));
+    }
+    if let Some(address) = transaction.dns.as_ref().and_then(|d| d.addresses.first()) {
+        attributes.push(string_attribute("network.peer.address", address.to_string()));
+    }
+    if !transaction.tags.is_empty() {
+        let tags: Vec<Value> = transaction.tags.iter().map(|t| json!({ "stringValue": t })).collect();
+        attributes.push(attribute("packetmind.tags", json!({ "arrayValue": { "values": tags } })));
+    }
+
+    // 与 HTTP 客户端语义约定一致：未收到响应或状态码 >= 400 记为错误
+    let status = match &transaction.response {
+        Some(response) => {
+            attributes.push(int_attribute("http.response.status_code", response.status as u64));
+            let size = transaction
+                .response_encoding
+                .as_ref()
+                .map(|e| e.encoded_size)
+                .unwrap_or(response.body.len());
+            attributes.push(int_attribute("http.response.body.size", size as u64));
+            if response.status >= 400 {
+                attributes.push(string_attribute("error.type", response.status.to_string()));
+                json!({ "code": STATUS_CODE_ERROR })
+            } else {
+                json!({})
+            }
+        }
+        None => {
+            attributes.push(string_attribute("error.type", "no_response"));
+            json!({ "code": STATUS_CODE_ERROR, "message": "No response received" })
+        }
+    };
+
+    let mut spans = vec![json!({
+        "traceId": trace_id,
+        "spanId": span_id,
+        "parentSpanId": parent_span_id,
+        "name": format!("{} {}", request.method, path),
+        "kind": SPAN_KIND_CLIENT,
+        "startTimeUnixNano": start.to_string(),
+        "endTimeUnixNano": end.to_string(),
+        "attributes": attributes,
+        "status": status,
+    })];
+
+    // 各阶段从请求开始依次排列，不超出父 span 的结束时间
+    if let Some(timings) = &transaction.timings {
+        let phases = [
+            ("dns", timings.dns_ms),
+            ("connect", timings.connect_ms),
+            ("tls", timings.tls_ms),
+            ("send", Some(timings.send_ms)),
+            ("wait", Some(timings.wait_ms)),
+            ("receive", Some(timings.receive_ms)),
+        ];
+        let mut cursor = start;
+        for (name, ms) in phases {
+            let Some(ms) = ms else { continue };
+            let phase_start = cursor.min(end);
+            cursor += millis_to_nanos(ms);
+            let phase_end = cursor.min(end);
+            spans.push(json!({
+                "traceId": trace_id,
+                "spanId": random_span_id(),
+                "parentSpanId": span_id,
+                "name": name,
+                "kind": SPAN_KIND_INTERNAL,
+                "startTimeUnixNano": phase_start.to_string(),
+                "endTimeUnixNano": phase_end.to_string(),
+                "attributes": [attribute("packetmind.phase_ms", json!({ "doubleValue": ms }))],
+            }));
+        }
+    }
+    spans
+}
@@ -0,0 +1,133 @@
+use crate::proxy::{HttpTransaction, RequestRule, SearchFilter};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_QUICK_FIND_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: String,
+    pub name: String,
+    pub filter: SearchFilter,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum QuickFindKind {
+    Transaction,
+    SavedSearch,
+    Rule,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuickFindResult {
+    pub kind: QuickFindKind,
+    pub id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub score: i64,
+    // 命中的字段内容及其中匹配字符的下标，供界面高亮
+    pub matched: String,
+    pub indices: Vec<usize>,
+}
+
+struct Candidate {
+    kind: QuickFindKind,
+    id: String,
+    title: String,
+    subtitle: Option<String>,
+    fields: Vec<String>,
+}
+
+// 事务、保存的搜索和规则统一打分排序；同分时事务按新到旧排列
+pub fn quick_find(
+    query: &str,
+    transactions: &[HttpTransaction],
+    saved_searches: &[SavedSearch],
+    rules: &[RequestRule],
+    limit: usize,
+) -> Vec<QuickFindResult> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let matcher = SkimMatcherV2::default().ignore_case();
+
+    let candidates = saved_searches
+        .iter()
+        .map(saved_search_candidate)
+        .chain(rules.iter().map(rule_candidate))
+        .chain(transactions.iter().rev().map(transaction_candidate));
+
+    let mut results: Vec<QuickFindResult> = candidates
+        .filter_map(|candidate| {
+            // 取各字段中得分最高的一项
+            let (score, matched, indices) = candidate
+                .fields
+                .iter()
+                .filter_map(|field| {
+                    matcher
+                        .fuzzy_indices(field, query)
+                        .map(|(score, indices)| (score, field, indices))
+                })
+                .max_by_key(|(score, _, _)| *score)?;
+            Some(QuickFindResult {
+                kind: candidate.kind,
+                id: candidate.id,
+                title: candidate.title,
+                subtitle: candidate.subtitle,
+                score,
+                matched: matched.clone(),
+                indices,
+            })
+        })
+        .collect();
+
+    results.sort_by_key(|r| std::cmp::Reverse(r.score));
+    results.truncate(limit);
+    results
+}
+
+fn transaction_candidate(transaction: &HttpTransaction) -> Candidate {
+    let status = transaction.response.as_ref().map(|r| r.status.to_string());
+    let mut subtitle = vec![status.clone().unwrap_or_else(|| "-".to_string())];
+    subtitle.extend(transaction.tags.iter().cloned());
+
+    let mut fields = vec![transaction.request.url.clone()];
+    fields.extend(status);
+    fields.extend(transaction.tags.iter().cloned());
+
+    Candidate {
+        kind: QuickFindKind::Transaction,
+        id: transaction.id.clone(),
+        title: format!("{} {}", transaction.request.method, transaction.request.url),
+        subtitle: Some(subtitle.join(" · ")),
+        fields,
+    }
+}
+
+fn saved_search_candidate(search: &SavedSearch) -> Candidate {
+    let mut fields = vec![search.name.clone()];
+    if !search.filter.keyword.is_empty() {
+        fields.push(search.filter.keyword.clone());
+    }
+    fields.extend(search.filter.domain.clone());
+
+    Candidate {
+        kind: QuickFindKind::SavedSearch,
+        id: search.id.clone(),
+        title: search.name.clone(),
+        subtitle: (!search.filter.keyword.is_empty()).then(|| search.filter.keyword.clone()),
+        fields,
+    }
+}
+
+fn rule_candidate(rule: &RequestRule) -> Candidate {
+    Candidate {
+        kind: QuickFindKind::Rule,
+        id: rule.id.clone(),
+        title: rule.name.clone(),
+        subtitle: Some(rule.matcher.summary()),
+        fields: vec![rule.name.clone(), rule.matcher.summary()],
+    }
+}
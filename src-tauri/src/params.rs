@@ -0,0 +1,152 @@
+use crate::classify::BodyKind;
+use crate::proxy::{HttpRequest, HttpTransaction};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamLocation {
+    Query,
+    Form,
+    Json,
+}
+
+impl ParamLocation {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Query => "查询参数",
+            Self::Form => "表单字段",
+            Self::Json => "JSON 字段",
+        }
+    }
+}
+
+// 请求中的单个参数；JSON 字段的名称是路径，形如 $.user.name、$.items[0]
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub location: ParamLocation,
+    pub name: String,
+    pub value: String,
+    // 查询参数和表单字段在原始顺序中的下标，允许同名参数重复出现
+    index: usize,
+    // JSON 字段对应的 JSON Pointer
+    pointer: String,
+}
+
+impl Parameter {
+    // 参数自身的键名，JSON 字段取路径的最后一段，如 $.user.password 取 password
+    pub fn key(&self) -> &str {
+        match self.location {
+            ParamLocation::Json => {
+                let last = self.name.rsplit('.').next().unwrap_or(&self.name);
+                last.split('[').next().unwrap_or(last)
+            }
+            ParamLocation::Query | ParamLocation::Form => &self.name,
+        }
+    }
+}
+
+// 查询字符串、表单请求体和 JSON 请求体中的全部参数，值已解码
+pub fn extract(transaction: &HttpTransaction) -> Vec<Parameter> {
+    let request = &transaction.request;
+    let mut params = Vec::new();
+    if let Ok(url) = url::Url::parse(&request.url) {
+        pairs(ParamLocation::Query, url.query_pairs(), &mut params);
+    }
+    match transaction.request_kind() {
+        BodyKind::FormUrlEncoded => {
+            pairs(ParamLocation::Form, url::form_urlencoded::parse(&request.body), &mut params);
+        }
+        BodyKind::Json => {
+            if let Ok(root) = serde_json::from_slice::<Value>(&request.body) {
+                json_leaves(&root, "$", "", &mut params);
+            }
+        }
+        _ => {}
+    }
+    params
+}
+
+fn pairs<'a>(
+    location: ParamLocation,
+    pairs: impl Iterator<Item = (Cow<'a, str>, Cow<'a, str>)>,
+    params: &mut Vec<Parameter>,
+) {
+    params.extend(pairs.enumerate().map(|(index, (name, value))| Parameter {
+        location,
+        name: name.into_owned(),
+        value: value.into_owned(),
+        index,
+        pointer: String::new(),
+    }));
+}
+
+// 收集字符串和数字叶子节点
+fn json_leaves(value: &Value, path: &str, pointer: &str, params: &mut Vec<Parameter>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                json_leaves(child, &format!("{}.{}", path, key), &format!("{}/{}", pointer, escaped), params);
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                json_leaves(child, &format!("{}[{}]", path, index), &format!("{}/{}", pointer, index), params);
+            }
+        }
+        Value::String(_) | Value::Number(_) => params.push(Parameter {
+            location: ParamLocation::Json,
+            name: path.to_string(),
+            value: value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string()),
+            index: 0,
+            pointer: pointer.to_string(),
+        }),
+        _ => {}
+    }
+}
+
+// 把请求中该参数的值替换为 value，其余内容不变
+pub fn with_value(request: &HttpRequest, param: &Parameter, value: &str) -> HttpRequest {
+    let mut mutated = request.clone();
+    match param.location {
+        ParamLocation::Query => {
+            if let Ok(mut url) = url::Url::parse(&request.url) {
+                let pairs = replace_pair(url.query_pairs(), param.index, value);
+                url.query_pairs_mut().clear().extend_pairs(&pairs);
+                mutated.url = url.to_string();
+            }
+        }
+        ParamLocation::Form => {
+            let pairs = replace_pair(url::form_urlencoded::parse(&request.body), param.index, value);
+            mutated.body = url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(&pairs)
+                .finish()
+                .into_bytes();
+        }
+        ParamLocation::Json => {
+            if let Ok(mut root) = serde_json::from_slice::<Value>(&request.body) {
+                if let Some(slot) = root.pointer_mut(&param.pointer) {
+                    *slot = Value::String(value.to_string());
+                }
+                mutated.body = serde_json::to_vec(&root).unwrap_or_default();
+            }
+        }
+    }
+    mutated
+}
+
+fn replace_pair<'a>(
+    pairs: impl Iterator<Item = (Cow<'a, str>, Cow<'a, str>)>,
+    index: usize,
+    value: &str,
+) -> Vec<(String, String)> {
+    pairs
+        .enumerate()
+        .map(|(i, (name, original))| {
+            let value = if i == index { value.to_string() } else { original.into_owned() };
+            (name.into_owned(), value)
+        })
+        .collect()
+}
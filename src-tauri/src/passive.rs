@@ -0,0 +1,808 @@
+use crate::active::SQL_ERROR_SIGNATURES;
+use crate::ai_analyzer::SecurityRisk;
+use crate::classify::BodyKind;
+use crate::cookies::{self, Cookie};
+use crate::jwt;
+use crate::params::{ParamLocation, Parameter};
+use crate::proxy::find_header;
+use crate::scanner::{Finding, FindingCategory, PassiveCheck, ScanContext};
+use crate::secrets::{self, SecretProvider};
+use regex::Regex;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::OnceLock;
+use url::{Host, Url};
+
+// 证据中参数值最多保留的字符数
+pub const MAX_EVIDENCE_CHARS: usize = 120;
+// 判断回显时参数值的最短长度
+const MIN_REFLECTED_LEN: usize = 4;
+
+// 按参数名判断的凭据和个人信息字段，比较前去掉大小写、下划线和连字符
+const SENSITIVE_PARAM_NAMES: [&str; 16] = [
+    "password", "passwd", "pwd", "secret", "clientsecret", "token", "accesstoken", "refreshtoken", "idtoken",
+    "apikey", "auth", "authorization", "creditcard", "cardnumber", "ssn", "socialsecurity",
+];
+
+pub fn default_checks() -> Vec<Box<dyn PassiveCheck>> {
+    vec![
+        Box::new(SqlInjectionCheck),
+        Box::new(XssCheck),
+        Box::new(OpenRedirectCheck),
+        Box::new(SsrfCheck),
+        Box::new(SensitiveDataCheck),
+        Box::new(SecretLeakCheck),
+        Box::new(CorsCheck),
+        Box::new(CookieCheck),
+        Box::new(JwtCheck),
+    ]
+}
+
+// 注入语法特征，只匹配单个参数解码后的值，避免把正常的 URL 和正文当作攻击
+fn sql_injection_regexes() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (r#"(?i)['"`]\s*\)*\s*\b(or|and)\b\s+\S+\s*(=|<|>|\blike\b)"#, "闭合引号后的布尔条件"),
+            (r"(?i)\b(or|and)\s+([0-9]+)\s*=\s*([0-9]+)\b", "恒真条件"),
+            (r"(?i)\bunion\b(\s+all)?\s+select\b", "UNION SELECT"),
+            (r"(?i);\s*(drop|delete|insert|update|alter|truncate|exec)\b", "堆叠语句"),
+            (r#"['"`]\s*(--|#|/\*)"#, "闭合引号后的注释"),
+            (r"(?i)\b(sleep|pg_sleep|benchmark)\s*\(|\bwaitfor\s+delay\b", "时间盲注函数"),
+        ]
+        .into_iter()
+        .map(|(pattern, label)| (Regex::new(pattern).unwrap(), label))
+        .collect()
+    })
+}
+
+fn xss_regexes() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (r"(?i)<\s*script\b", "script 标签"),
+            (r"(?i)<\s*[a-z]+\b[^>]*\bon[a-z]+\s*=", "带事件处理器的标签"),
+            (r"(?i)^\s*javascript\s*:|['\x22=]\s*javascript\s*:", "javascript: 伪协议"),
+            (r#"['"]\s*>\s*<\s*[a-z/!]"#, "闭合属性后插入标签"),
+        ]
+        .into_iter()
+        .map(|(pattern, label)| (Regex::new(pattern).unwrap(), label))
+        .collect()
+    })
+}
+
+fn param_evidence(param: &Parameter, label: &str) -> String {
+    let value: String = param.value.chars().take(MAX_EVIDENCE_CHARS).collect();
+    let ellipsis = if param.value.chars().count() > MAX_EVIDENCE_CHARS { "…" } else { "" };
+    format!("{} {} = {}{}（{}）", param.location.label(), param.name, value, ellipsis, label)
+}
+
+// 值足够长且原样出现在响应中才算回显，过短的值容易偶然命中；返回首次出现的位置
+fn reflection_offset(context: &ScanContext, param: &Parameter) -> Option<usize> {
+    if param.value.len() < MIN_REFLECTED_LEN {
+        return None;
+    }
+    context.response_text.find(&param.value)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HtmlContext {
+    Text,
+    Attribute,
+    Script,
+    Comment,
+}
+
+impl HtmlContext {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Text => "HTML 文本",
+            Self::Attribute => "标签属性",
+            Self::Script => "script 块",
+            Self::Comment => "HTML 注释",
+        }
+    }
+
+    // 按回显位置之前最近的标记判断所处的上下文
+    fn at(html: &str, offset: usize) -> Self {
+        let before = html[..offset].to_lowercase();
+        let last = |needle: &str| before.rfind(needle);
+        if last("<!--") > last("-->") {
+            Self::Comment
+        } else if last("<script") > last("</script") {
+            Self::Script
+        } else if last("<") > last(">") {
+            Self::Attribute
+        } else {
+            Self::Text
+        }
+    }
+}
+
+// 未经编码回显时能否突破当前上下文
+fn breaks_out(value: &str, context: HtmlContext) -> bool {
+    match context {
+        HtmlContext::Text => value.contains('<'),
+        HtmlContext::Attribute => value.contains(['"', '\'', '>']),
+        HtmlContext::Script => value.contains(['"', '\'', '<', '`']),
+        HtmlContext::Comment => value.contains("-->"),
+    }
+}
+
+pub struct SqlInjectionCheck;
+
+impl PassiveCheck for SqlInjectionCheck {
+    fn check(&self, context: &ScanContext) -> Vec<Finding> {
+        let response = context.response_text.to_lowercase();
+        let db_error = SQL_ERROR_SIGNATURES.iter().find(|s| response.contains(*s));
+        context
+            .params
+            .iter()
+            .filter_map(|param| {
+                let (_, label) = sql_injection_regexes().iter().find(|(regex, _)| regex.is_match(&param.value))?;
+                let target = format!("{} {}", param.location.label(), param.name);
+                let finding = match db_error {
+                    Some(signature) => Finding::new(
+                        "sql-injection",
+                        SecurityRisk::High,
+                        FindingCategory::SqlInjection,
+                        format!("潜在的 SQL 注入：{} 含注入语法，且响应出现数据库报错 \"{}\"", target, signature),
+                    ),
+                    None => Finding::new(
+                        "sql-injection",
+                        SecurityRisk::Medium,
+                        FindingCategory::SqlInjection,
+                        format!("潜在的 SQL 注入：{} 含注入语法", target),
+                    ),
+                };
+                Some(
+                    finding
+                        .subject(param.name.clone())
+                        .evidence(param_evidence(param, label))
+                        .remediation("使用参数化查询或 ORM 绑定参数，不要把请求参数拼接进 SQL 语句"),
+                )
+            })
+            .collect()
+    }
+}
+
+pub struct XssCheck;
+
+impl PassiveCheck for XssCheck {
+    fn check(&self, context: &ScanContext) -> Vec<Finding> {
+        let html = context.transaction.response_kind() == BodyKind::Html;
+        context
+            .params
+            .iter()
+            .filter_map(|param| {
+                let payload = xss_regexes().iter().find(|(regex, _)| regex.is_match(&param.value));
+                let reflection = reflection_offset(context, param)
+                    .filter(|_| html)
+                    .map(|offset| (offset, HtmlContext::at(&context.response_text, offset)));
+                let target = format!("{} {}", param.location.label(), param.name);
+                let finding = match (payload, reflection) {
+                    (Some((_, label)), Some((offset, location))) => Finding::new(
+                        "xss",
+                        SecurityRisk::High,
+                        FindingCategory::Xss,
+                        format!("潜在的 XSS 攻击：{} 含脚本载荷，且未经编码出现在 HTML 响应的{}中", target, location.label()),
+                    )
+                    .evidence(reflection_evidence(param, label, offset, location)),
+                    (Some((_, label)), None) => Finding::new(
+                        "xss",
+                        SecurityRisk::Medium,
+                        FindingCategory::Xss,
+                        format!("潜在的 XSS 攻击：{} 含脚本载荷，响应中未回显", target),
+                    )
+                    .evidence(param_evidence(param, label)),
+                    // 不含载荷但特殊字符原样回显，说明输出未做编码
+                    (None, Some((offset, location))) if breaks_out(&param.value, location) => Finding::new(
+                        "reflected-xss",
+                        if location == HtmlContext::Comment { SecurityRisk::Low } else { SecurityRisk::Medium },
+                        FindingCategory::Xss,
+                        format!("潜在的反射型 XSS：{} 的值未经编码出现在 HTML 响应的{}中", target, location.label()),
+                    )
+                    .evidence(reflection_evidence(param, "特殊字符未编码", offset, location)),
+                    _ => return None,
+                };
+                Some(
+                    finding
+                        .subject(param.name.clone())
+                        .remediation("输出到页面时按上下文进行 HTML/属性/JavaScript 编码，并配置 Content-Security-Policy"),
+                )
+            })
+            .collect()
+    }
+}
+
+fn reflection_evidence(param: &Parameter, label: &str, offset: usize, location: HtmlContext) -> String {
+    format!(
+        "{}；回显于响应体第 {} 字节（{}）",
+        param_evidence(param, label),
+        offset,
+        location.label()
+    )
+}
+
+// 常见的跳转目标参数名，比较时忽略大小写、下划线和连字符
+const REDIRECT_PARAM_NAMES: [&str; 16] = [
+    "url", "redirect", "redirecturl", "redirecturi", "redirectto", "return", "returnurl", "returnto",
+    "next", "continue", "dest", "destination", "goto", "target", "rurl", "forward",
+];
+
+fn is_redirect_param(name: &str) -> bool {
+    // JSON 字段取最后一段
+    let name = name.rsplit(['.', '[']).next().unwrap_or(name);
+    let normalized: String = name.chars().filter(|c| *c != '_' && *c != '-').collect::<String>().to_lowercase();
+    REDIRECT_PARAM_NAMES.contains(&normalized.as_str())
+}
+
+fn request_host(context: &ScanContext) -> Option<String> {
+    Url::parse(&context.transaction.request.url).ok()?.host_str().map(str::to_lowercase)
+}
+
+// 同一主机或互为子域名视为同站
+fn same_site(host: &str, other: &str) -> bool {
+    host == other || host.ends_with(&format!(".{}", other)) || other.ends_with(&format!(".{}", host))
+}
+
+// 参数值作为绝对地址或协议相对地址时的目标；浏览器把 /\ 和 \\ 开头的地址也当作协议相对地址
+fn absolute_target(value: &str) -> Option<Url> {
+    let value = value.trim();
+    let url = if value.starts_with("//") || value.starts_with("/\\") || value.starts_with("\\\\") {
+        Url::parse(&format!("http://{}", &value[2..]))
+    } else {
+        Url::parse(value)
+    };
+    url.ok().filter(|u| matches!(u.scheme(), "http" | "https") && u.host_str().is_some())
+}
+
+pub struct OpenRedirectCheck;
+
+impl PassiveCheck for OpenRedirectCheck {
+    fn check(&self, context: &ScanContext) -> Vec<Finding> {
+        let Some(own_host) = request_host(context) else {
+            return Vec::new();
+        };
+        // 3xx 响应实际跳转到的主机
+        let location = context
+            .transaction
+            .response
+            .as_ref()
+            .filter(|r| (300..400).contains(&r.status))
+            .and_then(|r| find_header(&r.headers, "location"))
+            .and_then(|l| Url::parse(&context.transaction.request.url).ok()?.join(l).ok())
+            .and_then(|u| u.host_str().map(str::to_lowercase));
+
+        context
+            .params
+            .iter()
+            .filter_map(|param| {
+                let target = absolute_target(&param.value)?;
+                let host = target.host_str()?.to_lowercase();
+                if same_site(&host, &own_host) {
+                    return None;
+                }
+                let name = format!("{} {}", param.location.label(), param.name);
+                let finding = if location.as_deref() == Some(host.as_str()) {
+                    Finding::new(
+                        "open-redirect",
+                        SecurityRisk::High,
+                        FindingCategory::OpenRedirect,
+                        format!("开放重定向：{} 指向外部主机 {}，响应随即跳转到该地址", name, host),
+                    )
+                    .header("Location")
+                } else if is_redirect_param(&param.name) {
+                    Finding::new(
+                        "open-redirect-param",
+                        SecurityRisk::Low,
+                        FindingCategory::OpenRedirect,
+                        format!("潜在的开放重定向：跳转参数 {} 指向外部主机 {}", name, host),
+                    )
+                } else {
+                    return None;
+                };
+                Some(
+                    finding
+                        .subject(param.name.clone())
+                        .evidence(param_evidence(param, "外部地址"))
+                        .remediation("跳转目标只接受站内相对路径，或按白名单校验目标主机；不要直接使用请求参数作为 Location"),
+                )
+            })
+            .collect()
+    }
+}
+
+// 云厂商实例元数据服务地址
+const METADATA_HOSTS: [&str; 5] = [
+    "169.254.169.254",
+    "metadata.google.internal",
+    "100.100.100.200",
+    "fd00:ec2::254",
+    "169.254.170.2",
+];
+
+// 元数据服务响应中的特征，出现在响应里说明服务端确实请求了该地址
+const METADATA_SIGNATURES: [&str; 5] = ["ami-id", "instance-id", "security-credentials", "computemetadata", "accesskeyid"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InternalTarget {
+    Metadata,
+    Loopback,
+    Private,
+}
+
+impl InternalTarget {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Metadata => "云元数据服务地址",
+            Self::Loopback => "本机回环地址",
+            Self::Private => "内网地址",
+        }
+    }
+
+    fn of_host(host: &Host<&str>) -> Option<Self> {
+        let ip = match host {
+            Host::Domain(domain) => {
+                let domain = domain.to_lowercase();
+                if METADATA_HOSTS.contains(&domain.as_str()) {
+                    return Some(Self::Metadata);
+                }
+                if domain == "localhost" || domain.ends_with(".localhost") {
+                    return Some(Self::Loopback);
+                }
+                return None;
+            }
+            Host::Ipv4(ip) => IpAddr::V4(*ip),
+            Host::Ipv6(ip) => ip.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(IpAddr::V6(*ip)),
+        };
+        if METADATA_HOSTS.contains(&ip.to_string().as_str()) {
+            return Some(Self::Metadata);
+        }
+        match ip {
+            IpAddr::V4(v4) if v4.is_loopback() || v4.is_unspecified() => Some(Self::Loopback),
+            IpAddr::V4(v4) if v4.is_private() || v4.is_link_local() => Some(Self::Private),
+            IpAddr::V6(v6) if v6.is_loopback() || v6.is_unspecified() => Some(Self::Loopback),
+            // fc00::/7 唯一本地地址和 fe80::/10 链路本地地址
+            IpAddr::V6(v6) if (v6.segments()[0] & 0xfe00) == 0xfc00 || (v6.segments()[0] & 0xffc0) == 0xfe80 => {
+                Some(Self::Private)
+            }
+            _ => None,
+        }
+    }
+
+    // 参数值可能是完整 URL，也可能只是 host[:port]
+    fn of_value(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Some(url) = Url::parse(value).ok().filter(|u| u.has_host()) {
+            return Self::of_host(&url.host()?);
+        }
+        let authority = value.split('/').next()?;
+        if authority.is_empty() || authority.contains(char::is_whitespace) {
+            return None;
+        }
+        Self::of_authority(authority)
+    }
+
+    // 裸值只认点分 IPv4、带方括号的 IPv6、localhost 和元数据主机名；
+    // 交给 URL 解析器时纯数字会被当作 IPv4（0 即 0.0.0.0），分页参数和数字 id 都会误报
+    fn of_authority(authority: &str) -> Option<Self> {
+        let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host).to_lowercase();
+        if METADATA_HOSTS.contains(&host.as_str()) {
+            return Some(Self::Metadata);
+        }
+        if let Some(bracketed) = host.strip_prefix('[') {
+            let ip: Ipv6Addr = bracketed.split(']').next()?.parse().ok()?;
+            return Self::of_host(&Host::Ipv6(ip));
+        }
+        let name = host.split(':').next()?;
+        match name.parse::<Ipv4Addr>() {
+            Ok(ip) => Self::of_host(&Host::Ipv4(ip)),
+            Err(_) => Self::of_host(&Host::Domain(name)),
+        }
+    }
+}
+
+pub struct SsrfCheck;
+
+impl PassiveCheck for SsrfCheck {
+    fn check(&self, context: &ScanContext) -> Vec<Finding> {
+        // 请求本身发往内网或本机时，参数中的内网地址通常是正常配置，只关注元数据地址
+        let internal_request = Url::parse(&context.transaction.request.url)
+            .ok()
+            .and_then(|u| u.host().map(|h| InternalTarget::of_host(&h).is_some()))
+            .unwrap_or(false);
+        let response = context.response_text.to_lowercase();
+        let metadata_leaked = METADATA_SIGNATURES.iter().find(|s| response.contains(*s));
+
+        context
+            .params
+            .iter()
+            .filter_map(|param| {
+                let target = InternalTarget::of_value(&param.value)?;
+                if internal_request && target != InternalTarget::Metadata {
+                    return None;
+                }
+                let name = format!("{} {}", param.location.label(), param.name);
+                let finding = match (target, metadata_leaked) {
+                    (InternalTarget::Metadata, Some(signature)) => Finding::new(
+                        "ssrf-metadata",
+                        SecurityRisk::Critical,
+                        FindingCategory::Ssrf,
+                        format!("SSRF：{} 指向{}，响应中出现元数据内容 \"{}\"", name, target.label(), signature),
+                    ),
+                    (InternalTarget::Metadata, None) => Finding::new(
+                        "ssrf-metadata",
+                        SecurityRisk::High,
+                        FindingCategory::Ssrf,
+                        format!("潜在的 SSRF：{} 指向{}", name, target.label()),
+                    ),
+                    _ => Finding::new(
+                        "ssrf-internal",
+                        SecurityRisk::Medium,
+                        FindingCategory::Ssrf,
+                        format!("潜在的 SSRF：{} 指向{}", name, target.label()),
+                    ),
+                };
+                Some(
+                    finding
+                        .subject(param.name.clone())
+                        .evidence(param_evidence(param, target.label()))
+                        .remediation("服务端按白名单校验要请求的地址，解析域名后拒绝内网、回环和链路本地地址，并禁用跟随重定向"),
+                )
+            })
+            .collect()
+    }
+}
+
+pub struct SensitiveDataCheck;
+
+impl SensitiveDataCheck {
+    fn is_sensitive(param: &Parameter) -> bool {
+        let normalized: String =
+            param.key().chars().filter(|c| *c != '_' && *c != '-').flat_map(char::to_lowercase).collect();
+        SENSITIVE_PARAM_NAMES.contains(&normalized.as_str())
+    }
+}
+
+impl PassiveCheck for SensitiveDataCheck {
+    // 只看参数名：出现在 URL 中的凭据会进入日志和浏览记录；请求体中的只在明文 HTTP 下报告
+    fn check(&self, context: &ScanContext) -> Vec<Finding> {
+        let plaintext = context.transaction.request.url.starts_with("http://");
+        context
+            .params
+            .iter()
+            .filter(|param| !param.value.is_empty() && Self::is_sensitive(param))
+            .filter(|param| matches!(param.location, ParamLocation::Query) || plaintext)
+            .map(|param| {
+                let target = format!("{} {}", param.location.label(), param.name);
+                let description = if matches!(param.location, ParamLocation::Query) {
+                    format!("敏感信息出现在 URL 中：{}", target)
+                } else {
+                    format!("敏感信息经明文 HTTP 传输：{}", target)
+                };
+                Finding::new("sensitive-data", SecurityRisk::Medium, FindingCategory::SensitiveData, description)
+                    .subject(param.name.clone())
+                    // 证据不包含参数值本身
+                    .evidence(format!("{}（值已隐藏）", target))
+                    .remediation("凭据和个人信息只通过 HTTPS 请求体或请求头传输，避免出现在 URL 中")
+            })
+            .collect()
+    }
+}
+
+// 已知服务的凭据格式和变量名像密钥的高熵字符串
+pub struct SecretLeakCheck;
+
+impl PassiveCheck for SecretLeakCheck {
+    fn check(&self, context: &ScanContext) -> Vec<Finding> {
+        secrets::scan(context.transaction)
+            .into_iter()
+            .map(|leak| {
+                let severity = match leak.provider {
+                    SecretProvider::PrivateKey => SecurityRisk::Critical,
+                    SecretProvider::Generic => SecurityRisk::Medium,
+                    _ => SecurityRisk::High,
+                };
+                let location = match &leak.header {
+                    Some(header) => format!("{} {}", leak.location.label(), header),
+                    None => leak.location.label().to_string(),
+                };
+                let finding = Finding::new(
+                    &format!("secret-{}", leak.provider.id()),
+                    severity,
+                    FindingCategory::SecretLeak,
+                    format!("泄露凭据：{}（{}）", leak.provider.label(), location),
+                )
+                .subject(leak.provider.label())
+                .evidence(format!("{}: {}", location, leak.masked))
+                .remediation("立即吊销并轮换该凭据；不要在 URL、消息体或响应中传输长期凭据，改用短期令牌或由服务端代为调用");
+                match &leak.header {
+                    Some(header) => finding.header(header),
+                    None => finding,
+                }
+            })
+            .collect()
+    }
+}
+
+// CORS 配置检测，结合配对的预检请求
+pub struct CorsCheck;
+
+impl PassiveCheck for CorsCheck {
+    fn check(&self, context: &ScanContext) -> Vec<Finding> {
+        let transaction = context.transaction;
+        let preflight = context.preflight;
+        let mut issues = Vec::new();
+        let cors = |id: &str, severity, header: &str, description: String, value: &str| {
+            Finding::new(id, severity, FindingCategory::Cors, description)
+                .header(header)
+                .evidence(format!("{}: {}", header, value))
+                .remediation("按白名单校验 Origin，只对可信来源返回 Access-Control-Allow-Origin，携带凭据时不要使用通配符或原样反射")
+        };
+        let origin = find_header(&transaction.request.headers, "origin");
+
+        let mut responses = Vec::new();
+        if let Some(response) = &transaction.response {
+            responses.push(("实际响应", response));
+        }
+        if let Some(response) = preflight.and_then(|p| p.response.as_ref()) {
+            responses.push(("预检响应", response));
+        }
+
+        for (label, response) in &responses {
+            let allow_origin = find_header(&response.headers, "access-control-allow-origin");
+            let allow_credentials = find_header(&response.headers, "access-control-allow-credentials")
+                .map(|v| v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false);
+
+            match allow_origin {
+                Some("*") if allow_credentials => {
+                    issues.push(cors(
+                        "cors-wildcard-credentials",
+                        SecurityRisk::Medium,
+                        "Access-Control-Allow-Origin",
+                        format!("CORS 配置错误（{}）: 通配符 Origin 与 Allow-Credentials 同时启用", label),
+                        "*",
+                    ));
+                }
+                Some("null") => {
+                    issues.push(cors(
+                        "cors-null-origin",
+                        SecurityRisk::High,
+                        "Access-Control-Allow-Origin",
+                        format!("CORS 配置错误（{}）: 允许 null Origin", label),
+                        "null",
+                    ));
+                }
+                Some(allowed) if allow_credentials && origin == Some(allowed) => {
+                    issues.push(cors(
+                        "cors-reflected-origin",
+                        SecurityRisk::High,
+                        "Access-Control-Allow-Origin",
+                        format!("CORS 风险（{}）: 携带凭据时原样反射请求 Origin {}", label, allowed),
+                        allowed,
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(preflight_response) = preflight.and_then(|p| p.response.as_ref()) {
+            if find_header(&preflight_response.headers, "access-control-allow-methods") == Some("*") {
+                issues.push(cors(
+                    "cors-wildcard-methods",
+                    SecurityRisk::Low,
+                    "Access-Control-Allow-Methods",
+                    "CORS 风险（预检响应）: Allow-Methods 使用通配符".to_string(),
+                    "*",
+                ));
+            }
+            let preflight_allows = find_header(&preflight_response.headers, "access-control-allow-origin");
+            let actual_allows = transaction.response.as_ref()
+                .and_then(|r| find_header(&r.headers, "access-control-allow-origin"))
+                .is_some();
+            if let Some(value) = preflight_allows.filter(|_| !actual_allows) {
+                issues.push(cors(
+                    "cors-inconsistent",
+                    SecurityRisk::Low,
+                    "Access-Control-Allow-Origin",
+                    "CORS 配置不一致: 预检通过但实际响应缺少 Access-Control-Allow-Origin".to_string(),
+                    value,
+                ));
+            }
+        }
+
+        issues
+    }
+}
+
+// Cookie 属性与作用域审计
+pub struct CookieCheck;
+
+// 去掉 Cookie 值，只保留名称和属性作为证据
+fn cookie_evidence(header: &str, line: &str) -> String {
+    let (pair, attributes) = line.split_once(';').unwrap_or((line, ""));
+    let name = pair.split_once('=').map(|(name, _)| name.trim()).unwrap_or(pair.trim());
+    if attributes.is_empty() {
+        format!("{}: {}=…", header, name)
+    } else {
+        format!("{}: {}=…;{}", header, name, attributes)
+    }
+}
+
+impl PassiveCheck for CookieCheck {
+    fn check(&self, context: &ScanContext) -> Vec<Finding> {
+        let transaction = context.transaction;
+        let mut issues = Vec::new();
+        let Ok(url) = url::Url::parse(&transaction.request.url) else {
+            return issues;
+        };
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+        // 浏览器把本机地址视为安全上下文，明文传输不算风险
+        let plain_http = url.scheme() == "http" && !matches!(host.as_str(), "localhost" | "127.0.0.1" | "[::1]");
+        let cookie = |id: &str, severity, header: &str, name: &str, description: String, remediation: &str| {
+            Finding::new(id, severity, FindingCategory::Cookie, description)
+                .header(header)
+                .subject(name)
+                .remediation(remediation)
+        };
+
+        // 请求中经明文 HTTP 携带的会话 Cookie
+        if plain_http {
+            if let Some(header) = find_header(&transaction.request.headers, "cookie") {
+                for name in header.split([';', '\n']).filter_map(|pair| pair.split_once('=')).map(|(name, _)| name.trim()) {
+                    if cookies::is_session_cookie(name) {
+                        issues.push(
+                            cookie(
+                                "cookie-session-plain-http",
+                                SecurityRisk::High,
+                                "Cookie",
+                                name,
+                                format!("会话 Cookie {} 经明文 HTTP 发送，可被网络中间人窃取", name),
+                                "全站启用 HTTPS 并为会话 Cookie 设置 Secure",
+                            )
+                            .evidence(format!("Cookie: {}=…", name)),
+                        );
+                    }
+                }
+            }
+        }
+
+        let Some(header) = transaction.response.as_ref().and_then(|r| find_header(&r.headers, "set-cookie")) else {
+            return issues;
+        };
+        let now = chrono::Utc::now();
+        for line in header.split('\n') {
+            let Some(parsed) = cookies::parse_set_cookie(line, &host, url.path(), now, &transaction.id) else {
+                continue;
+            };
+            // 删除指令不需要审计
+            if parsed.expires.is_some_and(|expires| expires <= now) {
+                continue;
+            }
+            let session = cookies::is_session_cookie(&parsed.name);
+            let name = parsed.name.as_str();
+            let weight = |sensitive, other| if session { sensitive } else { other };
+            let evidence = cookie_evidence("Set-Cookie", line);
+            let mut push = |finding: Finding| issues.push(finding.evidence(evidence.clone()));
+
+            if plain_http && session {
+                push(cookie(
+                    "cookie-session-plain-http",
+                    SecurityRisk::High,
+                    "Set-Cookie",
+                    name,
+                    format!("会话 Cookie {} 经明文 HTTP 下发", name),
+                    "全站启用 HTTPS 并为会话 Cookie 设置 Secure",
+                ));
+            }
+            if !parsed.secure {
+                push(cookie(
+                    "cookie-missing-secure",
+                    weight(SecurityRisk::Medium, SecurityRisk::Low),
+                    "Set-Cookie",
+                    name,
+                    format!("Cookie {} 未设置 Secure，可能随明文 HTTP 请求发送", name),
+                    "为 Cookie 添加 Secure 属性",
+                ));
+            }
+            if !parsed.http_only {
+                push(cookie(
+                    "cookie-missing-httponly",
+                    weight(SecurityRisk::Medium, SecurityRisk::Low),
+                    "Set-Cookie",
+                    name,
+                    format!("Cookie {} 未设置 HttpOnly，页面脚本可读取", name),
+                    "不需要被脚本读取的 Cookie 添加 HttpOnly 属性",
+                ));
+            }
+            match parsed.same_site.as_deref().map(str::to_lowercase).as_deref() {
+                None => push(cookie(
+                    "cookie-missing-samesite",
+                    SecurityRisk::Low,
+                    "Set-Cookie",
+                    name,
+                    format!("Cookie {} 未设置 SameSite，依赖浏览器默认行为防御 CSRF", name),
+                    "显式设置 SameSite=Lax 或 Strict",
+                )),
+                Some("none") if !parsed.secure => push(cookie(
+                    "cookie-samesite-none-insecure",
+                    SecurityRisk::Medium,
+                    "Set-Cookie",
+                    name,
+                    format!("Cookie {} 设置了 SameSite=None 但缺少 Secure，现代浏览器会拒绝该 Cookie", name),
+                    "SameSite=None 必须同时设置 Secure",
+                )),
+                Some("none") => push(cookie(
+                    "cookie-samesite-none",
+                    weight(SecurityRisk::Medium, SecurityRisk::Low),
+                    "Set-Cookie",
+                    name,
+                    format!("Cookie {} 使用 SameSite=None，跨站请求也会携带", name),
+                    "确认确实需要跨站发送，否则改为 SameSite=Lax",
+                )),
+                _ => {}
+            }
+            for (id, severity, description) in cookie_scope_issues(&parsed, &host, url.path(), session) {
+                push(cookie(id, severity, "Set-Cookie", name, description, "收窄 Domain 和 Path，只覆盖实际需要该 Cookie 的主机和路径"));
+            }
+        }
+        issues
+    }
+}
+
+// Domain 覆盖到父域名的所有子域名，或 Path 比下发它的接口宽得多
+fn cookie_scope_issues(
+    cookie: &Cookie,
+    host: &str,
+    request_path: &str,
+    session: bool,
+) -> Vec<(&'static str, SecurityRisk, String)> {
+    let mut issues = Vec::new();
+    if !cookie.host_only && cookie.domain != host {
+        issues.push((
+            "cookie-broad-domain",
+            if session { SecurityRisk::Medium } else { SecurityRisk::Low },
+            format!("Cookie {} 的 Domain={} 范围过宽，所有子域名都能读取和覆盖", cookie.name, cookie.domain),
+        ));
+    }
+    // 只对会话 Cookie 检查：由 /admin/login 等子路径下发却作用于整个站点
+    let set_below_root = request_path.trim_matches('/').contains('/');
+    if session && cookie.path == "/" && set_below_root {
+        issues.push((
+            "cookie-broad-path",
+            SecurityRisk::Low,
+            format!("Cookie {} 由 {} 下发但 Path=/，作用于站点所有路径", cookie.name, request_path),
+        ));
+    }
+    issues
+}
+
+// JWT 检测：alg=none、过期、内置字典可破解的弱密钥
+pub struct JwtCheck;
+
+impl PassiveCheck for JwtCheck {
+    fn check(&self, context: &ScanContext) -> Vec<Finding> {
+        let mut issues = Vec::new();
+        for mut found in jwt::find_in_transaction(context.transaction) {
+            jwt::crack_secret(&found.token, &mut found.info, jwt::COMMON_SECRETS.iter().copied());
+            let alg_none = found.info.algorithm.as_deref().is_some_and(|alg| alg.eq_ignore_ascii_case("none"));
+            for warning in &found.info.warnings {
+                let (id, severity, remediation) = if found.info.weak_secret.is_some() {
+                    ("jwt-weak-secret", SecurityRisk::Critical, "更换为足够长的随机签名密钥，并使已签发的令牌失效")
+                } else if alg_none {
+                    ("jwt-alg-none", SecurityRisk::Critical, "服务端固定允许的签名算法，拒绝 alg=none 的令牌")
+                } else {
+                    ("jwt-warning", SecurityRisk::Medium, "检查令牌的有效期和签名算法配置")
+                };
+                issues.push(
+                    Finding::new(id, severity, FindingCategory::Jwt, format!("JWT 风险（{}）: {}", found.location, warning))
+                        .subject(found.location.clone())
+                        .evidence(warning.clone())
+                        .remediation(remediation),
+                );
+            }
+        }
+        issues
+    }
+}
@@ -0,0 +1,295 @@
+use crate::proxy::{HttpRequest, HttpResponse, HttpTransaction, ProxyServer};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::net::Ipv4Addr;
+
+// pcapng 块类型
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+// 链路类型为裸 IP，省去伪造以太网头
+const LINKTYPE_RAW: u16 = 101;
+const OPT_END: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const SHB_USERAPPL: u16 = 4;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_PSH: u8 = 0x08;
+const TCP_ACK: u8 = 0x10;
+const MSS: usize = 1460;
+const CLIENT_ISN: u32 = 1_000;
+const SERVER_ISN: u32 = 500_000;
+const CLIENT_ADDR: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+const EPHEMERAL_PORT_BASE: u16 = 49152;
+// 解密后的 HTTPS 内容写到 Wireshark 默认按 HTTP 解析的端口，避免被当作 TLS
+const DECRYPTED_HTTPS_PORT: u16 = 80;
+
+struct Packet {
+    timestamp: chrono::DateTime<chrono::Utc>,
+    data: Vec<u8>,
+    comment: Option<String>,
+}
+
+struct Endpoint {
+    addr: Ipv4Addr,
+    port: u16,
+    seq: u32,
+}
+
+// 为每个事务合成一条完整的 TCP 连接（握手、请求、响应、挥手），Wireshark 可直接按 HTTP 解析
+pub fn pcapng(transactions: &[HttpTransaction]) -> Vec<u8> {
+    let mut out = Vec::new();
+    section_header(&mut out);
+    interface_description(&mut out);
+    for (index, transaction) in transactions.iter().enumerate() {
+        let Ok(url) = url::Url::parse(&transaction.request.url) else {
+            continue;
+        };
+        for packet in connection(transaction, &url, index) {
+            enhanced_packet(&mut out, &packet);
+        }
+    }
+    out
+}
+
+fn connection(transaction: &HttpTransaction, url: &url::Url, index: usize) -> Vec<Packet> {
+    let request = &transaction.request;
+    let started = request.timestamp;
+    let finished = started + chrono::Duration::from_std(transaction.duration.unwrap_or_default()).unwrap_or_default();
+    let responded = transaction
+        .response
+        .as_ref()
+        .map(|r| r.timestamp.clamp(started, finished.max(started)))
+        .unwrap_or(finished);
+    let tick = chrono::Duration::microseconds(1);
+
+    let mut client = Endpoint {
+        addr: CLIENT_ADDR,
+        port: EPHEMERAL_PORT_BASE + (index % 16384) as u16,
+        seq: CLIENT_ISN,
+    };
+    let mut server = Endpoint {
+        addr: server_addr(url),
+        port: match url.scheme() {
+            "https" | "wss" => DECRYPTED_HTTPS_PORT,
+            _ => url.port_or_known_default().unwrap_or(80),
+        },
+        seq: SERVER_ISN,
+    };
+
+    let mut packets = Vec::new();
+    let mut push = |timestamp, data, comment: Option<String>| packets.push(Packet { timestamp, data, comment });
+
+    // 三次握手，SYN 占一个序号
+    push(started, segment(&client, &server, 0, TCP_SYN, &[]), Some(format!("{} {}", request.method, request.url)));
+    client.seq += 1;
+    push(started, segment(&server, &client, client.seq, TCP_SYN | TCP_ACK, &[]), None);
+    server.seq += 1;
+    push(started, segment(&client, &server, server.seq, TCP_ACK, &[]), None);
+
+    let request_bytes = serialize_request(request, url);
+    for chunk in request_bytes.chunks(MSS) {
+        push(started + tick, segment(&client, &server, server.seq, TCP_PSH | TCP_ACK, chunk), None);
+        client.seq = client.seq.wrapping_add(chunk.len() as u32);
+    }
+    push(started + tick, segment(&server, &client, client.seq, TCP_ACK, &[]), None);
+
+    if let Some(response) = &transaction.response {
+        for chunk in serialize_response(response).chunks(MSS) {
+            push(responded, segment(&server, &client, client.seq, TCP_PSH | TCP_ACK, chunk), None);
+            server.seq = server.seq.wrapping_add(chunk.len() as u32);
+        }
+        push(responded, segment(&client, &server, server.seq, TCP_ACK, &[]), None);
+    }
+
+    // 四次挥手
+    let closed = finished.max(responded);
+    push(closed, segment(&client, &server, server.seq, TCP_FIN | TCP_ACK, &[]), None);
+    client.seq += 1;
+    push(closed, segment(&server, &client, client.seq, TCP_FIN | TCP_ACK, &[]), None);
+    server.seq += 1;
+    push(closed, segment(&client, &server, server.seq, TCP_ACK, &[]), None);
+    packets
+}
+
+// IP 字面量原样使用，域名映射到 198.18.0.0/15 测试网段，同一域名地址稳定
+fn server_addr(url: &url::Url) -> Ipv4Addr {
+    match url.host() {
+        Some(url::Host::Ipv4(addr)) => addr,
+        Some(host) => {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            host.to_string().hash(&mut hasher);
+            let hash = hasher.finish();
+            Ipv4Addr::new(198, 18 + (hash & 1) as u8, (hash >> 8) as u8, ((hash >> 16) as u8).max(1))
+        }
+        None => Ipv4Addr::new(198, 18, 0, 1),
+    }
+}
+
+// 存储的请求体/响应体已去掉分块编码，统一以 HTTP/1.1 和 Content-Length 重新组帧
+fn serialize_request(request: &HttpRequest, url: &url::Url) -> Vec<u8> {
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    let mut head = format!("{} {} HTTP/1.1\r\n", request.method, target);
+    if !request.headers.keys().any(|name| name.eq_ignore_ascii_case("host")) {
+        let host = url.host_str().unwrap_or_default();
+        match url.port() {
+            Some(port) => head.push_str(&format!("Host: {}:{}\r\n", host, port)),
+            None => head.push_str(&format!("Host: {}\r\n", host)),
+        }
+    }
+    write_headers(&mut head, &request.headers);
+    if !request.body.is_empty() {
+        head.push_str(&format!("Content-Length: {}\r\n", request.body.len()));
+    }
+    head.push_str("\r\n");
+    let mut bytes = head.into_bytes();
+    bytes.extend_from_slice(&request.body);
+    bytes
+}
+
+fn serialize_response(response: &HttpResponse) -> Vec<u8> {
+    let reason = hyper::StatusCode::from_u16(response.status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or_default();
+    let mut head = format!("HTTP/1.1 {} {}\r\n", response.status, reason);
+    write_headers(&mut head, &response.headers);
+    if !matches!(response.status, 100..=199 | 204 | 304) {
+        head.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+    }
+    head.push_str("\r\n");
+    let mut bytes = head.into_bytes();
+    bytes.extend_from_slice(&response.body);
+    bytes
+}
+
+// 多个 Set-Cookie 合并存储时以换行分隔，还原为多行
+fn write_headers(head: &mut String, headers: &HashMap<String, String>) {
+    let mut names: Vec<&String> = headers
+        .keys()
+        .filter(|name| {
+            !name.starts_with(':')
+                && !name.eq_ignore_ascii_case("content-length")
+                && !ProxyServer::is_hop_by_hop(name)
+        })
+        .collect();
+    names.sort();
+    for name in names {
+        for value in headers[name].split('\n') {
+            head.push_str(&format!("{}: {}\r\n", name, value.trim_end_matches('\r')));
+        }
+    }
+}
+
+fn segment(from: &Endpoint, to: &Endpoint, ack: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+    let mut tcp = Vec::with_capacity(20 + payload.len());
+    tcp.extend_from_slice(&from.port.to_be_bytes());
+    tcp.extend_from_slice(&to.port.to_be_bytes());
+    tcp.extend_from_slice(&from.seq.to_be_bytes());
+    tcp.extend_from_slice(&(if flags & TCP_ACK != 0 { ack } else { 0 }).to_be_bytes());
+    tcp.push(5 << 4);
+    tcp.push(flags);
+    tcp.extend_from_slice(&65535u16.to_be_bytes());
+    tcp.extend_from_slice(&[0, 0, 0, 0]);
+    tcp.extend_from_slice(payload);
+
+    // TCP 校验和覆盖伪首部
+    let mut pseudo = Vec::with_capacity(12 + tcp.len());
+    pseudo.extend_from_slice(&from.addr.octets());
+    pseudo.extend_from_slice(&to.addr.octets());
+    pseudo.extend_from_slice(&[0, 6]);
+    pseudo.extend_from_slice(&(tcp.len() as u16).to_be_bytes());
+    pseudo.extend_from_slice(&tcp);
+    let tcp_checksum = checksum(&pseudo);
+    tcp[16..18].copy_from_slice(&tcp_checksum.to_be_bytes());
+
+    let mut ip = Vec::with_capacity(20 + tcp.len());
+    ip.push(0x45);
+    ip.push(0);
+    ip.extend_from_slice(&((20 + tcp.len()) as u16).to_be_bytes());
+    ip.extend_from_slice(&[0, 0, 0x40, 0]);
+    ip.push(64);
+    ip.push(6);
+    ip.extend_from_slice(&[0, 0]);
+    ip.extend_from_slice(&from.addr.octets());
+    ip.extend_from_slice(&to.addr.octets());
+    let ip_checksum = checksum(&ip);
+    ip[10..12].copy_from_slice(&ip_checksum.to_be_bytes());
+    ip.extend_from_slice(&tcp);
+    ip
+}
+
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    body.extend_from_slice(value);
+    pad(body);
+}
+
+fn pad(body: &mut Vec<u8>) {
+    body.resize(body.len().next_multiple_of(4), 0);
+}
+
+// 块长度同时写在块首和块尾
+fn block(out: &mut Vec<u8>, block_type: u32, body: &[u8]) {
+    let total = (12 + body.len()) as u32;
+    out.extend_from_slice(&block_type.to_le_bytes());
+    out.extend_from_slice(&total.to_le_bytes());
+    out.extend_from_slice(body);
+    out.extend_from_slice(&total.to_le_bytes());
+}
+
+fn section_header(out: &mut Vec<u8>) {
+    let mut body = Vec::new();
+    body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+    body.extend_from_slice(&1u16.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    // 段长度未知
+    body.extend_from_slice(&(-1i64).to_le_bytes());
+    option(&mut body, SHB_USERAPPL, b"PacketMind");
+    option(&mut body, OPT_END, &[]);
+    block(out, SECTION_HEADER_BLOCK, &body);
+}
+
+fn interface_description(out: &mut Vec<u8>) {
+    let mut body = Vec::new();
+    body.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    body.extend_from_slice(&0u32.to_le_bytes());
+    block(out, INTERFACE_DESCRIPTION_BLOCK, &body);
+}
+
+// 时间戳使用默认精度（微秒）
+fn enhanced_packet(out: &mut Vec<u8>, packet: &Packet) {
+    let micros = packet.timestamp.timestamp_micros().max(0) as u64;
+    let mut body = Vec::new();
+    body.extend_from_slice(&0u32.to_le_bytes());
+    body.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+    body.extend_from_slice(&(micros as u32).to_le_bytes());
+    body.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+    body.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+    body.extend_from_slice(&packet.data);
+    pad(&mut body);
+    if let Some(comment) = &packet.comment {
+        option(&mut body, OPT_COMMENT, comment.as_bytes());
+        option(&mut body, OPT_END, &[]);
+    }
+    block(out, ENHANCED_PACKET_BLOCK, &body);
+}
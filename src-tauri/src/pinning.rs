@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use rustls::AlertDescription;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const PINNING_DETECTED_EVENT: &str = "pinning_detected";
+
+// 握手中途断开也可能只是客户端取消了请求，累计到这个次数才视为疑似证书绑定
+const ABORTS_BEFORE_SUSPECT: u32 = 2;
+const IDLE_BEFORE_SUSPECT: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinningSignal {
+    // 客户端收到证书后回复了证书相关的告警
+    CertificateRejected(String),
+    // 握手过程中直接断开连接
+    HandshakeAborted,
+    // 握手完成但没有发出任何请求就关闭，常见于在应用层校验证书的客户端
+    ClosedWithoutRequest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinningSuspect {
+    pub host: String,
+    pub rejected_handshakes: u32,
+    pub aborted_handshakes: u32,
+    pub idle_connections: u32,
+    pub last_alert: Option<String>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub likely: bool,
+    // 报告生成时该主机是否已在直通列表中
+    #[serde(default)]
+    pub passthrough: bool,
+    pub suggestion: String,
+}
+
+impl PinningSuspect {
+    fn is_likely(&self) -> bool {
+        self.rejected_handshakes > 0
+            || self.aborted_handshakes >= ABORTS_BEFORE_SUSPECT
+            || self.idle_connections >= IDLE_BEFORE_SUSPECT
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PinningMonitor {
+    suspects: HashMap<String, PinningSuspect>,
+}
+
+impl PinningMonitor {
+    // 主机首次被判定为疑似证书绑定时返回，用于通知界面
+    pub fn record(&mut self, host: &str, signal: PinningSignal) -> Option<PinningSuspect> {
+        let now = Utc::now();
+        let host = host.to_lowercase();
+        let suspect = self.suspects.entry(host.clone()).or_insert_with(|| PinningSuspect {
+            suggestion: format!("客户端可能对 {} 做了证书绑定（或尚未信任本地根证书），可将其加入 TLS 直通列表后重试", host),
+            host,
+            rejected_handshakes: 0,
+            aborted_handshakes: 0,
+            idle_connections: 0,
+            last_alert: None,
+            first_seen: now,
+            last_seen: now,
+            likely: false,
+            passthrough: false,
+        });
+        suspect.last_seen = now;
+        match signal {
+            PinningSignal::CertificateRejected(alert) => {
+                suspect.rejected_handshakes += 1;
+                suspect.last_alert = Some(alert);
+            }
+            PinningSignal::HandshakeAborted => suspect.aborted_handshakes += 1,
+            PinningSignal::ClosedWithoutRequest => suspect.idle_connections += 1,
+        }
+        if suspect.likely || !suspect.is_likely() {
+            return None;
+        }
+        suspect.likely = true;
+        Some(suspect.clone())
+    }
+
+    pub fn report(&self) -> Vec<PinningSuspect> {
+        let mut suspects: Vec<PinningSuspect> = self.suspects.values().cloned().collect();
+        suspects.sort_by(|a, b| b.likely.cmp(&a.likely).then(b.last_seen.cmp(&a.last_seen)));
+        suspects
+    }
+
+    pub fn clear(&mut self) {
+        self.suspects.clear();
+    }
+}
+
+// 从握手失败的错误中识别证书被拒绝或握手被中断；其他错误（如协议不匹配）返回 None
+pub fn classify(error: &std::io::Error) -> Option<PinningSignal> {
+    if let Some(rustls::Error::AlertReceived(alert)) = error.get_ref().and_then(|e| e.downcast_ref::<rustls::Error>()) {
+        return match alert {
+            AlertDescription::BadCertificate
+            | AlertDescription::UnsupportedCertificate
+            | AlertDescription::CertificateRevoked
+            | AlertDescription::CertificateExpired
+            | AlertDescription::CertificateUnknown
+            | AlertDescription::UnknownCA
+            | AlertDescription::AccessDenied => Some(PinningSignal::CertificateRejected(format!("{:?}", alert))),
+            _ => None,
+        };
+    }
+    match error.kind() {
+        std::io::ErrorKind::UnexpectedEof | std::io::ErrorKind::ConnectionReset | std::io::ErrorKind::ConnectionAborted => {
+            Some(PinningSignal::HandshakeAborted)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,144 @@
+use crate::export::{BodyInclusion, ExportOptions};
+use crate::classify::BodyKind;
+use crate::proxy::{HttpTransaction, ProxyServer};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+pub const POSTMAN_SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+// 按路径分组的目录树，叶子为请求
+#[derive(Default)]
+struct Folder {
+    folders: BTreeMap<String, Folder>,
+    items: Vec<Value>,
+}
+
+impl Folder {
+    fn insert(&mut self, path: &[String], item: Value) {
+        match path.split_first() {
+            Some((name, rest)) => self.folders.entry(name.clone()).or_default().insert(rest, item),
+            None => self.items.push(item),
+        }
+    }
+
+    fn into_items(self) -> Vec<Value> {
+        let mut items: Vec<Value> = self
+            .folders
+            .into_iter()
+            .map(|(name, folder)| json!({ "name": name, "item": folder.into_items() }))
+            .collect();
+        items.extend(self.items);
+        items
+    }
+}
+
+// Postman v2.1 集合：第一层按 host 分组，第二层按路径的第一段分组
+pub fn collection(name: &str, transactions: &[HttpTransaction], options: &ExportOptions) -> Value {
+    // 集合中的请求体需要可读可编辑，文本内容不使用 base64
+    let options = &ExportOptions {
+        bodies: match options.bodies {
+            BodyInclusion::Include => BodyInclusion::Base64BinaryOnly,
+            other => other,
+        },
+        ..options.clone()
+    };
+    let mut root = Folder::default();
+    for transaction in transactions.iter().filter(|t| !t.is_preflight()) {
+        let Ok(url) = url::Url::parse(&transaction.request.url) else {
+            continue;
+        };
+        let mut folder = vec![url.host_str().unwrap_or("unknown").to_string()];
+        if let Some(segment) = url.path_segments().and_then(|mut s| s.next()).filter(|s| !s.is_empty()) {
+            folder.push(segment.to_string());
+        }
+        root.insert(&folder, item(transaction, &url, options));
+    }
+
+    json!({
+        "info": {
+            "_postman_id": uuid::Uuid::new_v4().to_string(),
+            "name": name,
+            "schema": POSTMAN_SCHEMA,
+        },
+        "item": root.into_items(),
+    })
+}
+
+fn item(transaction: &HttpTransaction, url: &url::Url, options: &ExportOptions) -> Value {
+    let request = &transaction.request;
+    let mut headers: Vec<(&String, &String)> = request
+        .headers
+        .iter()
+        .filter(|(name, _)| {
+            !ProxyServer::is_hop_by_hop(name)
+                && !name.eq_ignore_ascii_case("host")
+                && !name.eq_ignore_ascii_case("content-length")
+        })
+        .collect();
+    headers.sort();
+
+    let mut postman_request = json!({
+        "method": request.method,
+        "header": headers
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect::<Vec<_>>(),
+        "url": postman_url(url),
+    });
+    if let Some(body) = body(transaction, options) {
+        postman_request["body"] = body;
+    }
+
+    json!({
+        "name": format!("{} {}", request.method, url.path()),
+        "request": postman_request,
+        "response": [],
+    })
+}
+
+fn postman_url(url: &url::Url) -> Value {
+    let mut value = json!({
+        "raw": url.as_str(),
+        "protocol": url.scheme(),
+        "host": url.host_str().unwrap_or_default().split('.').collect::<Vec<_>>(),
+        "path": url.path_segments().map(|s| s.collect::<Vec<_>>()).unwrap_or_default(),
+    });
+    if let Some(port) = url.port() {
+        value["port"] = json!(port.to_string());
+    }
+    let query: Vec<Value> = url
+        .query_pairs()
+        .map(|(key, value)| json!({ "key": key, "value": value }))
+        .collect();
+    if !query.is_empty() {
+        value["query"] = json!(query);
+    }
+    value
+}
+
+// Postman 只能以文件形式发送二进制内容，这类请求体不导出
+fn body(transaction: &HttpTransaction, options: &ExportOptions) -> Option<Value> {
+    let request = &transaction.request;
+    let body = options.encode_body(&request.body).filter(|b| b.encoding.is_none())?;
+    let kind = transaction.request_kind();
+
+    if kind == BodyKind::FormUrlEncoded && !body.redacted {
+        let fields: Vec<Value> = url::form_urlencoded::parse(body.text.as_bytes())
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect();
+        return Some(json!({ "mode": "urlencoded", "urlencoded": fields }));
+    }
+
+    let language = match kind {
+        BodyKind::Json | BodyKind::GraphQl => "json",
+        BodyKind::Xml => "xml",
+        BodyKind::Html => "html",
+        BodyKind::JavaScript => "javascript",
+        _ => "text",
+    };
+    Some(json!({
+        "mode": "raw",
+        "raw": body.text,
+        "options": { "raw": { "language": language } },
+    }))
+}
@@ -0,0 +1,923 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+// 无 schema 解码时嵌套消息的最大深度，防止恶意数据导致过深递归
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldKind {
+    Double,
+    Float,
+    Int64,
+    Uint64,
+    Int32,
+    Fixed64,
+    Fixed32,
+    Bool,
+    String,
+    Bytes,
+    Uint32,
+    Sfixed32,
+    Sfixed64,
+    Sint32,
+    Sint64,
+    // 全限定名，不带前导 "."
+    Message(String),
+    Enum(String),
+    // 解析 .proto 时尚未确定是消息还是枚举，链接阶段按作用域解析
+    Unresolved { name: String, scope: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldDescriptor {
+    pub name: String,
+    pub number: u32,
+    pub kind: FieldKind,
+    pub repeated: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MessageDescriptor {
+    pub fields: Vec<FieldDescriptor>,
+    // map<K, V> 字段对应的隐式条目消息，解码为 JSON 对象
+    pub map_entry: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct MethodDescriptor {
+    pub input: String,
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtoRegistrySummary {
+    pub files: Vec<String>,
+    pub messages: usize,
+    pub enums: usize,
+    // "/package.Service/Method" 形式，与 gRPC 请求路径一致
+    pub methods: Vec<String>,
+}
+
+// 已注册的消息、枚举和服务，键均为全限定名
+#[derive(Debug, Default)]
+pub struct ProtoRegistry {
+    files: Vec<String>,
+    messages: HashMap<String, MessageDescriptor>,
+    enums: HashMap<String, HashMap<i32, String>>,
+    methods: HashMap<String, MethodDescriptor>,
+}
+
+impl ProtoRegistry {
+    // .proto 按文本解析，其他扩展名（.pb、.desc、.protoset）按 protoc 生成的描述符集解析
+    pub fn register_file(&mut self, path: &Path) -> Result<()> {
+        let data = std::fs::read(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let is_proto = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("proto"));
+        let mut staged = ProtoRegistry::default();
+        if is_proto {
+            let source = String::from_utf8(data).map_err(|_| anyhow!("{} is not valid UTF-8", path.display()))?;
+            ProtoParser::new(&source).parse_file(&mut staged)
+        } else {
+            staged.read_descriptor_set(&data)
+        }
+        .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+
+        self.messages.extend(staged.messages);
+        self.enums.extend(staged.enums);
+        self.methods.extend(staged.methods);
+        let name = path.display().to_string();
+        if !self.files.contains(&name) {
+            self.files.push(name);
+        }
+        self.link();
+        Ok(())
+    }
+
+    pub fn summary(&self) -> ProtoRegistrySummary {
+        let mut methods: Vec<String> = self.methods.keys().cloned().collect();
+        methods.sort();
+        ProtoRegistrySummary {
+            files: self.files.clone(),
+            messages: self.messages.len(),
+            enums: self.enums.len(),
+            methods,
+        }
+    }
+
+    pub fn method(&self, path: &str) -> Option<&MethodDescriptor> {
+        self.methods.get(path)
+    }
+
+    pub fn decode(&self, message: &str, data: &[u8]) -> Result<Value> {
+        let descriptor = self
+            .messages
+            .get(message)
+            .ok_or_else(|| anyhow!("Unknown message type: {}", message))?;
+        self.decode_message(descriptor, data, 0)
+    }
+
+    // 跨文件引用的类型在全部文件注册后才能解析，每次注册后重新链接
+    fn link(&mut self) {
+        let messages: Vec<String> = self.messages.keys().cloned().collect();
+        for name in messages {
+            let resolved: Vec<FieldKind> = self.messages[&name]
+                .fields
+                .iter()
+                .map(|field| match &field.kind {
+                    FieldKind::Unresolved { name, scope } => self.resolve(name, scope).unwrap_or(field.kind.clone()),
+                    kind => kind.clone(),
+                })
+                .collect();
+            if let Some(message) = self.messages.get_mut(&name) {
+                for (field, kind) in message.fields.iter_mut().zip(resolved) {
+                    field.kind = kind;
+                }
+            }
+        }
+        let methods: Vec<(String, MethodDescriptor)> = self.methods.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        for (path, method) in methods {
+            let scope = path.trim_start_matches('/').split('/').next().unwrap_or_default().to_string();
+            let resolve = |name: &str| match self.resolve(name, &scope) {
+                Some(FieldKind::Message(full)) => full,
+                _ => name.trim_start_matches('.').to_string(),
+            };
+            let linked = MethodDescriptor { input: resolve(&method.input), output: resolve(&method.output) };
+            self.methods.insert(path, linked);
+        }
+    }
+
+    // 按 protobuf 的作用域规则由内向外查找；以 "." 开头的是全限定名
+    fn resolve(&self, name: &str, scope: &str) -> Option<FieldKind> {
+        let lookup = |full: &str| {
+            if self.messages.contains_key(full) {
+                Some(FieldKind::Message(full.to_string()))
+            } else if self.enums.contains_key(full) {
+                Some(FieldKind::Enum(full.to_string()))
+            } else {
+                None
+            }
+        };
+        if let Some(full) = name.strip_prefix('.') {
+            return lookup(full);
+        }
+        let mut scope = scope.to_string();
+        loop {
+            let candidate = if scope.is_empty() { name.to_string() } else { format!("{}.{}", scope, name) };
+            if let Some(kind) = lookup(&candidate) {
+                return Some(kind);
+            }
+            if scope.is_empty() {
+                return None;
+            }
+            scope = scope.rsplit_once('.').map(|(parent, _)| parent.to_string()).unwrap_or_default();
+        }
+    }
+
+    fn decode_message(&self, descriptor: &MessageDescriptor, data: &[u8], depth: usize) -> Result<Value> {
+        if depth > MAX_DEPTH {
+            return Err(anyhow!("Message nesting is too deep"));
+        }
+        let mut object = Map::new();
+        for (number, wire) in read_fields(data)? {
+            let Some(field) = descriptor.fields.iter().find(|f| f.number == number) else {
+                // 未知字段以字段编号为键保留原始内容
+                insert(&mut object, number.to_string(), decode_unknown(&wire, depth + 1), true);
+                continue;
+            };
+            let values = match (&wire, packable(&field.kind)) {
+                (Wire::Bytes(packed), true) => read_packed(packed, &field.kind)?
+                    .iter()
+                    .map(|wire| self.decode_value(&field.kind, wire, depth))
+                    .collect::<Result<Vec<_>>>()?,
+                _ => vec![self.decode_value(&field.kind, &wire, depth)?],
+            };
+            for value in values {
+                insert(&mut object, field.name.clone(), value, field.repeated);
+            }
+        }
+        if descriptor.map_entry {
+            return Ok(Value::Object(object));
+        }
+        // map 字段在线上是条目消息的重复字段，合并为一个对象
+        for field in &descriptor.fields {
+            let FieldKind::Message(entry) = &field.kind else {
+                continue;
+            };
+            if !self.messages.get(entry).is_some_and(|m| m.map_entry) {
+                continue;
+            }
+            if let Some(Value::Array(entries)) = object.remove(&field.name) {
+                let map = entries
+                    .into_iter()
+                    .map(|entry| {
+                        let key = match entry.get("key") {
+                            Some(Value::String(key)) => key.clone(),
+                            Some(key) => key.to_string(),
+                            None => String::new(),
+                        };
+                        (key, entry.get("value").cloned().unwrap_or(Value::Null))
+                    })
+                    .collect();
+                object.insert(field.name.clone(), Value::Object(map));
+            }
+        }
+        Ok(Value::Object(object))
+    }
+
+    fn decode_value(&self, kind: &FieldKind, wire: &Wire, depth: usize) -> Result<Value> {
+        let mismatch = || anyhow!("Wire type does not match field type {:?}", kind);
+        Ok(match (kind, wire) {
+            (FieldKind::Int32, Wire::Varint(v)) => Value::from(*v as i32),
+            (FieldKind::Int64, Wire::Varint(v)) => Value::from(*v as i64),
+            (FieldKind::Uint32, Wire::Varint(v)) => Value::from(*v as u32),
+            (FieldKind::Uint64, Wire::Varint(v)) => Value::from(*v),
+            (FieldKind::Sint32, Wire::Varint(v)) => Value::from(zigzag(*v) as i32),
+            (FieldKind::Sint64, Wire::Varint(v)) => Value::from(zigzag(*v)),
+            (FieldKind::Bool, Wire::Varint(v)) => Value::Bool(*v != 0),
+            (FieldKind::Enum(name), Wire::Varint(v)) => self
+                .enums
+                .get(name)
+                .and_then(|values| values.get(&(*v as i32)))
+                .map(|value| Value::String(value.clone()))
+                .unwrap_or_else(|| Value::from(*v as i32)),
+            (FieldKind::Fixed32, Wire::Fixed32(v)) => Value::from(*v),
+            (FieldKind::Sfixed32, Wire::Fixed32(v)) => Value::from(*v as i32),
+            (FieldKind::Float, Wire::Fixed32(v)) => Value::from(f32::from_bits(*v) as f64),
+            (FieldKind::Fixed64, Wire::Fixed64(v)) => Value::from(*v),
+            (FieldKind::Sfixed64, Wire::Fixed64(v)) => Value::from(*v as i64),
+            (FieldKind::Double, Wire::Fixed64(v)) => Value::from(f64::from_bits(*v)),
+            (FieldKind::String, Wire::Bytes(bytes)) => Value::String(String::from_utf8_lossy(bytes).into_owned()),
+            (FieldKind::Bytes, Wire::Bytes(bytes)) => Value::String(general_purpose::STANDARD.encode(bytes)),
+            (FieldKind::Message(name), Wire::Bytes(bytes)) => match self.messages.get(name) {
+                Some(descriptor) => self.decode_message(descriptor, bytes, depth + 1)?,
+                None => decode_unknown(wire, depth + 1),
+            },
+            // 类型未能解析时按无 schema 的方式展示
+            (FieldKind::Unresolved { .. }, wire) => decode_unknown(wire, depth + 1),
+            _ => return Err(mismatch()),
+        })
+    }
+
+    // FileDescriptorSet 本身是 protobuf 编码，按 descriptor.proto 中的字段编号读取
+    fn read_descriptor_set(&mut self, data: &[u8]) -> Result<()> {
+        for (number, wire) in read_fields(data)? {
+            if let (1, Wire::Bytes(file)) = (number, wire) {
+                self.read_file_descriptor(file)?;
+            }
+        }
+        if self.messages.is_empty() && self.methods.is_empty() {
+            return Err(anyhow!("No message types found in descriptor set"));
+        }
+        Ok(())
+    }
+
+    fn read_file_descriptor(&mut self, data: &[u8]) -> Result<()> {
+        let fields = read_fields(data)?;
+        let package = fields
+            .iter()
+            .find_map(|(number, wire)| (*number == 2).then(|| wire.as_str()).flatten())
+            .unwrap_or_default();
+        for (number, wire) in &fields {
+            let Wire::Bytes(bytes) = wire else {
+                continue;
+            };
+            match number {
+                4 => self.read_message_descriptor(bytes, package)?,
+                5 => self.read_enum_descriptor(bytes, package)?,
+                6 => self.read_service_descriptor(bytes, package)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn read_message_descriptor(&mut self, data: &[u8], scope: &str) -> Result<()> {
+        let fields = read_fields(data)?;
+        let name = qualify(scope, fields.iter().find_map(|(n, w)| (*n == 1).then(|| w.as_str()).flatten()).unwrap_or_default());
+        let mut message = MessageDescriptor::default();
+        for (number, wire) in &fields {
+            let Wire::Bytes(bytes) = wire else {
+                continue;
+            };
+            match number {
+                2 => message.fields.push(read_field_descriptor(bytes)?),
+                3 => self.read_message_descriptor(bytes, &name)?,
+                4 => self.read_enum_descriptor(bytes, &name)?,
+                // MessageOptions.map_entry
+                7 => message.map_entry = read_fields(bytes)?.iter().any(|(n, w)| *n == 7 && matches!(w, Wire::Varint(1))),
+                _ => {}
+            }
+        }
+        self.messages.insert(name, message);
+        Ok(())
+    }
+
+    fn read_enum_descriptor(&mut self, data: &[u8], scope: &str) -> Result<()> {
+        let fields = read_fields(data)?;
+        let name = qualify(scope, fields.iter().find_map(|(n, w)| (*n == 1).then(|| w.as_str()).flatten()).unwrap_or_default());
+        let mut values = HashMap::new();
+        for (number, wire) in &fields {
+            if let (2, Wire::Bytes(bytes)) = (number, wire) {
+                let value = read_fields(bytes)?;
+                let value_name = value.iter().find_map(|(n, w)| (*n == 1).then(|| w.as_str()).flatten());
+                let value_number = value.iter().find_map(|(n, w)| match (n, w) {
+                    (2, Wire::Varint(v)) => Some(*v as i32),
+                    _ => None,
+                });
+                if let (Some(value_name), Some(value_number)) = (value_name, value_number) {
+                    values.insert(value_number, value_name.to_string());
+                }
+            }
+        }
+        self.enums.insert(name, values);
+        Ok(())
+    }
+
+    fn read_service_descriptor(&mut self, data: &[u8], package: &str) -> Result<()> {
+        let fields = read_fields(data)?;
+        let service = qualify(package, fields.iter().find_map(|(n, w)| (*n == 1).then(|| w.as_str()).flatten()).unwrap_or_default());
+        for (number, wire) in &fields {
+            if let (2, Wire::Bytes(bytes)) = (number, wire) {
+                let method = read_fields(bytes)?;
+                let text = |field: u32| {
+                    method
+                        .iter()
+                        .find_map(|(n, w)| (*n == field).then(|| w.as_str()).flatten())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                self.methods.insert(
+                    format!("/{}/{}", service, text(1)),
+                    MethodDescriptor { input: text(2), output: text(3) },
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_field_descriptor(data: &[u8]) -> Result<FieldDescriptor> {
+    let fields = read_fields(data)?;
+    let varint = |field: u32| {
+        fields.iter().find_map(|(n, w)| match w {
+            Wire::Varint(v) if *n == field => Some(*v),
+            _ => None,
+        })
+    };
+    let text = |field: u32| fields.iter().find_map(|(n, w)| (*n == field).then(|| w.as_str()).flatten());
+    let type_name = text(6).unwrap_or_default().to_string();
+    let kind = match varint(5).unwrap_or_default() {
+        1 => FieldKind::Double,
+        2 => FieldKind::Float,
+        3 => FieldKind::Int64,
+        4 => FieldKind::Uint64,
+        5 => FieldKind::Int32,
+        6 => FieldKind::Fixed64,
+        7 => FieldKind::Fixed32,
+        8 => FieldKind::Bool,
+        9 => FieldKind::String,
+        11 => FieldKind::Message(type_name.trim_start_matches('.').to_string()),
+        12 => FieldKind::Bytes,
+        13 => FieldKind::Uint32,
+        14 => FieldKind::Enum(type_name.trim_start_matches('.').to_string()),
+        15 => FieldKind::Sfixed32,
+        16 => FieldKind::Sfixed64,
+        17 => FieldKind::Sint32,
+        18 => FieldKind::Sint64,
+        // protoc 输出中省略 type 时只有 type_name，链接阶段再区分
+        0 if !type_name.is_empty() => FieldKind::Unresolved { name: type_name, scope: String::new() },
+        other => return Err(anyhow!("Unsupported field type {}", other)),
+    };
+    Ok(FieldDescriptor {
+        name: text(1).unwrap_or_default().to_string(),
+        number: varint(3).unwrap_or_default() as u32,
+        kind,
+        // LABEL_REPEATED
+        repeated: varint(4) == Some(3),
+    })
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+fn packable(kind: &FieldKind) -> bool {
+    !matches!(
+        kind,
+        FieldKind::String | FieldKind::Bytes | FieldKind::Message(_) | FieldKind::Unresolved { .. }
+    )
+}
+
+fn zigzag(v: u64) -> i64 {
+    ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+fn insert(object: &mut Map<String, Value>, key: String, value: Value, repeated: bool) {
+    if !repeated {
+        object.insert(key, value);
+        return;
+    }
+    match object.entry(key).or_insert_with(|| Value::Array(Vec::new())) {
+        Value::Array(items) => items.push(value),
+        // 未知字段第二次出现时才转为数组
+        existing => *existing = Value::Array(vec![existing.take(), value]),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Wire<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+impl<'a> Wire<'a> {
+    fn as_str(&self) -> Option<&'a str> {
+        match self {
+            Self::Bytes(bytes) => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        }
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos).ok_or_else(|| anyhow!("Truncated varint"))?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(anyhow!("Varint is too long"))
+}
+
+fn take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8]> {
+    let end = pos.checked_add(len).filter(|end| *end <= data.len()).ok_or_else(|| anyhow!("Truncated field"))?;
+    let bytes = &data[*pos..end];
+    *pos = end;
+    Ok(bytes)
+}
+
+// 按线格式拆出字段；不支持已废弃的 group 编码
+pub fn read_fields(data: &[u8]) -> Result<Vec<(u32, Wire<'_>)>> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let key = read_varint(data, &mut pos)?;
+        let number = u32::try_from(key >> 3).map_err(|_| anyhow!("Invalid field number"))?;
+        if number == 0 {
+            return Err(anyhow!("Invalid field number 0"));
+        }
+        let wire = match key & 7 {
+            0 => Wire::Varint(read_varint(data, &mut pos)?),
+            1 => Wire::Fixed64(u64::from_le_bytes(take(data, &mut pos, 8)?.try_into()?)),
+            2 => {
+                let len = usize::try_from(read_varint(data, &mut pos)?)?;
+                Wire::Bytes(take(data, &mut pos, len)?)
+            }
+            5 => Wire::Fixed32(u32::from_le_bytes(take(data, &mut pos, 4)?.try_into()?)),
+            other => return Err(anyhow!("Unsupported wire type {}", other)),
+        };
+        fields.push((number, wire));
+    }
+    Ok(fields)
+}
+
+fn read_packed<'a>(data: &'a [u8], kind: &FieldKind) -> Result<Vec<Wire<'a>>> {
+    let mut values = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        values.push(match kind {
+            FieldKind::Double | FieldKind::Fixed64 | FieldKind::Sfixed64 => {
+                Wire::Fixed64(u64::from_le_bytes(take(data, &mut pos, 8)?.try_into()?))
+            }
+            FieldKind::Float | FieldKind::Fixed32 | FieldKind::Sfixed32 => {
+                Wire::Fixed32(u32::from_le_bytes(take(data, &mut pos, 4)?.try_into()?))
+            }
+            _ => Wire::Varint(read_varint(data, &mut pos)?),
+        });
+    }
+    Ok(values)
+}
+
+// 没有 schema 时的尽力解码：长度分隔字段依次尝试嵌套消息、UTF-8 文本和 base64
+pub fn decode_raw(data: &[u8]) -> Result<Value> {
+    let mut object = Map::new();
+    for (number, wire) in read_fields(data)? {
+        insert(&mut object, number.to_string(), decode_unknown(&wire, 1), true);
+    }
+    // 只出现一次的字段不包成数组
+    for value in object.values_mut() {
+        if let Value::Array(items) = value {
+            if items.len() == 1 {
+                *value = items.pop().unwrap_or(Value::Null);
+            }
+        }
+    }
+    Ok(Value::Object(object))
+}
+
+fn decode_unknown(wire: &Wire, depth: usize) -> Value {
+    match wire {
+        Wire::Varint(v) => Value::from(*v),
+        Wire::Fixed64(v) => Value::from(*v),
+        Wire::Fixed32(v) => Value::from(*v),
+        Wire::Bytes(bytes) => {
+            let printable = std::str::from_utf8(bytes)
+                .ok()
+                .filter(|text| text.chars().all(|c| !c.is_control() || c.is_whitespace()));
+            if let Some(text) = printable {
+                return Value::String(text.to_string());
+            }
+            if depth < MAX_DEPTH && !bytes.is_empty() {
+                if let Ok(nested) = decode_raw(bytes) {
+                    return nested;
+                }
+            }
+            Value::String(general_purpose::STANDARD.encode(bytes))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    Str(String),
+    Symbol(char),
+}
+
+// .proto 文本的精简解析器：只读取解码所需的消息、字段、枚举和服务，选项一律跳过
+struct ProtoParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl ProtoParser {
+    fn new(source: &str) -> Self {
+        Self { tokens: tokenize(source), pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self.tokens.get(self.pos).cloned().ok_or_else(|| anyhow!("Unexpected end of file"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Ident(ident) => Ok(ident),
+            other => Err(anyhow!("Expected identifier, found {:?}", other)),
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<()> {
+        match self.next()? {
+            Token::Symbol(c) if c == symbol => Ok(()),
+            other => Err(anyhow!("Expected '{}', found {:?}", symbol, other)),
+        }
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // 跳过到语句结束的分号，或跳过一个完整的花括号块
+    fn skip_statement(&mut self) -> Result<()> {
+        let mut depth = 0usize;
+        loop {
+            match self.next()? {
+                Token::Symbol(';') if depth == 0 => return Ok(()),
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn parse_file(&mut self, registry: &mut ProtoRegistry) -> Result<()> {
+        let mut package = String::new();
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Ident(keyword) if keyword == "package" => {
+                    self.pos += 1;
+                    package = self.ident()?;
+                    self.expect(';')?;
+                }
+                Token::Ident(keyword) if keyword == "message" => {
+                    self.pos += 1;
+                    self.parse_message(registry, &package)?;
+                }
+                Token::Ident(keyword) if keyword == "enum" => {
+                    self.pos += 1;
+                    self.parse_enum(registry, &package)?;
+                }
+                Token::Ident(keyword) if keyword == "service" => {
+                    self.pos += 1;
+                    self.parse_service(registry, &package)?;
+                }
+                Token::Symbol(';') => self.pos += 1,
+                // syntax、import、option、extend 等与解码无关
+                _ => self.skip_statement()?,
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_message(&mut self, registry: &mut ProtoRegistry, scope: &str) -> Result<()> {
+        let name = qualify(scope, &self.ident()?);
+        self.expect('{')?;
+        let mut message = MessageDescriptor::default();
+        self.parse_message_body(registry, &name, &mut message)?;
+        registry.messages.insert(name, message);
+        Ok(())
+    }
+
+    fn parse_message_body(&mut self, registry: &mut ProtoRegistry, name: &str, message: &mut MessageDescriptor) -> Result<()> {
+        loop {
+            let token = self.next()?;
+            let Token::Ident(keyword) = token else {
+                match token {
+                    Token::Symbol('}') => return Ok(()),
+                    Token::Symbol(';') => continue,
+                    other => return Err(anyhow!("Unexpected {:?} in message {}", other, name)),
+                }
+            };
+            match keyword.as_str() {
+                "message" => self.parse_message(registry, name)?,
+                "enum" => self.parse_enum(registry, name)?,
+                // oneof 中的字段与普通字段编码相同
+                "oneof" => {
+                    self.ident()?;
+                    self.expect('{')?;
+                    self.parse_message_body(registry, name, message)?;
+                }
+                "option" | "reserved" | "extensions" | "extend" => {
+                    self.pos -= 1;
+                    self.skip_statement()?;
+                }
+                "map" => {
+                    self.expect('<')?;
+                    let key = self.ident()?;
+                    self.expect(',')?;
+                    let value = self.ident()?;
+                    self.expect('>')?;
+                    let field_name = self.ident()?;
+                    let number = self.field_number()?;
+                    let entry = format!("{}.{}Entry", name, upper_camel(&field_name));
+                    registry.messages.insert(
+                        entry.clone(),
+                        MessageDescriptor {
+                            fields: vec![
+                                FieldDescriptor { name: "key".to_string(), number: 1, kind: scalar_or(&key, name), repeated: false },
+                                FieldDescriptor { name: "value".to_string(), number: 2, kind: scalar_or(&value, name), repeated: false },
+                            ],
+                            map_entry: true,
+                        },
+                    );
+                    message.fields.push(FieldDescriptor {
+                        name: field_name,
+                        number,
+                        kind: FieldKind::Message(entry),
+                        repeated: true,
+                    });
+                }
+                _ => {
+                    let (repeated, type_name) = match keyword.as_str() {
+                        "repeated" => (true, self.ident()?),
+                        "optional" | "required" => (false, self.ident()?),
+                        _ => (false, keyword),
+                    };
+                    if type_name == "group" {
+                        return Err(anyhow!("Groups are not supported in message {}", name));
+                    }
+                    let field_name = self.ident()?;
+                    let number = self.field_number()?;
+                    message.fields.push(FieldDescriptor {
+                        name: field_name,
+                        number,
+                        kind: scalar_or(&type_name, name),
+                        repeated,
+                    });
+                }
+            }
+        }
+    }
+
+    // "= N" 之后可能跟 [选项]，以分号结束
+    fn field_number(&mut self) -> Result<u32> {
+        self.expect('=')?;
+        let number = match self.next()? {
+            Token::Int(n) => u32::try_from(n).map_err(|_| anyhow!("Invalid field number {}", n))?,
+            other => return Err(anyhow!("Expected field number, found {:?}", other)),
+        };
+        if self.eat('[') {
+            while !self.eat(']') {
+                self.next()?;
+            }
+        }
+        self.expect(';')?;
+        Ok(number)
+    }
+
+    fn parse_enum(&mut self, registry: &mut ProtoRegistry, scope: &str) -> Result<()> {
+        let name = qualify(scope, &self.ident()?);
+        self.expect('{')?;
+        let mut values = HashMap::new();
+        loop {
+            match self.next()? {
+                Token::Symbol('}') => break,
+                Token::Symbol(';') => {}
+                Token::Ident(keyword) if keyword == "option" || keyword == "reserved" => {
+                    self.pos -= 1;
+                    self.skip_statement()?;
+                }
+                Token::Ident(value_name) => {
+                    self.expect('=')?;
+                    let negative = self.eat('-');
+                    let number = match self.next()? {
+                        Token::Int(n) => if negative { -n } else { n },
+                        other => return Err(anyhow!("Expected enum value, found {:?}", other)),
+                    };
+                    if self.eat('[') {
+                        while !self.eat(']') {
+                            self.next()?;
+                        }
+                    }
+                    self.expect(';')?;
+                    // 允许别名时保留第一个名称
+                    values.entry(number as i32).or_insert(value_name);
+                }
+                other => return Err(anyhow!("Unexpected {:?} in enum {}", other, name)),
+            }
+        }
+        registry.enums.insert(name, values);
+        Ok(())
+    }
+
+    fn parse_service(&mut self, registry: &mut ProtoRegistry, package: &str) -> Result<()> {
+        let service = qualify(package, &self.ident()?);
+        self.expect('{')?;
+        loop {
+            match self.next()? {
+                Token::Symbol('}') => return Ok(()),
+                Token::Symbol(';') => {}
+                Token::Ident(keyword) if keyword == "rpc" => {
+                    let method = self.ident()?;
+                    let input = self.rpc_type()?;
+                    if self.ident()? != "returns" {
+                        return Err(anyhow!("Expected 'returns' in rpc {}", method));
+                    }
+                    let output = self.rpc_type()?;
+                    // 方法体中只有选项
+                    if !self.eat(';') {
+                        self.skip_statement()?;
+                    }
+                    // 输入输出类型在链接阶段按服务所在的包解析
+                    registry.methods.insert(format!("/{}/{}", service, method), MethodDescriptor { input, output });
+                }
+                Token::Ident(_) => {
+                    self.pos -= 1;
+                    self.skip_statement()?;
+                }
+                other => return Err(anyhow!("Unexpected {:?} in service {}", other, service)),
+            }
+        }
+    }
+
+    fn rpc_type(&mut self) -> Result<String> {
+        self.expect('(')?;
+        let mut name = self.ident()?;
+        if name == "stream" {
+            if let Some(Token::Ident(_)) = self.peek() {
+                name = self.ident()?;
+            }
+        }
+        self.expect(')')?;
+        Ok(name)
+    }
+}
+
+fn scalar_or(type_name: &str, scope: &str) -> FieldKind {
+    match type_name {
+        "double" => FieldKind::Double,
+        "float" => FieldKind::Float,
+        "int64" => FieldKind::Int64,
+        "uint64" => FieldKind::Uint64,
+        "int32" => FieldKind::Int32,
+        "fixed64" => FieldKind::Fixed64,
+        "fixed32" => FieldKind::Fixed32,
+        "bool" => FieldKind::Bool,
+        "string" => FieldKind::String,
+        "bytes" => FieldKind::Bytes,
+        "uint32" => FieldKind::Uint32,
+        "sfixed32" => FieldKind::Sfixed32,
+        "sfixed64" => FieldKind::Sfixed64,
+        "sint32" => FieldKind::Sint32,
+        "sint64" => FieldKind::Sint64,
+        _ => FieldKind::Unresolved { name: type_name.to_string(), scope: scope.to_string() },
+    }
+}
+
+// map 条目消息的命名规则与 protoc 一致：foo_bar -> FooBarEntry
+fn upper_camel(name: &str) -> String {
+    let mut result = String::new();
+    let mut upper = true;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            result.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = '\0';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '"' | '\'' => {
+                let mut text = String::new();
+                let mut escaped = false;
+                for next in chars.by_ref() {
+                    match next {
+                        '\\' if !escaped => escaped = true,
+                        next if next == c && !escaped => break,
+                        next => {
+                            text.push(next);
+                            escaped = false;
+                        }
+                    }
+                }
+                tokens.push(Token::Str(text));
+            }
+            c if c.is_ascii_digit() => {
+                let mut text = String::from(c);
+                while let Some(next) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '.') {
+                    text.push(next);
+                }
+                let value = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+                    i64::from_str_radix(hex, 16).ok()
+                } else if text.len() > 1 && text.starts_with('0') && text.chars().all(|c| c.is_ascii_digit()) {
+                    i64::from_str_radix(&text[1..], 8).ok()
+                } else {
+                    text.parse().ok()
+                };
+                // 浮点数只出现在选项里，按 0 处理即可
+                tokens.push(Token::Int(value.unwrap_or_default()));
+            }
+            c if c.is_alphabetic() || c == '_' || c == '.' => {
+                let mut text = String::from(c);
+                while let Some(next) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '.') {
+                    text.push(next);
+                }
+                tokens.push(Token::Ident(text));
+            }
+            c => tokens.push(Token::Symbol(c)),
+        }
+    }
+    tokens
+}
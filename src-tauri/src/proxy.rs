@@ -1,17 +1,108 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use hyper::server::conn::http1;
+use tokio::sync::{mpsc, RwLock};
 use hyper::service::service_fn;
-use hyper::{Request, Response, StatusCode};
-use hyper::body::Incoming;
-use hyper_util::rt::TokioIo;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper::body::{Frame, Incoming};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Full, StreamBody};
+use bytes::Bytes;
 use tokio::net::{TcpListener, TcpStream};
 use anyhow::Result;
 use tracing::{info, error, warn};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use crate::dashboard::{self, LiveStats};
+use crate::history::{self, EndpointHistory, HistoricalStats};
+use crate::settings::{self, BodyCaptureOutcome, CaptureSettings};
+use crate::openapi::{ConformanceSummary, OpenApiSpec, SpecViolation};
+use crate::hooks::{self, ExportHook, HookInfo, HookRegistry, HookTrigger};
+use crate::selftest::{self, SelfTestReport, SELF_TEST_HOST};
+use crate::stats::{self, ActivityHeatmap, HeatmapGroupBy, TrafficStats};
+use crate::mitm::{self, CaCertInfo, CertificateAuthority, Rewind};
+use crate::emulation::{DeviceProfile, EmulationSettings};
+use crate::triage::{self, TriageItem, TriageQueue, TriageState, TriageSummary, TriageUpdate};
+use crate::ai_analyzer::{AIAnalysisResult, AIAnalyzer, AIModel, SecurityRisk, DEFAULT_AI_MODEL};
+use crate::waterfall::{self, LatencyBudget, PageLoadGroup};
+use crate::storage::{self, MemoryStore, TransactionStore};
+use crate::websocket::{self, WsDirection, WsMessage};
+use crate::palette::{self, QuickFindResult, SavedSearch};
+use crate::params::{self, Parameter};
+use crate::shadow::{self, ShadowComparison, ShadowConfig, ShadowReport};
+use crate::rules::{self, RuleEvaluation, RuleMatcher, ScriptPhase};
+use crate::ruleset::{self, ImportMode, RuleImportSummary};
+use crate::config::{self, AiConfigView, AiProvider, AiProviderConfig, AppConfig, ConfigStatus};
+use crate::keychain::{self, ApiKeySource};
+use crate::aicache::{self, AiCache};
+use crate::llm::{self, AiStreamEvent, StreamPayload};
+use crate::nlsearch::{self, InterpretedQuery};
+use crate::summary::{self, CaptureRange, CaptureSummary};
+use crate::rulegen::{self, RuleProposal};
+use crate::ai_response::{AIResponseConfig, AIResponseGenerator, GeneratedMock, ResponseType};
+use crate::mockdata::MockOptions;
+use crate::schema::{self, InferredSchema};
+use crate::baseline::{self, Anomaly, AnomalyBaseline, AnomalySeverity};
+use crate::redaction::{self, MaskTarget, MaskingSettings, PiiFinding, RedactionPolicy};
+use crate::scanner::{self, Finding, FindingStore, SecurityAnalyzer};
+use crate::active::{self, ActiveScanReport, ActiveScanSettings, ProbeOutcome, ProbeRateLimiter};
+use crate::secreport::{self, FindingsFormat};
+use crate::geoip::{self, GeoInfo, GeoIpDatabaseInfo, GeoIpResolver, MmdbReader};
+use crate::dns::{DnsCacheEntry, DnsResolution, DnsResolver, DnsSettings};
+use crate::upstream::{Timings, UpstreamClient};
+use crate::otel::{OtelExporter, OtelSettings, OtelStatus};
+use crate::chat::{self, ChatReply, ChatRole, Conversation, ConversationStore};
+use crate::modifications::{ModificationHistory, ModificationStage};
+use crate::quotas::{self, HostQuota, QuotaAlert, QuotaMonitor, QuotaUsage};
+use crate::breakpoints::{
+    self, Breakpoint, InterceptModification, InterceptPhase, InterceptQueue, InterceptResolution, InterceptedItem,
+};
+use crate::testserver::TestServer;
+use crate::export::ExportOptions;
+use crate::har;
+use crate::classify::{self, BodyKind};
+use crate::recovery::{self, Checkpoint, CheckpointWrite, RecoveryInfo};
+use crate::session::{self, SessionFile, SessionSummary};
+use crate::loadtest::{self, LoadTestFormat};
+use crate::postman;
+use crate::curl::{self, CurlOptions};
+use crate::mitmproxy;
+use crate::pcap;
+use crate::buffer::{self, CaptureBuffer, CaptureStats};
+use crate::detail::{BodyPart, TransactionDetail};
+use crate::hexdump::{self, HexDumpPage};
+use crate::jsonbody::{self, JsonBody};
+use crate::formdata::{self, ParsedBody};
+use crate::graphql::{self, GraphqlOperation};
+use crate::grpc::{self, GrpcBody};
+use crate::protobuf::{ProtoRegistry, ProtoRegistrySummary};
+use crate::jwt::{self, FoundJwt};
+use crate::cookies::{Cookie, CookieJar};
+use crate::secheaders::{self, SecurityHeaderReport};
+use crate::tlsinfo::{self, ForwardedTls, TlsInfo, UpstreamProbe, UpstreamTls};
+use crate::pinning::{self, PinningMonitor, PinningSignal, PinningSuspect};
+use crate::decoding;
+use crate::search::KeywordMatcher;
+use crate::bypass::{self, OriginalBypass, SystemProxyState};
+use crate::auth::{self, ProxyCredentials};
+use crate::throttle::{Direction, PacedBody, ThrottleProfile, ThrottleSettings, Throttler};
+use crate::scoring::{self, RiskScore};
+use tauri::Emitter;
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::WebSocketStream;
+use tokio::io::AsyncReadExt;
+use std::path::{Path, PathBuf};
+use notify::{EventKind, RecursiveMode, Watcher};
+
+// 返回给客户端的响应体按原始字节转发，二进制内容不做任何转换；超过阈值的响应边收边转发
+type ProxyBody = UnsyncBoxBody<Bytes, Box<dyn std::error::Error + Send + Sync>>;
+
+fn full_body(bytes: impl Into<Bytes>) -> ProxyBody {
+    Full::new(bytes.into()).map_err(|never| match never {}).boxed_unsync()
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HttpRequest {
@@ -20,6 +111,9 @@ pub struct HttpRequest {
     pub headers: HashMap<String, String>,
     pub body: Vec<u8>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    // 客户端与代理之间协商的协议版本，如 "HTTP/1.1"、"HTTP/2.0"
+    #[serde(default)]
+    pub version: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +122,16 @@ pub struct HttpResponse {
     pub headers: HashMap<String, String>,
     pub body: Vec<u8>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    // 代理与上游服务器之间协商的协议版本，代理自行生成的响应为 None
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+// 入库时解开的 Content-Encoding：存储的响应体和响应头已是解码后的内容，转发给客户端的仍是原始字节
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEncoding {
+    pub content_encoding: String,
+    pub encoded_size: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,56 +142,529 @@ pub struct HttpTransaction {
     pub duration: Option<std::time::Duration>,
     pub is_favorite: bool,
     pub tags: Vec<String>,
+    // CORS 预检配对：实际请求指向其预检，预检指向其实际请求
+    #[serde(default)]
+    pub preflight_id: Option<String>,
+    #[serde(default)]
+    pub preflight_for: Option<String>,
+    // 最近一次 AI 分析与安全检测的结果，随 HAR 一同导出
+    #[serde(default)]
+    pub ai_analysis: Option<AIAnalysisResult>,
+    #[serde(default)]
+    pub security_findings: Vec<String>,
+    // WebSocket 连接建立后双向传输的消息
+    #[serde(default)]
+    pub ws_messages: Vec<WsMessage>,
+    // 差异对比模式下 shadow 上游的响应及与主响应的差异
+    #[serde(default)]
+    pub shadow: Option<ShadowComparison>,
+    // 对该请求生效的规则 id，按执行顺序
+    #[serde(default)]
+    pub applied_rules: Vec<String>,
+    // 代理改写请求时保留原始请求及每一步修改，未改写时为空
+    #[serde(default)]
+    pub modifications: Vec<ModificationStage>,
+    // 重放产生的事务指向被重放的原始事务
+    #[serde(default)]
+    pub replay_of: Option<String>,
+    // 入库时嗅探出的实际内容类型，旧数据加载后补齐
+    #[serde(default)]
+    pub request_body_kind: Option<BodyKind>,
+    #[serde(default)]
+    pub response_body_kind: Option<BodyKind>,
+    // 入库时计算的风险评分，用于排序和筛选值得关注的请求
+    #[serde(default)]
+    pub risk: Option<RiskScore>,
+    // 测试人员对该请求的备注
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub response_encoding: Option<StoredEncoding>,
+    // 从请求中解析出的 GraphQL 操作，批量请求有多个
+    #[serde(default)]
+    pub graphql: Vec<GraphqlOperation>,
+    // 经 HTTPS 拦截捕获时两侧的 TLS 握手信息
+    #[serde(default)]
+    pub tls: Option<TlsInfo>,
+    // 未解密的 CONNECT 隧道只记录双向字节数，隧道关闭后填入
+    #[serde(default)]
+    pub tunnel: Option<TunnelStats>,
+    // 入库时检测到的个人信息，只记录类型、位置和遮盖后的样例
+    #[serde(default)]
+    pub pii_detected: Vec<PiiFinding>,
+    // 上游地址及其国家、ASN 归属，加载 GeoIP 数据库后在后台填入
+    #[serde(default)]
+    pub geo: Option<GeoInfo>,
+    // 转发前对上游主机的域名解析，直接访问 IP 或未经转发客户端的事务为空
+    #[serde(default)]
+    pub dns: Option<DnsResolution>,
+    // duration 的分阶段明细，只有经转发客户端发出的请求才有
+    #[serde(default)]
+    pub timings: Option<Timings>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TunnelStats {
+    // 客户端发往服务器
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+impl HttpTransaction {
+    pub fn new(
+        request: HttpRequest,
+        response: HttpResponse,
+        duration: std::time::Duration,
+        tags: Vec<String>,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            request,
+            response: Some(response),
+            duration: Some(duration),
+            is_favorite: false,
+            tags,
+            preflight_id: None,
+            preflight_for: None,
+            ai_analysis: None,
+            security_findings: Vec::new(),
+            ws_messages: Vec::new(),
+            shadow: None,
+            applied_rules: Vec::new(),
+            modifications: Vec::new(),
+            replay_of: None,
+            request_body_kind: None,
+            response_body_kind: None,
+            risk: None,
+            notes: None,
+            response_encoding: None,
+            graphql: Vec::new(),
+            tls: None,
+            tunnel: None,
+            pii_detected: Vec::new(),
+            geo: None,
+            dns: None,
+            timings: None,
+        }
+    }
+
+    // 需在 classify_bodies 之前调用；无法解码时保留原始字节和响应头
+    pub fn decode_response_body(&mut self) {
+        let Some(response) = self.response.as_mut() else {
+            return;
+        };
+        if self.response_encoding.is_some() || response.body.is_empty() {
+            return;
+        }
+        let Some(content_encoding) = find_header(&response.headers, "content-encoding")
+            .map(str::to_string)
+            .filter(|e| !e.trim().eq_ignore_ascii_case("identity"))
+        else {
+            return;
+        };
+        // 流式转发的响应只有前缀，压缩流不完整
+        let decoded = if self.tags.iter().any(|t| t == STREAMED_TAG) {
+            decoding::decode_prefix(&response.body, Some(&content_encoding))
+        } else {
+            decoding::decode(&response.body, Some(&content_encoding))
+        };
+        match decoded {
+            Ok(decoded) => {
+                let encoded_size = response.body.len();
+                response.body = decoded;
+                response.headers.retain(|name, _| !name.eq_ignore_ascii_case("content-encoding"));
+                for (name, value) in response.headers.iter_mut() {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        *value = response.body.len().to_string();
+                    }
+                }
+                // 按解码后的内容重新嗅探类型
+                self.response_body_kind = None;
+                self.response_encoding = Some(StoredEncoding { content_encoding, encoded_size });
+            }
+            Err(e) => warn!("Keeping encoded response body of {}: {}", self.request.url, e),
+        }
+    }
+
+    // 需在截断响应体之前调用，否则截断的 JSON 等会被误判；GraphQL 操作一并在此解析
+    pub fn classify_bodies(&mut self) {
+        if self.graphql.is_empty() {
+            self.graphql = graphql::extract(&self.request);
+        }
+        if self.request_body_kind.is_none() {
+            self.request_body_kind = Some(classify::classify(
+                &self.request.body,
+                find_header(&self.request.headers, "content-type"),
+            ));
+        }
+        if self.response_body_kind.is_none() {
+            self.response_body_kind = self
+                .response
+                .as_ref()
+                .map(|r| classify::classify(&r.body, find_header(&r.headers, "content-type")));
+        }
+    }
+
+    // 依赖内容类型，需在 classify_bodies 之后调用
+    pub fn rescore(&mut self) {
+        self.risk = Some(scoring::score(self));
+    }
+
+    // 检测个人信息并打标签，需在截断消息体之前调用
+    pub fn detect_pii(&mut self) {
+        self.pii_detected = redaction::scan(self);
+        if !self.pii_detected.is_empty() && !self.tags.iter().any(|t| t == redaction::PII_TAG) {
+            self.tags.push(redaction::PII_TAG.to_string());
+        }
+    }
+
+    pub fn risk_score(&self) -> u8 {
+        self.risk.as_ref().map(|r| r.score).unwrap_or(0)
+    }
+
+    // 用于列表展示和端点分组，代替笼统的 "POST /graphql"
+    pub fn graphql_label(&self) -> Option<String> {
+        let names: Vec<&str> = self.graphql.iter().filter_map(|op| op.operation_name.as_deref()).collect();
+        (!names.is_empty()).then(|| names.join(","))
+    }
+
+    pub fn request_kind(&self) -> BodyKind {
+        self.request_body_kind.unwrap_or(BodyKind::Empty)
+    }
+
+    pub fn response_kind(&self) -> BodyKind {
+        self.response_body_kind.unwrap_or(BodyKind::Empty)
+    }
+
+    // 关键字是否出现在按内容类型切分的请求体/响应体词中
+    pub fn body_contains(&self, keyword: &str) -> bool {
+        let terms: Vec<String> = keyword.split_whitespace().map(str::to_lowercase).collect();
+        if terms.is_empty() {
+            return false;
+        }
+        let mut tokens = classify::search_tokens(&self.request.body, self.request_kind());
+        if let Some(response) = &self.response {
+            tokens.extend(classify::search_tokens(&response.body, self.response_kind()));
+        }
+        terms.iter().all(|term| tokens.iter().any(|token| token.contains(term.as_str())))
+    }
+
+    pub fn is_preflight(&self) -> bool {
+        self.request.method == "OPTIONS"
+            && find_header(&self.request.headers, "origin").is_some()
+            && find_header(&self.request.headers, "access-control-request-method").is_some()
+    }
+}
+
+// 预检与实际请求的最大配对间隔
+const PREFLIGHT_PAIRING_WINDOW_SECS: i64 = 60;
+
+// 按名称查找请求头（不区分大小写）
+pub fn find_header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestRule {
     pub id: String,
     pub name: String,
-    pub pattern: String,
+    // 旧版本规则文件中的 pattern 字符串同样可以解析
+    #[serde(alias = "pattern")]
+    pub matcher: RuleMatcher,
     pub action: RuleAction,
     pub enabled: bool,
+    // 数值越大越先匹配，相同优先级按列表顺序
+    #[serde(default)]
+    pub priority: u32,
+    // 命中后不再匹配后续规则
+    #[serde(default)]
+    pub stop_processing: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RuleAction {
     Block,
     Redirect { target: String },
-    Rewrite { script: String },
+    Rewrite {
+        script: String,
+        #[serde(default)]
+        phase: ScriptPhase,
+    },
     Mock { response: String },
+    // 用本地文件或目录应答，路径支持 {host}、{path}、{file}、{query} 占位符
+    MapLocal {
+        path: String,
+        #[serde(default)]
+        content_type: Option<String>,
+    },
+    // 改写目标主机、端口和协议后继续转发；preserve_path 为 false 时请求根路径
+    MapRemote {
+        target_host: String,
+        #[serde(default)]
+        target_port: Option<u16>,
+        #[serde(default)]
+        target_scheme: Option<String>,
+        #[serde(default = "preserve_path_default")]
+        preserve_path: bool,
+    },
+    // 在 ms 上下浮动 jitter_ms 的随机延迟
+    Delay {
+        ms: u64,
+        #[serde(default)]
+        jitter_ms: u64,
+    },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+fn preserve_path_default() -> bool {
+    true
+}
+
+// 重放时覆盖原请求的字段，未给出的保持原值
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestOverrides {
+    pub method: Option<String>,
+    pub url: Option<String>,
+    pub headers: Option<HashMap<String, String>>,
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SearchFilter {
     pub keyword: String,
     pub method: Option<String>,
     pub status: Option<u16>,
+    // 状态码范围（含边界），没有响应的请求不满足范围条件
+    #[serde(default)]
+    pub min_status: Option<u16>,
+    #[serde(default)]
+    pub max_status: Option<u16>,
     pub domain: Option<String>,
+    pub collapse_preflight: Option<bool>,
+    // 只保留风险评分不低于该值的事务
+    #[serde(default)]
+    pub min_risk: Option<u8>,
+    // 按风险评分从高到低排序，默认按时间顺序
+    #[serde(default)]
+    pub sort_by_risk: Option<bool>,
+    // 关键字按正则表达式匹配
+    #[serde(default)]
+    pub use_regex: Option<bool>,
+    // 默认不区分大小写
+    #[serde(default)]
+    pub case_sensitive: Option<bool>,
+    // 关键字是否匹配请求体/响应体，默认匹配
+    #[serde(default)]
+    pub search_in_body: Option<bool>,
+    // 关键字是否匹配请求头/响应头，默认不匹配
+    #[serde(default)]
+    pub search_in_headers: Option<bool>,
+    // 请求开始时间范围（含边界）
+    #[serde(default)]
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    // 耗时范围，未完成的请求不满足任一耗时条件
+    #[serde(default)]
+    pub min_duration_ms: Option<u64>,
+    #[serde(default)]
+    pub max_duration_ms: Option<u64>,
+    // 请求体与响应体的字节数之和
+    #[serde(default)]
+    pub min_body_size: Option<usize>,
+    // 须同时带有全部标签
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    // 带有任一标签即排除
+    #[serde(default)]
+    pub exclude_tags: Option<Vec<String>>,
+    // 批量请求中任一操作名称匹配即可，不区分大小写
+    #[serde(default)]
+    pub graphql_operation: Option<String>,
+    // 上游地址所属国家，ISO 代码或英文名称均可；未解析出归属的事务不满足条件
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub asn: Option<u32>,
+}
+
+// 上游返回的响应体；限速时为逐块放行的消息体
+type UpstreamBody = UnsyncBoxBody<Bytes, hyper::Error>;
+
+// 上游响应；流式转发时 rest 为尚未读完的响应体
+struct Forwarded {
+    response: HttpResponse,
+    rest: Option<UpstreamBody>,
+    timings: Timings,
+    tls: Option<ForwardedTls>,
+}
+
+// 附加在 MITM 连接每个请求上的握手信息；authority 用于补上后台探测的结果
+struct MitmConnection {
+    authority: String,
+    tls: TlsInfo,
 }
 
+// 不应转发的逐跳头
+const HOP_BY_HOP_HEADERS: [&str; 9] = [
+    "connection", "keep-alive", "proxy-authenticate", "proxy-authorization",
+    "proxy-connection", "te", "trailer", "transfer-encoding", "upgrade",
+];
+
+// 响应体超过流式阈值、只记录了前缀的事务
+pub const STREAMED_TAG: &str = "body-streamed";
+
+// 命中 TLS 直通列表、未做解密的隧道
+pub const PASSTHROUGH_TAG: &str = "tls-passthrough";
+
+// 上游连接超时
+const UPSTREAM_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(Clone)]
 pub struct ProxyServer {
     port: u16,
+    client: UpstreamClient,
     transactions: Arc<RwLock<Vec<HttpTransaction>>>,
     filters: Arc<RwLock<Vec<String>>>,
     rules: Arc<RwLock<Vec<RequestRule>>>,
     favorites: Arc<RwLock<Vec<String>>>,
     is_running: Arc<RwLock<bool>>,
+    live_stats: Arc<RwLock<LiveStats>>,
+    dashboard_task: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+    data_dir: Arc<RwLock<Option<PathBuf>>>,
+    historical_stats: Arc<RwLock<HistoricalStats>>,
+    anomaly_baseline: Arc<RwLock<AnomalyBaseline>>,
+    // 最近检测到的异常，新的在后
+    anomalies: Arc<RwLock<VecDeque<Anomaly>>>,
+    // 导出、AI 提示和详情面板中的个人信息遮盖设置
+    pii_masking: Arc<RwLock<MaskingSettings>>,
+    // 发给模型和导出前整体替换的请求头与字段
+    redaction_policy: Arc<RwLock<RedactionPolicy>>,
+    security_analyzer: Arc<SecurityAnalyzer>,
+    // 被动和主动扫描累计的结果
+    security_findings: Arc<RwLock<FindingStore>>,
+    active_scan: Arc<RwLock<ActiveScanSettings>>,
+    probe_limiter: Arc<RwLock<ProbeRateLimiter>>,
+    geoip: Arc<RwLock<GeoIpResolver>>,
+    // 转发客户端使用的解析器，内部带缓存
+    dns: DnsResolver,
+    otel: OtelExporter,
+    settings: Arc<RwLock<CaptureSettings>>,
+    openapi_spec: Arc<RwLock<Option<OpenApiSpec>>>,
+    hooks: Arc<RwLock<HookRegistry>>,
+    ca: Arc<RwLock<Option<Arc<CertificateAuthority>>>>,
+    emulation: Arc<RwLock<EmulationSettings>>,
+    triage: Arc<RwLock<TriageQueue>>,
+    latency_budgets: Arc<RwLock<Vec<LatencyBudget>>>,
+    store: Arc<RwLock<Arc<dyn TransactionStore>>>,
+    saved_searches: Arc<RwLock<Vec<SavedSearch>>>,
+    shadow_config: Arc<RwLock<Option<ShadowConfig>>>,
+    quotas: Arc<RwLock<QuotaMonitor>>,
+    app_handle: Arc<RwLock<Option<tauri::AppHandle>>>,
+    intercepts: Arc<RwLock<InterceptQueue>>,
+    test_server: Arc<RwLock<Option<TestServer>>>,
+    checkpoint: Arc<RwLock<Checkpoint>>,
+    buffer: Arc<RwLock<CaptureBuffer>>,
+    system_proxy: Arc<RwLock<SystemProxyState>>,
+    credentials: Arc<RwLock<Option<ProxyCredentials>>>,
+    // 监听所有网卡以供局域网设备使用，必须先设置代理认证
+    allow_lan: Arc<RwLock<bool>>,
+    throttle: Arc<RwLock<Throttler>>,
+    config_status: Arc<RwLock<Option<ConfigStatus>>>,
+    ai_config: Arc<RwLock<Option<AiProviderConfig>>>,
+    // 随 AI 配置变化重建，各命令共用同一个实例
+    ai_analyzer: Arc<RwLock<AIAnalyzer>>,
+    ai_key_source: Arc<RwLock<Option<ApiKeySource>>>,
+    ai_cache: Arc<RwLock<AiCache>>,
+    // 进行中的流式 AI 请求，取消时中止对应任务
+    ai_requests: Arc<RwLock<HashMap<String, tokio::task::AbortHandle>>>,
+    conversations: Arc<RwLock<ConversationStore>>,
+    // 用户注册的 .proto 文件和描述符集，用于解码 gRPC 消息
+    proto_registry: Arc<RwLock<ProtoRegistry>>,
+    // 按域名汇总的 Cookie，随事务记录实时更新
+    cookie_jar: Arc<RwLock<CookieJar>>,
+    // 按 CONNECT 目标缓存的上游 TLS 探测状态
+    tls_probes: Arc<RwLock<HashMap<String, UpstreamProbe>>>,
+    // 拦截时客户端拒绝证书的迹象，按主机汇总
+    pinning: Arc<RwLock<PinningMonitor>>,
 }
 
 impl ProxyServer {
     pub fn new(port: u16) -> Self {
+        // 上游客户端直接连接目标，不走系统代理，否则会转发回自身；重定向交由客户端处理
+        let dns = DnsResolver::default();
+        let client = UpstreamClient::new(dns.clone(), UPSTREAM_CONNECT_TIMEOUT);
+        
         Self {
             port,
+            client,
             transactions: Arc::new(RwLock::new(Vec::new())),
             filters: Arc::new(RwLock::new(Vec::new())),
             rules: Arc::new(RwLock::new(Vec::new())),
             favorites: Arc::new(RwLock::new(Vec::new())),
             is_running: Arc::new(RwLock::new(false)),
+            live_stats: Arc::new(RwLock::new(LiveStats::default())),
+            dashboard_task: Arc::new(RwLock::new(None)),
+            data_dir: Arc::new(RwLock::new(None)),
+            historical_stats: Arc::new(RwLock::new(HistoricalStats::default())),
+            anomaly_baseline: Arc::new(RwLock::new(AnomalyBaseline::default())),
+            anomalies: Arc::new(RwLock::new(VecDeque::new())),
+            pii_masking: Arc::new(RwLock::new(MaskingSettings::default())),
+            redaction_policy: Arc::new(RwLock::new(RedactionPolicy::default())),
+            security_analyzer: Arc::new(SecurityAnalyzer::default()),
+            security_findings: Arc::new(RwLock::new(FindingStore::default())),
+            active_scan: Arc::new(RwLock::new(ActiveScanSettings::default())),
+            probe_limiter: Arc::new(RwLock::new(ProbeRateLimiter::default())),
+            geoip: Arc::new(RwLock::new(GeoIpResolver::default())),
+            dns,
+            otel: OtelExporter::default(),
+            settings: Arc::new(RwLock::new(CaptureSettings::default())),
+            openapi_spec: Arc::new(RwLock::new(None)),
+            hooks: Arc::new(RwLock::new(HookRegistry::default())),
+            ca: Arc::new(RwLock::new(None)),
+            emulation: Arc::new(RwLock::new(EmulationSettings::default())),
+            triage: Arc::new(RwLock::new(TriageQueue::default())),
+            latency_budgets: Arc::new(RwLock::new(Vec::new())),
+            store: Arc::new(RwLock::new(Arc::new(MemoryStore))),
+            saved_searches: Arc::new(RwLock::new(Vec::new())),
+            shadow_config: Arc::new(RwLock::new(None)),
+            quotas: Arc::new(RwLock::new(QuotaMonitor::default())),
+            app_handle: Arc::new(RwLock::new(None)),
+            intercepts: Arc::new(RwLock::new(InterceptQueue::default())),
+            test_server: Arc::new(RwLock::new(None)),
+            checkpoint: Arc::new(RwLock::new(Checkpoint::default())),
+            buffer: Arc::new(RwLock::new(CaptureBuffer::default())),
+            system_proxy: Arc::new(RwLock::new(SystemProxyState::default())),
+            credentials: Arc::new(RwLock::new(None)),
+            allow_lan: Arc::new(RwLock::new(false)),
+            throttle: Arc::new(RwLock::new(Throttler::default())),
+            config_status: Arc::new(RwLock::new(None)),
+            ai_config: Arc::new(RwLock::new(None)),
+            ai_analyzer: Arc::new(RwLock::new(AIAnalyzer::new(
+                None,
+                AIModel::OpenAI { model: DEFAULT_AI_MODEL.to_string() },
+            ))),
+            ai_key_source: Arc::new(RwLock::new(None)),
+            ai_cache: Arc::new(RwLock::new(AiCache::default())),
+            ai_requests: Arc::new(RwLock::new(HashMap::new())),
+            conversations: Arc::new(RwLock::new(ConversationStore::default())),
+            proto_registry: Arc::new(RwLock::new(ProtoRegistry::default())),
+            cookie_jar: Arc::new(RwLock::new(CookieJar::default())),
+            tls_probes: Arc::new(RwLock::new(HashMap::new())),
+            pinning: Arc::new(RwLock::new(PinningMonitor::default())),
         }
     }
 
     pub async fn start(&self) -> Result<()> {
-        let addr = SocketAddr::from(([127, 0, 0, 1], self.port));
+        let allow_lan = *self.allow_lan.read().await;
+        if allow_lan && self.credentials.read().await.is_none() {
+            return Err(anyhow::anyhow!("LAN access requires proxy credentials"));
+        }
+        // 监听 0.0.0.0 时本机仍可经 127.0.0.1 访问，系统代理设置不受影响
+        let ip = if allow_lan { [0, 0, 0, 0] } else { [127, 0, 0, 1] };
+        let addr = SocketAddr::from((ip, self.port));
         let listener = TcpListener::bind(addr).await?;
         
         info!("Proxy server listening on {}", addr);
@@ -99,11 +676,10 @@ impl ProxyServer {
         
         loop {
             let (stream, _) = listener.accept().await?;
-            let transactions = self.transactions.clone();
-            let filters = self.filters.clone();
+            let server = self.clone();
             
             tokio::spawn(async move {
-                if let Err(e) = Self::handle_connection(stream, transactions, filters).await {
+                if let Err(e) = server.handle_connection(stream).await {
                     error!("Error handling connection: {}", e);
                 }
             });
@@ -131,9 +707,219 @@ impl ProxyServer {
             self.configure_linux_proxy().await?;
         }
         
+        self.apply_proxy_bypass().await;
+        Ok(())
+    }
+
+    // 系统代理绕过列表，代理运行中修改时立即重新应用
+    pub async fn set_proxy_bypass(&self, entries: Vec<String>) -> Result<Vec<String>> {
+        let entries = bypass::normalize(entries)?;
+        self.system_proxy.write().await.bypass = entries.clone();
+        if self.is_running().await {
+            self.restore_proxy_bypass().await;
+            self.apply_proxy_bypass().await;
+        }
+        Ok(entries)
+    }
+
+    pub async fn get_proxy_bypass(&self) -> Vec<String> {
+        self.system_proxy.read().await.bypass.clone()
+    }
+
+    // 传入 None 关闭代理认证；允许局域网访问时不能关闭
+    pub async fn set_proxy_credentials(&self, credentials: Option<ProxyCredentials>) -> Result<()> {
+        let allow_lan = self.allow_lan.read().await;
+        let mut current = self.credentials.write().await;
+        match &credentials {
+            Some(credentials) => info!("Proxy authentication enabled for user {}", credentials.username()),
+            None if *allow_lan => {
+                return Err(anyhow::anyhow!("Disable LAN access before removing proxy credentials"));
+            }
+            None => info!("Proxy authentication disabled"),
+        }
+        *current = credentials;
+        Ok(())
+    }
+
+    // 下次启动代理时生效
+    pub async fn set_allow_lan(&self, enabled: bool) -> Result<()> {
+        let mut allow_lan = self.allow_lan.write().await;
+        if enabled && self.credentials.read().await.is_none() {
+            return Err(anyhow::anyhow!("Set proxy credentials before allowing LAN access"));
+        }
+        *allow_lan = enabled;
         Ok(())
     }
 
+    pub async fn get_allow_lan(&self) -> bool {
+        *self.allow_lan.read().await
+    }
+
+    // 未配置绕过列表时不改动系统原有设置
+    async fn apply_proxy_bypass(&self) {
+        let mut state = self.system_proxy.write().await;
+        if state.bypass.is_empty() || state.original.is_some() {
+            return;
+        }
+        info!("Applying system proxy bypass list: {}", state.bypass.join(", "));
+        
+        #[cfg(target_os = "macos")]
+        {
+            state.original = Some(Self::apply_macos_bypass(&state.bypass));
+        }
+        
+        #[cfg(target_os = "windows")]
+        {
+            state.original = Some(Self::apply_windows_bypass(&state.bypass));
+        }
+        
+        #[cfg(target_os = "linux")]
+        {
+            state.original = Some(Self::apply_linux_bypass(&state.bypass));
+        }
+    }
+
+    async fn restore_proxy_bypass(&self) {
+        let Some(original) = self.system_proxy.write().await.original.take() else {
+            return;
+        };
+        match original {
+            #[cfg(target_os = "macos")]
+            OriginalBypass::MacOs(services) => Self::restore_macos_bypass(services),
+            #[cfg(target_os = "windows")]
+            OriginalBypass::Windows(value) => Self::restore_windows_bypass(value),
+            #[cfg(target_os = "linux")]
+            OriginalBypass::Linux(value) => Self::restore_linux_bypass(value),
+        }
+        info!("System proxy bypass list restored");
+    }
+
+    #[cfg(target_os = "macos")]
+    fn macos_network_services() -> Vec<String> {
+        use std::process::Command;
+        
+        Command::new("networksetup")
+            .args(["-listallnetworkservices"])
+            .output()
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .skip(1)
+                    // 以 * 开头的是已停用的服务
+                    .map(|s| s.trim().trim_start_matches('*').trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[cfg(target_os = "macos")]
+    fn apply_macos_bypass(entries: &[String]) -> OriginalBypass {
+        use std::process::Command;
+        
+        let mut original = Vec::new();
+        for service in Self::macos_network_services() {
+            let current = Command::new("networksetup")
+                .args(["-getproxybypassdomains", &service])
+                .output()
+                .map(|output| bypass::parse_macos_bypass(&String::from_utf8_lossy(&output.stdout)))
+                .unwrap_or_default();
+            let _ = Command::new("networksetup")
+                .arg("-setproxybypassdomains")
+                .arg(&service)
+                .args(entries)
+                .output();
+            original.push((service, current));
+        }
+        OriginalBypass::MacOs(original)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn restore_macos_bypass(services: Vec<(String, Vec<String>)>) {
+        use std::process::Command;
+        
+        for (service, domains) in services {
+            // 传入 Empty 清空列表
+            let domains = if domains.is_empty() { vec!["Empty".to_string()] } else { domains };
+            let _ = Command::new("networksetup")
+                .arg("-setproxybypassdomains")
+                .arg(&service)
+                .args(&domains)
+                .output();
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    fn apply_windows_bypass(entries: &[String]) -> OriginalBypass {
+        use std::process::Command;
+        
+        let current = Command::new("powershell")
+            .args([
+                "-Command",
+                r#"(Get-ItemProperty -Path "HKCU:\Software\Microsoft\Windows\CurrentVersion\Internet Settings" -Name ProxyOverride -ErrorAction SilentlyContinue).ProxyOverride"#,
+            ])
+            .output()
+            .ok()
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|value| !value.is_empty());
+        Self::set_windows_proxy_override(Some(bypass::windows_override(entries)));
+        OriginalBypass::Windows(current)
+    }
+
+    #[cfg(target_os = "windows")]
+    fn restore_windows_bypass(value: Option<String>) {
+        Self::set_windows_proxy_override(value);
+    }
+
+    #[cfg(target_os = "windows")]
+    fn set_windows_proxy_override(value: Option<String>) {
+        use std::process::Command;
+        
+        let path = r#""HKCU:\Software\Microsoft\Windows\CurrentVersion\Internet Settings""#;
+        let script = match value {
+            // PowerShell 单引号字符串中单引号写作两个
+            Some(value) => format!(
+                "Set-ItemProperty -Path {} -Name ProxyOverride -Value '{}'",
+                path,
+                value.replace('\'', "''")
+            ),
+            None => format!("Remove-ItemProperty -Path {} -Name ProxyOverride -ErrorAction SilentlyContinue", path),
+        };
+        let _ = Command::new("powershell").args(["-Command", &script]).output();
+    }
+
+    #[cfg(target_os = "linux")]
+    fn apply_linux_bypass(entries: &[String]) -> OriginalBypass {
+        use std::process::Command;
+        
+        let current = Command::new("gsettings")
+            .args(["get", "org.gnome.system.proxy", "ignore-hosts"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .unwrap_or_default();
+        let _ = Command::new("gsettings")
+            .args(["set", "org.gnome.system.proxy", "ignore-hosts", &bypass::gsettings_list(entries)])
+            .output();
+        OriginalBypass::Linux(current)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn restore_linux_bypass(value: String) {
+        use std::process::Command;
+        
+        // 读取失败时恢复为 GNOME 的默认值
+        let value = if value.is_empty() {
+            "['localhost', '127.0.0.0/8', '::1']".to_string()
+        } else {
+            value
+        };
+        let _ = Command::new("gsettings")
+            .args(["set", "org.gnome.system.proxy", "ignore-hosts", &value])
+            .output();
+    }
+
     #[cfg(target_os = "macos")]
     async fn configure_macos_proxy(&self) -> Result<()> {
         use std::process::Command;
@@ -231,192 +1017,2236 @@ impl ProxyServer {
         Ok(())
     }
 
-    async fn handle_connection(
-        stream: TcpStream,
-        transactions: Arc<RwLock<Vec<HttpTransaction>>>,
-        filters: Arc<RwLock<Vec<String>>>,
-    ) -> Result<()> {
+    async fn handle_connection(&self, stream: TcpStream) -> Result<()> {
         let io = TokioIo::new(stream);
         
         let service = service_fn(|req: Request<Incoming>| {
-            let transactions = transactions.clone();
-            let filters = filters.clone();
+            let server = self.clone();
             
             async move {
-                Self::handle_request(req, transactions, filters).await
+                server.handle_request(req).await
             }
         });
 
-        http1::Builder::new()
-            .serve_connection(io, service)
-            .await?;
+        Self::connection_builder()
+            .serve_connection_with_upgrades(io, service)
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
             
         Ok(())
     }
 
-    async fn handle_request(
-        req: Request<Incoming>,
-        transactions: Arc<RwLock<Vec<HttpTransaction>>>,
-        filters: Arc<RwLock<Vec<String>>>,
-    ) -> Result<Response<String>, hyper::Error> {
+    // 同时支持 HTTP/1.1 与 HTTP/2，按连接前言自动识别
+    fn connection_builder() -> auto::Builder<TokioExecutor> {
+        let mut builder = auto::Builder::new(TokioExecutor::new());
+        builder.http1()
+            .preserve_header_case(true)
+            .title_case_headers(true);
+        builder
+    }
+
+    async fn handle_request(&self, mut req: Request<Incoming>) -> Result<Response<ProxyBody>, hyper::Error> {
+        // CONNECT 隧道只在建立时认证一次，隧道内的请求不再经过这里
+        if let Some(credentials) = self.credentials.read().await.as_ref() {
+            if !credentials.authorize(req.headers().get(hyper::header::PROXY_AUTHORIZATION)) {
+                warn!("Rejected unauthenticated proxy request: {} {}", req.method(), req.uri());
+                return Ok(Self::proxy_auth_required());
+            }
+            // 凭据不写入抓包记录
+            req.headers_mut().remove(hyper::header::PROXY_AUTHORIZATION);
+        }
+        
+        if req.method() == Method::CONNECT {
+            return Ok(self.handle_connect(req).await);
+        }
+        
+        let url = Self::absolute_url(&req);
+        self.process_request(req, url).await
+    }
+
+    async fn process_request(&self, req: Request<Incoming>, url: String) -> Result<Response<ProxyBody>, hyper::Error> {
+        if websocket::is_upgrade_request(req.headers()) {
+            return Ok(self.handle_websocket(req, url).await);
+        }
+        
         let method = req.method().to_string();
-        let url = req.uri().to_string();
+        let version = format!("{:?}", req.version());
         
         // Check filters - 使用模糊匹配
-        let filters = filters.read().await;
-        let is_filtered = if !filters.is_empty() {
-            let should_filter = filters.iter().any(|filter| {
-                // 提取域名进行模糊匹配
-                let domain = Self::extract_domain_from_url(&url);
-                domain.to_lowercase().contains(&filter.to_lowercase()) || 
-                url.to_lowercase().contains(&filter.to_lowercase())
-            });
-            
-            if should_filter {
-                warn!("Request filtered by '{}': {}", filters.join(", "), url);
-                true
-            } else {
-                false
-            }
-        } else {
-            false
-        };
+        let is_filtered = self.is_filtered(&url).await;
         
         info!("Handling request: {} {}", method, url);
         
-        // Create transaction
-        let transaction_id = uuid::Uuid::new_v4().to_string();
         let start_time = std::time::Instant::now();
         
-        let headers: HashMap<String, String> = req.headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-            .collect();
+        let headers = Self::collect_headers(req.headers());
+        let mitm = req.extensions().get::<Arc<MitmConnection>>().cloned();
         
-        // 读取请求体 - 暂时跳过
-        let body = Vec::new();
+        // 读取完整请求体
+        let body = req.into_body().collect().await?.to_bytes().to_vec();
         
-        let request = HttpRequest {
+        let mut request = HttpRequest {
             method,
             url,
             headers,
-            body: body.to_vec(),
+            body,
             timestamp: chrono::Utc::now(),
+            version: Some(version),
         };
         
-        // 转发请求到目标服务器
-        let response_result = Self::forward_request(&request).await;
+        // 自检请求由代理直接应答，其余请求转发到目标服务器
+        let host = Self::extract_domain_from_url(&request.url);
+        let is_self_test = host == SELF_TEST_HOST;
+        let mut emulated = None;
+        let mut shadow = None;
+        let mut throttled = None;
+        let mut dns = None;
+        let mut timings = None;
+        let mut forwarded_tls = None;
+        // 超过流式阈值时尚未读取的上游响应，其余部分直接转发给客户端
+        let mut streaming = None;
+        let mut intercepted = false;
+        let mut dropped = false;
+        let mut evaluation = RuleEvaluation::default();
+        let mut history = ModificationHistory::new(&request);
+        let response_result = if is_self_test {
+            Ok(Self::self_test_echo(&request))
+        } else {
+            // 规则先于设备模拟执行；Block、Mock 与 MapLocal 由代理直接应答
+            evaluation = rules::apply(&self.rules.read().await, &mut request, &mut history).await;
+            // 延迟在转发之前生效，Block 与 Mock 的响应同样推迟返回
+            if !evaluation.delay.is_zero() {
+                tokio::time::sleep(evaluation.delay).await;
+            }
+            match evaluation.response.take() {
+                Some(response) => Ok(response),
+                None => {
+                    // Redirect 与 Rewrite 可能改变目标主机
+                    let host = Self::extract_domain_from_url(&request.url);
+                    // 设备模拟改写的请求头会一并记录，便于核对实际发出的内容
+                    emulated = self.emulation.read().await.apply(&host, &mut request);
+                    if let Some(profile_id) = &emulated {
+                        history.record(format!("emulation:{}", profile_id), &request);
+                    }
+                    // 断点处编辑的是即将发出的请求
+                    if let Some(resumed) = self.intercept(InterceptPhase::Request, &mut request, None).await {
+                        intercepted = true;
+                        dropped = !resumed;
+                        history.record("breakpoint", &request);
+                    }
+                    if dropped {
+                        Ok(Self::dropped_response())
+                    } else {
+                        shadow = self.shadow_config.read().await.clone().and_then(|config| {
+                            config.route(&host, &mut request).map(|shadow_request| (config, shadow_request))
+                        });
+                        history.record("shadow", &request);
+                        let capture = self.settings.read().await.clone();
+                        // 响应阶段的规则和断点需要完整响应体，否则只缓冲到存储策略的上限
+                        let needs_body = rules::may_apply_response(&self.rules.read().await, &request)
+                            || self.intercepts.read().await.will_pause(InterceptPhase::Response, &request);
+                        let buffer_limit = |content_type: Option<&str>| {
+                            if needs_body {
+                                capture.stream_threshold
+                            } else {
+                                capture.buffer_limit(content_type)
+                            }
+                        };
+                        // 先解析并记录，转发客户端随后命中同一缓存
+                        dns = self.dns.lookup_url(&request.url).await;
+                        let (result, profile_id) = self.forward_throttled(&host, &request, &buffer_limit).await;
+                        throttled = profile_id;
+                        result.map(|forwarded| {
+                            streaming = forwarded.rest;
+                            timings = Some(forwarded.timings.with_dns(dns.as_ref()));
+                            forwarded_tls = forwarded.tls;
+                            forwarded.response
+                        })
+                    }
+                }
+            }
+        };
         
-        let (response, duration) = match response_result {
-            Ok(resp) => (resp, start_time.elapsed()),
+        let mut network_error = false;
+        let mut response = match response_result {
+            Ok(resp) => resp,
             Err(e) => {
                 error!("Failed to forward request: {}", e);
+                network_error = true;
                 // 返回错误响应
-                let error_response = HttpResponse {
-                    status: 502,
-                    headers: HashMap::new(),
-                    body: format!("Proxy error: {}", e).into_bytes(),
-                    timestamp: chrono::Utc::now(),
-                };
-                (error_response, start_time.elapsed())
+                Self::proxy_error_response(&e)
             }
         };
+        // 流式转发的响应只有前缀，不参与响应阶段规则和断点
+        if !is_self_test && !dropped && streaming.is_none() {
+            // 响应阶段规则先于响应断点执行，断点处编辑的是改写后的响应
+            let applied = rules::apply_response(
+                &self.rules.read().await,
+                &request,
+                &mut response,
+                &mut evaluation.script_errors,
+            ).await;
+            for rule_id in applied {
+                if !evaluation.applied_rules.contains(&rule_id) {
+                    evaluation.applied_rules.push(rule_id);
+                }
+            }
+            if let Some(resumed) = self.intercept(InterceptPhase::Response, &mut request, Some(&mut response)).await {
+                intercepted = true;
+                if !resumed {
+                    dropped = true;
+                    response = Self::dropped_response();
+                }
+            }
+        }
+        for error in std::mem::take(&mut evaluation.script_errors) {
+            self.emit(rules::SCRIPT_ERROR_EVENT, error).await;
+        }
+        let duration = start_time.elapsed();
         
         let mut tags = Vec::new();
         if is_filtered {
             tags.push("filtered".to_string());
         }
+        if is_self_test {
+            tags.push("self-test".to_string());
+        }
+        if network_error {
+            tags.push(triage::NETWORK_ERROR_TAG.to_string());
+        }
+        if let Some(profile_id) = emulated {
+            tags.push(format!("emulated:{}", profile_id));
+        }
+        if shadow.is_some() {
+            tags.push(shadow::SHADOW_TAG.to_string());
+        }
+        if let Some(profile_id) = throttled {
+            tags.push(format!("throttled:{}", profile_id));
+        }
+        if intercepted {
+            tags.push("intercepted".to_string());
+        }
+        if dropped {
+            tags.push("dropped".to_string());
+        }
+        if streaming.is_some() {
+            tags.push(STREAMED_TAG.to_string());
+        }
         
-        let transaction = HttpTransaction {
-            id: transaction_id,
-            request,
-            response: Some(response.clone()),
-            duration: Some(duration),
-            is_favorite: false,
-            tags,
-        };
-        
-        // Store transaction
-        transactions.write().await.push(transaction);
-        
-        // Build response
-        let mut response_builder = Response::builder()
-            .status(StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK));
-            
-        for (key, value) in &response.headers {
-            response_builder = response_builder.header(key, value);
+        let tls = self.connection_tls(mitm.as_deref()).await;
+        let probe_pending = tls.as_ref().is_some_and(|info| info.upstream_probe.is_none());
+        let transaction_id = self
+            .record_transaction(HttpTransaction {
+                applied_rules: evaluation.applied_rules,
+                modifications: history.into_stages(),
+                tls: tls.map(|info| TlsInfo { forwarded: forwarded_tls, ..info }),
+                dns,
+                timings,
+                ..HttpTransaction::new(request, response.clone(), duration, tags)
+            })
+            .await;
+        if probe_pending {
+            self.follow_upstream_probe(&transaction_id, mitm.as_deref()).await;
+        }
+        if let Some((config, shadow_request)) = shadow {
+            self.compare_shadow(transaction_id, config, shadow_request, response.clone(), streaming.is_some());
         }
         
-        Ok(response_builder
-            .body(String::from_utf8_lossy(&response.body).to_string())
-            .unwrap())
+        Ok(match streaming {
+            Some(rest) => Self::build_streaming_response(&response, rest),
+            None => Self::build_client_response(&response),
+        })
     }
 
-    fn extract_domain_from_url(url: &str) -> String {
-        // 处理 CONNECT 请求格式 (CONNECT www.google.com:443)
-        if url.contains(":") && !url.starts_with("http") {
-            return url.split(":").next().unwrap_or(url).to_string();
-        }
+    // 先与上游完成 WebSocket 握手，成功后向客户端返回 101，升级后的连接在后台双向转发并记录每条消息
+    async fn handle_websocket(&self, mut req: Request<Incoming>, url: String) -> Response<ProxyBody> {
+        let start_time = std::time::Instant::now();
+        let request = HttpRequest {
+            method: req.method().to_string(),
+            url,
+            headers: Self::collect_headers(req.headers()),
+            body: Vec::new(),
+            timestamp: chrono::Utc::now(),
+            version: Some(format!("{:?}", req.version())),
+        };
         
-        // 处理普通 URL 格式
-        if let Ok(parsed_url) = url::Url::parse(url) {
-            if let Some(host) = parsed_url.host_str() {
-                return host.to_string();
-            }
+        info!("Handling request: WEBSOCKET {}", request.url);
+        
+        let mitm = req.extensions().get::<Arc<MitmConnection>>().cloned();
+        let tls = self.connection_tls(mitm.as_deref()).await;
+        let probe_pending = tls.as_ref().is_some_and(|info| info.upstream_probe.is_none());
+        let mut tags = vec!["websocket".to_string()];
+        if self.is_filtered(&request.url).await {
+            tags.push("filtered".to_string());
         }
         
-        // 如果解析失败，尝试简单的字符串提取
-        if url.starts_with("http://") || url.starts_with("https://") {
-            if let Some(start) = url.find("://") {
-                let after_protocol = &url[start + 3..];
-                if let Some(end) = after_protocol.find('/') {
-                    return after_protocol[..end].to_string();
-                } else if let Some(end) = after_protocol.find(':') {
-                    return after_protocol[..end].to_string();
-                } else {
-                    return after_protocol.to_string();
+        let client_key = req.headers()
+            .get(hyper::header::SEC_WEBSOCKET_KEY)
+            .map(|k| k.as_bytes().to_vec());
+        let handshake = async {
+            let key = client_key.ok_or_else(|| anyhow::anyhow!("Missing Sec-WebSocket-Key"))?;
+            let upstream_request = websocket::upstream_request(
+                &request.url,
+                request.headers.iter().filter(|(k, _)| !Self::is_hop_by_hop(k)),
+            )?;
+            let (upstream, upstream_response) = tokio::time::timeout(
+                UPSTREAM_CONNECT_TIMEOUT,
+                tokio_tungstenite::connect_async(upstream_request),
+            )
+            .await??;
+            anyhow::Ok((key, upstream, upstream_response))
+        };
+        
+        let (key, upstream, upstream_response) = match handshake.await {
+            Ok(result) => result,
+            Err(e) => {
+                error!("WebSocket handshake failed for {}: {}", request.url, e);
+                let response = Self::proxy_error_response(&e);
+                tags.push(triage::NETWORK_ERROR_TAG.to_string());
+                let transaction_id = self
+                    .record_transaction(HttpTransaction { tls, ..HttpTransaction::new(request, response.clone(), start_time.elapsed(), tags) })
+                    .await;
+                if probe_pending {
+                    self.follow_upstream_probe(&transaction_id, mitm.as_deref()).await;
                 }
+                return Self::build_client_response(&response);
             }
-        }
-        
-        url.to_string()
-    }
-
-    async fn forward_request(request: &HttpRequest) -> Result<HttpResponse> {
-        // 简化的代理实现 - 返回模拟响应
-        // 在实际应用中，这里会转发到真实的目标服务器
+        };
         
-        let status = match request.method.as_str() {
-            "GET" => 200,
-            "POST" => 201,
-            "PUT" => 200,
-            "DELETE" => 204,
-            _ => 200,
+        let response = HttpResponse {
+            status: upstream_response.status().as_u16(),
+            headers: Self::collect_headers(upstream_response.headers()),
+            body: Vec::new(),
+            timestamp: chrono::Utc::now(),
+            version: Some(format!("{:?}", upstream_response.version())),
         };
+        let transaction_id = self
+            .record_transaction(HttpTransaction { tls, ..HttpTransaction::new(request, response.clone(), start_time.elapsed(), tags) })
+            .await;
+        if probe_pending {
+            self.follow_upstream_probe(&transaction_id, mitm.as_deref()).await;
+        }
         
-        let mut headers = HashMap::new();
-        headers.insert("Content-Type".to_string(), "application/json".to_string());
-        headers.insert("X-Proxy-By".to_string(), "PacketMind AI".to_string());
-        
-        let body = serde_json::json!({
-            "message": "Proxied by PacketMind AI",
-            "original_request": {
-                "method": request.method,
-                "url": request.url,
-                "timestamp": chrono::Utc::now().to_rfc3339(),
-            },
-            "proxy_info": {
-                "version": "1.0.0",
-                "features": ["auto_proxy", "ai_analysis", "filtering"]
+        // 上游的 Accept 对应代理自己的 key，需按客户端的 key 重新计算
+        let mut client_response = Self::build_client_response(&response);
+        let headers = client_response.headers_mut();
+        headers.insert(hyper::header::UPGRADE, hyper::header::HeaderValue::from_static("websocket"));
+        headers.insert(hyper::header::CONNECTION, hyper::header::HeaderValue::from_static("Upgrade"));
+        if let Ok(accept) = tokio_tungstenite::tungstenite::handshake::derive_accept_key(&key).parse() {
+            headers.insert(hyper::header::SEC_WEBSOCKET_ACCEPT, accept);
+        }
+        
+        let on_upgrade = hyper::upgrade::on(&mut req);
+        let server = self.clone();
+        tokio::spawn(async move {
+            match on_upgrade.await {
+                Ok(upgraded) => {
+                    let client = WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None).await;
+                    server.relay_websocket(&transaction_id, client, upstream).await;
+                }
+                Err(e) => error!("WebSocket upgrade failed: {}", e),
             }
-        }).to_string();
+        });
         
-        Ok(HttpResponse {
-            status,
+        client_response
+    }
+    
+    // 双向转发直到任一端断开，Close 帧同样转发以便对端完成关闭握手
+    async fn relay_websocket<C, U>(&self, transaction_id: &str, client: WebSocketStream<C>, upstream: WebSocketStream<U>)
+    where
+        C: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+        U: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let (mut client_sink, mut client_stream) = client.split();
+        let (mut upstream_sink, mut upstream_stream) = upstream.split();
+        let mut pending = Vec::new();
+        let mut flush = tokio::time::interval(websocket::WS_FLUSH_INTERVAL);
+        flush.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        
+        loop {
+            let (direction, message) = tokio::select! {
+                message = client_stream.next() => (WsDirection::ClientToServer, message),
+                message = upstream_stream.next() => (WsDirection::ServerToClient, message),
+                _ = flush.tick() => {
+                    self.flush_ws_messages(transaction_id, &mut pending).await;
+                    continue;
+                }
+            };
+            let message = match message {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => {
+                    warn!("WebSocket {} closed with error: {}", transaction_id, e);
+                    break;
+                }
+                None => break,
+            };
+            
+            if let Some(record) = WsMessage::from_message(direction, &message) {
+                pending.push(record);
+                if pending.len() >= websocket::WS_FLUSH_BATCH {
+                    self.flush_ws_messages(transaction_id, &mut pending).await;
+                }
+            }
+            let sent = match direction {
+                WsDirection::ClientToServer => upstream_sink.send(message).await,
+                WsDirection::ServerToClient => client_sink.send(message).await,
+            };
+            if sent.is_err() {
+                break;
+            }
+        }
+        
+        self.flush_ws_messages(transaction_id, &mut pending).await;
+        let _ = client_sink.close().await;
+        let _ = upstream_sink.close().await;
+    }
+
+    async fn flush_ws_messages(&self, transaction_id: &str, pending: &mut Vec<WsMessage>) {
+        if pending.is_empty() {
+            return;
+        }
+        let batch = std::mem::take(pending);
+        self.update_transaction(transaction_id, |t| {
+            t.ws_messages.extend(batch);
+            let overflow = t.ws_messages.len().saturating_sub(websocket::MAX_WS_MESSAGES);
+            if overflow > 0 {
+                t.ws_messages.drain(..overflow);
+                if !t.tags.iter().any(|tag| tag == websocket::WS_TRUNCATED_TAG) {
+                    t.tags.push(websocket::WS_TRUNCATED_TAG.to_string());
+                }
+            }
+        })
+        .await;
+    }
+
+    // 命中断点时暂停并通知界面，等待放行、丢弃或超时；未命中返回 None，丢弃返回 Some(false)
+    async fn intercept(
+        &self,
+        phase: InterceptPhase,
+        request: &mut HttpRequest,
+        response: Option<&mut HttpResponse>,
+    ) -> Option<bool> {
+        let (item, receiver) = self.intercepts.write().await.pause(phase, request, response.as_deref())?;
+        let item_id = item.id.clone();
+        info!("Paused at breakpoint {}: {} {}", item.breakpoint_id, request.method, request.url);
+        self.emit(breakpoints::BREAKPOINT_EVENT, item).await;
+        
+        match tokio::time::timeout(breakpoints::INTERCEPT_TIMEOUT, receiver).await {
+            Ok(Ok(InterceptResolution::Resume(item))) => {
+                let item = *item;
+                *request = item.request;
+                if let (Some(response), Some(edited)) = (response, item.response) {
+                    *response = edited;
+                }
+                Some(true)
+            }
+            Ok(Ok(InterceptResolution::Drop)) => Some(false),
+            _ => {
+                warn!("Breakpoint item {} timed out, resuming unchanged", item_id);
+                self.intercepts.write().await.discard(&item_id);
+                Some(true)
+            }
+        }
+    }
+
+    fn dropped_response() -> HttpResponse {
+        HttpResponse {
+            status: 502,
+            headers: HashMap::new(),
+            body: b"Dropped at breakpoint".to_vec(),
+            timestamp: chrono::Utc::now(),
+            version: None,
+        }
+    }
+
+    // 在后台请求 shadow 上游，完成后把对比结果写回事务，不影响客户端响应
+    // partial 表示主响应为流式转发，记录的响应体只是前缀
+    fn compare_shadow(
+        &self,
+        transaction_id: String,
+        config: ShadowConfig,
+        request: HttpRequest,
+        primary: HttpResponse,
+        partial: bool,
+    ) {
+        let server = self.clone();
+        tokio::spawn(async move {
+            let start_time = std::time::Instant::now();
+            let result = server.forward_request(&request).await;
+            let duration = start_time.elapsed();
+            
+            let comparison = match result {
+                Ok((mut response, _)) => {
+                    let divergences = config.compare(&primary, &response, !partial);
+                    server.settings.read().await.apply_to_response(&mut response);
+                    ShadowComparison {
+                        url: request.url,
+                        response: Some(response),
+                        error: None,
+                        duration: Some(duration),
+                        divergences,
+                        partial,
+                    }
+                }
+                Err(e) => {
+                    warn!("Shadow request to {} failed: {}", request.url, e);
+                    ShadowComparison {
+                        url: request.url,
+                        response: None,
+                        error: Some(e.to_string()),
+                        duration: Some(duration),
+                        divergences: Vec::new(),
+                        partial,
+                    }
+                }
+            };
+            
+            server.update_transaction(&transaction_id, |t| {
+                if comparison.diverged() {
+                    t.tags.push(shadow::SHADOW_DIVERGED_TAG.to_string());
+                }
+                t.shadow = Some(comparison);
+            }).await;
+        });
+    }
+
+    // 处理 CONNECT：开启 HTTPS 拦截时解密 TLS，否则原样转发双向字节流
+    async fn handle_connect(&self, req: Request<Incoming>) -> Response<ProxyBody> {
+        let authority = req.uri().authority().map(|a| a.to_string()).unwrap_or_default();
+        let start_time = std::time::Instant::now();
+        let request = HttpRequest {
+            method: "CONNECT".to_string(),
+            url: authority.clone(),
+            headers: Self::collect_headers(req.headers()),
+            body: Vec::new(),
+            timestamp: chrono::Utc::now(),
+            version: None,
+        };
+        
+        info!("Handling request: CONNECT {}", authority);
+        
+        let mut tags = vec!["tunnel".to_string()];
+        if self.is_filtered(&authority).await {
+            tags.push("filtered".to_string());
+        }
+        
+        let intercept = {
+            let settings = self.settings.read().await;
+            let host = authority.rsplit_once(':').map(|(h, _)| h).unwrap_or(&authority);
+            let passthrough = settings.intercept_https && settings.is_tls_passthrough(host);
+            if passthrough {
+                tags.push(PASSTHROUGH_TAG.to_string());
+            }
+            settings.intercept_https && !passthrough
+        };
+        let upstream = if intercept {
+            // 是否真正建立上游连接要等读到客户端首个字节后再决定
+            None
+        } else {
+            match TcpStream::connect(&authority).await {
+                Ok(upstream) => Some(upstream),
+                Err(e) => {
+                    error!("Failed to connect to {}: {}", authority, e);
+                    tags.push(triage::NETWORK_ERROR_TAG.to_string());
+                    let response = Self::proxy_error_response(&e);
+                    self.record_transaction(HttpTransaction::new(request, response.clone(), start_time.elapsed(), tags)).await;
+                    return Self::build_client_response(&response);
+                }
+            }
+        };
+        
+        let server = self.clone();
+        tokio::spawn(async move {
+            let upgraded = match hyper::upgrade::on(req).await {
+                Ok(upgraded) => TokioIo::new(upgraded),
+                Err(e) => {
+                    error!("Failed to upgrade CONNECT to {}: {}", authority, e);
+                    return;
+                }
+            };
+            
+            let result = match upstream {
+                Some(upstream) => server.tunnel(Rewind::new(Vec::new(), upgraded), upstream, request, start_time, tags).await,
+                None => server.intercept_or_tunnel(upgraded, request, start_time, tags).await,
+            };
+            if let Err(e) = result {
+                warn!("Tunnel to {} closed with error: {}", authority, e);
+            }
+        });
+        
+        Response::new(ProxyBody::default())
+    }
+
+    async fn intercept_or_tunnel(
+        &self,
+        mut client: TokioIo<hyper::upgrade::Upgraded>,
+        request: HttpRequest,
+        start_time: std::time::Instant,
+        tags: Vec<String>,
+    ) -> Result<()> {
+        // 预读首字节判断是否为 TLS 握手
+        let mut first = [0u8; 1];
+        let n = client.read(&mut first).await?;
+        let client = Rewind::new(first[..n].to_vec(), client);
+        
+        if n == 1 && mitm::is_tls_client_hello(first[0]) {
+            return self.serve_mitm(client, request.url).await;
+        }
+        
+        let upstream = TcpStream::connect(&request.url).await?;
+        self.tunnel(client, upstream, request, start_time, tags).await
+    }
+
+    // 建立时先记录隧道，关闭后补上字节数和持续时间；出错时同样保留已转发的字节数
+    async fn tunnel<T>(
+        &self,
+        client: Rewind<T>,
+        upstream: TcpStream,
+        request: HttpRequest,
+        start_time: std::time::Instant,
+        tags: Vec<String>,
+    ) -> Result<()>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let response = HttpResponse {
+            status: 200,
+            headers: HashMap::new(),
+            body: Vec::new(),
+            timestamp: chrono::Utc::now(),
+            version: None,
+        };
+        let transaction_id = self
+            .record_transaction(HttpTransaction {
+                tunnel: Some(TunnelStats::default()),
+                ..HttpTransaction::new(request, response, start_time.elapsed(), tags)
+            })
+            .await;
+        
+        let (mut client_read, mut client_write) = tokio::io::split(client);
+        let (mut upstream_read, mut upstream_write) = upstream.into_split();
+        let mut stats = TunnelStats::default();
+        let result = tokio::try_join!(
+            Self::pipe(&mut client_read, &mut upstream_write, &mut stats.bytes_sent),
+            Self::pipe(&mut upstream_read, &mut client_write, &mut stats.bytes_received),
+        );
+        self.update_transaction(&transaction_id, |t| {
+            t.tunnel = Some(stats);
+            t.duration = Some(start_time.elapsed());
+        })
+        .await;
+        result?;
+        Ok(())
+    }
+
+    // 读到 EOF 后关闭对端的写方向，让另一方向自然结束
+    async fn pipe<R, W>(reader: &mut R, writer: &mut W, transferred: &mut u64) -> std::io::Result<()>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::AsyncWriteExt;
+        let mut buf = vec![0u8; 16 * 1024];
+        loop {
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                return writer.shutdown().await;
+            }
+            writer.write_all(&buf[..n]).await?;
+            *transferred += n as u64;
+        }
+    }
+
+    // 用动态签发的证书与客户端完成 TLS 握手，再按普通 HTTP 请求处理
+    async fn serve_mitm<T>(&self, client: Rewind<T>, authority: String) -> Result<()>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        let host = authority.rsplit_once(':').map(|(h, _)| h).unwrap_or(&authority).to_string();
+        let ca = self.certificate_authority().await?;
+        let config = ca.server_config(&host)?;
+        // 上游探测在后台进行，不阻塞与客户端的握手；两侧的握手信息附加到该连接上的每个请求
+        let upstream = self.upstream_tls(&authority).await;
+        let tls = match tokio_rustls::TlsAcceptor::from(config).accept(client).await {
+            Ok(tls) => tls,
+            Err(e) => {
+                if let Some(signal) = pinning::classify(&e) {
+                    self.record_pinning(&host, signal).await;
+                }
+                return Err(e.into());
+            }
+        };
+        let connection = tls.get_ref().1;
+        let info = Arc::new(MitmConnection {
+            authority: authority.clone(),
+            tls: TlsInfo {
+                client: tlsinfo::describe(connection, connection.server_name()),
+                upstream_probe: upstream,
+                forwarded: None,
+            },
+        });
+        let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        
+        let service = service_fn(|mut req: Request<Incoming>| {
+            let server = self.clone();
+            let url = Self::tunneled_url(&req, &authority);
+            req.extensions_mut().insert(info.clone());
+            requests.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            
+            async move {
+                server.process_request(req, url).await
+            }
+        });
+        
+        let result = Self::connection_builder()
+            .serve_connection_with_upgrades(TokioIo::new(tls), service)
+            .await;
+        if requests.load(std::sync::atomic::Ordering::Relaxed) == 0 {
+            self.record_pinning(&host, PinningSignal::ClosedWithoutRequest).await;
+        }
+        result.map_err(|e| anyhow::anyhow!(e))?;
+        
+        Ok(())
+    }
+
+    async fn record_pinning(&self, host: &str, signal: PinningSignal) {
+        let detected = self.pinning.write().await.record(host, signal);
+        if let Some(suspect) = detected {
+            warn!("Client likely pins the certificate for {}", suspect.host);
+            self.emit(pinning::PINNING_DETECTED_EVENT, suspect).await;
+        }
+    }
+
+    // 返回仍有效的探测结果；没有时在后台发起探测，同一目标同时只探测一次
+    async fn upstream_tls(&self, authority: &str) -> Option<UpstreamTls> {
+        {
+            let mut probes = self.tls_probes.write().await;
+            match probes.get(authority) {
+                Some(UpstreamProbe::Done(probed)) if probed.is_fresh() => return Some(probed.clone()),
+                Some(UpstreamProbe::Pending(_)) => return None,
+                _ => {
+                    probes.insert(authority.to_string(), UpstreamProbe::Pending(Vec::new()));
+                }
+            }
+        }
+        let server = self.clone();
+        let authority = authority.to_string();
+        tokio::spawn(async move {
+            let probed = tlsinfo::probe(&authority, &server.dns, UPSTREAM_CONNECT_TIMEOUT).await;
+            // 失败的结果同样缓存，避免每个连接都重试一次
+            let waiting = match server.tls_probes.write().await.insert(authority, UpstreamProbe::Done(probed.clone())) {
+                Some(UpstreamProbe::Pending(waiting)) => waiting,
+                _ => Vec::new(),
+            };
+            for transaction_id in waiting {
+                server.attach_upstream_probe(&transaction_id, probed.clone()).await;
+            }
+        });
+        None
+    }
+
+    // 记录事务时探测若已完成，直接带上结果
+    async fn connection_tls(&self, mitm: Option<&MitmConnection>) -> Option<TlsInfo> {
+        let mitm = mitm?;
+        let mut tls = mitm.tls.clone();
+        if tls.upstream_probe.is_none() {
+            if let Some(UpstreamProbe::Done(probed)) = self.tls_probes.read().await.get(&mitm.authority) {
+                tls.upstream_probe = Some(probed.clone());
+            }
+        }
+        Some(tls)
+    }
+
+    // 记录时探测仍未完成的事务，登记后由探测任务补上结果
+    async fn follow_upstream_probe(&self, transaction_id: &str, mitm: Option<&MitmConnection>) {
+        let Some(mitm) = mitm else {
+            return;
+        };
+        let probed = match self.tls_probes.write().await.get_mut(&mitm.authority) {
+            Some(UpstreamProbe::Pending(waiting)) => {
+                waiting.push(transaction_id.to_string());
+                return;
+            }
+            Some(UpstreamProbe::Done(probed)) => probed.clone(),
+            None => return,
+        };
+        self.attach_upstream_probe(transaction_id, probed).await;
+    }
+
+    async fn attach_upstream_probe(&self, transaction_id: &str, probed: UpstreamTls) {
+        self.update_transaction(transaction_id, |transaction| {
+            if let Some(tls) = transaction.tls.as_mut() {
+                tls.upstream_probe = Some(probed);
+            }
+        })
+        .await;
+    }
+
+    // TLS 隧道内的请求为相对路径，根据 CONNECT 目标补全为 https URL；HTTP/2 请求从 :authority 取主机
+    fn tunneled_url(req: &Request<Incoming>, authority: &str) -> String {
+        let host = req.headers()
+            .get(hyper::header::HOST)
+            .and_then(|h| h.to_str().ok())
+            .or_else(|| req.uri().authority().map(|a| a.as_str()))
+            .map(str::to_string)
+            .unwrap_or_else(|| authority.trim_end_matches(":443").to_string());
+        let path = req.uri().path_and_query().map(|p| p.as_str()).unwrap_or("/");
+        format!("https://{}{}", host, path)
+    }
+
+    async fn certificate_authority(&self) -> Result<Arc<CertificateAuthority>> {
+        if let Some(ca) = self.ca.read().await.as_ref() {
+            return Ok(ca.clone());
+        }
+        
+        let mut ca_slot = self.ca.write().await;
+        if let Some(ca) = ca_slot.as_ref() {
+            return Ok(ca.clone());
+        }
+        let data_dir = self.data_dir.read().await.clone();
+        let ca = Arc::new(match data_dir {
+            Some(dir) => tokio::task::spawn_blocking(move || CertificateAuthority::load_or_generate(&dir)).await??,
+            None => CertificateAuthority::generate()?,
+        });
+        *ca_slot = Some(ca.clone());
+        Ok(ca)
+    }
+
+    // 存储事务并更新统计、触发钩子
+    async fn record_transaction(&self, mut transaction: HttpTransaction) -> String {
+        transaction.decode_response_body();
+        transaction.classify_bodies();
+        transaction.rescore();
+        transaction.detect_pii();
+        
+        // 按内容类型限制存储的响应体大小
+        if let Some(response) = transaction.response.as_mut() {
+            match self.settings.read().await.apply_to_response(response) {
+                BodyCaptureOutcome::Full => {}
+                BodyCaptureOutcome::Truncated => transaction.tags.push("body-truncated".to_string()),
+                BodyCaptureOutcome::Skipped => transaction.tags.push("body-skipped".to_string()),
+            }
+        }
+        
+        let transaction_id = transaction.id.clone();
+        let is_probe = transaction.tags.iter().any(|t| t == active::ACTIVE_PROBE_TAG);
+        
+        // 更新仪表盘实时计数
+        let host = Self::extract_domain_from_url(&transaction.request.url);
+        self.live_stats.write().await.record(&transaction, host.clone());
+        
+        // 累计长期统计，按节流间隔落盘；配额用量基于同一份统计
+        let (snapshot, quota_alerts) = {
+            let mut historical_stats = self.historical_stats.write().await;
+            historical_stats.record(&transaction);
+            let day = transaction.request.timestamp.format("%Y-%m-%d").to_string();
+            let alerts = self.quotas.write().await.check(&historical_stats, &host, &day);
+            (historical_stats.snapshot_if_due(), alerts)
+        };
+        if let Some((path, data)) = snapshot {
+            Self::persist(path, data).await;
+        }
+        for alert in quota_alerts {
+            warn!("Quota {:?} for {}: {:?} at {:.0}%", alert.level, alert.host, alert.metric, alert.usage_percent);
+            self.emit(quotas::QUOTA_ALERT_EVENT, alert).await;
+        }
+        
+        // 与该接口的历史基线比较，基线随之更新
+        let (anomalies, snapshot) = {
+            let mut anomaly_baseline = self.anomaly_baseline.write().await;
+            (anomaly_baseline.observe(&transaction), anomaly_baseline.snapshot_if_due())
+        };
+        if let Some((path, data)) = snapshot {
+            Self::persist(path, data).await;
+        }
+        if !anomalies.is_empty() {
+            let mut recent = self.anomalies.write().await;
+            for anomaly in &anomalies {
+                recent.push_back(anomaly.clone());
+            }
+            while recent.len() > baseline::MAX_RECENT_ANOMALIES {
+                recent.pop_front();
+            }
+        }
+        for anomaly in anomalies {
+            self.emit(baseline::ANOMALY_EVENT, anomaly).await;
+        }
+        
+        // 失败请求进入排查队列
+        self.triage.write().await.track(&transaction);
+        self.cookie_jar.write().await.observe(&transaction);
+        
+        // Store transaction
+        {
+            let mut transactions = self.transactions.write().await;
+            Self::pair_preflight(&mut transactions, &mut transaction);
+            
+            // 配对会修改之前的预检事务，一并同步到存储后端
+            let store = self.store.read().await.clone();
+            if let Some(preflight) = transaction.preflight_id.as_ref()
+                .and_then(|id| transactions.iter().find(|t| &t.id == id))
+            {
+                Self::store_transaction(store.as_ref(), preflight);
+            }
+            Self::store_transaction(store.as_ref(), &transaction);
+            
+            // 触发导出钩子
+            if self.hooks.read().await.has_enabled(&HookTrigger::TransactionCompleted) {
+                let view = self.export_view(vec![transaction.clone()]).await;
+                if let Ok(payload) = serde_json::to_value(&view[0]) {
+                    hooks::dispatch(self.hooks.clone(), HookTrigger::TransactionCompleted, payload);
+                }
+            }
+            
+            // 发往外部收集器的数据同样经过脱敏
+            if self.otel.is_enabled().await {
+                let view = self.export_view(vec![transaction.clone()]).await;
+                self.otel.record(&view[0]).await;
+            }
+            self.buffer.write().await.added(&transaction);
+            transactions.push(transaction);
+            self.enforce_buffer_limits(&mut transactions).await;
+        }
+        self.checkpoint.write().await.mark(&transaction_id);
+        
+        // 被动安全扫描在后台进行，不阻塞转发；主动扫描的探测请求本身带有载荷，不再扫描
+        if !is_probe {
+            self.spawn_passive_scan(transaction_id.clone());
+        }
+        if !self.geoip.read().await.is_empty() {
+            self.spawn_geo_lookup(transaction_id.clone());
+        }
+        
+        transaction_id
+    }
+
+    // 解析上游地址需要 DNS 查询，在后台进行
+    fn spawn_geo_lookup(&self, transaction_id: String) {
+        let proxy = self.clone();
+        tokio::spawn(async move {
+            let Some((url, resolved)) = proxy
+                .transactions
+                .read()
+                .await
+                .iter()
+                .find(|t| t.id == transaction_id)
+                .map(|t| (t.request.url.clone(), t.dns.as_ref().and_then(|d| d.addresses.first().copied())))
+            else {
+                return;
+            };
+            if let Some(geo) = proxy.resolve_geo(&url, resolved).await {
+                proxy.update_transaction(&transaction_id, |t| t.geo = Some(geo)).await;
+            }
+        });
+    }
+
+    // 优先使用转发时记录的解析结果，导入的事务再经同一解析器查询
+    async fn resolve_geo(&self, url: &str, resolved: Option<std::net::IpAddr>) -> Option<GeoInfo> {
+        let ip = match resolved {
+            Some(ip) => ip,
+            None => match url::Url::parse(url).ok()?.host()? {
+                url::Host::Ipv4(ip) => std::net::IpAddr::V4(ip),
+                url::Host::Ipv6(ip) => std::net::IpAddr::V6(ip),
+                url::Host::Domain(host) => {
+                    let resolution = self.dns.lookup(host).await;
+                    if let Some(e) = &resolution.error {
+                        warn!("Failed to resolve {} for GeoIP lookup: {}", host, e);
+                    }
+                    resolution.addresses.first().copied()?
+                }
+            },
+        };
+        Some(self.geoip.read().await.lookup(ip))
+    }
+
+    // 加载 MaxMind 格式（.mmdb）数据库，并为尚无归属信息的事务补充查询
+    pub async fn load_geoip_database(&self, path: &str) -> Result<GeoIpDatabaseInfo> {
+        let path = PathBuf::from(path);
+        let reader = tokio::task::spawn_blocking(move || MmdbReader::open(&path)).await??;
+        let info = reader.info().clone();
+        self.geoip.write().await.add(reader);
+        let pending: Vec<String> = self
+            .transactions
+            .read()
+            .await
+            .iter()
+            .filter(|t| t.geo.as_ref().is_none_or(|g| g.country_code.is_none() && g.asn.is_none()))
+            .map(|t| t.id.clone())
+            .collect();
+        for id in pending {
+            self.spawn_geo_lookup(id);
+        }
+        Ok(info)
+    }
+
+    pub async fn get_geoip_databases(&self) -> Vec<GeoIpDatabaseInfo> {
+        self.geoip.read().await.databases()
+    }
+
+    pub async fn clear_geoip_databases(&self) {
+        self.geoip.write().await.clear();
+    }
+
+    pub async fn get_dns_settings(&self) -> DnsSettings {
+        self.dns.settings().await
+    }
+
+    pub async fn set_dns_settings(&self, settings: DnsSettings) -> Result<DnsSettings> {
+        self.dns.set_settings(settings).await
+    }
+
+    pub async fn get_dns_cache(&self) -> Vec<DnsCacheEntry> {
+        self.dns.cache_entries().await
+    }
+
+    pub async fn clear_dns_cache(&self) {
+        self.dns.clear_cache().await;
+    }
+
+    // 以 OTLP 链路导出转发的请求，各转发阶段为子 span
+    pub async fn get_otel_settings(&self) -> OtelSettings {
+        self.otel.settings().await
+    }
+
+    pub async fn set_otel_settings(&self, settings: OtelSettings) -> Result<OtelSettings> {
+        self.otel.set_settings(settings).await
+    }
+
+    pub async fn get_otel_status(&self) -> OtelStatus {
+        self.otel.status().await
+    }
+
+    pub async fn flush_otel_export(&self) -> OtelStatus {
+        self.otel.flush().await;
+        self.otel.status().await
+    }
+
+    fn spawn_passive_scan(&self, transaction_id: String) {
+        let proxy = self.clone();
+        tokio::spawn(async move {
+            match proxy.scan_transaction(&transaction_id).await {
+                Ok(findings) => {
+                    for finding in findings {
+                        proxy.emit(scanner::SECURITY_FINDING_EVENT, finding).await;
+                    }
+                }
+                Err(e) => warn!("Passive scan of {} failed: {}", transaction_id, e),
+            }
+        });
+    }
+
+    // 运行全部被动检查，结果替换该事务之前的扫描结果
+    pub async fn scan_transaction(&self, transaction_id: &str) -> Result<Vec<Finding>> {
+        let (transaction, preflight) = {
+            let transactions = self.transactions.read().await;
+            let transaction = transactions
+                .iter()
+                .find(|t| t.id == transaction_id)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+            let preflight = transaction
+                .preflight_id
+                .as_ref()
+                .and_then(|id| transactions.iter().find(|t| &t.id == id))
+                .cloned();
+            (transaction, preflight)
+        };
+        let analyzer = self.security_analyzer.clone();
+        let findings = tokio::task::spawn_blocking(move || analyzer.scan(&transaction, preflight.as_ref())).await?;
+        self.security_findings.write().await.replace(transaction_id, &findings);
+        self.set_security_findings(transaction_id, findings.iter().map(|f| f.description.clone()).collect()).await;
+        Ok(findings)
+    }
+
+    pub async fn get_security_findings(
+        &self,
+        min_severity: Option<SecurityRisk>,
+        transaction_id: Option<&str>,
+        limit: usize,
+    ) -> Vec<Finding> {
+        self.security_findings.read().await.list(min_severity, transaction_id, limit)
+    }
+
+    // 导出累计的扫描结果，按发现时间先后排列
+    pub async fn export_findings(&self, format: FindingsFormat) -> String {
+        let mut findings = self.security_findings.read().await.list(None, None, scanner::MAX_FINDINGS);
+        findings.reverse();
+        let selected: Vec<HttpTransaction> = self
+            .transactions
+            .read()
+            .await
+            .iter()
+            .filter(|t| findings.iter().any(|f| f.transaction_id == t.id))
+            .cloned()
+            .collect();
+        // 证据中的参数值与导出的 URL 一样按脱敏策略和个人信息遮盖设置处理
+        let params: HashMap<String, Vec<Parameter>> = selected.iter().map(|t| (t.id.clone(), params::extract(t))).collect();
+        let urls: HashMap<String, String> =
+            self.export_view(selected).await.into_iter().map(|t| (t.id, t.request.url)).collect();
+        let policy = self.redaction_policy.read().await.clone();
+        let masking = self.pii_masking.read().await.clone();
+        for finding in &mut findings {
+            let Some(evidence) = finding.evidence.as_mut() else {
+                continue;
+            };
+            if let Some(params) = params.get(&finding.transaction_id) {
+                *evidence = policy.redact_evidence(evidence, params);
+            }
+            if masking.applies_to(MaskTarget::Export) {
+                *evidence = masking.mask_text(evidence).into_owned();
+            }
+        }
+        secreport::generate(format, &findings, &urls)
+    }
+
+    pub async fn get_active_scan_settings(&self) -> ActiveScanSettings {
+        self.active_scan.read().await.clone()
+    }
+
+    pub async fn set_active_scan_settings(&self, settings: ActiveScanSettings) -> ActiveScanSettings {
+        let settings = settings.normalized();
+        *self.active_scan.write().await = settings.clone();
+        settings
+    }
+
+    // 主动扫描：按参数逐个注入载荷重放请求，与原始响应对比确认问题
+    pub async fn active_scan_transaction(&self, transaction_id: &str) -> Result<ActiveScanReport> {
+        let transaction = self.transactions.read().await
+            .iter()
+            .find(|t| t.id == transaction_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+        let settings = self.active_scan.read().await.clone();
+        let host = settings.authorize(&transaction.request.url)?;
+        let probes = active::build_probes(&transaction, settings.max_probes_per_request);
+        info!("Active scan of {}: {} probes against {}", transaction_id, probes.len(), host);
+
+        let mut report = ActiveScanReport {
+            transaction_id: transaction_id.to_string(),
+            host: host.clone(),
+            probes: Vec::new(),
+            findings: Vec::new(),
+        };
+        // 已确认的参数与问题类型，不再发送同类载荷
+        let mut confirmed = HashSet::new();
+        for probe in probes {
+            if confirmed.contains(&(probe.location, probe.parameter.clone(), probe.kind)) {
+                continue;
+            }
+            // 扫描过程中关闭开关或移出范围时立即停止
+            let current = self.active_scan.read().await.clone();
+            if current.authorize(&probe.request.url).is_err() {
+                warn!("Active scan of {} stopped: scanning disabled or host out of scope", transaction_id);
+                break;
+            }
+            let wait = self.probe_limiter.write().await.reserve(&host, &current);
+            tokio::time::sleep(wait).await;
+
+            // 单个探测失败时记录下来继续扫描，已得到的结果照常保存
+            let baseline = transaction.response.as_ref();
+            let recorded = match self
+                .send_and_record(probe.request.clone(), active::ACTIVE_PROBE_TAG, Some(transaction_id.to_string()))
+                .await
+            {
+                Ok(recorded) => recorded,
+                Err(e) => {
+                    warn!("Active probe for {} failed: {}", transaction_id, e);
+                    report.probes.push(ProbeOutcome::failed(&probe, None, baseline, e.to_string()));
+                    continue;
+                }
+            };
+            if recorded.tags.iter().any(|tag| tag == triage::NETWORK_ERROR_TAG) {
+                let error = recorded.response.as_ref().map(|r| String::from_utf8_lossy(&r.body).into_owned()).unwrap_or_default();
+                report.probes.push(ProbeOutcome::failed(&probe, Some(&recorded.id), baseline, error));
+                continue;
+            }
+            let (outcome, finding) =
+                active::evaluate(&probe, &recorded.id, baseline, recorded.response.as_ref());
+            report.probes.push(outcome);
+            if let Some(mut finding) = finding {
+                confirmed.insert((probe.location, probe.parameter.clone(), probe.kind));
+                finding.transaction_id = transaction_id.to_string();
+                finding.host = host.clone();
+                self.emit(scanner::SECURITY_FINDING_EVENT, finding.clone()).await;
+                report.findings.push(finding);
+            }
+        }
+
+        self.security_findings.write().await.replace_active(transaction_id, &report.findings);
+        Ok(report)
+    }
+
+    async fn is_filtered(&self, url: &str) -> bool {
+        let filters = self.filters.read().await;
+        if filters.is_empty() {
+            return false;
+        }
+        
+        let should_filter = filters.iter().any(|filter| {
+            // 提取域名进行模糊匹配
+            let domain = Self::extract_domain_from_url(url);
+            domain.to_lowercase().contains(&filter.to_lowercase()) || 
+            url.to_lowercase().contains(&filter.to_lowercase())
+        });
+        
+        if should_filter {
+            warn!("Request filtered by '{}': {}", filters.join(", "), url);
+        }
+        should_filter
+    }
+
+    // 代理请求通常是绝对 URI，直连请求则根据 Host 头补全
+    fn absolute_url(req: &Request<Incoming>) -> String {
+        let uri = req.uri();
+        if uri.scheme().is_some() {
+            return uri.to_string();
+        }
+        let host = req.headers()
+            .get(hyper::header::HOST)
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or("localhost");
+        let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+        format!("http://{}{}", host, path)
+    }
+
+    fn collect_headers(header_map: &hyper::HeaderMap) -> HashMap<String, String> {
+        Self::merge_headers(header_map.iter().map(|(k, v)| (k.as_str(), v.as_bytes())))
+    }
+
+    // 同名头合并存储：Set-Cookie 以换行分隔，其余以逗号分隔
+    pub fn merge_headers<'a>(pairs: impl Iterator<Item = (&'a str, &'a [u8])>) -> HashMap<String, String> {
+        let mut headers: HashMap<String, String> = HashMap::new();
+        for (name, value) in pairs {
+            let value = String::from_utf8_lossy(value).to_string();
+            let separator = if name.eq_ignore_ascii_case("set-cookie") { "\n" } else { ", " };
+            headers
+                .entry(name.to_string())
+                .and_modify(|existing| {
+                    existing.push_str(separator);
+                    existing.push_str(&value);
+                })
+                .or_insert(value);
+        }
+        headers
+    }
+
+    fn proxy_error_response(error: &impl std::fmt::Display) -> HttpResponse {
+        HttpResponse {
+            status: 502,
+            headers: HashMap::new(),
+            body: format!("Proxy error: {}", error).into_bytes(),
+            timestamp: chrono::Utc::now(),
+            version: None,
+        }
+    }
+
+    // Proxy-Authenticate 属于逐跳头，不能经 build_client_response 生成
+    fn proxy_auth_required() -> Response<ProxyBody> {
+        Response::builder()
+            .status(StatusCode::PROXY_AUTHENTICATION_REQUIRED)
+            .header(hyper::header::PROXY_AUTHENTICATE, auth::challenge())
+            .body(full_body(Bytes::from_static(b"Proxy authentication required")))
+            .unwrap_or_else(|_| Response::new(ProxyBody::default()))
+    }
+
+    pub fn is_hop_by_hop(name: &str) -> bool {
+        HOP_BY_HOP_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name))
+    }
+
+    fn build_client_response(response: &HttpResponse) -> Response<ProxyBody> {
+        // 响应体已完整缓冲，长度由 hyper 重新计算
+        Self::client_response_builder(response, false)
+            .body(full_body(response.body.clone()))
+            .unwrap_or_else(|_| Response::new(ProxyBody::default()))
+    }
+
+    // 先发出已读取的前缀，再逐块转发上游剩余的响应体；上游为 chunked 时由 hyper 重新分块
+    fn build_streaming_response(response: &HttpResponse, rest: UpstreamBody) -> Response<ProxyBody> {
+        let prefix = futures_util::stream::once(futures_util::future::ready(Ok(Frame::data(Bytes::from(
+            response.body.clone(),
+        )))));
+        let rest = futures_util::stream::unfold(Some(rest), |upstream| async move {
+            let mut upstream = upstream?;
+            match upstream.frame().await? {
+                Ok(frame) => Some((Ok(frame), Some(upstream))),
+                // 上游中断时结束响应体，客户端会看到不完整的传输
+                Err(e) => Some((Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>), None)),
+            }
+        });
+        Self::client_response_builder(response, true)
+            .body(StreamBody::new(prefix.chain(rest)).boxed_unsync())
+            .unwrap_or_else(|_| Response::new(ProxyBody::default()))
+    }
+
+    fn client_response_builder(response: &HttpResponse, keep_length: bool) -> hyper::http::response::Builder {
+        let mut response_builder = Response::builder()
+            .status(StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK));
+            
+        for (key, value) in &response.headers {
+            if Self::is_hop_by_hop(key) || (!keep_length && key.eq_ignore_ascii_case("content-length")) {
+                continue;
+            }
+            if key.eq_ignore_ascii_case("set-cookie") {
+                for cookie in value.split('\n') {
+                    response_builder = response_builder.header(key, cookie);
+                }
+            } else {
+                response_builder = response_builder.header(key, value);
+            }
+        }
+        response_builder
+    }
+
+    // 将实际请求与最近一次尚未配对的 CORS 预检关联
+    fn pair_preflight(transactions: &mut [HttpTransaction], transaction: &mut HttpTransaction) {
+        if transaction.is_preflight() {
+            return;
+        }
+        let Some(origin) = find_header(&transaction.request.headers, "origin") else {
+            return;
+        };
+
+        let preflight = transactions.iter_mut().rev().find(|t| {
+            t.is_preflight()
+                && t.preflight_for.is_none()
+                && t.request.url == transaction.request.url
+                && find_header(&t.request.headers, "origin") == Some(origin)
+                && find_header(&t.request.headers, "access-control-request-method")
+                    .map(|m| m.eq_ignore_ascii_case(&transaction.request.method))
+                    .unwrap_or(false)
+                && (transaction.request.timestamp - t.request.timestamp).num_seconds()
+                    <= PREFLIGHT_PAIRING_WINDOW_SECS
+        });
+
+        if let Some(preflight) = preflight {
+            preflight.preflight_for = Some(transaction.id.clone());
+            transaction.preflight_id = Some(preflight.id.clone());
+        }
+    }
+
+    fn store_transaction(store: &dyn TransactionStore, transaction: &HttpTransaction) {
+        if let Err(e) = store.upsert(transaction) {
+            warn!("Failed to store transaction {}: {}", transaction.id, e);
+        }
+    }
+
+    // 修改指定事务并同步到存储后端，返回修改后的副本
+    async fn update_transaction<F>(&self, transaction_id: &str, update: F) -> Option<HttpTransaction>
+    where
+        F: FnOnce(&mut HttpTransaction),
+    {
+        let updated = {
+            let mut transactions = self.transactions.write().await;
+            let transaction = transactions.iter_mut().find(|t| t.id == transaction_id)?;
+            let before = buffer::body_bytes(transaction);
+            update(transaction);
+            self.buffer.write().await.resized(before, buffer::body_bytes(transaction));
+            transaction.clone()
+        };
+        Self::store_transaction(self.store.read().await.as_ref(), &updated);
+        self.checkpoint.write().await.mark(transaction_id);
+        Some(updated)
+    }
+
+    // 应用未注入句柄时（如命令行自检）静默跳过
+    async fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Some(app) = self.app_handle.read().await.as_ref() {
+            if let Err(e) = app.emit(event, payload) {
+                warn!("Failed to emit {}: {}", event, e);
+            }
+        }
+    }
+
+    async fn persist(path: PathBuf, data: Vec<u8>) {
+        if let Err(e) = tokio::fs::write(&path, data).await {
+            warn!("Failed to write {}: {}", path.display(), e);
+        }
+    }
+
+    fn extract_domain_from_url(url: &str) -> String {
+        // 处理 CONNECT 请求格式 (CONNECT www.google.com:443)
+        if url.contains(":") && !url.starts_with("http") {
+            return url.split(":").next().unwrap_or(url).to_string();
+        }
+        
+        // 处理普通 URL 格式
+        if let Ok(parsed_url) = url::Url::parse(url) {
+            if let Some(host) = parsed_url.host_str() {
+                return host.to_string();
+            }
+        }
+        
+        // 如果解析失败，尝试简单的字符串提取
+        if url.starts_with("http://") || url.starts_with("https://") {
+            if let Some(start) = url.find("://") {
+                let after_protocol = &url[start + 3..];
+                if let Some(end) = after_protocol.find('/') {
+                    return after_protocol[..end].to_string();
+                } else if let Some(end) = after_protocol.find(':') {
+                    return after_protocol[..end].to_string();
+                } else {
+                    return after_protocol.to_string();
+                }
+            }
+        }
+        
+        url.to_string()
+    }
+
+    fn self_test_echo(request: &HttpRequest) -> HttpResponse {
+        let mut headers = HashMap::new();
+        headers.insert("Content-Type".to_string(), "application/json".to_string());
+
+        let body = json!({
+            "method": request.method,
+            "url": request.url,
+            "headers": request.headers,
+            "body": String::from_utf8_lossy(&request.body),
+        }).to_string();
+
+        HttpResponse {
+            status: 200,
             headers,
             body: body.into_bytes(),
             timestamp: chrono::Utc::now(),
+            version: None,
+        }
+    }
+
+    // 网络节流：按往返延迟推迟发出请求，请求体与响应体（含流式转发的剩余部分）逐块按带宽放行
+    async fn forward_throttled(
+        &self,
+        host: &str,
+        request: &HttpRequest,
+        buffer_limit: &(dyn Fn(Option<&str>) -> usize + Sync),
+    ) -> (Result<Forwarded>, Option<&'static str>) {
+        let (profile_id, latency) = {
+            let throttle = self.throttle.read().await;
+            let Some(profile_id) = throttle.profile_id_for(host) else {
+                return (self.forward_streaming(request, buffer_limit, None).await, None);
+            };
+            (profile_id, throttle.latency_for(host))
+        };
+        tokio::time::sleep(latency).await;
+        (self.forward_streaming(request, buffer_limit, Some(host)).await, Some(profile_id))
+    }
+
+    async fn forward_request(&self, request: &HttpRequest) -> Result<(HttpResponse, Timings)> {
+        let forwarded = self.forward_streaming(request, &|_| usize::MAX, None).await?;
+        Ok((forwarded.response, forwarded.timings))
+    }
+
+    // 客户端的 chunked 请求体已由 hyper 解除分块，按完整长度重新发出；
+    // 响应体逐块读取，超过 buffer_limit 按响应类型给出的字节数时返回已读取的前缀和尚未读完的上游响应体；
+    // 指定 throttle_host 时两个方向都按该主机的节流配置限速
+    async fn forward_streaming(
+        &self,
+        request: &HttpRequest,
+        buffer_limit: &(dyn Fn(Option<&str>) -> usize + Sync),
+        throttle_host: Option<&str>,
+    ) -> Result<Forwarded> {
+        let mut upstream_request = Request::builder()
+            .method(Method::from_bytes(request.method.as_bytes())?)
+            .uri(request.url.as_str());
+        for (key, value) in &request.headers {
+            // Host 和 Content-Length 由客户端库根据 URL 和请求体生成
+            if Self::is_hop_by_hop(key)
+                || key.eq_ignore_ascii_case("host")
+                || key.eq_ignore_ascii_case("content-length")
+            {
+                continue;
+            }
+            upstream_request = upstream_request.header(key.as_str(), value.as_str());
+        }
+        let body = Full::new(Bytes::from(request.body.clone()));
+        let body = match throttle_host {
+            Some(host) => PacedBody::new(body, self.throttle.clone(), host, Direction::Upload).boxed_unsync(),
+            None => body.boxed_unsync(),
+        };
+        let upstream_request = upstream_request.body(body)?;
+        
+        let (upstream_response, clock) = self.client.send(upstream_request).await?;
+        let tls = upstream_response.extensions().get::<ForwardedTls>().cloned();
+        let status = upstream_response.status().as_u16();
+        let version = format!("{:?}", upstream_response.version());
+        let headers = Self::merge_headers(
+            upstream_response.headers().iter().map(|(k, v)| (k.as_str(), v.as_bytes()))
+        );
+        let stream_threshold = buffer_limit(find_header(&headers, "content-type"));
+        
+        let mut upstream_body = match throttle_host {
+            Some(host) => {
+                PacedBody::new(upstream_response.into_body(), self.throttle.clone(), host, Direction::Download).boxed_unsync()
+            }
+            None => upstream_response.into_body().boxed_unsync(),
+        };
+        let mut body = Vec::new();
+        let mut rest = None;
+        while let Some(frame) = upstream_body.frame().await {
+            if let Ok(chunk) = frame?.into_data() {
+                body.extend_from_slice(&chunk);
+                if body.len() > stream_threshold {
+                    rest = Some(upstream_body);
+                    break;
+                }
+            }
+        }
+        
+        let response = HttpResponse {
+            status,
+            headers,
+            body,
+            timestamp: chrono::Utc::now(),
+            version: Some(version),
+        };
+        Ok(Forwarded { response, rest, timings: clock.finish(), tls })
+    }
+
+    // 应用数据目录，由 Tauri setup 阶段注入
+    pub async fn set_data_dir(&self, dir: PathBuf) {
+        if let Err(e) = tokio::fs::create_dir_all(&dir).await {
+            warn!("Failed to create data dir {}: {}", dir.display(), e);
+        }
+        *self.historical_stats.write().await = HistoricalStats::load(dir.join(history::HISTORY_FILE_NAME));
+        *self.anomaly_baseline.write().await = AnomalyBaseline::load(dir.join(baseline::BASELINE_FILE_NAME));
+        // 统计待恢复的事务需要读取整个文件
+        let session_dir = dir.clone();
+        if let Ok(Some(info)) = tokio::task::spawn_blocking(move || recovery::begin_session(&session_dir)).await {
+            info!("Found {} transactions from an unclean shutdown", info.transactions);
+        }
+        *self.checkpoint.write().await = Checkpoint::new(&dir);
+        *self.ai_cache.write().await = AiCache::load(dir.join(aicache::AI_CACHE_FILE_NAME));
+        *self.conversations.write().await = ConversationStore::load(dir.join(chat::CONVERSATIONS_DIR));
+        let geoip_dir = dir.join(geoip::GEOIP_DIR);
+        let loaded = tokio::task::spawn_blocking(move || {
+            let mut resolver = GeoIpResolver::default();
+            resolver.load_dir(&geoip_dir);
+            resolver
+        })
+        .await;
+        if let Ok(resolver) = loaded {
+            *self.geoip.write().await = resolver;
+        }
+        *self.data_dir.write().await = Some(dir.clone());
+        self.spawn_checkpoint_task();
+        self.spawn_config_watcher(&dir);
+    }
+
+    // 配置文件热加载：监听数据目录而不是文件本身，编辑器常以重命名的方式替换文件；
+    // 事件防抖后按内容判断是否变化，启动时先加载已有文件
+    fn spawn_config_watcher(&self, dir: &Path) {
+        let path = dir.join(config::CONFIG_FILE_NAME);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let watched = path.clone();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            // 读取配置本身也会产生访问事件
+            if let Ok(event) = event {
+                if !matches!(event.kind, EventKind::Access(_)) && event.paths.iter().any(|p| p.file_name() == watched.file_name()) {
+                    let _ = tx.send(());
+                }
+            }
         })
+        .and_then(|mut watcher| watcher.watch(dir, RecursiveMode::NonRecursive).map(|_| watcher));
+        // 无法监听时只在启动时加载一次
+        let watcher = watcher.map_err(|e| warn!("Failed to watch {}: {}", dir.display(), e)).ok();
+        let proxy = self.clone();
+        tokio::spawn(async move {
+            let _watcher = watcher;
+            let mut last = None;
+            loop {
+                let current = config::fingerprint(&path);
+                if current.is_some() && current != last {
+                    last = current;
+                    if let Err(e) = proxy.reload_config().await {
+                        warn!("Failed to reload config: {}", e);
+                    }
+                }
+                if rx.recv().await.is_none() {
+                    return;
+                }
+                loop {
+                    match tokio::time::timeout(config::CONFIG_DEBOUNCE, rx.recv()).await {
+                        Ok(Some(())) => continue,
+                        Ok(None) => return,
+                        Err(_) => break,
+                    }
+                }
+            }
+        });
+    }
+
+    async fn config_path(&self) -> Option<PathBuf> {
+        self.data_dir.read().await.as_ref().map(|dir| dir.join(config::CONFIG_FILE_NAME))
+    }
+
+    pub async fn reload_config(&self) -> Result<ConfigStatus> {
+        let path = self.config_path().await.ok_or_else(|| anyhow::anyhow!("Data directory is not set"))?;
+        let read_path = path.clone();
+        let result = match tokio::task::spawn_blocking(move || config::read(&read_path)).await {
+            Ok(Ok(config)) => self.apply_config(config).await,
+            Ok(Err(e)) => Err(e),
+            Err(e) => Err(e.into()),
+        };
+        
+        let mut status = self.config_status.write().await;
+        let previous = status.take();
+        let next = match &result {
+            Ok(sections) => ConfigStatus {
+                path: path.display().to_string(),
+                loaded_at: Some(chrono::Utc::now()),
+                sections: sections.clone(),
+                error: None,
+            },
+            // 加载失败时沿用上一次成功的配置，只记录错误
+            Err(e) => ConfigStatus {
+                path: path.display().to_string(),
+                loaded_at: previous.as_ref().and_then(|s| s.loaded_at),
+                sections: previous.map(|s| s.sections).unwrap_or_default(),
+                error: Some(e.to_string()),
+            },
+        };
+        *status = Some(next.clone());
+        drop(status);
+        
+        match result {
+            Ok(_) => {
+                info!("Loaded config from {}: {}", next.path, next.sections.join(", "));
+                self.emit(config::CONFIG_RELOADED_EVENT, next.clone()).await;
+                Ok(next)
+            }
+            Err(e) => {
+                self.emit(config::CONFIG_ERROR_EVENT, next).await;
+                Err(e)
+            }
+        }
+    }
+
+    // 先校验全部部分再应用，避免只应用了一部分配置；切换存储可能失败，放在最前面
+    async fn apply_config(&self, config: AppConfig) -> Result<Vec<String>> {
+        let sections = config.sections().into_iter().map(str::to_string).collect();
+        let capture = config.capture.map(CaptureSettings::validated).transpose()?;
+        let ai = config.ai.map(AiProviderConfig::validated).transpose()?;
+        let bypass = config.bypass.map(bypass::normalize).transpose()?;
+        let dns = config.dns.map(DnsSettings::validated).transpose()?;
+        let otel = config.otel.map(OtelSettings::validated).transpose()?;
+        if let Some(capture) = capture {
+            self.set_settings(capture).await?;
+        }
+        if let Some(rules) = config.rules {
+            *self.rules.write().await = rules;
+        }
+        if let Some(filters) = config.filters {
+            *self.filters.write().await = filters;
+        }
+        if let Some(bypass) = bypass {
+            self.set_proxy_bypass(bypass).await?;
+        }
+        if let Some(throttle) = config.throttle {
+            self.throttle.write().await.set_settings(throttle);
+        }
+        if let Some(redaction) = config.redaction {
+            self.set_redaction_policy(redaction).await;
+        }
+        if let Some(pii_masking) = config.pii_masking {
+            self.set_pii_masking(pii_masking).await;
+        }
+        if let Some(active_scan) = config.active_scan {
+            self.set_active_scan_settings(active_scan).await;
+        }
+        if let Some(dns) = dns {
+            self.set_dns_settings(dns).await?;
+        }
+        if let Some(otel) = otel {
+            self.set_otel_settings(otel).await?;
+        }
+        if let Some(ai) = ai {
+            *self.ai_config.write().await = Some(ai);
+            self.rebuild_ai_analyzer().await;
+        }
+        Ok(sections)
+    }
+
+    pub async fn get_config_status(&self) -> Option<ConfigStatus> {
+        self.config_status.read().await.clone()
+    }
+
+    pub async fn get_ai_provider_config(&self) -> Option<AiProviderConfig> {
+        self.ai_config.read().await.clone()
+    }
+
+    pub async fn ai_analyzer(&self) -> AIAnalyzer {
+        self.ai_analyzer.read().await.clone()
+    }
+
+    pub async fn get_ai_config(&self) -> Option<AiConfigView> {
+        let config = self.ai_config.read().await.clone()?;
+        let source = *self.ai_key_source.read().await;
+        Some(AiConfigView {
+            provider: config.provider,
+            model: config.model,
+            base_url: config.base_url,
+            max_tokens: config.max_tokens,
+            has_api_key: source.is_some(),
+            api_key_source: source,
+        })
+    }
+
+    // api_key 为空时保留已保存的密钥，为空字符串时从钥匙串删除
+    pub async fn set_ai_config(
+        &self,
+        provider: AiProvider,
+        model: String,
+        api_key: Option<String>,
+        base_url: Option<String>,
+    ) -> Result<AiConfigView> {
+        let api_key = api_key.map(|key| key.trim().to_string());
+        let previous = self.ai_config.read().await.clone().filter(|c| c.provider == provider);
+        // 界面中填写了密钥时不再使用配置文件指定的环境变量
+        let api_key_env = match api_key {
+            Some(_) => None,
+            None => previous.as_ref().and_then(|c| c.api_key_env.clone()),
+        };
+        let config = AiProviderConfig {
+            provider,
+            model,
+            base_url,
+            api_key_env,
+            max_tokens: previous.and_then(|c| c.max_tokens),
+        }
+        .validated()?;
+        if let Some(key) = api_key {
+            tokio::task::spawn_blocking(move || match key.is_empty() {
+                true => keychain::delete_api_key(provider),
+                false => keychain::store_api_key(provider, &key),
+            })
+            .await??;
+        }
+        // 有数据目录时保存到配置文件，重启后仍然有效
+        if let Some(path) = self.config_path().await {
+            let saved = config.clone();
+            tokio::task::spawn_blocking(move || config::write_ai(&path, &saved)).await??;
+        }
+        *self.ai_config.write().await = Some(config);
+        self.rebuild_ai_analyzer().await;
+        self.get_ai_config().await.ok_or_else(|| anyhow::anyhow!("AI config was not applied"))
+    }
+
+    // 未配置服务商时不读取任何密钥，避免抓到的流量在用户不知情时被发往外部服务
+    async fn rebuild_ai_analyzer(&self) {
+        let Some(config) = self.ai_config.read().await.clone() else {
+            return;
+        };
+        let lookup = config.clone();
+        let resolved = tokio::task::spawn_blocking(move || keychain::resolve_api_key(&lookup)).await.ok().flatten();
+        let source = resolved.as_ref().map(|(_, source)| *source);
+        *self.ai_analyzer.write().await = AIAnalyzer::from_config(&config, resolved.map(|(key, _)| key));
+        *self.ai_key_source.write().await = source;
+    }
+
+    // gRPC 消息解码：任一文件无法解析时，之前已注册的文件保持有效
+    pub async fn register_proto_files(&self, paths: Vec<String>) -> Result<ProtoRegistrySummary> {
+        let mut registry = self.proto_registry.write().await;
+        for path in &paths {
+            registry.register_file(std::path::Path::new(path))?;
+        }
+        Ok(registry.summary())
+    }
+
+    pub async fn clear_proto_files(&self) {
+        *self.proto_registry.write().await = ProtoRegistry::default();
+    }
+
+    pub async fn get_proto_registry(&self) -> ProtoRegistrySummary {
+        self.proto_registry.read().await.summary()
+    }
+
+    pub async fn get_grpc_messages(&self, transaction_id: &str, part: BodyPart) -> Result<GrpcBody> {
+        let transactions = self.transactions.read().await;
+        let transaction = transactions
+            .iter()
+            .find(|t| t.id == transaction_id)
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+        let registry = self.proto_registry.read().await;
+        grpc::decode(&registry, transaction, part)
+    }
+
+    // 只在持锁期间取出令牌，读取字典和爆破放到阻塞线程池中，不占用事务锁
+    pub async fn find_jwts(&self, transaction_id: &str, wordlist_path: Option<String>) -> Result<Vec<FoundJwt>> {
+        let mut found = {
+            let transactions = self.transactions.read().await;
+            let transaction = transactions
+                .iter()
+                .find(|t| t.id == transaction_id)
+                .ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+            jwt::find_in_transaction(transaction)
+        };
+        tokio::task::spawn_blocking(move || {
+            let wordlist = jwt::load_wordlist(wordlist_path.as_deref())?;
+            for jwt in &mut found {
+                jwt::crack_secret(&jwt.token, &mut jwt.info, wordlist.iter().map(String::as_str));
+            }
+            Ok(found)
+        })
+        .await?
+    }
+
+    pub async fn get_cookies(&self, domain: Option<&str>) -> Vec<Cookie> {
+        self.cookie_jar.read().await.cookies(domain)
+    }
+
+    pub async fn clear_cookies(&self, domain: Option<&str>) -> usize {
+        self.cookie_jar.write().await.clear(domain)
+    }
+
+    pub async fn get_tls_info(&self, transaction_id: &str) -> Result<TlsInfo> {
+        let transactions = self.transactions.read().await;
+        let transaction = transactions
+            .iter()
+            .find(|t| t.id == transaction_id)
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+        transaction
+            .tls
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Transaction was not captured through HTTPS interception"))
+    }
+
+    pub async fn get_security_header_report(&self, host: &str) -> SecurityHeaderReport {
+        secheaders::audit(host, &self.transactions.read().await)
+    }
+
+    // 交给 AI 分析的副本：gRPC 消息体替换为解码后的 JSON，其他事务原样返回
+    pub async fn analysis_view(&self, transaction: &HttpTransaction) -> HttpTransaction {
+        let mut view = transaction.clone();
+        if !grpc::is_grpc_transaction(transaction) {
+            return view;
+        }
+        let registry = self.proto_registry.read().await;
+        if let Some(json) = grpc::as_json_text(&registry, transaction, BodyPart::Request) {
+            view.request.body = json;
+            view.request_body_kind = Some(BodyKind::Json);
+        }
+        if let Some(json) = grpc::as_json_text(&registry, transaction, BodyPart::Response) {
+            if let Some(response) = view.response.as_mut() {
+                response.body = json;
+                view.response_body_kind = Some(BodyKind::Json);
+            }
+        }
+        view
+    }
+
+    // 发给模型的副本：在 analysis_view 的基础上按脱敏策略替换凭据，再按设置遮盖个人信息
+    async fn ai_view(&self, transaction: &HttpTransaction) -> HttpTransaction {
+        let view = self.redaction_policy.read().await.apply(&self.analysis_view(transaction).await);
+        self.pii_masking.read().await.view(view, MaskTarget::AiPrompt)
+    }
+
+    // 定期把新增或修改的事务写入检查点，异常退出后可从中恢复
+    fn spawn_checkpoint_task(&self) {
+        let proxy = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(recovery::CHECKPOINT_INTERVAL);
+            loop {
+                interval.tick().await;
+                proxy.write_checkpoint().await;
+            }
+        });
+    }
+
+    async fn write_checkpoint(&self) {
+        let Some(write) = self.checkpoint.write().await.take() else {
+            return;
+        };
+        let (path, batch, append) = {
+            let transactions = self.transactions.read().await;
+            match write {
+                CheckpointWrite::Append(path, ids) => {
+                    let batch = transactions.iter().filter(|t| ids.contains(&t.id)).cloned().collect();
+                    (path, batch, true)
+                }
+                CheckpointWrite::Rewrite(path) => (path, transactions.clone(), false),
+            }
+        };
+        let result = tokio::task::spawn_blocking(move || recovery::write(&path, &batch, append)).await;
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to write checkpoint: {}", e),
+            Err(e) => warn!("Checkpoint task failed: {}", e),
+        }
+    }
+
+    // 应用正常退出时调用，清除检查点和运行标记
+    pub async fn end_session(&self) {
+        if let Some(dir) = self.data_dir.read().await.as_ref() {
+            recovery::end_session(dir);
+        }
+    }
+
+    // 会话保存：事务、过滤器、规则、收藏和保存的搜索写入单个文件
+    pub async fn save_session(&self, path: &str) -> Result<SessionSummary> {
+        let transactions = self.transactions.read().await.clone();
+        let favorites = transactions.iter().filter(|t| t.is_favorite).map(|t| t.id.clone()).collect();
+        let session = SessionFile {
+            version: session::SESSION_FORMAT_VERSION,
+            saved_at: chrono::Utc::now(),
+            transactions,
+            filters: self.filters.read().await.clone(),
+            rules: self.rules.read().await.clone(),
+            favorites,
+            saved_searches: self.saved_searches.read().await.clone(),
+        };
+        
+        let path = PathBuf::from(path);
+        let summary = session.summary(&path);
+        tokio::task::spawn_blocking(move || session::write(&path, &session)).await??;
+        info!("Saved session with {} transactions to {}", summary.transactions, summary.path);
+        
+        if self.hooks.read().await.has_enabled(&HookTrigger::SessionSaved) {
+            if let Ok(payload) = serde_json::to_value(&summary) {
+                hooks::dispatch(self.hooks.clone(), HookTrigger::SessionSaved, payload);
+            }
+        }
+        Ok(summary)
+    }
+
+    // 打开会话：替换当前工作集，已持久化到存储后端的事务保持不变
+    pub async fn load_session(&self, path: &str) -> Result<SessionSummary> {
+        let path = PathBuf::from(path);
+        let read_path = path.clone();
+        let mut session = tokio::task::spawn_blocking(move || session::read(&read_path)).await??;
+        let summary = session.summary(&path);
+        
+        // 无效的规则（如脚本无法编译）跳过，不影响其余内容
+        session.rules.retain(|rule| match rules::validate(rule) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Skipping invalid rule {} from session: {}", rule.id, e);
+                false
+            }
+        });
+        for transaction in &mut session.transactions {
+            transaction.is_favorite |= session.favorites.contains(&transaction.id);
+            transaction.decode_response_body();
+            transaction.classify_bodies();
+            transaction.rescore();
+        }
+        session.transactions.sort_by_key(|t| t.request.timestamp);
+        
+        let store = self.store.read().await.clone();
+        for transaction in &session.transactions {
+            Self::store_transaction(store.as_ref(), transaction);
+        }
+        {
+            let mut transactions = self.transactions.write().await;
+            *transactions = session.transactions;
+            self.cookie_jar.write().await.rebuild(&transactions);
+            self.buffer.write().await.recount(&transactions);
+            self.enforce_buffer_limits(&mut transactions).await;
+        }
+        self.checkpoint.write().await.reset();
+        *self.filters.write().await = session.filters;
+        *self.rules.write().await = session.rules;
+        *self.saved_searches.write().await = session.saved_searches;
+        info!("Loaded session with {} transactions from {}", summary.transactions, summary.path);
+        Ok(summary)
+    }
+
+    // 崩溃恢复
+    pub async fn get_recovery_info(&self) -> Option<RecoveryInfo> {
+        let dir = self.data_dir.read().await.clone()?;
+        tokio::task::spawn_blocking(move || recovery::recovery_info(&dir)).await.ok().flatten()
+    }
+
+    // 恢复上次异常退出前的事务，恢复后删除待恢复文件；返回新增的条目数
+    pub async fn restore_recovery(&self) -> Result<usize> {
+        let dir = self
+            .data_dir
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("App data dir is not set"))?;
+        let path = dir.join(recovery::RECOVERY_FILE_NAME);
+        let mut restored = tokio::task::spawn_blocking(move || recovery::load(&path)).await??;
+        for transaction in &mut restored {
+            transaction.decode_response_body();
+            transaction.classify_bodies();
+        }
+        let added = self.merge_transactions(restored).await;
+        recovery::discard(&dir)?;
+        Ok(added)
+    }
+
+    pub async fn discard_recovery(&self) -> Result<()> {
+        match self.data_dir.read().await.as_ref() {
+            Some(dir) => recovery::discard(dir),
+            None => Ok(()),
+        }
+    }
+
+    // 事件推送所用的应用句柄，由 Tauri setup 阶段注入
+    pub async fn set_app_handle(&self, app: tauri::AppHandle) {
+        *self.app_handle.write().await = Some(app);
+    }
+
+    // 最近的异常，新的在前
+    pub async fn get_anomalies(&self, min_severity: Option<AnomalySeverity>, limit: usize) -> Vec<Anomaly> {
+        self.anomalies
+            .read()
+            .await
+            .iter()
+            .rev()
+            .filter(|a| min_severity.is_none_or(|min| a.severity >= min))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    // 清空基线和已记录的异常，之后重新学习
+    pub async fn reset_anomaly_baseline(&self) {
+        let snapshot = self.anomaly_baseline.write().await.reset();
+        if let Some((path, data)) = snapshot {
+            Self::persist(path, data).await;
+        }
+        self.anomalies.write().await.clear();
+    }
+
+    pub async fn get_pii_masking(&self) -> MaskingSettings {
+        self.pii_masking.read().await.clone()
+    }
+
+    pub async fn set_pii_masking(&self, settings: MaskingSettings) {
+        *self.pii_masking.write().await = settings;
+    }
+
+    pub async fn get_redaction_policy(&self) -> RedactionPolicy {
+        self.redaction_policy.read().await.clone()
+    }
+
+    pub async fn set_redaction_policy(&self, policy: RedactionPolicy) {
+        *self.redaction_policy.write().await = policy.normalized();
+    }
+
+    pub async fn get_historical_stats(&self, host: Option<&str>, days: Option<u32>) -> Vec<EndpointHistory> {
+        self.historical_stats.read().await.query(host, days)
+    }
+
+    // 抓包设置
+    pub async fn get_pinning_report(&self) -> Vec<PinningSuspect> {
+        let settings = self.settings.read().await;
+        let mut suspects = self.pinning.read().await.report();
+        for suspect in &mut suspects {
+            suspect.passthrough = settings.is_tls_passthrough(&suspect.host);
+        }
+        suspects
+    }
+
+    pub async fn clear_pinning_report(&self) {
+        self.pinning.write().await.clear();
+    }
+
+    pub async fn set_tls_passthrough(&self, hosts: Vec<String>) -> Result<Vec<String>> {
+        let hosts = settings::normalize_passthrough(hosts)?;
+        self.settings.write().await.tls_passthrough = hosts.clone();
+        Ok(hosts)
+    }
+
+    pub async fn get_settings(&self) -> CaptureSettings {
+        self.settings.read().await.clone()
+    }
+
+    pub async fn set_settings(&self, settings: CaptureSettings) -> Result<()> {
+        let settings = settings.validated()?;
+        if settings.storage != self.settings.read().await.storage {
+            self.switch_storage(&settings.storage).await?;
+        }
+        *self.settings.write().await = settings;
+        // 调低上限后立即生效
+        self.enforce_buffer_limits(&mut *self.transactions.write().await).await;
+        Ok(())
+    }
+
+    // 调用方需持有 transactions 写锁
+    async fn enforce_buffer_limits(&self, transactions: &mut Vec<HttpTransaction>) {
+        let limits = self.settings.read().await.buffer.clone();
+        let evicted = self.buffer.write().await.enforce(transactions, &limits);
+        if evicted > 0 {
+            info!("Evicted {} oldest transactions to stay within capture buffer limits", evicted);
+        }
+    }
+
+    pub async fn get_capture_stats(&self) -> CaptureStats {
+        let transactions = self.transactions.read().await;
+        let limits = self.settings.read().await.buffer.clone();
+        self.buffer.read().await.stats(&transactions, &limits)
+    }
+
+    // 切换存储后端：当前会话写入新后端，并合并新后端中已持久化的事务
+    async fn switch_storage(&self, backend: &storage::StorageBackend) -> Result<()> {
+        let data_dir = self.data_dir.read().await.clone();
+        let store = storage::open(backend, data_dir.as_deref())?;
+        let persisted = store.load()?;
+        
+        let mut transactions = self.transactions.write().await;
+        for transaction in transactions.iter() {
+            store.upsert(transaction)?;
+        }
+        let restored: Vec<HttpTransaction> = persisted
+            .into_iter()
+            .filter(|p| !transactions.iter().any(|t| t.id == p.id))
+            .map(|mut p| {
+                p.decode_response_body();
+                p.classify_bodies();
+                p.rescore();
+                p
+            })
+            .collect();
+        if !restored.is_empty() {
+            transactions.extend(restored);
+            transactions.sort_by_key(|t| t.request.timestamp);
+            self.buffer.write().await.recount(&transactions);
+            self.enforce_buffer_limits(&mut transactions).await;
+        }
+        
+        *self.store.write().await = store;
+        Ok(())
+    }
+
+    // 设备模拟
+    pub async fn get_emulation_settings(&self) -> EmulationSettings {
+        self.emulation.read().await.clone()
+    }
+
+    pub async fn get_device_profiles(&self) -> Vec<DeviceProfile> {
+        self.emulation.read().await.profiles()
+    }
+
+    pub async fn set_device_profile(&self, host: Option<&str>, profile_id: Option<String>) -> Result<()> {
+        let mut emulation = self.emulation.write().await;
+        if let Some(id) = &profile_id {
+            if emulation.find_profile(id).is_none() {
+                return Err(anyhow::anyhow!("Device profile not found: {}", id));
+            }
+        }
+        emulation.set_profile(host, profile_id);
+        Ok(())
+    }
+
+    // 网络节流，host 为空时设置全局配置
+    pub async fn get_throttle_settings(&self) -> ThrottleSettings {
+        self.throttle.read().await.settings()
+    }
+
+    pub async fn set_throttle_profile(&self, host: Option<&str>, profile: Option<ThrottleProfile>) {
+        self.throttle.write().await.set_profile(host, profile);
+    }
+
+    pub async fn save_device_profile(&self, profile: DeviceProfile) {
+        self.emulation.write().await.save_custom_profile(profile);
+    }
+
+    pub async fn remove_device_profile(&self, profile_id: &str) {
+        self.emulation.write().await.remove_custom_profile(profile_id);
+    }
+
+    // 内置测试服务，首次访问时启动
+    pub async fn get_test_server_url(&self) -> Result<String> {
+        let mut test_server = self.test_server.write().await;
+        if let Some(server) = test_server.as_ref() {
+            return Ok(server.base_url());
+        }
+        let server = TestServer::start().await?;
+        let url = server.base_url();
+        info!("Test server listening on {}", url);
+        *test_server = Some(server);
+        Ok(url)
+    }
+
+    pub async fn stop_test_server(&self) {
+        *self.test_server.write().await = None;
+    }
+
+    // 断点
+    pub async fn add_breakpoint(&self, breakpoint: Breakpoint) {
+        self.intercepts.write().await.add_breakpoint(breakpoint);
+    }
+
+    pub async fn remove_breakpoint(&self, breakpoint_id: &str) {
+        self.intercepts.write().await.remove_breakpoint(breakpoint_id);
+    }
+
+    pub async fn get_breakpoints(&self) -> Vec<Breakpoint> {
+        self.intercepts.read().await.breakpoints()
+    }
+
+    pub async fn get_intercepted(&self) -> Vec<InterceptedItem> {
+        self.intercepts.read().await.list()
+    }
+
+    pub async fn modify_intercepted(&self, item_id: &str, modification: InterceptModification) -> Result<InterceptedItem> {
+        self.intercepts.write().await.modify(item_id, modification)
+    }
+
+    pub async fn resume_intercepted(&self, item_id: &str) -> Result<()> {
+        self.intercepts.write().await.resume(item_id)
+    }
+
+    pub async fn drop_intercepted(&self, item_id: &str) -> Result<()> {
+        self.intercepts.write().await.drop_item(item_id)
+    }
+
+    // 主机配额
+    pub async fn add_quota(&self, quota: HostQuota) {
+        self.quotas.write().await.add(quota);
+    }
+
+    pub async fn remove_quota(&self, quota_id: &str) {
+        self.quotas.write().await.remove(quota_id);
+    }
+
+    pub async fn get_quota_usage(&self) -> Vec<QuotaUsage> {
+        let historical_stats = self.historical_stats.read().await;
+        self.quotas.read().await.usage(&historical_stats)
+    }
+
+    pub async fn get_quota_alerts(&self) -> Vec<QuotaAlert> {
+        self.quotas.read().await.alerts()
+    }
+
+    // 差异对比模式
+    pub async fn get_shadow_config(&self) -> Option<ShadowConfig> {
+        self.shadow_config.read().await.clone()
+    }
+
+    pub async fn set_shadow_config(&self, config: Option<ShadowConfig>) -> Result<()> {
+        if let Some(config) = &config {
+            config.validate()?;
+        }
+        *self.shadow_config.write().await = config;
+        Ok(())
+    }
+
+    pub async fn get_shadow_report(&self) -> ShadowReport {
+        shadow::build_report(&self.transactions.read().await)
+    }
+
+    // 失败请求排查队列
+    pub async fn get_triage_queue(&self, state: Option<TriageState>) -> Vec<TriageItem> {
+        self.triage.read().await.list(state)
+    }
+
+    pub async fn get_triage_summary(&self) -> TriageSummary {
+        self.triage.read().await.summary()
+    }
+
+    pub async fn update_triage_item(&self, transaction_id: &str, update: TriageUpdate) -> Result<TriageItem> {
+        self.triage.write().await.update(transaction_id, update)
+    }
+
+    pub async fn attach_triage_analysis(&self, transaction_id: &str, analysis: AIAnalysisResult) -> Result<TriageItem> {
+        self.triage.write().await.attach_analysis(transaction_id, analysis)
+    }
+
+    pub async fn clear_resolved_triage(&self) {
+        self.triage.write().await.clear_resolved();
     }
 
     pub async fn get_transactions(&self) -> Vec<HttpTransaction> {
@@ -434,15 +3264,79 @@ impl ProxyServer {
 
     pub async fn clear_transactions(&self) {
         self.transactions.write().await.clear();
+        self.security_findings.write().await.clear();
+        self.buffer.write().await.reset();
+        self.checkpoint.write().await.reset();
+        if let Err(e) = self.store.read().await.clear() {
+            warn!("Failed to clear stored transactions: {}", e);
+        }
     }
 
     pub async fn is_running(&self) -> bool {
         *self.is_running.read().await
     }
 
+    // 代理自检
+    pub async fn run_self_test(&self) -> SelfTestReport {
+        let intercept_https = self.settings.read().await.intercept_https;
+        let ca_cert_path = self.ca_cert_path().await.filter(|path| path.exists());
+        let credentials = self.credentials.read().await.clone();
+        selftest::run(self.port, self.is_running().await, intercept_https, ca_cert_path, credentials).await
+    }
+
+    // 根证书管理
+    async fn ca_cert_path(&self) -> Option<PathBuf> {
+        self.data_dir.read().await.as_ref().map(|dir| dir.join(mitm::CA_CERT_FILE_NAME))
+    }
+
+    // 重新生成根证书会使之前安装的证书失效
+    pub async fn generate_ca_cert(&self) -> Result<CaCertInfo> {
+        let data_dir = self.data_dir.read().await.clone();
+        let ca = Arc::new(tokio::task::spawn_blocking(move || -> Result<CertificateAuthority> {
+            let ca = CertificateAuthority::generate()?;
+            if let Some(dir) = &data_dir {
+                ca.save(dir)?;
+            }
+            Ok(ca)
+        }).await??);
+        *self.ca.write().await = Some(ca.clone());
+        Ok(ca.info(self.ca_cert_path().await.as_deref()))
+    }
+
+    pub async fn get_ca_cert_info(&self) -> Result<CaCertInfo> {
+        let ca = self.certificate_authority().await?;
+        Ok(ca.info(self.ca_cert_path().await.as_deref()))
+    }
+
+    pub async fn export_ca_cert(&self, path: &str) -> Result<()> {
+        let ca = self.certificate_authority().await?;
+        tokio::fs::write(path, ca.cert_pem()).await?;
+        Ok(())
+    }
+
+    pub async fn install_ca_cert(&self) -> Result<String> {
+        // 确保证书已生成并落盘
+        self.certificate_authority().await?;
+        let cert_path = self.ca_cert_path()
+            .await
+            .filter(|path| path.exists())
+            .ok_or_else(|| anyhow::anyhow!("CA certificate has not been saved to the app data dir"))?;
+        mitm::install_ca_cert(&cert_path).await
+    }
+
     pub async fn stop(&self) {
         *self.is_running.write().await = false;
         
+        // 保存尚未落盘的长期统计与异常基线
+        let snapshot = self.historical_stats.write().await.snapshot();
+        if let Some((path, data)) = snapshot {
+            Self::persist(path, data).await;
+        }
+        let snapshot = self.anomaly_baseline.write().await.snapshot();
+        if let Some((path, data)) = snapshot {
+            Self::persist(path, data).await;
+        }
+        
         // 恢复系统代理设置
         self.restore_system_proxy().await;
     }
@@ -464,6 +3358,8 @@ impl ProxyServer {
         {
             self.restore_linux_proxy().await;
         }
+        
+        self.restore_proxy_bypass().await;
     }
 
     #[cfg(target_os = "macos")]
@@ -524,14 +3420,15 @@ impl ProxyServer {
     }
 
     // 搜索功能
-    pub async fn search_transactions(&self, filter: SearchFilter) -> Vec<HttpTransaction> {
+    pub async fn search_transactions(&self, filter: SearchFilter) -> Result<Vec<HttpTransaction>> {
+        let keyword = KeywordMatcher::new(&filter)?;
         let transactions = self.transactions.read().await;
-        transactions
+        let mut results = transactions
             .iter()
             .filter(|t| {
-                let matches_keyword = filter.keyword.is_empty() || 
-                    t.request.url.contains(&filter.keyword) ||
-                    t.request.method.contains(&filter.keyword);
+                let matches_keyword = keyword.as_ref()
+                    .map(|k| k.matches(t))
+                    .unwrap_or(true);
                 
                 let matches_method = filter.method.as_ref()
                     .map(|m| t.request.method == *m)
@@ -540,26 +3437,736 @@ impl ProxyServer {
                 let matches_status = filter.status
                     .map(|s| t.response.as_ref().map(|r| r.status == s).unwrap_or(false))
                     .unwrap_or(true);
+                let status = t.response.as_ref().map(|r| r.status);
+                let matches_status_range = filter.min_status
+                    .map(|min| status.is_some_and(|s| s >= min))
+                    .unwrap_or(true)
+                    && filter.max_status
+                        .map(|max| status.is_some_and(|s| s <= max))
+                        .unwrap_or(true);
                 
                 let matches_domain = filter.domain.as_ref()
                     .map(|d| t.request.url.contains(d))
                     .unwrap_or(true);
                 
-                matches_keyword && matches_method && matches_status && matches_domain
+                // 折叠已配对的预检请求
+                let collapsed = filter.collapse_preflight.unwrap_or(false)
+                    && t.preflight_for.is_some();
+                
+                let matches_risk = filter.min_risk
+                    .map(|min| t.risk_score() >= min)
+                    .unwrap_or(true);
+                
+                let matches_time = filter.from.map(|from| t.request.timestamp >= from).unwrap_or(true)
+                    && filter.to.map(|to| t.request.timestamp <= to).unwrap_or(true);
+                
+                let duration_ms = t.duration.map(|d| d.as_millis() as u64);
+                let matches_duration = filter.min_duration_ms
+                    .map(|min| duration_ms.map(|d| d >= min).unwrap_or(false))
+                    .unwrap_or(true)
+                    && filter.max_duration_ms
+                        .map(|max| duration_ms.map(|d| d <= max).unwrap_or(false))
+                        .unwrap_or(true);
+                
+                let matches_size = filter.min_body_size
+                    .map(|min| t.request.body.len() + t.response.as_ref().map(|r| r.body.len()).unwrap_or(0) >= min)
+                    .unwrap_or(true);
+                
+                let matches_tags = filter.tags.as_ref()
+                    .map(|tags| tags.iter().all(|tag| t.tags.contains(tag)))
+                    .unwrap_or(true)
+                    && !filter.exclude_tags.as_ref()
+                        .map(|tags| tags.iter().any(|tag| t.tags.contains(tag)))
+                        .unwrap_or(false);
+                
+                let matches_graphql = filter.graphql_operation.as_ref()
+                    .map(|name| t.graphql.iter().any(|op| {
+                        op.operation_name.as_ref().is_some_and(|n| n.eq_ignore_ascii_case(name))
+                    }))
+                    .unwrap_or(true);
+                
+                let matches_geo = filter.country.as_ref()
+                    .map(|c| t.geo.as_ref().is_some_and(|g| g.matches_country(c)))
+                    .unwrap_or(true)
+                    && filter.asn
+                        .map(|asn| t.geo.as_ref().is_some_and(|g| g.asn == Some(asn)))
+                        .unwrap_or(true);
+                
+                matches_keyword && matches_method && matches_status && matches_status_range && matches_domain && matches_risk
+                    && matches_time && matches_duration && matches_size && matches_tags && matches_graphql
+                    && matches_geo && !collapsed
             })
             .cloned()
-            .collect()
+            .collect::<Vec<_>>();
+        if filter.sort_by_risk.unwrap_or(false) {
+            results.sort_by_key(|t| std::cmp::Reverse(t.risk_score()));
+        }
+        Ok(results)
+    }
+
+    // 自然语言搜索：先把查询翻译为过滤条件，再按普通搜索执行
+    pub async fn semantic_search(&self, query: &str) -> Result<(InterpretedQuery, Vec<HttpTransaction>)> {
+        let analyzer = self.ai_analyzer().await;
+        let interpretation = nlsearch::interpret(&analyzer, query).await;
+        let results = self.search_transactions(interpretation.filter.clone()).await?;
+        Ok((interpretation, results))
+    }
+
+    // 汇总时间范围内的事务，生成可贴进缺陷报告的 Markdown 摘要
+    pub async fn summarize_capture(&self, range: CaptureRange) -> Result<CaptureSummary> {
+        let mut transactions: Vec<HttpTransaction> =
+            self.transactions.read().await.iter().filter(|t| range.contains(t)).cloned().collect();
+        if transactions.is_empty() {
+            return Err(anyhow::anyhow!("No transactions in the selected range"));
+        }
+        for transaction in transactions.iter_mut() {
+            *transaction = self.ai_view(transaction).await;
+        }
+        let analyzer = self.ai_analyzer().await;
+        Ok(summary::summarize(&analyzer, range, &transactions).await)
+    }
+
+    // 按给定事务或接口（取最近的样本）合并请求体与响应体的 JSON Schema
+    pub async fn infer_json_schema(
+        &self,
+        transaction_ids: Option<&[String]>,
+        endpoint: Option<&str>,
+    ) -> Result<InferredSchema> {
+        let selected: Vec<HttpTransaction> = {
+            let transactions = self.transactions.read().await;
+            match (transaction_ids, endpoint) {
+                (Some(ids), _) if !ids.is_empty() => {
+                    transactions.iter().filter(|t| ids.contains(&t.id)).cloned().collect()
+                }
+                (_, Some(endpoint)) if !endpoint.trim().is_empty() => transactions
+                    .iter()
+                    .rev()
+                    .filter(|t| schema::matches_endpoint(t, endpoint))
+                    .take(schema::MAX_SCHEMA_SAMPLES)
+                    .cloned()
+                    .collect(),
+                _ => return Err(anyhow::anyhow!("Specify transaction ids or an endpoint")),
+            }
+        };
+        if selected.is_empty() {
+            return Err(anyhow::anyhow!("No matching transactions"));
+        }
+        let mut views = Vec::with_capacity(selected.len());
+        for transaction in &selected {
+            views.push(self.analysis_view(transaction).await);
+        }
+        Ok(schema::infer(&views))
+    }
+
+    // 优先使用传入的 schema；否则同一接口抓到过成功的 JSON 响应时，按推断出的 schema 生成模拟数据
+    pub async fn generate_mock_response(
+        &self,
+        request: HttpRequest,
+        options: MockOptions,
+        schema: Option<serde_json::Value>,
+    ) -> Result<GeneratedMock> {
+        let observed = match schema {
+            Some(_) => schema::SchemaBuilder::default(),
+            None => schema::observed_response_schema(&self.transactions.read().await, &request.method, &request.url),
+        };
+        let config = AIResponseConfig {
+            enable_ai_responses: true,
+            response_type: ResponseType::Mock,
+            content_template: None,
+            ai_model: self.ai_analyzer().await.model_name().to_string(),
+            record_count: options.record_count,
+            locale: options.locale,
+        };
+        let mut generator = AIResponseGenerator::new(config);
+        if let Some(schema) = schema.or_else(|| observed.build()) {
+            generator = generator.with_observed_schema(schema);
+        }
+        let response = generator.generate_response(&request).await?;
+        Ok(GeneratedMock {
+            status: response.status,
+            headers: response.headers,
+            body: String::from_utf8_lossy(&response.body).into_owned(),
+            observed_samples: observed.samples(),
+        })
+    }
+
+    // 同 id 的搜索覆盖保存
+    pub async fn save_search(&self, search: SavedSearch) {
+        let mut saved_searches = self.saved_searches.write().await;
+        saved_searches.retain(|s| s.id != search.id);
+        saved_searches.push(search);
+    }
+
+    pub async fn remove_saved_search(&self, search_id: &str) {
+        self.saved_searches.write().await.retain(|s| s.id != search_id);
+    }
+
+    pub async fn get_saved_searches(&self) -> Vec<SavedSearch> {
+        self.saved_searches.read().await.clone()
+    }
+
+    // 命令面板：模糊匹配事务、保存的搜索和规则
+    pub async fn quick_find(&self, query: &str, limit: usize) -> Vec<QuickFindResult> {
+        let transactions = self.transactions.read().await;
+        let saved_searches = self.saved_searches.read().await;
+        let rules = self.rules.read().await;
+        palette::quick_find(query, &transactions, &saved_searches, &rules, limit)
+    }
+
+    // 仪表盘实时事件流
+    pub async fn start_dashboard_stream(&self, app: tauri::AppHandle, interval: std::time::Duration) {
+        let handle = dashboard::spawn_dashboard_stream(app, self.live_stats.clone(), interval);
+        if let Some(previous) = self.dashboard_task.write().await.replace(handle) {
+            previous.abort();
+        }
+    }
+
+    pub async fn stop_dashboard_stream(&self) {
+        if let Some(handle) = self.dashboard_task.write().await.take() {
+            handle.abort();
+        }
+    }
+
+    // OpenAPI 规范校验
+    pub async fn import_openapi_spec(&self, content: &str) -> Result<(String, usize)> {
+        let spec = OpenApiSpec::parse(content)?;
+        let summary = (spec.title(), spec.operation_count());
+        *self.openapi_spec.write().await = Some(spec);
+        Ok(summary)
+    }
+
+    pub async fn clear_openapi_spec(&self) {
+        *self.openapi_spec.write().await = None;
+    }
+
+    pub async fn get_spec_violations(&self, transaction_id: &str) -> Result<Vec<SpecViolation>> {
+        let spec = self.openapi_spec.read().await;
+        let spec = spec.as_ref().ok_or_else(|| anyhow::anyhow!("No OpenAPI spec imported"))?;
+        let transactions = self.transactions.read().await;
+        let transaction = transactions
+            .iter()
+            .find(|t| t.id == transaction_id)
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+        Ok(spec.validate(transaction))
+    }
+
+    pub async fn get_spec_conformance(&self) -> Result<ConformanceSummary> {
+        let spec = self.openapi_spec.read().await;
+        let spec = spec.as_ref().ok_or_else(|| anyhow::anyhow!("No OpenAPI spec imported"))?;
+        let transactions = self.transactions.read().await;
+        Ok(spec.conformance(&transactions))
+    }
+
+    // 导出钩子
+    pub async fn add_hook(&self, hook: ExportHook) -> Result<()> {
+        self.hooks.write().await.add(hook)
+    }
+
+    pub async fn remove_hook(&self, hook_id: &str) {
+        self.hooks.write().await.remove(hook_id);
+    }
+
+    pub async fn get_hooks(&self) -> Vec<HookInfo> {
+        self.hooks.read().await.list()
+    }
+
+    // 请求频率热力图
+    pub async fn get_activity_heatmap(&self, bucket_seconds: u64, group_by: HeatmapGroupBy) -> ActivityHeatmap {
+        let transactions = self.transactions.read().await;
+        stats::activity_heatmap(&transactions, bucket_seconds, group_by)
+    }
+
+    // 流量仪表盘的聚合统计
+    pub async fn get_traffic_stats(&self, range: CaptureRange, bucket_seconds: Option<u64>) -> TrafficStats {
+        let transactions = self.transactions.read().await;
+        stats::traffic_stats(&transactions, range, bucket_seconds)
+    }
+
+    // 延迟预算与瀑布图
+    pub async fn add_latency_budget(&self, budget: LatencyBudget) {
+        let mut budgets = self.latency_budgets.write().await;
+        budgets.retain(|b| b.id != budget.id);
+        budgets.push(budget);
+    }
+
+    pub async fn remove_latency_budget(&self, budget_id: &str) {
+        self.latency_budgets.write().await.retain(|b| b.id != budget_id);
+    }
+
+    pub async fn get_latency_budgets(&self) -> Vec<LatencyBudget> {
+        self.latency_budgets.read().await.clone()
+    }
+
+    pub async fn get_waterfall(&self) -> Vec<PageLoadGroup> {
+        let transactions = self.transactions.read().await;
+        let budgets = self.latency_budgets.read().await;
+        waterfall::build_waterfall(&transactions, &budgets)
+    }
+
+    // 重放：经上游客户端重新发送原请求，结果记为新事务
+    pub async fn replay_transaction(&self, transaction_id: &str, overrides: RequestOverrides) -> Result<HttpTransaction> {
+        let mut request = self.transactions.read().await
+            .iter()
+            .find(|t| t.id == transaction_id)
+            .map(|t| t.request.clone())
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+        if let Some(method) = overrides.method {
+            request.method = method;
+        }
+        if let Some(url) = overrides.url {
+            request.url = url;
+        }
+        if let Some(headers) = overrides.headers {
+            request.headers = headers;
+        }
+        if let Some(body) = overrides.body {
+            request.body = body.into_bytes();
+        }
+        request.timestamp = chrono::Utc::now();
+        
+        info!("Replaying {}: {} {}", transaction_id, request.method, request.url);
+        self.send_and_record(request, "replayed", Some(transaction_id.to_string())).await
+    }
+
+    // 请求编辑器：不经过抓包直接构造请求发送，结果记为新事务
+    pub async fn send_custom_request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: HashMap<String, String>,
+        body: String,
+    ) -> Result<HttpTransaction> {
+        let method = hyper::Method::from_bytes(method.trim().to_uppercase().as_bytes())
+            .map_err(|_| anyhow::anyhow!("Invalid method: {}", method))?;
+        let parsed = url::Url::parse(url.trim())?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(anyhow::anyhow!("Unsupported scheme: {}", parsed.scheme()));
+        }
+        let request = HttpRequest {
+            method: method.to_string(),
+            url: parsed.to_string(),
+            headers,
+            body: body.into_bytes(),
+            timestamp: chrono::Utc::now(),
+            version: Some("HTTP/1.1".to_string()),
+        };
+
+        info!("Sending custom request: {} {}", request.method, request.url);
+        self.send_and_record(request, "composed", None).await
+    }
+
+    async fn send_and_record(
+        &self,
+        request: HttpRequest,
+        tag: &str,
+        replay_of: Option<String>,
+    ) -> Result<HttpTransaction> {
+        let start_time = std::time::Instant::now();
+        let mut tags = vec![tag.to_string()];
+        let dns = self.dns.lookup_url(&request.url).await;
+        let (response, timings) = match self.forward_request(&request).await {
+            Ok((response, timings)) => (response, Some(timings.with_dns(dns.as_ref()))),
+            Err(e) => {
+                error!("Failed to send {} request: {}", tag, e);
+                tags.push(triage::NETWORK_ERROR_TAG.to_string());
+                (Self::proxy_error_response(&e), None)
+            }
+        };
+        
+        let recorded_id = self
+            .record_transaction(HttpTransaction {
+                replay_of,
+                dns,
+                timings,
+                ..HttpTransaction::new(request, response, start_time.elapsed(), tags)
+            })
+            .await;
+        self.transactions.read().await
+            .iter()
+            .find(|t| t.id == recorded_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Transaction was not recorded"))
+    }
+
+    // 风险评分：挑出尚未做过 AI 分析、启发式得分最高的事务交给模型评分
+    pub async fn model_scoring_candidates(&self, limit: usize, min_score: u8) -> Vec<HttpTransaction> {
+        let transactions = self.transactions.read().await;
+        let mut candidates: Vec<&HttpTransaction> = transactions
+            .iter()
+            .filter(|t| t.ai_analysis.is_none() && !t.is_preflight() && t.risk_score() >= min_score)
+            .collect();
+        candidates.sort_by_key(|t| std::cmp::Reverse(t.risk_score()));
+        candidates.into_iter().take(limit).cloned().collect()
     }
 
     // 收藏功能
     pub async fn toggle_favorite(&self, transaction_id: &str) -> bool {
-        let mut transactions = self.transactions.write().await;
-        if let Some(transaction) = transactions.iter_mut().find(|t| t.id == transaction_id) {
-            transaction.is_favorite = !transaction.is_favorite;
-            transaction.is_favorite
-        } else {
-            false
+        self.update_transaction(transaction_id, |t| t.is_favorite = !t.is_favorite)
+            .await
+            .map(|t| t.is_favorite)
+            .unwrap_or(false)
+    }
+
+    // 标签管理：返回更新后的标签
+    pub async fn add_tag(&self, transaction_id: &str, tag: &str) -> Result<Vec<String>> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(anyhow::anyhow!("Tag must not be empty"));
+        }
+        self.update_transaction(transaction_id, |t| {
+            if !t.tags.iter().any(|existing| existing == tag) {
+                t.tags.push(tag.to_string());
+            }
+        })
+        .await
+        .map(|t| t.tags)
+        .ok_or_else(|| anyhow::anyhow!("Transaction not found"))
+    }
+
+    pub async fn remove_tag(&self, transaction_id: &str, tag: &str) -> Result<Vec<String>> {
+        self.update_transaction(transaction_id, |t| t.tags.retain(|existing| existing != tag.trim()))
+            .await
+            .map(|t| t.tags)
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found"))
+    }
+
+    pub async fn get_transactions_by_tag(&self, tag: &str) -> Vec<HttpTransaction> {
+        let transactions = self.transactions.read().await;
+        transactions
+            .iter()
+            .filter(|t| t.tags.iter().any(|existing| existing == tag))
+            .cloned()
+            .collect()
+    }
+
+    // 备注为空时清除
+    pub async fn set_transaction_note(&self, transaction_id: &str, note: Option<String>) -> Result<()> {
+        let note = note.filter(|n| !n.trim().is_empty());
+        self.update_transaction(transaction_id, |t| t.notes = note)
+            .await
+            .map(|_| ())
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found"))
+    }
+
+    pub async fn get_transaction_note(&self, transaction_id: &str) -> Option<Option<String>> {
+        let transactions = self.transactions.read().await;
+        transactions
+            .iter()
+            .find(|t| t.id == transaction_id)
+            .map(|t| t.notes.clone())
+    }
+
+    pub async fn set_ai_analysis(&self, transaction_id: &str, analysis: AIAnalysisResult) {
+        self.update_transaction(transaction_id, |t| {
+            t.ai_analysis = Some(analysis);
+            t.rescore();
+        })
+        .await;
+    }
+
+    // 相同内容且相同模型的分析结果直接取缓存，不重复消耗 token
+    pub async fn analyze_transaction(&self, transaction: &HttpTransaction) -> Result<AIAnalysisResult> {
+        self.analyze_transaction_with(transaction, None).await
+    }
+
+    async fn analyze_transaction_with(
+        &self,
+        transaction: &HttpTransaction,
+        on_delta: Option<&mut (dyn FnMut(&str) + Send)>,
+    ) -> Result<AIAnalysisResult> {
+        let view = self.ai_view(transaction).await;
+        let analyzer = self.ai_analyzer().await;
+        let content_hash = aicache::content_hash(&view);
+        let model = analyzer.model_label();
+        let cached = self.ai_cache.read().await.get(&content_hash, &model);
+        let analysis = match cached {
+            Some(analysis) => analysis,
+            None => {
+                let analysis = analyzer.analyze_transaction_streaming(&view, on_delta).await?;
+                let snapshot = self.ai_cache.write().await.insert(&content_hash, &model, &analysis);
+                if let Some((path, data)) = snapshot {
+                    Self::persist(path, data).await;
+                }
+                analysis
+            }
+        };
+        self.set_ai_analysis(&transaction.id, analysis.clone()).await;
+        Ok(analysis)
+    }
+
+    // 立即返回请求 id，模型输出通过 ai:stream 事件逐段推送，最后推送完整结果
+    pub async fn analyze_transaction_stream(&self, transaction_id: &str) -> Result<String> {
+        let transaction = self
+            .transactions
+            .read()
+            .await
+            .iter()
+            .find(|t| t.id == transaction_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+        let proxy = self.clone();
+        Ok(self
+            .spawn_ai_request(move |deltas| async move {
+                let mut on_delta = move |text: &str| {
+                    let _ = deltas.send(text.to_string());
+                };
+                let result = proxy.analyze_transaction_with(&transaction, Some(&mut on_delta)).await?;
+                Ok(serde_json::to_value(result)?)
+            })
+            .await)
+    }
+
+    // 在后台运行一个 AI 请求：run 把模型输出写入通道，这里负责转发为 ai:stream 事件并登记以便取消
+    async fn spawn_ai_request<F, Fut>(&self, run: F) -> String
+    where
+        F: FnOnce(mpsc::UnboundedSender<String>) -> Fut,
+        Fut: std::future::Future<Output = Result<serde_json::Value>> + Send + 'static,
+    {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let (deltas, mut received) = mpsc::unbounded_channel();
+        let job = run(deltas);
+        let proxy = self.clone();
+        let id = request_id.clone();
+        // 持有写锁直到登记完成，避免任务先于登记结束
+        let mut requests = self.ai_requests.write().await;
+        let task = tokio::spawn(async move {
+            let forward = async {
+                while let Some(text) = received.recv().await {
+                    let event = AiStreamEvent { request_id: id.clone(), payload: StreamPayload::Delta { text } };
+                    proxy.emit(llm::AI_STREAM_EVENT, event).await;
+                }
+            };
+            // job 结束时发送端随之释放，转发循环也就结束了
+            let (result, ()) = tokio::join!(job, forward);
+            let payload = match result {
+                Ok(result) => StreamPayload::Done { result },
+                Err(e) => StreamPayload::Error { message: e.to_string() },
+            };
+            proxy.ai_requests.write().await.remove(&id);
+            proxy.emit(llm::AI_STREAM_EVENT, AiStreamEvent { request_id: id, payload }).await;
+        });
+        requests.insert(request_id.clone(), task.abort_handle());
+        request_id
+    }
+
+    // 围绕单个事务的多轮对话；不传 conversation_id 时新建对话
+    pub async fn chat_about_transaction(
+        &self,
+        transaction_id: &str,
+        message: &str,
+        conversation_id: Option<&str>,
+    ) -> Result<ChatReply> {
+        self.chat_with(transaction_id, message, conversation_id, None).await
+    }
+
+    // 流式版本，回复逐段通过 ai:stream 事件推送，完成时推送完整的 ChatReply
+    pub async fn chat_about_transaction_stream(
+        &self,
+        transaction_id: &str,
+        message: &str,
+        conversation_id: Option<&str>,
+    ) -> Result<String> {
+        // 先校验参数，出错时直接返回而不是通过事件
+        self.chat_context(transaction_id, message, conversation_id).await?;
+        let proxy = self.clone();
+        let (transaction_id, message) = (transaction_id.to_string(), message.to_string());
+        let conversation_id = conversation_id.map(str::to_string);
+        Ok(self
+            .spawn_ai_request(move |deltas| async move {
+                let mut on_delta = move |text: &str| {
+                    let _ = deltas.send(text.to_string());
+                };
+                let reply = proxy
+                    .chat_with(&transaction_id, &message, conversation_id.as_deref(), Some(&mut on_delta))
+                    .await?;
+                Ok(serde_json::to_value(reply)?)
+            })
+            .await)
+    }
+
+    async fn chat_context(
+        &self,
+        transaction_id: &str,
+        message: &str,
+        conversation_id: Option<&str>,
+    ) -> Result<(HttpTransaction, Conversation)> {
+        if message.trim().is_empty() {
+            return Err(anyhow::anyhow!("Message is empty"));
+        }
+        let transaction = self
+            .transactions
+            .read()
+            .await
+            .iter()
+            .find(|t| t.id == transaction_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+        let conversation = match conversation_id {
+            Some(id) => {
+                let conversation = self
+                    .conversations
+                    .read()
+                    .await
+                    .get(id)
+                    .ok_or_else(|| anyhow::anyhow!("Conversation not found"))?;
+                if conversation.transaction_id != transaction_id {
+                    return Err(anyhow::anyhow!("Conversation belongs to another transaction"));
+                }
+                conversation
+            }
+            None => Conversation::new(transaction_id),
+        };
+        Ok((self.ai_view(&transaction).await, conversation))
+    }
+
+    async fn chat_with(
+        &self,
+        transaction_id: &str,
+        message: &str,
+        conversation_id: Option<&str>,
+        on_delta: Option<&mut (dyn FnMut(&str) + Send)>,
+    ) -> Result<ChatReply> {
+        let (transaction, conversation) = self.chat_context(transaction_id, message, conversation_id).await?;
+        let analyzer = self.ai_analyzer().await;
+        let (system, messages) = conversation.prompt(&transaction, message);
+        // 对话没有启发式回退，模型不可用时不记录本次提问
+        let content = analyzer
+            .complete(&system, &messages, false, on_delta)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No AI provider is configured; set an API key or a local model first"))?;
+        let conversation_id = conversation.id.clone();
+        let (reply, snapshot) = {
+            let mut conversations = self.conversations.write().await;
+            // 等待回复期间同一对话可能已追加了其他问答，在最新的版本上追加本次问答
+            let mut latest = match conversations.get(&conversation_id) {
+                Some(latest) => latest,
+                None if conversation.messages.is_empty() => conversation,
+                None => return Err(anyhow::anyhow!("Conversation was deleted while waiting for the reply")),
+            };
+            latest.push(ChatRole::User, message.trim().to_string(), None);
+            let reply = latest.push(ChatRole::Assistant, content, Some(analyzer.model_name().to_string()));
+            (reply, conversations.save(latest))
+        };
+        if let Some((path, data)) = snapshot {
+            if let Some(dir) = path.parent() {
+                if let Err(e) = tokio::fs::create_dir_all(dir).await {
+                    warn!("Failed to create {}: {}", dir.display(), e);
+                }
+            }
+            Self::persist(path, data).await;
         }
+        Ok(ChatReply { conversation_id, reply })
+    }
+
+    pub async fn get_conversation(&self, conversation_id: &str) -> Option<Conversation> {
+        self.conversations.read().await.get(conversation_id)
+    }
+
+    pub async fn list_conversations(&self, transaction_id: Option<&str>) -> Vec<Conversation> {
+        self.conversations.read().await.list(transaction_id)
+    }
+
+    pub async fn delete_conversation(&self, conversation_id: &str) -> Result<bool> {
+        self.conversations.write().await.delete(conversation_id)
+    }
+
+    pub async fn cancel_ai_request(&self, request_id: &str) -> bool {
+        let Some(handle) = self.ai_requests.write().await.remove(request_id) else {
+            return false;
+        };
+        handle.abort();
+        self.emit(
+            llm::AI_STREAM_EVENT,
+            AiStreamEvent { request_id: request_id.to_string(), payload: StreamPayload::Cancelled },
+        )
+        .await;
+        true
+    }
+
+    // 给定事务时只清除该事务的缓存；返回清除的条目数
+    pub async fn invalidate_ai_cache(&self, transaction_id: Option<&str>) -> Result<usize> {
+        let content_hash = match transaction_id {
+            Some(id) => {
+                let transaction = self
+                    .transactions
+                    .read()
+                    .await
+                    .iter()
+                    .find(|t| t.id == id)
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+                Some(aicache::content_hash(&self.ai_view(&transaction).await))
+            }
+            None => None,
+        };
+        let (removed, snapshot) = self.ai_cache.write().await.invalidate(content_hash.as_deref());
+        if let Some((path, data)) = snapshot {
+            Self::persist(path, data).await;
+        }
+        Ok(removed)
+    }
+
+    async fn set_security_findings(&self, transaction_id: &str, findings: Vec<String>) {
+        self.update_transaction(transaction_id, |t| {
+            t.security_findings = findings;
+            t.rescore();
+        })
+        .await;
+    }
+
+    // 界面展示用的副本，按设置遮盖个人信息
+    async fn ui_view(&self, transaction_id: &str) -> Option<HttpTransaction> {
+        let transaction = self.transactions.read().await.iter().find(|t| t.id == transaction_id).cloned()?;
+        Some(self.pii_masking.read().await.view(transaction, MaskTarget::Ui))
+    }
+
+    pub async fn get_transaction_detail(&self, transaction_id: &str) -> Option<TransactionDetail> {
+        self.ui_view(transaction_id).await.as_ref().map(TransactionDetail::from)
+    }
+
+    // 十六进制按存储的字节显示；响应体在入库时已解除 Content-Encoding
+    pub async fn get_body_hexdump(
+        &self,
+        transaction_id: &str,
+        part: BodyPart,
+        offset: usize,
+        length: usize,
+    ) -> Result<HexDumpPage> {
+        let transactions = self.transactions.read().await;
+        let transaction = transactions
+            .iter()
+            .find(|t| t.id == transaction_id)
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+        let (body, _) = part.select(transaction).ok_or_else(|| anyhow::anyhow!("Transaction has no response"))?;
+        Ok(hexdump::page(body, offset, length))
+    }
+
+    pub async fn get_body_as_json(&self, transaction_id: &str, part: BodyPart, include_paths: bool) -> Result<JsonBody> {
+        let transaction = self.ui_view(transaction_id).await.ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+        let (body, headers) = part.select(&transaction).ok_or_else(|| anyhow::anyhow!("Transaction has no response"))?;
+        jsonbody::parse(body, headers, include_paths)
+    }
+
+    // 表单只出现在请求体中
+    pub async fn get_parsed_body(&self, transaction_id: &str) -> Result<ParsedBody> {
+        let transaction = self.ui_view(transaction_id).await.ok_or_else(|| anyhow::anyhow!("Transaction not found"))?;
+        formdata::parse(&transaction.request.body, &transaction.request.headers)
+    }
+
+    pub async fn get_modification_history(&self, transaction_id: &str) -> Option<Vec<ModificationStage>> {
+        let transactions = self.transactions.read().await;
+        transactions
+            .iter()
+            .find(|t| t.id == transaction_id)
+            .map(|t| t.modifications.clone())
+    }
+
+    pub async fn get_ws_messages(&self, transaction_id: &str) -> Option<Vec<WsMessage>> {
+        let transactions = self.transactions.read().await;
+        transactions
+            .iter()
+            .find(|t| t.id == transaction_id)
+            .map(|t| t.ws_messages.clone())
     }
 
     pub async fn get_favorites(&self) -> Vec<HttpTransaction> {
@@ -572,8 +4179,26 @@ impl ProxyServer {
     }
 
     // 规则管理
-    pub async fn add_rule(&self, rule: RequestRule) {
+    pub async fn add_rule(&self, rule: RequestRule) -> Result<()> {
+        rules::validate(&rule)?;
         self.rules.write().await.push(rule);
+        Ok(())
+    }
+
+    // 批量添加：任一规则无效时整体拒绝
+    pub async fn add_rules(&self, new_rules: Vec<RequestRule>) -> Result<usize> {
+        for rule in &new_rules {
+            rules::validate(rule).map_err(|e| anyhow::anyhow!("Invalid rule '{}': {}", rule.name, e))?;
+        }
+        let count = new_rules.len();
+        self.rules.write().await.extend(new_rules);
+        Ok(count)
+    }
+
+    // 由模型把描述翻译为规则，只返回待确认的结果，不加入规则列表
+    pub async fn create_rule_from_text(&self, text: &str) -> Result<RuleProposal> {
+        let analyzer = self.ai_analyzer().await;
+        rulegen::propose_rules(&analyzer, text).await
     }
 
     pub async fn remove_rule(&self, rule_id: &str) {
@@ -585,48 +4210,147 @@ impl ProxyServer {
         self.rules.read().await.clone()
     }
 
+    // 按给定 id 顺序排列规则，未列出的规则保持原有相对顺序排在其后
+    pub async fn reorder_rules(&self, rule_ids: &[String]) -> Result<()> {
+        let mut rules = self.rules.write().await;
+        if let Some(unknown) = rule_ids.iter().find(|id| !rules.iter().any(|r| &r.id == *id)) {
+            return Err(anyhow::anyhow!("Rule not found: {}", unknown));
+        }
+        let position = |rule: &RequestRule| rule_ids.iter().position(|id| *id == rule.id).unwrap_or(usize::MAX);
+        rules.sort_by_key(position);
+        Ok(())
+    }
+
+    // 规则集导入导出，返回导出的规则数量
+    pub async fn export_rules(&self, path: &str) -> Result<usize> {
+        let rules = self.rules.read().await.clone();
+        let path = PathBuf::from(path);
+        let count = rules.len();
+        tokio::task::spawn_blocking(move || ruleset::write(&path, &rules)).await??;
+        Ok(count)
+    }
+
+    pub async fn import_rules(&self, path: &str, mode: ImportMode) -> Result<RuleImportSummary> {
+        let path = PathBuf::from(path);
+        let imported = tokio::task::spawn_blocking(move || ruleset::read(&path)).await??;
+        let summary = ruleset::import(&mut *self.rules.write().await, imported, mode);
+        info!("Imported rules: {} added, {} updated, {} total", summary.added, summary.updated, summary.total);
+        Ok(summary)
+    }
+
+    // 导出用的副本：按脱敏策略替换凭据，再按设置遮盖个人信息
+    async fn export_view(&self, transactions: Vec<HttpTransaction>) -> Vec<HttpTransaction> {
+        let policy = self.redaction_policy.read().await;
+        let masking = self.pii_masking.read().await;
+        transactions.iter().map(|t| masking.view(policy.apply(t), MaskTarget::Export)).collect()
+    }
+
     // HAR 导出
-    pub async fn export_har(&self) -> String {
+    pub async fn export_har(&self, options: &ExportOptions) -> String {
+        let transactions = self.export_view(self.transactions.read().await.clone()).await;
+        serde_json::to_string_pretty(&har::export(&transactions, options)).unwrap_or_default()
+    }
+
+    // mitmproxy 流文件导出，返回写入的流数量
+    pub async fn export_mitmproxy_flows(&self, path: &str) -> Result<usize> {
+        let transactions = self.export_view(self.transactions.read().await.clone()).await;
+        let count = transactions.len();
+        let data = mitmproxy::flows(&transactions, self.port);
+        let path = PathBuf::from(path);
+        tokio::task::spawn_blocking(move || std::fs::write(path, data)).await??;
+        Ok(count)
+    }
+
+    // pcapng 导出：每个事务合成一条 TCP 连接，返回写入的事务数量
+    pub async fn export_pcapng(&self, path: &str) -> Result<usize> {
+        let transactions = self.export_view(self.transactions.read().await.clone()).await;
+        let count = transactions.len();
+        let path = PathBuf::from(path);
+        tokio::task::spawn_blocking(move || std::fs::write(path, pcap::pcapng(&transactions))).await??;
+        Ok(count)
+    }
+
+    // 复制为 curl 命令
+    pub async fn generate_curl(&self, transaction_id: &str, options: &CurlOptions) -> Option<String> {
         let transactions = self.transactions.read().await;
-        let har_entries: Vec<serde_json::Value> = transactions
+        transactions
             .iter()
-            .map(|t| {
-                json!({
-                    "startedDateTime": t.request.timestamp.to_rfc3339(),
-                    "time": t.duration.map(|d| d.as_millis() as u64).unwrap_or(0),
-                    "request": {
-                        "method": t.request.method,
-                        "url": t.request.url,
-                        "headers": t.request.headers.iter().map(|(k, v)| json!({
-                            "name": k,
-                            "value": v
-                        })).collect::<Vec<_>>(),
-                        "bodySize": t.request.body.len()
-                    },
-                    "response": t.response.as_ref().map(|r| json!({
-                        "status": r.status,
-                        "headers": r.headers.iter().map(|(k, v)| json!({
-                            "name": k,
-                            "value": v
-                        })).collect::<Vec<_>>(),
-                        "bodySize": r.body.len()
-                    }))
-                })
-            })
+            .find(|t| t.id == transaction_id)
+            .map(|t| curl::generate(&t.request, options))
+    }
+
+    // Postman 集合导出，未指定 id 时导出全部事务
+    pub async fn export_postman(
+        &self,
+        collection_name: &str,
+        transaction_ids: Option<Vec<String>>,
+        options: &ExportOptions,
+    ) -> String {
+        let selected: Vec<HttpTransaction> = self
+            .transactions
+            .read()
+            .await
+            .iter()
+            .filter(|t| transaction_ids.as_ref().map(|ids| ids.contains(&t.id)).unwrap_or(true))
+            .cloned()
             .collect();
+        let selected = self.export_view(selected).await;
+        serde_json::to_string_pretty(&postman::collection(collection_name, &selected, options)).unwrap_or_default()
+    }
 
-        let har = json!({
-            "log": {
-                "version": "1.2",
-                "creator": {
-                    "name": "PacketMind AI",
-                    "version": "1.0.0"
-                },
-                "entries": har_entries
-            }
-        });
+    // 压测脚本导出：指定 id 时按录制顺序导出这些事务（如一段用户操作流程），否则按过滤条件筛选
+    pub async fn export_load_test(
+        &self,
+        format: LoadTestFormat,
+        filter: Option<SearchFilter>,
+        transaction_ids: Option<Vec<String>>,
+        options: &ExportOptions,
+    ) -> Result<String> {
+        let selected = match (transaction_ids, filter) {
+            (Some(ids), _) => self.transactions.read().await
+                .iter()
+                .filter(|t| ids.contains(&t.id))
+                .cloned()
+                .collect(),
+            (None, Some(filter)) => self.search_transactions(filter).await?,
+            (None, None) => self.transactions.read().await.clone(),
+        };
+        let selected = self.export_view(selected).await;
+        Ok(loadtest::generate(format, &selected, options))
+    }
+
+    // 导入 HAR，恢复 _packetmind 扩展中的分析结果；返回导入的条目数
+    pub async fn import_har(&self, content: &str) -> Result<usize> {
+        let imported = har::import(content)?;
+        let count = imported.len();
+        self.merge_transactions(imported).await;
+        Ok(count)
+    }
 
-        serde_json::to_string_pretty(&har).unwrap_or_default()
+    // 合并外部来源的事务，重复导入同一来源时不产生重复条目；返回新增的条目数
+    async fn merge_transactions(&self, mut incoming: Vec<HttpTransaction>) -> usize {
+        let store = self.store.read().await.clone();
+        let mut checkpoint = self.checkpoint.write().await;
+        let mut transactions = self.transactions.write().await;
+        let mut cookie_jar = self.cookie_jar.write().await;
+        let mut added = 0;
+        // 按时间顺序回放，Cookie 的覆盖和删除才与实际一致
+        incoming.sort_by_key(|t| t.request.timestamp);
+        for mut transaction in incoming {
+            if !transactions.iter().any(|t| t.id == transaction.id) {
+                transaction.rescore();
+                transaction.detect_pii();
+                cookie_jar.observe(&transaction);
+                Self::store_transaction(store.as_ref(), &transaction);
+                checkpoint.mark(&transaction.id);
+                transactions.push(transaction);
+                added += 1;
+            }
+        }
+        transactions.sort_by_key(|t| t.request.timestamp);
+        self.buffer.write().await.recount(&transactions);
+        self.enforce_buffer_limits(&mut transactions).await;
+        added
     }
 
     // 编码工具
@@ -0,0 +1,164 @@
+use crate::history::HistoricalStats;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const QUOTA_ALERT_EVENT: &str = "quota:alert";
+
+fn default_warn_at_percent() -> u8 {
+    80
+}
+
+// 按自然日（UTC）统计，host 同时匹配其子域名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostQuota {
+    pub id: String,
+    pub host: String,
+    pub max_requests_per_day: Option<u64>,
+    pub max_mb_per_day: Option<f64>,
+    #[serde(default = "default_warn_at_percent")]
+    pub warn_at_percent: u8,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QuotaLevel {
+    Ok,
+    Warning,
+    Exceeded,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum QuotaMetric {
+    Requests,
+    Bytes,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub quota: HostQuota,
+    pub day: String,
+    pub requests: u64,
+    pub bytes: u64,
+    pub requests_percent: Option<f64>,
+    pub bytes_percent: Option<f64>,
+    pub level: QuotaLevel,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaAlert {
+    pub quota_id: String,
+    pub host: String,
+    pub day: String,
+    pub metric: QuotaMetric,
+    pub level: QuotaLevel,
+    pub usage_percent: f64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl HostQuota {
+    pub fn matches(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        let expected = self.host.to_lowercase();
+        host == expected || host.ends_with(&format!(".{}", expected))
+    }
+
+    fn level(&self, percent: f64) -> QuotaLevel {
+        if percent >= 100.0 {
+            QuotaLevel::Exceeded
+        } else if percent >= self.warn_at_percent as f64 {
+            QuotaLevel::Warning
+        } else {
+            QuotaLevel::Ok
+        }
+    }
+
+    pub fn usage(&self, stats: &HistoricalStats, day: &str) -> QuotaUsage {
+        let (requests, bytes) = stats.host_usage(&self.host, day);
+        let requests_percent = self
+            .max_requests_per_day
+            .map(|max| requests as f64 * 100.0 / max.max(1) as f64);
+        let bytes_percent = self
+            .max_mb_per_day
+            .map(|max| bytes as f64 * 100.0 / (max * 1024.0 * 1024.0).max(1.0));
+        let level = [requests_percent, bytes_percent]
+            .into_iter()
+            .flatten()
+            .map(|percent| self.level(percent))
+            .max()
+            .unwrap_or(QuotaLevel::Ok);
+
+        QuotaUsage {
+            quota: self.clone(),
+            day: day.to_string(),
+            requests,
+            bytes,
+            requests_percent,
+            bytes_percent,
+            level,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct QuotaMonitor {
+    quotas: Vec<HostQuota>,
+    // 每个配额每天每项指标已提醒到的级别，同一级别只提醒一次
+    raised: HashMap<(String, String, QuotaMetric), QuotaLevel>,
+    alerts: Vec<QuotaAlert>,
+}
+
+impl QuotaMonitor {
+    pub fn add(&mut self, quota: HostQuota) {
+        self.quotas.retain(|q| q.id != quota.id);
+        self.quotas.push(quota);
+    }
+
+    pub fn remove(&mut self, quota_id: &str) {
+        self.quotas.retain(|q| q.id != quota_id);
+        self.raised.retain(|(id, _, _), _| id != quota_id);
+    }
+
+    pub fn usage(&self, stats: &HistoricalStats) -> Vec<QuotaUsage> {
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        self.quotas.iter().map(|q| q.usage(stats, &today)).collect()
+    }
+
+    pub fn alerts(&self) -> Vec<QuotaAlert> {
+        self.alerts.clone()
+    }
+
+    // 记录一次请求后检查相关配额，返回新升级的提醒
+    pub fn check(&mut self, stats: &HistoricalStats, host: &str, day: &str) -> Vec<QuotaAlert> {
+        // 跨天后旧的提醒状态不再需要
+        self.raised.retain(|(_, raised_day, _), _| raised_day.as_str() >= day);
+        let mut new_alerts = Vec::new();
+        for quota in self.quotas.iter().filter(|q| q.matches(host)) {
+            let usage = quota.usage(stats, day);
+            let metrics = [
+                (QuotaMetric::Requests, usage.requests_percent),
+                (QuotaMetric::Bytes, usage.bytes_percent),
+            ];
+            for (metric, percent) in metrics {
+                let Some(percent) = percent else {
+                    continue;
+                };
+                let level = quota.level(percent);
+                let key = (quota.id.clone(), day.to_string(), metric);
+                let raised = self.raised.get(&key).copied().unwrap_or(QuotaLevel::Ok);
+                if level > raised {
+                    self.raised.insert(key, level);
+                    new_alerts.push(QuotaAlert {
+                        quota_id: quota.id.clone(),
+                        host: quota.host.clone(),
+                        day: day.to_string(),
+                        metric,
+                        level,
+                        usage_percent: percent,
+                        timestamp: chrono::Utc::now(),
+                    });
+                }
+            }
+        }
+        self.alerts.extend(new_alerts.iter().cloned());
+        new_alerts
+    }
+}
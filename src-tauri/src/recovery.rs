@@ -0,0 +1,181 @@
+use crate::proxy::HttpTransaction;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+// 当前会话的检查点，每行一个事务 JSON，同一事务后写入的行覆盖先前的
+pub const CHECKPOINT_FILE_NAME: &str = "checkpoint.jsonl";
+// 上次异常退出时留下的检查点，等待用户选择恢复或丢弃
+pub const RECOVERY_FILE_NAME: &str = "recovery.jsonl";
+// 运行期间存在，正常退出时删除；启动时仍存在说明上次未正常退出
+const LOCK_FILE_NAME: &str = "session.lock";
+
+// 两次写入检查点之间的间隔
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+// 追加写入使文件超过该大小时整体重写，去掉被覆盖的旧版本
+const COMPACT_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryInfo {
+    pub transactions: usize,
+    pub saved_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub bytes: u64,
+}
+
+// 记录自上次写入后新增或修改的事务，由后台任务定期增量写入
+#[derive(Debug, Default)]
+pub struct Checkpoint {
+    path: Option<PathBuf>,
+    dirty: HashSet<String>,
+    // 清空事务后下次写入时整体重写
+    rewrite: bool,
+}
+
+pub enum CheckpointWrite {
+    Append(PathBuf, HashSet<String>),
+    Rewrite(PathBuf),
+}
+
+impl Checkpoint {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            path: Some(dir.join(CHECKPOINT_FILE_NAME)),
+            dirty: HashSet::new(),
+            rewrite: false,
+        }
+    }
+
+    pub fn mark(&mut self, transaction_id: &str) {
+        if self.path.is_some() && !self.rewrite {
+            self.dirty.insert(transaction_id.to_string());
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.dirty.clear();
+        self.rewrite = true;
+    }
+
+    pub fn take(&mut self) -> Option<CheckpointWrite> {
+        let path = self.path.clone()?;
+        if std::mem::take(&mut self.rewrite) {
+            self.dirty.clear();
+            return Some(CheckpointWrite::Rewrite(path));
+        }
+        if self.dirty.is_empty() {
+            return None;
+        }
+        let oversized = std::fs::metadata(&path).map(|m| m.len() > COMPACT_THRESHOLD).unwrap_or(false);
+        let dirty = std::mem::take(&mut self.dirty);
+        Some(if oversized { CheckpointWrite::Rewrite(path) } else { CheckpointWrite::Append(path, dirty) })
+    }
+}
+
+// 整体重写时先写临时文件再改名，写到一半失败不会丢掉已有的检查点
+pub fn write(path: &Path, transactions: &[HttpTransaction], append: bool) -> Result<()> {
+    if append {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        return write_lines(file, transactions);
+    }
+    let temp = path.with_extension("tmp");
+    write_lines(std::fs::File::create(&temp)?, transactions)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+fn write_lines(file: std::fs::File, transactions: &[HttpTransaction]) -> Result<()> {
+    let mut writer = std::io::BufWriter::new(file);
+    for transaction in transactions {
+        serde_json::to_writer(&mut writer, transaction)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    writer.get_ref().sync_data()?;
+    Ok(())
+}
+
+// 启动时调用：上次未正常退出则把其检查点转为待恢复文件，然后开始新会话
+pub fn begin_session(dir: &Path) -> Option<RecoveryInfo> {
+    let lock = dir.join(LOCK_FILE_NAME);
+    let checkpoint = dir.join(CHECKPOINT_FILE_NAME);
+    let recovery = dir.join(RECOVERY_FILE_NAME);
+
+    if lock.exists() && has_content(&checkpoint) {
+        warn!("Previous session did not shut down cleanly, keeping checkpoint for recovery");
+        // 多次异常退出且未处理时追加到已有的待恢复文件
+        if let Err(e) = append_file(&checkpoint, &recovery) {
+            warn!("Failed to keep checkpoint {}: {}", checkpoint.display(), e);
+        }
+    }
+    let _ = std::fs::remove_file(&checkpoint);
+    if let Err(e) = std::fs::write(&lock, format!("{}\n{}", std::process::id(), chrono::Utc::now().to_rfc3339())) {
+        warn!("Failed to write {}: {}", lock.display(), e);
+    }
+    recovery_info(dir)
+}
+
+// 正常退出时调用，会话数据不再需要恢复
+pub fn end_session(dir: &Path) {
+    let _ = std::fs::remove_file(dir.join(CHECKPOINT_FILE_NAME));
+    let _ = std::fs::remove_file(dir.join(LOCK_FILE_NAME));
+}
+
+pub fn recovery_info(dir: &Path) -> Option<RecoveryInfo> {
+    let path = dir.join(RECOVERY_FILE_NAME);
+    let metadata = std::fs::metadata(&path).ok().filter(|m| m.len() > 0)?;
+    let transactions = load(&path).map(|t| t.len()).unwrap_or(0);
+    Some(RecoveryInfo {
+        transactions,
+        saved_at: metadata.modified().ok().map(chrono::DateTime::<chrono::Utc>::from),
+        bytes: metadata.len(),
+    })
+}
+
+// 按 id 合并，保留每个事务最后写入的版本；崩溃时写了一半的末行直接跳过
+pub fn load(path: &Path) -> Result<Vec<HttpTransaction>> {
+    let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut order = Vec::new();
+    let mut latest: HashMap<String, HttpTransaction> = HashMap::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<HttpTransaction>(&line) {
+            Ok(transaction) => {
+                if !latest.contains_key(&transaction.id) {
+                    order.push(transaction.id.clone());
+                }
+                latest.insert(transaction.id.clone(), transaction);
+            }
+            Err(e) => warn!("Skipping unreadable checkpoint line: {}", e),
+        }
+    }
+    Ok(order.into_iter().filter_map(|id| latest.remove(&id)).collect())
+}
+
+pub fn discard(dir: &Path) -> Result<()> {
+    let path = dir.join(RECOVERY_FILE_NAME);
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn has_content(path: &Path) -> bool {
+    std::fs::metadata(path).map(|m| m.len() > 0).unwrap_or(false)
+}
+
+fn append_file(from: &Path, to: &Path) -> Result<()> {
+    let mut source = std::fs::File::open(from)?;
+    let mut target = std::fs::OpenOptions::new().create(true).append(true).open(to)?;
+    // 上一段末尾可能是写了一半的行，先换行避免与下一段首行粘连
+    target.write_all(b"\n")?;
+    std::io::copy(&mut source, &mut target)?;
+    target.sync_data()?;
+    Ok(())
+}
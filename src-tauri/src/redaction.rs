@@ -0,0 +1,581 @@
+use crate::classify::{self, BodyKind};
+use crate::export::REDACTED_PLACEHOLDER;
+use crate::modifications::FieldChange;
+use crate::params::Parameter;
+use crate::passive::MAX_EVIDENCE_CHARS;
+use crate::proxy::{find_header, HttpRequest, HttpResponse, HttpTransaction};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+// 含个人信息的事务打上该标签，便于在列表中筛选
+pub const PII_TAG: &str = "pii-detected";
+
+// 单个消息体只扫描开头部分，避免大响应拖慢入库；遮盖时仍处理全文
+const MAX_SCAN_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Email,
+    CreditCard,
+    Phone,
+    NationalId,
+    BearerToken,
+}
+
+impl PiiKind {
+    pub const ALL: [PiiKind; 5] =
+        [PiiKind::Email, PiiKind::CreditCard, PiiKind::Phone, PiiKind::NationalId, PiiKind::BearerToken];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::CreditCard => "credit_card",
+            Self::Phone => "phone",
+            Self::NationalId => "national_id",
+            Self::BearerToken => "bearer_token",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiLocation {
+    Url,
+    RequestHeader,
+    RequestBody,
+    ResponseHeader,
+    ResponseBody,
+}
+
+impl PiiLocation {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Url => "URL",
+            Self::RequestHeader => "请求头",
+            Self::RequestBody => "请求体",
+            Self::ResponseHeader => "响应头",
+            Self::ResponseBody => "响应体",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PiiFinding {
+    pub kind: PiiKind,
+    pub location: PiiLocation,
+    // 所在的头名称，仅头部位置有
+    pub header: Option<String>,
+    pub count: usize,
+    // 第一处匹配按部分遮盖后的样子，便于确认而不暴露原值
+    pub preview: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskStyle {
+    // 保留末四位或邮箱域名，便于区分不同的值
+    #[default]
+    Partial,
+    // 整体替换为类型占位符
+    Full,
+}
+
+// 遮盖生效的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaskTarget {
+    Export,
+    AiPrompt,
+    Ui,
+}
+
+// 检测始终进行，遮盖只作用于选中的类型和位置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaskingSettings {
+    pub kinds: Vec<PiiKind>,
+    pub style: MaskStyle,
+    pub exports: bool,
+    pub ai_prompts: bool,
+    // 详情面板默认显示原文，抓包调试时通常需要看到真实值
+    pub ui: bool,
+}
+
+impl Default for MaskingSettings {
+    fn default() -> Self {
+        Self { kinds: PiiKind::ALL.to_vec(), style: MaskStyle::Partial, exports: true, ai_prompts: true, ui: false }
+    }
+}
+
+// 默认替换为占位符的凭据类请求头
+const DEFAULT_REDACTED_HEADERS: [&str; 8] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+    "x-auth-token",
+    "x-csrf-token",
+    "x-xsrf-token",
+];
+
+// 默认替换为占位符的消息体字段和查询参数
+const DEFAULT_REDACTED_FIELDS: [&str; 12] = [
+    "password",
+    "passwd",
+    "secret",
+    "client_secret",
+    "token",
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "api_key",
+    "apikey",
+    "private_key",
+    "session_id",
+];
+
+// 数据离开应用（发给模型、导出）前按名称整体替换的请求头和字段，名称不区分大小写
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionPolicy {
+    pub headers: Vec<String>,
+    // JSON 任意层级的键、表单字段和 URL 查询参数
+    pub body_fields: Vec<String>,
+}
+
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        Self {
+            headers: DEFAULT_REDACTED_HEADERS.iter().map(|h| h.to_string()).collect(),
+            body_fields: DEFAULT_REDACTED_FIELDS.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PiiMatch {
+    kind: PiiKind,
+    start: usize,
+    end: usize,
+}
+
+struct Detector {
+    kind: PiiKind,
+    // 有第一个捕获组时只取该组，用于排除前缀
+    pattern: Regex,
+    validate: fn(&str) -> bool,
+}
+
+// 按优先级排列，重叠的匹配只保留先出现的检测器的结果
+fn detectors() -> &'static [Detector] {
+    static DETECTORS: OnceLock<Vec<Detector>> = OnceLock::new();
+    DETECTORS.get_or_init(|| {
+        let detector = |kind, pattern: &str, validate| Detector {
+            kind,
+            pattern: Regex::new(pattern).expect("valid PII pattern"),
+            validate,
+        };
+        vec![
+            detector(PiiKind::BearerToken, r"(?i)\bbearer\s+([A-Za-z0-9\-._~+/]{8,}=*)", any),
+            detector(PiiKind::BearerToken, r"\b(eyJ[A-Za-z0-9_-]{5,}\.eyJ[A-Za-z0-9_-]{5,}\.[A-Za-z0-9_-]*)", any),
+            detector(PiiKind::Email, r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b", any),
+            detector(PiiKind::NationalId, r"\b([0-9]{3}-[0-9]{2}-[0-9]{4})\b", valid_ssn),
+            detector(PiiKind::NationalId, r"\b([0-9]{17}[0-9Xx])\b", valid_resident_id),
+            detector(PiiKind::CreditCard, r"\b([0-9](?:[ -]?[0-9]){12,18})\b", valid_card),
+            detector(PiiKind::Phone, r"(?:^|[^\w+])(\+[1-9][0-9 .()-]{6,18}[0-9])\b", valid_phone),
+            detector(PiiKind::Phone, r"(?:^|[^\w(])(\([0-9]{3}\) ?[0-9]{3}[-.][0-9]{4})\b", valid_phone),
+            detector(PiiKind::Phone, r"\b([0-9]{3}[-.][0-9]{3}[-.][0-9]{4})\b", valid_phone),
+            // 中国大陆手机号
+            detector(PiiKind::Phone, r"\b(1[3-9][0-9]{9})\b", valid_phone),
+        ]
+    })
+}
+
+fn any(_: &str) -> bool {
+    true
+}
+
+// 美国社会安全号：区号不能为 000、666 或 9xx，组号和序号不能全为零
+fn valid_ssn(text: &str) -> bool {
+    let area = &text[0..3];
+    area != "000" && area != "666" && !area.starts_with('9') && &text[4..6] != "00" && &text[7..11] != "0000"
+}
+
+// 18 位居民身份证号：出生日期有效且校验码符合 ISO 7064 MOD 11-2
+fn valid_resident_id(text: &str) -> bool {
+    const WEIGHTS: [u32; 17] = [7, 9, 10, 5, 8, 4, 2, 1, 6, 3, 7, 9, 10, 5, 8, 4, 2];
+    let bytes = text.as_bytes();
+    let sum: u32 = bytes[..17].iter().zip(WEIGHTS).map(|(b, w)| (b - b'0') as u32 * w).sum();
+    let check = b"10X98765432"[(sum % 11) as usize];
+    bytes[17].to_ascii_uppercase() == check && chrono::NaiveDate::parse_from_str(&text[6..14], "%Y%m%d").is_ok()
+}
+
+// 13 到 19 位、属于已知发卡组织号段且通过 Luhn 校验；号段限制可排除以 1 开头的毫秒时间戳
+fn valid_card(text: &str) -> bool {
+    let digits: Vec<u32> = text.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let known_issuer = matches!((digits[0], digits[1]), (4, _) | (6, _) | (5, 1..=5) | (2, 2..=7) | (3, 4..=8));
+    known_issuer && luhn(&digits)
+}
+
+fn luhn(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (0, _) => d,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn valid_phone(text: &str) -> bool {
+    (7..=15).contains(&text.chars().filter(char::is_ascii_digit).count())
+}
+
+fn find(text: &str) -> Vec<PiiMatch> {
+    let mut matches: Vec<PiiMatch> = Vec::new();
+    for detector in detectors() {
+        for captures in detector.pattern.captures_iter(text) {
+            let Some(span) = captures.get(1).or_else(|| captures.get(0)) else {
+                continue;
+            };
+            let (start, end) = (span.start(), span.end());
+            if matches.iter().any(|m| m.start < end && start < m.end) || !(detector.validate)(span.as_str()) {
+                continue;
+            }
+            matches.push(PiiMatch { kind: detector.kind, start, end });
+        }
+    }
+    matches.sort_by_key(|m| m.start);
+    matches
+}
+
+// 按字符边界截取开头部分
+pub fn scan_prefix(text: &str) -> &str {
+    let mut end = text.len().min(MAX_SCAN_BYTES);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+fn mask_value(kind: PiiKind, value: &str, style: MaskStyle) -> String {
+    if style == MaskStyle::Full {
+        return format!("[redacted:{}]", kind.name());
+    }
+    match kind {
+        PiiKind::Email => match value.split_once('@') {
+            Some((local, domain)) => format!("{}***@{}", local.chars().next().unwrap_or('*'), domain),
+            None => "***".to_string(),
+        },
+        // 令牌即使只保留几位也可能被用来关联会话，一律整体替换
+        PiiKind::BearerToken => format!("[redacted:{}]", kind.name()),
+        // 保留分隔符和末四位
+        PiiKind::CreditCard | PiiKind::Phone | PiiKind::NationalId => {
+            let keep_from = value.char_indices().rev().filter(|(_, c)| c.is_ascii_alphanumeric()).nth(3).map(|(i, _)| i);
+            value
+                .char_indices()
+                .map(|(i, c)| if c.is_ascii_alphanumeric() && keep_from.is_some_and(|k| i < k) { '*' } else { c })
+                .collect()
+        }
+    }
+}
+
+// 改写会离开应用的全部报文：主请求与响应、改写前各阶段的请求及其变更记录、shadow 响应
+fn rewrite(
+    transaction: &HttpTransaction,
+    request: impl Fn(&mut HttpRequest),
+    response: impl Fn(&mut HttpResponse),
+    change: impl Fn(&mut FieldChange),
+) -> HttpTransaction {
+    let mut rewritten = transaction.clone();
+    request(&mut rewritten.request);
+    if let Some(r) = rewritten.response.as_mut() {
+        response(r);
+    }
+    for stage in &mut rewritten.modifications {
+        request(&mut stage.request);
+        stage.changes.iter_mut().for_each(&change);
+    }
+    if let Some(r) = rewritten.shadow.as_mut().and_then(|s| s.response.as_mut()) {
+        response(r);
+    }
+    rewritten
+}
+
+// 扫描 URL、请求/响应头和文本消息体，相同位置的同类匹配合并为一条
+pub fn scan(transaction: &HttpTransaction) -> Vec<PiiFinding> {
+    let mut findings: BTreeMap<(PiiLocation, Option<String>, PiiKind), PiiFinding> = BTreeMap::new();
+    let mut record = |location: PiiLocation, header: Option<&str>, text: &str| {
+        for m in find(scan_prefix(text)) {
+            findings
+                .entry((location, header.map(str::to_string), m.kind))
+                .and_modify(|f| f.count += 1)
+                .or_insert_with(|| PiiFinding {
+                    kind: m.kind,
+                    location,
+                    header: header.map(str::to_string),
+                    count: 1,
+                    preview: mask_value(m.kind, &text[m.start..m.end], MaskStyle::Partial),
+                });
+        }
+    };
+    let request = &transaction.request;
+    record(PiiLocation::Url, None, &request.url);
+    for (name, value) in &request.headers {
+        record(PiiLocation::RequestHeader, Some(name), value);
+    }
+    if let Ok(body) = std::str::from_utf8(&request.body) {
+        record(PiiLocation::RequestBody, None, body);
+    }
+    if let Some(response) = &transaction.response {
+        for (name, value) in &response.headers {
+            record(PiiLocation::ResponseHeader, Some(name), value);
+        }
+        if let Ok(body) = std::str::from_utf8(&response.body) {
+            record(PiiLocation::ResponseBody, None, body);
+        }
+    }
+    findings.into_values().collect()
+}
+
+impl MaskingSettings {
+    pub fn applies_to(&self, target: MaskTarget) -> bool {
+        !self.kinds.is_empty()
+            && match target {
+                MaskTarget::Export => self.exports,
+                MaskTarget::AiPrompt => self.ai_prompts,
+                MaskTarget::Ui => self.ui,
+            }
+    }
+
+    pub fn mask_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let matches: Vec<PiiMatch> = find(text).into_iter().filter(|m| self.kinds.contains(&m.kind)).collect();
+        if matches.is_empty() {
+            return Cow::Borrowed(text);
+        }
+        let mut masked = String::with_capacity(text.len());
+        let mut last = 0;
+        for m in matches {
+            masked.push_str(&text[last..m.start]);
+            masked.push_str(&mask_value(m.kind, &text[m.start..m.end], self.style));
+            last = m.end;
+        }
+        masked.push_str(&text[last..]);
+        Cow::Owned(masked)
+    }
+
+    // 非 UTF-8 的消息体按二进制处理，保持原样
+    fn mask_body(&self, body: &mut Vec<u8>) {
+        if let Ok(text) = std::str::from_utf8(body) {
+            if let Cow::Owned(masked) = self.mask_text(text) {
+                *body = masked.into_bytes();
+            }
+        }
+    }
+
+    fn mask_string(&self, text: &mut String) {
+        if let Cow::Owned(masked) = self.mask_text(text) {
+            *text = masked;
+        }
+    }
+
+    fn mask_headers(&self, headers: &mut HashMap<String, String>) {
+        for value in headers.values_mut() {
+            self.mask_string(value);
+        }
+    }
+
+    fn mask_request(&self, request: &mut HttpRequest) {
+        self.mask_string(&mut request.url);
+        self.mask_headers(&mut request.headers);
+        self.mask_body(&mut request.body);
+    }
+
+    fn mask_response(&self, response: &mut HttpResponse) {
+        self.mask_headers(&mut response.headers);
+        self.mask_body(&mut response.body);
+    }
+
+    pub fn mask_transaction(&self, transaction: &HttpTransaction) -> HttpTransaction {
+        rewrite(
+            transaction,
+            |request| self.mask_request(request),
+            |response| self.mask_response(response),
+            |change| {
+                for value in [&mut change.before, &mut change.after].into_iter().flatten() {
+                    self.mask_string(value);
+                }
+            },
+        )
+    }
+
+    // 不需要遮盖时原样返回
+    pub fn view(&self, transaction: HttpTransaction, target: MaskTarget) -> HttpTransaction {
+        if self.applies_to(target) {
+            self.mask_transaction(&transaction)
+        } else {
+            transaction
+        }
+    }
+}
+
+impl RedactionPolicy {
+    // 名称统一为小写，去掉空项和重复项
+    pub fn normalized(self) -> Self {
+        let normalize = |names: Vec<String>| {
+            let mut names: Vec<String> =
+                names.into_iter().map(|n| n.trim().to_lowercase()).filter(|n| !n.is_empty()).collect();
+            names.sort();
+            names.dedup();
+            names
+        };
+        Self { headers: normalize(self.headers), body_fields: normalize(self.body_fields) }
+    }
+
+    pub fn redacts_header(&self, name: &str) -> bool {
+        self.headers.iter().any(|h| h.eq_ignore_ascii_case(name))
+    }
+
+    fn redacts_field(&self, name: &str) -> bool {
+        self.body_fields.iter().any(|f| f.eq_ignore_ascii_case(name))
+    }
+
+    fn redact_headers(&self, headers: &mut HashMap<String, String>) {
+        for (name, value) in headers.iter_mut() {
+            if self.redacts_header(name) {
+                *value = REDACTED_PLACEHOLDER.to_string();
+            }
+        }
+    }
+
+    // 返回是否有字段被替换
+    fn redact_json(&self, value: &mut Value) -> bool {
+        match value {
+            Value::Object(object) => {
+                let mut changed = false;
+                for (key, value) in object.iter_mut() {
+                    if self.redacts_field(key) {
+                        *value = Value::String(REDACTED_PLACEHOLDER.to_string());
+                        changed = true;
+                    } else {
+                        changed |= self.redact_json(value);
+                    }
+                }
+                changed
+            }
+            Value::Array(items) => items.iter_mut().fold(false, |changed, item| self.redact_json(item) | changed),
+            _ => false,
+        }
+    }
+
+    // a=1&b=2 形式的查询串或表单，只替换命中字段的值，其余部分保持原样
+    fn redact_pairs(&self, text: &str) -> Option<String> {
+        let mut changed = false;
+        let pairs: Vec<String> = text
+            .split('&')
+            .map(|pair| {
+                let Some((name, _)) = pair.split_once('=') else {
+                    return pair.to_string();
+                };
+                let decoded: String = url::form_urlencoded::parse(name.as_bytes()).map(|(key, _)| key).collect();
+                if self.redacts_field(&decoded) {
+                    changed = true;
+                    format!("{}={}", name, REDACTED_PLACEHOLDER)
+                } else {
+                    pair.to_string()
+                }
+            })
+            .collect();
+        changed.then(|| pairs.join("&"))
+    }
+
+    fn redact_url(&self, url: &mut String) {
+        let Some((base, rest)) = url.split_once('?') else {
+            return;
+        };
+        let (query, fragment) = match rest.split_once('#') {
+            Some((query, fragment)) => (query, Some(fragment)),
+            None => (rest, None),
+        };
+        if let Some(query) = self.redact_pairs(query) {
+            *url = match fragment {
+                Some(fragment) => format!("{}?{}#{}", base, query, fragment),
+                None => format!("{}?{}", base, query),
+            };
+        }
+    }
+
+    // 只处理 JSON 和 urlencoded 表单；JSON 改写后键顺序可能变化
+    fn redact_body(&self, body: &mut Vec<u8>, headers: &HashMap<String, String>) {
+        match classify::classify(body, find_header(headers, "content-type")) {
+            BodyKind::Json | BodyKind::GraphQl => {
+                if let Ok(mut value) = serde_json::from_slice::<Value>(body) {
+                    if self.redact_json(&mut value) {
+                        *body = serde_json::to_vec(&value).unwrap_or_default();
+                    }
+                }
+            }
+            BodyKind::FormUrlEncoded => {
+                if let Some(redacted) = std::str::from_utf8(body).ok().and_then(|text| self.redact_pairs(text)) {
+                    *body = redacted.into_bytes();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn redact_change(&self, change: &mut FieldChange) {
+        let redact_header = change.field.strip_prefix("header:").is_some_and(|name| self.redacts_header(name));
+        for value in [&mut change.before, &mut change.after].into_iter().flatten() {
+            if redact_header {
+                *value = REDACTED_PLACEHOLDER.to_string();
+            } else if change.field == "url" {
+                self.redact_url(value);
+            } else if change.field == "body" {
+                if let Ok(mut json) = serde_json::from_str::<Value>(value) {
+                    if self.redact_json(&mut json) {
+                        *value = json.to_string();
+                    }
+                }
+            }
+        }
+    }
+
+    // 扫描结果的证据会引用参数值（过长时只有开头部分），字段名命中的参数值替换为占位符
+    pub fn redact_evidence(&self, evidence: &str, params: &[Parameter]) -> String {
+        let mut evidence = evidence.to_string();
+        for param in params.iter().filter(|p| !p.value.is_empty() && self.redacts_field(p.key())) {
+            let shown: String = param.value.chars().take(MAX_EVIDENCE_CHARS).collect();
+            evidence = evidence.replace(&param.value, REDACTED_PLACEHOLDER).replace(&shown, REDACTED_PLACEHOLDER);
+        }
+        evidence
+    }
+
+    pub fn apply(&self, transaction: &HttpTransaction) -> HttpTransaction {
+        rewrite(
+            transaction,
+            |request| {
+                self.redact_url(&mut request.url);
+                self.redact_body(&mut request.body, &request.headers);
+                self.redact_headers(&mut request.headers);
+            },
+            |response| {
+                self.redact_body(&mut response.body, &response.headers);
+                self.redact_headers(&mut response.headers);
+            },
+            |change| self.redact_change(change),
+        )
+    }
+}
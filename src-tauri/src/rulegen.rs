@@ -0,0 +1,98 @@
+use crate::ai_analyzer::AIAnalyzer;
+use crate::llm::{self, ChatMessage};
+use crate::proxy::RequestRule;
+use crate::rules;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const RULEGEN_SYSTEM_PROMPT: &str = r#"把用户对代理规则的自然语言描述翻译为规则。只输出一个 JSON 对象：
+{"rules": [规则, ...], "notes": [无法表达或有歧义之处的说明, ...]}
+每条规则：
+{
+  "name": string,                  // 简短的中文名称
+  "matcher": 匹配条件,
+  "action": 动作,
+  "priority": number,              // 可选，越大越先匹配
+  "stop_processing": boolean       // 可选，命中后不再匹配后续规则
+}
+匹配条件（type 字段区分）：
+  {"type": "url", "pattern": string, "regex": boolean}   // regex 为 false 时含 * 按通配符匹配整个 URL，否则按子串匹配
+  {"type": "method", "methods": ["GET", ...]}
+  {"type": "header", "name": string, "value": string, "regex": boolean, "response": boolean}   // value 可省略，只要求头存在
+  {"type": "body_contains", "text": string, "response": boolean}
+  {"type": "status", "codes": [number], "min": number, "max": number}   // 响应状态码
+  {"type": "all", "matchers": [...]} / {"type": "any", "matchers": [...]} / {"type": "not", "matcher": {...}}
+动作（只能是以下之一）：
+  "Block"
+  {"Redirect": {"target": 完整 URL}}
+  {"Mock": {"response": 响应体，或以 "HTTP/1.1 状态码" 开头的完整响应文本}}
+  {"MapRemote": {"target_host": string, "target_port": number, "target_scheme": "http"|"https", "preserve_path": boolean}}
+  {"Delay": {"ms": number, "jitter_ms": number}}
+  {"Rewrite": {"script": rhai 脚本, "phase": "request"|"response"|"both"}}   // 脚本中可读写 request/response 变量的 method、url、headers、body、status
+描述中的每个独立意图生成一条规则，不要添加用户没有要求的规则。"#;
+
+// 模型生成但未通过校验的规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectedRule {
+    pub rule: Value,
+    pub error: String,
+}
+
+// 待用户确认的规则，确认前不会加入规则列表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleProposal {
+    pub text: String,
+    pub rules: Vec<RequestRule>,
+    pub rejected: Vec<RejectedRule>,
+    pub notes: Vec<String>,
+    pub model: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ModelRules {
+    #[serde(default)]
+    rules: Vec<Value>,
+    #[serde(default)]
+    notes: Vec<String>,
+}
+
+pub async fn propose_rules(analyzer: &AIAnalyzer, text: &str) -> Result<RuleProposal> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(anyhow!("Rule description is empty"));
+    }
+    let content = analyzer
+        .complete(RULEGEN_SYSTEM_PROMPT, &[ChatMessage::user(text)], true, None)
+        .await?
+        .ok_or_else(|| anyhow!("No AI provider is configured; set an API key or a local model first"))?;
+    let (rules, rejected, notes) = parse_rules(&content)?;
+    Ok(RuleProposal { text: text.to_string(), rules, rejected, notes, model: analyzer.model_name().to_string() })
+}
+
+// 逐条补全 id 与启用状态后校验，单条无效不影响其余规则
+pub fn parse_rules(content: &str) -> Result<(Vec<RequestRule>, Vec<RejectedRule>, Vec<String>)> {
+    let parsed: ModelRules = serde_json::from_str(llm::extract_json(content)?)
+        .map_err(|e| anyhow!("Model returned malformed rules: {}", e))?;
+    let (mut rules, mut rejected) = (Vec::new(), Vec::new());
+    for raw in parsed.rules {
+        match build_rule(raw.clone()) {
+            Ok(rule) => rules.push(rule),
+            Err(e) => rejected.push(RejectedRule { rule: raw, error: e.to_string() }),
+        }
+    }
+    if rules.is_empty() && rejected.is_empty() {
+        return Err(anyhow!("Model did not produce any rules"));
+    }
+    Ok((rules, rejected, parsed.notes))
+}
+
+fn build_rule(mut raw: Value) -> Result<RequestRule> {
+    let object = raw.as_object_mut().ok_or_else(|| anyhow!("Rule is not a JSON object"))?;
+    object.insert("id".to_string(), Value::String(uuid::Uuid::new_v4().to_string()));
+    object.insert("enabled".to_string(), Value::Bool(true));
+    object.entry("name").or_insert_with(|| Value::String("未命名规则".to_string()));
+    let rule: RequestRule = serde_json::from_value(raw)?;
+    rules::validate(&rule)?;
+    Ok(rule)
+}
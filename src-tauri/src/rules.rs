@@ -0,0 +1,687 @@
+use crate::decoding;
+use crate::search;
+use crate::modifications::ModificationHistory;
+use crate::proxy::{find_header, HttpRequest, HttpResponse, RequestRule, RuleAction};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+// 改写脚本的沙箱限制
+const SCRIPT_MAX_OPERATIONS: u64 = 100_000;
+
+pub const SCRIPT_ERROR_EVENT: &str = "rule:script-error";
+
+// 改写脚本执行的阶段；响应阶段脚本可读取请求但对请求的修改不生效
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptPhase {
+    #[default]
+    Request,
+    Response,
+    Both,
+}
+
+impl ScriptPhase {
+    fn includes(self, phase: ScriptPhase) -> bool {
+        self == ScriptPhase::Both || self == phase
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ScriptPhase::Request => "request",
+            ScriptPhase::Response => "response",
+            ScriptPhase::Both => "both",
+        }
+    }
+}
+
+// 脚本执行失败时通知界面，事务照常转发
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptError {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub phase: ScriptPhase,
+    pub url: String,
+    pub message: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl ScriptError {
+    fn new(rule: &RequestRule, phase: ScriptPhase, url: &str, error: anyhow::Error) -> Self {
+        warn!("Rewrite rule '{}' failed in {} phase: {}", rule.name, phase.name(), error);
+        Self {
+            rule_id: rule.id.clone(),
+            rule_name: rule.name.clone(),
+            phase,
+            url: url.to_string(),
+            message: error.to_string(),
+            timestamp: chrono::Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RuleEvaluation {
+    // 按执行顺序记录生效的规则 id
+    pub applied_rules: Vec<String>,
+    // Block、Mock 与 MapLocal 直接给出响应，不再转发
+    pub response: Option<HttpResponse>,
+    // 所有命中的 Delay 规则累加的延迟
+    pub delay: Duration,
+    pub script_errors: Vec<ScriptError>,
+}
+
+// 脚本中 `request` 变量的结构
+#[derive(Debug, Serialize, Deserialize)]
+struct ScriptRequest {
+    method: String,
+    url: String,
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+// 脚本中 `response` 变量的结构，body 为解开 Content-Encoding 后的内容
+#[derive(Debug, Serialize, Deserialize)]
+struct ScriptResponse {
+    status: u16,
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+// 规则的匹配条件；旧版本的 pattern 字符串按 URL 通配/子串匹配反序列化为 Pattern
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleMatcher {
+    // regex 为 false 时与 Pattern 相同，按通配符或子串匹配
+    Url {
+        pattern: String,
+        #[serde(default)]
+        regex: bool,
+    },
+    Method { methods: Vec<String> },
+    // 未给出 value 时只要求头存在；value 按子串或正则匹配，均不区分大小写
+    Header {
+        name: String,
+        #[serde(default)]
+        value: Option<String>,
+        #[serde(default)]
+        regex: bool,
+        #[serde(default)]
+        response: bool,
+    },
+    BodyContains {
+        text: String,
+        #[serde(default)]
+        response: bool,
+    },
+    // 命中 codes 中任一状态码，或落在 [min, max] 区间内
+    Status {
+        #[serde(default)]
+        codes: Vec<u16>,
+        #[serde(default)]
+        min: Option<u16>,
+        #[serde(default)]
+        max: Option<u16>,
+    },
+    All { matchers: Vec<RuleMatcher> },
+    Any { matchers: Vec<RuleMatcher> },
+    Not { matcher: Box<RuleMatcher> },
+    #[serde(untagged)]
+    Pattern(String),
+}
+
+impl RuleMatcher {
+    // 涉及响应的条件在请求阶段不成立，这类规则只在响应阶段生效
+    pub fn matches(&self, request: &HttpRequest, response: Option<&HttpResponse>) -> bool {
+        match self {
+            Self::Pattern(pattern) => matches(pattern, &request.url),
+            Self::Url { pattern, regex: false } => matches(pattern, &request.url),
+            Self::Url { pattern, regex: true } => regex_matches(pattern, &request.url),
+            Self::Method { methods } => methods.iter().any(|m| m.eq_ignore_ascii_case(&request.method)),
+            Self::Header { name, value, regex, response: in_response } => {
+                let headers = if *in_response {
+                    match response {
+                        Some(response) => &response.headers,
+                        None => return false,
+                    }
+                } else {
+                    &request.headers
+                };
+                let Some(actual) = find_header(headers, name) else {
+                    return false;
+                };
+                match value {
+                    None => true,
+                    Some(value) if *regex => regex_matches(value, actual),
+                    Some(value) => actual.to_lowercase().contains(&value.to_lowercase()),
+                }
+            }
+            Self::BodyContains { text, response: in_response } => {
+                let body = if *in_response {
+                    match response {
+                        Some(response) => decoded_body(&response.headers, &response.body),
+                        None => return false,
+                    }
+                } else {
+                    decoded_body(&request.headers, &request.body)
+                };
+                String::from_utf8_lossy(&body).to_lowercase().contains(&text.to_lowercase())
+            }
+            Self::Status { codes, min, max } => {
+                let Some(status) = response.map(|r| r.status) else {
+                    return false;
+                };
+                let in_range = (min.is_some() || max.is_some())
+                    && min.map(|min| status >= min).unwrap_or(true)
+                    && max.map(|max| status <= max).unwrap_or(true);
+                codes.contains(&status) || in_range
+            }
+            Self::All { matchers } => matchers.iter().all(|m| m.matches(request, response)),
+            Self::Any { matchers } => matchers.iter().any(|m| m.matches(request, response)),
+            Self::Not { matcher } => !matcher.matches(request, response),
+        }
+    }
+
+    // 是否含有依赖响应的条件
+    pub fn needs_response(&self) -> bool {
+        match self {
+            Self::Header { response, .. } | Self::BodyContains { response, .. } => *response,
+            Self::Status { .. } => true,
+            Self::All { matchers } | Self::Any { matchers } => matchers.iter().any(Self::needs_response),
+            Self::Not { matcher } => matcher.needs_response(),
+            Self::Pattern(_) | Self::Url { .. } | Self::Method { .. } => false,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self {
+            Self::Pattern(pattern) | Self::Url { pattern, regex: false } => {
+                pattern_regex(pattern)?;
+            }
+            Self::Url { pattern, regex: true } | Self::Header { value: Some(pattern), regex: true, .. } => {
+                search::cached_regex(pattern, false)?;
+            }
+            Self::Method { methods } if methods.is_empty() => {
+                return Err(anyhow!("Method condition needs at least one method"));
+            }
+            Self::Status { codes, min: None, max: None } if codes.is_empty() => {
+                return Err(anyhow!("Status condition needs codes or a range"));
+            }
+            Self::All { matchers } | Self::Any { matchers } => {
+                for matcher in matchers {
+                    matcher.validate()?;
+                }
+            }
+            Self::Not { matcher } => matcher.validate()?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // 命令面板等处展示的简短描述
+    pub fn summary(&self) -> String {
+        match self {
+            Self::Pattern(pattern) | Self::Url { pattern, regex: false } => pattern.clone(),
+            Self::Url { pattern, regex: true } => format!("/{}/", pattern),
+            Self::Method { methods } => methods.join("|"),
+            Self::Header { name, value, .. } => match value {
+                Some(value) => format!("{}: {}", name, value),
+                None => name.clone(),
+            },
+            Self::BodyContains { text, .. } => format!("body ~ {}", text),
+            Self::Status { codes, min, max } => {
+                let mut parts: Vec<String> = codes.iter().map(u16::to_string).collect();
+                if min.is_some() || max.is_some() {
+                    parts.push(format!(
+                        "{}-{}",
+                        min.map(|m| m.to_string()).unwrap_or_default(),
+                        max.map(|m| m.to_string()).unwrap_or_default()
+                    ));
+                }
+                format!("status {}", parts.join(","))
+            }
+            Self::All { matchers } => matchers.iter().map(Self::summary).collect::<Vec<_>>().join(" AND "),
+            Self::Any { matchers } => matchers.iter().map(Self::summary).collect::<Vec<_>>().join(" OR "),
+            Self::Not { matcher } => format!("NOT ({})", matcher.summary()),
+        }
+    }
+}
+
+fn regex_matches(pattern: &str, text: &str) -> bool {
+    search::cached_regex(pattern, false).map(|regex| regex.is_match(text)).unwrap_or(false)
+}
+
+// 注册规则时检查匹配条件和脚本，避免转发时才发现错误
+pub fn validate(rule: &RequestRule) -> Result<()> {
+    rule.matcher.validate()?;
+    match &rule.action {
+        RuleAction::Rewrite { script, .. } => {
+            rhai::Engine::new()
+                .compile(script)
+                .map_err(|e| anyhow!("Script error: {}", e))?;
+        }
+        RuleAction::MapLocal { path, .. } if path.trim().is_empty() => {
+            return Err(anyhow!("Map Local path must not be empty"));
+        }
+        RuleAction::MapRemote { target_host, target_port, target_scheme, preserve_path } => {
+            map_remote("http://localhost/", target_host, *target_port, target_scheme.as_deref(), *preserve_path)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// 模式中含 * 时按通配符匹配整个 URL，否则按子串匹配（均不区分大小写）
+pub fn matches(pattern: &str, url: &str) -> bool {
+    match pattern_regex(pattern) {
+        Ok(Some(regex)) => regex.is_match(url),
+        Ok(None) => url.to_lowercase().contains(&pattern.to_lowercase()),
+        Err(_) => false,
+    }
+}
+
+fn pattern_regex(pattern: &str) -> Result<Option<Regex>> {
+    if !pattern.contains('*') {
+        return Ok(None);
+    }
+    let glob = pattern
+        .split('*')
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join(".*");
+    Ok(Some(Regex::new(&format!("(?i)^{}$", glob))?))
+}
+
+// 启用的规则按优先级从高到低排列，相同优先级保持列表顺序
+// 规则匹配时消息体尚未解码，按 Content-Encoding 解码后再查找；无法解码时按原始字节匹配
+fn decoded_body<'a>(headers: &HashMap<String, String>, body: &'a [u8]) -> Cow<'a, [u8]> {
+    match find_header(headers, "content-encoding") {
+        Some(encoding) => decoding::decode(body, Some(encoding)).map(Cow::Owned).unwrap_or(Cow::Borrowed(body)),
+        None => Cow::Borrowed(body),
+    }
+}
+
+fn ordered(rules: &[RequestRule]) -> Vec<&RequestRule> {
+    let mut ordered: Vec<&RequestRule> = rules.iter().filter(|r| r.enabled).collect();
+    ordered.sort_by_key(|r| std::cmp::Reverse(r.priority));
+    ordered
+}
+
+// 按优先级依次执行规则：Redirect、MapRemote、Rewrite 与 Delay 生效后继续匹配后续规则，
+// Block、Mock 与 MapLocal 命中即终止；设置了 stop_processing 的规则生效后同样终止
+pub async fn apply(rules: &[RequestRule], request: &mut HttpRequest, history: &mut ModificationHistory) -> RuleEvaluation {
+    let mut evaluation = RuleEvaluation::default();
+    for rule in ordered(rules) {
+        // 依赖响应的条件（包括 Not、Any 中嵌套的）在请求阶段无法判断，交给响应阶段
+        if rule.matcher.needs_response() || !rule.matcher.matches(request, None) {
+            continue;
+        }
+        match &rule.action {
+            RuleAction::Block | RuleAction::Mock { .. } | RuleAction::MapLocal { .. } => {
+                evaluation.response = terminal_response(rule, request).await;
+            }
+            RuleAction::MapRemote { target_host, target_port, target_scheme, preserve_path } => {
+                // 改写前后的 URL 由修改历史保存
+                match map_remote(&request.url, target_host, *target_port, target_scheme.as_deref(), *preserve_path) {
+                    Ok(url) => request.url = url,
+                    Err(e) => {
+                        warn!("Map Remote rule '{}' failed: {}", rule.name, e);
+                        continue;
+                    }
+                }
+            }
+            RuleAction::Redirect { target } => {
+                request.url = target.clone();
+            }
+            RuleAction::Rewrite { script, phase } => {
+                // 仅在响应阶段执行的脚本此时不算生效
+                if !phase.includes(ScriptPhase::Request) {
+                    continue;
+                }
+                // 脚本出错时保留原请求继续转发
+                if let Err(e) = rewrite_request(script, request) {
+                    let error = ScriptError::new(rule, ScriptPhase::Request, &request.url, e);
+                    evaluation.script_errors.push(error);
+                    continue;
+                }
+            }
+            RuleAction::Delay { ms, jitter_ms } => {
+                evaluation.delay += jittered_delay(*ms, *jitter_ms);
+            }
+        }
+        history.record(format!("rule:{}", rule.id), request);
+        evaluation.applied_rules.push(rule.id.clone());
+        if evaluation.response.is_some() || rule.stop_processing {
+            break;
+        }
+    }
+    evaluation
+}
+
+// Block、Mock 与 MapLocal 由代理直接给出的响应
+async fn terminal_response(rule: &RequestRule, request: &HttpRequest) -> Option<HttpResponse> {
+    match &rule.action {
+        RuleAction::Block => Some(synthetic_response(
+            403,
+            "text/plain",
+            format!("Blocked by rule: {}", rule.name).into_bytes(),
+        )),
+        RuleAction::Mock { response } => Some(mock_response(response)),
+        RuleAction::MapLocal { path, content_type } => Some(map_local(path, content_type.as_deref(), &request.url).await),
+        _ => None,
+    }
+}
+
+// 收到响应后按优先级执行响应阶段的规则，按实际发出的请求匹配；返回生效的规则 id。
+// 响应阶段执行改写脚本，以及匹配条件依赖响应（如状态码）的 Block、Mock 与 MapLocal，后者替换上游响应后终止
+fn runs_in_response(rule: &RequestRule) -> bool {
+    match &rule.action {
+        RuleAction::Rewrite { phase, .. } => phase.includes(ScriptPhase::Response),
+        RuleAction::Block | RuleAction::Mock { .. } | RuleAction::MapLocal { .. } => rule.matcher.needs_response(),
+        _ => false,
+    }
+}
+
+// 转发前判断响应阶段是否可能有规则执行；依赖响应的条件此时无法判断，按可能命中处理
+pub fn may_apply_response(rules: &[RequestRule], request: &HttpRequest) -> bool {
+    ordered(rules).into_iter().any(|rule| {
+        runs_in_response(rule) && (rule.matcher.needs_response() || rule.matcher.matches(request, None))
+    })
+}
+
+pub async fn apply_response(
+    rules: &[RequestRule],
+    request: &HttpRequest,
+    response: &mut HttpResponse,
+    errors: &mut Vec<ScriptError>,
+) -> Vec<String> {
+    let mut applied = Vec::new();
+    for rule in ordered(rules) {
+        if !runs_in_response(rule) || !rule.matcher.matches(request, Some(response)) {
+            continue;
+        }
+        if let RuleAction::Rewrite { script, .. } = &rule.action {
+            match rewrite_response(script, request, response) {
+                Ok(()) => applied.push(rule.id.clone()),
+                Err(e) => errors.push(ScriptError::new(rule, ScriptPhase::Response, &request.url, e)),
+            }
+        } else if let Some(replacement) = terminal_response(rule, request).await {
+            *response = replacement;
+            applied.push(rule.id.clone());
+            break;
+        }
+        if rule.stop_processing {
+            break;
+        }
+    }
+    applied
+}
+
+fn jittered_delay(ms: u64, jitter_ms: u64) -> Duration {
+    if jitter_ms == 0 {
+        return Duration::from_millis(ms);
+    }
+    // 借用 v4 UUID 的随机位，在 [ms - jitter_ms, ms + jitter_ms] 内均匀取值
+    let offset = (uuid::Uuid::new_v4().as_u128() % (jitter_ms as u128 * 2 + 1)) as u64;
+    Duration::from_millis(ms.saturating_add(offset).saturating_sub(jitter_ms))
+}
+
+fn script_engine() -> rhai::Engine {
+    let mut engine = rhai::Engine::new();
+    engine.set_max_operations(SCRIPT_MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine
+}
+
+fn script_request(request: &HttpRequest) -> ScriptRequest {
+    ScriptRequest {
+        method: request.method.clone(),
+        url: request.url.clone(),
+        headers: request.headers.clone(),
+        body: String::from_utf8_lossy(&request.body).to_string(),
+    }
+}
+
+fn push_script_value<T: Serialize>(scope: &mut rhai::Scope, name: &'static str, value: &T) -> Result<()> {
+    let value = rhai::serde::to_dynamic(value).map_err(|e| anyhow!("Script error: {}", e))?;
+    scope.push_dynamic(name, value);
+    Ok(())
+}
+
+fn take_script_value<T: serde::de::DeserializeOwned>(scope: &rhai::Scope, name: &str) -> Result<T> {
+    let value = scope
+        .get_value::<rhai::Dynamic>(name)
+        .ok_or_else(|| anyhow!("Script removed the {} variable", name))?;
+    rhai::serde::from_dynamic(&value).map_err(|e| anyhow!("Script error: {}", e))
+}
+
+// 脚本通过 `request` 变量读写 method、url、headers 和 body，`phase` 为 "request"
+fn rewrite_request(script: &str, request: &mut HttpRequest) -> Result<()> {
+    let input = script_request(request);
+    let original_body = input.body.clone();
+    let mut scope = rhai::Scope::new();
+    push_script_value(&mut scope, "request", &input)?;
+    scope.push_constant("phase", ScriptPhase::Request.name());
+
+    script_engine()
+        .run_with_scope(&mut scope, script)
+        .map_err(|e| anyhow!("Script error: {}", e))?;
+
+    let output: ScriptRequest = take_script_value(&scope, "request")?;
+
+    request.method = output.method;
+    request.url = output.url;
+    request.headers = output.headers;
+    // 未改动的二进制请求体不经过字符串转换
+    if output.body != original_body {
+        request.body = output.body.into_bytes();
+    }
+    Ok(())
+}
+
+// 脚本通过 `response` 变量读写 status、headers 和 body，`phase` 为 "response"
+fn rewrite_response(script: &str, request: &HttpRequest, response: &mut HttpResponse) -> Result<()> {
+    let content_encoding = find_header(&response.headers, "content-encoding");
+    // 无法解码时按原始字节交给脚本
+    let decoded = decoding::decode(&response.body, content_encoding).ok();
+    let original_body = String::from_utf8_lossy(decoded.as_deref().unwrap_or(&response.body)).to_string();
+    let input = ScriptResponse {
+        status: response.status,
+        headers: response.headers.clone(),
+        body: original_body.clone(),
+    };
+    let mut scope = rhai::Scope::new();
+    push_script_value(&mut scope, "request", &script_request(request))?;
+    push_script_value(&mut scope, "response", &input)?;
+    scope.push_constant("phase", ScriptPhase::Response.name());
+
+    script_engine()
+        .run_with_scope(&mut scope, script)
+        .map_err(|e| anyhow!("Script error: {}", e))?;
+
+    let output: ScriptResponse = take_script_value(&scope, "response")?;
+    response.status = output.status;
+    response.headers = output.headers;
+    if output.body != original_body {
+        // 改写后的响应体以明文返回，去掉原有的编码声明
+        response.headers.retain(|name, _| !name.eq_ignore_ascii_case("content-encoding"));
+        response.body = output.body.into_bytes();
+    }
+    Ok(())
+}
+
+// 以 "HTTP/" 开头时按完整响应报文解析状态码和响应头，否则整体作为 200 响应体
+fn mock_response(spec: &str) -> HttpResponse {
+    if !spec.starts_with("HTTP/") {
+        let content_type = if serde_json::from_str::<serde_json::Value>(spec).is_ok() {
+            "application/json"
+        } else {
+            "text/plain"
+        };
+        return synthetic_response(200, content_type, spec.as_bytes().to_vec());
+    }
+
+    let (head, body) = spec
+        .split_once("\r\n\r\n")
+        .or_else(|| spec.split_once("\n\n"))
+        .unwrap_or((spec, ""));
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .unwrap_or(200);
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    HttpResponse {
+        status,
+        headers,
+        body: body.as_bytes().to_vec(),
+        timestamp: chrono::Utc::now(),
+        version: None,
+    }
+}
+
+fn map_remote(
+    url: &str,
+    host: &str,
+    port: Option<u16>,
+    scheme: Option<&str>,
+    preserve_path: bool,
+) -> Result<String> {
+    let mut url = url::Url::parse(url)?;
+    if let Some(scheme) = scheme {
+        let scheme = scheme.to_lowercase();
+        if scheme != "http" && scheme != "https" {
+            return Err(anyhow!("Unsupported Map Remote scheme: {}", scheme));
+        }
+        url.set_scheme(&scheme).map_err(|_| anyhow!("Cannot change scheme to {}", scheme))?;
+    }
+    url.set_host(Some(host.trim())).map_err(|e| anyhow!("Invalid Map Remote host '{}': {}", host, e))?;
+    // 未指定端口时使用协议默认端口，不沿用原 URL 的端口
+    url.set_port(port).map_err(|_| anyhow!("Cannot set port on {}", url))?;
+    if !preserve_path {
+        url.set_path("/");
+        url.set_query(None);
+    }
+    Ok(url.to_string())
+}
+
+// 模板中第一个占位符之前的目录部分，展开后的文件不能超出该目录；没有占位符时即模板本身
+fn template_root(template: &str) -> (&str, &str) {
+    let Some(placeholder) = template.find('{') else {
+        return (template, "");
+    };
+    let split = template[..placeholder].rfind(['/', '\\']).map(|i| i + 1).unwrap_or(0);
+    template.split_at(split)
+}
+
+async fn is_dir(path: &Path) -> bool {
+    tokio::fs::metadata(path).await.map(|m| m.is_dir()).unwrap_or(false)
+}
+
+fn path_traversal_rejected() -> HttpResponse {
+    synthetic_response(403, "text/plain", b"Map Local: path traversal rejected".to_vec())
+}
+
+// 模板展开后指向目录时，按 URL 路径在目录下查找文件，目录本身返回 index.html
+async fn map_local(template: &str, content_type: Option<&str>, url: &str) -> HttpResponse {
+    let parsed = url::Url::parse(url).ok();
+    let url_path = parsed
+        .as_ref()
+        .map(|u| urlencoding::decode(u.path()).map(|p| p.into_owned()).unwrap_or_else(|_| u.path().to_string()))
+        .unwrap_or_default();
+    let relative = url_path.trim_start_matches('/');
+    let file_name = relative.rsplit('/').next().unwrap_or_default();
+    let (root, pattern) = template_root(template);
+    // 没有占位符且指向文件时路径完全由规则给出，直接读取
+    if pattern.is_empty() && !is_dir(Path::new(template)).await {
+        return read_mapped(Path::new(template), content_type).await;
+    }
+    let expanded = pattern
+        .replace("{host}", parsed.as_ref().and_then(|u| u.host_str()).unwrap_or_default())
+        .replace("{path}", relative)
+        .replace("{file}", file_name)
+        .replace("{query}", parsed.as_ref().and_then(|u| u.query()).unwrap_or_default());
+
+    let root = PathBuf::from(if root.is_empty() { "." } else { root });
+    let mut suffix = PathBuf::from(expanded);
+    if is_dir(&root.join(&suffix)).await && !template.contains("{path}") && !template.contains("{file}") {
+        suffix.push(relative);
+    }
+    if is_dir(&root.join(&suffix)).await {
+        suffix.push("index.html");
+    }
+    // 占位符的值来自请求，展开部分只允许普通路径段，不能借 .. 或绝对路径跳出映射目录
+    if !suffix.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return path_traversal_rejected();
+    }
+    let path = root.join(&suffix);
+
+    // 符号链接同样不能指向目录之外
+    let resolved = match (tokio::fs::canonicalize(&path).await, tokio::fs::canonicalize(&root).await) {
+        (Ok(resolved), Ok(root)) if resolved.starts_with(&root) => resolved,
+        (Ok(_), Ok(_)) => return path_traversal_rejected(),
+        (Err(e), _) | (_, Err(e)) => return map_local_unreadable(&path, e),
+    };
+    read_mapped(&resolved, content_type).await
+}
+
+async fn read_mapped(path: &Path, content_type: Option<&str>) -> HttpResponse {
+    match tokio::fs::read(path).await {
+        Ok(body) => {
+            let content_type = content_type.unwrap_or_else(|| content_type_for(path));
+            synthetic_response(200, content_type, body)
+        }
+        Err(e) => map_local_unreadable(path, e),
+    }
+}
+
+fn map_local_unreadable(path: &Path, error: std::io::Error) -> HttpResponse {
+    warn!("Map Local could not read {}: {}", path.display(), error);
+    synthetic_response(
+        404,
+        "text/plain",
+        format!("Map Local: cannot read {}: {}", path.display(), error).into_bytes(),
+    )
+}
+
+fn content_type_for(path: &Path) -> &'static str {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "application/javascript; charset=utf-8",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "txt" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "woff2" => "font/woff2",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+fn synthetic_response(status: u16, content_type: &str, body: Vec<u8>) -> HttpResponse {
+    HttpResponse {
+        status,
+        headers: HashMap::from([("content-type".to_string(), content_type.to_string())]),
+        body,
+        timestamp: chrono::Utc::now(),
+        version: None,
+    }
+}
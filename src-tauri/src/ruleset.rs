@@ -0,0 +1,94 @@
+use crate::proxy::RequestRule;
+use crate::rules;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const RULESET_FORMAT: &str = "packetmind-rules";
+pub const RULESET_FORMAT_VERSION: u32 = 1;
+
+// 规则集文件：格式化的 JSON，便于团队在版本库中共享和审阅
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleSetFile {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    pub rules: Vec<RequestRule>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    // 同 id 的规则原位替换，其余追加到末尾
+    #[default]
+    Merge,
+    Replace,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleImportSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub total: usize,
+}
+
+pub fn write(path: &Path, rules: &[RequestRule]) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = RuleSetFile {
+        format: RULESET_FORMAT.to_string(),
+        version: RULESET_FORMAT_VERSION,
+        exported_at: chrono::Utc::now(),
+        rules: rules.to_vec(),
+    };
+    std::fs::write(path, serde_json::to_vec_pretty(&file)?)?;
+    Ok(())
+}
+
+// 任一规则无效时整体拒绝导入，避免只导入半套规则
+pub fn read(path: &Path) -> Result<Vec<RequestRule>> {
+    let file: RuleSetFile = serde_json::from_slice(&std::fs::read(path)?)
+        .map_err(|e| anyhow!("Invalid rule set file: {}", e))?;
+    if file.format != RULESET_FORMAT {
+        return Err(anyhow!("Not a rule set file: format is '{}'", file.format));
+    }
+    if file.version > RULESET_FORMAT_VERSION {
+        return Err(anyhow!(
+            "Rule set version {} is newer than supported version {}",
+            file.version,
+            RULESET_FORMAT_VERSION
+        ));
+    }
+    for (index, rule) in file.rules.iter().enumerate() {
+        if rule.id.is_empty() {
+            return Err(anyhow!("Rule #{} has an empty id", index + 1));
+        }
+        if file.rules[..index].iter().any(|r| r.id == rule.id) {
+            return Err(anyhow!("Duplicate rule id: {}", rule.id));
+        }
+        rules::validate(rule).map_err(|e| anyhow!("Invalid rule '{}': {}", rule.name, e))?;
+    }
+    Ok(file.rules)
+}
+
+pub fn import(existing: &mut Vec<RequestRule>, imported: Vec<RequestRule>, mode: ImportMode) -> RuleImportSummary {
+    if mode == ImportMode::Replace {
+        *existing = imported;
+        return RuleImportSummary { added: existing.len(), updated: 0, total: existing.len() };
+    }
+    let (mut added, mut updated) = (0, 0);
+    for rule in imported {
+        match existing.iter_mut().find(|r| r.id == rule.id) {
+            Some(current) => {
+                *current = rule;
+                updated += 1;
+            }
+            None => {
+                existing.push(rule);
+                added += 1;
+            }
+        }
+    }
+    RuleImportSummary { added, updated, total: existing.len() }
+}
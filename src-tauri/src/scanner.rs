@@ -0,0 +1,199 @@
+use crate::ai_analyzer::SecurityRisk;
+use crate::params::{self, Parameter};
+use crate::passive;
+use crate::proxy::HttpTransaction;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+pub const SECURITY_FINDING_EVENT: &str = "security:finding";
+
+// 内存中保留的扫描结果数量，超出后丢弃最早的
+pub const MAX_FINDINGS: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FindingCategory {
+    SqlInjection,
+    Xss,
+    SensitiveData,
+    Cors,
+    Jwt,
+    Cookie,
+    PathTraversal,
+    OpenRedirect,
+    Ssrf,
+    SecretLeak,
+}
+
+// 单条扫描结果；事务上只保存 description 文本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    // 检查项标识，如 "cors-null-origin"，同一类问题在不同事务上相同
+    pub id: String,
+    pub severity: SecurityRisk,
+    pub category: FindingCategory,
+    pub description: String,
+    // 触发该结果的原始内容片段
+    pub evidence: Option<String>,
+    pub remediation: String,
+    // 问题所在的头，如 "Set-Cookie"、"Access-Control-Allow-Origin"
+    pub header: Option<String>,
+    // 具体对象，如 Cookie 名或 JWT 所在位置
+    pub subject: Option<String>,
+    // 主动扫描确认该问题时发出的探测请求
+    #[serde(default)]
+    pub probe_transaction_id: Option<String>,
+    // 以下由扫描器填入
+    #[serde(default)]
+    pub transaction_id: String,
+    #[serde(default)]
+    pub host: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl Finding {
+    pub fn new(id: &str, severity: SecurityRisk, category: FindingCategory, description: impl Into<String>) -> Self {
+        Self {
+            id: id.to_string(),
+            severity,
+            category,
+            description: description.into(),
+            evidence: None,
+            remediation: String::new(),
+            header: None,
+            subject: None,
+            probe_transaction_id: None,
+            transaction_id: String::new(),
+            host: String::new(),
+            detected_at: Utc::now(),
+        }
+    }
+
+    pub fn header(mut self, header: &str) -> Self {
+        self.header = Some(header.to_string());
+        self
+    }
+
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    pub fn evidence(mut self, evidence: impl Into<String>) -> Self {
+        self.evidence = Some(evidence.into());
+        self
+    }
+
+    pub fn remediation(mut self, remediation: impl Into<String>) -> Self {
+        self.remediation = remediation.into();
+        self
+    }
+
+    pub fn probe(mut self, probe_transaction_id: &str) -> Self {
+        self.probe_transaction_id = Some(probe_transaction_id.to_string());
+        self
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.probe_transaction_id.is_some()
+    }
+}
+
+// 交给每个检查项的输入
+pub struct ScanContext<'a> {
+    pub transaction: &'a HttpTransaction,
+    // 配对的 CORS 预检请求
+    pub preflight: Option<&'a HttpTransaction>,
+    // 查询字符串、表单和 JSON 请求体中解析出的参数
+    pub params: Vec<Parameter>,
+    // 响应体文本，二进制内容为空
+    pub response_text: String,
+}
+
+// 被动检查只读取已抓到的流量，不发送任何请求
+pub trait PassiveCheck: Send + Sync {
+    fn check(&self, context: &ScanContext) -> Vec<Finding>;
+}
+
+// 依次运行所有检查项，汇总结果
+pub struct SecurityAnalyzer {
+    checks: Vec<Box<dyn PassiveCheck>>,
+}
+
+impl Default for SecurityAnalyzer {
+    fn default() -> Self {
+        Self { checks: passive::default_checks() }
+    }
+}
+
+impl SecurityAnalyzer {
+    pub fn scan(&self, transaction: &HttpTransaction, preflight: Option<&HttpTransaction>) -> Vec<Finding> {
+        let context = ScanContext {
+            transaction,
+            preflight,
+            params: params::extract(transaction),
+            response_text: transaction
+                .response
+                .as_ref()
+                .filter(|_| !transaction.response_kind().is_binary())
+                .map(|r| String::from_utf8_lossy(&r.body).to_string())
+                .unwrap_or_default(),
+        };
+        let host = url::Url::parse(&transaction.request.url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let detected_at = Utc::now();
+        self.checks
+            .iter()
+            .flat_map(|check| check.check(&context))
+            .map(|mut finding| {
+                finding.transaction_id = transaction.id.clone();
+                finding.host = host.clone();
+                finding.detected_at = detected_at;
+                finding
+            })
+            .collect()
+    }
+}
+
+// 累计的扫描结果，新的在后
+#[derive(Debug, Default)]
+pub struct FindingStore {
+    findings: VecDeque<Finding>,
+}
+
+impl FindingStore {
+    // 同一事务重新扫描时替换之前的被动扫描结果，主动扫描结果保留
+    pub fn replace(&mut self, transaction_id: &str, findings: &[Finding]) {
+        self.replace_where(transaction_id, false, findings);
+    }
+
+    pub fn replace_active(&mut self, transaction_id: &str, findings: &[Finding]) {
+        self.replace_where(transaction_id, true, findings);
+    }
+
+    fn replace_where(&mut self, transaction_id: &str, active: bool, findings: &[Finding]) {
+        self.findings.retain(|f| f.transaction_id != transaction_id || f.is_active() != active);
+        self.findings.extend(findings.iter().cloned());
+        while self.findings.len() > MAX_FINDINGS {
+            self.findings.pop_front();
+        }
+    }
+
+    // 按时间倒序
+    pub fn list(&self, min_severity: Option<SecurityRisk>, transaction_id: Option<&str>, limit: usize) -> Vec<Finding> {
+        self.findings
+            .iter()
+            .rev()
+            .filter(|f| min_severity.is_none_or(|min| f.severity >= min))
+            .filter(|f| transaction_id.is_none_or(|id| f.transaction_id == id))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.findings.clear();
+    }
+}
@@ -0,0 +1,353 @@
+use crate::history::split_endpoint;
+use crate::jsonbody;
+use crate::proxy::HttpTransaction;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+use std::sync::OnceLock;
+
+pub const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+// 不同取值不超过该数量且每个值平均出现两次以上时视为枚举
+const MAX_ENUM_VALUES: usize = 8;
+// 每个字段最多记录的不同字符串数量，超出后不再判断枚举
+const MAX_TRACKED_VALUES: usize = 32;
+const MAX_EXAMPLES: usize = 3;
+// 嵌套过深的部分不再展开
+const MAX_DEPTH: usize = 32;
+// 同一接口最多参与推断的样本数，取最近的
+pub const MAX_SCHEMA_SAMPLES: usize = 50;
+
+fn email_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[A-Za-z]{2,}$").expect("valid email pattern"))
+}
+
+// 字符串格式：同一字段所有取值格式一致时才写入 schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StringFormat {
+    DateTime,
+    Date,
+    Email,
+    Uuid,
+    Uri,
+    Ipv4,
+}
+
+impl StringFormat {
+    fn detect(text: &str) -> Option<Self> {
+        if chrono::DateTime::parse_from_rfc3339(text).is_ok() {
+            Some(Self::DateTime)
+        } else if chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d").is_ok() {
+            Some(Self::Date)
+        } else if text.len() == 36 && uuid::Uuid::parse_str(text).is_ok() {
+            Some(Self::Uuid)
+        } else if email_pattern().is_match(text) {
+            Some(Self::Email)
+        } else if (text.starts_with("http://") || text.starts_with("https://")) && url::Url::parse(text).is_ok() {
+            Some(Self::Uri)
+        } else if text.parse::<Ipv4Addr>().is_ok() {
+            Some(Self::Ipv4)
+        } else {
+            None
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::DateTime => "date-time",
+            Self::Date => "date",
+            Self::Email => "email",
+            Self::Uuid => "uuid",
+            Self::Uri => "uri",
+            Self::Ipv4 => "ipv4",
+        }
+    }
+}
+
+// 某个位置上所有观察值的汇总
+#[derive(Debug, Clone, Default)]
+struct Node {
+    nulls: u64,
+    booleans: u64,
+    integers: u64,
+    // 非整数的数字
+    numbers: u64,
+    strings: u64,
+    arrays: u64,
+    objects: u64,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    string_values: BTreeMap<String, u64>,
+    values_overflow: bool,
+    // 外层 None 表示还没有字符串；Some(None) 表示格式不一致
+    format: Option<Option<StringFormat>>,
+    properties: BTreeMap<String, Node>,
+    // 出现该属性的对象数量，等于 objects 时为必填
+    presence: u64,
+    items: Option<Box<Node>>,
+    min_items: Option<usize>,
+    max_items: usize,
+}
+
+impl Node {
+    fn observe(&mut self, value: &Value, depth: usize) {
+        match value {
+            Value::Null => self.nulls += 1,
+            Value::Bool(_) => self.booleans += 1,
+            Value::Number(n) => {
+                if n.is_f64() {
+                    self.numbers += 1;
+                } else {
+                    self.integers += 1;
+                }
+                if let Some(n) = n.as_f64() {
+                    self.minimum = Some(self.minimum.map_or(n, |m| m.min(n)));
+                    self.maximum = Some(self.maximum.map_or(n, |m| m.max(n)));
+                }
+            }
+            Value::String(text) => {
+                self.strings += 1;
+                let format = StringFormat::detect(text);
+                self.format = Some(match self.format {
+                    None => format,
+                    Some(previous) if previous == format => format,
+                    Some(_) => None,
+                });
+                if let Some(count) = self.string_values.get_mut(text) {
+                    *count += 1;
+                } else if self.string_values.len() < MAX_TRACKED_VALUES {
+                    self.string_values.insert(text.clone(), 1);
+                } else {
+                    self.values_overflow = true;
+                }
+            }
+            Value::Array(items) => {
+                self.arrays += 1;
+                self.min_items = Some(self.min_items.map_or(items.len(), |m| m.min(items.len())));
+                self.max_items = self.max_items.max(items.len());
+                if depth < MAX_DEPTH {
+                    for item in items {
+                        self.items.get_or_insert_with(Default::default).observe(item, depth + 1);
+                    }
+                }
+            }
+            Value::Object(object) => {
+                self.objects += 1;
+                if depth < MAX_DEPTH {
+                    for (key, value) in object {
+                        let property = self.properties.entry(key.clone()).or_default();
+                        property.presence += 1;
+                        property.observe(value, depth + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    fn to_schema(&self) -> Value {
+        let mut schema = Map::new();
+        let mut types = Vec::new();
+        if self.objects > 0 {
+            types.push("object");
+        }
+        if self.arrays > 0 {
+            types.push("array");
+        }
+        if self.strings > 0 {
+            types.push("string");
+        }
+        // 整数与小数混合出现时统一为 number
+        if self.numbers > 0 {
+            types.push("number");
+        } else if self.integers > 0 {
+            types.push("integer");
+        }
+        if self.booleans > 0 {
+            types.push("boolean");
+        }
+        if self.nulls > 0 {
+            types.push("null");
+        }
+        match types.as_slice() {
+            [] => {}
+            [single] => {
+                schema.insert("type".to_string(), json!(single));
+            }
+            _ => {
+                schema.insert("type".to_string(), json!(types));
+            }
+        }
+
+        if self.objects > 0 {
+            let properties: Map<String, Value> =
+                self.properties.iter().map(|(key, node)| (key.clone(), node.to_schema())).collect();
+            let required: Vec<&String> = self
+                .properties
+                .iter()
+                .filter(|(_, node)| node.presence == self.objects)
+                .map(|(key, _)| key)
+                .collect();
+            schema.insert("properties".to_string(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".to_string(), json!(required));
+            }
+        }
+        if self.arrays > 0 {
+            if let Some(items) = &self.items {
+                schema.insert("items".to_string(), items.to_schema());
+            }
+            schema.insert("minItems".to_string(), json!(self.min_items.unwrap_or(0)));
+            schema.insert("maxItems".to_string(), json!(self.max_items));
+        }
+        if self.strings > 0 {
+            let format = self.format.flatten();
+            if let Some(format) = format {
+                schema.insert("format".to_string(), json!(format.name()));
+            }
+            let distinct = self.string_values.len();
+            // 与其他类型混合的字段不写枚举，否则非字符串取值会不满足 schema
+            let only_strings = self.objects + self.arrays + self.numbers + self.integers + self.booleans == 0;
+            let is_enum = only_strings
+                && format.is_none()
+                && !self.values_overflow
+                && distinct <= MAX_ENUM_VALUES
+                && self.strings >= distinct as u64 * 2;
+            if is_enum {
+                let mut values: Vec<Value> = self.string_values.keys().map(|v| json!(v)).collect();
+                if self.nulls > 0 {
+                    values.push(Value::Null);
+                }
+                schema.insert("enum".to_string(), Value::Array(values));
+            } else if format.is_none() {
+                let mut frequent: Vec<(&String, &u64)> = self.string_values.iter().collect();
+                frequent.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+                let examples: Vec<&String> = frequent.into_iter().take(MAX_EXAMPLES).map(|(v, _)| v).collect();
+                schema.insert("examples".to_string(), json!(examples));
+            }
+        }
+        if self.numbers + self.integers > 0 {
+            let number = |n: f64| if self.numbers == 0 { json!(n as i64) } else { json!(n) };
+            if let Some(minimum) = self.minimum {
+                schema.insert("minimum".to_string(), number(minimum));
+            }
+            if let Some(maximum) = self.maximum {
+                schema.insert("maximum".to_string(), number(maximum));
+            }
+        }
+        Value::Object(schema)
+    }
+}
+
+// 逐个观察 JSON 样本，合并为一份 schema
+#[derive(Debug, Clone, Default)]
+pub struct SchemaBuilder {
+    root: Node,
+    samples: usize,
+}
+
+impl SchemaBuilder {
+    pub fn observe(&mut self, value: &Value) {
+        self.root.observe(value, 0);
+        self.samples += 1;
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    // 没有样本时返回 None
+    pub fn build(&self) -> Option<Value> {
+        if self.samples == 0 {
+            return None;
+        }
+        let mut schema = self.root.to_schema();
+        if let Value::Object(object) = &mut schema {
+            object.insert("$schema".to_string(), json!(JSON_SCHEMA_DIALECT));
+        }
+        Some(schema)
+    }
+}
+
+// "方法 主机/归一化路径"，与统计和历史中的接口键一致
+pub fn endpoint_key(method: &str, url: &str) -> String {
+    let (host, path) = split_endpoint(url);
+    format!("{} {}{}", method.to_uppercase(), host, path)
+}
+
+// 接口键匹配：完整的 "方法 主机/路径"、只有 "主机/路径"，或 "方法 URL"
+pub fn matches_endpoint(transaction: &HttpTransaction, endpoint: &str) -> bool {
+    let endpoint = endpoint.trim();
+    let key = endpoint_key(&transaction.request.method, &transaction.request.url);
+    match endpoint.split_once(' ') {
+        Some((method, url)) if url.starts_with("http://") || url.starts_with("https://") => {
+            key == endpoint_key(method, url)
+        }
+        Some(_) => key.eq_ignore_ascii_case(endpoint),
+        None => key.split_once(' ').is_some_and(|(_, rest)| rest == endpoint),
+    }
+}
+
+// 非空且能解析为 JSON 的请求体
+pub fn request_json(transaction: &HttpTransaction) -> Option<Value> {
+    let request = &transaction.request;
+    if request.body.is_empty() {
+        return None;
+    }
+    jsonbody::parse(&request.body, &request.headers, false).ok()?.value
+}
+
+// 成功响应中能解析为 JSON 的响应体
+pub fn response_json(transaction: &HttpTransaction) -> Option<Value> {
+    let response = transaction.response.as_ref().filter(|r| (200..300).contains(&r.status))?;
+    jsonbody::parse(&response.body, &response.headers, false).ok()?.value
+}
+
+// 请求体与响应体分别推断；错误响应结构通常不同，只使用 2xx 响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InferredSchema {
+    pub transaction_count: usize,
+    pub request_samples: usize,
+    pub response_samples: usize,
+    pub request: Option<Value>,
+    pub response: Option<Value>,
+}
+
+pub fn infer<'a>(transactions: impl IntoIterator<Item = &'a HttpTransaction>) -> InferredSchema {
+    let (mut request, mut response) = (SchemaBuilder::default(), SchemaBuilder::default());
+    let mut transaction_count = 0;
+    for transaction in transactions {
+        transaction_count += 1;
+        if let Some(value) = request_json(transaction) {
+            request.observe(&value);
+        }
+        if let Some(value) = response_json(transaction) {
+            response.observe(&value);
+        }
+    }
+    InferredSchema {
+        transaction_count,
+        request_samples: request.samples(),
+        response_samples: response.samples(),
+        request: request.build(),
+        response: response.build(),
+    }
+}
+
+// 用同一接口最近的成功响应推断响应体 schema
+pub fn observed_response_schema(transactions: &[HttpTransaction], method: &str, url: &str) -> SchemaBuilder {
+    let key = endpoint_key(method, url);
+    let mut builder = SchemaBuilder::default();
+    for value in transactions
+        .iter()
+        .rev()
+        .filter(|t| endpoint_key(&t.request.method, &t.request.url) == key)
+        .filter_map(response_json)
+        .take(MAX_SCHEMA_SAMPLES)
+    {
+        builder.observe(&value);
+    }
+    builder
+}
@@ -0,0 +1,160 @@
+use crate::ai_analyzer::{AIAnalysisResult, SecurityRisk};
+use crate::classify;
+use crate::proxy::{find_header, HttpTransaction};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+pub const MAX_SCORE: u8 = 100;
+// 批量模型评分默认处理的事务数，只挑启发式得分最高的一批，控制调用成本
+pub const DEFAULT_MODEL_SCORING_LIMIT: usize = 20;
+
+// 响应体只扫描开头部分，保证入库时评分足够便宜
+const SCAN_BYTES: usize = 64 * 1024;
+const SLOW_RESPONSE_MS: u128 = 3000;
+const LARGE_RESPONSE_BYTES: usize = 1024 * 1024;
+
+// 0-100 的风险/值得关注程度，入库时按启发式规则计算，有 AI 分析结果时再结合模型评分
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RiskScore {
+    pub score: u8,
+    pub heuristic_score: u8,
+    pub model_score: Option<u8>,
+    pub reasons: Vec<String>,
+}
+
+impl RiskScore {
+    // 有模型评分时取两者平均
+    fn combined(heuristic_score: u8, model_score: Option<u8>) -> u8 {
+        match model_score {
+            Some(model) => ((heuristic_score as u16 + model as u16) / 2) as u8,
+            None => heuristic_score,
+        }
+    }
+}
+
+struct Signal {
+    weight: u8,
+    reason: &'static str,
+}
+
+fn injection_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)(union\s+select|'\s*or\s+'?1'?\s*=\s*'?1|;\s*drop\s+table|<script|javascript:|onerror\s*=|\.\./\.\./)")
+            .expect("valid injection pattern")
+    })
+}
+
+fn secret_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r#"(?i)("(password|passwd|secret|api_?key|access_?token|private_?key)"\s*:\s*"[^"]+")"#,
+            r"|eyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.",
+            r"|-----BEGIN [A-Z ]*PRIVATE KEY-----",
+            r"|AKIA[0-9A-Z]{16}",
+        ))
+        .expect("valid secret pattern")
+    })
+}
+
+fn sensitive_param(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["token", "password", "passwd", "secret", "api_key", "apikey", "access_key", "session"]
+        .iter()
+        .any(|p| name.contains(p))
+}
+
+fn signals(transaction: &HttpTransaction) -> Vec<Signal> {
+    let request = &transaction.request;
+    let mut signals = Vec::new();
+    let mut add = |weight, reason| signals.push(Signal { weight, reason });
+
+    match transaction.response.as_ref().map(|r| r.status) {
+        Some(status) if status >= 500 => add(25, "Server error response"),
+        Some(401) | Some(403) => add(15, "Authentication or authorization failure"),
+        Some(status) if status >= 400 => add(8, "Client error response"),
+        None => add(10, "No response received"),
+        _ => {}
+    }
+
+    let url = url::Url::parse(&request.url).ok();
+    if let Some(url) = &url {
+        if url.query_pairs().any(|(name, _)| sensitive_param(&name)) {
+            add(20, "Credentials or tokens in URL query");
+        }
+        let has_credentials = find_header(&request.headers, "authorization").is_some()
+            || find_header(&request.headers, "cookie").is_some();
+        if url.scheme() == "http" && has_credentials {
+            add(25, "Credentials sent over plain HTTP");
+        }
+        let path = url.path().to_lowercase();
+        if ["/admin", "/login", "/auth", "/oauth", "/token", "/internal", "/debug"]
+            .iter()
+            .any(|p| path.contains(p))
+        {
+            add(10, "Sensitive endpoint");
+        }
+    }
+
+    let request_body = classify::analyzable_text(&request.body, transaction.request_kind());
+    let decoded_url = urlencoding::decode(&request.url).map(|u| u.into_owned()).unwrap_or_default();
+    if injection_pattern().is_match(&decoded_url) || injection_pattern().is_match(&request_body) {
+        add(30, "Injection-like payload in request");
+    }
+
+    if matches!(request.method.as_str(), "PUT" | "DELETE" | "PATCH") {
+        add(5, "State-changing method");
+    }
+
+    if let Some(response) = &transaction.response {
+        let kind = transaction.response_kind();
+        let end = response.body.len().min(SCAN_BYTES);
+        let body = classify::analyzable_text(&response.body[..end], kind);
+        if secret_pattern().is_match(&body) {
+            add(20, "Secrets or tokens in response body");
+        }
+        if response.body.len() > LARGE_RESPONSE_BYTES {
+            add(5, "Large response body");
+        }
+        let set_cookie = find_header(&response.headers, "set-cookie").unwrap_or_default().to_lowercase();
+        if !set_cookie.is_empty() && !set_cookie.contains("httponly") {
+            add(8, "Cookie set without HttpOnly");
+        }
+    }
+
+    if transaction.duration.map(|d| d.as_millis() > SLOW_RESPONSE_MS).unwrap_or(false) {
+        add(5, "Slow response");
+    }
+    if !transaction.security_findings.is_empty() {
+        add(25, "Security findings reported");
+    }
+    signals
+}
+
+pub fn score(transaction: &HttpTransaction) -> RiskScore {
+    let signals = signals(transaction);
+    let heuristic_score = signals
+        .iter()
+        .map(|s| s.weight as u16)
+        .sum::<u16>()
+        .min(MAX_SCORE as u16) as u8;
+    let model_score = transaction.ai_analysis.as_ref().map(model_score);
+    RiskScore {
+        score: RiskScore::combined(heuristic_score, model_score),
+        heuristic_score,
+        model_score,
+        reasons: signals.iter().map(|s| s.reason.to_string()).collect(),
+    }
+}
+
+// 模型给出的风险等级换算为分数
+pub fn model_score(analysis: &AIAnalysisResult) -> u8 {
+    match analysis.security_risk {
+        SecurityRisk::Low => 15,
+        SecurityRisk::Medium => 45,
+        SecurityRisk::High => 75,
+        SecurityRisk::Critical => 100,
+    }
+}
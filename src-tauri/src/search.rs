@@ -0,0 +1,117 @@
+use crate::classify;
+use crate::proxy::{HttpTransaction, SearchFilter};
+use anyhow::{anyhow, Result};
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+// 搜索框边输入边过滤时会反复使用同一个表达式，缓存编译结果
+const REGEX_CACHE_CAPACITY: usize = 64;
+// 限制编译后的大小，避免病态表达式占用过多内存
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+// 以（表达式, 是否区分大小写）为键
+type RegexCache = Mutex<HashMap<(String, bool), Arc<Regex>>>;
+
+fn regex_cache() -> &'static RegexCache {
+    static CACHE: OnceLock<RegexCache> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn cached_regex(pattern: &str, case_sensitive: bool) -> Result<Arc<Regex>> {
+    let key = (pattern.to_string(), case_sensitive);
+    let mut cache = regex_cache().lock().map_err(|_| anyhow!("Regex cache is poisoned"))?;
+    if let Some(regex) = cache.get(&key) {
+        return Ok(regex.clone());
+    }
+    let regex = RegexBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| anyhow!("Invalid search regex: {}", e))?;
+    let regex = Arc::new(regex);
+    if cache.len() >= REGEX_CACHE_CAPACITY {
+        cache.clear();
+    }
+    cache.insert(key, regex.clone());
+    Ok(regex)
+}
+
+enum Pattern {
+    Text { keyword: String, case_sensitive: bool },
+    Regex(Arc<Regex>),
+}
+
+// 按过滤条件匹配关键字：URL、方法和备注总是参与匹配，请求体/响应体和请求头按选项参与
+pub struct KeywordMatcher {
+    pattern: Pattern,
+    in_body: bool,
+    in_headers: bool,
+}
+
+impl KeywordMatcher {
+    // 关键字为空时返回 None，表示不按关键字过滤
+    pub fn new(filter: &SearchFilter) -> Result<Option<Self>> {
+        if filter.keyword.is_empty() {
+            return Ok(None);
+        }
+        let case_sensitive = filter.case_sensitive.unwrap_or(false);
+        let pattern = if filter.use_regex.unwrap_or(false) {
+            Pattern::Regex(cached_regex(&filter.keyword, case_sensitive)?)
+        } else {
+            Pattern::Text {
+                keyword: if case_sensitive { filter.keyword.clone() } else { filter.keyword.to_lowercase() },
+                case_sensitive,
+            }
+        };
+        Ok(Some(Self {
+            pattern,
+            in_body: filter.search_in_body.unwrap_or(true),
+            in_headers: filter.search_in_headers.unwrap_or(false),
+        }))
+    }
+
+    fn matches_text(&self, text: &str) -> bool {
+        match &self.pattern {
+            Pattern::Text { keyword, case_sensitive: true } => text.contains(keyword.as_str()),
+            Pattern::Text { keyword, case_sensitive: false } => text.to_lowercase().contains(keyword.as_str()),
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    pub fn matches(&self, transaction: &HttpTransaction) -> bool {
+        let request = &transaction.request;
+        if self.matches_text(&request.url)
+            || self.matches_text(&request.method)
+            || transaction.notes.as_deref().map(|n| self.matches_text(n)).unwrap_or(false)
+        {
+            return true;
+        }
+        if self.in_headers {
+            let response_headers = transaction.response.iter().flat_map(|r| r.headers.iter());
+            if request
+                .headers
+                .iter()
+                .chain(response_headers)
+                .any(|(name, value)| self.matches_text(&format!("{}: {}", name, value)))
+            {
+                return true;
+            }
+        }
+        self.in_body && self.matches_body(transaction)
+    }
+
+    fn matches_body(&self, transaction: &HttpTransaction) -> bool {
+        // 不区分大小写的普通关键字沿用按内容类型切分的词匹配
+        if let Pattern::Text { keyword, case_sensitive: false } = &self.pattern {
+            return transaction.body_contains(keyword);
+        }
+        let request = &transaction.request;
+        self.matches_text(&classify::analyzable_text(&request.body, transaction.request_kind()))
+            || transaction
+                .response
+                .as_ref()
+                .map(|r| self.matches_text(&classify::analyzable_text(&r.body, transaction.response_kind())))
+                .unwrap_or(false)
+    }
+}
@@ -0,0 +1,325 @@
+use crate::classify::BodyKind;
+use crate::proxy::{find_header, HttpTransaction};
+use serde::{Deserialize, Serialize};
+
+// HSTS 的 max-age 低于半年时视为偏短（与 HSTS preload 列表的要求一致）
+const HSTS_MIN_MAX_AGE: u64 = 180 * 24 * 3600;
+const MAX_DISTINCT_VALUES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderGrade {
+    Pass,
+    Warning,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeaderCheck {
+    pub header: String,
+    pub grade: HeaderGrade,
+    // 参与评估的响应中带有该头的数量
+    pub present_in: usize,
+    pub total: usize,
+    // 出现过的不同取值，最多保留几个
+    pub values: Vec<String>,
+    pub issues: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityHeaderReport {
+    pub host: String,
+    pub responses_checked: usize,
+    pub https: bool,
+    // 0-100，按各项结果加权扣分
+    pub score: u8,
+    pub grade: String,
+    pub checks: Vec<HeaderCheck>,
+}
+
+struct Sample<'a> {
+    https: bool,
+    html: bool,
+    headers: &'a std::collections::HashMap<String, String>,
+}
+
+pub fn audit(host: &str, transactions: &[HttpTransaction]) -> SecurityHeaderReport {
+    let host = host.trim().to_lowercase();
+    let samples: Vec<Sample> = transactions
+        .iter()
+        .filter_map(|t| {
+            let url = url::Url::parse(&t.request.url).ok()?;
+            if !url.host_str()?.eq_ignore_ascii_case(&host) {
+                return None;
+            }
+            let response = t.response.as_ref()?;
+            // 1xx 和 304 不携带完整的响应头，不参与评估
+            if response.status < 200 || response.status == 304 {
+                return None;
+            }
+            Some(Sample {
+                https: url.scheme() == "https",
+                html: t.response_kind() == BodyKind::Html,
+                headers: &response.headers,
+            })
+        })
+        .collect();
+
+    let https = samples.iter().any(|s| s.https);
+    // CSP 和防点击劫持只对页面有意义；未抓到页面时退回到全部响应
+    let documents: Vec<&Sample> = if samples.iter().any(|s| s.html) {
+        samples.iter().filter(|s| s.html).collect()
+    } else {
+        samples.iter().collect()
+    };
+    let all: Vec<&Sample> = samples.iter().collect();
+    let secure: Vec<&Sample> = samples.iter().filter(|s| s.https).collect();
+
+    let checks = vec![
+        check_csp(&documents),
+        check_hsts(&secure, https),
+        evaluate("X-Content-Type-Options", &all, HeaderGrade::Fail, |value| {
+            if value.trim().eq_ignore_ascii_case("nosniff") {
+                (HeaderGrade::Pass, None)
+            } else {
+                (HeaderGrade::Fail, Some(format!("取值 \"{}\" 无效，应为 nosniff", value)))
+            }
+        }),
+        check_frame_options(&documents),
+        check_referrer_policy(&all),
+        check_permissions_policy(&all),
+    ];
+
+    let score = if samples.is_empty() { 0 } else { score(&checks) };
+    SecurityHeaderReport {
+        host,
+        responses_checked: samples.len(),
+        https,
+        score,
+        grade: letter(score).to_string(),
+        checks,
+    }
+}
+
+// 逐个响应评估取值；部分响应缺失时至多给出警告
+fn evaluate(
+    header: &str,
+    samples: &[&Sample],
+    missing: HeaderGrade,
+    judge: impl Fn(&str) -> (HeaderGrade, Option<String>),
+) -> HeaderCheck {
+    let mut check = HeaderCheck {
+        header: header.to_string(),
+        grade: HeaderGrade::Pass,
+        present_in: 0,
+        total: samples.len(),
+        values: Vec::new(),
+        issues: Vec::new(),
+    };
+    for sample in samples {
+        let Some(value) = find_header(sample.headers, header) else {
+            continue;
+        };
+        check.present_in += 1;
+        if check.values.iter().any(|v| v == value) {
+            continue;
+        }
+        let (grade, issue) = judge(value);
+        check.grade = check.grade.max(grade);
+        check.issues.extend(issue);
+        if check.values.len() < MAX_DISTINCT_VALUES {
+            check.values.push(value.to_string());
+        }
+    }
+    if check.total == 0 {
+        return check;
+    }
+    if check.present_in == 0 {
+        check.grade = missing;
+        check.issues.insert(0, format!("所有响应均缺少 {}", header));
+    } else if check.present_in < check.total {
+        check.grade = check.grade.max(HeaderGrade::Warning.min(missing));
+        check.issues.push(format!("仅 {}/{} 个响应包含 {}", check.present_in, check.total, header));
+    }
+    check
+}
+
+fn check_csp(documents: &[&Sample]) -> HeaderCheck {
+    let mut check = evaluate("Content-Security-Policy", documents, HeaderGrade::Fail, |value| {
+        let issues = csp_issues(value);
+        match issues.is_empty() {
+            true => (HeaderGrade::Pass, None),
+            false => (HeaderGrade::Warning, Some(issues.join("；"))),
+        }
+    });
+    // 只有 Report-Only 策略时不会拦截任何内容
+    if check.present_in == 0
+        && documents.iter().any(|s| find_header(s.headers, "content-security-policy-report-only").is_some())
+    {
+        check.issues.push("仅配置了 Content-Security-Policy-Report-Only，策略不会生效".to_string());
+    }
+    check
+}
+
+fn csp_issues(policy: &str) -> Vec<String> {
+    let directives: Vec<(String, Vec<&str>)> = policy
+        .split(';')
+        .filter_map(|directive| {
+            let mut tokens = directive.split_whitespace();
+            Some((tokens.next()?.to_lowercase(), tokens.collect()))
+        })
+        .collect();
+    let sources = |name: &str| directives.iter().find(|(n, _)| n == name).map(|(_, s)| s.as_slice());
+    let mut issues = Vec::new();
+    match sources("script-src").or_else(|| sources("default-src")) {
+        None => issues.push("未限制脚本来源（缺少 script-src 和 default-src）".to_string()),
+        Some(script) => {
+            let lower: Vec<String> = script.iter().map(|s| s.to_lowercase()).collect();
+            let has = |token: &str| lower.iter().any(|s| s == token);
+            // 带有 nonce 或 hash 时浏览器会忽略 'unsafe-inline'
+            let has_nonce = lower.iter().any(|s| s.starts_with("'nonce-") || s.starts_with("'sha"));
+            if has("'unsafe-inline'") && !has_nonce {
+                issues.push("脚本允许 'unsafe-inline'".to_string());
+            }
+            if has("'unsafe-eval'") {
+                issues.push("脚本允许 'unsafe-eval'".to_string());
+            }
+            if lower.iter().any(|s| matches!(s.as_str(), "*" | "http:" | "https:" | "data:")) {
+                issues.push("脚本来源包含通配符或整个协议".to_string());
+            }
+        }
+    }
+    if sources("object-src").or_else(|| sources("default-src")).is_none() {
+        issues.push("未限制 object-src".to_string());
+    }
+    if sources("base-uri").is_none() {
+        issues.push("未设置 base-uri，可被注入的 <base> 标签劫持相对路径".to_string());
+    }
+    issues
+}
+
+fn check_hsts(secure: &[&Sample], https: bool) -> HeaderCheck {
+    let mut check = evaluate("Strict-Transport-Security", secure, HeaderGrade::Fail, |value| {
+        let mut max_age = None;
+        let mut include_subdomains = false;
+        for directive in value.split(';') {
+            let (name, arg) = directive.split_once('=').unwrap_or((directive, ""));
+            match name.trim().to_lowercase().as_str() {
+                "max-age" => max_age = arg.trim().trim_matches('"').parse::<u64>().ok(),
+                "includesubdomains" => include_subdomains = true,
+                _ => {}
+            }
+        }
+        match max_age {
+            None => (HeaderGrade::Fail, Some("缺少有效的 max-age".to_string())),
+            Some(0) => (HeaderGrade::Fail, Some("max-age=0 会清除 HSTS 策略".to_string())),
+            Some(age) if age < HSTS_MIN_MAX_AGE => {
+                (HeaderGrade::Warning, Some(format!("max-age={} 少于 180 天", age)))
+            }
+            Some(_) if !include_subdomains => (HeaderGrade::Pass, Some("未包含 includeSubDomains".to_string())),
+            Some(_) => (HeaderGrade::Pass, None),
+        }
+    });
+    if !https {
+        check.grade = HeaderGrade::Fail;
+        check.issues = vec!["未抓到该主机的 HTTPS 响应，流量以明文传输".to_string()];
+    }
+    check
+}
+
+fn check_frame_options(documents: &[&Sample]) -> HeaderCheck {
+    let mut check = evaluate("X-Frame-Options", documents, HeaderGrade::Fail, |value| {
+        match value.trim().to_uppercase().as_str() {
+            "DENY" | "SAMEORIGIN" => (HeaderGrade::Pass, None),
+            v if v.starts_with("ALLOW-FROM") => {
+                (HeaderGrade::Warning, Some("ALLOW-FROM 已被浏览器废弃，应改用 CSP frame-ancestors".to_string()))
+            }
+            _ => (HeaderGrade::Fail, Some(format!("取值 \"{}\" 无效", value))),
+        }
+    });
+    // CSP frame-ancestors 优先于 X-Frame-Options，两者有其一即可
+    let frame_ancestors = documents.iter().all(|s| {
+        find_header(s.headers, "content-security-policy")
+            .is_some_and(|csp| csp.to_lowercase().contains("frame-ancestors"))
+    });
+    if check.present_in < check.total && frame_ancestors && !documents.is_empty() {
+        check.grade = HeaderGrade::Pass;
+        check.issues = vec!["由 CSP frame-ancestors 提供防点击劫持保护".to_string()];
+    }
+    check
+}
+
+fn check_referrer_policy(all: &[&Sample]) -> HeaderCheck {
+    evaluate("Referrer-Policy", all, HeaderGrade::Warning, |value| {
+        // 逗号分隔时以最后一个浏览器认识的取值为准
+        let policy = value
+            .split(',')
+            .map(|p| p.trim().to_lowercase())
+            .rfind(|p| {
+                matches!(
+                    p.as_str(),
+                    "no-referrer"
+                        | "no-referrer-when-downgrade"
+                        | "origin"
+                        | "origin-when-cross-origin"
+                        | "same-origin"
+                        | "strict-origin"
+                        | "strict-origin-when-cross-origin"
+                        | "unsafe-url"
+                )
+            });
+        match policy.as_deref() {
+            None => (HeaderGrade::Fail, Some(format!("取值 \"{}\" 无效", value))),
+            Some("unsafe-url") => (HeaderGrade::Fail, Some("unsafe-url 会把完整 URL 泄露给第三方".to_string())),
+            Some("no-referrer-when-downgrade") | Some("origin-when-cross-origin") | Some("origin") => (
+                HeaderGrade::Warning,
+                Some(format!("{} 仍会向跨站请求发送来源信息", policy.as_deref().unwrap_or_default())),
+            ),
+            Some(_) => (HeaderGrade::Pass, None),
+        }
+    })
+}
+
+fn check_permissions_policy(all: &[&Sample]) -> HeaderCheck {
+    let mut check = evaluate("Permissions-Policy", all, HeaderGrade::Warning, |value| {
+        if value.trim().is_empty() {
+            (HeaderGrade::Warning, Some("策略为空".to_string()))
+        } else {
+            (HeaderGrade::Pass, None)
+        }
+    });
+    if check.present_in == 0 && all.iter().any(|s| find_header(s.headers, "feature-policy").is_some()) {
+        check.issues.push("仅配置了已废弃的 Feature-Policy".to_string());
+    }
+    check
+}
+
+// 各项权重合计 100，警告扣一半
+fn score(checks: &[HeaderCheck]) -> u8 {
+    let weight = |header: &str| match header {
+        "Content-Security-Policy" => 25,
+        "Strict-Transport-Security" => 20,
+        "X-Content-Type-Options" | "X-Frame-Options" => 15,
+        "Referrer-Policy" => 13,
+        _ => 12,
+    };
+    let penalty: u32 = checks
+        .iter()
+        .map(|c| match c.grade {
+            HeaderGrade::Pass => 0,
+            HeaderGrade::Warning => weight(&c.header) / 2,
+            HeaderGrade::Fail => weight(&c.header),
+        })
+        .sum();
+    100u32.saturating_sub(penalty) as u8
+}
+
+fn letter(score: u8) -> &'static str {
+    match score {
+        90.. => "A",
+        80..=89 => "B",
+        70..=79 => "C",
+        60..=69 => "D",
+        _ => "F",
+    }
+}
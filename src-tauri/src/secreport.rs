@@ -0,0 +1,195 @@
+use crate::ai_analyzer::SecurityRisk;
+use crate::scanner::Finding;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FindingsFormat {
+    // SARIF 2.1.0，可导入 GitHub Code Scanning 等安全平台
+    Sarif,
+    Markdown,
+}
+
+const SEVERITIES: [SecurityRisk; 4] = [SecurityRisk::Critical, SecurityRisk::High, SecurityRisk::Medium, SecurityRisk::Low];
+
+fn severity_label(severity: SecurityRisk) -> &'static str {
+    match severity {
+        SecurityRisk::Critical => "严重",
+        SecurityRisk::High => "高危",
+        SecurityRisk::Medium => "中危",
+        SecurityRisk::Low => "低危",
+    }
+}
+
+// urls 为事务 id 到请求 URL 的映射，用于定位结果
+pub fn generate(format: FindingsFormat, findings: &[Finding], urls: &HashMap<String, String>) -> String {
+    match format {
+        FindingsFormat::Sarif => serde_json::to_string_pretty(&sarif(findings, urls)).unwrap_or_default(),
+        FindingsFormat::Markdown => markdown(findings, urls),
+    }
+}
+
+fn sarif_level(severity: SecurityRisk) -> &'static str {
+    match severity {
+        SecurityRisk::Critical | SecurityRisk::High => "error",
+        SecurityRisk::Medium => "warning",
+        SecurityRisk::Low => "note",
+    }
+}
+
+// GitHub 按 security-severity 数值划分严重程度
+fn security_severity(severity: SecurityRisk) -> &'static str {
+    match severity {
+        SecurityRisk::Critical => "9.5",
+        SecurityRisk::High => "8.0",
+        SecurityRisk::Medium => "5.5",
+        SecurityRisk::Low => "3.0",
+    }
+}
+
+// 每个检查项 id 对应一条规则，规则的严重程度取该 id 下最高的一条
+fn sarif(findings: &[Finding], urls: &HashMap<String, String>) -> Value {
+    let mut rules: BTreeMap<&str, &Finding> = BTreeMap::new();
+    for finding in findings {
+        rules
+            .entry(finding.id.as_str())
+            .and_modify(|existing| {
+                if finding.severity > existing.severity {
+                    *existing = finding;
+                }
+            })
+            .or_insert(finding);
+    }
+    let rule_index: HashMap<&str, usize> = rules.keys().enumerate().map(|(i, id)| (*id, i)).collect();
+
+    let rules: Vec<Value> = rules
+        .values()
+        .map(|f| {
+            json!({
+                "id": f.id,
+                "name": format!("{:?}", f.category),
+                "shortDescription": { "text": f.id },
+                "help": { "text": f.remediation },
+                "defaultConfiguration": { "level": sarif_level(f.severity) },
+                "properties": {
+                    "tags": ["security", format!("{:?}", f.category)],
+                    "security-severity": security_severity(f.severity),
+                },
+            })
+        })
+        .collect();
+
+    let results: Vec<Value> = findings
+        .iter()
+        .map(|f| {
+            let uri = urls.get(&f.transaction_id).cloned().unwrap_or_else(|| format!("https://{}/", f.host));
+            let mut message = f.description.clone();
+            if let Some(evidence) = &f.evidence {
+                let _ = write!(message, "\n证据：{}", evidence);
+            }
+            json!({
+                "ruleId": f.id,
+                "ruleIndex": rule_index[f.id.as_str()],
+                "level": sarif_level(f.severity),
+                "message": { "text": message },
+                "locations": [{
+                    "physicalLocation": { "artifactLocation": { "uri": uri } },
+                }],
+                "partialFingerprints": {
+                    "findingKey": format!("{}|{}|{}", f.id, f.host, f.subject.as_deref().unwrap_or("")),
+                },
+                "properties": {
+                    "transactionId": f.transaction_id,
+                    "probeTransactionId": f.probe_transaction_id,
+                    "host": f.host,
+                    "header": f.header,
+                    "subject": f.subject,
+                    "evidence": f.evidence,
+                    "remediation": f.remediation,
+                    "detectedAt": f.detected_at.to_rfc3339(),
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "PacketMind AI",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+// 证据可能包含反引号，改用不会与内容冲突的代码标记
+fn inline_code(text: &str) -> String {
+    let text = text.replace(['\r', '\n'], " ");
+    if text.contains('`') {
+        format!("`` {} ``", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+// 按主机分组，组内按严重程度从高到低
+fn markdown(findings: &[Finding], urls: &HashMap<String, String>) -> String {
+    let mut out = String::from("# 安全扫描报告\n\n");
+    let _ = writeln!(out, "- 生成时间：{}", chrono::Utc::now().to_rfc3339());
+    let counts: Vec<String> = SEVERITIES
+        .iter()
+        .map(|s| format!("{} {}", severity_label(*s), findings.iter().filter(|f| f.severity == *s).count()))
+        .collect();
+    let _ = writeln!(out, "- 结果数：{}（{}）", findings.len(), counts.join("，"));
+    if findings.is_empty() {
+        out.push_str("\n无\n");
+        return out;
+    }
+
+    let mut by_host: BTreeMap<&str, Vec<&Finding>> = BTreeMap::new();
+    for finding in findings {
+        by_host.entry(finding.host.as_str()).or_default().push(finding);
+    }
+    for (host, host_findings) in by_host {
+        let host = if host.is_empty() { "未知主机" } else { host };
+        let _ = writeln!(out, "\n## {}", host);
+        for severity in SEVERITIES {
+            let group: Vec<&&Finding> = host_findings.iter().filter(|f| f.severity == severity).collect();
+            if group.is_empty() {
+                continue;
+            }
+            let _ = writeln!(out, "\n### {}（{}）\n", severity_label(severity), group.len());
+            for f in group {
+                let _ = writeln!(out, "- **{}**（`{}`）", f.description, f.id);
+                match urls.get(&f.transaction_id) {
+                    Some(url) => {
+                        let _ = writeln!(out, "  - 事务：`{}` {}", f.transaction_id, inline_code(url));
+                    }
+                    None => {
+                        let _ = writeln!(out, "  - 事务：`{}`", f.transaction_id);
+                    }
+                }
+                if let Some(probe) = &f.probe_transaction_id {
+                    let _ = writeln!(out, "  - 探测请求：`{}`", probe);
+                }
+                if let Some(evidence) = &f.evidence {
+                    let _ = writeln!(out, "  - 证据：{}", inline_code(evidence));
+                }
+                if !f.remediation.is_empty() {
+                    let _ = writeln!(out, "  - 修复建议：{}", f.remediation);
+                }
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,190 @@
+use crate::proxy::HttpTransaction;
+use crate::redaction::{scan_prefix, PiiLocation};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
+// 通用规则：值的香农熵（比特/字符）低于该值视为普通文本或占位符
+const MIN_ENTROPY: f64 = 3.5;
+
+// 这些请求头本来就用于携带凭据，不算泄露
+const CREDENTIAL_HEADERS: [&str; 3] = ["authorization", "proxy-authorization", "cookie"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretProvider {
+    AwsAccessKey,
+    GitHub,
+    Slack,
+    SlackWebhook,
+    GoogleApiKey,
+    Stripe,
+    PrivateKey,
+    // 按变量名和熵判断的未知类型密钥
+    Generic,
+}
+
+impl SecretProvider {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::AwsAccessKey => "aws-access-key",
+            Self::GitHub => "github-token",
+            Self::Slack => "slack-token",
+            Self::SlackWebhook => "slack-webhook",
+            Self::GoogleApiKey => "google-api-key",
+            Self::Stripe => "stripe-key",
+            Self::PrivateKey => "private-key",
+            Self::Generic => "generic-secret",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::AwsAccessKey => "AWS Access Key",
+            Self::GitHub => "GitHub 令牌",
+            Self::Slack => "Slack 令牌",
+            Self::SlackWebhook => "Slack Webhook",
+            Self::GoogleApiKey => "Google API Key",
+            Self::Stripe => "Stripe 密钥",
+            Self::PrivateKey => "私钥",
+            Self::Generic => "疑似密钥",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SecretLeak {
+    pub provider: SecretProvider,
+    pub location: PiiLocation,
+    // 所在的头名称，仅头部位置有
+    pub header: Option<String>,
+    // 保留首尾几位，其余替换为 *
+    pub masked: String,
+}
+
+struct Detector {
+    provider: SecretProvider,
+    // 有第一个捕获组时只取该组
+    pattern: Regex,
+}
+
+fn detectors() -> &'static [Detector] {
+    static DETECTORS: OnceLock<Vec<Detector>> = OnceLock::new();
+    DETECTORS.get_or_init(|| {
+        let detector = |provider, pattern: &str| Detector {
+            provider,
+            pattern: Regex::new(pattern).expect("valid secret pattern"),
+        };
+        vec![
+            detector(SecretProvider::PrivateKey, r"-----BEGIN (?:RSA |EC |DSA |OPENSSH |ENCRYPTED |PGP )?PRIVATE KEY(?: BLOCK)?-----"),
+            detector(SecretProvider::AwsAccessKey, r"\b((?:AKIA|ASIA)[0-9A-Z]{16})\b"),
+            detector(SecretProvider::GitHub, r"\b(gh[pousr]_[A-Za-z0-9]{36,255}|github_pat_[A-Za-z0-9_]{82})\b"),
+            detector(SecretProvider::SlackWebhook, r"https://hooks\.slack\.com/services/T[A-Za-z0-9]+/B[A-Za-z0-9]+/[A-Za-z0-9]+"),
+            detector(SecretProvider::Slack, r"\b(xox[abposr]-[A-Za-z0-9-]{10,})\b"),
+            detector(SecretProvider::GoogleApiKey, r"\b(AIza[0-9A-Za-z_-]{35})"),
+            detector(SecretProvider::Stripe, r"\b((?:sk|rk)_live_[0-9A-Za-z]{24,})\b"),
+        ]
+    })
+}
+
+// 变量名像密钥、值足够长的赋值，如 "client_secret": "..."、API_KEY=...
+fn generic_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r#"(?i)[a-z0-9_-]*(?:secret|token|api[_-]?key|access[_-]?key|private[_-]?key|passwd|password|credential)[a-z0-9_-]*["']?\s*[:=]\s*["']?([A-Za-z0-9+/=_.-]{20,})"#,
+        )
+        .unwrap()
+    })
+}
+
+fn entropy(text: &str) -> f64 {
+    let mut counts = [0usize; 256];
+    for byte in text.bytes() {
+        counts[byte as usize] += 1;
+    }
+    let len = text.len() as f64;
+    counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+// 同时含字母和数字且熵足够高，排除单词拼接、纯数字 ID 和 xxxx 之类的占位符
+fn looks_random(value: &str) -> bool {
+    value.bytes().any(|b| b.is_ascii_digit())
+        && value.bytes().any(|b| b.is_ascii_alphabetic())
+        && entropy(value) >= MIN_ENTROPY
+}
+
+fn mask(provider: SecretProvider, value: &str) -> String {
+    // PEM 头本身不含密钥内容
+    if provider == SecretProvider::PrivateKey {
+        return format!("{}…", value);
+    }
+    let chars: Vec<char> = value.chars().collect();
+    let keep = if chars.len() >= 16 { 4 } else { 2 };
+    if chars.len() <= keep * 2 {
+        return "*".repeat(chars.len());
+    }
+    let head: String = chars[..keep].iter().collect();
+    let tail: String = chars[chars.len() - keep..].iter().collect();
+    format!("{}{}{}", head, "*".repeat((chars.len() - keep * 2).min(8)), tail)
+}
+
+fn find(text: &str) -> Vec<(SecretProvider, String)> {
+    let text = scan_prefix(text);
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    let mut found = Vec::new();
+    for detector in detectors() {
+        for captures in detector.pattern.captures_iter(text) {
+            let Some(span) = captures.get(1).or_else(|| captures.get(0)) else {
+                continue;
+            };
+            spans.push((span.start(), span.end()));
+            found.push((detector.provider, mask(detector.provider, span.as_str())));
+        }
+    }
+    for captures in generic_pattern().captures_iter(text) {
+        let span = captures.get(1).expect("generic pattern has a value group");
+        let overlaps = spans.iter().any(|(start, end)| *start < span.end() && span.start() < *end);
+        if !overlaps && looks_random(span.as_str()) {
+            found.push((SecretProvider::Generic, mask(SecretProvider::Generic, span.as_str())));
+        }
+    }
+    found
+}
+
+// 扫描 URL、请求/响应头和消息体，相同位置的同一个值只报告一次
+pub fn scan(transaction: &HttpTransaction) -> Vec<SecretLeak> {
+    let mut leaks = BTreeSet::new();
+    let mut record = |location: PiiLocation, header: Option<&str>, text: &str| {
+        for (provider, masked) in find(text) {
+            leaks.insert(SecretLeak { provider, location, header: header.map(str::to_string), masked });
+        }
+    };
+    let request = &transaction.request;
+    record(PiiLocation::Url, None, &request.url);
+    for (name, value) in &request.headers {
+        if !CREDENTIAL_HEADERS.contains(&name.to_lowercase().as_str()) {
+            record(PiiLocation::RequestHeader, Some(name), value);
+        }
+    }
+    if let Ok(body) = std::str::from_utf8(&request.body) {
+        record(PiiLocation::RequestBody, None, body);
+    }
+    if let Some(response) = &transaction.response {
+        for (name, value) in &response.headers {
+            record(PiiLocation::ResponseHeader, Some(name), value);
+        }
+        if let Ok(body) = std::str::from_utf8(&response.body) {
+            record(PiiLocation::ResponseBody, None, body);
+        }
+    }
+    leaks.into_iter().collect()
+}
@@ -0,0 +1,238 @@
+use crate::auth::ProxyCredentials;
+use crate::mitm;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// 由代理自身应答的内置回显地址，不会转发到外部
+pub const SELF_TEST_HOST: &str = "packetmind.selftest";
+const EXTERNAL_TEST_URL: &str = "http://example.com/";
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    pub overall: CheckStatus,
+    pub proxy_port: u16,
+    pub checks: Vec<SelfTestCheck>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+// ca_cert_path 为 None 表示尚未生成根证书；启用了代理认证时，经代理的检查携带同一组凭据
+pub async fn run(
+    port: u16,
+    is_running: bool,
+    intercept_https: bool,
+    ca_cert_path: Option<PathBuf>,
+    credentials: Option<ProxyCredentials>,
+) -> SelfTestReport {
+    let mut checks = Vec::new();
+
+    if !is_running {
+        checks.push(SelfTestCheck {
+            name: "listener".to_string(),
+            status: CheckStatus::Fail,
+            message: "代理未启动，请先启动代理".to_string(),
+            duration_ms: 0,
+        });
+    } else {
+        checks.push(timed("listener", check_listener(port)).await);
+        checks.push(timed("echo", check_echo(port, credentials.as_ref())).await);
+        checks.push(timed("upstream", check_upstream(port, credentials.as_ref())).await);
+    }
+    checks.push(timed("system_proxy", check_system_proxy(port)).await);
+    checks.push(timed("mitm_trust", check_mitm_trust(intercept_https, ca_cert_path)).await);
+
+    let overall = if checks.iter().any(|c| c.status == CheckStatus::Fail) {
+        CheckStatus::Fail
+    } else if checks.iter().any(|c| c.status == CheckStatus::Warn) {
+        CheckStatus::Warn
+    } else {
+        CheckStatus::Pass
+    };
+
+    SelfTestReport {
+        overall,
+        proxy_port: port,
+        checks,
+        timestamp: chrono::Utc::now(),
+    }
+}
+
+async fn timed<F>(name: &str, check: F) -> SelfTestCheck
+where
+    F: std::future::Future<Output = (CheckStatus, String)>,
+{
+    let start = Instant::now();
+    let (status, message) = check.await;
+    SelfTestCheck {
+        name: name.to_string(),
+        status,
+        message,
+        duration_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+fn proxied_client(port: u16, credentials: Option<&ProxyCredentials>) -> Result<reqwest::Client, String> {
+    let mut proxy = reqwest::Proxy::all(format!("http://127.0.0.1:{}", port))
+        .map_err(|e| e.to_string())?;
+    if let Some(credentials) = credentials {
+        proxy = proxy.basic_auth(credentials.username(), credentials.password());
+    }
+    reqwest::Client::builder()
+        .proxy(proxy)
+        .timeout(CHECK_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+async fn check_listener(port: u16) -> (CheckStatus, String) {
+    let connect = tokio::net::TcpStream::connect(("127.0.0.1", port));
+    match tokio::time::timeout(CHECK_TIMEOUT, connect).await {
+        Ok(Ok(_)) => (CheckStatus::Pass, format!("127.0.0.1:{} 可连接", port)),
+        Ok(Err(e)) => (CheckStatus::Fail, format!("无法连接 127.0.0.1:{}: {}", port, e)),
+        Err(_) => (CheckStatus::Fail, format!("连接 127.0.0.1:{} 超时", port)),
+    }
+}
+
+async fn check_echo(port: u16, credentials: Option<&ProxyCredentials>) -> (CheckStatus, String) {
+    let client = match proxied_client(port, credentials) {
+        Ok(client) => client,
+        Err(e) => return (CheckStatus::Fail, e),
+    };
+    let token = uuid::Uuid::new_v4().to_string();
+    let url = format!("http://{}/echo?token={}", SELF_TEST_HOST, token);
+
+    match client.get(&url).send().await {
+        Ok(response) => {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            if status.is_success() && body.contains(&token) {
+                (CheckStatus::Pass, "经代理访问内置回显端点成功".to_string())
+            } else {
+                (CheckStatus::Fail, format!("回显端点返回异常: HTTP {}", status))
+            }
+        }
+        Err(e) => (CheckStatus::Fail, format!("经代理访问回显端点失败: {}", e)),
+    }
+}
+
+async fn check_upstream(port: u16, credentials: Option<&ProxyCredentials>) -> (CheckStatus, String) {
+    let client = match proxied_client(port, credentials) {
+        Ok(client) => client,
+        Err(e) => return (CheckStatus::Fail, e),
+    };
+
+    match client.get(EXTERNAL_TEST_URL).send().await {
+        Ok(response) => {
+            let status = response.status();
+            if status.is_server_error() {
+                (CheckStatus::Fail, format!("经代理访问 {} 返回 HTTP {}", EXTERNAL_TEST_URL, status))
+            } else {
+                (CheckStatus::Pass, format!("经代理访问 {} 返回 HTTP {}", EXTERNAL_TEST_URL, status))
+            }
+        }
+        Err(e) => (CheckStatus::Fail, format!("经代理访问 {} 失败: {}", EXTERNAL_TEST_URL, e)),
+    }
+}
+
+async fn check_mitm_trust(intercept_https: bool, ca_cert_path: Option<PathBuf>) -> (CheckStatus, String) {
+    if !intercept_https {
+        return (CheckStatus::Skipped, "HTTPS 拦截未开启，跳过证书信任检查".to_string());
+    }
+    let Some(cert_path) = ca_cert_path else {
+        return (CheckStatus::Warn, "尚未生成根证书，请先生成并安装".to_string());
+    };
+
+    match mitm::is_ca_trusted(&cert_path).await {
+        Some(true) => (CheckStatus::Pass, "根证书已被系统信任".to_string()),
+        Some(false) => (CheckStatus::Warn, "根证书未被系统信任，HTTPS 请求会出现证书错误".to_string()),
+        None => (CheckStatus::Skipped, "无法检查当前平台的证书信任状态".to_string()),
+    }
+}
+
+async fn check_system_proxy(port: u16) -> (CheckStatus, String) {
+    let expected = format!("127.0.0.1:{}", port);
+    match read_system_proxy().await {
+        Some(current) if current.contains(&expected) || current.contains(&format!("localhost:{}", port)) => {
+            (CheckStatus::Pass, format!("系统代理已指向 {}", expected))
+        }
+        Some(current) if current.is_empty() => {
+            (CheckStatus::Warn, "系统代理未配置，只有手动配置代理的客户端会被抓包".to_string())
+        }
+        Some(current) => (CheckStatus::Warn, format!("系统代理指向其他地址: {}", current)),
+        None => (CheckStatus::Skipped, "无法读取当前平台的系统代理配置".to_string()),
+    }
+}
+
+// 返回 "host:port" 形式的当前系统 HTTP 代理，未启用时为空字符串
+#[cfg(target_os = "macos")]
+async fn read_system_proxy() -> Option<String> {
+    let output = tokio::process::Command::new("scutil").arg("--proxy").output().await.ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let field = |name: &str| {
+        text.lines()
+            .find_map(|line| line.trim().strip_prefix(name).map(|v| v.trim_start_matches([' ', ':']).trim().to_string()))
+    };
+    if field("HTTPEnable").as_deref() != Some("1") {
+        return Some(String::new());
+    }
+    Some(format!("{}:{}", field("HTTPProxy").unwrap_or_default(), field("HTTPPort").unwrap_or_default()))
+}
+
+#[cfg(target_os = "windows")]
+async fn read_system_proxy() -> Option<String> {
+    let script = r#"
+        $settings = Get-ItemProperty -Path "HKCU:\Software\Microsoft\Windows\CurrentVersion\Internet Settings"
+        if ($settings.ProxyEnable -eq 1) { $settings.ProxyServer } else { "" }
+    "#;
+    let output = tokio::process::Command::new("powershell")
+        .args(["-Command", script])
+        .output()
+        .await
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(target_os = "linux")]
+async fn read_system_proxy() -> Option<String> {
+    let gsettings = |args: &'static [&'static str]| async move {
+        tokio::process::Command::new("gsettings")
+            .args(args)
+            .output()
+            .await
+            .ok()
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().trim_matches('\'').to_string())
+    };
+
+    match gsettings(&["get", "org.gnome.system.proxy", "mode"]).await {
+        Some(mode) if mode == "manual" => {
+            let host = gsettings(&["get", "org.gnome.system.proxy.http", "host"]).await.unwrap_or_default();
+            let port = gsettings(&["get", "org.gnome.system.proxy.http", "port"]).await.unwrap_or_default();
+            Some(format!("{}:{}", host, port))
+        }
+        Some(_) => Some(String::new()),
+        // 非 GNOME 环境，退回到环境变量
+        None => std::env::var("http_proxy").or_else(|_| std::env::var("HTTP_PROXY")).ok(),
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+async fn read_system_proxy() -> Option<String> {
+    None
+}
@@ -0,0 +1,90 @@
+use crate::palette::SavedSearch;
+use crate::proxy::{HttpTransaction, RequestRule};
+use anyhow::{anyhow, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+
+pub const SESSION_FORMAT_VERSION: u32 = 1;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// 会话文件：gzip 压缩的 JSON，完整保存一次抓包的工作现场，可归档或分享
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFile {
+    pub version: u32,
+    pub saved_at: chrono::DateTime<chrono::Utc>,
+    // 事务本身带有标签、收藏标记、AI 分析和安全检测结果
+    pub transactions: Vec<HttpTransaction>,
+    #[serde(default)]
+    pub filters: Vec<String>,
+    #[serde(default)]
+    pub rules: Vec<RequestRule>,
+    #[serde(default)]
+    pub favorites: Vec<String>,
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub path: String,
+    pub saved_at: chrono::DateTime<chrono::Utc>,
+    pub transactions: usize,
+    pub filters: usize,
+    pub rules: usize,
+    pub favorites: usize,
+}
+
+impl SessionFile {
+    pub fn summary(&self, path: &Path) -> SessionSummary {
+        SessionSummary {
+            path: path.display().to_string(),
+            saved_at: self.saved_at,
+            transactions: self.transactions.len(),
+            filters: self.filters.len(),
+            rules: self.rules.len(),
+            favorites: self.favorites.len(),
+        }
+    }
+}
+
+// 先写临时文件再改名，保存中途失败不会损坏已有的会话文件
+pub fn write(path: &Path, session: &SessionFile) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_extension("tmp");
+    {
+        let file = std::fs::File::create(&temp)?;
+        let mut encoder = GzEncoder::new(std::io::BufWriter::new(file), flate2::Compression::default());
+        serde_json::to_writer(&mut encoder, session)?;
+        let mut writer = encoder.finish()?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+    }
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+// 兼容未压缩的 JSON，便于手工编辑后再打开
+pub fn read(path: &Path) -> Result<SessionFile> {
+    let data = std::fs::read(path)?;
+    let json = if data.starts_with(&GZIP_MAGIC) {
+        let mut json = Vec::new();
+        GzDecoder::new(data.as_slice()).read_to_end(&mut json)?;
+        json
+    } else {
+        data
+    };
+    let session: SessionFile = serde_json::from_slice(&json)?;
+    if session.version > SESSION_FORMAT_VERSION {
+        return Err(anyhow!(
+            "Session file version {} is newer than supported version {}",
+            session.version,
+            SESSION_FORMAT_VERSION
+        ));
+    }
+    Ok(session)
+}
@@ -0,0 +1,170 @@
+use crate::proxy::{find_header, HttpResponse};
+use crate::buffer::BufferLimits;
+use crate::storage::StorageBackend;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BodyCapturePolicy {
+    Full,
+    Truncate { max_bytes: usize },
+    Skip,
+}
+
+// content_type 支持精确匹配 (application/json) 和通配符 (video/*)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyLimitRule {
+    pub content_type: String,
+    pub policy: BodyCapturePolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureSettings {
+    pub body_limits: Vec<BodyLimitRule>,
+    pub default_policy: BodyCapturePolicy,
+    // 解密 HTTPS 需要客户端信任本地根证书，默认关闭
+    #[serde(default)]
+    pub intercept_https: bool,
+    #[serde(default)]
+    pub storage: StorageBackend,
+    // 内存中保留的事务上限，超出时淘汰最旧的非收藏事务
+    #[serde(default)]
+    pub buffer: BufferLimits,
+    // 响应体超过该字节数时改为边收边转发，只保留前缀用于记录
+    #[serde(default = "default_stream_threshold")]
+    pub stream_threshold: usize,
+    // 开启 HTTPS 解密时仍原样转发的主机，用于做了证书绑定的应用；支持 *.example.com
+    #[serde(default)]
+    pub tls_passthrough: Vec<String>,
+}
+
+fn default_stream_threshold() -> usize {
+    16 * 1024 * 1024
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyCaptureOutcome {
+    Full,
+    Truncated,
+    Skipped,
+}
+
+impl Default for CaptureSettings {
+    fn default() -> Self {
+        Self {
+            body_limits: vec![
+                BodyLimitRule {
+                    content_type: "application/json".to_string(),
+                    policy: BodyCapturePolicy::Full,
+                },
+                BodyLimitRule {
+                    content_type: "text/html".to_string(),
+                    policy: BodyCapturePolicy::Truncate { max_bytes: 64 * 1024 },
+                },
+                BodyLimitRule {
+                    content_type: "video/*".to_string(),
+                    policy: BodyCapturePolicy::Skip,
+                },
+                BodyLimitRule {
+                    content_type: "audio/*".to_string(),
+                    policy: BodyCapturePolicy::Skip,
+                },
+            ],
+            default_policy: BodyCapturePolicy::Truncate { max_bytes: 1024 * 1024 },
+            intercept_https: false,
+            storage: StorageBackend::Memory,
+            buffer: BufferLimits::default(),
+            stream_threshold: default_stream_threshold(),
+            tls_passthrough: Vec::new(),
+        }
+    }
+}
+
+impl CaptureSettings {
+    pub fn validated(mut self) -> Result<Self> {
+        self.tls_passthrough = normalize_passthrough(self.tls_passthrough)?;
+        Ok(self)
+    }
+
+    pub fn policy_for(&self, content_type: Option<&str>) -> &BodyCapturePolicy {
+        // 去掉 charset 等参数
+        let mime = content_type
+            .and_then(|ct| ct.split(';').next())
+            .map(|ct| ct.trim().to_lowercase())
+            .unwrap_or_default();
+
+        self.body_limits
+            .iter()
+            .find(|rule| mime_matches(&rule.content_type, &mime))
+            .map(|rule| &rule.policy)
+            .unwrap_or(&self.default_policy)
+    }
+
+    // 转发时最多缓冲的响应体字节数，超出部分边收边转发；截断或跳过的类型只需缓冲到存储上限
+    pub fn buffer_limit(&self, content_type: Option<&str>) -> usize {
+        match *self.policy_for(content_type) {
+            BodyCapturePolicy::Full => self.stream_threshold,
+            BodyCapturePolicy::Truncate { max_bytes } => max_bytes.min(self.stream_threshold),
+            BodyCapturePolicy::Skip => 0,
+        }
+    }
+
+    // 只作用于存储的副本，转发给客户端的响应不受影响
+    pub fn apply_to_response(&self, response: &mut HttpResponse) -> BodyCaptureOutcome {
+        let content_type = find_header(&response.headers, "content-type");
+        match *self.policy_for(content_type) {
+            BodyCapturePolicy::Full => BodyCaptureOutcome::Full,
+            BodyCapturePolicy::Truncate { max_bytes } if response.body.len() > max_bytes => {
+                response.body.truncate(max_bytes);
+                BodyCaptureOutcome::Truncated
+            }
+            BodyCapturePolicy::Truncate { .. } => BodyCaptureOutcome::Full,
+            BodyCapturePolicy::Skip if response.body.is_empty() => BodyCaptureOutcome::Full,
+            BodyCapturePolicy::Skip => {
+                response.body.clear();
+                BodyCaptureOutcome::Skipped
+            }
+        }
+    }
+
+    pub fn is_tls_passthrough(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_lowercase();
+        self.tls_passthrough.iter().any(|pattern| match pattern.strip_prefix("*.") {
+            Some(suffix) => host.strip_suffix(suffix).is_some_and(|prefix| prefix.ends_with('.')),
+            None => *pattern == host,
+        })
+    }
+}
+
+// 去重并统一为小写；只允许主机名和开头的 "*." 通配
+pub fn normalize_passthrough(entries: Vec<String>) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for entry in entries {
+        let entry = entry.trim().trim_end_matches('.').to_lowercase();
+        if entry.is_empty() {
+            continue;
+        }
+        let host = entry.strip_prefix("*.").unwrap_or(&entry);
+        let valid = host
+            .split('.')
+            .all(|label| !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == ':'));
+        if !valid {
+            return Err(anyhow!("Invalid passthrough host: {}", entry));
+        }
+        if !normalized.contains(&entry) {
+            normalized.push(entry);
+        }
+    }
+    Ok(normalized)
+}
+
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    let pattern = pattern.trim().to_lowercase();
+    if pattern == "*" || pattern == "*/*" {
+        return true;
+    }
+    match pattern.strip_suffix("/*") {
+        Some(prefix) => mime.split('/').next() == Some(prefix),
+        None => pattern == mime,
+    }
+}
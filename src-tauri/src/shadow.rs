@@ -0,0 +1,229 @@
+use crate::proxy::{find_header, HttpRequest, HttpResponse, HttpTransaction};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+pub const SHADOW_TAG: &str = "shadow";
+pub const SHADOW_DIVERGED_TAG: &str = "shadow-diverged";
+// 单次对比最多报告的差异数
+const MAX_DIVERGENCES: usize = 50;
+// 每次请求都会变化的响应头，默认不参与对比
+const VOLATILE_HEADERS: [&str; 10] = [
+    "date", "age", "expires", "last-modified", "etag", "set-cookie",
+    "server", "via", "x-request-id", "content-length",
+];
+
+// 命中的请求改发到 primary，响应返回客户端；同一请求异步发往 shadow 仅用于对比
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowConfig {
+    // 只复制发往该主机的请求，为空时复制全部
+    #[serde(default)]
+    pub host: Option<String>,
+    pub primary: String,
+    pub shadow: String,
+    #[serde(default)]
+    pub ignore_headers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Divergence {
+    // status、header:<name> 或 body:<JSON 路径>
+    pub field: String,
+    pub primary: Option<String>,
+    pub shadow: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowComparison {
+    pub url: String,
+    pub response: Option<HttpResponse>,
+    pub error: Option<String>,
+    pub duration: Option<std::time::Duration>,
+    pub divergences: Vec<Divergence>,
+    // 主响应为流式转发、只记录了前缀，未对比响应体
+    #[serde(default)]
+    pub partial: bool,
+}
+
+impl ShadowComparison {
+    pub fn diverged(&self) -> bool {
+        self.error.is_some() || !self.divergences.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShadowReport {
+    pub compared: usize,
+    pub diverged: usize,
+    pub shadow_errors: usize,
+    // 各字段出现差异的次数，按次数降序
+    pub fields: Vec<(String, usize)>,
+    pub diverged_transactions: Vec<String>,
+}
+
+impl ShadowConfig {
+    pub fn validate(&self) -> Result<()> {
+        for base in [&self.primary, &self.shadow] {
+            let url = url::Url::parse(base)?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(anyhow!("Unsupported upstream scheme: {}", base));
+            }
+        }
+        Ok(())
+    }
+
+    // 命中时把请求改写为发往 primary，并返回发往 shadow 的副本
+    pub fn route(&self, host: &str, request: &mut HttpRequest) -> Option<HttpRequest> {
+        if let Some(expected) = &self.host {
+            if !expected.eq_ignore_ascii_case(host) {
+                return None;
+            }
+        }
+        let primary_url = rebase(&request.url, &self.primary)?;
+        let shadow_url = rebase(&request.url, &self.shadow)?;
+        request.url = primary_url;
+        let mut shadow_request = request.clone();
+        shadow_request.url = shadow_url;
+        Some(shadow_request)
+    }
+
+    // compare_body 为 false 时只对比状态码和响应头
+    pub fn compare(&self, primary: &HttpResponse, shadow: &HttpResponse, compare_body: bool) -> Vec<Divergence> {
+        let mut divergences = Vec::new();
+        if primary.status != shadow.status {
+            divergences.push(Divergence {
+                field: "status".to_string(),
+                primary: Some(primary.status.to_string()),
+                shadow: Some(shadow.status.to_string()),
+            });
+        }
+
+        let ignored = |name: &str| {
+            VOLATILE_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name))
+                || self.ignore_headers.iter().any(|h| h.eq_ignore_ascii_case(name))
+        };
+        let mut names: Vec<String> = primary
+            .headers
+            .keys()
+            .chain(shadow.headers.keys())
+            .map(|k| k.to_lowercase())
+            .filter(|k| !ignored(k))
+            .collect();
+        names.sort();
+        names.dedup();
+        for name in names {
+            let a = find_header(&primary.headers, &name);
+            let b = find_header(&shadow.headers, &name);
+            if a != b {
+                divergences.push(Divergence {
+                    field: format!("header:{}", name),
+                    primary: a.map(str::to_string),
+                    shadow: b.map(str::to_string),
+                });
+            }
+        }
+
+        // 两边都是 JSON 时按字段对比，忽略键顺序和格式差异
+        if compare_body {
+            match (
+                serde_json::from_slice::<Value>(&primary.body),
+                serde_json::from_slice::<Value>(&shadow.body),
+            ) {
+                (Ok(a), Ok(b)) => diff_json("$", &a, &b, &mut divergences),
+                _ if primary.body != shadow.body => divergences.push(Divergence {
+                    field: "body".to_string(),
+                    primary: Some(format!("{} bytes", primary.body.len())),
+                    shadow: Some(format!("{} bytes", shadow.body.len())),
+                }),
+                _ => {}
+            }
+        }
+
+        divergences.truncate(MAX_DIVERGENCES);
+        divergences
+    }
+}
+
+// 保留原请求的路径和查询，替换协议、主机和端口；base 带路径时作为前缀
+fn rebase(url: &str, base: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    let mut target = url::Url::parse(base).ok()?;
+    let prefix = target.path().trim_end_matches('/').to_string();
+    target.set_path(&format!("{}{}", prefix, url.path()));
+    target.set_query(url.query());
+    Some(target.to_string())
+}
+
+fn diff_json(path: &str, a: &Value, b: &Value, divergences: &mut Vec<Divergence>) {
+    if divergences.len() >= MAX_DIVERGENCES || a == b {
+        return;
+    }
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = format!("{}.{}", path, key);
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => diff_json(&child, x, y, divergences),
+                    (x, y) => push_body_divergence(child, x, y, divergences),
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let child = format!("{}[{}]", path, i);
+                match (a.get(i), b.get(i)) {
+                    (Some(x), Some(y)) => diff_json(&child, x, y, divergences),
+                    (x, y) => push_body_divergence(child, x, y, divergences),
+                }
+            }
+        }
+        (a, b) => push_body_divergence(path.to_string(), Some(a), Some(b), divergences),
+    }
+}
+
+fn push_body_divergence(path: String, a: Option<&Value>, b: Option<&Value>, divergences: &mut Vec<Divergence>) {
+    if divergences.len() < MAX_DIVERGENCES {
+        divergences.push(Divergence {
+            field: format!("body:{}", path),
+            primary: a.map(Value::to_string),
+            shadow: b.map(Value::to_string),
+        });
+    }
+}
+
+fn index_regex() -> &'static Regex {
+    static INDEX: OnceLock<Regex> = OnceLock::new();
+    INDEX.get_or_init(|| Regex::new(r"\[\d+\]").unwrap())
+}
+
+pub fn build_report(transactions: &[HttpTransaction]) -> ShadowReport {
+    let mut report = ShadowReport::default();
+    let mut fields: HashMap<String, usize> = HashMap::new();
+    for transaction in transactions {
+        let Some(comparison) = &transaction.shadow else {
+            continue;
+        };
+        report.compared += 1;
+        if comparison.error.is_some() {
+            report.shadow_errors += 1;
+        }
+        if comparison.diverged() {
+            report.diverged += 1;
+            report.diverged_transactions.push(transaction.id.clone());
+        }
+        for divergence in &comparison.divergences {
+            // 数组下标不同的同类差异合并计数
+            let field = index_regex().replace_all(&divergence.field, "[]").to_string();
+            *fields.entry(field).or_insert(0) += 1;
+        }
+    }
+    report.fields = fields.into_iter().collect();
+    report.fields.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    report
+}
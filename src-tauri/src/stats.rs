@@ -0,0 +1,346 @@
+use crate::dashboard::StatusClassCounts;
+use crate::history::split_endpoint;
+use crate::proxy::HttpTransaction;
+use crate::summary::CaptureRange;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HeatmapGroupBy {
+    Host,
+    Endpoint,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeatmapRow {
+    pub key: String,
+    pub total: u64,
+    // 与 ActivityHeatmap::bucket_starts 一一对应
+    pub counts: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityHeatmap {
+    pub bucket_seconds: u64,
+    pub bucket_starts: Vec<chrono::DateTime<chrono::Utc>>,
+    pub rows: Vec<HeatmapRow>,
+}
+
+// 热力图最多的时间桶数，超出时自动放大桶宽
+const MAX_HEATMAP_BUCKETS: i64 = 2000;
+// 桶宽上限，更宽的桶没有意义，也保证转换成 i64 时不会溢出
+const MAX_BUCKET_SECONDS: u64 = 366 * 86_400;
+
+// 按请求时间划分的时间桶
+struct Buckets {
+    seconds: i64,
+    origin: i64,
+    count: usize,
+}
+
+impl Buckets {
+    // 起点对齐到桶宽的整数倍，桶数过多时自动放大桶宽
+    fn new(first: i64, last: i64, bucket_seconds: u64) -> Self {
+        let mut seconds = bucket_seconds.clamp(1, MAX_BUCKET_SECONDS) as i64;
+        let span = last - first;
+        if span / seconds >= MAX_HEATMAP_BUCKETS {
+            seconds = span / MAX_HEATMAP_BUCKETS + 1;
+        }
+        let origin = first - first.rem_euclid(seconds);
+        Self {
+            seconds,
+            origin,
+            count: ((last - origin) / seconds + 1) as usize,
+        }
+    }
+
+    fn index(&self, timestamp: i64) -> usize {
+        ((timestamp - self.origin) / self.seconds) as usize
+    }
+
+    fn start(&self, index: usize) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp(self.origin + index as i64 * self.seconds, 0)
+    }
+}
+
+pub fn activity_heatmap(
+    transactions: &[HttpTransaction],
+    bucket_seconds: u64,
+    group_by: HeatmapGroupBy,
+) -> ActivityHeatmap {
+    let (Some(first), Some(last)) = (
+        transactions.iter().map(|t| t.request.timestamp).min(),
+        transactions.iter().map(|t| t.request.timestamp).max(),
+    ) else {
+        return ActivityHeatmap {
+            bucket_seconds: bucket_seconds.clamp(1, MAX_BUCKET_SECONDS),
+            bucket_starts: Vec::new(),
+            rows: Vec::new(),
+        };
+    };
+    let buckets = Buckets::new(first.timestamp(), last.timestamp(), bucket_seconds);
+
+    let mut rows: HashMap<String, Vec<u32>> = HashMap::new();
+    for transaction in transactions {
+        let (host, path) = split_endpoint(&transaction.request.url);
+        let key = match group_by {
+            HeatmapGroupBy::Host => host,
+            HeatmapGroupBy::Endpoint => format!("{} {}{}", transaction.request.method, host, path),
+        };
+        let index = buckets.index(transaction.request.timestamp.timestamp());
+        rows.entry(key).or_insert_with(|| vec![0; buckets.count])[index] += 1;
+    }
+
+    let mut rows: Vec<HeatmapRow> = rows
+        .into_iter()
+        .map(|(key, counts)| HeatmapRow {
+            total: counts.iter().map(|&c| c as u64).sum(),
+            key,
+            counts,
+        })
+        .collect();
+    rows.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.key.cmp(&b.key)));
+
+    let bucket_starts = (0..buckets.count)
+        .filter_map(|i| buckets.start(i))
+        .collect();
+
+    ActivityHeatmap {
+        bucket_seconds: buckets.seconds as u64,
+        bucket_starts,
+        rows,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostTraffic {
+    pub host: String,
+    pub requests: u64,
+    pub error_count: u64,
+    // 客户端发往服务器，含请求体和隧道上行
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+// status 为空表示未收到响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusCount {
+    pub status: Option<u16>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MethodCount {
+    pub method: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointLatency {
+    pub endpoint: String,
+    pub count: u64,
+    pub error_count: u64,
+    pub avg_ms: Option<u64>,
+    pub p50_ms: Option<u64>,
+    pub p90_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorRateBucket {
+    pub start: chrono::DateTime<chrono::Utc>,
+    pub total: u64,
+    pub errors: u64,
+    // 桶内没有请求时为 0
+    pub error_rate: f64,
+}
+
+// 仪表盘所需的聚合数据，在后端算好，避免把全部事务传给前端
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrafficStats {
+    pub range: CaptureRange,
+    pub transaction_count: u64,
+    pub error_count: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    // 去重后的主机数与接口数，列表只保留请求最多的前若干项
+    pub host_count: usize,
+    pub endpoint_count: usize,
+    pub hosts: Vec<HostTraffic>,
+    pub by_status: Vec<StatusCount>,
+    pub by_status_class: StatusClassCounts,
+    pub by_method: Vec<MethodCount>,
+    pub endpoints: Vec<EndpointLatency>,
+    pub bucket_seconds: u64,
+    pub error_rate: Vec<ErrorRateBucket>,
+}
+
+// 主机和接口列表的最大条数
+const MAX_TRAFFIC_ROWS: usize = 100;
+// 未指定桶宽时按时间跨度自动选择，使桶数不超过该值
+const DEFAULT_ERROR_RATE_BUCKETS: i64 = 60;
+
+// 与摘要一致：未收到响应或状态码 >= 400 视为失败
+fn is_error(transaction: &HttpTransaction) -> bool {
+    transaction.response.as_ref().is_none_or(|r| r.status >= 400)
+}
+
+// 响应体入库时已解码，按线上传输的编码后大小计
+fn transferred_bytes(transaction: &HttpTransaction) -> (u64, u64) {
+    let mut sent = transaction.request.body.len() as u64;
+    let mut received = match (&transaction.response, &transaction.response_encoding) {
+        (Some(_), Some(encoding)) => encoding.encoded_size as u64,
+        (Some(response), None) => response.body.len() as u64,
+        (None, _) => 0,
+    };
+    if let Some(tunnel) = transaction.tunnel {
+        sent += tunnel.bytes_sent;
+        received += tunnel.bytes_received;
+    }
+    (sent, received)
+}
+
+// 最近秩法，sorted 须已升序
+fn percentile(sorted: &[u64], p: usize) -> Option<u64> {
+    sorted.get((sorted.len() * p).div_ceil(100).saturating_sub(1)).copied()
+}
+
+pub fn traffic_stats(
+    transactions: &[HttpTransaction],
+    range: CaptureRange,
+    bucket_seconds: Option<u64>,
+) -> TrafficStats {
+    let selected: Vec<&HttpTransaction> = transactions.iter().filter(|t| range.contains(t)).collect();
+
+    let mut error_count = 0;
+    let mut bytes_sent = 0;
+    let mut bytes_received = 0;
+    let mut hosts: HashMap<String, HostTraffic> = HashMap::new();
+    let mut statuses: HashMap<Option<u16>, u64> = HashMap::new();
+    let mut by_status_class = StatusClassCounts::default();
+    let mut methods: HashMap<String, u64> = HashMap::new();
+    let mut endpoints: HashMap<String, (u64, u64, Vec<u64>)> = HashMap::new();
+
+    for transaction in &selected {
+        let (host, path) = split_endpoint(&transaction.request.url);
+        let status = transaction.response.as_ref().map(|r| r.status);
+        let failed = is_error(transaction);
+        let (sent, received) = transferred_bytes(transaction);
+        error_count += failed as u64;
+        bytes_sent += sent;
+        bytes_received += received;
+
+        let endpoint = format!("{} {}{}", transaction.request.method, host, path);
+        let entry = hosts.entry(host.clone()).or_insert_with(|| HostTraffic {
+            host,
+            requests: 0,
+            error_count: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+        });
+        entry.requests += 1;
+        entry.error_count += failed as u64;
+        entry.bytes_sent += sent;
+        entry.bytes_received += received;
+
+        *statuses.entry(status).or_default() += 1;
+        by_status_class.record(status);
+        *methods.entry(transaction.request.method.clone()).or_default() += 1;
+
+        let entry = endpoints.entry(endpoint).or_default();
+        entry.0 += 1;
+        entry.1 += failed as u64;
+        if let Some(duration) = transaction.duration {
+            entry.2.push(duration.as_millis() as u64);
+        }
+    }
+
+    let host_count = hosts.len();
+    let mut hosts: Vec<HostTraffic> = hosts.into_values().collect();
+    hosts.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.host.cmp(&b.host)));
+    hosts.truncate(MAX_TRAFFIC_ROWS);
+
+    let mut by_status: Vec<StatusCount> = statuses.into_iter().map(|(status, count)| StatusCount { status, count }).collect();
+    by_status.sort_by_key(|s| (s.status.is_none(), s.status));
+
+    let mut by_method: Vec<MethodCount> = methods.into_iter().map(|(method, count)| MethodCount { method, count }).collect();
+    by_method.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.method.cmp(&b.method)));
+
+    let endpoint_count = endpoints.len();
+    let mut endpoints: Vec<EndpointLatency> = endpoints
+        .into_iter()
+        .map(|(endpoint, (count, error_count, mut durations))| {
+            durations.sort_unstable();
+            EndpointLatency {
+                endpoint,
+                count,
+                error_count,
+                avg_ms: (!durations.is_empty()).then(|| durations.iter().sum::<u64>() / durations.len() as u64),
+                p50_ms: percentile(&durations, 50),
+                p90_ms: percentile(&durations, 90),
+                p95_ms: percentile(&durations, 95),
+                p99_ms: percentile(&durations, 99),
+                max_ms: durations.last().copied(),
+            }
+        })
+        .collect();
+    endpoints.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.endpoint.cmp(&b.endpoint)));
+    endpoints.truncate(MAX_TRAFFIC_ROWS);
+
+    let (bucket_seconds, error_rate) = error_rate_series(&selected, bucket_seconds);
+
+    TrafficStats {
+        range,
+        transaction_count: selected.len() as u64,
+        error_count,
+        bytes_sent,
+        bytes_received,
+        host_count,
+        endpoint_count,
+        hosts,
+        by_status,
+        by_status_class,
+        by_method,
+        endpoints,
+        bucket_seconds,
+        error_rate,
+    }
+}
+
+// 时间桶划分与热力图相同
+fn error_rate_series(transactions: &[&HttpTransaction], bucket_seconds: Option<u64>) -> (u64, Vec<ErrorRateBucket>) {
+    let (Some(first), Some(last)) = (
+        transactions.iter().map(|t| t.request.timestamp).min(),
+        transactions.iter().map(|t| t.request.timestamp).max(),
+    ) else {
+        return (bucket_seconds.unwrap_or(60).clamp(1, MAX_BUCKET_SECONDS), Vec::new());
+    };
+
+    let span = last.timestamp() - first.timestamp();
+    let bucket_seconds = bucket_seconds.unwrap_or((span / DEFAULT_ERROR_RATE_BUCKETS + 1) as u64);
+    let buckets = Buckets::new(first.timestamp(), last.timestamp(), bucket_seconds);
+
+    let mut counts = vec![(0u64, 0u64); buckets.count];
+    for transaction in transactions {
+        let index = buckets.index(transaction.request.timestamp.timestamp());
+        counts[index].0 += 1;
+        counts[index].1 += is_error(transaction) as u64;
+    }
+
+    let series = counts
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, (total, errors))| {
+            Some(ErrorRateBucket {
+                start: buckets.start(i)?,
+                total,
+                errors,
+                error_rate: if total == 0 { 0.0 } else { errors as f64 / total as f64 },
+            })
+        })
+        .collect();
+    (buckets.seconds as u64, series)
+}
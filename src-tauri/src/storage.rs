@@ -0,0 +1,175 @@
+use crate::proxy::HttpTransaction;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+pub const SQLITE_FILE_NAME: &str = "transactions.db";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum StorageBackend {
+    // 仅保存在内存中，关闭应用即丢失
+    #[default]
+    Memory,
+    // path 为空时使用应用数据目录下的 transactions.db
+    Sqlite { path: Option<String> },
+    // 每个新增或修改的事务以 JSON POST 到 url，由远端按 id 去重
+    Rest {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+}
+
+// 事务的持久化后端；代理内存中始终保留当前会话的工作集，写操作同步到后端
+pub trait TransactionStore: Send + Sync {
+    // 切换到该后端时恢复已持久化的事务
+    fn load(&self) -> Result<Vec<HttpTransaction>>;
+    fn upsert(&self, transaction: &HttpTransaction) -> Result<()>;
+    fn clear(&self) -> Result<()>;
+}
+
+pub fn open(backend: &StorageBackend, data_dir: Option<&Path>) -> Result<Arc<dyn TransactionStore>> {
+    Ok(match backend {
+        StorageBackend::Memory => Arc::new(MemoryStore),
+        StorageBackend::Sqlite { path } => {
+            let path = match (path, data_dir) {
+                (Some(path), _) => Path::new(path).to_path_buf(),
+                (None, Some(dir)) => dir.join(SQLITE_FILE_NAME),
+                (None, None) => return Err(anyhow!("SQLite storage requires a path or app data dir")),
+            };
+            Arc::new(SqliteStore::open(&path)?)
+        }
+        StorageBackend::Rest { url, headers } => Arc::new(RestSink::spawn(url.clone(), headers.clone())?),
+    })
+}
+
+pub struct MemoryStore;
+
+impl TransactionStore for MemoryStore {
+    fn load(&self) -> Result<Vec<HttpTransaction>> {
+        Ok(Vec::new())
+    }
+
+    fn upsert(&self, _transaction: &HttpTransaction) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub struct SqliteStore {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = rusqlite::Connection::open(path)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS transactions (
+                 id TEXT PRIMARY KEY,
+                 timestamp TEXT NOT NULL,
+                 data TEXT NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS transactions_timestamp ON transactions (timestamp);",
+        )?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> Result<std::sync::MutexGuard<'_, rusqlite::Connection>> {
+        self.conn.lock().map_err(|_| anyhow!("SQLite connection poisoned"))
+    }
+}
+
+impl TransactionStore for SqliteStore {
+    fn load(&self) -> Result<Vec<HttpTransaction>> {
+        let conn = self.conn()?;
+        let mut statement = conn.prepare("SELECT data FROM transactions ORDER BY timestamp")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut transactions = Vec::new();
+        for data in rows {
+            // 单条记录损坏不影响其他记录
+            match serde_json::from_str(&data?) {
+                Ok(transaction) => transactions.push(transaction),
+                Err(e) => warn!("Skipping unreadable stored transaction: {}", e),
+            }
+        }
+        Ok(transactions)
+    }
+
+    fn upsert(&self, transaction: &HttpTransaction) -> Result<()> {
+        let data = serde_json::to_string(transaction)?;
+        self.conn()?.execute(
+            "INSERT INTO transactions (id, timestamp, data) VALUES (?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            rusqlite::params![transaction.id, transaction.request.timestamp.to_rfc3339(), data],
+        )?;
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.conn()?.execute("DELETE FROM transactions", [])?;
+        Ok(())
+    }
+}
+
+// 远端写入在后台按顺序发送，不阻塞代理主流程；远端数据不回读
+pub struct RestSink {
+    sender: mpsc::UnboundedSender<serde_json::Value>,
+}
+
+impl RestSink {
+    pub fn spawn(url: String, headers: HashMap<String, String>) -> Result<Self> {
+        url::Url::parse(&url)?;
+        let (sender, mut receiver) = mpsc::unbounded_channel::<serde_json::Value>();
+        let client = reqwest::Client::builder()
+            .no_proxy()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()?;
+
+        tokio::spawn(async move {
+            while let Some(payload) = receiver.recv().await {
+                let mut request = client.post(&url).json(&payload);
+                for (name, value) in &headers {
+                    request = request.header(name.as_str(), value.as_str());
+                }
+                match request.send().await {
+                    Ok(response) if !response.status().is_success() => {
+                        warn!("Storage sink {} returned HTTP {}", url, response.status());
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to send transaction to storage sink {}: {}", url, e),
+                }
+            }
+        });
+
+        Ok(Self { sender })
+    }
+}
+
+impl TransactionStore for RestSink {
+    fn load(&self) -> Result<Vec<HttpTransaction>> {
+        Ok(Vec::new())
+    }
+
+    fn upsert(&self, transaction: &HttpTransaction) -> Result<()> {
+        self.sender
+            .send(serde_json::to_value(transaction)?)
+            .map_err(|_| anyhow!("Storage sink stopped"))
+    }
+
+    fn clear(&self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+use crate::ai_analyzer::AIAnalyzer;
+use crate::chat::transaction_context;
+use crate::dashboard::StatusClassCounts;
+use crate::history::split_endpoint;
+use crate::llm::ChatMessage;
+use crate::proxy::HttpTransaction;
+use crate::scoring;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use tracing::warn;
+
+// 各类列表的条数上限
+const TOP_ENDPOINTS: usize = 10;
+const TOP_ERROR_CLUSTERS: usize = 10;
+const TOP_SECURITY: usize = 10;
+const TOP_HOTSPOTS: usize = 5;
+// 附给模型的代表性事务数量及每个请求/响应体的预算
+const REPRESENTATIVE_TRANSACTIONS: usize = 6;
+const REPRESENTATIVE_BODY_BUDGET: usize = 1024;
+// 达到该风险分才列入安全发现
+const SECURITY_MIN_SCORE: u8 = 40;
+
+const SUMMARY_SYSTEM_PROMPT: &str = "你是资深的 HTTP 调试工程师。根据下面的抓包统计和代表性事务写一份可以直接贴进缺陷报告的 Markdown 摘要，依次包含以下二级标题：概览、关键接口、错误聚类、安全发现、性能热点、建议。只使用给出的数据，不要编造接口或数字；某一部分没有内容时写“无”。只输出 Markdown，不要包在代码块里。";
+
+// 起止时间均可省略，省略时不限制
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaptureRange {
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl CaptureRange {
+    pub fn contains(&self, transaction: &HttpTransaction) -> bool {
+        let timestamp = transaction.request.timestamp;
+        self.from.is_none_or(|from| timestamp >= from) && self.to.is_none_or(|to| timestamp <= to)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointSummary {
+    pub endpoint: String,
+    pub count: u64,
+    pub error_count: u64,
+    pub avg_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+}
+
+// 同一接口、同一状态的失败请求；status 为空表示未收到响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorCluster {
+    pub endpoint: String,
+    pub status: Option<u16>,
+    pub count: u64,
+    pub sample_transaction_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityHighlight {
+    pub transaction_id: String,
+    pub endpoint: String,
+    pub score: u8,
+    pub reasons: Vec<String>,
+    pub findings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureStats {
+    pub transaction_count: u64,
+    pub first_timestamp: Option<DateTime<Utc>>,
+    pub last_timestamp: Option<DateTime<Utc>>,
+    pub host_count: usize,
+    pub by_status_class: StatusClassCounts,
+    pub top_endpoints: Vec<EndpointSummary>,
+    pub error_clusters: Vec<ErrorCluster>,
+    pub security: Vec<SecurityHighlight>,
+    // 按 p95 耗时排序的最慢接口
+    pub hotspots: Vec<EndpointSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureSummary {
+    pub range: CaptureRange,
+    pub markdown: String,
+    pub stats: CaptureStats,
+    // 为空表示由统计数据直接生成
+    pub model: Option<String>,
+    // 模型生成失败时的原因
+    pub fallback_reason: Option<String>,
+    pub generated_at: DateTime<Utc>,
+}
+
+// 有可用模型时由模型撰写，失败或未配置时直接按统计数据生成
+pub async fn summarize(analyzer: &AIAnalyzer, range: CaptureRange, transactions: &[HttpTransaction]) -> CaptureSummary {
+    let stats = capture_stats(transactions);
+    let (markdown, model, fallback_reason) = match model_summary(analyzer, &stats, transactions).await {
+        Ok(Some(markdown)) => (markdown, Some(analyzer.model_name().to_string()), None),
+        Ok(None) => (render_markdown(&stats), None, None),
+        Err(e) => {
+            warn!("Failed to summarize capture with model: {}", e);
+            (render_markdown(&stats), None, Some(e.to_string()))
+        }
+    };
+    CaptureSummary { range, markdown, stats, model, fallback_reason, generated_at: Utc::now() }
+}
+
+async fn model_summary(
+    analyzer: &AIAnalyzer,
+    stats: &CaptureStats,
+    transactions: &[HttpTransaction],
+) -> Result<Option<String>> {
+    let mut prompt = format!("统计数据（JSON）：\n{}\n\n代表性事务：", serde_json::to_string_pretty(stats)?);
+    for (index, transaction) in representatives(stats, transactions).into_iter().enumerate() {
+        write!(prompt, "\n\n### 事务 {} ({})\n{}", index + 1, transaction.id, transaction_context(transaction, REPRESENTATIVE_BODY_BUDGET))?;
+    }
+    let content = analyzer
+        .complete(SUMMARY_SYSTEM_PROMPT, &[ChatMessage::user(prompt)], false, None)
+        .await?;
+    Ok(content.map(|c| strip_markdown_fence(&c).to_string()))
+}
+
+// 模型有时仍会把输出包在 ```markdown 代码块里
+fn strip_markdown_fence(content: &str) -> &str {
+    let trimmed = content.trim();
+    trimmed
+        .strip_prefix("```markdown")
+        .or_else(|| trimmed.strip_prefix("```md"))
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(str::trim)
+        .unwrap_or(trimmed)
+}
+
+pub fn capture_stats(transactions: &[HttpTransaction]) -> CaptureStats {
+    let mut by_status_class = StatusClassCounts::default();
+    let mut hosts = HashSet::new();
+    let mut endpoints: HashMap<String, (u64, u64, Vec<u64>)> = HashMap::new();
+    let mut clusters: HashMap<(String, Option<u16>), (u64, &str)> = HashMap::new();
+    let mut security = Vec::new();
+
+    for transaction in transactions {
+        let (host, path) = split_endpoint(&transaction.request.url);
+        let endpoint = format!("{} {}{}", transaction.request.method, host, path);
+        hosts.insert(host);
+        let status = transaction.response.as_ref().map(|r| r.status);
+        by_status_class.record(status);
+
+        let failed = status.is_none_or(|s| s >= 400);
+        let entry = endpoints.entry(endpoint.clone()).or_default();
+        entry.0 += 1;
+        if failed {
+            entry.1 += 1;
+            clusters.entry((endpoint.clone(), status)).or_insert((0, &transaction.id)).0 += 1;
+        }
+        if let Some(duration) = transaction.duration {
+            entry.2.push(duration.as_millis() as u64);
+        }
+
+        let risk = scoring::score(transaction);
+        if risk.score >= SECURITY_MIN_SCORE || !transaction.security_findings.is_empty() {
+            security.push(SecurityHighlight {
+                transaction_id: transaction.id.clone(),
+                endpoint,
+                score: risk.score,
+                reasons: risk.reasons,
+                findings: transaction.security_findings.clone(),
+            });
+        }
+    }
+
+    let mut endpoints: Vec<EndpointSummary> = endpoints
+        .into_iter()
+        .map(|(endpoint, (count, error_count, mut durations))| {
+            durations.sort_unstable();
+            let p95 = durations.get((durations.len() * 95).div_ceil(100).saturating_sub(1)).copied();
+            EndpointSummary {
+                endpoint,
+                count,
+                error_count,
+                avg_ms: (!durations.is_empty()).then(|| durations.iter().sum::<u64>() / durations.len() as u64),
+                p95_ms: p95,
+                max_ms: durations.last().copied(),
+            }
+        })
+        .collect();
+
+    let mut hotspots: Vec<EndpointSummary> = endpoints.iter().filter(|e| e.p95_ms.is_some()).cloned().collect();
+    hotspots.sort_by(|a, b| b.p95_ms.cmp(&a.p95_ms).then_with(|| a.endpoint.cmp(&b.endpoint)));
+    hotspots.truncate(TOP_HOTSPOTS);
+
+    endpoints.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.endpoint.cmp(&b.endpoint)));
+    endpoints.truncate(TOP_ENDPOINTS);
+
+    let mut error_clusters: Vec<ErrorCluster> = clusters
+        .into_iter()
+        .map(|((endpoint, status), (count, sample))| ErrorCluster {
+            endpoint,
+            status,
+            count,
+            sample_transaction_id: sample.to_string(),
+        })
+        .collect();
+    error_clusters.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.endpoint.cmp(&b.endpoint)));
+    error_clusters.truncate(TOP_ERROR_CLUSTERS);
+
+    security.sort_by_key(|s| std::cmp::Reverse(s.score));
+    security.truncate(TOP_SECURITY);
+
+    CaptureStats {
+        transaction_count: transactions.len() as u64,
+        first_timestamp: transactions.iter().map(|t| t.request.timestamp).min(),
+        last_timestamp: transactions.iter().map(|t| t.request.timestamp).max(),
+        host_count: hosts.len(),
+        by_status_class,
+        top_endpoints: endpoints,
+        error_clusters,
+        security,
+        hotspots,
+    }
+}
+
+// 依次取最大错误聚类、最高风险和最慢接口的样本，去重
+fn representatives<'a>(stats: &CaptureStats, transactions: &'a [HttpTransaction]) -> Vec<&'a HttpTransaction> {
+    let slowest = |endpoint: &str| {
+        transactions
+            .iter()
+            .filter(|t| {
+                let (host, path) = split_endpoint(&t.request.url);
+                format!("{} {}{}", t.request.method, host, path) == endpoint
+            })
+            .max_by_key(|t| t.duration)
+            .map(|t| t.id.clone())
+    };
+    let candidates = stats
+        .error_clusters
+        .iter()
+        .take(3)
+        .map(|c| Some(c.sample_transaction_id.clone()))
+        .chain(stats.security.iter().take(2).map(|s| Some(s.transaction_id.clone())))
+        .chain(stats.hotspots.iter().take(2).map(|h| slowest(&h.endpoint)))
+        .flatten();
+
+    let mut picked: Vec<&HttpTransaction> = Vec::new();
+    for id in candidates {
+        if picked.len() >= REPRESENTATIVE_TRANSACTIONS {
+            break;
+        }
+        if picked.iter().any(|t| t.id == id) {
+            continue;
+        }
+        if let Some(transaction) = transactions.iter().find(|t| t.id == id) {
+            picked.push(transaction);
+        }
+    }
+    picked
+}
+
+fn format_ms(ms: Option<u64>) -> String {
+    ms.map(|ms| format!("{}ms", ms)).unwrap_or_else(|| "-".to_string())
+}
+
+// 没有可用模型时直接按统计数据生成报告
+pub fn render_markdown(stats: &CaptureStats) -> String {
+    let mut out = String::from("# 抓包摘要\n\n## 概览\n\n");
+    let counts = &stats.by_status_class;
+    let _ = writeln!(out, "- 事务数：{}", stats.transaction_count);
+    if let (Some(first), Some(last)) = (stats.first_timestamp, stats.last_timestamp) {
+        let _ = writeln!(out, "- 时间范围：{} ~ {}", first.to_rfc3339(), last.to_rfc3339());
+    }
+    let _ = writeln!(out, "- 主机数：{}", stats.host_count);
+    let _ = writeln!(
+        out,
+        "- 状态分布：2xx {}，3xx {}，4xx {}，5xx {}，无响应 {}",
+        counts.success, counts.redirection, counts.client_error, counts.server_error, counts.failed
+    );
+
+    out.push_str("\n## 关键接口\n\n");
+    if stats.top_endpoints.is_empty() {
+        out.push_str("无\n");
+    } else {
+        out.push_str("| 接口 | 请求数 | 失败数 | 平均耗时 | p95 |\n| --- | --- | --- | --- | --- |\n");
+        for e in &stats.top_endpoints {
+            let _ = writeln!(
+                out,
+                "| `{}` | {} | {} | {} | {} |",
+                e.endpoint, e.count, e.error_count, format_ms(e.avg_ms), format_ms(e.p95_ms)
+            );
+        }
+    }
+
+    out.push_str("\n## 错误聚类\n\n");
+    if stats.error_clusters.is_empty() {
+        out.push_str("无\n");
+    }
+    for c in &stats.error_clusters {
+        let status = c.status.map(|s| s.to_string()).unwrap_or_else(|| "无响应".to_string());
+        let _ = writeln!(out, "- `{}` → {}：{} 次（示例 {}）", c.endpoint, status, c.count, c.sample_transaction_id);
+    }
+
+    out.push_str("\n## 安全发现\n\n");
+    if stats.security.is_empty() {
+        out.push_str("无\n");
+    }
+    for s in &stats.security {
+        let details: Vec<&str> = s.findings.iter().chain(&s.reasons).map(String::as_str).collect();
+        let _ = writeln!(out, "- `{}`（风险 {}，{}）：{}", s.endpoint, s.score, s.transaction_id, details.join("；"));
+    }
+
+    out.push_str("\n## 性能热点\n\n");
+    if stats.hotspots.is_empty() {
+        out.push_str("无\n");
+    }
+    for h in &stats.hotspots {
+        let _ = writeln!(
+            out,
+            "- `{}`：p95 {}，最大 {}，共 {} 次",
+            h.endpoint, format_ms(h.p95_ms), format_ms(h.max_ms), h.count
+        );
+    }
+    out
+}
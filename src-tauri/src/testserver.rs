@@ -0,0 +1,185 @@
+use anyhow::Result;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+const MAX_DELAY_MS: u64 = 10_000;
+const MAX_REDIRECTS: u32 = 20;
+const MAX_BYTES: usize = 10 * 1024 * 1024;
+
+// 本地测试服务，仅监听回环地址，端口由系统分配
+pub struct TestServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl TestServer {
+    pub async fn start() -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+
+        let task = tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("Test server accept failed: {}", e);
+                        continue;
+                    }
+                };
+                tokio::spawn(async move {
+                    let service = service_fn(handle);
+                    if let Err(e) = hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        warn!("Test server connection error: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { addr, task })
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle(req: Request<Incoming>) -> Result<Response<Full<Bytes>>, hyper::Error> {
+    let segments: Vec<String> = req
+        .uri()
+        .path()
+        .trim_matches('/')
+        .split('/')
+        .map(str::to_string)
+        .collect();
+    let argument = segments.get(1).map(String::as_str).unwrap_or_default();
+
+    let response = match segments[0].as_str() {
+        "" => json_response(StatusCode::OK, index()),
+        "echo" | "anything" => json_response(StatusCode::OK, echo(req).await?),
+        "status" => match argument.parse::<u16>().ok().and_then(|c| StatusCode::from_u16(c).ok()) {
+            Some(status) => response(status, "text/plain", Bytes::new()),
+            None => error_response(StatusCode::BAD_REQUEST, "Invalid status code"),
+        },
+        "delay" => match argument.parse::<u64>() {
+            Ok(ms) => {
+                tokio::time::sleep(Duration::from_millis(ms.min(MAX_DELAY_MS))).await;
+                json_response(StatusCode::OK, echo(req).await?)
+            }
+            Err(_) => error_response(StatusCode::BAD_REQUEST, "Invalid delay"),
+        },
+        // /redirect/n 经过 n 次 302 后到达 /echo
+        "redirect" => match argument.parse::<u32>() {
+            Ok(n) if n <= MAX_REDIRECTS => {
+                let location = if n <= 1 { "/echo".to_string() } else { format!("/redirect/{}", n - 1) };
+                let mut response = response(StatusCode::FOUND, "text/plain", Bytes::new());
+                if let Ok(location) = location.parse() {
+                    response.headers_mut().insert(hyper::header::LOCATION, location);
+                }
+                response
+            }
+            _ => error_response(StatusCode::BAD_REQUEST, "Invalid redirect count"),
+        },
+        "bytes" => match argument.parse::<usize>() {
+            Ok(n) if n <= MAX_BYTES => response(StatusCode::OK, "application/octet-stream", pseudo_random_bytes(n)),
+            _ => error_response(StatusCode::BAD_REQUEST, "Invalid byte count"),
+        },
+        "json" => json_response(StatusCode::OK, sample_json()),
+        _ => error_response(StatusCode::NOT_FOUND, "Not found"),
+    };
+    Ok(response)
+}
+
+fn index() -> serde_json::Value {
+    json!({
+        "endpoints": [
+            "/echo", "/anything/{path}", "/status/{code}", "/delay/{ms}",
+            "/redirect/{n}", "/bytes/{n}", "/json",
+        ]
+    })
+}
+
+async fn echo(req: Request<Incoming>) -> Result<serde_json::Value, hyper::Error> {
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let query: HashMap<String, String> = req
+        .uri()
+        .query()
+        .map(|q| url::form_urlencoded::parse(q.as_bytes()).into_owned().collect())
+        .unwrap_or_default();
+    let headers: HashMap<String, String> = req
+        .headers()
+        .iter()
+        .map(|(k, v)| (k.to_string(), String::from_utf8_lossy(v.as_bytes()).to_string()))
+        .collect();
+    let body = req.into_body().collect().await?.to_bytes();
+
+    Ok(json!({
+        "method": method,
+        "path": path,
+        "query": query,
+        "headers": headers,
+        "body": String::from_utf8_lossy(&body),
+    }))
+}
+
+fn sample_json() -> serde_json::Value {
+    json!({
+        "users": [
+            { "id": 1, "name": "Alice", "email": "alice@example.com", "roles": ["admin"] },
+            { "id": 2, "name": "Bob", "email": "bob@example.com", "roles": [] }
+        ],
+        "total": 2,
+        "next": null
+    })
+}
+
+// 固定种子的伪随机数据，便于重复对比
+fn pseudo_random_bytes(n: usize) -> Bytes {
+    let mut state: u32 = 0x9e37_79b9;
+    (0..n)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect::<Vec<u8>>()
+        .into()
+}
+
+fn response(status: StatusCode, content_type: &str, body: Bytes) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(body));
+    *response.status_mut() = status;
+    if let Ok(content_type) = content_type.parse() {
+        response.headers_mut().insert(hyper::header::CONTENT_TYPE, content_type);
+    }
+    response
+}
+
+fn json_response(status: StatusCode, value: serde_json::Value) -> Response<Full<Bytes>> {
+    response(status, "application/json", Bytes::from(value.to_string()))
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    json_response(status, json!({ "error": message }))
+}
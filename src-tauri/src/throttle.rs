@@ -0,0 +1,266 @@
+use bytes::Bytes;
+use hyper::body::{Body, Frame, SizeHint};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+// 每个令牌桶允许的突发量，按 100ms 的带宽计算
+const BURST_WINDOW: Duration = Duration::from_millis(100);
+// 限速转发时每次放行的最大字节数，块越小速率越平滑
+const PACE_CHUNK_SIZE: usize = 4 * 1024;
+
+// 预设参数与 Chrome DevTools 的网络节流预设保持一致
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ThrottleProfile {
+    Gprs,
+    #[serde(rename = "3g")]
+    ThreeG,
+    #[serde(rename = "4g")]
+    FourG,
+    Custom {
+        download_kbps: u32,
+        upload_kbps: u32,
+        #[serde(default)]
+        latency_ms: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThrottleLimits {
+    // 0 表示该方向不限速
+    pub download_kbps: u32,
+    pub upload_kbps: u32,
+    pub latency_ms: u64,
+}
+
+impl ThrottleProfile {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Gprs => "gprs",
+            Self::ThreeG => "3g",
+            Self::FourG => "4g",
+            Self::Custom { .. } => "custom",
+        }
+    }
+
+    pub fn limits(&self) -> ThrottleLimits {
+        let (download_kbps, upload_kbps, latency_ms) = match *self {
+            Self::Gprs => (50, 20, 500),
+            Self::ThreeG => (750, 250, 100),
+            Self::FourG => (4000, 3000, 20),
+            Self::Custom { download_kbps, upload_kbps, latency_ms } => (download_kbps, upload_kbps, latency_ms),
+        };
+        ThrottleLimits { download_kbps, upload_kbps, latency_ms }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Upload,
+    Download,
+}
+
+// hosts 的键支持精确主机名和以 "." 开头的子域名后缀 (.example.com)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThrottleSettings {
+    #[serde(default)]
+    pub global: Option<ThrottleProfile>,
+    #[serde(default)]
+    pub hosts: HashMap<String, ThrottleProfile>,
+}
+
+impl ThrottleSettings {
+    // 返回匹配到的范围（主机规则或 "*"）及其配置；精确匹配优先于最长的后缀匹配
+    fn profile_for(&self, host: &str) -> Option<(String, &ThrottleProfile)> {
+        let host = host.to_lowercase();
+        if let Some(profile) = self.hosts.get(&host) {
+            return Some((host, profile));
+        }
+
+        self.hosts
+            .iter()
+            .filter(|(pattern, _)| {
+                pattern.starts_with('.') && (host.ends_with(pattern.as_str()) || host == pattern[1..])
+            })
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(pattern, profile)| (pattern.clone(), profile))
+            .or_else(|| self.global.as_ref().map(|profile| ("*".to_string(), profile)))
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    bytes_per_sec: f64,
+    capacity: f64,
+    // 可以为负，表示之前的请求预占了尚未到账的带宽
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(kbps: u32) -> Self {
+        let bytes_per_sec = kbps as f64 * 1000.0 / 8.0;
+        let capacity = bytes_per_sec * BURST_WINDOW.as_secs_f64();
+        Self { bytes_per_sec, capacity, tokens: capacity, last_refill: Instant::now() }
+    }
+
+    // 预占 n 字节的带宽，返回需要等待的时长
+    fn reserve(&mut self, bytes: usize) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.bytes_per_sec).min(self.capacity);
+        self.last_refill = now;
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.bytes_per_sec)
+        }
+    }
+}
+
+// 同一范围内的并发请求共享令牌桶，模拟共用一条慢速链路
+#[derive(Debug, Default)]
+pub struct Throttler {
+    settings: ThrottleSettings,
+    buckets: HashMap<(String, Direction), TokenBucket>,
+}
+
+impl Throttler {
+    pub fn settings(&self) -> ThrottleSettings {
+        self.settings.clone()
+    }
+
+    pub fn set_settings(&mut self, settings: ThrottleSettings) {
+        self.settings = settings;
+        self.buckets.clear();
+    }
+
+    pub fn set_profile(&mut self, host: Option<&str>, profile: Option<ThrottleProfile>) {
+        match (host, profile) {
+            (Some(host), Some(profile)) => {
+                self.settings.hosts.insert(host.to_lowercase(), profile);
+            }
+            (Some(host), None) => {
+                self.settings.hosts.remove(&host.to_lowercase());
+            }
+            (None, profile) => self.settings.global = profile,
+        }
+        self.buckets.clear();
+    }
+
+    pub fn profile_id_for(&self, host: &str) -> Option<&'static str> {
+        self.settings.profile_for(host).map(|(_, profile)| profile.id())
+    }
+
+    pub fn latency_for(&self, host: &str) -> Duration {
+        self.settings
+            .profile_for(host)
+            .map(|(_, profile)| Duration::from_millis(profile.limits().latency_ms))
+            .unwrap_or(Duration::ZERO)
+    }
+
+    // 预占 bytes 字节的带宽，返回放行前需要等待的时长
+    pub fn reserve(&mut self, host: &str, direction: Direction, bytes: usize) -> Duration {
+        let Some((scope, profile)) = self.settings.profile_for(host) else {
+            return Duration::ZERO;
+        };
+        let limits = profile.limits();
+        let kbps = match direction {
+            Direction::Upload => limits.upload_kbps,
+            Direction::Download => limits.download_kbps,
+        };
+        if kbps == 0 || bytes == 0 {
+            return Duration::ZERO;
+        }
+        self.buckets
+            .entry((scope, direction))
+            .or_insert_with(|| TokenBucket::new(kbps))
+            .reserve(bytes)
+    }
+}
+
+type Delay = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+// 按令牌桶逐块放行的消息体：上行与下行分别对应发往上游的请求体和上游返回的响应体，
+// 同一范围内的并发传输共享带宽；非数据帧（trailers）直接放行
+pub struct PacedBody<B> {
+    inner: B,
+    throttle: Arc<RwLock<Throttler>>,
+    host: String,
+    direction: Direction,
+    // 已从 inner 读出、尚未放行的数据
+    pending: Bytes,
+    // 正在等待带宽的块及其等待
+    waiting: Option<(Bytes, Delay)>,
+}
+
+impl<B> PacedBody<B> {
+    pub fn new(inner: B, throttle: Arc<RwLock<Throttler>>, host: &str, direction: Direction) -> Self {
+        Self { inner, throttle, host: host.to_string(), direction, pending: Bytes::new(), waiting: None }
+    }
+
+    fn buffered(&self) -> u64 {
+        (self.pending.len() + self.waiting.as_ref().map(|(chunk, _)| chunk.len()).unwrap_or(0)) as u64
+    }
+}
+
+impl<B> Body for PacedBody<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, B::Error>>> {
+        let this = &mut *self;
+        loop {
+            if let Some((chunk, mut delay)) = this.waiting.take() {
+                if delay.as_mut().poll(cx).is_pending() {
+                    this.waiting = Some((chunk, delay));
+                    return Poll::Pending;
+                }
+                return Poll::Ready(Some(Ok(Frame::data(chunk))));
+            }
+            if !this.pending.is_empty() {
+                let chunk = this.pending.split_to(this.pending.len().min(PACE_CHUNK_SIZE));
+                let (throttle, host, direction, bytes) = (this.throttle.clone(), this.host.clone(), this.direction, chunk.len());
+                let delay: Delay = Box::pin(async move {
+                    let delay = throttle.write().await.reserve(&host, direction, bytes);
+                    tokio::time::sleep(delay).await;
+                });
+                this.waiting = Some((chunk, delay));
+                continue;
+            }
+            match Pin::new(&mut this.inner).poll_frame(cx) {
+                Poll::Ready(Some(Ok(frame))) => match frame.into_data() {
+                    Ok(data) => this.pending = data,
+                    Err(frame) => return Poll::Ready(Some(Ok(frame))),
+                },
+                other => return other,
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.waiting.is_none() && self.pending.is_empty() && self.inner.is_end_stream()
+    }
+
+    // 保留原消息体的长度信息，定长的请求体仍以 Content-Length 发出
+    fn size_hint(&self) -> SizeHint {
+        let inner = self.inner.size_hint();
+        let buffered = self.buffered();
+        let mut hint = SizeHint::new();
+        if let Some(upper) = inner.upper() {
+            hint.set_upper(upper + buffered);
+        }
+        hint.set_lower(inner.lower() + buffered);
+        hint
+    }
+}
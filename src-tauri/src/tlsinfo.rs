@@ -0,0 +1,254 @@
+use crate::dns::DnsResolver;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CommonState, DigitallySignedStruct, ProtocolVersion, SignatureScheme};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+// 同一目标的探测结果在此时间内复用
+pub const UPSTREAM_PROBE_TTL: chrono::Duration = chrono::Duration::minutes(10);
+// 失败的探测同样缓存，时间短一些以便目标恢复后重新探测
+pub const UPSTREAM_PROBE_FAILURE_TTL: chrono::Duration = chrono::Duration::minutes(1);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TlsHandshake {
+    pub version: Option<String>,
+    pub cipher_suite: Option<String>,
+    pub alpn: Option<String>,
+    pub sni: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub serial: String,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+    pub expired: bool,
+    pub subject_alt_names: Vec<String>,
+    pub self_signed: bool,
+    pub sha256_fingerprint: String,
+}
+
+// 代理对同一目标单独发起的旁路探测连接，不校验证书，握手参数不一定与实际转发的连接一致；
+// 实际转发走 UpstreamClient（hyper-util + native-tls）的连接，见 ForwardedTls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamTls {
+    pub handshake: Option<TlsHandshake>,
+    // 服务器发送的证书链，第一张为叶子证书
+    pub certificate_chain: Vec<CertificateInfo>,
+    pub error: Option<String>,
+    pub probed_at: DateTime<Utc>,
+}
+
+impl UpstreamTls {
+    pub fn is_fresh(&self) -> bool {
+        let ttl = if self.error.is_some() { UPSTREAM_PROBE_FAILURE_TTL } else { UPSTREAM_PROBE_TTL };
+        Utc::now() - self.probed_at < ttl
+    }
+}
+
+// 代理按目标缓存的探测状态；探测在后台进行，期间记录的事务等结果出来后再补上
+#[derive(Debug, Clone)]
+pub enum UpstreamProbe {
+    Pending(Vec<String>),
+    Done(UpstreamTls),
+}
+
+// 转发客户端实际使用的上游连接；native-tls 不提供协议版本和加密套件，只记录 ALPN、SNI 和叶子证书
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardedTls {
+    pub handshake: TlsHandshake,
+    pub certificate: Option<CertificateInfo>,
+    // 复用已有连接时早于本次请求
+    pub established_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsInfo {
+    // 客户端与代理之间的握手，代理以动态签发的证书应答
+    pub client: TlsHandshake,
+    // 探测在后台进行，尚未完成时为空
+    #[serde(alias = "upstream", default)]
+    pub upstream_probe: Option<UpstreamTls>,
+    // 转发失败或未经转发客户端发出（如 WebSocket）时为空
+    #[serde(default)]
+    pub forwarded: Option<ForwardedTls>,
+}
+
+pub fn describe(state: &CommonState, sni: Option<&str>) -> TlsHandshake {
+    TlsHandshake {
+        version: state.protocol_version().map(version_name),
+        cipher_suite: state.negotiated_cipher_suite().map(|suite| format!("{:?}", suite.suite())),
+        alpn: state.alpn_protocol().map(|p| String::from_utf8_lossy(p).into_owned()),
+        sni: sni.map(str::to_string),
+    }
+}
+
+fn version_name(version: ProtocolVersion) -> String {
+    match version {
+        ProtocolVersion::TLSv1_3 => "TLS 1.3".to_string(),
+        ProtocolVersion::TLSv1_2 => "TLS 1.2".to_string(),
+        ProtocolVersion::TLSv1_1 => "TLS 1.1".to_string(),
+        ProtocolVersion::TLSv1_0 => "TLS 1.0".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+// 探测失败不影响请求转发，错误记录在结果中；域名与转发一样经代理的解析器解析
+pub async fn probe(authority: &str, dns: &DnsResolver, timeout: Duration) -> UpstreamTls {
+    let probed_at = Utc::now();
+    match tokio::time::timeout(timeout, handshake(authority, dns)).await {
+        Ok(Ok((handshake, certificate_chain))) => {
+            UpstreamTls { handshake: Some(handshake), certificate_chain, error: None, probed_at }
+        }
+        Ok(Err(e)) => UpstreamTls { handshake: None, certificate_chain: Vec::new(), error: Some(e.to_string()), probed_at },
+        Err(_) => UpstreamTls {
+            handshake: None,
+            certificate_chain: Vec::new(),
+            error: Some(format!("TLS probe to {} timed out", authority)),
+            probed_at,
+        },
+    }
+}
+
+async fn handshake(authority: &str, dns: &DnsResolver) -> Result<(TlsHandshake, Vec<CertificateInfo>)> {
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => {
+            (host, port.parse::<u16>().map_err(|_| anyhow!("Invalid port in {}", authority))?)
+        }
+        _ => (authority, 443),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(RecordOnly(provider)))
+        .with_no_client_auth();
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    let server_name = ServerName::try_from(host.to_string()).map_err(|e| anyhow!("Invalid server name {}: {}", host, e))?;
+    // IP 地址不会作为 SNI 发送
+    let sni = matches!(server_name, ServerName::DnsName(_)).then_some(host);
+    let addresses = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => {
+            let resolution = dns.lookup(host).await;
+            if let Some(error) = resolution.error {
+                return Err(anyhow!(error));
+            }
+            resolution.addresses
+        }
+    };
+    let mut last_error = anyhow!("No addresses to connect to for {}", host);
+    let mut stream = None;
+    for ip in addresses {
+        let addr = SocketAddr::new(ip, port);
+        match TcpStream::connect(addr).await {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(e) => last_error = anyhow!("Failed to connect to {}: {}", addr, e),
+        }
+    }
+    let stream = stream.ok_or(last_error)?;
+    let tls = tokio_rustls::TlsConnector::from(Arc::new(config)).connect(server_name, stream).await?;
+    let (_, connection) = tls.get_ref();
+    let chain = connection
+        .peer_certificates()
+        .unwrap_or_default()
+        .iter()
+        .map(|der| parse_certificate(der))
+        .collect::<Result<Vec<_>>>()?;
+    Ok((describe(connection, sni), chain))
+}
+
+pub fn parse_certificate(der: &[u8]) -> Result<CertificateInfo> {
+    let (_, cert) = X509Certificate::from_der(der).map_err(|e| anyhow!("Invalid certificate: {}", e))?;
+    let validity = cert.validity();
+    let not_before = DateTime::from_timestamp(validity.not_before.timestamp(), 0);
+    let not_after = DateTime::from_timestamp(validity.not_after.timestamp(), 0);
+    let subject_alt_names = cert
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .map(|san| {
+            san.value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(dns) => Some(dns.to_string()),
+                    GeneralName::IPAddress(ip) => match ip.len() {
+                        4 => Some(std::net::Ipv4Addr::from(<[u8; 4]>::try_from(*ip).ok()?).to_string()),
+                        16 => Some(std::net::Ipv6Addr::from(<[u8; 16]>::try_from(*ip).ok()?).to_string()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let subject = cert.subject().to_string();
+    let issuer = cert.issuer().to_string();
+    Ok(CertificateInfo {
+        self_signed: subject == issuer,
+        subject,
+        issuer,
+        serial: cert.raw_serial_as_string(),
+        not_before,
+        not_after,
+        expired: not_after.is_some_and(|t| t < Utc::now()),
+        subject_alt_names,
+        sha256_fingerprint: Sha256::digest(der).iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":"),
+    })
+}
+
+// 只记录服务器证书、不做信任校验，这样证书有问题的站点同样能拿到证书链用于排查
+#[derive(Debug)]
+struct RecordOnly(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for RecordOnly {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
@@ -0,0 +1,147 @@
+use crate::ai_analyzer::AIAnalysisResult;
+use crate::proxy::HttpTransaction;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+// 代理转发失败（连接、DNS、超时等）时打在事务上的标签
+pub const NETWORK_ERROR_TAG: &str = "network-error";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TriageState {
+    New,
+    Investigating,
+    Resolved,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FailureKind {
+    Client,
+    Server,
+    Network,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageItem {
+    pub transaction_id: String,
+    pub method: String,
+    pub url: String,
+    pub status: Option<u16>,
+    pub kind: FailureKind,
+    pub state: TriageState,
+    pub assignee: Option<String>,
+    pub note: String,
+    pub ai_analysis: Option<AIAnalysisResult>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TriageSummary {
+    pub new: usize,
+    pub investigating: usize,
+    pub resolved: usize,
+}
+
+// 前端只需传入要修改的字段
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TriageUpdate {
+    pub state: Option<TriageState>,
+    pub assignee: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct TriageQueue {
+    items: Vec<TriageItem>,
+}
+
+pub fn classify(transaction: &HttpTransaction) -> Option<FailureKind> {
+    if transaction.tags.iter().any(|t| t == NETWORK_ERROR_TAG) {
+        return Some(FailureKind::Network);
+    }
+    match transaction.response.as_ref()?.status {
+        400..=499 => Some(FailureKind::Client),
+        500..=599 => Some(FailureKind::Server),
+        _ => None,
+    }
+}
+
+impl TriageQueue {
+    // 失败的事务自动入队，其余忽略
+    pub fn track(&mut self, transaction: &HttpTransaction) {
+        let Some(kind) = classify(transaction) else {
+            return;
+        };
+        let now = chrono::Utc::now();
+        self.items.push(TriageItem {
+            transaction_id: transaction.id.clone(),
+            method: transaction.request.method.clone(),
+            url: transaction.request.url.clone(),
+            status: transaction.response.as_ref().map(|r| r.status),
+            kind,
+            state: TriageState::New,
+            assignee: None,
+            note: String::new(),
+            ai_analysis: None,
+            created_at: now,
+            updated_at: now,
+        });
+    }
+
+    pub fn list(&self, state: Option<TriageState>) -> Vec<TriageItem> {
+        self.items
+            .iter()
+            .filter(|item| state.is_none_or(|s| item.state == s))
+            .cloned()
+            .collect()
+    }
+
+    pub fn summary(&self) -> TriageSummary {
+        let mut summary = TriageSummary::default();
+        for item in &self.items {
+            match item.state {
+                TriageState::New => summary.new += 1,
+                TriageState::Investigating => summary.investigating += 1,
+                TriageState::Resolved => summary.resolved += 1,
+            }
+        }
+        summary
+    }
+
+    pub fn update(&mut self, transaction_id: &str, update: TriageUpdate) -> Result<TriageItem> {
+        let item = self.find_mut(transaction_id)?;
+        if let Some(state) = update.state {
+            item.state = state;
+        }
+        if let Some(assignee) = update.assignee {
+            item.assignee = if assignee.trim().is_empty() { None } else { Some(assignee) };
+        }
+        if let Some(note) = update.note {
+            item.note = note;
+        }
+        item.updated_at = chrono::Utc::now();
+        Ok(item.clone())
+    }
+
+    pub fn attach_analysis(&mut self, transaction_id: &str, analysis: AIAnalysisResult) -> Result<TriageItem> {
+        let item = self.find_mut(transaction_id)?;
+        item.ai_analysis = Some(analysis);
+        // 生成解释视为已开始排查
+        if item.state == TriageState::New {
+            item.state = TriageState::Investigating;
+        }
+        item.updated_at = chrono::Utc::now();
+        Ok(item.clone())
+    }
+
+    pub fn clear_resolved(&mut self) {
+        self.items.retain(|item| item.state != TriageState::Resolved);
+    }
+
+    fn find_mut(&mut self, transaction_id: &str) -> Result<&mut TriageItem> {
+        self.items
+            .iter_mut()
+            .find(|item| item.transaction_id == transaction_id)
+            .ok_or_else(|| anyhow!("Triage item not found: {}", transaction_id))
+    }
+}
@@ -0,0 +1,339 @@
+use crate::dns::{DnsResolution, DnsResolver};
+use crate::tlsinfo::{self, ForwardedTls, TlsHandshake};
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use chrono::Utc;
+use http_body_util::combinators::UnsyncBoxBody;
+use hyper::body::{Body, Frame, Incoming, SizeHint};
+use hyper::{Request, Response, Uri};
+use hyper_util::client::legacy::connect::{Connected, Connection};
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_native_tls::TlsStream;
+
+// 转发耗时的阶段划分，单位毫秒；复用已有连接时没有连接和握手阶段
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    // 直接访问 IP 时为空，命中缓存时为 0
+    pub dns_ms: Option<f64>,
+    pub connect_ms: Option<f64>,
+    // 明文连接时为空
+    pub tls_ms: Option<f64>,
+    // 连接就绪到请求体交付完毕
+    pub send_ms: f64,
+    // 请求发出到收到响应头（TTFB）
+    pub wait_ms: f64,
+    // 读取响应体；流式转发时只包含代理缓冲的前缀
+    pub receive_ms: f64,
+}
+
+impl Timings {
+    // 域名解析在转发之前单独进行，由调用方补上
+    pub fn with_dns(mut self, dns: Option<&DnsResolution>) -> Self {
+        self.dns_ms = dns.map(|d| d.duration_ms);
+        self
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// 新建连接时由连接器记录，随响应扩展带回
+#[derive(Debug, Clone, Copy)]
+struct ConnectTimings {
+    started: Instant,
+    connect: Duration,
+    tls: Option<Duration>,
+    established: Instant,
+}
+
+// 单次请求的计时点，响应体读取完毕后换算为各阶段耗时
+pub struct RequestClock {
+    started: Instant,
+    connection: Option<ConnectTimings>,
+    body_sent: Arc<Mutex<Option<Instant>>>,
+    headers_at: Instant,
+}
+
+impl RequestClock {
+    pub fn finish(&self) -> Timings {
+        let done = Instant::now();
+        // 连接在本次请求开始后建立，说明是为它新建的；否则为复用
+        let fresh = self.connection.filter(|c| c.started >= self.started);
+        let ready = fresh.map(|c| c.established).unwrap_or(self.started);
+        let body_sent = self.body_sent.lock().ok().and_then(|t| *t).filter(|t| *t >= ready).unwrap_or(ready);
+        Timings {
+            dns_ms: None,
+            connect_ms: fresh.map(|c| millis(c.connect)),
+            tls_ms: fresh.and_then(|c| c.tls).map(millis),
+            send_ms: millis(body_sent - ready),
+            wait_ms: millis(self.headers_at.saturating_duration_since(body_sent)),
+            receive_ms: millis(done - self.headers_at),
+        }
+    }
+}
+
+// 发往上游的请求体；限速时为逐块放行的消息体
+pub type RequestBody = UnsyncBoxBody<Bytes, Infallible>;
+
+// 每一帧被连接取走时记下时间，最后一帧的时间作为发送阶段的结束
+struct TimedBody {
+    inner: RequestBody,
+    sent: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Body for TimedBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+        let frame = Pin::new(&mut self.inner).poll_frame(cx);
+        if frame.is_ready() {
+            if let Ok(mut sent) = self.sent.lock() {
+                *sent = Some(Instant::now());
+            }
+        }
+        frame
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// 自带连接器的转发客户端：域名解析走共享解析器，分别计时 TCP 连接与 TLS 握手
+#[derive(Clone)]
+pub struct UpstreamClient {
+    client: Client<TimedConnector, TimedBody>,
+}
+
+impl UpstreamClient {
+    pub fn new(dns: DnsResolver, connect_timeout: Duration) -> Self {
+        let tls = native_tls::TlsConnector::builder()
+            .request_alpns(&["h2", "http/1.1"])
+            .build()
+            .map(tokio_native_tls::TlsConnector::from)
+            .map_err(|e| tracing::warn!("Failed to initialize upstream TLS: {}", e))
+            .ok();
+        let connector = TimedConnector { dns, tls, connect_timeout };
+        Self { client: Client::builder(TokioExecutor::new()).build(connector) }
+    }
+
+    pub async fn send(&self, request: Request<RequestBody>) -> Result<(Response<Incoming>, RequestClock)> {
+        let started = Instant::now();
+        let sent = Arc::new(Mutex::new(None));
+        let request = request.map(|body| TimedBody { inner: body, sent: sent.clone() });
+        let response = self.client.request(request).await.map_err(|e| anyhow!(describe(&e)))?;
+        let clock = RequestClock {
+            started,
+            connection: response.extensions().get::<ConnectTimings>().copied(),
+            body_sent: sent,
+            headers_at: Instant::now(),
+        };
+        Ok((response, clock))
+    }
+}
+
+// 客户端错误的顶层信息过于笼统，拼接完整的错误链
+fn describe(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(e) = source {
+        message.push_str(": ");
+        message.push_str(&e.to_string());
+        source = e.source();
+    }
+    message
+}
+
+#[derive(Clone)]
+struct TimedConnector {
+    dns: DnsResolver,
+    // 初始化失败时 HTTPS 请求报错，HTTP 仍可转发
+    tls: Option<tokio_native_tls::TlsConnector>,
+    connect_timeout: Duration,
+}
+
+impl tower_service::Service<Uri> for TimedConnector {
+    type Response = UpstreamConnection;
+    type Error = anyhow::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<UpstreamConnection>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connector = self.clone();
+        Box::pin(async move { connector.connect(uri).await })
+    }
+}
+
+impl TimedConnector {
+    async fn connect(self, uri: Uri) -> Result<UpstreamConnection> {
+        let started = Instant::now();
+        let host = uri.host().ok_or_else(|| anyhow!("URL has no host: {}", uri))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let https = uri.scheme_str() == Some("https");
+        let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+
+        let addresses = match host.parse::<IpAddr>() {
+            Ok(ip) => vec![ip],
+            Err(_) => {
+                let resolution = self.dns.lookup(host).await;
+                if let Some(error) = resolution.error {
+                    return Err(anyhow!(error));
+                }
+                resolution.addresses
+            }
+        };
+
+        let connect_start = Instant::now();
+        let mut last_error = anyhow!("No addresses to connect to for {}", host);
+        let mut stream = None;
+        for ip in addresses {
+            let addr = SocketAddr::new(ip, port);
+            match tokio::time::timeout(self.connect_timeout, TcpStream::connect(addr)).await {
+                Ok(Ok(s)) => {
+                    stream = Some(s);
+                    break;
+                }
+                Ok(Err(e)) => last_error = anyhow!("Failed to connect to {}: {}", addr, e),
+                Err(_) => last_error = anyhow!("Connection to {} timed out", addr),
+            }
+        }
+        let stream = stream.ok_or(last_error)?;
+        let _ = stream.set_nodelay(true);
+        let connect = connect_start.elapsed();
+
+        if !https {
+            let timings = ConnectTimings { started, connect, tls: None, established: Instant::now() };
+            return Ok(UpstreamConnection { io: TokioIo::new(MaybeTls::Plain(stream)), timings, tls: None });
+        }
+        let tls = self.tls.as_ref().ok_or_else(|| anyhow!("Upstream TLS is unavailable"))?;
+        let handshake_start = Instant::now();
+        let stream = tls.connect(host, stream).await.map_err(|e| anyhow!("TLS handshake with {} failed: {}", host, e))?;
+        let tls_time = handshake_start.elapsed();
+        let timings = ConnectTimings { started, connect, tls: Some(tls_time), established: Instant::now() };
+        let negotiated = stream.get_ref();
+        let alpn = negotiated.negotiated_alpn().ok().flatten();
+        let certificate = negotiated
+            .peer_certificate()
+            .ok()
+            .flatten()
+            .and_then(|cert| cert.to_der().ok())
+            .and_then(|der| tlsinfo::parse_certificate(&der).ok());
+        let tls = ForwardedTls {
+            handshake: TlsHandshake {
+                version: None,
+                cipher_suite: None,
+                alpn: alpn.map(|p| String::from_utf8_lossy(&p).into_owned()),
+                // IP 地址不会作为 SNI 发送
+                sni: host.parse::<IpAddr>().is_err().then(|| host.to_string()),
+            },
+            certificate,
+            established_at: Utc::now(),
+        };
+        Ok(UpstreamConnection { io: TokioIo::new(MaybeTls::Tls(Box::new(stream))), timings, tls: Some(tls) })
+    }
+}
+
+enum MaybeTls {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for MaybeTls {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            MaybeTls::Plain(s) => Pin::new(s).poll_read(cx, buf),
+            MaybeTls::Tls(s) => Pin::new(s.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for MaybeTls {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            MaybeTls::Plain(s) => Pin::new(s).poll_write(cx, buf),
+            MaybeTls::Tls(s) => Pin::new(s.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            MaybeTls::Plain(s) => Pin::new(s).poll_flush(cx),
+            MaybeTls::Tls(s) => Pin::new(s.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            MaybeTls::Plain(s) => Pin::new(s).poll_shutdown(cx),
+            MaybeTls::Tls(s) => Pin::new(s.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+struct UpstreamConnection {
+    io: TokioIo<MaybeTls>,
+    timings: ConnectTimings,
+    // 与计时一起随响应扩展带回，复用连接的请求拿到的是该连接建立时的握手
+    tls: Option<ForwardedTls>,
+}
+
+impl Connection for UpstreamConnection {
+    fn connected(&self) -> Connected {
+        let connected = Connected::new().extra(self.timings);
+        let Some(tls) = &self.tls else {
+            return connected;
+        };
+        let h2 = tls.handshake.alpn.as_deref() == Some("h2");
+        let connected = connected.extra(tls.clone());
+        if h2 {
+            connected.negotiated_h2()
+        } else {
+            connected
+        }
+    }
+}
+
+impl hyper::rt::Read for UpstreamConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl hyper::rt::Write for UpstreamConnection {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,153 @@
+use crate::history::split_endpoint;
+use crate::proxy::{find_header, HttpTransaction};
+use crate::upstream::Timings;
+use serde::{Deserialize, Serialize};
+
+// 没有 Referer 的子请求归入在此时间窗口内开始的最近一次页面加载
+const PAGE_LOAD_WINDOW_MS: i64 = 30_000;
+
+// endpoint 为 host + 归一化路径（如 api.example.com/users/{id}），以 * 结尾时按前缀匹配
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyBudget {
+    pub id: String,
+    pub method: Option<String>,
+    pub endpoint: String,
+    pub budget_ms: u64,
+}
+
+impl LatencyBudget {
+    pub fn matches(&self, method: &str, endpoint: &str) -> bool {
+        if let Some(expected) = &self.method {
+            if !expected.eq_ignore_ascii_case(method) {
+                return false;
+            }
+        }
+        let pattern = self.endpoint.to_lowercase();
+        let endpoint = endpoint.to_lowercase();
+        match pattern.strip_suffix('*') {
+            Some(prefix) => endpoint.starts_with(prefix),
+            None => endpoint == pattern,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaterfallEntry {
+    pub transaction_id: String,
+    pub method: String,
+    pub url: String,
+    // 相对所在页面加载开始的偏移
+    pub start_offset_ms: i64,
+    pub duration_ms: u64,
+    // 各阶段耗时，用于在条形内分段显示
+    pub timings: Option<Timings>,
+    pub budget_ms: Option<u64>,
+    pub over_budget: bool,
+    pub exceeded_by_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageLoadGroup {
+    // 无法归属到页面的请求 page_url 为 None
+    pub page_url: Option<String>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub total_ms: u64,
+    pub over_budget_count: usize,
+    // 超出预算部分的累计时长
+    pub blocking_time_ms: u64,
+    pub entries: Vec<WaterfallEntry>,
+}
+
+// 选择最具体（endpoint 最长）的匹配预算
+pub fn budget_for<'a>(budgets: &'a [LatencyBudget], transaction: &HttpTransaction) -> Option<&'a LatencyBudget> {
+    let (host, path) = split_endpoint(&transaction.request.url);
+    let endpoint = format!("{}{}", host, path);
+    budgets
+        .iter()
+        .filter(|b| b.matches(&transaction.request.method, &endpoint))
+        .max_by_key(|b| (b.method.is_some(), b.endpoint.len()))
+}
+
+fn is_document(transaction: &HttpTransaction) -> bool {
+    let accepts_html = find_header(&transaction.request.headers, "accept")
+        .map(|a| a.starts_with("text/html"))
+        .unwrap_or(false);
+    let returns_html = transaction
+        .response
+        .as_ref()
+        .and_then(|r| find_header(&r.headers, "content-type"))
+        .map(|ct| ct.starts_with("text/html"))
+        .unwrap_or(false);
+    transaction.request.method == "GET" && (accepts_html || returns_html)
+}
+
+pub fn build_waterfall(transactions: &[HttpTransaction], budgets: &[LatencyBudget]) -> Vec<PageLoadGroup> {
+    let mut ordered: Vec<&HttpTransaction> = transactions.iter().collect();
+    ordered.sort_by_key(|t| t.request.timestamp);
+
+    let mut groups: Vec<PageLoadGroup> = Vec::new();
+    let mut orphans: Option<PageLoadGroup> = None;
+
+    for transaction in ordered {
+        let started_at = transaction.request.timestamp;
+        let group_index = if is_document(transaction) {
+            groups.push(PageLoadGroup {
+                page_url: Some(transaction.request.url.clone()),
+                started_at,
+                total_ms: 0,
+                over_budget_count: 0,
+                blocking_time_ms: 0,
+                entries: Vec::new(),
+            });
+            Some(groups.len() - 1)
+        } else {
+            // 优先按 Referer 归属，否则归入时间窗口内最近的页面
+            let referer = find_header(&transaction.request.headers, "referer");
+            referer
+                .and_then(|r| groups.iter().rposition(|g| g.page_url.as_deref() == Some(r)))
+                .or_else(|| {
+                    groups.len().checked_sub(1).filter(|&last| {
+                        referer.is_none()
+                            && (started_at - groups[last].started_at).num_milliseconds() <= PAGE_LOAD_WINDOW_MS
+                    })
+                })
+        };
+
+        let group = match group_index {
+            Some(index) => &mut groups[index],
+            None => orphans.get_or_insert_with(|| PageLoadGroup {
+                page_url: None,
+                started_at,
+                total_ms: 0,
+                over_budget_count: 0,
+                blocking_time_ms: 0,
+                entries: Vec::new(),
+            }),
+        };
+
+        let duration_ms = transaction.duration.map(|d| d.as_millis() as u64).unwrap_or(0);
+        let budget_ms = budget_for(budgets, transaction).map(|b| b.budget_ms);
+        let exceeded_by_ms = budget_ms.map(|b| duration_ms.saturating_sub(b)).unwrap_or(0);
+        let start_offset_ms = (started_at - group.started_at).num_milliseconds();
+
+        group.total_ms = group.total_ms.max((start_offset_ms.max(0) as u64) + duration_ms);
+        if exceeded_by_ms > 0 {
+            group.over_budget_count += 1;
+            group.blocking_time_ms += exceeded_by_ms;
+        }
+        group.entries.push(WaterfallEntry {
+            transaction_id: transaction.id.clone(),
+            method: transaction.request.method.clone(),
+            url: transaction.request.url.clone(),
+            start_offset_ms,
+            duration_ms,
+            timings: transaction.timings,
+            budget_ms,
+            over_budget: exceeded_by_ms > 0,
+            exceeded_by_ms,
+        });
+    }
+
+    groups.extend(orphans);
+    groups
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::Message;
+
+// 每个连接只保留最近的这么多帧，更早的帧丢弃并打上标签
+pub const MAX_WS_MESSAGES: usize = 1000;
+pub const WS_TRUNCATED_TAG: &str = "ws-truncated";
+// 帧先在内存中累积，攒够一批、间隔到期或连接关闭时再写入事务，不必每帧都复制和存储整个事务
+pub const WS_FLUSH_BATCH: usize = 100;
+pub const WS_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum WsDirection {
+    ClientToServer,
+    ServerToClient,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum WsMessageKind {
+    Text,
+    Binary,
+    Ping,
+    Pong,
+    Close,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsMessage {
+    pub direction: WsDirection,
+    pub kind: WsMessageKind,
+    pub payload: Vec<u8>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl WsMessage {
+    pub fn from_message(direction: WsDirection, message: &Message) -> Option<Self> {
+        let (kind, payload) = match message {
+            Message::Text(text) => (WsMessageKind::Text, text.as_bytes().to_vec()),
+            Message::Binary(data) => (WsMessageKind::Binary, data.clone()),
+            Message::Ping(data) => (WsMessageKind::Ping, data.clone()),
+            Message::Pong(data) => (WsMessageKind::Pong, data.clone()),
+            Message::Close(frame) => (
+                WsMessageKind::Close,
+                frame
+                    .as_ref()
+                    .map(|f| format!("{} {}", u16::from(f.code), f.reason).into_bytes())
+                    .unwrap_or_default(),
+            ),
+            // 读取时不会出现原始帧
+            Message::Frame(_) => return None,
+        };
+        Some(Self {
+            direction,
+            kind,
+            payload,
+            timestamp: chrono::Utc::now(),
+        })
+    }
+}
+
+// 握手阶段由双方各自生成的头，不能原样转发给上游
+const HANDSHAKE_HEADERS: [&str; 4] = [
+    "sec-websocket-key",
+    "sec-websocket-version",
+    // 不协商压缩扩展，帧内容保持明文便于记录
+    "sec-websocket-extensions",
+    "host",
+];
+
+pub fn is_upgrade_request(headers: &hyper::HeaderMap) -> bool {
+    headers
+        .get(hyper::header::UPGRADE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false)
+}
+
+// http(s) 地址转换为 ws(s)
+pub fn to_ws_url(url: &str) -> String {
+    if let Some(rest) = url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        url.to_string()
+    }
+}
+
+// 构造发往上游的握手请求，保留 Cookie、Origin、子协议等客户端头；调用方需先去掉逐跳头
+pub fn upstream_request<'a>(url: &str, headers: impl Iterator<Item = (&'a String, &'a String)>) -> Result<Request> {
+    let mut request = to_ws_url(url).into_client_request()?;
+    for (name, value) in headers {
+        if HANDSHAKE_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+            continue;
+        }
+        let name = hyper::header::HeaderName::from_bytes(name.as_bytes())?;
+        request.headers_mut().insert(name, value.parse()?);
+    }
+    Ok(request)
+}
@@ -18,6 +18,16 @@ interface AIAnalysisResult {
     data_flow_direction: string
     compliance_issues: string[]
   }
+  model?: string | null
+  cached?: boolean
+}
+
+interface SecurityFinding {
+  severity: 'Low' | 'Medium' | 'High' | 'Critical'
+  category: string
+  description: string
+  header?: string | null
+  subject?: string | null
 }
 
 interface Transaction {
@@ -36,7 +46,7 @@ interface AIAnalysisProps {
 export function AIAnalysis({ transactions }: AIAnalysisProps) {
   const [selectedTransaction, setSelectedTransaction] = useState<string>('')
   const [analysisResult, setAnalysisResult] = useState<AIAnalysisResult | null>(null)
-  const [vulnerabilities, setVulnerabilities] = useState<string[]>([])
+  const [vulnerabilities, setVulnerabilities] = useState<SecurityFinding[]>([])
   const [insights, setInsights] = useState<string[]>([])
   const [isAnalyzing, setIsAnalyzing] = useState(false)
 
@@ -61,7 +71,7 @@ export function AIAnalysis({ transactions }: AIAnalysisProps) {
     
     setIsAnalyzing(true)
     try {
-      const vulns = await invoke<string[]>('detect_vulnerabilities', {
+      const vulns = await invoke<SecurityFinding[]>('detect_vulnerabilities', {
         transactionId: selectedTransaction
       })
       setVulnerabilities(vulns)
@@ -161,6 +171,10 @@ export function AIAnalysis({ transactions }: AIAnalysisProps) {
           <h3 className="text-lg font-semibold mb-4 flex items-center gap-2">
             <Brain className="w-5 h-5" />
             AI 分析结果
+            <span className="text-xs font-normal text-gray-500">
+              {analysisResult.model ? `由 ${analysisResult.model} 生成` : '未配置 API Key，基于本地规则分析'}
+              {analysisResult.cached && '（缓存）'}
+            </span>
           </h3>
           
           <div className="grid grid-cols-1 md:grid-cols-2 gap-6">
@@ -270,7 +284,13 @@ export function AIAnalysis({ transactions }: AIAnalysisProps) {
             {vulnerabilities.map((vuln, index) => (
               <li key={index} className="flex items-start gap-2 text-red-600">
                 <AlertTriangle className="w-4 h-4 mt-0.5 flex-shrink-0" />
-                <span>{vuln}</span>
+                <span className={`px-2 py-0.5 rounded text-xs font-medium ${getRiskColor(vuln.severity)}`}>
+                  {vuln.severity}
+                </span>
+                <span>{vuln.description}</span>
+                {vuln.header && (
+                  <span className="text-xs text-gray-500 font-mono">{vuln.header}</span>
+                )}
               </li>
             ))}
           </ul>